@@ -0,0 +1,102 @@
+// the layouts are a part of the editor binary, its modules are included here as they are, with its lints
+#![allow(warnings)]
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use pbedit_core::{batch, proto, trz, typedefs, wire};
+use typedefs::PbReader;
+use proto::ProtoData;
+use view::{LayoutConfig, Layouts};
+use wire::MessageData;
+
+#[path = "../src/view.rs"]
+mod view;
+#[path = "../src/overview.rs"]
+mod overview;
+#[path = "../src/render.rs"]
+mod render;
+#[path = "../src/timestamp.rs"]
+mod timestamp;
+#[path = "../src/formats.rs"]
+mod formats;
+#[path = "../src/config.rs"]
+mod config;
+
+// as in the editor
+#[derive(Default)]
+struct Selection {
+    layout: usize,
+    y: usize,
+    x: u16,
+}
+
+const SCHEMA: &str = "message Root { repeated Item items = 1; }\n\
+    message Item { int32 id = 1; string name = 2; repeated int64 values = 3; Point at = 4; }\n\
+    message Point { double x = 1; double y = 2; }";
+
+// top-level items of the synthetic document, about 1.5 MB
+const ITEMS: usize = 20_000;
+
+const WIDTH: u16 = 120;
+const HEIGHT: u16 = 50;
+const SCROLL_LINES: usize = 10_000;
+
+fn varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn len_field(id: u64, content: &[u8], out: &mut Vec<u8>) {
+    varint(id << 3 | 2, out);
+    varint(content.len() as u64, out);
+    out.extend_from_slice(content);
+}
+
+fn synthetic_document() -> MessageData {
+    let mut bytes = vec![];
+    for i in 0..ITEMS as u64 {
+        let mut item = vec![];
+        varint(1 << 3, &mut item);
+        varint(i, &mut item);
+        len_field(2, format!("item number {}", i).as_bytes(), &mut item);
+        let mut packed = vec![];
+        for v in 0..8 { varint(i * 1000 + v, &mut packed); }
+        len_field(3, &packed, &mut item);
+        let mut point = vec![];
+        point.push(1 << 3 | 1);
+        point.extend_from_slice(&(i as f64).to_le_bytes());
+        point.push(2 << 3 | 1);
+        point.extend_from_slice(&(-(i as f64)).to_le_bytes());
+        len_field(4, &point, &mut item);
+        len_field(1, &item, &mut bytes);
+    }
+    let proto = ProtoData::new(SCHEMA).unwrap().finalize().unwrap();
+    let root = proto.get_message_definition("Root").unwrap();
+    let data = MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, root, &mut (bytes.len() as u32)).unwrap();
+    data
+}
+
+fn layout(c: &mut Criterion) {
+    let data = synthetic_document();
+    // the items are shown by pages, as in the editor
+    let config = LayoutConfig::default();
+    let new_layouts = || Layouts::new(&data, &config, "bench.pb".into(), WIDTH, HEIGHT);
+    let mut group = c.benchmark_group("layout");
+    group.bench_function("new", |b| b.iter(|| black_box(new_layouts())));
+    // a screen in the middle of the layouts, as after a jump to a record
+    group.bench_function("ensure_loaded screen", |b| b.iter_batched(new_layouts, |mut layouts| {
+        let middle = layouts.items.len() / 2;
+        layouts.ensure_loaded(&data, &config, middle, HEIGHT as usize, HEIGHT as usize, &mut Selection::default());
+        layouts
+    }, BatchSize::LargeInput));
+    // a long scroll from the top
+    group.bench_function("ensure_loaded scroll", |b| b.iter_batched(new_layouts, |mut layouts| {
+        layouts.ensure_loaded(&data, &config, 0, 0, SCROLL_LINES, &mut Selection::default());
+        layouts
+    }, BatchSize::LargeInput));
+    group.finish();
+}
+
+criterion_group!(benches, layout);
+criterion_main!(benches);
@@ -0,0 +1,98 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use pbedit_core::proto::ProtoData;
+use pbedit_core::typedefs::{PbReader, PbReaderTrait};
+use pbedit_core::wire::MessageData;
+
+const SCHEMA: &str = "message Root { repeated Item items = 1; }\n\
+    message Item { int32 id = 1; string name = 2; repeated int64 values = 3; Point at = 4; }\n\
+    message Point { double x = 1; double y = 2; }";
+
+// items of the synthetic data, about 1.5 MB
+const ITEMS: usize = 20_000;
+
+fn varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn len_field(id: u64, content: &[u8], out: &mut Vec<u8>) {
+    varint(id << 3 | 2, out);
+    varint(content.len() as u64, out);
+    out.extend_from_slice(content);
+}
+
+fn synthetic_data() -> Vec<u8> {
+    let mut res = vec![];
+    for i in 0..ITEMS as u64 {
+        let mut item = vec![];
+        varint(1 << 3, &mut item);
+        varint(i, &mut item);
+        len_field(2, format!("item number {}", i).as_bytes(), &mut item);
+        let mut packed = vec![];
+        for v in 0..8 { varint(i * 1000 + v, &mut packed); }
+        len_field(3, &packed, &mut item);
+        let mut point = vec![];
+        point.push(1 << 3 | 1);
+        point.extend_from_slice(&(i as f64).to_le_bytes());
+        point.push(2 << 3 | 1);
+        point.extend_from_slice(&(-(i as f64)).to_le_bytes());
+        len_field(4, &point, &mut item);
+        len_field(1, &item, &mut res);
+    }
+    res
+}
+
+// a schema of many messages, each with fields of all kinds and comments
+fn synthetic_schema() -> String {
+    let mut res = String::new();
+    for m in 0..300 {
+        res += &format!("// message {}\nmessage M{} {{\n", m, m);
+        for f in 1..=20 {
+            match f % 4 {
+                0 => res += &format!("  // field {}\n  repeated int64 f{} = {};\n", f, f, f),
+                1 => res += &format!("  string f{} = {};\n", f, f),
+                2 => res += &format!("  M{} f{} = {};\n", (m + 1) % 300, f, f),
+                _ => res += &format!("  optional double f{} = {} [default = 1.5];\n", f, f),
+            }
+        }
+        res += "}\n";
+    }
+    res
+}
+
+fn decode(c: &mut Criterion) {
+    let proto = ProtoData::new(SCHEMA).unwrap().finalize().unwrap();
+    let bytes = synthetic_data();
+    let mut group = c.benchmark_group("decode");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("tags and varints", |b| b.iter(|| {
+        let mut limit = bytes.len() as u32;
+        let mut reader = PbReader::new(bytes.as_slice());
+        let mut count = 0;
+        while limit > 0 {
+            let tag = reader.read_tag(&mut limit).unwrap();
+            reader.read_len(tag.length, &mut limit).unwrap();
+            count += 1;
+        }
+        black_box(count)
+    }));
+    group.bench_function("message data", |b| b.iter(|| {
+        let root = proto.get_message_definition("Root").unwrap();
+        MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, root, &mut (bytes.len() as u32)).unwrap()
+    }));
+    group.finish();
+}
+
+fn parse(c: &mut Criterion) {
+    let text = synthetic_schema();
+    let mut group = c.benchmark_group("proto");
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("parse and finalize", |b| b.iter(|| ProtoData::new(black_box(&text)).unwrap().finalize().unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, decode, parse);
+criterion_main!(benches);
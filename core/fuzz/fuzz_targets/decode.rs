@@ -0,0 +1,35 @@
+#![no_main]
+
+use std::rc::Rc;
+use libfuzzer_sys::fuzz_target;
+use pbedit_core::blob::{Source, SourceReader};
+use pbedit_core::proto::ProtoData;
+use pbedit_core::typedefs::PbReader;
+use pbedit_core::wire::MessageData;
+
+// every wire type, packed values, a recursive message, a map and an enum; the seeds in corpus/decode are of it
+const SCHEMA: &str = "enum E { A = 0; B = 1; }\n\
+    message M { int32 i = 1; string s = 2; repeated sint64 p = 3; double d = 4; repeated float f = 5; M m = 6; E e = 7; bytes b = 8;\n\
+    repeated M r = 9; map<string, M> mm = 10; repeated E re = 11; repeated fixed64 rf = 12; bool bo = 13; }";
+
+thread_local! {
+    static PROTO: ProtoData = ProtoData::new(SCHEMA).unwrap().finalize().unwrap();
+}
+
+// any input is read or rejected with an error, never a panic; what is read is shown, written and read again
+fuzz_target!(|input: &[u8]| {
+    PROTO.with(|proto| {
+        let root = proto.get_message_definition("M").unwrap();
+        let read = |bytes: &[u8]| MessageData::new(&mut PbReader::new(bytes), proto, root.clone(), &mut (bytes.len() as u32));
+        let mapped = MessageData::new(&mut SourceReader::new(Rc::new(Source::Read(input.to_vec()))), proto, root.clone(), &mut (input.len() as u32));
+        let Ok(data) = read(input) else {
+            assert!(mapped.is_err(), "read from the memory only");
+            return;
+        };
+        assert_eq!(mapped.expect("read from the stream only").fields.len(), data.fields.len());
+        let _ = data.to_string();
+        let mut output = vec![];
+        data.write(&mut output, proto, root.clone()).unwrap();
+        read(&output).expect("written data is read again");
+    });
+});
@@ -0,0 +1,345 @@
+use std::io;
+use crate::proto::ProtoData;
+use crate::proto::FieldProtoPtr;
+use crate::typedefs::CommonFieldProto;
+use crate::trz::{Change, ChangeType};
+use crate::walk::DepthFirst;
+use crate::wire::{FieldPath, FieldValue, MessageData, ScalarValue};
+
+// Edit commands applied to the data without the UI, one command per line:
+//   set a.b[2].name = "foo"   overwrite a value, missing fields and parent messages are created
+//   insert a.c[1] = 10        insert a value (a message if no value) before the index
+//   delete a.c                remove a field with all its content
+//   decode a.d as Item        show and edit a bytes field as a message of the type
+//   encode a.d                a decoded bytes field back to bytes
+//   merge a.c                 place all values of a repeated field together, after the first one
+// empty lines and lines started with # are ignored
+
+/// An edit command of a script, with the path it is applied to.
+pub enum Command {
+    /// `set path = value`: the value is parsed as the type of the field.
+    Set(FieldPath, String),
+    /// `insert path = value`, or `insert path` for an empty message.
+    Insert(FieldPath, Option<String>),
+    /// `delete path`.
+    Delete(FieldPath),
+    /// `decode path as Type`: the bytes field is read as a message of the type.
+    Decode(FieldPath, String),
+    /// `encode path`: the message decoded from bytes becomes a bytes field again.
+    Encode(FieldPath),
+    /// `merge path`: the values of the repeated field are placed together.
+    Merge(FieldPath),
+}
+
+impl Command {
+    /// The command of a script line, None for an empty line or a comment.
+    pub fn parse(line: &str, data: &MessageData, proto: &ProtoData) -> io::Result<Option<Command>> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { return Ok(None); }
+        let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if !["set", "insert", "delete", "decode", "encode", "merge"].contains(&name) {
+            return Err(error(format!("unknown command \"{}\"", name)));
+        }
+        if name == "decode" {
+            let (path, typename) = args.split_once(" as ").ok_or_else(|| error("message type expected: decode path as Type".into()))?;
+            return Ok(Some(Command::Decode(FieldPath::parse_in(path, data, proto)?, typename.trim().to_string())));
+        }
+        let (path, value) = match args.split_once('=') {
+            Some((path, value)) => (path, Some(value.trim().to_string())),
+            None => (args, None),
+        };
+        let path = FieldPath::parse_in(path, data, proto)?;
+        match (name, value) {
+            ("set", Some(value)) => Ok(Some(Command::Set(path, value))),
+            ("insert", value) => Ok(Some(Command::Insert(path, value))),
+            ("delete", None) => Ok(Some(Command::Delete(path))),
+            ("encode", None) => Ok(Some(Command::Encode(path))),
+            ("merge", None) => Ok(Some(Command::Merge(path))),
+            ("set", None) => Err(error("value expected: set path = value".into())),
+            _ => Err(error(format!("unexpected value: {} path", name))),
+        }
+    }
+
+    /// Applies the command to the data, an error if a path or a value does not match the schema.
+    pub fn execute(self, data: &mut MessageData, proto: &ProtoData) -> io::Result<()> {
+        match self {
+            Command::Set(path, text) => {
+                create_parents(data, proto, &path)?;
+                let value = parse_value(data, &path, &text)?;
+                if data.get_field(&path.0).is_some() {
+                    apply(data, Change::change_value(path, value))
+                } else {
+                    check_append_index(data, &path)?;
+                    apply(data, Change::insert_scalar(path, value))
+                }
+            }
+            Command::Insert(path, text) => {
+                create_parents(data, proto, &path)?;
+                if data.get_field(&path.0).is_none() {
+                    check_append_index(data, &path)?;
+                }
+                match text {
+                    Some(text) => {
+                        let value = parse_value(data, &path, &text)?;
+                        apply(data, Change::insert_scalar(path, value))
+                    }
+                    None => {
+                        let message = new_message(data, proto, &path)?;
+                        apply(data, Change::insert_message(path, message))
+                    }
+                }
+            }
+            Command::Delete(path) => {
+                if data.get_field(&path.0).is_none() {
+                    return Err(error("field not found".into()));
+                }
+                apply(data, Change::delete_value(path))
+            }
+            Command::Decode(path, typename) => {
+                let msg_def = proto.get_message_definition(&typename).ok_or_else(|| error(format!("message {} not found", typename)))?;
+                data.decode_bytes(&path, msg_def, proto).map(|_| ())
+            }
+            Command::Encode(path) => data.encode_bytes(&path, proto).map(|_| ()),
+            Command::Merge(path) => {
+                let (last, parent) = path.0.split_last().unwrap();
+                match data.get_submessage_mut(parent) {
+                    Some(msg) if msg.group_count(last.id) > 0 => { msg.merge_groups(last.id); Ok(()) }
+                    _ => Err(error("field not found".into())),
+                }
+            }
+        }
+    }
+}
+
+/// Applies the commands of the script line by line, returns the number of commands executed.
+/// An error names the line of the command that failed, the commands before it stay applied.
+pub fn apply_script(data: &mut MessageData, proto: &ProtoData, script: &str) -> io::Result<usize> {
+    let mut count = 0;
+    for (n, line) in script.lines().enumerate() {
+        let with_line = |e: io::Error| io::Error::new(e.kind(), format!("line {}: {}", n + 1, e));
+        if let Some(command) = Command::parse(line, data, proto).map_err(with_line)? {
+            command.execute(data, proto).map_err(with_line)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// The command doing the change, for the data before it, None for unknown fields.
+pub fn change_to_command(data: &MessageData, change: &Change) -> Option<String> {
+    let path = change.path.to_text(data)?;
+    let def = data.get_field_definition(&change.path)?;
+    let value = |scalar: &ScalarValue| value_text(&def, scalar);
+    match &change.action {
+        ChangeType::Overwrite(FieldValue::SCALAR(scalar)) => Some(format!("set {} = {}", path, value(scalar)?)),
+        ChangeType::Insert(FieldValue::SCALAR(scalar)) => Some(format!("insert {} = {}", path, value(scalar)?)),
+        ChangeType::Insert(FieldValue::MESSAGE(message)) if message.fields.is_empty() => Some(format!("insert {}", path)),
+        ChangeType::Delete => Some(format!("delete {}", path)),
+        _ => None,
+    }
+}
+
+/// The commands inserting the field of the data with all its content, None if it has unknown fields.
+pub fn field_commands(data: &MessageData, path: &FieldPath) -> Option<Vec<String>> {
+    let field = data.get_field(&path.0)?;
+    let text = path.to_text(data)?;
+    let msg = match &field.value {
+        FieldValue::SCALAR(scalar) => return Some(vec![format!("insert {} = {}", text, value_text(&field.def, scalar)?)]),
+        FieldValue::MESSAGE(msg) => msg,
+    };
+    // the content goes in the order of the data, each insert appends a value
+    let mut res = vec![format!("insert {}", text)];
+    for item in DepthFirst::new(msg) {
+        let full = FieldPath([path.0.as_slice(), &item.path.0].concat());
+        let text = full.to_text(data)?;
+        match item.value() {
+            FieldValue::SCALAR(scalar) => res.push(format!("insert {} = {}", text, value_text(item.def(), scalar)?)),
+            FieldValue::MESSAGE(_) => res.push(format!("insert {}", text)),
+        }
+    }
+    Some(res)
+}
+
+/// The value as written in the commands, None for an unknown field.
+pub fn value_text(def: &FieldProtoPtr, scalar: &ScalarValue) -> Option<String> {
+    match scalar {
+        ScalarValue::STR(text) => Some(quote(text)),
+        ScalarValue::ENUM(index) => Some(def.get_enum_name_by_index(*index).map_or(index.to_string(), |name| name.to_string())),
+        ScalarValue::UNKNOWN(..) => None,
+        scalar => Some(scalar.to_string().trim_end().to_string()),
+    }
+}
+
+// a string value with escapes, as CommonFieldProto::unquote expects
+fn quote(text: &str) -> String {
+    let mut res = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            '\0' => res.push_str("\\0"),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+fn error(msg: String) -> io::Error { io::Error::new(io::ErrorKind::InvalidInput, msg) }
+
+fn apply(data: &mut MessageData, mut change: Change) -> io::Result<()> {
+    data.apply(&mut change).ok_or_else(|| error("cannot change the data".into()))
+}
+
+fn parse_value(data: &MessageData, path: &FieldPath, text: &str) -> io::Result<crate::wire::ScalarValue> {
+    let def = data.get_field_definition(path).ok_or_else(|| error("field not found".into()))?;
+    if def.is_message() {
+        return Err(error(format!("field \"{}\" is a message, a value cannot be set", def.name())));
+    }
+    def.parse_value(text).ok_or_else(|| error(CommonFieldProto::range_error(def.as_ref(), text).
+        unwrap_or_else(|| format!("\"{}\" is not a {} value", text, def.typename()))))
+}
+
+fn new_message(data: &MessageData, proto: &ProtoData, path: &FieldPath) -> io::Result<MessageData> {
+    let def = data.get_field_definition(path).ok_or_else(|| error("field not found".into()))?;
+    let msg_def = proto.get_message_definition(&def.typename()).
+        ok_or_else(|| error(format!("field \"{}\" is not a message, a value expected", def.name())))?;
+    Ok(MessageData { def: msg_def, fields: vec![], packed: vec![] })
+}
+
+// a new value can be added only right after the last existing one
+fn check_append_index(data: &MessageData, path: &FieldPath) -> io::Result<()> {
+    if let Some((last, parent)) = path.0.split_last() {
+        let parent = data.get_submessage(parent).ok_or_else(|| error("parent message not found".into()))?;
+        let count = parent.fields.iter().filter(|f| f.id() == last.id).count();
+        if last.index > count {
+            return Err(error(format!("index {} is out of range, the field has {} values", last.index, count)));
+        }
+    }
+    Ok(())
+}
+
+fn create_parents(data: &mut MessageData, proto: &ProtoData, path: &FieldPath) -> io::Result<()> {
+    for len in 1..path.0.len() {
+        let parent = FieldPath(path.0[..len].to_vec());
+        if data.get_submessage(&parent.0).is_none() {
+            check_append_index(data, &parent)?;
+            let message = new_message(data, proto, &parent)?;
+            apply(data, Change::insert_message(parent, message))?;
+        }
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod batch_tests {
+    use super::*;
+    use crate::typedefs::PbReader;
+
+    const PROTO: &str = "enum E { A = 0; B = 1; }\nmessage Root { string name = 1; Item item = 2; repeated int32 ids = 3; }\nmessage Item { E kind = 1; repeated Item children = 2; bytes raw = 3; bool ok = 4; }";
+
+    fn run(script: &str) -> io::Result<String> {
+        let proto = ProtoData::new(PROTO).unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut read = PbReader::new([].as_slice());
+        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut 0).unwrap();
+        apply_script(&mut data, &proto, script)?;
+        Ok(data.to_string())
+    }
+
+    #[test]
+    fn set_values() {
+        assert_eq!(run("set name = \"foo \\\"bar\\\"\"").unwrap(), "message Root {\n  name = foo \"bar\"\n}\n");
+        assert_eq!(run("set name = a\nset name = b").unwrap(), "message Root {\n  name = b\n}\n");
+        assert_eq!(run("# comment\n\nset item.kind = B\nset item.ok = true\nset item.raw = 0a ff").unwrap(),
+                   "message Root {\n  item = message Item {\n  kind = B\n  ok = true\n  raw = 0a ff\n}\n\n}\n");
+        assert_eq!(run("set item.children[0].children[0].ok = 1").unwrap(),
+                   "message Root {\n  item = message Item {\n  children = message Item {\n  children = message Item {\n  ok = true\n}\n\n}\n\n}\n\n}\n");
+    }
+
+    #[test]
+    fn repeated_values() {
+        assert_eq!(run("set ids[0] = 1\nset ids[1] = 2\ninsert ids[1] = 3").unwrap(), "message Root {\n  ids = 1\n  ids = 3\n  ids = 2\n}\n");
+        assert_eq!(run("set ids = 1\nset ids[1] = 2\ndelete ids[0]").unwrap(), "message Root {\n  ids = 2\n}\n");
+        assert_eq!(run("insert item.children\ninsert item.children\nset item.children[1].ok = true").unwrap(),
+                   "message Root {\n  item = message Item {\n  children = message Item {\n}\n\n  children = message Item {\n  ok = true\n}\n\n}\n\n}\n");
+        assert_eq!(run("set ids[0] = 1\nset name = a\nset ids[1] = 2\nmerge ids").unwrap(), "message Root {\n  ids = 1\n  ids = 2\n  name = a\n}\n");
+        assert_eq!(run("merge ids").unwrap_err().to_string(), "line 1: field not found");
+    }
+
+    #[test]
+    fn errors() {
+        let error = |script| run(script).unwrap_err().to_string();
+        assert_eq!(error("set ids[1] = 1"), "line 1: index 1 is out of range, the field has 0 values");
+        assert_eq!(error("set name = a\nset age = 1"), "line 2: field \"age\" not found in message Root");
+        assert_eq!(error("set name[1] = a"), "line 1: field \"name\" is not repeated");
+        assert_eq!(error("set name.x = a"), "line 1: field \"name\" is not a message");
+        assert_eq!(error("set ids = x"), "line 1: \"x\" is not a int32 value");
+        assert_eq!(error("set ids = 0x8000_0000"), "line 1: 0x8000_0000 is out of the int32 range -2147483648..2147483647");
+        assert_eq!(error("set item = 1"), "line 1: field \"item\" is a message, a value cannot be set");
+        assert_eq!(error("delete item"), "line 1: field not found");
+        assert_eq!(error("remove item"), "line 1: unknown command \"remove\"");
+        assert_eq!(error("set ids"), "line 1: value expected: set path = value");
+    }
+
+    #[test]
+    fn decode_bytes() {
+        assert_eq!(run("set item.raw = 20 01\ndecode item.raw as Item\nset item.raw.kind = B").unwrap(),
+                   "message Root {\n  item = message Item {\n  raw = message Item {\n  ok = true\n  kind = B\n}\n\n}\n\n}\n");
+        assert_eq!(run("set item.raw = 20 01\ndecode item.raw as Item\nset item.raw.kind = B\nencode item.raw").unwrap(),
+                   "message Root {\n  item = message Item {\n  raw = 20 01 08 01\n}\n\n}\n");
+        let error = |script| run(script).unwrap_err().to_string();
+        assert_eq!(error("set item.kind = A\ndecode item.kind as Item"), "line 2: not a bytes field");
+        assert_eq!(error("set item.raw = 20 01\ndecode item.raw as Root"), "line 2: not a Root message: unknown fields");
+        assert_eq!(error("set item.raw = 20 01\ndecode item.raw as Other"), "line 2: message Other not found");
+        assert_eq!(error("set item.raw = 20 01\nencode item.raw"), "line 2: not a decoded bytes field");
+    }
+
+    #[test]
+    fn commands_for_changes() {
+        let proto = ProtoData::new(PROTO).unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        let start = "set name = x\nset item.kind = A\nset item.raw = 00\ninsert item.children\nset ids[0] = 1\n";
+        apply_script(&mut data, &proto, start).unwrap();
+        let changes = [
+            Change::change_value([(1, 0)].into(), ScalarValue::STR("a \"b\"\n".into())),
+            Change::change_value([(2, 0), (1, 0)].into(), ScalarValue::ENUM(1)),
+            Change::insert_scalar([(3, 1)].into(), ScalarValue::I32(-5)),
+            Change::insert_message([(2, 0), (2, 1)].into(), MessageData { def: proto.get_message_definition("Item").unwrap(), fields: vec![], packed: vec![] }),
+            Change::change_value([(2, 0), (3, 0)].into(), ScalarValue::BYTES(vec![0x0a, 0xff].into())),
+            Change::delete_value([(3, 0)].into()),
+        ];
+        let mut script = String::new();
+        for mut change in changes {
+            script += &change_to_command(&data, &change).unwrap();
+            script.push('\n');
+            data.apply(&mut change).unwrap();
+        }
+        assert_eq!(script, "set name = \"a \\\"b\\\"\\n\"\nset item.kind = B\ninsert ids[1] = -5\ninsert item.children[1]\nset item.raw = 0a ff\ndelete ids[0]\n");
+        assert_eq!(run(&(start.to_string() + &script)).unwrap(), data.to_string());
+    }
+
+    #[test]
+    fn commands_for_field() {
+        let proto = ProtoData::new(PROTO).unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        let start = "set item.kind = B
+set item.children[0].ok = true
+insert item.children[1]
+set item.children[1].raw = 01 02
+set ids[0] = 7
+";
+        apply_script(&mut data, &proto, start).unwrap();
+        let commands = field_commands(&data, &[(2, 0)].into()).unwrap();
+        assert_eq!(commands, ["insert item", "insert item.kind = B", "insert item.children[0]", "insert item.children[0].ok = true",
+            "insert item.children[1]", "insert item.children[1].raw = 01 02"]);
+        assert_eq!(field_commands(&data, &[(3, 0)].into()).unwrap(), ["insert ids[0] = 7"]);
+        assert_eq!(run(&(commands.join("\n") + "\nset ids[0] = 7")).unwrap(), data.to_string());
+    }
+}
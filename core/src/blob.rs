@@ -0,0 +1,211 @@
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
+use crate::typedefs::{count_bytes_read, PbReaderTrait};
+
+/// The content of a data file: mapped into memory, or read (stdin, small files, tests).
+pub enum Source {
+    /// The file mapped into memory.
+    Mapped(memmap2::Mmap),
+    /// The bytes read.
+    Read(Vec<u8>),
+}
+
+impl Source {
+    /// Maps the file, the pages of it are read when a value is shown or written. The file must not be truncated
+    /// by other programs while the data is open, it is replaced by a new file on saving.
+    pub fn map(path: &Path) -> io::Result<Arc<Source>> {
+        let file = File::open(path)?;
+        // an empty file cannot be mapped on some systems
+        if file.metadata()?.len() == 0 { return Ok(Arc::new(Source::Read(vec![]))); }
+        Ok(Arc::new(Source::Mapped(unsafe { memmap2::Mmap::map(&file)? })))
+    }
+}
+
+impl Deref for Source {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match self {
+            Source::Mapped(map) => map,
+            Source::Read(bytes) => bytes,
+        }
+    }
+}
+
+/// The value of a bytes field: a range of the source until the value is changed, then its own buffer.
+#[derive(Clone)]
+pub enum Blob {
+    /// A changed or created value.
+    Owned(Vec<u8>),
+    /// The start and the length in the source, positions fit in 32 bits as the read limits do.
+    Shared(Arc<Source>, u32, u32),
+}
+
+impl Blob {
+    /// True while the value is a range of the source.
+    pub fn is_shared(&self) -> bool { matches!(self, Blob::Shared(..)) }
+}
+
+impl Deref for Blob {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        match self {
+            Blob::Owned(bytes) => bytes,
+            Blob::Shared(source, start, len) => &source[*start as usize..(*start + *len) as usize],
+        }
+    }
+}
+
+impl Default for Blob {
+    fn default() -> Self { Blob::Owned(vec![]) }
+}
+
+impl From<Vec<u8>> for Blob {
+    fn from(bytes: Vec<u8>) -> Self { Blob::Owned(bytes) }
+}
+
+impl PartialEq for Blob {
+    fn eq(&self, other: &Self) -> bool { **self == **other }
+}
+
+impl Debug for Blob {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result { Debug::fmt(&**self, f) }
+}
+
+/// The value of a string field: its UTF-8 is checked once when it is read, then it is used as a str
+/// without copying it from the source. A changed value has its own buffer.
+#[derive(Clone, Default, PartialEq)]
+pub struct Text(Blob);
+
+impl Text {
+    /// The text of the bytes, None if they are not UTF-8.
+    pub fn from_blob(blob: Blob) -> Option<Text> {
+        std::str::from_utf8(&blob).is_ok().then_some(Text(blob))
+    }
+
+    /// True while the text is a range of the source.
+    pub fn is_shared(&self) -> bool { self.0.is_shared() }
+}
+
+impl Deref for Text {
+    type Target = str;
+    fn deref(&self) -> &str {
+        // checked by from_blob, the owned buffers come from strings; a mapped file is not changed while it is open
+        unsafe { std::str::from_utf8_unchecked(&self.0) }
+    }
+}
+
+impl From<String> for Text {
+    fn from(text: String) -> Self { Text(Blob::Owned(text.into_bytes())) }
+}
+
+impl From<&str> for Text {
+    fn from(text: &str) -> Self { Text::from(text.to_string()) }
+}
+
+impl Debug for Text {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result { Debug::fmt(&**self, f) }
+}
+
+impl std::fmt::Display for Text {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result { std::fmt::Display::fmt(&**self, f) }
+}
+
+/// Reads the data from the source, bytes and string values are kept as ranges of it.
+pub struct SourceReader {
+    source: Arc<Source>,
+    pos: usize,
+}
+
+impl SourceReader {
+    /// A reader from the start of the source.
+    pub fn new(source: Arc<Source>) -> SourceReader { SourceReader { source, pos: 0 } }
+
+    fn take(&mut self, length: u32, limit: &mut u32) -> io::Result<usize> {
+        if *limit < length || self.pos + length as usize > self.source.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "read data out of limit"));
+        }
+        *limit -= length;
+        self.pos += length as usize;
+        Ok(self.pos - length as usize)
+    }
+}
+
+impl Drop for SourceReader {
+    fn drop(&mut self) {
+        count_bytes_read(self.pos);
+    }
+}
+
+impl PbReaderTrait for SourceReader {
+    fn pos(&self) -> usize { self.pos }
+    fn read_varint(&mut self, limit: &mut u32) -> io::Result<i128> {
+        let mut value: i128 = 0;
+        let mut bits_read: u8 = 0;
+        while *limit > 0 {
+            let Some(byte) = self.source.get(self.pos) else { break; };
+            *limit -= 1;
+            self.pos += 1;
+            value |= ((byte & 0x7f) as i128) << bits_read;
+            if byte & 0x80 == 0 { return Ok(value); }
+            if *limit == 0 { break; }
+            if bits_read > 64 - 8 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "VARINT overflow"));
+            }
+            bits_read += 7;
+        }
+        Err(io::Error::new(io::ErrorKind::UnexpectedEof, "not completed VARINT"))
+    }
+    fn read_len(&mut self, length: u32, limit: &mut u32) -> io::Result<Vec<u8>> {
+        let start = self.take(length, limit)?;
+        Ok(self.source[start..self.pos].to_vec())
+    }
+    fn read_blob(&mut self, length: u32, limit: &mut u32) -> io::Result<Blob> {
+        let start = self.take(length, limit)?;
+        Ok(Blob::Shared(self.source.clone(), start as u32, length))
+    }
+}
+
+
+#[cfg(test)]
+mod blob_tests {
+    use super::*;
+    use crate::proto::ProtoData;
+    use crate::typedefs::PbReader;
+    use crate::wire::{FieldValue, MessageData, ScalarValue};
+
+    #[test]
+    fn shared_values() {
+        let proto = ProtoData::new("message M { int32 n = 1; bytes b = 2; repeated string s = 3; }").unwrap().finalize().unwrap();
+        let bytes = vec![0x08, 0x96, 0x01, 0x12, 0x03, 0x01, 0x02, 0x03, 0x1a, 0x01, b'a'];
+        let root = proto.auto_detect_root_message().unwrap();
+        let source = Arc::new(Source::Read(bytes.clone()));
+        let data = MessageData::new(&mut SourceReader::new(source.clone()), &proto, root.clone(), &mut (bytes.len() as u32)).unwrap();
+        let FieldValue::SCALAR(ScalarValue::BYTES(blob)) = &data.fields[1].value else { panic!() };
+        assert!(blob.is_shared());
+        assert_eq!(**blob, [1, 2, 3]);
+        // strings too
+        let FieldValue::SCALAR(ScalarValue::STR(text)) = &data.fields[2].value else { panic!() };
+        assert!(text.is_shared());
+        assert_eq!(&**text, "a");
+        assert_eq!(Arc::strong_count(&source), 3);
+        assert!(Text::from_blob(Blob::Shared(source.clone(), 1, 2)).is_none());
+
+        // read the same way as by the stream reader, written back byte for byte
+        let read = MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, root.clone(), &mut (bytes.len() as u32)).unwrap();
+        assert_eq!(data.fields.iter().map(|f| f.pos).collect::<Vec<_>>(), read.fields.iter().map(|f| f.pos).collect::<Vec<_>>());
+        let mut out = vec![];
+        data.write(&mut out, &proto, root.clone()).unwrap();
+        assert_eq!(out, bytes);
+
+        let mut limit = 2;
+        assert!(SourceReader::new(source.clone()).read_blob(3, &mut limit).is_err());
+        // a changed value is not in the source
+        let mut data = data;
+        data.fields[2].value = FieldValue::SCALAR(ScalarValue::STR("b".into()));
+        assert_eq!(Arc::strong_count(&source), 2);
+    }
+}
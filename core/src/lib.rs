@@ -0,0 +1,42 @@
+//! The schema-aware editing engine of protoedit, without the terminal UI.
+//!
+//! * [`proto`] parses `.proto` files: [`proto::ProtoData::new`] reads the text, `finalize` resolves the types.
+//! * [`wire`] decodes the data into [`wire::MessageData`], edits it by [`wire::FieldPath`] and encodes it back,
+//!   fields read but not changed are written as they were read.
+//! * [`batch`] applies edit commands like `set a.b[2].name = "foo"` (the `--apply` format of protoedit).
+//! * [`trz`] describes a change of the data, it can be applied and reverted.
+//! * [`walk`] visits the fields of a message depth first.
+//! * [`blob`] keeps the bytes values of a mapped data file in the file until they are changed.
+//!
+//! ```
+//! use pbedit_core::proto::ProtoData;
+//! use pbedit_core::typedefs::PbReader;
+//! use pbedit_core::wire::MessageData;
+//!
+//! let proto = ProtoData::new("message M { int32 id = 1; string name = 2; }").unwrap().finalize().unwrap();
+//! let bytes = [0x08, 0x05];
+//! let mut data = MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, proto.auto_detect_root_message().unwrap(), &mut (bytes.len() as u32)).unwrap();
+//! pbedit_core::batch::apply_script(&mut data, &proto, "set name = \"x\"").unwrap();
+//! let mut out = vec![];
+//! data.write(&mut out, &proto, data.def.clone()).unwrap();
+//! assert_eq!(out, [0x08, 0x05, 0x12, 0x01, b'x']);
+//! ```
+
+#![warn(missing_docs)]
+// `len` of the values and messages is the size of their encoding, there is nothing like an empty one
+#![allow(clippy::len_without_is_empty)]
+
+/// The schema: messages, enums and field definitions read from `.proto` files.
+pub mod proto;
+/// The field definitions of each type, reading and writing their values in the wire format.
+pub mod typedefs;
+/// The decoded data: messages, fields, values and the paths to them.
+pub mod wire;
+/// Changes of the data, applied, undone and redone.
+pub mod trz;
+/// Depth-first traversal of the fields of a message.
+pub mod walk;
+/// The edit commands of `--apply` scripts.
+pub mod batch;
+/// Bytes and string values kept in the data file until they are changed.
+pub mod blob;
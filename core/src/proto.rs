@@ -0,0 +1,1181 @@
+use std::collections::HashSet;
+use std::fmt::{Debug, Formatter};
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::Arc;
+use pest::iterators::{Pairs};
+use crate::typedefs::*;
+
+use pest::Parser;
+use grammar::{PBParser, Rule};
+
+// the parser generated from the grammar, its rules are not a part of the API
+mod grammar {
+    #[derive(pest_derive::Parser)]
+    #[grammar = "pb.pest"]
+    pub struct PBParser;
+}
+
+// the largest field number, "max" of the reserved ranges
+const MAX_FIELD_NUMBER: i32 = (1 << 29) - 1;
+
+
+/// A `.proto` file read from the disk.
+pub struct ProtoFile {
+    path: PathBuf,
+    /// The text of the file.
+    pub content: String,
+}
+
+/// The messages and enums of one or more `.proto` files, usable to read the data after [`ProtoData::finalize`].
+#[derive(Clone)]
+pub struct ProtoData {
+    messages: Vec<MessageProtoPtr>,
+    enums: Vec<EnumProtoPtr>,
+    /// The definition of the fields not found in the messages.
+    pub unknown_field: FieldProtoPtr,
+    /// Fields of types not found by finalize (message.field: type), they are read as unknown.
+    pub unresolved: Vec<String>,
+    // fields of extend blocks: the extended type, the scope of the block and the field named (Scope.name),
+    // they are added to the extended messages by finalize
+    extensions: Vec<(String, String, FieldProtoPtr)>,
+}
+
+/// A field definition shared by the message and the data read with it.
+pub type FieldProtoPtr = Arc<dyn FieldProto>;
+/// A message definition shared by the schema and the data.
+pub type MessageProtoPtr = Arc<MessageProto>;
+/// An enum definition shared by the schema and its fields.
+pub type EnumProtoPtr = Arc<EnumProto>;
+
+/// A message of the schema.
+pub struct MessageProto {
+    /// The full name, with the names of the outer messages: `Outer.Inner`.
+    pub name: String,
+    /// The fields in the order of the definition.
+    pub fields: Vec<FieldProtoPtr>,
+    /// The comment above the definition.
+    pub comment: String,
+    /// Field numbers not to be used, from the reserved statements.
+    pub reserved: Vec<RangeInclusive<i32>>,
+    /// Field names not to be used, from the reserved statements.
+    pub reserved_names: Vec<String>,
+}
+
+// the presence of fields declared without optional, required or repeated, by the features of editions
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Presence {
+    Implicit,
+    Explicit,
+    Required,
+}
+
+impl Presence {
+    // of a file or a message: explicit in editions, set by `option features.field_presence = IMPLICIT;`,
+    // else the presence of the outer scope
+    fn of_scope(pairs: Pairs<Rule>, outer: Presence) -> Presence {
+        let mut res = outer;
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::edition => res = Presence::Explicit,
+                Rule::option => {
+                    let text = pair.as_str();
+                    let name = pair.into_inner().next().map_or("", |name| name.as_str());
+                    let value = text.split_once('=').map_or("", |(_, value)| value.trim().trim_end_matches(';').trim());
+                    res = Self::from_option(name, value).unwrap_or(res);
+                }
+                _ => {}
+            }
+        }
+        res
+    }
+
+    fn from_option(name: &str, value: &str) -> Option<Presence> {
+        if name != "features.field_presence" { return None; }
+        match value {
+            "IMPLICIT" => Some(Presence::Implicit),
+            "EXPLICIT" => Some(Presence::Explicit),
+            "LEGACY_REQUIRED" => Some(Presence::Required),
+            _ => None,
+        }
+    }
+}
+
+/// An enum of the schema.
+pub struct EnumProto {
+    /// The full name, with the names of the outer messages.
+    pub name: String,
+    /// The name, the number and the comment of each value.
+    pub variants: Vec<(String, i32, String)>,
+    /// The comment above the definition.
+    pub comment: String,
+}
+
+impl ProtoData {
+    /// The definitions of the text of a `.proto` file, not linked together before [`ProtoData::finalize`].
+    pub fn new(input: &str) -> io::Result<ProtoData> {
+        match PBParser::parse(Rule::file, input) {
+            Ok(rules_pairs) => ProtoData::from_pairs(rules_pairs),
+            Err(e) => Err(io::Error::other(e.to_string()))
+        }
+    }
+
+    /// The message not used by the others, None if there are several or none.
+    pub fn auto_detect_root_message(&self) -> Option<MessageProtoPtr> {
+        let mut candidates = self.root_candidates();
+        if candidates.len() == 1 { candidates.pop() } else { None }
+    }
+
+    /// Messages not used as a field type of another message.
+    pub fn root_candidates(&self) -> Vec<MessageProtoPtr> {
+        // root message cannot be used as a field of another message (but can be himself field)
+        let all_msg_names: HashSet<String> = self.messages.iter().map(|m| m.name.clone()).collect();
+
+        // remove auto-created messages for map fields
+        let all_msg_names = all_msg_names.into_iter().filter(|m| !m.contains(",")).collect();
+
+        let mut sub_msg_names = vec![];
+        let names = self.type_names();
+        for msg in &self.messages {
+            for fld in &msg.fields {
+//                if fld.is_message() { // unless the proto data finalized we do not know is it a message
+                    let typename = Self::resolve_type(&names, &msg.name, &fld.typename());
+                    if typename != msg.name {
+                        sub_msg_names.push(typename);
+                    }
+//                }
+            }
+        }
+
+        let used_msg: HashSet<String> = sub_msg_names.into_iter().collect();
+
+        let top_lvl_msg = &all_msg_names - &used_msg;
+
+        // in the order of the definitions
+        self.messages.iter().filter(|m| top_lvl_msg.contains(&m.name)).cloned().collect()
+    }
+
+    /// By the full name, or by the name in its parent (Nested for Parent.Nested) if it is unique.
+    pub fn get_message_definition(&self, name: &str) -> Option<MessageProtoPtr> {
+        if let Ok(index) = self.messages.binary_search_by(|m| m.name.as_str().cmp(name)) {
+            Some(self.messages[index].clone())
+        } else {
+            Self::find_nested(&self.messages, |m| m.name.as_str(), name).cloned()
+        }
+    }
+
+    /// By the full name, or by the name in its parent if it is unique.
+    pub fn get_enum_definition(&self, name: &str) -> Option<&EnumProto> {
+        if let Ok(index) = self.enums.binary_search_by(|m| m.name.as_str().cmp(name)) {
+            Some(&self.enums[index])
+        } else {
+            Self::find_nested(&self.enums, |e| e.name.as_str(), name).map(|e| e.as_ref())
+        }
+    }
+
+    /// Sorted by the full name after finalize.
+    pub fn messages(&self) -> &[MessageProtoPtr] { &self.messages }
+    /// Sorted by the full name after finalize.
+    pub fn enums(&self) -> &[EnumProtoPtr] { &self.enums }
+
+    fn find_nested<'a, T>(items: &'a [T], name_of: impl Fn(&T) -> &str, name: &str) -> Option<&'a T> {
+        let suffix = format!(".{}", name);
+        // messages of map entries (key,value) are not nested
+        let mut found = items.iter().filter(|item| name_of(item).ends_with(&suffix) && !name_of(item).contains(','));
+        let first = found.next();
+        if found.next().is_some() { None } else { first }
+    }
+
+    fn scoped_name(scope: &str, name: &str) -> String {
+        if scope.is_empty() { name.to_string() } else { format!("{}.{}", scope, name) }
+    }
+
+    // the full name of the message or enum used as a type in the scope, the innermost scope is searched first as protoc does
+    fn resolve_name(names: &HashSet<&str>, scope: &str, name: &str) -> Option<String> {
+        if let Some(name) = name.strip_prefix('.') { return Self::resolve_name(names, "", name); }
+        let mut scope: Vec<&str> = scope.split('.').filter(|s| !s.is_empty()).collect();
+        loop {
+            let full = Self::scoped_name(&scope.join("."), name);
+            if names.contains(full.as_str()) { return Some(full); }
+            if scope.pop().is_none() { break; }
+        }
+        // package names are not kept, pkg.Type is Type
+        name.split_once('.').and_then(|(_, rest)| Self::resolve_name(names, "", rest))
+    }
+
+    // the name of a field type used in the message, the types of a map (key,value) are resolved each
+    fn resolve_type(names: &HashSet<&str>, scope: &str, typename: &str) -> String {
+        typename.split(',').map(|part| Self::resolve_name(names, scope, part).unwrap_or_else(|| part.to_string())).
+            collect::<Vec<String>>().join(",")
+    }
+
+    fn type_names(&self) -> HashSet<&str> {
+        self.messages.iter().map(|m| m.name.as_str()).chain(self.enums.iter().map(|e| e.name.as_str())).collect()
+    }
+
+    /// Adds the definitions of another file, before finalize.
+    pub fn append(&mut self, mut other: ProtoData) {
+        self.messages.append(&mut other.messages);
+        self.enums.append(&mut other.enums);
+        self.extensions.append(&mut other.extensions);
+    }
+
+    // nested messages and enums are named by the scope: Parent.Nested
+    fn add_message(pairs: Pairs<Rule>, comment: String, scope: &str, outer: Presence) -> io::Result<ProtoData> {
+        let mut it = pairs.into_iter(); // first get the message name
+        let name_rule = it.next().unwrap();
+        debug_assert_eq!(name_rule.as_rule(), Rule::name);
+        let name = Self::scoped_name(scope, name_rule.as_span().as_str());
+        let presence = Presence::of_scope(it.clone(), outer);
+        let mut field_comment = String::new();
+
+        let mut fields: Vec<Arc<dyn FieldProto>> = Vec::new(); // read message fields and other content
+        let (mut reserved, mut reserved_names) = (vec![], vec![]);
+        let mut res = ProtoData { messages: vec![], enums: vec![], unknown_field: Arc::new(UnknownFieldDefinition::new()), unresolved: vec![], extensions: vec![] };
+        for pair in it {
+            match pair.as_rule() {
+                Rule::msg_field => {
+                    fields.push(Self::field_from_pair(field_comment.clone(), pair.into_inner(), None, presence, None));
+                    field_comment.clear();
+                }
+                Rule::enum1 => {
+                    res.enums.push(Self::add_enum(pair.into_inner(), field_comment.clone(), &name));
+                    field_comment.clear();
+                }
+                Rule::message => {
+                    res.append(Self::add_message(pair.into_inner(), field_comment.clone(), &name, presence)?);
+                    field_comment.clear();
+                }
+                Rule::one_of => {
+                    let mut it = pair.into_inner();
+                    let name_rule = it.next().unwrap();
+                    debug_assert_eq!(name_rule.as_rule(), Rule::name);
+                    let oneof_name = Some(name_rule.as_span().as_str().to_string());
+
+                    for pair in it {
+                        match pair.as_rule() {
+                            Rule::msg_field => {
+                                fields.push(Self::field_from_pair(field_comment.clone(), pair.into_inner(), oneof_name.clone(), presence, None));
+                                field_comment.clear();
+                            }
+                            Rule::COMMENT => {
+                                if !field_comment.is_empty() { field_comment += "\n"; }
+                                field_comment += pair.as_span().as_str().trim_start_matches("//");
+                            }
+                            //Rule::option | Rule::EOI
+                            _ => { panic!("Unknown oneof rule: {:?}", pair.as_rule()); }
+                        }
+                    }
+                }
+                Rule::extend => {
+                    res.extensions.append(&mut Self::add_extend(pair.into_inner(), &name, presence));
+                    field_comment.clear();
+                }
+                Rule::reserved => {
+                    for item in pair.into_inner() {
+                        match item.as_rule() {
+                            Rule::str_literal => reserved_names.push(item.as_str().trim_matches('"').to_string()),
+                            Rule::reserved_range => {
+                                let number = |text: &str| Self::int_lit(text).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                                    format!("{}: reserved {} is not a field number", name, text)));
+                                let mut it = item.into_inner();
+                                let first = number(it.next().unwrap().as_str())?;
+                                let last = match it.next() {
+                                    Some(last) if last.as_rule() == Rule::max => MAX_FIELD_NUMBER,
+                                    Some(last) => number(last.as_str())?,
+                                    None => first,
+                                };
+                                reserved.push(first..=last);
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Rule::COMMENT => {
+                    if !field_comment.is_empty() { field_comment += "\n"; }
+                    field_comment += pair.as_span().as_str().trim_start_matches("//");
+                }
+                Rule::mapname | Rule::extensions |
+                Rule::option | Rule::EOI => {}
+                _ => { panic!("Unknown message rule: {:?}", pair.as_rule()); }
+            };
+        }
+
+        res.messages.push(Arc::new(MessageProto { name, fields, comment, reserved, reserved_names }));
+        Ok(res)
+    }
+
+    // a number of the protobuf grammar (intLit): decimal, octal with a leading 0 or hex with 0x
+    fn int_lit(text: &str) -> Option<i32> {
+        let (digits, radix) = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => (hex, 16),
+            None if text.len() > 1 && text.starts_with('0') => (&text[1..], 8),
+            None => (text, 10),
+        };
+        i32::from_str_radix(digits, radix).ok()
+    }
+
+    fn add_enum(pairs: Pairs<Rule>, comment: String, scope: &str) -> EnumProtoPtr {
+        let mut variants = Vec::new();
+        let mut field_comment = String::new();
+
+        let mut it = pairs.into_iter();
+        let name_rule = it.next().unwrap();
+        debug_assert_eq!(name_rule.as_rule(), Rule::name);
+        let name = Self::scoped_name(scope, name_rule.as_span().as_str());
+
+        for pair in it {
+            match pair.as_rule() {
+                Rule::enum_field => {
+                    let mut it = pair.into_inner();
+                    let name = it.next().unwrap().as_str().to_string();
+                    let value = it.next().unwrap().as_str().to_string();
+                    variants.push((name, value.parse().unwrap(), field_comment.clone()));
+                    field_comment.clear();
+                    // after the options of the value, if any
+                    if let Some(r) = it.find(|r| r.as_rule() != Rule::field_options) {
+                        if r.as_rule() == Rule::COMMENT {
+                            if !field_comment.is_empty() { field_comment += "\n"; }
+                            field_comment += r.as_span().as_str().trim_start_matches("//");
+                        }
+                    }
+                }
+                Rule::option | Rule::EOI => {}
+                _ => {
+                    panic!("Unknown enum rule: {:?}", pair.as_rule());
+                }
+            };
+        }
+
+        Arc::new(EnumProto { name, variants, comment })
+    }
+
+    // the fields of an extend block with the extended type
+    fn add_extend(pairs: Pairs<Rule>, scope: &str, presence: Presence) -> Vec<(String, String, FieldProtoPtr)> {
+        let mut it = pairs.into_iter();
+        let extendee = it.next().unwrap().as_str().to_string();
+        let mut res = vec![];
+        let mut comment = String::new();
+        for pair in it {
+            match pair.as_rule() {
+                Rule::msg_field => {
+                    res.push((extendee.clone(), scope.to_string(), Self::field_from_pair(comment.clone(), pair.into_inner(), None, presence, Some(scope))));
+                    comment.clear();
+                }
+                Rule::COMMENT => {
+                    if !comment.is_empty() { comment += "\n"; }
+                    comment += pair.as_span().as_str().trim_start_matches("//");
+                }
+                _ => {}
+            }
+        }
+        res
+    }
+
+    // an extension field is named by the scope of its extend block in parentheses, as in the options: (Scope.name)
+    fn field_from_pair(comment: String, pairs: Pairs<Rule>, oneof_name: Option<String>, presence: Presence, extension_scope: Option<&str>) -> Arc<dyn FieldProto> {
+        let mut name = String::new();
+        let mut repeated = false;
+        let mut optional = false;
+        let mut required = false;
+        let mut field_presence = None;
+        let mut type_name = String::new();
+        let mut id = 0;
+        let mut default = None;
+        let mut deprecated = false;
+        let mut json_name = None;
+        //        let mut map_types : Option<(String, String)> = None;
+
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::cardinality => {
+                    repeated = pair.as_span().as_str() == "repeated";
+                    optional = pair.as_span().as_str() == "optional";
+                    required = pair.as_span().as_str() == "required";
+                }
+                Rule::mapname => {
+                    let mut it = pair.into_inner();
+                    let key_type = it.next().unwrap().as_str().to_string();
+                    let value_type = it.next().unwrap().as_str().to_string();
+                    type_name = format!("{},{}", key_type, value_type);
+                    //if repeated { warn!("map field ({}) cannot be repeated", name); }
+                    repeated = true;
+                }
+                Rule::typename => {
+                    type_name = pair.as_str().to_string();
+                }
+                Rule::name => {
+                    name = pair.as_span().as_str().to_string();
+                }
+                Rule::integer => {
+                    id = pair.as_span().as_str().parse().unwrap();
+                }
+                Rule::field_options => {
+                    for option in pair.into_inner().filter(|option| option.as_rule() == Rule::field_option) {
+                        let mut it = option.into_inner();
+                        let name = it.next().unwrap().as_str();
+                        let value = it.next().unwrap().as_str().trim();
+                        match name {
+                            "default" => default = Some(value.to_string()),
+                            "deprecated" => deprecated = value == "true",
+                            "json_name" => json_name = CommonFieldProto::unquote(value),
+                            _ => field_presence = Presence::from_option(name, value).or(field_presence),
+                        }
+                    }
+                }
+                Rule::COMMENT | //=> { comments = comments + pair.as_span().as_str(); }
+                Rule::option | Rule::EOI => {}
+                _ => {
+                    panic!("Unknown field rule: {:?}", pair.as_rule());
+                }
+            }
+        };
+
+        // a field without a label has the presence of its message or its own
+        if !repeated && !optional && !required {
+            match field_presence.unwrap_or(presence) {
+                Presence::Explicit => optional = true,
+                Presence::Required => required = true,
+                Presence::Implicit => {}
+            }
+        }
+        if let Some(scope) = extension_scope {
+            name = format!("({})", Self::scoped_name(scope, &name));
+        }
+        CommonFieldProto { name, id, repeated, optional, required, comment, oneof_name, default, deprecated, json_name }.into_field(type_name)
+    }
+
+    fn from_pairs(pairs: Pairs<Rule>) -> io::Result<ProtoData> {
+        let mut res = ProtoData { messages: vec![], enums: vec![], unknown_field: Arc::new(UnknownFieldDefinition::new()), unresolved: vec![], extensions: vec![] };
+        let mut comments = String::new();
+        for pair in pairs {
+            let presence = Presence::of_scope(pair.clone().into_inner(), Presence::Implicit);
+            for inner_pair in pair.into_inner() {
+                match inner_pair.as_rule() {
+                    //                    Rule::file => { return Self::from_pairs(inner_pair.into_inner()); }
+                    Rule::message => {
+                        res.append(Self::add_message(inner_pair.into_inner(), comments.clone(), "", presence)?);
+                        comments.clear();
+                    }
+                    Rule::enum1 => {
+                        res.enums.push(Self::add_enum(inner_pair.into_inner(), comments.clone(), ""));
+                        comments.clear();
+                    }
+                    Rule::extend => {
+                        res.extensions.append(&mut Self::add_extend(inner_pair.into_inner(), "", presence));
+                        comments.clear();
+                    }
+                    Rule::COMMENT => {
+                        if !comments.is_empty() { comments += "\n"; }
+                        comments += inner_pair.as_span().as_str().trim_start_matches("//");
+                    }
+                    Rule::edition | Rule::option | Rule::EOI => {}
+                    _ => {
+                        panic!("Unknown rule: {:?}", inner_pair.as_rule());
+                    }
+                };
+            }
+        }
+        //        res.create_map_messages();
+        //        res.messages.sort_by(|a, b| a.name.cmp(&b.name));
+        //        res.enums.sort_by(|a, b| a.name.cmp(&b.name));
+        //        res.link_user_types();
+        Ok(res)
+    }
+
+    fn create_map_messages(&mut self) {
+        let mut map_names = vec![]; // collect maps fields from all messages
+        let names = self.type_names();
+        for msg in &self.messages {
+            for field in &msg.fields {
+                if field.typename().contains(',') {
+                    map_names.push(Self::resolve_type(&names, &msg.name, &field.typename()));
+                }
+            }
+        }
+        // remove duplicated map types
+        let map_names_hashset: HashSet<String> = map_names.into_iter().collect();
+
+        // add new messages types for each found map type
+        for name in map_names_hashset {
+            let mut fields = vec![];
+            for (id, field_type) in (1..).zip(name.split(",")) {
+                fields.push(CommonFieldProto::new_field(format!("@{}", id),
+                                                        field_type.to_string(), id,
+                                                        false, false,
+                                                        String::new(), None));
+            }
+            self.messages.push(Arc::new(MessageProto { name, fields, comment: String::new(), reserved: vec![], reserved_names: vec![] }));
+        }
+    }
+
+    //    fn link_user_types(&mut self) {
+    //        for msg in &self.messages {
+    //            for field in &msg.fields {
+    //                field.link_user_types(&self.enums, &self.messages);
+    //            }
+    //        }
+    //    }
+
+
+    // extension fields are added to the messages they extend; extensions of other types (the options of
+    // descriptor.proto) are not read
+    fn add_extensions(&mut self) {
+        let names = self.type_names();
+        let resolved: Vec<(String, FieldProtoPtr)> = self.extensions.iter().
+            filter_map(|(extendee, scope, field)| Some((Self::resolve_name(&names, scope, extendee)?, field.clone()))).collect();
+        self.extensions.clear();
+        for (extendee, field) in resolved {
+            let Some(msg) = self.messages.iter_mut().find(|msg| msg.name == extendee) else { continue; };
+            let mut fields = msg.fields.clone();
+            fields.push(field);
+            *msg = Arc::new(MessageProto { name: msg.name.clone(), fields, comment: msg.comment.clone(), reserved: msg.reserved.clone(), reserved_names: msg.reserved_names.clone() });
+        }
+    }
+
+    /// Adds the extensions to the extended messages, links the field types to the messages and enums,
+    /// an error for a default value not matching its field.
+    pub fn finalize(mut self) -> io::Result<ProtoData> {
+        self.add_extensions();
+        self.create_map_messages();
+        self.messages.sort_by(|a, b| a.name.cmp(&b.name));
+        self.enums.sort_by(|a, b| a.name.cmp(&b.name));
+        //self.link_user_types();
+
+        let mut unresolved = vec![];
+        let names = self.type_names();
+        for msg in &self.messages {
+            for field in &msg.fields {
+                let typename = Self::resolve_type(&names, &msg.name, &field.typename());
+                if !field.link_user_types(&typename, &self.enums, &self.messages) {
+                    unresolved.push(format!("{}.{}: {}", msg.name, field.name(), field.typename()));
+                }
+            }
+        }
+        self.unresolved = unresolved;
+
+        // enum defaults are known after linking
+        for msg in &self.messages {
+            for field in &msg.fields {
+                if let Some(text) = &field.get_common_definition().default {
+                    if field.declared_default().is_none() {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{}.{}: the default {} is not a {} value", msg.name, field.name(), text, field.typename())));
+                    }
+                }
+            }
+        }
+
+        // self.messages.sort_by(|a, b| a.name.cmp(&b.name));
+        // self.enums.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(self)
+    }
+}
+
+impl MessageProto {
+    /// The field number is in a reserved range.
+    pub fn is_reserved(&self, id: i32) -> bool {
+        self.reserved.iter().any(|range| range.contains(&id))
+    }
+    /// The definition of the field with the number.
+    pub fn get_field(&self, number: i32) -> Option<FieldProtoPtr> {
+        if let Some(fd) = self.fields.iter().find(|m| m.id() == number) {
+            return Some(fd.clone());
+        }
+        None
+    }
+}
+
+impl Debug for ProtoData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for msg in &self.messages {
+            write!(f, "{:?}", msg)?;
+        }
+        for enm in &self.enums {
+            write!(f, "{:?}", enm)?;
+        }
+        Ok(())
+    }
+}
+impl Debug for MessageProto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "message {} {{", self.name)?;
+
+        let mut oneof = String::new();
+        //let mut oneof3: Option<String> = None;
+
+        for field in &self.fields {
+            let mut oneof2 = String::new();
+            if let Some(ofn) = field.oneof_name() {
+                oneof2 = ofn.clone();
+            }
+
+
+
+            //if oneof3 != new_oneof {
+            //    if new_oneof.is_some() {
+            //        writeln!(f, "  oneof {} {{", oneof3.unwrap())?;
+            //    }
+            //    oneof3 = new_oneof;
+            //}
+
+            if oneof != oneof2 {
+                oneof = oneof2.clone();
+                writeln!(f, "  oneof {} {{", oneof)?;
+            }
+
+            if !oneof.is_empty() { write!(f, "  ")?; }
+
+            write!(f, "  {:?}", field)?;
+        }
+        if !oneof.is_empty() {
+            writeln!(f, "  }}")?;
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+impl Debug for EnumProto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "enum {} {{", self.name)?;
+        for variant in &self.variants {
+            writeln!(f, "  {} = {};", variant.0, variant.1)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+
+impl ProtoFile {
+    fn new(path: PathBuf) -> io::Result<ProtoFile> {
+        let content = std::fs::read_to_string(&path).map_err(|e| io::Error::new(e.kind(), format!("cannot read {}: {}", path.display(), e)))?;
+        Ok(ProtoFile { path, content })
+    }
+
+    /// The path the file was read by.
+    pub fn path(&self) -> &PathBuf { &self.path }
+
+    /// The definitions of the file, errors are reported with the file name.
+    pub fn parse(&self) -> io::Result<ProtoData> {
+        ProtoData::new(&self.content).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", self.path.display(), e)))
+    }
+
+    /// The file with all the files it imports and they import in turn, searched next to the importing file and
+    /// in the proto path (<https://protobuf.dev/programming-guides/proto3/#importing>). As protoc does, every import
+    /// is loaded, `public` is only about the names visible to a file. The first file is the given one,
+    /// a file imported several times (or in a cycle) is read once.
+    pub fn new_with_imports(name: PathBuf, proto_path: Vec<PathBuf>) -> io::Result<Vec<ProtoFile>> {
+        let mut all_files: Vec<ProtoFile> = vec![];
+        let mut files: Vec<ProtoFile> = vec![ProtoFile::new(name)?];
+        loop {
+            // add the imports of the files added last
+            let mut new_files = vec![];
+            for file in &files {
+                new_files.append(&mut file.read_imports(&proto_path)?);
+            }
+            all_files.append(&mut files);
+            if new_files.is_empty() { return Ok(all_files); }
+
+            // remove files already in the list (circular dependency)
+            for new in new_files {
+                let key = new.key();
+                if !all_files.iter().chain(files.iter()).any(|old| old.key() == key) { files.push(new); }
+            }
+        }
+    }
+
+    // the same file may be imported by different relative paths
+    fn key(&self) -> PathBuf { self.path.canonicalize().unwrap_or_else(|_| self.path.clone()) }
+
+    #[cfg(test)]
+    fn extract_imports(&self) -> Vec<(String, bool)> { // (file_name, is_public)
+        self.extract_import_lines().into_iter().map(|(_, name, public)| (name, public)).collect()
+    }
+
+    // (line number, file_name, is_public) of import "file_path.proto"; import public "..."; import weak "...";
+    fn extract_import_lines(&self) -> Vec<(usize, String, bool)> {
+        let mut res = vec![];
+        for (index, line) in self.content.lines().enumerate() {
+            let s = line.trim();
+            let Some(s) = s.strip_prefix("import") else { continue; };
+            // a field named importance is not an import
+            if !s.starts_with(|c: char| c.is_whitespace() || c == '"' || c == '\'') { continue; }
+            let s = s.split(';').next().unwrap().trim();
+            let (is_public, s) = match s.strip_prefix("public") {
+                Some(rest) => (true, rest.trim()),
+                None => (false, s.strip_prefix("weak").map_or(s, |rest| rest.trim())),
+            };
+            res.push((index + 1, s.trim_matches(|c| c == '"' || c == '\'').to_string(), is_public));
+        }
+        res
+    }
+
+    // search file by name in all possible locations
+    fn resolve_path(&self, name: &str, proto_path: &Vec<PathBuf>) -> Option<PathBuf> {
+        let name = PathBuf::from(name);
+        // as written in the import directive
+        if let Ok(absolute) = std::path::absolute(&name) {
+            if absolute.is_file() {
+                return Some(absolute);
+            }
+        }
+        if name.is_relative() {
+
+            // relative to current proto file
+            if let Some(parent_path) = self.path.parent() {
+                let file_path = parent_path.join(&name);
+                if file_path.is_file() {
+                    return Some(file_path);
+                }
+            }
+
+            // search in the provided list of directories
+            for dir in proto_path {
+                let file_path = dir.join(&name);
+                if file_path.is_file() {
+                    return Some(file_path);
+                }
+            }
+        }
+        None
+    }
+
+    fn read_imports(&self, proto_path: &Vec<PathBuf>) -> io::Result<Vec<ProtoFile>> {
+        let mut res = vec![];
+        for (line, name, _) in self.extract_import_lines().into_iter() {
+            let Some(path) = self.resolve_path(&name, proto_path) else {
+                let mut dirs: Vec<String> = self.path.parent().into_iter().chain(proto_path.iter().map(|dir| dir.as_path())).
+                    map(|dir| dir.display().to_string()).collect();
+                if dirs.first().is_some_and(|dir| dir.is_empty()) { dirs[0] = ".".into(); }
+                return Err(io::Error::new(io::ErrorKind::NotFound, format!("{}:{}: imported file {} not found in {}",
+                                                                            self.path.display(), line, name, dirs.join(", "))));
+            };
+            res.push(Self::new(path)?);
+        }
+        Ok(res)
+    }
+}
+
+
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+
+
+#[cfg(test)]
+mod parsing {
+    use super::*;
+    use crate::typedefs::PbReader;
+    use crate::wire::{FieldPath, FieldValue, MessageData, ScalarValue};
+
+    const TEST_DATA_DIR: &str = r"C:\V\prj\rust\p18089\test-data-maker\data\";
+
+    #[test]
+    fn conformance() {
+        for path in [
+            // https://github.com/protocolbuffers/protobuf/blob/main/conformance/conformance.proto
+            "conformance.proto",
+            // https://github.com/protocolbuffers/protobuf/blob/main/src/google/protobuf/test_messages_proto3.proto
+            "test_messages_proto3.proto",
+            "addressbook.proto",
+        ] {
+            let path = TEST_DATA_DIR.to_string() + path;
+            assert!(ProtoData::new(std::fs::read_to_string(path).unwrap().as_str()).unwrap().finalize().is_ok());
+        }
+    }
+
+    #[test]
+    fn nested() {
+        let proto_str = r#"message TestMessage {
+
+  message NestedMessage {
+    int32 a = 1;
+  }
+
+  enum NestedEnum {
+    FOO = 0;
+    BAR = 1;
+    NEG = -1;
+  }
+}"#;
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+
+        assert_eq!(proto.messages.len(), 2);
+        assert_eq!(proto.enums.len(), 1);
+        assert!(proto.get_message_definition("TestMessage").is_some());
+        assert!(proto.get_message_definition("NestedMessage").is_some());
+        assert!(proto.get_enum_definition("NestedEnum").is_some());
+    }
+
+
+    #[test]
+    fn nested_scopes() {
+        let proto_str = r#"package pkg;
+message A {
+  enum Status { A_OK = 0; }
+  message Item { Status s = 1; B.Status b = 2; .Status g = 3; }
+  Status s = 1;
+  Item item = 2;
+  map<string, Item> items = 3;
+  B b = 4;
+}
+message B {
+  enum Status { B_OK = 0; }
+  Status s = 1;
+  pkg.A.Item item = 2;
+}
+enum Status { G_OK = 0; }"#;
+        let proto = ProtoData::new(proto_str).unwrap();
+        assert_eq!(proto.auto_detect_root_message().unwrap().name, "A");
+        let proto = proto.finalize().unwrap();
+        assert!(proto.unresolved.is_empty());
+        let types = |name: &str| -> Vec<String> {
+            proto.get_message_definition(name).unwrap().fields.iter().map(|f| f.typename()).collect()
+        };
+        assert_eq!(types("A"), ["A.Status", "A.Item", "string,A.Item", "B"]);
+        assert_eq!(types("A.Item"), ["A.Status", "B.Status", "Status"]);
+        assert_eq!(types("B"), ["B.Status", "A.Item"]);
+        assert_eq!(types("string,A.Item")[1], "A.Item");
+        // the name in the parent is enough while it's unique
+        assert_eq!(proto.get_message_definition("Item").unwrap().name, "A.Item");
+        assert_eq!(proto.get_enum_definition("Status").unwrap().variants[0].0, "G_OK");
+        assert!(proto.get_enum_definition("B.Status").is_some());
+    }
+
+    #[test]
+    fn duplicated_maps() {
+        let proto_str = r#"message TestMessage {
+          map<int32, string> f1 = 1;
+          map<int32, string> f2 = 2;
+          map<int32, fixed32> f2 = 3;
+        }"#;
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        assert_eq!(proto.messages.len(), 3);
+        assert!(proto.get_message_definition("TestMessage").is_some());
+        assert!(proto.get_message_definition("int32,string").is_some());
+        assert!(proto.get_message_definition("int32,fixed32").is_some());
+    }
+
+
+    #[test]
+    fn comments() {
+        let proto_str = r#"
+//comment 1
+message TestMessage {
+  //comment 2
+  int32 a = 1;
+}
+//multiline
+//comment 3
+enum NestedEnum {
+    FOO = 0;
+    //comment 4
+    BAR = 1;
+}
+"#;
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        assert_eq!(proto.messages.len(), 1);
+        let msg = proto.auto_detect_root_message().unwrap();
+        assert_eq!(msg.comment, "comment 1");
+        assert_eq!(msg.fields.len(), 1);
+        assert_eq!(msg.fields[0].comment(), "comment 2");
+
+        let enum0 = &proto.enums[0];
+        assert_eq!(enum0.comment, "multiline\ncomment 3");
+        assert_eq!(enum0.variants[1].2, "comment 4");
+    }
+
+
+    #[test]
+    fn field_defaults() {
+        let proto_str = r#"
+syntax = "proto2";
+enum Color { RED = 0; GREEN = 1; }
+message M {
+  optional int32 n = 1 [default = -5];
+  optional string s = 2 [json_name = "str", default = "a, b]\"c"];
+  optional Color color = 3 [default = GREEN, deprecated = true];
+  optional double d = 4 [default = inf];
+  optional bool b = 5;
+}
+"#;
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        let msg = proto.auto_detect_root_message().unwrap();
+        let defaults: Vec<FieldValue> = msg.fields.iter().map(|f| f.default()).collect();
+        assert_eq!(defaults, [
+            FieldValue::SCALAR(ScalarValue::I32(-5)),
+            FieldValue::SCALAR(ScalarValue::STR("a, b]\"c".into())),
+            FieldValue::SCALAR(ScalarValue::ENUM(1)),
+            FieldValue::SCALAR(ScalarValue::F64(f64::INFINITY)),
+            FieldValue::SCALAR(ScalarValue::BOOL(false)),
+        ]);
+        assert!(msg.fields[0].explicit_presence());
+        assert_eq!(msg.fields[2].get_common_definition().default.as_deref(), Some("GREEN"));
+        assert_eq!((msg.fields[1].json_name(), msg.fields[2].json_name()), ("str".to_string(), "color".to_string()));
+
+        let error = ProtoData::new("message M { optional int32 n = 1 [default = x]; }").unwrap().finalize().err().unwrap();
+        assert_eq!(error.to_string(), "M.n: the default x is not a int32 value");
+    }
+
+    #[test]
+    fn extension_fields() {
+        let proto_str = r#"
+syntax = "proto2";
+extend google.protobuf.FieldOptions { optional string note = 50000; }
+message Base {
+  optional int32 id = 1;
+  extensions 100 to 199;
+}
+// the nickname
+extend Base { optional string nick = 100; }
+message Outer {
+  extend Base {
+    // the tags
+    repeated Outer tags = 101;
+  }
+  optional Base base = 1;
+}
+"#;
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        let base = proto.get_message_definition("Base").unwrap();
+        let names: Vec<String> = base.fields.iter().map(|f| f.name()).collect();
+        assert_eq!(names, ["id", "(nick)", "(Outer.tags)"]);
+        assert!(base.fields[2].is_message() && base.fields[2].repeated());
+        assert_eq!(base.fields[2].comment(), " the tags");
+        assert!(proto.unresolved.is_empty());
+
+        // read as fields of the message, written back as read, found by the paths
+        let bytes = [0x0a, 0x0a, 0x08, 0x01, 0xa2, 0x06, 0x02, 0x68, 0x69, 0xaa, 0x06, 0x00][..].to_vec();
+        let outer = proto.get_message_definition("Outer").unwrap();
+        let data = MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, outer.clone(), &mut (bytes.len() as u32)).unwrap();
+        let FieldValue::MESSAGE(msg) = &data.fields[0].value else { panic!() };
+        assert_eq!(msg.fields[1].def.name(), "(nick)");
+        assert_eq!(msg.fields[1].value, FieldValue::SCALAR(ScalarValue::STR("hi".into())));
+        let mut output = vec![];
+        data.write(&mut output, &proto, outer.clone()).unwrap();
+        assert_eq!(output, bytes);
+        assert_eq!(FieldPath::parse("base.(Outer.tags)[0]", &outer, &proto).unwrap().0, FieldPath::from([(1, 0), (101, 0)]).0);
+        assert_eq!(FieldPath::from([(1, 0), (101, 0)]).to_text(&data).as_deref(), Some("base.(Outer.tags)[0]"));
+    }
+
+    #[test]
+    fn field_presence() {
+        let presence = |proto: &ProtoData, msg: &str| -> Vec<(bool, bool)> {
+            proto.get_message_definition(msg).unwrap().fields.iter().map(|f| (f.explicit_presence(), f.required())).collect()
+        };
+        let proto = ProtoData::new("syntax = \"proto3\";\nmessage M { int32 a = 1; optional int32 b = 2; }").unwrap().finalize().unwrap();
+        assert_eq!(presence(&proto, "M"), [(false, false), (true, false)]);
+        let proto = ProtoData::new("syntax = \"proto2\";\nmessage M { required int32 a = 1; optional int32 b = 2; }").unwrap().finalize().unwrap();
+        assert_eq!(presence(&proto, "M"), [(true, true), (true, false)]);
+
+        // explicit by default, the features of the file, the message and the field are inherited in this order
+        let proto_str = r#"
+edition = "2023";
+package p;
+option features.field_presence = IMPLICIT;
+option java_multiple_files = true;
+enum E {
+  option features.enum_type = CLOSED;
+  A = 0;
+  B = 1 [deprecated = true]; // old
+}
+message M {
+  int32 a = 1;
+  int32 b = 2 [features.field_presence = EXPLICIT];
+  repeated int32 c = 3;
+  message N {
+    option features.field_presence = EXPLICIT;
+    string s = 1;
+    E e = 2 [features.field_presence = LEGACY_REQUIRED];
+  }
+}
+"#;
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        assert_eq!(presence(&proto, "M"), [(false, false), (true, false), (false, false)]);
+        assert_eq!(presence(&proto, "M.N"), [(true, false), (true, true)]);
+        assert_eq!(proto.get_enum_definition("E").unwrap().variants[1].0, "B");
+        let proto = ProtoData::new("edition = \"2023\";\nmessage M { int32 a = 1; }").unwrap().finalize().unwrap();
+        assert_eq!(presence(&proto, "M"), [(true, false)]);
+    }
+
+    #[test]
+    fn reserved_and_deprecated() {
+        let proto_str = "message M {\n  reserved 2, 15, 9 to 11, 40 to max;\n  reserved \"foo\", \"bar\";\n  int32 a = 1 [deprecated = true];\n  int32 b = 3 [deprecated=false];\n}";
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        let msg = proto.auto_detect_root_message().unwrap();
+        assert_eq!(msg.reserved, [2..=2, 15..=15, 9..=11, 40..=MAX_FIELD_NUMBER]);
+        assert_eq!(msg.reserved_names, ["foo", "bar"]);
+        assert!(msg.is_reserved(10) && msg.is_reserved(1000) && !msg.is_reserved(3));
+        assert_eq!((msg.fields[0].deprecated(), msg.fields[1].deprecated()), (true, false));
+
+        // hex and octal numbers, in the extension ranges too
+        let proto = ProtoData::new("message M {\n  reserved 0x10, 017 to 0X1f;\n  extensions 0x100 to max;\n  int32 a = 1;\n}").unwrap().finalize().unwrap();
+        assert_eq!(proto.auto_detect_root_message().unwrap().reserved, [16..=16, 15..=31]);
+        let error = |text: &str| ProtoData::new(text).err().unwrap().to_string();
+        assert_eq!(error("message M {\n  message N { reserved 5, 099; }\n}"), "M.N: reserved 099 is not a field number");
+        assert_eq!(error("message M { reserved 0x100000000; }"), "M: reserved 0x100000000 is not a field number");
+    }
+
+    #[test]
+    fn auto_detect_root_message() {
+        {
+            let proto = ProtoData::new("message M1 { M2 m = 2; }\nmessage M2 { }").unwrap();
+            assert_eq!(proto.auto_detect_root_message().unwrap().name, "M1");
+        }
+        {
+            let proto = ProtoData::new("message M1 { M2 m = 2; }\nmessage M2 { }\nmessage M3 { }").unwrap();
+            let _root_msg = proto.auto_detect_root_message().is_none();
+            assert_eq!(proto.root_candidates().iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["M1", "M3"]);
+        }
+        {
+            let proto = ProtoData::new("message M1 { M2 m = 2; }\nmessage M2 { }\nmessage M3 { M1 m = 1; }").unwrap();
+            assert_eq!(proto.auto_detect_root_message().unwrap().name, "M3");
+        }
+        {
+            let proto = ProtoData::new("message M1 { M2 m = 2; }\nmessage M2 { M1 m = 1; }").unwrap();
+            assert!(proto.auto_detect_root_message().is_none());
+        }
+        {
+            let proto = ProtoData::new("").unwrap();
+            assert!(proto.auto_detect_root_message().is_none());
+        }
+    }
+
+
+    #[test]
+    fn import_files() {
+        let proto_file = ProtoFile::new((TEST_DATA_DIR.to_string() + "test_messages_proto3.proto").into()).unwrap();
+        assert_eq!(proto_file.extract_imports(), [
+            ("google/protobuf/any.proto".to_string(), false),
+            ("google/protobuf/duration.proto".to_string(), false),
+            ("google/protobuf/field_mask.proto".to_string(), false),
+            ("google/protobuf/struct.proto".to_string(), false),
+            ("google/protobuf/timestamp.proto".to_string(), false),
+            ("google/protobuf/wrappers.proto".to_string(), false),
+        ]);
+    }
+
+    #[test]
+    fn import_files_public() {
+        let proto_file = ProtoFile::new((TEST_DATA_DIR.to_string() + "import_tests/1.proto").into()).unwrap();
+        assert_eq!(proto_file.extract_imports(), [
+            ("2.proto".to_string(), false),
+            ("3.proto".to_string(), true),
+            ("dir/4.proto".to_string(), false),
+        ]);
+    }
+
+    #[test]
+    fn import_files_1() { // 1.proto -> import 3 files
+        let files = ProtoFile::new_with_imports((TEST_DATA_DIR.to_string() + "import_tests/1.proto").into(), vec![]).unwrap();
+        assert_eq!(files.len(), 4);
+    }
+
+    #[test]
+    fn import_files_5() { // 5.proto -> 6.proto -> 7.proto (not public, loaded all the same)
+        let files = ProtoFile::new_with_imports((TEST_DATA_DIR.to_string() + "import_tests/5.proto").into(), vec![]).unwrap();
+        assert_eq!(files.len(), 3);
+    }
+
+    #[test]
+    fn import_files_8() { // 8.proto -> 9.proto -> 7.proto
+        let files = ProtoFile::new_with_imports((TEST_DATA_DIR.to_string() + "import_tests/8.proto").into(), vec![]).unwrap();
+        assert_eq!(files.len(), 3);
+    }
+
+    #[test]
+    fn import_files_10() { // 10.proto -> dir/11.proto -> dir/4.proto (file in the same dir as parent)
+        let files = ProtoFile::new_with_imports((TEST_DATA_DIR.to_string() + "import_tests/10.proto").into(), vec![]).unwrap();
+        assert_eq!(files.len(), 3);
+    }
+
+    #[test]
+    fn import_files_12() { // 12.proto -> dir/4.proto (file found in the proto_path)
+        let files = ProtoFile::new_with_imports((TEST_DATA_DIR.to_string() + "import_tests/12.proto").into(),
+                                                vec![(TEST_DATA_DIR.to_string() + "import_tests/dir/").into()]).unwrap();
+        assert_eq!(files.len(), 2);
+    }
+
+    #[test]
+    fn import_files_13() { // 13.proto -> 13.proto ...
+        let files = ProtoFile::new_with_imports((TEST_DATA_DIR.to_string() + "import_tests/13.proto").into(), vec![]).unwrap();
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn import_resolver() {
+        let root = std::env::temp_dir().join(format!("protoedit_imports_{}", std::process::id()));
+        let (main_dir, include) = (root.join("main"), root.join("include"));
+        std::fs::create_dir_all(main_dir.join("sub")).unwrap();
+        std::fs::create_dir_all(include.join("lib")).unwrap();
+        let write = |path: PathBuf, text: &str| std::fs::write(path, text).unwrap();
+        // a.proto -> sub/b.proto (next to a) -> lib/c.proto (public, in the second -I directory) -> ../main/a.proto (cycle)
+        write(main_dir.join("a.proto"), "syntax = \"proto3\";\nimport \"sub/b.proto\";\nmessage A { B b = 1; C c = 2; int32 importance = 3; }");
+        write(main_dir.join("sub/b.proto"), "import public \"lib/c.proto\"; // c\nimport weak \"lib/d.proto\";\nmessage B { }");
+        write(include.join("lib/c.proto"), "import public 'a.proto';\nmessage C { D d = 1; }");
+        write(include.join("lib/d.proto"), "message D { }");
+
+        let files = ProtoFile::new_with_imports(main_dir.join("a.proto"), vec![root.clone(), include.clone(), main_dir.clone()]).unwrap();
+        let names: Vec<String> = files.iter().map(|f| f.path().file_name().unwrap().to_string_lossy().to_string()).collect();
+        // d.proto is a private import of b.proto, loaded as protoc does
+        assert_eq!(names, ["a.proto", "b.proto", "c.proto", "d.proto"]);
+        assert_eq!(files[1].extract_imports(), [("lib/c.proto".to_string(), true), ("lib/d.proto".to_string(), false)]);
+
+        let mut proto = files[0].parse().unwrap();
+        for file in &files[1..] { proto.append(file.parse().unwrap()); }
+        let proto = proto.finalize().unwrap();
+        assert!(proto.unresolved.is_empty());
+
+        // the plain imports of an imported file: a -> b -> c
+        write(main_dir.join("a.proto"), "import \"sub/b.proto\";\nmessage A { B b = 1; }");
+        write(main_dir.join("sub/b.proto"), "import \"lib/c.proto\";\nmessage B { C c = 1; }");
+        write(include.join("lib/c.proto"), "message C { int32 x = 1; }");
+        let files = ProtoFile::new_with_imports(main_dir.join("a.proto"), vec![include.clone()]).unwrap();
+        assert_eq!(files.len(), 3);
+        let mut proto = files[0].parse().unwrap();
+        for file in &files[1..] { proto.append(file.parse().unwrap()); }
+        assert!(proto.finalize().unwrap().unresolved.is_empty());
+
+        let error = ProtoFile::new_with_imports(main_dir.join("a.proto"), vec![]).err().unwrap().to_string();
+        assert_eq!(error, format!("{}:1: imported file lib/c.proto not found in {}", main_dir.join("sub/b.proto").display(), main_dir.join("sub").display()));
+        write(include.join("lib/c.proto"), "message C { D d = 1 }");
+        let error = ProtoFile::new_with_imports(main_dir.join("a.proto"), vec![include.clone()]).unwrap()[2].parse().unwrap_err().to_string();
+        assert!(error.starts_with(&format!("{}: ", include.join("lib/c.proto").display())));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,102 @@
+// TODO    pub fn change<'y>(&self) -> Changes<'y> where 'x: 'y
+// { Changes { old: vec![], new: vec![], root_message: self } }
+
+use std::sync::Arc;
+use crate::wire::{FieldData, FieldPath, FieldPos, FieldValue, MessageData, ScalarValue};
+
+
+/// A change of the field at the path. Applying it makes the reverse change, kept to undo it.
+pub struct Change {
+    /// The field changed, or the position of the value inserted.
+    pub path: FieldPath,
+    /// What is done with the field.
+    pub action: ChangeType,
+}
+/// What a change does with its field.
+pub enum ChangeType {
+    /// Overwrite field data, old value for undo or new for redo.
+    Overwrite(FieldValue),
+    /// Insert new field.
+    Insert(FieldValue),
+    /// Remove field.
+    Delete,
+    /// A deleted field with its position in the parent message, to undo the deletion.
+    Restore(FieldData, usize),
+}
+
+/// A group of changes undone at once, they are stored as applied: the reverse ones.
+pub struct Step {
+    /// The reverse changes.
+    pub changes: Vec<Change>,
+    /// The edit commands doing the changes, as in the journal.
+    pub commands: Vec<String>,
+}
+
+/// The steps to undo and to redo, the last ones go first.
+#[derive(Default)]
+pub struct History {
+    /// The steps done.
+    pub undo: Vec<Step>,
+    /// The steps undone.
+    pub redo: Vec<Step>,
+}
+
+impl History {
+    /// The first later step changing the fields of the step, its values or the indexes of them.
+    pub fn conflict(&self, index: usize) -> Option<usize> {
+        let step = &self.undo[index];
+        (index + 1..self.undo.len()).find(|&later| self.undo[later].changes.iter().any(|other| step.changes.iter().any(|change| change.overlaps(other))))
+    }
+}
+
+impl Change {
+    /// A new value of the scalar field.
+    pub fn change_value(path: FieldPath, value: ScalarValue) -> Self { Self { path, action: ChangeType::Overwrite(FieldValue::SCALAR(value)) } }
+    /// A scalar value inserted at the path.
+    pub fn insert_scalar(path: FieldPath, value: ScalarValue) -> Self { Self { path, action: ChangeType::Insert(FieldValue::SCALAR(value)) } }
+    /// A message inserted at the path.
+    pub fn insert_message(path: FieldPath, value: MessageData) -> Self { Self { path, action: ChangeType::Insert(FieldValue::MESSAGE(Arc::new(value))) } }
+    /// The value at the path deleted with its content.
+    pub fn delete_value(path: FieldPath) -> Self { Self { path, action: ChangeType::Delete } }
+    /// One of the paths contains the other, or one change inserts or deletes a value of a repeated field
+    /// the other path goes through (the following values change their indexes).
+    pub fn overlaps(&self, other: &Change) -> bool {
+        let (a, b) = (&self.path.0, &other.path.0);
+        let len = a.len().min(b.len());
+        if a[..len] == b[..len] { return true; }
+        let shifts = |change: &Change, path: &[FieldPos]| {
+            let len = change.path.0.len();
+            change.layout_changed() && path.len() >= len && path[..len - 1] == change.path.0[..len - 1] && path[len - 1].id == change.path.0[len - 1].id
+        };
+        shifts(self, b) || shifts(other, a)
+    }
+    /// The change inserts or removes a value, the indexes of the following values change.
+    pub fn layout_changed(&self) -> bool {
+        match self.action {
+            ChangeType::Insert(_) => true,
+            ChangeType::Delete => true,
+            ChangeType::Restore(..) => true,
+            ChangeType::Overwrite(_) => false,
+        }
+    }
+    /// The path of a value after this change is applied (it holds the reverse change then): the following values
+    /// of the field a value is inserted into or deleted from change their indexes. None for the deleted value and
+    /// the fields inside it.
+    pub fn moved_path(&self, path: &FieldPath) -> Option<FieldPath> {
+        let Some((last, parents)) = self.path.0.split_last() else { return Some(path.clone()); };
+        let len = self.path.0.len();
+        if path.0.len() < len || path.0[..len - 1] != *parents || path.0[len - 1].id != last.id { return Some(path.clone()); }
+        let mut res = path.clone();
+        let index = &mut res.0[len - 1].index;
+        match self.action {
+            // inserted
+            ChangeType::Delete if *index >= last.index => *index += 1,
+            // deleted
+            ChangeType::Restore(..) if *index == last.index => return None,
+            ChangeType::Restore(..) if *index > last.index => *index -= 1,
+            _ => {}
+        }
+        Some(res)
+    }
+
+}
\ No newline at end of file
@@ -0,0 +1,984 @@
+use std::string::String;
+use std::{io, mem};
+use std::sync::OnceLock;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::wire::*;
+use crate::blob::{Blob, Text};
+use crate::proto::{EnumProtoPtr, MessageProtoPtr};
+
+/// How to store an integer out of the range of the field type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overflow {
+    /// The nearest allowed value.
+    Clamp,
+    /// Lower bits of the value, as a cast in C.
+    Wrap,
+}
+
+/// What the definitions of all field types have: the declaration of the field in its message.
+#[derive(Default)]
+pub struct CommonFieldProto {
+    /// The name as declared.
+    pub name: String,
+    /// The field number.
+    pub id: i32,
+    /// Declared repeated, or a map.
+    pub repeated: bool,
+    /// Declared with the optional keyword, or with explicit presence by the features of an edition:
+    /// a value equal to the default is kept apart from no value.
+    pub optional: bool,
+    /// Declared with the proto2 required keyword or the LEGACY_REQUIRED presence of an edition.
+    pub required: bool,
+    /// The comment above the declaration.
+    pub comment: String,
+    /// The oneof the field belongs to.
+    pub oneof_name: Option<String>,
+    /// The value of the proto2 option [default = ...] as written.
+    pub default: Option<String>,
+    /// `[deprecated = true]`.
+    pub deprecated: bool,
+    /// The option [json_name = "..."] without the quotes.
+    pub json_name: Option<String>,
+}
+
+
+/// Reads the wire format. Each read takes its bytes from `limit`, the bytes left in the message being read;
+/// reading past it is an error.
+pub trait PbReaderTrait {
+    /// The bytes read from the start.
+    fn pos(&self) -> usize;
+    /// A varint, as a number wide enough for any 64-bit value with its sign.
+    fn read_varint(&mut self, limit: &mut u32) -> io::Result<i128>;
+    /// The next `length` bytes.
+    fn read_len(&mut self, length: u32, limit: &mut u32) -> io::Result<Vec<u8>>;
+    /// The value of a bytes field, a reader of a mapped file keeps it in the file.
+    fn read_blob(&mut self, length: u32, limit: &mut u32) -> io::Result<Blob> { self.read_len(length, limit).map(Blob::Owned) }
+    /// The tag of a field, with the length of a length-delimited value read after it.
+    fn read_tag(&mut self, limit: &mut u32) -> io::Result<Tag> {
+        let first_number = self.read_varint(limit)? as i32;
+        let length =
+            match (first_number & 7) as u8 {
+                WT_VARINT => 0,
+                WT_I32 => 4,
+                WT_I64 => 8,
+                WT_LEN => self.read_varint(limit)? as u32,
+                // the content of a group is read up to its end tag
+                WT_SGROUP | WT_EGROUP => 0,
+                other =>
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Unsupported length type ({}) ", other))),
+            };
+        Ok(Tag { first_number, length })
+    }
+}
+
+/// Reads the wire format from any reader.
+pub struct PbReader<ReaderType: io::Read> {
+    reader: ReaderType,
+    pos: usize,
+}
+
+// bytes read by all the readers of the process, counted when a reader is dropped
+static BYTES_READ: AtomicUsize = AtomicUsize::new(0);
+
+/// Bytes read by all the readers of the process, counted when a reader is dropped.
+pub fn bytes_read() -> usize {
+    BYTES_READ.load(Ordering::Relaxed)
+}
+
+pub(crate) fn count_bytes_read(count: usize) {
+    BYTES_READ.fetch_add(count, Ordering::Relaxed);
+}
+
+impl<ReaderType: io::Read> PbReader<ReaderType> {
+    /// A reader from the current position of the reader.
+    pub fn new(reader: ReaderType) -> PbReader<ReaderType> {
+        PbReader { reader, pos: 0 }
+    }
+}
+
+impl<ReaderType: io::Read> Drop for PbReader<ReaderType> {
+    fn drop(&mut self) {
+        count_bytes_read(self.pos);
+    }
+}
+impl<ReaderType: io::Read> PbReaderTrait for PbReader<ReaderType> {
+    fn pos(&self) -> usize {
+        self.pos
+    }
+    // read variable length integral value
+    fn read_varint(&mut self, limit: &mut u32) -> io::Result<i128> {
+        let mut buf: [u8; 1] = [0];
+        let mut value: i128 = 0;
+        let mut bits_read: u8 = 0;
+        // a varint cut by the limit is not completed, the bytes after it are not read
+        while *limit > 0 && 1 == self.reader.read(&mut buf)? {
+            *limit -= 1;
+            self.pos += 1;
+            if 0 == (0x80u8 & buf[0]) {
+                value |= (buf[0] as i128) << bits_read;
+                return Ok(value);
+            } else {
+                if *limit == 0 { break; }
+                value |= ((buf[0] & 0x7fu8) as i128) << bits_read;
+            }
+            if bits_read > 64 - 8 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "VARINT overflow"));
+            }
+            bits_read += 7;
+        }
+        Err(io::Error::new(io::ErrorKind::UnexpectedEof, "not completed VARINT"))
+    }
+    // read string or bytes with provided data length
+    fn read_len(&mut self, length: u32, limit: &mut u32) -> io::Result<Vec<u8>> {
+        if *limit >= length {
+            *limit -= length;
+            let mut buf = vec![0u8; length as usize];
+            self.reader.read_exact(&mut buf)?;
+            self.pos += length as usize;
+            Ok(buf)
+        } else {
+            Err(io::Error::new(io::ErrorKind::UnexpectedEof, "read data out of limit"))
+        }
+    }
+}
+
+
+impl CommonFieldProto {
+    // read integral or real value with predefined length
+    fn read_fixed<const LEN: usize>(reader: &mut dyn PbReaderTrait, limit: &mut u32) -> io::Result<[u8; LEN]> {
+        let mut buf = [0u8; LEN];
+        let vec_buf = reader.read_len(LEN as u32, limit)?;
+        buf.copy_from_slice(&vec_buf);
+        Ok(buf)
+    }
+
+    /// The bytes of a fixed32, fixed64, float or double value.
+    pub fn write_fixed<const N: usize>(writer: &mut dyn std::io::Write, data: &[u8; N]) -> io::Result<()> {
+        writer.write_all(data)?;
+        Ok(())
+    }
+
+    /// A negative value is sign-extended to 64 bits, 10 bytes.
+    pub fn write_varint(writer: &mut dyn std::io::Write, data: i128) -> io::Result<()> {
+        // a negative value is sign-extended to 64 bits, 10 bytes
+        let mut data = if data < 0 { data as i64 as u64 } else { data as u64 };
+        let mut buf = Vec::with_capacity(8);
+        //while data & 0x80 != 0 { // > 0x7f {
+        while data > 0x7f {
+            buf.push(((data as u8) & 0x7f) | 0x80);
+            data >>= 7;
+        }
+        buf.push(data as u8);
+        writer.write_all(&buf)
+    }
+
+    /// The content of a length-delimited value, the length is written by the caller.
+    pub fn write_len(writer: &mut dyn std::io::Write, data: &[u8]) -> io::Result<()> {
+        writer.write_all(data)?;
+        Ok(())
+    }
+
+    /// item_count is itemCount, as json names are made of field names.
+    pub fn lower_camel_case(name: &str) -> String {
+        let mut res = String::new();
+        let mut upper = false;
+        for c in name.chars() {
+            if c == '_' { upper = true; continue; }
+            res.push(if upper { c.to_ascii_uppercase() } else { c });
+            upper = false;
+        }
+        res
+    }
+
+    /// "text with \"escapes\"" or a text without quotes as is.
+    pub fn unquote(text: &str) -> Option<String> {
+        let inner = match text.strip_prefix('"') {
+            Some(rest) => rest.strip_suffix('"')?,
+            None => return Some(text.to_string()),
+        };
+        let mut res = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                res.push(c);
+                continue;
+            }
+            match chars.next()? {
+                'n' => res.push('\n'),
+                'r' => res.push('\r'),
+                't' => res.push('\t'),
+                '0' => res.push('\0'),
+                other => res.push(other), // \" and \\
+            }
+        }
+        Some(res)
+    }
+
+    /// Bytes as shown by the editor: "0a ff 10" (spaces are optional).
+    pub fn parse_hex(text: &str) -> Option<Vec<u8>> {
+        let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+        if !digits.len().is_multiple_of(2) || !digits.iter().all(|c| c.is_ascii_hexdigit()) { return None; }
+        digits.chunks(2).map(|pair| {
+            let s: String = pair.iter().collect();
+            u8::from_str_radix(&s, 16).ok()
+        }).collect()
+    }
+
+    /// Min, max and size in bits of the integer field type.
+    pub fn integer_range(typename: &str) -> Option<(i128, i128, u32)> {
+        match typename {
+            "int32" => Some((Int32FieldProto::MIN as i128, Int32FieldProto::MAX as i128, 32)),
+            "uint32" => Some((UInt32FieldProto::MIN as i128, UInt32FieldProto::MAX as i128, 32)),
+            "sint32" => Some((SInt32FieldProto::MIN as i128, SInt32FieldProto::MAX as i128, 32)),
+            "fixed32" => Some((FixedUInt32FieldProto::MIN as i128, FixedUInt32FieldProto::MAX as i128, 32)),
+            "sfixed32" => Some((FixedInt32FieldProto::MIN as i128, FixedInt32FieldProto::MAX as i128, 32)),
+            "int64" => Some((Int64FieldProto::MIN as i128, Int64FieldProto::MAX as i128, 64)),
+            "uint64" => Some((UInt64FieldProto::MIN as i128, UInt64FieldProto::MAX as i128, 64)),
+            "sint64" => Some((SInt64FieldProto::MIN as i128, SInt64FieldProto::MAX as i128, 64)),
+            "fixed64" => Some((FixedUInt64FieldDefinition::MIN as i128, FixedUInt64FieldDefinition::MAX as i128, 64)),
+            "sfixed64" => Some((FixedInt64FieldDefinition::MIN as i128, FixedInt64FieldDefinition::MAX as i128, 64)),
+            _ => None,
+        }
+    }
+
+    /// Decimal or 0x hex with an optional sign, '_' may separate digits: -0x8000_0000, 1_000_000.
+    pub fn parse_integer(text: &str) -> Option<i128> {
+        let text = text.trim();
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (radix, digits) = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => (16, hex),
+            None => (10, text),
+        };
+        if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") { return None; }
+        let digits = digits.replace('_', "");
+        // from_str_radix accepts a sign too, it is taken above
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) { return None; }
+        let value = i128::from_str_radix(&digits, radix).ok()?;
+        Some(if negative { -value } else { value })
+    }
+
+    /// The value of an integer field type, None if the text is not an integer or out of the type range.
+    pub fn parse_in_range<T: TryFrom<i128>>(text: &str) -> Option<T> {
+        Self::parse_integer(text).and_then(|value| T::try_from(value).ok())
+    }
+
+    /// An integer that parse_value refuses only because of the field type range.
+    pub fn out_of_range(def: &dyn FieldProto, text: &str) -> bool {
+        match (Self::integer_range(&def.typename()), Self::parse_integer(text)) {
+            (Some((min, max, _)), Some(value)) => value < min || value > max,
+            _ => false,
+        }
+    }
+
+    /// "3000000000 is out of the int32 range -2147483648..2147483647", None for a value in the range or not an integer.
+    pub fn range_error(def: &dyn FieldProto, text: &str) -> Option<String> {
+        let (min, max, _) = Self::integer_range(&def.typename())?;
+        Self::out_of_range(def, text).then(|| format!("{} is out of the {} range {}..{}", text.trim(), def.typename(), min, max))
+    }
+
+    /// The integer of the text, brought into the range of the field type.
+    pub fn parse_with_overflow(def: &dyn FieldProto, text: &str, overflow: Overflow) -> Option<ScalarValue> {
+        let (min, max, bits) = Self::integer_range(&def.typename())?;
+        let value = Self::parse_integer(text)?;
+        let value = match overflow {
+            Overflow::Clamp => value.clamp(min, max),
+            Overflow::Wrap => {
+                let modulo = 1i128 << bits;
+                let unsigned = value.rem_euclid(modulo);
+                let wrapped = if min < 0 && unsigned >= modulo / 2 { unsigned - modulo } else { unsigned };
+                // the lowest value is not allowed for sint types
+                wrapped.clamp(min, max)
+            }
+        };
+        def.parse_value(&value.to_string())
+    }
+
+    /// The definition of a field of the type: a scalar type, or the name of a message or enum.
+    pub fn new_field(name: String, type_name: String, id: i32, repeated: bool, optional: bool, comment: String, oneof_name: Option<String>) -> Arc<dyn FieldProto> {
+        CommonFieldProto { name, id, repeated, optional, required: false, comment, oneof_name, default: None, deprecated: false, json_name: None }.into_field(type_name)
+    }
+
+    /// The definition of a field of the type with this declaration.
+    pub fn into_field(self, type_name: String) -> Arc<dyn FieldProto> {
+        let common = self;
+        match type_name.as_str() {
+            "int32" => Arc::new(Int32FieldProto(common)),
+            "uint32" => Arc::new(UInt32FieldProto(common)),
+            "sint32" => Arc::new(SInt32FieldProto(common)),
+            "fixed32" => Arc::new(FixedUInt32FieldProto(common)),
+            "sfixed32" => Arc::new(FixedInt32FieldProto(common)),
+
+            "int64" => Arc::new(Int64FieldProto(common)),
+            "uint64" => Arc::new(UInt64FieldProto(common)),
+            "sint64" => Arc::new(SInt64FieldProto(common)),
+            "fixed64" => Arc::new(FixedUInt64FieldDefinition(common)),
+            "sfixed64" => Arc::new(FixedInt64FieldDefinition(common)),
+
+            "float" => Arc::new(FloatFieldDefinition(common)),
+            "double" => Arc::new(DoubleFieldDefinition(common)),
+
+            "bool" => Arc::new(BoolFieldDefinition(common)),
+
+            "string" => Arc::new(StringFieldDefinition(common)),
+
+            "bytes" => Arc::new(BytesFieldDefinition(common)),
+
+            _ => Arc::new(EnumOrMessageFieldDefinition::new(common, type_name)),
+        }
+    }
+}
+
+/// The definition of a field of a message: its declaration and how the values of its type are read and written.
+pub trait FieldProto: Send + Sync {
+    /// A value of the field, `field_len` is the length from the tag for length-delimited types.
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, field_len: u32) -> io::Result<ScalarValue>;
+    /// Write only data, without field name and length.
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()>;
+    /// Value from a text written by the user, None if the text does not match the field type.
+    fn parse_value(&self, _text: &str) -> Option<ScalarValue> { None }
+    /// The name as declared.
+    fn name(&self) -> String { self.get_common_definition().name.clone() }
+    /// The scalar type, or the full name of the message or enum; the types of a map as `key,value`.
+    fn typename(&self) -> String;
+    /// The field number.
+    fn id(&self) -> i32 { self.get_common_definition().id }
+    /// Declared repeated, or a map.
+    fn repeated(&self) -> bool { self.get_common_definition().repeated }
+    /// The wire type of a single value.
+    fn wire_type(&self) -> u8 { WT_VARINT }
+    /// Only if the field belongs to an oneof.
+    fn oneof_name(&self) -> &Option<String> { &self.get_common_definition().oneof_name }
+    /// See [`CommonFieldProto::optional`].
+    fn optional(&self) -> bool { self.get_common_definition().optional }
+    /// See [`CommonFieldProto::required`].
+    fn required(&self) -> bool { self.get_common_definition().required }
+    /// Declared with `[deprecated = true]`.
+    fn deprecated(&self) -> bool { self.get_common_definition().deprecated }
+    /// The name in the protobuf json mapping: the declared json_name or the name in lowerCamelCase.
+    fn json_name(&self) -> String { self.get_common_definition().json_name.clone().unwrap_or_else(|| CommonFieldProto::lower_camel_case(&self.name())) }
+    /// A value equal to the default differs from no value: optional, required, oneof, message fields and ones with a declared default.
+    fn explicit_presence(&self) -> bool { self.optional() || self.required() || self.oneof_name().is_some() || self.is_message() || self.declared_default().is_some() }
+    /// The comment above the declaration.
+    fn comment(&self) -> String { self.get_common_definition().comment.clone() }
+    /// The value of an absent field and of a new one.
+    fn default(&self) -> FieldValue { self.declared_default().map_or_else(|| self.zero(), FieldValue::SCALAR) }
+    /// The value of the type when no default is declared.
+    fn zero(&self) -> FieldValue;
+    /// The value of [default = ...], None if there is no option or its value does not match the type.
+    fn declared_default(&self) -> Option<ScalarValue> { self.parse_value(self.get_common_definition().default.as_ref()?) }
+    /// The declaration.
+    fn get_common_definition(&self) -> &CommonFieldProto;
+    //fn message_type_name(&self) -> &str { "" } // only if the field stores a message
+    /// The name of the enum value with the number, None for other types and unknown numbers.
+    fn get_enum_name_by_index(&self, _i: i32) -> Option<&str> { None }
+    /// The field holds a message, known after [`crate::proto::ProtoData::finalize`].
+    fn is_message(&self) -> bool { false }
+    /// Typename is the full name of the type found for the field in its scope, false if there is no such type.
+    fn link_user_types(&self, _: &str, _: &Vec<EnumProtoPtr>, _: &Vec<MessageProtoPtr>) -> bool { true }
+}
+
+impl Debug for dyn FieldProto {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.typename().contains(",") {
+            write!(f, "map<{}>", self.typename())?;
+        } else {
+            if self.repeated() { write!(f, "repeated ")? }
+            if self.required() { write!(f, "required ")? }
+            write!(f, "{}", self.typename())?;
+        }
+
+        writeln!(f, " {} = {};", self.name(), self.id())
+    }
+}
+
+
+/// A field of the `int32` type.
+pub struct Int32FieldProto(pub CommonFieldProto);
+
+impl Int32FieldProto {
+    /// The smallest value of the type.
+    pub const MIN: i32 = i32::MIN;
+    /// The largest value of the type.
+    pub const MAX: i32 = i32::MAX;
+}
+impl FieldProto for Int32FieldProto {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, _field_len: u32) -> io::Result<ScalarValue> {
+        let value = reader.read_varint(limit)? as i32;
+        Ok(ScalarValue::I32(value))
+    }
+
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::I32(value) = data {
+            CommonFieldProto::write_varint(writer, *value as i128)
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "int32".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::I32) }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::I32(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+/// A field of the `uint32` type.
+pub struct UInt32FieldProto(pub CommonFieldProto);
+impl UInt32FieldProto {
+    /// The smallest value of the type.
+    pub const MIN: u32 = u32::MIN;
+    /// The largest value of the type.
+    pub const MAX: u32 = u32::MAX;
+}
+impl FieldProto for UInt32FieldProto {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, _field_len: u32) -> io::Result<ScalarValue> {
+        let value = reader.read_varint(limit)? as u32;
+        Ok(ScalarValue::U32(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::U32(value) = data {
+            CommonFieldProto::write_varint(writer, *value as i128)
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "uint32".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::U32) }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::U32(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `sint32` type.
+pub struct SInt32FieldProto(pub CommonFieldProto);
+impl SInt32FieldProto {
+    /// The smallest value of the type.
+    pub const MIN: i32 = i32::MIN;
+    /// The largest value of the type.
+    pub const MAX: i32 = i32::MAX;
+}
+
+impl FieldProto for SInt32FieldProto {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, _field_len: u32) -> io::Result<ScalarValue> {
+        // a longer varint is cut to 32 bits, as protoc does
+        let zigzag = reader.read_varint(limit)? as u32;
+        let value = (zigzag >> 1) as i32 ^ -((zigzag & 1) as i32);
+        Ok(ScalarValue::S32(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::S32(value) = data {
+            let zigzag = ((*value << 1) ^ (*value >> 31)) as u32;
+            return CommonFieldProto::write_varint(writer, zigzag as i128);
+        }
+        unreachable!()
+    }
+    fn typename(&self) -> String { "sint32".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::S32) }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::S32(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `sfixed32` type.
+pub struct FixedInt32FieldProto(pub CommonFieldProto);
+impl FixedInt32FieldProto {
+    /// The smallest value of the type.
+    pub const MIN: i32 = i32::MIN;
+    /// The largest value of the type.
+    pub const MAX: i32 = i32::MAX;
+}
+impl FieldProto for FixedInt32FieldProto {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, field_len: u32) -> io::Result<ScalarValue> {
+        debug_assert_eq!(field_len, mem::size_of::<i32>() as u32);
+        let bytes = CommonFieldProto::read_fixed(reader, limit)?;
+        let value = i32::from_le_bytes(bytes);
+        Ok(ScalarValue::SF32(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::SF32(value) = data {
+            CommonFieldProto::write_fixed(writer, &value.to_le_bytes())
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "sfixed32".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::SF32) }
+    fn wire_type(&self) -> u8 { WT_I32 }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::SF32(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `fixed32` type.
+pub struct FixedUInt32FieldProto(pub CommonFieldProto);
+impl FixedUInt32FieldProto {
+    /// The smallest value of the type.
+    pub const MIN: u32 = u32::MIN;
+    /// The largest value of the type.
+    pub const MAX: u32 = u32::MAX;
+}
+impl FieldProto for FixedUInt32FieldProto {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, field_len: u32) -> io::Result<ScalarValue> {
+        debug_assert_eq!(field_len, mem::size_of::<u32>() as u32);
+        let bytes = CommonFieldProto::read_fixed(reader, limit)?;
+        let value = u32::from_le_bytes(bytes);
+        Ok(ScalarValue::UF32(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::UF32(value) = data {
+            CommonFieldProto::write_fixed(writer, &value.to_le_bytes())
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "fixed32".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::UF32) }
+    fn wire_type(&self) -> u8 { WT_I32 }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::UF32(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `int64` type.
+pub struct Int64FieldProto(pub CommonFieldProto);
+impl Int64FieldProto {
+    /// The smallest value of the type.
+    pub const MIN: i64 = i64::MIN;
+    /// The largest value of the type.
+    pub const MAX: i64 = i64::MAX;
+}
+impl FieldProto for Int64FieldProto {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, _field_len: u32) -> io::Result<ScalarValue> {
+        let value = reader.read_varint(limit)? as i64;
+        Ok(ScalarValue::I64(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::I64(value) = data {
+            CommonFieldProto::write_varint(writer, *value as i128)
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "int64".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::I64) }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::I64(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `uint64` type.
+pub struct UInt64FieldProto(pub CommonFieldProto);
+impl UInt64FieldProto {
+    /// The smallest value of the type.
+    pub const MIN: u64 = u64::MIN;
+    /// The largest value of the type.
+    pub const MAX: u64 = u64::MAX;
+}
+impl FieldProto for UInt64FieldProto {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, _field_len: u32) -> io::Result<ScalarValue> {
+        let value = reader.read_varint(limit)? as u64;
+        Ok(ScalarValue::U64(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::U64(value) = data {
+            CommonFieldProto::write_varint(writer, *value as i128)
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "uint64".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::U64) }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::U64(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `sint64` type.
+pub struct SInt64FieldProto(pub CommonFieldProto);
+impl SInt64FieldProto {
+    /// The smallest value of the type.
+    pub const MIN: i64 = i64::MIN;
+    /// The largest value of the type.
+    pub const MAX: i64 = i64::MAX;
+}
+impl FieldProto for SInt64FieldProto {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, _field_len: u32) -> io::Result<ScalarValue> {
+        let zigzag = reader.read_varint(limit)? as u64;
+        let value = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+        Ok(ScalarValue::S64(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::S64(value) = data {
+            let zigzag = ((*value << 1) ^ (*value >> 63)) as u64;
+            return CommonFieldProto::write_varint(writer, zigzag as i128);
+        }
+        unreachable!()
+    }
+    fn typename(&self) -> String { "sint64".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::S64) }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::S64(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `sfixed64` type.
+pub struct FixedInt64FieldDefinition(pub CommonFieldProto);
+impl FixedInt64FieldDefinition {
+    /// The smallest value of the type.
+    pub const MIN: i64 = i64::MIN;
+    /// The largest value of the type.
+    pub const MAX: i64 = i64::MAX;
+}
+impl FieldProto for FixedInt64FieldDefinition {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, field_len: u32) -> io::Result<ScalarValue> {
+        debug_assert_eq!(field_len, mem::size_of::<i64>() as u32);
+        let bytes = CommonFieldProto::read_fixed(reader, limit)?;
+        let value = i64::from_le_bytes(bytes);
+        Ok(ScalarValue::SF64(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::SF64(value) = data {
+            CommonFieldProto::write_fixed(writer, &value.to_le_bytes())
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "sfixed64".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::SF64) }
+    fn wire_type(&self) -> u8 { WT_I64 }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::SF64(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `fixed64` type.
+pub struct FixedUInt64FieldDefinition(pub CommonFieldProto);
+impl FixedUInt64FieldDefinition {
+    /// The smallest value of the type.
+    pub const MIN: u64 = u64::MIN;
+    /// The largest value of the type.
+    pub const MAX: u64 = u64::MAX;
+}
+impl FieldProto for FixedUInt64FieldDefinition {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, field_len: u32) -> io::Result<ScalarValue> {
+        debug_assert_eq!(field_len, mem::size_of::<u64>() as u32);
+        let bytes = CommonFieldProto::read_fixed(reader, limit)?;
+        let value = u64::from_le_bytes(bytes);
+        Ok(ScalarValue::UF64(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::UF64(value) = data {
+            CommonFieldProto::write_fixed(writer, &value.to_le_bytes())
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "fixed64".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::parse_in_range(text).map(ScalarValue::UF64) }
+    fn wire_type(&self) -> u8 { WT_I64 }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::UF64(0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `float` type.
+pub struct FloatFieldDefinition(pub CommonFieldProto);
+impl FieldProto for FloatFieldDefinition {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, field_len: u32) -> io::Result<ScalarValue> {
+        debug_assert_eq!(field_len, mem::size_of::<f32>() as u32);
+        let bytes = CommonFieldProto::read_fixed(reader, limit)?;
+        let value = f32::from_le_bytes(bytes);
+        Ok(ScalarValue::F32(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::F32(value) = data {
+            CommonFieldProto::write_fixed(writer, &value.to_le_bytes())
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "float".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { text.parse().ok().map(ScalarValue::F32) }
+    fn wire_type(&self) -> u8 { WT_I32 }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::F32(0.0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `double` type.
+pub struct DoubleFieldDefinition(pub CommonFieldProto);
+impl FieldProto for DoubleFieldDefinition {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, field_len: u32) -> io::Result<ScalarValue> {
+        debug_assert_eq!(field_len, mem::size_of::<f64>() as u32);
+        let bytes = CommonFieldProto::read_fixed(reader, limit)?;
+        let value = f64::from_le_bytes(bytes);
+        Ok(ScalarValue::F64(value))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::F64(value) = data {
+            CommonFieldProto::write_fixed(writer, &value.to_le_bytes())
+        } else { unreachable!() }
+    }
+    fn typename(&self) -> String { "double".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { text.parse().ok().map(ScalarValue::F64) }
+    fn wire_type(&self) -> u8 { WT_I64 }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::F64(0.0)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `bool` type.
+pub struct BoolFieldDefinition(pub CommonFieldProto);
+impl FieldProto for BoolFieldDefinition {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, _field_len: u32) -> io::Result<ScalarValue> {
+        let value = reader.read_varint(limit)?;
+        Ok(ScalarValue::BOOL(value != 0))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::BOOL(value) = data {
+            return CommonFieldProto::write_varint(writer, *value as i128);
+        }
+        unreachable!()
+    }
+    fn typename(&self) -> String { "bool".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> {
+        match text {
+            "true" | "1" => Some(ScalarValue::BOOL(true)),
+            "false" | "0" => Some(ScalarValue::BOOL(false)),
+            _ => None,
+        }
+    }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::BOOL(false)) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `string` type.
+pub struct StringFieldDefinition(pub CommonFieldProto);
+impl FieldProto for StringFieldDefinition {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, field_len: u32) -> io::Result<ScalarValue> {
+        match Text::from_blob(reader.read_blob(field_len, limit)?) {
+            Some(value) => Ok(ScalarValue::STR(value)),
+            None => Ok(ScalarValue::STR("wrong unicode data".into())),
+        }
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::STR(value) = data {
+            return CommonFieldProto::write_len(writer, value.as_bytes());
+        }
+        unreachable!()
+    }
+    fn typename(&self) -> String { "string".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> { CommonFieldProto::unquote(text).map(|text| ScalarValue::STR(text.into())) }
+    fn wire_type(&self) -> u8 { WT_LEN }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::STR(Text::default())) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of the `bytes` type.
+pub struct BytesFieldDefinition(pub CommonFieldProto);
+impl FieldProto for BytesFieldDefinition {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, field_len: u32) -> io::Result<ScalarValue> {
+        Ok(ScalarValue::BYTES(reader.read_blob(field_len, limit)?))
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::BYTES(value) = data {
+            return CommonFieldProto::write_len(writer, value);
+        }
+        unreachable!()
+    }
+    fn typename(&self) -> String { "bytes".to_string() }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> {
+        if text.starts_with('"') {
+            CommonFieldProto::unquote(text).map(|s| ScalarValue::BYTES(s.into_bytes().into()))
+        } else {
+            CommonFieldProto::parse_hex(text).map(|bytes| ScalarValue::BYTES(bytes.into()))
+        }
+    }
+    fn wire_type(&self) -> u8 { WT_LEN }
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::BYTES(Blob::default())) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// The definition of the fields not declared in their message, their values are kept as read.
+pub struct UnknownFieldDefinition(pub CommonFieldProto);
+impl Default for UnknownFieldDefinition {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnknownFieldDefinition {
+    /// Named `???`, repeated as any field of the data may be.
+    pub fn new() -> Self {
+        Self(CommonFieldProto { name: "???".to_string(), id: 0, repeated: true, optional: false, required: false, oneof_name: None, comment: String::new(), default: None, deprecated: false, json_name: None })
+    }
+
+    /// The value of the tag as read. A group (proto2) is kept as its content, the length of the tag is the length of the content.
+    pub fn read_unknown(reader: &mut dyn PbReaderTrait, limit: &mut u32, tlv: Tag) -> io::Result<ScalarValue> {
+        if tlv.wire_type() == WT_SGROUP {
+            let buf = Self::read_group(reader, limit, tlv.field_id())?;
+            return Ok(ScalarValue::UNKNOWN(Tag { first_number: tlv.first_number, length: buf.len() as u32 }, buf));
+        }
+        if tlv.wire_type() == WT_EGROUP {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("end of group {} without its start", tlv.field_id())));
+        }
+        // an empty length-delimited value has the length 0 too
+        if tlv.wire_type() == WT_VARINT {
+            let value = reader.read_varint(limit)? as i64;
+            let mut vec: Vec<u8> = value.to_le_bytes().into();
+            while vec.last() == Some(&0) { // remove insignificant zeroes
+                vec.pop();
+            }
+            Ok(ScalarValue::UNKNOWN(tlv, vec))
+        } else {
+            let buf = reader.read_len(tlv.length, limit)?;
+            Ok(ScalarValue::UNKNOWN(tlv, buf))
+        }
+    }
+
+    // the values up to the end tag of the group, written back as they are read; nested groups are kept in the content
+    // with their tags, they are tracked in a list and not by recursion, any depth fits the stack
+    fn read_group(reader: &mut dyn PbReaderTrait, limit: &mut u32, id: i32) -> io::Result<Vec<u8>> {
+        let mut buf = vec![];
+        let mut open = vec![id];
+        while *limit > 0 {
+            let tag = reader.read_tag(limit)?;
+            match tag.wire_type() {
+                WT_EGROUP => {
+                    let inner = open.pop().unwrap_or_default();
+                    if tag.field_id() != inner {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("end of group {} inside group {}", tag.field_id(), inner)));
+                    }
+                    if open.is_empty() { return Ok(buf); }
+                    CommonFieldProto::write_varint(&mut buf, tag.first_number as i128)?;
+                }
+                WT_SGROUP => {
+                    open.push(tag.field_id());
+                    CommonFieldProto::write_varint(&mut buf, tag.first_number as i128)?;
+                }
+                _ => {
+                    let value = Self::read_unknown(reader, limit, tag)?;
+                    Self::write_unknown(&mut buf, &value)?;
+                }
+            }
+        }
+        Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("group {} without its end", id)))
+    }
+
+    fn write_unknown(writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::UNKNOWN(tlv, buf) = data {
+            CommonFieldProto::write_varint(writer, tlv.first_number as i128)?;
+
+            if tlv.wire_type() == WT_VARINT {
+                let mut buf128 = [0u8; 16];
+                for i in 0..=15 {
+                    if i >= buf.len() { break; }
+                    buf128[i] = buf[i];
+                }
+                let value = i128::from_le_bytes(buf128);
+                return CommonFieldProto::write_varint(writer, value);
+            } else if tlv.wire_type() == WT_SGROUP {
+                writer.write_all(buf)?;
+                return CommonFieldProto::write_varint(writer, (tlv.first_number + 1) as i128);
+            } else {
+                if !tlv.auto_length() { CommonFieldProto::write_varint(writer, tlv.length as i128)?; }
+                return CommonFieldProto::write_len(writer, buf.as_slice());
+            }
+        }
+        unreachable!()
+    }
+}
+impl FieldProto for UnknownFieldDefinition {
+    fn read(&self, _reader: &mut dyn PbReaderTrait, _limit: &mut u32, _field_len: u32) -> io::Result<ScalarValue> {
+        unreachable!()
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        Self::write_unknown(writer, data)
+    }
+    fn typename(&self) -> String { "unknown".to_string() }
+    fn wire_type(&self) -> u8 { panic!("wire type unknown"); } // depend on data read, but here is only type description
+    fn zero(&self) -> FieldValue { FieldValue::SCALAR(ScalarValue::UNKNOWN(Tag { first_number: 0, length: 0 }, Vec::new())) }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.0 }
+}
+
+
+/// A field of a message or enum type, the type is found by [`crate::proto::ProtoData::finalize`].
+pub struct EnumOrMessageFieldDefinition {
+    /// The declaration.
+    pub common: CommonFieldProto,
+    /// The enum, if the type is one.
+    pub enum_proto: OnceLock<EnumProtoPtr>,
+    /// The message, if the type is one.
+    pub is_message: OnceLock<MessageProtoPtr>,   // TODO rename
+    /// The type name as declared, `typename()` is the full name of the type found.
+    pub typename: String,
+}
+impl EnumOrMessageFieldDefinition {
+    /// A field of the type not linked yet.
+    pub fn new(common: CommonFieldProto, typename: String) -> Self {
+        EnumOrMessageFieldDefinition {
+            common,
+            enum_proto: OnceLock::new(),
+            is_message: OnceLock::new(),
+            typename,
+        }
+    }
+}
+impl FieldProto for EnumOrMessageFieldDefinition {
+    fn read(&self, reader: &mut dyn PbReaderTrait, limit: &mut u32, _field_len: u32) -> io::Result<ScalarValue> {
+        if self.enum_proto.get().is_some() {
+            let value = reader.read_varint(limit)? as i32;
+            Ok(ScalarValue::ENUM(value))
+        } else {
+            panic!("read incomplete field definition {}", &self.common.name)
+        }
+
+        //if !self.variants.is_empty() {
+        //    let value = reader.read_varint(limit)? as i32;
+        //    Ok(ScalarValue::ENUM(value))
+        //} else {
+        //    panic!("read incomplete field definition {}", &self.common.name)
+        //}
+    }
+    fn write(&self, writer: &mut dyn io::Write, data: &ScalarValue) -> io::Result<()> {
+        if let ScalarValue::ENUM(value) = data {
+            return CommonFieldProto::write_varint(writer, *value as i128);
+        }
+        unreachable!()
+    }
+    fn parse_value(&self, text: &str) -> Option<ScalarValue> {
+        if self.is_message() { return None; }
+        let variants = &self.enum_proto.get()?.variants;
+        if let Some(variant) = variants.iter().find(|v| v.0 == text) {
+            return Some(ScalarValue::ENUM(variant.1));
+        }
+        // unknown enum values are allowed by proto3, ?5 as they are shown
+        text.strip_prefix('?').unwrap_or(text).parse().ok().map(ScalarValue::ENUM)
+    }
+    // the full name after the type is linked
+    fn typename(&self) -> String {
+        match (self.is_message.get(), self.enum_proto.get()) {
+            (Some(msg), _) => msg.name.clone(),
+            (_, Some(enm)) => enm.name.clone(),
+            _ => self.typename.clone(),
+        }
+    }
+    fn wire_type(&self) -> u8 { if self.is_message.get().is_some() { WT_LEN } else { WT_VARINT } }
+    fn zero(&self) -> FieldValue {
+        if let Some(def) = self.is_message.get() {
+            FieldValue::MESSAGE(Arc::new(MessageData { def: def.clone(), fields: vec![], packed: vec![] }))
+        } else {
+            FieldValue::SCALAR(ScalarValue::ENUM(0))
+        }
+    }
+    fn get_common_definition(&self) -> &CommonFieldProto { &self.common }
+    fn is_message(&self) -> bool { self.is_message.get().is_some() }
+    fn get_enum_name_by_index(&self, i: i32) -> Option<&str> {
+        for v in &self.enum_proto.get()?.variants {
+            if v.1 == i {
+                return Some(&v.0);
+            }
+        }
+        None
+    }
+    fn link_user_types(&self, typename: &str, enums: &Vec<EnumProtoPtr>, messages: &Vec<MessageProtoPtr>) -> bool {
+        if let Ok(index) = messages.binary_search_by(|m| m.name.as_str().cmp(typename)) {
+            let _ = self.is_message.set(messages[index].clone());
+            return true;
+        }
+        if let Ok(index) = enums.binary_search_by(|m| m.name.as_str().cmp(typename)) {
+            self.enum_proto.set(enums[index].clone()).unwrap();
+            return true;
+        }
+        false
+    }
+}
+
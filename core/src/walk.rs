@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use crate::proto::FieldProtoPtr;
+use crate::wire::{FieldData, FieldPath, FieldPos, FieldValue, MessageData};
+
+/// A field met by the traversal: its path from the root, the definition and the value.
+pub struct Item<'a> {
+    /// The path from the message walked.
+    pub path: FieldPath,
+    /// The field with its definition and value.
+    pub field: &'a FieldData,
+}
+
+impl<'a> Item<'a> {
+    /// The definition of the field.
+    pub fn def(&self) -> &'a FieldProtoPtr { &self.field.def }
+    /// The value of the field.
+    pub fn value(&self) -> &'a FieldValue { &self.field.value }
+    /// 1 for the fields of the message walked.
+    pub fn depth(&self) -> usize { self.path.0.len() }
+}
+
+/// Fields in the order of the data, a message goes before its content.
+pub struct DepthFirst<'a> {
+    // messages being walked with the position of the next field and the counts of values met by ids
+    stack: Vec<(&'a MessageData, usize, HashMap<i32, usize>)>,
+    // path of the message on the top of the stack
+    path: FieldPath,
+    // the message returned last, its content goes next
+    enter: Option<(&'a MessageData, FieldPos)>,
+}
+
+impl<'a> DepthFirst<'a> {
+    /// The fields of the root message and all nested ones.
+    pub fn new(root: &'a MessageData) -> DepthFirst<'a> {
+        DepthFirst { stack: vec![(root, 0, HashMap::new())], path: FieldPath::new(), enter: None }
+    }
+
+    /// Do not go into the message returned last.
+    pub fn skip_children(&mut self) { self.enter = None; }
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = Item<'a>;
+
+    fn next(&mut self) -> Option<Item<'a>> {
+        if let Some((msg, pos)) = self.enter.take() {
+            self.stack.push((msg, 0, HashMap::new()));
+            self.path.push(pos);
+        }
+        loop {
+            let (msg, next, counts) = self.stack.last_mut()?;
+            if let Some(field) = msg.fields.get(*next) {
+                *next += 1;
+                let index = counts.entry(field.id()).or_insert(0);
+                let pos = FieldPos { id: field.id(), index: *index };
+                *index += 1;
+                if let FieldValue::MESSAGE(msg) = &field.value { self.enter = Some((msg, pos.clone())); }
+                return Some(Item { path: self.path.add(pos), field });
+            }
+            self.stack.pop();
+            self.path.0.pop();
+        }
+    }
+}
+
+/// What the visitor wants next.
+pub enum Walk {
+    /// Go on, into the message if the field is one.
+    Continue,
+    /// The content of the message is not visited.
+    SkipChildren,
+    /// No more fields.
+    Stop,
+}
+
+/// Receives all fields of the data in the depth-first order.
+pub trait Visitor {
+    /// Called for each field, the answer says where to go next.
+    fn visit(&mut self, item: &Item) -> Walk;
+}
+
+impl<F: FnMut(&Item) -> Walk> Visitor for F {
+    fn visit(&mut self, item: &Item) -> Walk { self(item) }
+}
+
+/// Passes the fields of the root message and the nested ones to the visitor, in the order of [`DepthFirst`].
+pub fn walk(root: &MessageData, visitor: &mut dyn Visitor) {
+    let mut it = DepthFirst::new(root);
+    while let Some(item) = it.next() {
+        match visitor.visit(&item) {
+            Walk::Continue => {}
+            Walk::SkipChildren => it.skip_children(),
+            Walk::Stop => break,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod walk_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::proto::ProtoData;
+    use crate::typedefs::PbReader;
+
+    fn data() -> (ProtoData, MessageData) {
+        let proto = ProtoData::new("message M { repeated N n = 1; int32 b = 2; }\nmessage N { repeated int32 a = 1; N c = 2; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        apply_script(&mut data, &proto, "set n[0].a[0] = 1\nset n[0].a[1] = 2\nset n[1].c.a = 3\nset b = 4").unwrap();
+        (proto, data)
+    }
+
+    fn text(item: &Item) -> String {
+        let path: Vec<String> = item.path.0.iter().map(|pos| format!("{}[{}]", pos.id, pos.index)).collect();
+        format!("{} {}", path.join("."), item.def().name())
+    }
+
+    #[test]
+    fn depth_first_order() {
+        let (proto, data) = data();
+        let items: Vec<String> = DepthFirst::new(&data).map(|item| text(&item)).collect();
+        assert_eq!(items, ["1[0] n", "1[0].1[0] a", "1[0].1[1] a", "1[1] n", "1[1].2[0] c", "1[1].2[0].1[0] a", "2[0] b"]);
+        let empty = MessageData::new(&mut PbReader::new([].as_slice()), &proto, data.def.clone(), &mut 0).unwrap();
+        assert!(DepthFirst::new(&empty).next().is_none());
+    }
+
+    #[test]
+    fn visitor() {
+        let (_, data) = data();
+        let mut items = vec![];
+        walk(&data, &mut |item: &Item| {
+            items.push(text(item));
+            match item.def().name().as_str() {
+                "n" if item.path.0[0].index == 0 => Walk::SkipChildren,
+                "a" => Walk::Stop,
+                _ => Walk::Continue,
+            }
+        });
+        assert_eq!(items, ["1[0] n", "1[1] n", "1[1].2[0] c", "1[1].2[0].1[0] a"]);
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt::{Display, Formatter};
+use crate::proto::{FieldProtoPtr, MessageProtoPtr, ProtoData};
+use crate::walk::DepthFirst;
+use crate::wire::{FieldPath, FieldPos, FieldValue, MessageData, ScalarValue, WT_LEN};
+
+// what happens with a field of the data if it is read with another version of the schema
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    // the field number is not in the new message
+    Unknown,
+    // the same wire type, the value is read as another type (old, new)
+    TypeChanged(String, String),
+    // the value cannot be read with the new definition
+    Conflict(String),
+    // an enum number with no variant (the enum type, the nearest variant and its number)
+    NoName(String, i32, Option<(String, i32)>),
+}
+
+pub struct Finding {
+    pub path: FieldPath,
+    // the path of the field by the names of the current schema
+    pub text: String,
+    pub issue: Issue,
+}
+
+impl Display for Finding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.issue {
+            Issue::Unknown => write!(f, "? {}: becomes unknown", self.text),
+            Issue::TypeChanged(old, new) => write!(f, "~ {}: {} → {}", self.text, old, new),
+            Issue::Conflict(reason) => write!(f, "! {}: {}", self.text, reason),
+            Issue::NoName(typename, value, None) => write!(f, "? {}: {} is not a value of {}", self.text, value, typename),
+            Issue::NoName(typename, value, Some((name, number))) =>
+                write!(f, "? {}: {} is not a value of {}, nearest {} = {}", self.text, value, typename, name, number),
+        }
+    }
+}
+
+// fields of the data that are not read the same way with the new root message,
+// the content of unknown and changed messages is checked against the new definitions
+pub fn audit(data: &MessageData, new_proto: &ProtoData, new_root: MessageProtoPtr) -> Vec<Finding> {
+    let mut res = vec![];
+    // new definitions of the messages containing the item, by depth
+    let mut parents = vec![new_root];
+    let mut it = DepthFirst::new(data);
+    while let Some(item) = it.next() {
+        parents.truncate(item.depth());
+        let def = item.def();
+        // unknown fields of the current schema are kept as is
+        if def.typename() == "unknown" {
+            continue;
+        }
+        // the issues of the type are reported for the first value of a repeated field
+        let index = item.path.0.last().unwrap().index;
+        let issue = match parents.last().unwrap().get_field(def.id()) {
+            None => (index == 0).then_some(Issue::Unknown),
+            Some(new_def) => {
+                let issue = compare(def, &new_def, item.value(), index);
+                if new_def.is_message() && issue.as_ref().map_or(true, |issue| !matches!(issue, Issue::Conflict(_))) {
+                    if let Some(msg) = new_proto.get_message_definition(&new_def.typename()) { parents.push(msg); }
+                }
+                issue
+            }
+        };
+        if parents.len() <= item.depth() { it.skip_children(); }
+        if let Some(issue) = issue {
+            let text = item.path.to_text(data).unwrap_or_default();
+            res.push(Finding { path: item.path, text, issue });
+        }
+    }
+    res
+}
+
+// enum values of the data with no variant in the schema, they are kept as numbers when the data is saved
+pub fn unknown_enums(data: &MessageData, proto: &ProtoData) -> Vec<Finding> {
+    let mut res = vec![];
+    for item in DepthFirst::new(data) {
+        let FieldValue::SCALAR(ScalarValue::ENUM(value)) = item.value() else { continue; };
+        if item.def().get_enum_name_by_index(*value).is_some() { continue; }
+        let typename = item.def().typename();
+        let nearest = proto.get_enum_definition(&typename).and_then(|def| nearest_variant(&def.variants, *value));
+        let text = item.path.to_text(data).unwrap_or_default();
+        res.push(Finding { path: item.path, text, issue: Issue::NoName(typename, *value, nearest) });
+    }
+    res
+}
+
+// the closest number, the lower one of two at the same distance
+fn nearest_variant(variants: &[(String, i32, String)], value: i32) -> Option<(String, i32)> {
+    let variant = variants.iter().min_by_key(|v| ((v.1 as i64 - value as i64).abs(), v.1))?;
+    Some((variant.0.clone(), variant.1))
+}
+
+fn compare(old: &FieldProtoPtr, new: &FieldProtoPtr, value: &FieldValue, index: usize) -> Option<Issue> {
+    let (old_type, new_type) = (old.typename(), new.typename());
+    let new_wire = if new.is_message() { WT_LEN } else { new.wire_type() };
+    let old_wire = if old.is_message() { WT_LEN } else { old.wire_type() };
+    if old_wire != new_wire {
+        return (index == 0).then(|| Issue::Conflict(format!("{} in the data, {} in the new schema", old_type, new_type)));
+    }
+    // the second value is reported, the last one is read for a singular field
+    if index == 1 && old.repeated() && !new.repeated() {
+        return Some(Issue::Conflict(format!("several values, {} is not repeated in the new schema", new.name())));
+    }
+    if old_type != new_type {
+        return (index == 0).then_some(Issue::TypeChanged(old_type, new_type));
+    }
+    match value {
+        FieldValue::SCALAR(ScalarValue::ENUM(v)) if old.get_enum_name_by_index(*v).is_some() && new.get_enum_name_by_index(*v).is_none() =>
+            Some(Issue::Conflict(format!("{} is not a value of {} in the new schema", old.get_enum_name_by_index(*v).unwrap(), new_type))),
+        _ => None,
+    }
+}
+
+// fields of the data read from `original` that would not be written back byte for byte: overlong varints,
+// values of other types in the file, etc.; the innermost fields are reported, the whole data if no field differs
+pub fn round_trip(data: &MessageData, original: &[u8], proto: &ProtoData) -> Vec<String> {
+    let mut res = vec![];
+    check_fields(data, data, original, proto, &FieldPath::new(), &mut res);
+    if res.is_empty() {
+        let mut buf = vec![];
+        if data.write(&mut buf, proto, data.def.clone()).is_err() || buf != original {
+            res.push(format!("! the data: {} bytes in the file, {} bytes written", original.len(), buf.len()));
+        }
+    }
+    res
+}
+
+// the offset of the first byte that is not the same
+pub fn first_difference(original: &[u8], written: &[u8]) -> Option<usize> {
+    match original.iter().zip(written).position(|(a, b)| a != b) {
+        Some(pos) => Some(pos),
+        None => (original.len() != written.len()).then(|| original.len().min(written.len())),
+    }
+}
+
+fn check_fields(root: &MessageData, msg: &MessageData, original: &[u8], proto: &ProtoData, path: &FieldPath, res: &mut Vec<String>) {
+    let mut counts = std::collections::HashMap::<i32, usize>::new();
+    for field in &msg.fields {
+        let index = counts.entry(field.id()).or_default();
+        let path = path.add(FieldPos { id: field.id(), index: *index });
+        *index += 1;
+        // values of a packed field share the tag
+        let Some(wire) = field.wire.as_ref().filter(|_| !field.packed()) else { continue; };
+        let Some(read) = original.get(wire.tag_pos..field.pos + wire.len) else { continue; };
+        let mut written = vec![];
+        if field.write(&mut written, proto).is_ok() && written == read { continue; }
+        let count = res.len();
+        if let FieldValue::MESSAGE(child) = &field.value {
+            check_fields(root, child, original, proto, &path, res);
+        }
+        if res.len() == count {
+            let text = path.to_text(root).unwrap_or_else(|| format!("#{}", field.id()));
+            res.push(format!("! {}: {} bytes in the file, {} bytes written", text, read.len(), written.len()));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod audit_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::typedefs::PbReader;
+
+    #[test]
+    fn schema_changes() {
+        let proto = ProtoData::new("message M { repeated int32 a = 1; string b = 2; N n = 3; E e = 4; int64 c = 5; }\n\
+            message N { int32 x = 1; int32 y = 2; }\nenum E { E0 = 0; E1 = 1; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        apply_script(&mut data, &proto, "set a[0] = 1\nset a[1] = 2\nset b = x\nset n.x = 1\nset n.y = 2\nset e = E1\nset c = 3").unwrap();
+
+        let new_proto = ProtoData::new("message M { int32 a = 1; bytes b = 2; K n = 3; E e = 4; fixed64 c = 5; }\n\
+            message K { sint32 x = 1; }\nenum E { E0 = 0; }").unwrap().finalize().unwrap();
+        let report: Vec<String> = audit(&data, &new_proto, new_proto.get_message_definition("M").unwrap()).iter().map(|f| f.to_string()).collect();
+        assert_eq!(report, ["! a[1]: several values, a is not repeated in the new schema", "~ b: string → bytes", "~ n: N → K",
+            "~ n.x: int32 → sint32", "? n.y: becomes unknown", "! e: E1 is not a value of E in the new schema", "! c: int64 in the data, fixed64 in the new schema"]);
+
+        assert!(audit(&data, &proto, data.def.clone()).is_empty());
+    }
+
+    #[test]
+    fn enum_numbers() {
+        let proto = ProtoData::new("message M { E e = 1; repeated E r = 2; F f = 3; }\nenum E { E0 = 0; E4 = 4; E8 = 8; }\nenum F { F1 = 1; }").unwrap().finalize().unwrap();
+        // e = 7, r = [6, -2] packed, f = 1
+        let bytes = [0x08, 0x07, 0x12, 0x0b, 0x06, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0x18, 0x01];
+        let data = MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut (bytes.len() as u32)).unwrap();
+        assert!(round_trip(&data, &bytes, &proto).is_empty());
+        let report: Vec<String> = unknown_enums(&data, &proto).iter().map(|f| f.to_string()).collect();
+        assert_eq!(report, ["? e: 7 is not a value of E, nearest E8 = 8", "? r[0]: 6 is not a value of E, nearest E4 = 4", "? r[1]: -2 is not a value of E, nearest E0 = 0"]);
+    }
+
+    #[test]
+    fn not_round_trip() {
+        let proto = ProtoData::new("message M { int32 a = 1; N n = 2; string s = 3; }\nmessage N { int32 x = 1; }").unwrap().finalize().unwrap();
+        let read = |bytes: &[u8]| MessageData::new(&mut PbReader::new(bytes), &proto, proto.get_message_definition("M").unwrap(), &mut (bytes.len() as u32)).unwrap();
+        let bytes = [0x08, 0x01, 0x12, 0x02, 0x08, 0x05, 0x1a, 0x01, 0x61];
+        assert!(round_trip(&read(&bytes), &bytes, &proto).is_empty());
+        // overlong varints: 0 in two bytes
+        let bytes = [0x08, 0x80, 0x00, 0x12, 0x03, 0x08, 0x85, 0x00, 0x1a, 0x01, 0x61];
+        assert_eq!(round_trip(&read(&bytes), &bytes, &proto), ["! a: 3 bytes in the file, 2 bytes written", "! n.x: 3 bytes in the file, 2 bytes written"]);
+    }
+
+    #[test]
+    fn round_trip_kept() {
+        let proto = ProtoData::new("message M { int32 a = 1; repeated int32 p = 2; repeated int32 u = 3; N n = 4; }\nmessage N { int32 x = 1; }").unwrap().finalize().unwrap();
+        let read = |bytes: &[u8]| MessageData::new(&mut PbReader::new(bytes), &proto, proto.get_message_definition("M").unwrap(), &mut (bytes.len() as u32)).unwrap();
+        // fields out of the proto order, packed and unpacked values, groups of a repeated field, unknown fields (9, n.7)
+        let bytes = [0x22, 0x04, 0x38, 0x01, 0x08, 0x02, 0x18, 0x01, 0x48, 0x96, 0x01, 0x12, 0x02, 0x01, 0x02, 0x08, 0x07, 0x18, 0x02];
+        let data = read(&bytes);
+        assert_eq!(round_trip(&data, &bytes, &proto), [""; 0]);
+        assert_eq!(first_difference(&bytes, &bytes), None);
+        assert_eq!(first_difference(&bytes, &bytes[..4]), Some(4));
+    }
+}
@@ -0,0 +1,118 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// how often the changes are written to the recovery file
+pub const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+const HEADER: &str = "# protoedit recovery file, changes not saved to";
+
+// edit commands (in the --apply format) of the changes not saved yet,
+// written periodically next to the data file to replay them after a crash
+pub struct Journal {
+    // None if there is no data file (stdin)
+    pub path: Option<PathBuf>,
+    pub commands: Vec<String>,
+    // count of the commands in the recovery file
+    written: usize,
+    last_write: Instant,
+}
+
+impl Journal {
+    pub fn new(data_file: Option<&Path>) -> Journal {
+        Journal { path: data_file.map(Self::recovery_path), commands: vec![], written: 0, last_write: Instant::now() }
+    }
+
+    // data.pb.recovery for data.pb
+    pub fn recovery_path(data_file: &Path) -> PathBuf {
+        let mut name = data_file.as_os_str().to_owned();
+        name.push(".recovery");
+        name.into()
+    }
+
+    // commands of a previous session that was not finished normally
+    pub fn load(&self) -> Option<String> {
+        let text = std::fs::read_to_string(self.path.as_ref()?).ok()?;
+        if text.lines().any(|line| !line.trim().is_empty() && !line.starts_with('#')) { Some(text) } else { None }
+    }
+
+    pub fn push(&mut self, command: String) { self.commands.push(command); }
+
+    // write the commands if there are new ones and the interval passed, returns true if written
+    pub fn autosave(&mut self, now: Instant) -> io::Result<bool> {
+        if self.written == self.commands.len() || now.duration_since(self.last_write) < AUTOSAVE_INTERVAL {
+            return Ok(false);
+        }
+        self.write()?;
+        self.last_write = now;
+        Ok(true)
+    }
+
+    pub fn write(&mut self) -> io::Result<()> {
+        let Some(path) = &self.path else { return Ok(()); };
+        if self.commands.is_empty() {
+            return self.remove();
+        }
+        let data_file = path.with_extension("");
+        let mut text = format!("{} {}\n", HEADER, data_file.display());
+        for command in &self.commands {
+            text += command;
+            text.push('\n');
+        }
+        // written to a temporary file first, to keep the previous version if writing fails
+        let temp = path.with_extension("recovery.tmp");
+        std::fs::write(&temp, text)?;
+        std::fs::rename(&temp, path)?;
+        self.written = self.commands.len();
+        Ok(())
+    }
+
+    // after the data is saved, or the editor is closed normally
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.commands.clear();
+        self.remove()
+    }
+
+    fn remove(&mut self) -> io::Result<()> {
+        self.written = 0;
+        match &self.path {
+            Some(path) if path.exists() => std::fs::remove_file(path),
+            _ => Ok(()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod autosave_tests {
+    use super::*;
+
+    #[test]
+    fn write_and_load() {
+        let data_file = std::env::temp_dir().join(format!("protoedit_journal_{}.pb", std::process::id()));
+        let mut journal = Journal::new(Some(&data_file));
+        assert_eq!(journal.path, Some(PathBuf::from(format!("{}.recovery", data_file.display()))));
+        assert_eq!(journal.load(), None);
+
+        let start = Instant::now();
+        assert!(!journal.autosave(start + AUTOSAVE_INTERVAL).unwrap());
+        journal.push("set a = 1".into());
+        assert!(!journal.autosave(start).unwrap());
+        assert!(journal.autosave(start + AUTOSAVE_INTERVAL).unwrap());
+        assert!(!journal.autosave(start + AUTOSAVE_INTERVAL * 2).unwrap());
+        let text = journal.load().unwrap();
+        assert_eq!(text.lines().nth(1), Some("set a = 1"));
+
+        journal.clear().unwrap();
+        assert_eq!(journal.load(), None);
+        assert!(!journal.path.as_ref().unwrap().exists());
+    }
+
+    #[test]
+    fn no_data_file() {
+        let mut journal = Journal::new(None);
+        journal.push("delete a".into());
+        journal.write().unwrap();
+        assert_eq!(journal.load(), None);
+    }
+}
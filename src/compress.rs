@@ -0,0 +1,99 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+
+// a data file compressed by gzip or zstd, found by the magic bytes; it is decompressed when it is read
+// and compressed again when it is saved
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+// the default levels of the gzip and zstd programs
+const GZIP_LEVEL: u32 = 6;
+const ZSTD_LEVEL: i32 = 3;
+
+impl Compression {
+    pub fn detect(bytes: &[u8]) -> Option<Compression> {
+        if bytes.starts_with(&[0x1f, 0x8b]) { return Some(Compression::Gzip); }
+        if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) { return Some(Compression::Zstd); }
+        None
+    }
+
+    // the compression of an existing file by its content, of a new one by its extension
+    pub fn of_file(path: &Path) -> Option<Compression> {
+        if let Ok(file) = std::fs::File::open(path) {
+            let mut magic = vec![];
+            return file.take(4).read_to_end(&mut magic).ok().and_then(|_| Self::detect(&magic));
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Some(Compression::Gzip),
+            Some("zst" | "zstd") => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    // all the members or frames written one after another, as the programs read them
+    pub fn decompress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let mut res = vec![];
+        let read = match self {
+            Compression::Gzip => MultiGzDecoder::new(bytes).read_to_end(&mut res).map(|_| ()),
+            Compression::Zstd => zstd::stream::copy_decode(bytes, &mut res),
+        };
+        read.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", self.name(), e)))?;
+        Ok(res)
+    }
+
+    pub fn compress(self, bytes: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(vec![], flate2::Compression::new(GZIP_LEVEL));
+                encoder.write_all(bytes)?;
+                encoder.finish()
+            }
+            Compression::Zstd => zstd::encode_all(bytes, ZSTD_LEVEL),
+        }
+    }
+}
+
+// the content of the file, decompressed if it is compressed
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    match Compression::detect(&bytes) {
+        Some(compression) => compression.decompress(&bytes),
+        None => Ok(bytes),
+    }
+}
+
+
+#[cfg(test)]
+mod compress_tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let bytes = b"\x08\x96\x01\x12\x03abc".repeat(100);
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let compressed = compression.compress(&bytes).unwrap();
+            assert_eq!(Compression::detect(&compressed), Some(compression));
+            assert!(compressed.len() < bytes.len());
+            assert_eq!(compression.decompress(&compressed).unwrap(), bytes);
+            // concatenated files are read whole
+            assert_eq!(compression.decompress(&compressed.repeat(2)).unwrap(), bytes.repeat(2));
+        }
+
+        assert_eq!(Compression::detect(&bytes), None);
+        assert_eq!(Compression::of_file(Path::new("no/such/data.pb.zst")), Some(Compression::Zstd));
+        assert_eq!(Compression::Gzip.decompress(b"\x1f\x8b not gzip").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert!(Compression::Zstd.decompress(&[0x28, 0xb5, 0x2f, 0xfd, 0x00]).unwrap_err().to_string().starts_with("zstd: "));
+    }
+}
@@ -0,0 +1,204 @@
+use std::path::PathBuf;
+use crate::timestamp::TimeZone;
+use crate::view::{FloatFormat, LayoutConfig, MessageLayoutConfig, COLUMN_WIDTH};
+
+// the settings file read at the start, lines `name = value`, # starts a comment line:
+//   indent = 1
+//   margin_left = 0
+//   margin_right = 0
+//   addresses = decimal
+//   offsets = on
+//   floats = shortest                       (or decimal, scientific)
+//   page = 1000
+//   scaffold = on                           (an inserted message gets one of each nested message)
+//   digits = comma                          (1,234,567; or underscore 1_234_567, dot 1.234.567, space 1 234 567, plain by default)
+//   decimal = comma                         (floats shown as 2,5; point by default)
+//   timestamps = local                      (google.protobuf.Timestamp as a date; or utc, an offset +02:00, off by default)
+//   sizes = human                           (a collapsed message size as 1.2 KiB, bytes by default)
+//   json_names = original                   (json written with the field names of the proto file; json by default, json_name or lowerCamelCase)
+//   columns.Order = id:6, total:12, name    (a collapsed Order is a table row, the width is 10 if omitted)
+pub const CONFIG_FILE_NAME: &str = "protoedit.conf";
+
+// $XDG_CONFIG_HOME/protoedit.conf, or ~/.config/protoedit.conf (%APPDATA% on Windows)
+pub fn default_path(vars: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    let dir = vars("XDG_CONFIG_HOME").map(PathBuf::from).
+        or_else(|| vars("HOME").map(|home| PathBuf::from(home).join(".config"))).
+        or_else(|| vars("APPDATA").map(PathBuf::from))?;
+    Some(dir.join(CONFIG_FILE_NAME))
+}
+
+// the settings of the text are set to the config, the error has the line number
+pub fn apply(text: &str, config: &mut LayoutConfig) -> Result<(), String> {
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') { continue; }
+        apply_line(line, config).map_err(|e| format!("line {}: {}", index + 1, e))?;
+    }
+    Ok(())
+}
+
+fn apply_line(line: &str, config: &mut LayoutConfig) -> Result<(), String> {
+    let Some((name, value)) = line.split_once('=') else {
+        return Err(format!("\"name = value\" expected: {}", line));
+    };
+    let (name, value) = (name.trim(), value.trim());
+    if let Some(type_name) = name.strip_prefix("columns.") {
+        config.messages.insert(type_name.to_string(), parse_columns(value)?);
+        return Ok(());
+    }
+    let number = || value.parse::<u16>().ok().filter(|n| *n <= 16).ok_or(format!("{}: a number from 0 to 16 expected", name));
+    match name {
+        "indent" => config.level_indent = number()?,
+        "margin_left" => config.margin_left = number()?,
+        "margin_right" => config.margin_right = number()?,
+        "addresses" => config.hex_addresses = match value {
+            "hex" => true,
+            "decimal" => false,
+            _ => return Err(format!("addresses: hex or decimal expected, not {}", value)),
+        },
+        "offsets" => config.bytes_offsets = match value {
+            "on" => true,
+            "off" => false,
+            _ => return Err(format!("offsets: on or off expected, not {}", value)),
+        },
+        "floats" => config.float_format = match value {
+            "shortest" => FloatFormat::Shortest,
+            "decimal" => FloatFormat::Decimal,
+            "scientific" => FloatFormat::Scientific,
+            _ => return Err(format!("floats: shortest, decimal or scientific expected, not {}", value)),
+        },
+        "scaffold" => config.scaffold = match value {
+            "on" => true,
+            "off" => false,
+            _ => return Err(format!("scaffold: on or off expected, not {}", value)),
+        },
+        "digits" => config.digit_separator = match value {
+            "plain" => None,
+            "comma" => Some(','),
+            "underscore" => Some('_'),
+            "dot" => Some('.'),
+            "space" => Some(' '),
+            _ => return Err(format!("digits: plain, comma, underscore, dot or space expected, not {}", value)),
+        },
+        "decimal" => config.decimal_comma = match value {
+            "point" => false,
+            "comma" => true,
+            _ => return Err(format!("decimal: point or comma expected, not {}", value)),
+        },
+        "timestamps" => config.time_zone = match value {
+            "off" => None,
+            "local" => Some(TimeZone::local()),
+            _ => Some(TimeZone::parse_offset(value).ok_or(format!("timestamps: off, local, utc or an offset as +02:00 expected, not {}", value))?),
+        },
+        "sizes" => config.human_sizes = match value {
+            "bytes" => false,
+            "human" => true,
+            _ => return Err(format!("sizes: bytes or human expected, not {}", value)),
+        },
+        "json_names" => config.original_json_names = match value {
+            "json" => false,
+            "original" => true,
+            _ => return Err(format!("json_names: json or original expected, not {}", value)),
+        },
+        "page" => config.page_size = value.parse::<usize>().ok().filter(|n| (10..=100_000).contains(n)).
+            ok_or("page: a number from 10 to 100000 expected".to_string())?,
+        _ => return Err(format!("unknown setting {}", name)),
+    }
+    Ok(())
+}
+
+// "name:width, name", an empty text has no columns
+pub fn parse_columns(text: &str) -> Result<MessageLayoutConfig, String> {
+    let mut res = MessageLayoutConfig::default();
+    for item in text.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let (name, width) = match item.split_once(':') {
+            Some((name, width)) => (name.trim(), width.trim().parse::<u16>().ok().filter(|w| (1..=100).contains(w)).
+                ok_or(format!("{}: a width from 1 to 100 expected", name.trim()))?),
+            None => (item, COLUMN_WIDTH),
+        };
+        res.columns.push(name.to_string());
+        res.columns_width.push(width);
+    }
+    Ok(res)
+}
+
+pub fn columns_text(columns: &MessageLayoutConfig) -> String {
+    columns.columns.iter().zip(&columns.columns_width).map(|(name, width)| format!("{}:{}", name, width)).collect::<Vec<_>>().join(", ")
+}
+
+// the settings text with the columns line of the message type replaced, added or removed (None),
+// other lines and comments are kept
+pub fn with_columns(text: &str, type_name: &str, columns: Option<&MessageLayoutConfig>) -> String {
+    let key = format!("columns.{}", type_name);
+    let mut new_line = columns.map(|columns| format!("{} = {}", key, columns_text(columns)));
+    let mut res = String::new();
+    for line in text.lines() {
+        if line.split_once('=').is_some_and(|(name, _)| name.trim() == key) {
+            if let Some(new_line) = new_line.take() { res += &new_line; res.push('\n'); }
+            continue;
+        }
+        res += line;
+        res.push('\n');
+    }
+    if let Some(new_line) = new_line { res += &new_line; res.push('\n'); }
+    res
+}
+
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn settings() {
+        let mut config = LayoutConfig::default();
+        apply("# dense\nindent = 1\n\nmargin_left=0\n margin_right = 3 \naddresses = decimal\noffsets = on\n", &mut config).unwrap();
+        assert_eq!((config.level_indent, config.margin_left, config.margin_right, config.hex_addresses, config.bytes_offsets), (1, 0, 3, false, true));
+
+        assert_eq!(apply("indent = 1\nwidth = 3", &mut config), Err("line 2: unknown setting width".into()));
+        assert_eq!(apply("indent = -1", &mut config), Err("line 1: indent: a number from 0 to 16 expected".into()));
+        assert_eq!(apply("addresses = octal", &mut config), Err("line 1: addresses: hex or decimal expected, not octal".into()));
+        assert_eq!(apply("indent", &mut config), Err("line 1: \"name = value\" expected: indent".into()));
+        apply("floats = scientific", &mut config).unwrap();
+        assert_eq!(config.float_format, FloatFormat::Scientific);
+        assert_eq!(apply("floats = hex", &mut config), Err("line 1: floats: shortest, decimal or scientific expected, not hex".into()));
+        apply("page = 200", &mut config).unwrap();
+        assert_eq!(config.page_size, 200);
+        assert_eq!(apply("page = 5", &mut config), Err("line 1: page: a number from 10 to 100000 expected".into()));
+        apply("scaffold = on", &mut config).unwrap();
+        assert!(config.scaffold);
+        apply("digits = underscore\nsizes = human", &mut config).unwrap();
+        assert_eq!((config.digit_separator, config.human_sizes), (Some('_'), true));
+        assert_eq!(apply("digits = tab", &mut config), Err("line 1: digits: plain, comma, underscore, dot or space expected, not tab".into()));
+        apply("digits = dot\ndecimal = comma\ntimestamps = -03:30", &mut config).unwrap();
+        assert_eq!((config.digit_separator, config.decimal_comma, config.time_zone.as_ref().map(|zone| zone.offset_at(0))), (Some('.'), true, Some(-12600)));
+        assert_eq!(apply("timestamps = 3", &mut config), Err("line 1: timestamps: off, local, utc or an offset as +02:00 expected, not 3".into()));
+        apply("json_names = original", &mut config).unwrap();
+        assert!(config.original_json_names);
+        assert_eq!(apply("json_names = camel", &mut config), Err("line 1: json_names: json or original expected, not camel".into()));
+    }
+
+    #[test]
+    fn columns() {
+        let mut config = LayoutConfig::default();
+        apply("columns.Order = id:6, total , name:12", &mut config).unwrap();
+        let order = &config.messages["Order"];
+        assert_eq!((order.columns.clone(), order.columns_width.clone()), (vec!["id".to_string(), "total".into(), "name".into()], vec![6, 10, 12]));
+        assert_eq!(columns_text(order), "id:6, total:10, name:12");
+        assert_eq!(apply("columns.Order = id:0", &mut config), Err("line 1: id: a width from 1 to 100 expected".into()));
+
+        let columns = parse_columns("a:3").unwrap();
+        assert_eq!(with_columns("# mine\nindent = 1\n", "Order", Some(&columns)), "# mine\nindent = 1\ncolumns.Order = a:3\n");
+        assert_eq!(with_columns("columns.Order = b:4\nindent = 1", "Order", Some(&columns)), "columns.Order = a:3\nindent = 1\n");
+        assert_eq!(with_columns("columns.Order = b:4\ncolumns.Item = c:5\n", "Order", None), "columns.Item = c:5\n");
+    }
+
+    #[test]
+    fn path() {
+        let vars = |name: &str| match name { "HOME" => Some("/home/u".to_string()), _ => None };
+        assert_eq!(default_path(&vars), Some(PathBuf::from("/home/u/.config/protoedit.conf")));
+        let vars = |name: &str| match name { "XDG_CONFIG_HOME" => Some("/cfg".to_string()), "HOME" => Some("/home/u".to_string()), _ => None };
+        assert_eq!(default_path(&vars), Some(PathBuf::from("/cfg/protoedit.conf")));
+        assert_eq!(default_path(&|_| None), None);
+    }
+}
@@ -0,0 +1,84 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// what the editor was doing, updated after each event and printed when it crashes
+struct Context {
+    selected: String,
+    command: String,
+}
+
+static CONTEXT: Mutex<Context> = Mutex::new(Context { selected: String::new(), command: String::new() });
+
+pub fn set_selected(text: String) {
+    if let Ok(mut context) = CONTEXT.lock() { context.selected = text; }
+}
+
+pub fn set_command(text: String) {
+    if let Ok(mut context) = CONTEXT.lock() { context.command = text; }
+}
+
+// the panic hook: `restore` returns the terminal to the normal mode, then a short error is printed
+// instead of the backtrace, the backtrace goes to the report file in the temp directory
+pub fn install(restore: fn()) {
+    std::panic::set_hook(Box::new(move |info| {
+        restore();
+        let message = panic_message(info.payload());
+        let location = info.location().map_or(String::new(), |location| format!("{}:{}", location.file(), location.line()));
+        let (selected, command) = match CONTEXT.try_lock() {
+            Ok(context) => (context.selected.clone(), context.command.clone()),
+            Err(_) => (String::new(), String::new()),
+        };
+        let report = report_text(&message, &location, &selected, &command, &Backtrace::force_capture().to_string());
+        eprintln!("error: protoedit crashed: {}", message);
+        if !selected.is_empty() { eprintln!("  selected field: {}", selected); }
+        if !command.is_empty() { eprintln!("  last key: {}", command); }
+        let path = report_path();
+        match std::fs::write(&path, report) {
+            Ok(()) => eprintln!("the crash report is written to {}, please attach it to the issue", path.display()),
+            Err(e) => eprintln!("cannot write the crash report {}: {}", path.display(), e),
+        }
+    }));
+}
+
+// the text given to panic!() or the message of unwrap() and others
+pub fn panic_message(payload: &dyn Any) -> String {
+    if let Some(text) = payload.downcast_ref::<&str>() {
+        text.to_string()
+    } else if let Some(text) = payload.downcast_ref::<String>() {
+        text.clone()
+    } else {
+        "unknown error".to_string()
+    }
+}
+
+pub fn report_text(message: &str, location: &str, selected: &str, command: &str, backtrace: &str) -> String {
+    format!("protoedit {} ({} {})\nmessage: {}\nlocation: {}\nselected field: {}\nlast key: {}\n\nbacktrace:\n{}\n",
+            env!("CARGO_PKG_VERSION"), std::env::consts::OS, std::env::consts::ARCH, message, location, selected, command, backtrace.trim_end())
+}
+
+// protoedit-crash-<pid>.txt, one report for each run
+fn report_path() -> PathBuf {
+    std::env::temp_dir().join(format!("protoedit-crash-{}.txt", std::process::id()))
+}
+
+
+#[cfg(test)]
+mod crash_tests {
+    use super::*;
+
+    #[test]
+    fn message() {
+        assert_eq!(panic_message(&"layout is not loaded"), "layout is not loaded");
+        assert_eq!(panic_message(&format!("index {} out of range", 5)), "index 5 out of range");
+        assert_eq!(panic_message(&5), "unknown error");
+    }
+
+    #[test]
+    fn report() {
+        let text = report_text("index 5 out of range", "src/view.rs:10", "m3.m6[1]", "Ctrl+E - edit the value", "0: main\n");
+        assert!(text.starts_with(&format!("protoedit {} (", env!("CARGO_PKG_VERSION"))));
+        assert!(text.ends_with("message: index 5 out of range\nlocation: src/view.rs:10\nselected field: m3.m6[1]\nlast key: Ctrl+E - edit the value\n\nbacktrace:\n0: main\n"));
+    }
+}
@@ -0,0 +1,159 @@
+use std::fmt::{Display, Formatter};
+use crate::wire::{FieldData, FieldValue, MessageData, ScalarValue};
+
+// field level differences between two versions of the data,
+// values of a repeated field are compared by their indexes
+pub enum Difference {
+    Added(String, String),
+    Removed(String, String),
+    Changed(String, String, String),
+    // the repeated field will be saved packed (true) or unpacked
+    Encoding(String, bool),
+    // the values of the repeated field placed in several groups are saved together
+    Merged(String, usize),
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Difference::Added(path, value) => write!(f, "+ {} = {}", path, value),
+            Difference::Removed(path, value) => write!(f, "- {} = {}", path, value),
+            Difference::Changed(path, old, new) => write!(f, "~ {}: {} → {}", path, old, new),
+            Difference::Encoding(path, packed) => write!(f, "~ {}: {}", path, if *packed { "packed" } else { "unpacked" }),
+            Difference::Merged(path, groups) => write!(f, "~ {}: {} groups merged", path, groups),
+        }
+    }
+}
+
+impl Difference {
+    pub fn path(&self) -> &str {
+        match self {
+            Difference::Added(path, _) | Difference::Removed(path, _) | Difference::Changed(path, _, _) |
+            Difference::Encoding(path, _) | Difference::Merged(path, _) => path,
+        }
+    }
+}
+
+pub fn compare(old: &MessageData, new: &MessageData) -> Vec<Difference> {
+    let mut res = vec![];
+    compare_messages(old, new, "", &mut res);
+    res
+}
+
+fn compare_messages(old: &MessageData, new: &MessageData, prefix: &str, res: &mut Vec<Difference>) {
+    // fields in the order they are placed in the data
+    let mut ids = vec![];
+    for field in old.fields.iter().chain(new.fields.iter()) {
+        if !ids.contains(&field_id(field)) { ids.push(field_id(field)); }
+    }
+
+    for id in ids {
+        let old_values: Vec<&FieldData> = old.fields.iter().filter(|f| field_id(f) == id).collect();
+        let new_values: Vec<&FieldData> = new.fields.iter().filter(|f| field_id(f) == id).collect();
+        if let Some(field) = new_values.first() {
+            if old_values.len() > 0 && old.is_packed(&field.def) != new.is_packed(&field.def) {
+                let name = if prefix.is_empty() { field.def.name() } else { format!("{}.{}", prefix, field.def.name()) };
+                res.push(Difference::Encoding(name, new.is_packed(&field.def)));
+            }
+            let groups = old.group_count(id);
+            if old_values.len() == new_values.len() && groups > 1 && new.group_count(id) == 1 {
+                let name = if prefix.is_empty() { field.def.name() } else { format!("{}.{}", prefix, field.def.name()) };
+                res.push(Difference::Merged(name, groups));
+            }
+        }
+        for index in 0..old_values.len().max(new_values.len()) {
+            let field = old_values.get(index).or(new_values.get(index)).unwrap();
+            let path = field_path(prefix, field, index);
+            match (old_values.get(index), new_values.get(index)) {
+                (Some(old), Some(new)) => match (&old.value, &new.value) {
+                    (FieldValue::MESSAGE(old_msg), FieldValue::MESSAGE(new_msg)) => compare_messages(old_msg, new_msg, &path, res),
+                    _ => {
+                        let (old_text, new_text) = (value_to_string(old), value_to_string(new));
+                        if old_text != new_text { res.push(Difference::Changed(path, old_text, new_text)); }
+                    }
+                },
+                (Some(old), None) => res.push(Difference::Removed(path, value_to_string(old))),
+                (None, Some(new)) => res.push(Difference::Added(path, value_to_string(new))),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+fn field_id(field: &FieldData) -> i32 {
+    match &field.value {
+        FieldValue::SCALAR(ScalarValue::UNKNOWN(tag, _)) => tag.field_id(),
+        _ => field.def.id(),
+    }
+}
+
+// written as FieldPath::parse expects, unknown fields by number
+fn field_path(prefix: &str, field: &FieldData, index: usize) -> String {
+    let mut name = match &field.value {
+        FieldValue::SCALAR(ScalarValue::UNKNOWN(tag, _)) => format!("#{}", tag.field_id()),
+        _ => field.def.name(),
+    };
+    if field.def.repeated() { name += &format!("[{}]", index); }
+    if prefix.is_empty() { name } else { format!("{}.{}", prefix, name) }
+}
+
+pub fn value_to_string(field: &FieldData) -> String {
+    match &field.value {
+        FieldValue::MESSAGE(msg) => format!("message {}", msg.def.name),
+        FieldValue::SCALAR(ScalarValue::ENUM(index)) => field.def.get_enum_name_by_index(*index).map(|name| name.to_string()).unwrap_or(index.to_string()),
+        FieldValue::SCALAR(ScalarValue::STR(text)) => format!("{:?}", text),
+        FieldValue::SCALAR(scalar) => scalar.to_string(),
+    }
+}
+
+
+#[cfg(test)]
+mod diff_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::proto::ProtoData;
+    use crate::typedefs::PbReader;
+
+    const PROTO: &str = "enum E { A = 0; B = 1; }\nmessage Root { string name = 1; Item item = 2; repeated int32 ids = 3; }\nmessage Item { E kind = 1; repeated Item children = 2; bool ok = 4; }";
+
+    fn diff(old_script: &str, new_script: &str) -> Vec<String> {
+        let proto = ProtoData::new(PROTO).unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut old = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        let mut new = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        apply_script(&mut old, &proto, old_script).unwrap();
+        apply_script(&mut new, &proto, new_script).unwrap();
+        compare(&old, &new).iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn scalar_changes() {
+        assert!(diff("set name = a", "set name = a").is_empty());
+        assert_eq!(diff("set name = a", "set name = b"), ["~ name: \"a\" → \"b\""]);
+        assert_eq!(diff("", "set item.kind = B"), ["+ item = message Item"]);
+        assert_eq!(diff("set item.kind = A", "set item.kind = B\nset item.ok = true"), ["~ item.kind: A → B", "+ item.ok = true"]);
+    }
+
+    #[test]
+    fn repeated_changes() {
+        assert_eq!(diff("set ids[0] = 1\nset ids[1] = 2", "set ids[0] = 1"), ["- ids[1] = 2"]);
+        assert_eq!(diff("set ids[0] = 1", "set ids[0] = 3\nset ids[1] = 1"), ["~ ids[0]: 1 → 3", "+ ids[1] = 1"]);
+        assert_eq!(diff("insert item.children\nset item.children[1].ok = true", "insert item.children\nset item.children[1].ok = false\ndelete item.children[0]"),
+                   ["+ item.children[0].ok = false", "- item.children[1] = message Item"]);
+    }
+
+    #[test]
+    fn encoding_changes() {
+        let proto = ProtoData::new(PROTO).unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut old = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        apply_script(&mut old, &proto, "set ids[0] = 1").unwrap();
+        let mut new = MessageData::new(&mut PbReader::new([0x1a, 0x01, 0x01].as_slice()), &proto, root_msg, &mut 3).unwrap();
+        assert_eq!(compare(&old, &new).iter().map(|d| d.to_string()).collect::<Vec<_>>(), ["~ ids: packed"]);
+        new.toggle_packed(3);
+        assert!(compare(&old, &new).is_empty());
+
+        let script = "set ids[0] = 1\nset name = a\nset ids[1] = 2";
+        assert_eq!(diff(script, &format!("{}\nmerge ids", script)), ["~ ids: 2 groups merged"]);
+    }
+}
@@ -0,0 +1,426 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::io;
+use crate::proto::{FieldProtoPtr, MessageProto, ProtoData};
+use crate::wire::{FieldPath, FieldValue, MessageData, ScalarValue};
+
+// Simple expressions evaluated against a message:
+//   settings.timeout > 0 && items.len() >= 1
+//   name == "foo" || !(kind == "B")
+//   kind == B, a name that is not a field is the name of an enum value
+// fields are addressed by path as in batch commands, not set fields have default values,
+// len() is the count of a repeated field (if no index given) or the length of a string or bytes value.
+// operators by priority: || && (== != < <= > >=) (+ -) (* / %) (! -)
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Enum(i32, Option<String>), // value and its name, if known
+    Message(bool),             // is the message present in the data
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op { Or, And, Eq, Ne, Lt, Le, Gt, Ge, Add, Sub, Mul, Div, Rem }
+
+enum Node {
+    Literal(Value),
+    Field(FieldPath, FieldProtoPtr),
+    Len(FieldPath, FieldProtoPtr, bool), // the last flag: count the values of a repeated field
+    Not(Box<Node>),
+    Neg(Box<Node>),
+    Binary(Op, Box<Node>, Box<Node>),
+}
+
+pub struct Expr {
+    root: Node,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Value(Value),
+    Path(String),
+    Len(String),
+    Op(Op),
+    Not,
+    Open,
+    Close,
+}
+
+fn error(msg: String) -> io::Error { io::Error::new(io::ErrorKind::InvalidInput, msg) }
+
+impl Expr {
+    pub fn parse(text: &str, root: &MessageProto, proto: &ProtoData) -> io::Result<Expr> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser { tokens, pos: 0, root, proto };
+        let node = parser.parse_binary(0)?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(error(format!("unexpected {:?} in expression", token)));
+        }
+        Ok(Expr { root: node, text: text.trim().to_string() })
+    }
+
+    pub fn eval(&self, data: &MessageData) -> io::Result<Value> { self.root.eval(data) }
+
+    // the expression must have a boolean result, a message is true if present
+    pub fn check(&self, data: &MessageData) -> io::Result<bool> { self.root.eval_bool(data) }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result { write!(f, "{}", self.text) }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Bool(v) => write!(f, "{}", v),
+            Value::Int(v) => write!(f, "{}", v),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Str(v) => write!(f, "\"{}\"", v),
+            Value::Bytes(v) => write!(f, "{}", ScalarValue::BYTES(v.clone().into())),
+            Value::Enum(v, Some(name)) => write!(f, "{}", name),
+            Value::Enum(v, None) => write!(f, "{}", v),
+            Value::Message(true) => write!(f, "message"),
+            Value::Message(false) => write!(f, "empty message"),
+        }
+    }
+}
+
+impl Value {
+    fn from_scalar(value: &ScalarValue, def: &FieldProtoPtr) -> Value {
+        match value {
+            ScalarValue::I32(v) | ScalarValue::S32(v) | ScalarValue::SF32(v) => Value::Int(*v as i128),
+            ScalarValue::U32(v) | ScalarValue::UF32(v) => Value::Int(*v as i128),
+            ScalarValue::I64(v) | ScalarValue::S64(v) | ScalarValue::SF64(v) => Value::Int(*v as i128),
+            ScalarValue::U64(v) | ScalarValue::UF64(v) => Value::Int(*v as i128),
+            ScalarValue::F32(v) => Value::Float(*v as f64),
+            ScalarValue::F64(v) => Value::Float(*v),
+            ScalarValue::BOOL(v) => Value::Bool(*v),
+            ScalarValue::STR(v) => Value::Str(v.to_string()),
+            ScalarValue::BYTES(v) => Value::Bytes(v.to_vec()),
+            ScalarValue::UNKNOWN(_, v) => Value::Bytes(v.clone()),
+            ScalarValue::ENUM(v) => Value::Enum(*v, def.get_enum_name_by_index(*v).map(|s| s.to_string())),
+            ScalarValue::DELETED => Value::Message(false),
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Int(v) => Some(*v as f64),
+            Value::Float(v) => Some(*v),
+            Value::Enum(v, _) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+            (Value::Bytes(a), Value::Bytes(b)) => Some(a.cmp(b)),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::Message(a), Value::Message(b)) => Some(a.cmp(b)),
+            // an enum can be compared with its name
+            (Value::Enum(_, Some(name)), Value::Str(s)) => Some(name.as_str().cmp(s.as_str())),
+            (Value::Str(s), Value::Enum(_, Some(name))) => Some(s.as_str().cmp(name.as_str())),
+            _ => self.as_number()?.partial_cmp(&other.as_number()?),
+        }
+    }
+}
+
+impl Node {
+    fn eval(&self, data: &MessageData) -> io::Result<Value> {
+        match self {
+            Node::Literal(value) => Ok(value.clone()),
+            Node::Field(path, def) => {
+                let value = match data.get_field(&path.0) {
+                    Some(field) => &field.value,
+                    None => &def.default(),
+                };
+                Ok(match value {
+                    FieldValue::SCALAR(scalar) => Value::from_scalar(scalar, def),
+                    FieldValue::MESSAGE(_) => Value::Message(data.get_field(&path.0).is_some()),
+                })
+            }
+            Node::Len(path, def, count) => {
+                if *count {
+                    let (last, parent) = path.0.split_last().unwrap();
+                    let count = data.get_submessage(parent).map_or(0, |msg| msg.fields.iter().filter(|f| f.id() == last.id).count());
+                    return Ok(Value::Int(count as i128));
+                }
+                match data.get_field(&path.0).map(|f| &f.value) {
+                    Some(FieldValue::SCALAR(ScalarValue::STR(s))) => Ok(Value::Int(s.chars().count() as i128)),
+                    Some(FieldValue::SCALAR(ScalarValue::BYTES(b))) => Ok(Value::Int(b.len() as i128)),
+                    Some(FieldValue::MESSAGE(msg)) => Ok(Value::Int(msg.fields.len() as i128)),
+                    None => Ok(Value::Int(0)),
+                    _ => Err(error(format!("len() is not supported for {} field \"{}\"", def.typename(), def.name()))),
+                }
+            }
+            Node::Not(node) => match node.eval(data)? {
+                Value::Bool(v) => Ok(Value::Bool(!v)),
+                Value::Message(v) => Ok(Value::Bool(!v)),
+                other => Err(error(format!("cannot apply ! to {}", other))),
+            },
+            Node::Neg(node) => match node.eval(data)? {
+                Value::Int(v) => Ok(Value::Int(-v)),
+                Value::Float(v) => Ok(Value::Float(-v)),
+                other => Err(error(format!("cannot negate {}", other))),
+            },
+            Node::Binary(Op::And, left, right) => Ok(Value::Bool(left.eval_bool(data)? && right.eval_bool(data)?)),
+            Node::Binary(Op::Or, left, right) => Ok(Value::Bool(left.eval_bool(data)? || right.eval_bool(data)?)),
+            Node::Binary(op, left, right) => {
+                let (left, right) = (left.eval(data)?, right.eval(data)?);
+                match op {
+                    Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge => {
+                        let order = left.compare(&right).ok_or_else(|| error(format!("cannot compare {} and {}", left, right)))?;
+                        Ok(Value::Bool(match op {
+                            Op::Eq => order == Ordering::Equal,
+                            Op::Ne => order != Ordering::Equal,
+                            Op::Lt => order == Ordering::Less,
+                            Op::Le => order != Ordering::Greater,
+                            Op::Gt => order == Ordering::Greater,
+                            _ => order != Ordering::Less,
+                        }))
+                    }
+                    _ => Self::arithmetic(*op, left, right),
+                }
+            }
+        }
+    }
+
+    fn eval_bool(&self, data: &MessageData) -> io::Result<bool> {
+        match self.eval(data)? {
+            Value::Bool(v) | Value::Message(v) => Ok(v),
+            other => Err(error(format!("boolean value expected, found {}", other))),
+        }
+    }
+
+    fn arithmetic(op: Op, left: Value, right: Value) -> io::Result<Value> {
+        if let (Value::Int(a), Value::Int(b)) = (&left, &right) {
+            let res = match op {
+                Op::Add => a.checked_add(*b),
+                Op::Sub => a.checked_sub(*b),
+                Op::Mul => a.checked_mul(*b),
+                Op::Div => a.checked_div(*b),
+                _ => a.checked_rem(*b),
+            };
+            return res.map(Value::Int).ok_or_else(|| error(format!("arithmetic error in {} {:?} {}", left, op, right)));
+        }
+        if let (Value::Str(a), Value::Str(b), Op::Add) = (&left, &right, op) {
+            return Ok(Value::Str(a.clone() + b));
+        }
+        match (left.as_number(), right.as_number()) {
+            (Some(a), Some(b)) => Ok(Value::Float(match op {
+                Op::Add => a + b,
+                Op::Sub => a - b,
+                Op::Mul => a * b,
+                Op::Div => a / b,
+                _ => a % b,
+            })),
+            _ => Err(error(format!("cannot calculate {} {:?} {}", left, op, right))),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> io::Result<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied().unwrap_or('\0');
+        let two = |op| (Token::Op(op), 2);
+        let (token, len) = match c {
+            ' ' | '\t' | '\r' | '\n' => {
+                i += 1;
+                continue;
+            }
+            '(' => (Token::Open, 1),
+            ')' => (Token::Close, 1),
+            '|' if next == '|' => two(Op::Or),
+            '&' if next == '&' => two(Op::And),
+            '=' if next == '=' => two(Op::Eq),
+            '!' if next == '=' => two(Op::Ne),
+            '<' if next == '=' => two(Op::Le),
+            '>' if next == '=' => two(Op::Ge),
+            '!' => (Token::Not, 1),
+            '<' => (Token::Op(Op::Lt), 1),
+            '>' => (Token::Op(Op::Gt), 1),
+            '+' => (Token::Op(Op::Add), 1),
+            '-' => (Token::Op(Op::Sub), 1),
+            '*' => (Token::Op(Op::Mul), 1),
+            '/' => (Token::Op(Op::Div), 1),
+            '%' => (Token::Op(Op::Rem), 1),
+            '"' => {
+                let mut end = i + 1;
+                while end < chars.len() && chars[end] != '"' {
+                    if chars[end] == '\\' { end += 1; }
+                    end += 1;
+                }
+                if end >= chars.len() { return Err(error("string is not closed".into())); }
+                let quoted: String = chars[i..=end].iter().collect();
+                let value = crate::typedefs::CommonFieldProto::unquote(&quoted).unwrap();
+                (Token::Value(Value::Str(value)), end + 1 - i)
+            }
+            c if c.is_ascii_digit() => {
+                let mut end = i;
+                while end < chars.len() && (chars[end].is_ascii_alphanumeric() || chars[end] == '.') { end += 1; }
+                let number: String = chars[i..end].iter().collect();
+                let value = if let Ok(v) = number.parse::<i128>() {
+                    Value::Int(v)
+                } else if let Some(v) = number.strip_prefix("0x").and_then(|hex| i128::from_str_radix(hex, 16).ok()) {
+                    Value::Int(v)
+                } else if let Ok(v) = number.parse::<f64>() {
+                    Value::Float(v)
+                } else { return Err(error(format!("wrong number {}", number))); };
+                (Token::Value(value), end - i)
+            }
+            c if c.is_alphabetic() || c == '_' || c == '@' => {
+                let mut end = i;
+                let mut in_index = false; // spaces are allowed only inside of the index brackets
+                while end < chars.len() {
+                    let c = chars[end];
+                    match c {
+                        '[' => in_index = true,
+                        ']' => in_index = false,
+                        ' ' if in_index => {}
+                        c if c.is_alphanumeric() || "_@.".contains(c) => {}
+                        _ => break,
+                    }
+                    end += 1;
+                }
+                let path: String = chars[i..end].iter().collect();
+                let rest: String = chars[end..].iter().take(2).collect();
+                match path.as_str() {
+                    "true" => (Token::Value(Value::Bool(true)), end - i),
+                    "false" => (Token::Value(Value::Bool(false)), end - i),
+                    _ => match path.strip_suffix(".len") {
+                        Some(path) if rest == "()" => (Token::Len(path.to_string()), end + 2 - i),
+                        _ => (Token::Path(path), end - i),
+                    },
+                }
+            }
+            other => return Err(error(format!("unexpected character '{}' in expression", other))),
+        };
+        tokens.push(token);
+        i += len;
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    root: &'a MessageProto,
+    proto: &'a ProtoData,
+}
+
+impl<'a> Parser<'a> {
+    const PRIORITIES: [&'static [Op]; 5] = [&[Op::Or], &[Op::And], &[Op::Eq, Op::Ne, Op::Lt, Op::Le, Op::Gt, Op::Ge], &[Op::Add, Op::Sub], &[Op::Mul, Op::Div, Op::Rem]];
+
+    fn parse_binary(&mut self, level: usize) -> io::Result<Node> {
+        if level >= Self::PRIORITIES.len() { return self.parse_unary(); }
+        let mut left = self.parse_binary(level + 1)?;
+        while let Some(Token::Op(op)) = self.tokens.get(self.pos) {
+            let op = *op;
+            if !Self::PRIORITIES[level].contains(&op) { break; }
+            self.pos += 1;
+            let right = self.parse_binary(level + 1)?;
+            left = Node::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> io::Result<Node> {
+        let token = self.tokens.get(self.pos).cloned().ok_or_else(|| error("unexpected end of expression".into()))?;
+        self.pos += 1;
+        match token {
+            Token::Not => Ok(Node::Not(Box::new(self.parse_unary()?))),
+            Token::Op(Op::Sub) => Ok(Node::Neg(Box::new(self.parse_unary()?))),
+            Token::Open => {
+                let node = self.parse_binary(0)?;
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    return Err(error("')' expected".into()));
+                }
+                self.pos += 1;
+                Ok(node)
+            }
+            Token::Value(value) => Ok(Node::Literal(value)),
+            Token::Path(text) => match self.resolve(&text) {
+                Ok((path, def)) => Ok(Node::Field(path, def)),
+                // the name of an enum value, as the field it is compared with is shown: kind == ERROR
+                Err(_) if self.proto.enums().iter().any(|e| e.variants.iter().any(|v| v.0 == text)) => Ok(Node::Literal(Value::Str(text))),
+                Err(e) => Err(e),
+            },
+            Token::Len(text) => {
+                let (path, def) = self.resolve(&text)?;
+                let count = def.repeated() && !text.trim_end().ends_with(']');
+                Ok(Node::Len(path, def, count))
+            }
+            other => Err(error(format!("unexpected {:?} in expression", other))),
+        }
+    }
+
+    fn resolve(&self, text: &str) -> io::Result<(FieldPath, FieldProtoPtr)> {
+        let path = FieldPath::parse(text, self.root, self.proto)?;
+        let mut def: Option<FieldProtoPtr> = None;
+        for pos in &path.0 {
+            let field = match &def {
+                None => self.root.get_field(pos.id),
+                Some(parent) => self.proto.get_message_definition(&parent.typename()).and_then(|msg| msg.get_field(pos.id)),
+            };
+            def = Some(field.ok_or_else(|| error(format!("wrong path {}", text)))?);
+        }
+        Ok((path, def.ok_or_else(|| error("empty path".into()))?))
+    }
+}
+
+
+#[cfg(test)]
+mod expr_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::typedefs::PbReader;
+
+    const PROTO: &str = "enum E { A = 0; B = 1; }\nmessage Root { string name = 1; Settings settings = 2; repeated Item items = 3; }\nmessage Settings { int32 timeout = 1; double ratio = 2; E kind = 3; }\nmessage Item { uint64 id = 1; bytes raw = 2; }";
+
+    fn check(script: &str, expr: &str) -> io::Result<bool> {
+        let proto = ProtoData::new(PROTO).unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut read = PbReader::new([].as_slice());
+        let mut data = MessageData::new(&mut read, &proto, root_msg.clone(), &mut 0).unwrap();
+        apply_script(&mut data, &proto, script).unwrap();
+        Expr::parse(expr, &root_msg, &proto)?.check(&data)
+    }
+
+    #[test]
+    fn compare_fields() {
+        let script = "set settings.timeout = 30\nset items[0].id = 5\nset items[1].raw = 01 02\nset name = \"abc\"\nset settings.kind = B";
+        assert!(check(script, "settings.timeout > 0 && items.len() >= 1").unwrap());
+        assert!(check(script, "settings.timeout == 30 && items.len() == 2 && items[1].raw.len() == 2").unwrap());
+        assert!(!check(script, "settings.timeout < 10 || name != \"abc\"").unwrap());
+        assert!(check(script, "settings.kind == \"B\" && settings.kind == 1").unwrap());
+        assert!(check(script, "settings.kind == B && settings.kind != A").unwrap());
+        assert!(check(script, "(items[0].id + 1) * 2 == 12 && -settings.timeout < 0").unwrap());
+        assert!(check(script, "settings.ratio == 0 && settings.ratio >= 0.0 && name.len() == 3").unwrap());
+        assert!(check(script, "settings && !(items[1].id > 0)").unwrap());
+        // not set fields have default values
+        assert!(check("", "settings.timeout == 0 && name == \"\" && items.len() == 0 && !settings").unwrap());
+    }
+
+    #[test]
+    fn errors() {
+        let error = |expr| check("", expr).unwrap_err().to_string();
+        assert_eq!(error("setings.timeout > 0"), "field \"setings\" not found in message Root");
+        assert_eq!(error("settings.timeout"), "boolean value expected, found 0");
+        assert_eq!(error("name > 1"), "cannot compare \"\" and 1");
+        assert_eq!(error("(name == \"\""), "')' expected");
+        assert_eq!(error("name == \"a"), "string is not closed");
+        assert_eq!(error("settings.timeout > 0 0"), "unexpected Value(Int(0)) in expression");
+        assert_eq!(error("settings.timeout / 0 == 1"), "arithmetic error in 0 Div 0");
+    }
+}
@@ -0,0 +1,121 @@
+use std::io;
+use crate::proto::{MessageProtoPtr, ProtoData};
+use crate::typedefs::{PbReader, PbReaderTrait, UnknownFieldDefinition};
+use crate::overview::size_text;
+use crate::wire::{ScalarValue, WT_LEN};
+
+// values of the data read as a message type: fields of the type, unknown ones and ones written
+// with another wire type than the field has; the bytes of the top-level values of the type with their tags
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct Counts {
+    pub known: usize,
+    pub unknown: usize,
+    pub mismatched: usize,
+    pub known_size: usize,
+}
+
+// how well the data is read as a message type, the error instead of the counts if it cannot be read at all
+pub struct Fit {
+    pub def: MessageProtoPtr,
+    pub counts: Result<Counts, String>,
+    pub size: usize,
+}
+
+impl Fit {
+    pub fn new(bytes: &[u8], proto: &ProtoData, def: MessageProtoPtr) -> Fit {
+        let mut counts = Counts::default();
+        let res = scan(bytes, proto, &def, &mut counts).map(|_| counts).map_err(|e| e.to_string());
+        Fit { def, counts: res, size: bytes.len() }
+    }
+
+    // the line of the choice: "Order (5 fields): 12 known, 1 unknown, 2 of another wire type, 230 B of 240 B"
+    pub fn text(&self) -> String {
+        let count = self.def.fields.len();
+        let name = format!("{} ({} field{})", self.def.name, count, if count == 1 { "" } else { "s" });
+        match &self.counts {
+            Ok(counts) => {
+                let issues = if counts.unknown + counts.mismatched == 0 { "no issues".to_string() } else {
+                    format!("{} unknown, {} of another wire type", counts.unknown, counts.mismatched)
+                };
+                format!("{}: {} known, {}, {} of {}", name, counts.known, issues, size_text(counts.known_size), size_text(self.size))
+            }
+            Err(e) => format!("{}: not readable, {}", name, e),
+        }
+    }
+}
+
+// the values are only split by their tags, not decoded, so any wire type is taken by any field;
+// a message field with the content not readable as the message counts as of another wire type
+fn scan(bytes: &[u8], proto: &ProtoData, def: &MessageProtoPtr, counts: &mut Counts) -> io::Result<()> {
+    let mut limit = bytes.len() as u32;
+    let mut reader = PbReader::new(bytes);
+    while limit > 0 {
+        let start = reader.pos();
+        let tag = reader.read_tag(&mut limit)?;
+        let wire_type = tag.wire_type();
+        let id = tag.field_id();
+        let ScalarValue::UNKNOWN(_, content) = UnknownFieldDefinition::read_unknown(&mut reader, &mut limit, tag)? else { unreachable!() };
+        let Some(field) = def.get_field(id) else {
+            counts.unknown += 1;
+            continue;
+        };
+        let known = if field.is_message() {
+            let mut nested = Counts::default();
+            match proto.get_message_definition(&field.typename()) {
+                Some(msg) if wire_type == WT_LEN && scan(&content, proto, &msg, &mut nested).is_ok() => {
+                    counts.known += nested.known + 1;
+                    counts.unknown += nested.unknown;
+                    counts.mismatched += nested.mismatched;
+                    true
+                }
+                _ => false,
+            }
+        } else {
+            wire_type == field.wire_type() || (wire_type == WT_LEN && field.repeated())
+        };
+        if !known {
+            counts.mismatched += 1;
+        } else {
+            if !field.is_message() { counts.known += 1; }
+            counts.known_size += reader.pos() - start;
+        }
+    }
+    Ok(())
+}
+
+// the candidates by how cleanly the data is read: readable first, then by the count of issues,
+// then by the count of known fields; the top-level messages if there are several, otherwise all of them
+pub fn rank_roots(bytes: &[u8], proto: &ProtoData) -> Vec<Fit> {
+    let mut candidates = proto.root_candidates();
+    if candidates.len() < 2 {
+        candidates = proto.messages().iter().filter(|m| !m.name.contains(',')).cloned().collect();
+    }
+    let mut res: Vec<Fit> = candidates.into_iter().map(|def| Fit::new(bytes, proto, def)).collect();
+    res.sort_by_key(|fit| match fit.counts {
+        Ok(counts) => (0, counts.unknown + counts.mismatched, usize::MAX - counts.known_size, usize::MAX - counts.known),
+        Err(_) => (1, 0, 0, 0),
+    });
+    res
+}
+
+
+#[cfg(test)]
+mod fingerprint_tests {
+    use super::*;
+
+    #[test]
+    fn ranked() {
+        let proto = ProtoData::new("message A { int32 id = 1; string name = 2; }\nmessage B { string id = 1; }\nmessage C { int32 id = 1; }\nmessage D { fixed32 id = 1; }").unwrap().finalize().unwrap();
+        // id: 5, name: "x"
+        let bytes = [0x08, 5, 0x12, 1, b'x'];
+        assert!(Fit::new(&[0x0A, 5, 1], &proto, proto.get_message_definition("A").unwrap()).counts.is_err());
+        let fits = rank_roots(&bytes, &proto);
+        let lines: Vec<String> = fits.iter().map(Fit::text).collect();
+        assert_eq!(lines, [
+            "A (2 fields): 2 known, no issues, 5 B of 5 B",
+            "C (1 field): 1 known, 1 unknown, 0 of another wire type, 2 B of 5 B",
+            "B (1 field): 0 known, 1 unknown, 1 of another wire type, 0 B of 5 B",
+            "D (1 field): 0 known, 1 unknown, 1 of another wire type, 0 B of 5 B",
+        ]);
+    }
+}
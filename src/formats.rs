@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+use crate::render::Renderer;
+
+// the formats chosen for the fields of each data file, kept next to the settings file for the next sessions:
+//   /home/u/data/orders.pb	items.created=timestamp
+// the fields are paths without indexes as for --render
+pub const FORMATS_FILE_NAME: &str = "protoedit.formats";
+
+// how the values of one field are shown: an integer in hex or as a time since 1970,
+// a Timestamp message as its date, a string or bytes value by a renderer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldFormat {
+    Hex,
+    Timestamp,
+    Render(Renderer),
+}
+
+const INTEGERS: [&str; 10] = ["int32", "int64", "uint32", "uint64", "sint32", "sint64", "fixed32", "fixed64", "sfixed32", "sfixed64"];
+
+impl FieldFormat {
+    pub fn from_name(name: &str) -> Option<FieldFormat> {
+        match name {
+            "hex" => Some(FieldFormat::Hex),
+            "timestamp" => Some(FieldFormat::Timestamp),
+            _ => Renderer::from_name(name).map(FieldFormat::Render),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FieldFormat::Hex => "hex",
+            FieldFormat::Timestamp => "timestamp",
+            FieldFormat::Render(renderer) => renderer.name(),
+        }
+    }
+
+    // the names of the formats for a field of the type, the first one ("dec" or "default") shows it as by default
+    pub fn names_for(typename: &str) -> Vec<&'static str> {
+        let integer = INTEGERS.contains(&typename);
+        let mut names = vec![if integer { "dec" } else { "default" }];
+        if integer { names.extend(["hex", "timestamp"]); }
+        if typename == "Timestamp" { names.push("timestamp"); }
+        for renderer in [Renderer::Utf8, Renderer::JsonPretty, Renderer::Uuid, Renderer::Base64, Renderer::IpAddress] {
+            if renderer.applies_to(typename) { names.push(renderer.name()); }
+        }
+        names
+    }
+}
+
+pub fn default_path(vars: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    crate::config::default_path(vars).map(|path| path.with_file_name(FORMATS_FILE_NAME))
+}
+
+// the formats of the data file, lines with unknown formats are skipped
+pub fn read(text: &str, data_file: &str) -> Vec<(String, FieldFormat)> {
+    text.lines().filter_map(|line| {
+        let (file, rule) = line.split_once('\t')?;
+        let (field, name) = rule.split_once('=')?;
+        (file == data_file).then_some(())?;
+        Some((field.to_string(), FieldFormat::from_name(name)?))
+    }).collect()
+}
+
+// the text with the line of the field of the data file replaced, added or removed (None), other lines are kept
+pub fn with_format(text: &str, data_file: &str, field: &str, format: Option<FieldFormat>) -> String {
+    let key = format!("{}\t{}=", data_file, field);
+    let mut res: String = text.lines().filter(|line| !line.starts_with(&key)).map(|line| format!("{}\n", line)).collect();
+    if let Some(format) = format {
+        res += &format!("{}{}\n", key, format.name());
+    }
+    res
+}
+
+
+#[cfg(test)]
+mod formats_tests {
+    use super::*;
+
+    #[test]
+    fn formats_of_files() {
+        let text = with_format("", "/d/a.pb", "items.created", Some(FieldFormat::Timestamp));
+        let text = with_format(&text, "/d/b.pb", "id", Some(FieldFormat::Render(Renderer::Uuid)));
+        let text = with_format(&text, "/d/a.pb", "flags", Some(FieldFormat::Hex));
+        assert_eq!(read(&text, "/d/a.pb"), [("items.created".to_string(), FieldFormat::Timestamp), ("flags".into(), FieldFormat::Hex)]);
+        assert_eq!(read(&text, "/d/b.pb"), [("id".to_string(), FieldFormat::Render(Renderer::Uuid))]);
+
+        let text = with_format(&text, "/d/a.pb", "items.created", None);
+        let text = with_format(&text, "/d/a.pb", "flags", Some(FieldFormat::Timestamp));
+        assert_eq!(text, "/d/b.pb\tid=uuid\n/d/a.pb\tflags=timestamp\n");
+        assert!(read("/d/a.pb\tflags=octal\nbroken line\n", "/d/a.pb").is_empty());
+
+        assert_eq!(FieldFormat::names_for("int64"), ["dec", "hex", "timestamp"]);
+        assert_eq!(FieldFormat::names_for("string"), ["default", "json-pretty"]);
+        assert_eq!(FieldFormat::from_name("base64"), Some(FieldFormat::Render(Renderer::Base64)));
+    }
+}
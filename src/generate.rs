@@ -0,0 +1,160 @@
+use std::sync::Arc;
+use crate::proto::{FieldProtoPtr, MessageProtoPtr, ProtoData};
+use crate::wire::{FieldData, FieldValue, MessageData, ScalarValue};
+
+// messages nested deeper are not set, recursive types end there
+const MAX_DEPTH: usize = 4;
+// the most values of a repeated field below the root
+const MAX_REPEATED: u64 = 3;
+
+const WORDS: [&str; 16] = [
+    "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel",
+    "india", "juliett", "kilo", "lima", "mike", "november", "oscar", "papa",
+];
+
+// xorshift64* seeded through splitmix64, the same seed gives the same data on every platform
+pub struct Random(u64);
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        // the state must not be zero
+        Random((z ^ (z >> 31)).max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 { self.next() % n }
+
+    fn signed(&mut self, max: i64) -> i64 { self.below(2 * max as u64 + 1) as i64 - max }
+}
+
+// a document of the root message with every field set: `count` values of each repeated field of the root,
+// up to MAX_REPEATED of the nested ones, one member of each oneof and the messages down to MAX_DEPTH levels
+pub fn document(proto: &ProtoData, root: MessageProtoPtr, count: usize, random: &mut Random) -> MessageData {
+    let mut data = MessageData { def: root, fields: vec![], packed: vec![] };
+    fill(&mut data, proto, Some(count), 0, random);
+    data
+}
+
+fn fill(msg: &mut MessageData, proto: &ProtoData, count: Option<usize>, depth: usize, random: &mut Random) {
+    let defs = msg.def.fields.clone();
+    // the member set in each oneof
+    let mut oneofs: Vec<(&String, i32)> = vec![];
+    for def in &defs {
+        let Some(name) = def.oneof_name() else { continue; };
+        if oneofs.iter().any(|(oneof, _)| *oneof == name) { continue; }
+        let members: Vec<&FieldProtoPtr> = defs.iter().filter(|other| other.oneof_name().as_ref() == Some(name)).collect();
+        oneofs.push((name, members[random.below(members.len() as u64) as usize].id()));
+    }
+    for def in &defs {
+        if def.id() == 0 { continue; }
+        if let Some(name) = def.oneof_name() {
+            if !oneofs.iter().any(|(oneof, id)| *oneof == name && *id == def.id()) { continue; }
+        }
+        let amount = match (def.repeated(), count) {
+            (false, _) => 1,
+            (true, Some(count)) => count,
+            (true, None) => random.below(MAX_REPEATED + 1) as usize,
+        };
+        for _ in 0..amount {
+            let Some(value) = value(def, proto, depth, random) else { break; };
+            msg.fields.push(FieldData { def: def.clone(), pos: usize::MAX, value, wire: None });
+        }
+        // repeated numbers are packed as proto3 writes them by default
+        if amount > 0 && MessageData::packable(def) { msg.packed.push(def.id()); }
+    }
+}
+
+fn value(def: &FieldProtoPtr, proto: &ProtoData, depth: usize, random: &mut Random) -> Option<FieldValue> {
+    if def.is_message() {
+        if depth + 1 >= MAX_DEPTH { return None; }
+        let FieldValue::MESSAGE(mut msg) = def.zero() else { return None; };
+        fill(Arc::make_mut(&mut msg), proto, None, depth + 1, random);
+        return Some(FieldValue::MESSAGE(msg));
+    }
+    let scalar = match def.typename().as_str() {
+        "int32" => ScalarValue::I32(random.signed(1000) as i32),
+        "sint32" => ScalarValue::S32(random.signed(1000) as i32),
+        "sfixed32" => ScalarValue::SF32(random.signed(1000) as i32),
+        "uint32" => ScalarValue::U32(random.below(1000) as u32),
+        "fixed32" => ScalarValue::UF32(random.below(1000) as u32),
+        "int64" => ScalarValue::I64(random.signed(1_000_000)),
+        "sint64" => ScalarValue::S64(random.signed(1_000_000)),
+        "sfixed64" => ScalarValue::SF64(random.signed(1_000_000)),
+        "uint64" => ScalarValue::U64(random.below(1_000_000)),
+        "fixed64" => ScalarValue::UF64(random.below(1_000_000)),
+        // two decimal places, as typed by a person
+        "float" => ScalarValue::F32(random.signed(100_000) as f32 / 100.0),
+        "double" => ScalarValue::F64(random.signed(100_000) as f64 / 100.0),
+        "bool" => ScalarValue::BOOL(random.below(2) == 1),
+        "string" => {
+            let words: Vec<&str> = (0..1 + random.below(3)).map(|_| WORDS[random.below(WORDS.len() as u64) as usize]).collect();
+            ScalarValue::STR(words.join(" ").into())
+        }
+        "bytes" => ScalarValue::BYTES((0..1 + random.below(16)).map(|_| random.next() as u8).collect::<Vec<u8>>().into()),
+        typename => {
+            // an enum takes one of its variants, a type not found in the proto files is not set
+            let variants = &proto.get_enum_definition(typename)?.variants;
+            ScalarValue::ENUM(variants.get(random.below(variants.len().max(1) as u64) as usize).map_or(0, |variant| variant.1))
+        }
+    };
+    Some(FieldValue::SCALAR(scalar))
+}
+
+
+#[cfg(test)]
+mod generate_tests {
+    use super::*;
+    use crate::typedefs::PbReader;
+
+    const PROTO: &str = "enum E { A = 0; B = 5; }\n\
+        message M { repeated Item items = 1; M next = 2; oneof kind { string s = 3; bytes b = 4; } map<string, int32> tags = 5; }\n\
+        message Item { int32 n = 1; E e = 2; repeated double d = 3; Item child = 4; }";
+
+    fn written(seed: u64) -> (ProtoData, Vec<u8>) {
+        let proto = ProtoData::new(PROTO).unwrap().finalize().unwrap();
+        let root = proto.get_message_definition("M").unwrap();
+        let data = document(&proto, root.clone(), 5, &mut Random::new(seed));
+        let mut bytes = vec![];
+        data.write(&mut bytes, &proto, root).unwrap();
+        (proto, bytes)
+    }
+
+    #[test]
+    fn random_document() {
+        let (proto, bytes) = written(7);
+        assert_eq!(bytes, written(7).1);
+        assert_ne!(bytes, written(8).1);
+
+        // read back as valid data of the root
+        let root = proto.get_message_definition("M").unwrap();
+        let data = MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, root, &mut (bytes.len() as u32)).unwrap();
+        let items: Vec<&FieldData> = data.fields.iter().filter(|f| f.def.id() == 1).collect();
+        assert_eq!(items.len(), 5);
+        for item in items {
+            let FieldValue::MESSAGE(item) = &item.value else { panic!() };
+            let e = item.fields.iter().find(|f| f.def.id() == 2).unwrap();
+            assert!(matches!(e.value, FieldValue::SCALAR(ScalarValue::ENUM(0 | 5))));
+            assert!(item.fields.iter().filter(|f| f.def.id() == 3).count() <= MAX_REPEATED as usize);
+        }
+        // one member of the oneof
+        assert_eq!(data.fields.iter().filter(|f| f.def.id() == 3 || f.def.id() == 4).count(), 1);
+
+        // the recursive message ends at the depth limit
+        let mut depth = 0;
+        let mut msg = &data;
+        while let Some(FieldValue::MESSAGE(next)) = msg.fields.iter().find(|f| f.def.id() == 2).map(|f| &f.value) {
+            msg = next;
+            depth += 1;
+        }
+        assert_eq!(depth, MAX_DEPTH - 1);
+    }
+}
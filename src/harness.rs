@@ -0,0 +1,111 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crate::view::{ScreenLine, WIDE_TAIL};
+
+// keys of a script as in the README and the help screen: `Down Down F5 Ctrl+E "12" Enter`,
+// a quoted text is typed char by char, `Space` is a space, # starts a comment up to the end of the line
+pub fn parse_keys(script: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut res = vec![];
+    for line in script.lines() {
+        let mut rest = line.trim_start();
+        while !rest.is_empty() && !rest.starts_with('#') {
+            if let Some(quoted) = rest.strip_prefix('"') {
+                let end = quoted.find('"').ok_or_else(|| format!("no closing quote: \"{}", quoted))?;
+                res.extend(quoted[..end].chars().map(|c| press(KeyCode::Char(c), KeyModifiers::NONE)));
+                rest = quoted[end + 1..].trim_start();
+                continue;
+            }
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            res.push(parse_key(&rest[..end])?);
+            rest = rest[end..].trim_start();
+        }
+    }
+    Ok(res)
+}
+
+fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new_with_kind(code, modifiers, KeyEventKind::Press)
+}
+
+// Ctrl+E, Shift+F4, Alt+Down, PgDn, O
+fn parse_key(name: &str) -> Result<KeyEvent, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    loop {
+        // a single + is the key itself
+        let Some((prefix, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) else { break; };
+        modifiers |= match prefix {
+            "Ctrl" => KeyModifiers::CONTROL,
+            "Alt" => KeyModifiers::ALT,
+            "Shift" => KeyModifiers::SHIFT,
+            _ => return Err(format!("unknown modifier: {}", name)),
+        };
+        rest = key;
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next(), chars.next()) {
+        // the char is shifted already, Ctrl+E and Alt+W are the lower case ones
+        (Some(c), None) if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => KeyCode::Char(c.to_ascii_lowercase()),
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match rest {
+            "Space" => KeyCode::Char(' '),
+            "Enter" => KeyCode::Enter,
+            "Esc" => KeyCode::Esc,
+            "Tab" => KeyCode::Tab,
+            "BackTab" => KeyCode::BackTab,
+            "Backspace" => KeyCode::Backspace,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PgUp" => KeyCode::PageUp,
+            "PgDn" => KeyCode::PageDown,
+            "Del" => KeyCode::Delete,
+            "Ins" => KeyCode::Insert,
+            _ => match rest.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n @ 1..=12) => KeyCode::F(n),
+                _ => return Err(format!("unknown key: {}", name)),
+            },
+        },
+    };
+    Ok(press(code, modifiers))
+}
+
+// the text of each line followed by the letters of its styles, one for each column (TextStyle::letter)
+pub fn styled_lines(lines: &[ScreenLine]) -> Vec<String> {
+    let mut res = vec![];
+    for line in lines {
+        res.push(line.0.iter().map(|(c, _)| *c).filter(|c| *c != WIDE_TAIL).collect());
+        // a wide char takes two columns, its style too
+        res.push(line.0.iter().map(|(_, style)| style.letter()).collect());
+    }
+    res
+}
+
+
+#[cfg(test)]
+mod harness_tests {
+    use super::*;
+    use crate::keymap::KeyBinding;
+    use crate::view::TextStyle;
+
+    #[test]
+    fn keys() {
+        let keys = parse_keys("Down Shift+F4 Ctrl+E \"a b\" Enter # the value\nAlt+1 PgDn + Space").unwrap();
+        let names: Vec<String> = keys.iter().map(|key| KeyBinding::new(key.code, key.modifiers).to_string()).collect();
+        assert_eq!(names, ["Down", "Shift+F4", "Ctrl+E", "a", " ", "b", "Enter", "Alt+1", "PgDn", "+", " "]);
+        assert_eq!(keys[2].code, KeyCode::Char('e'));
+        assert_eq!(parse_keys("Ctrl+F13"), Err("unknown key: Ctrl+F13".to_string()));
+        assert_eq!(parse_keys("Meta+X"), Err("unknown modifier: Meta+X".to_string()));
+        assert!(parse_keys("\"abc").is_err());
+    }
+
+    #[test]
+    fn styles() {
+        let mut line = ScreenLine::new(8);
+        line.add_string(" f1:".into(), TextStyle::FieldName);
+        line.add_string(" 中".into(), TextStyle::SelectedValue);
+        assert_eq!(styled_lines(&[line]), [" f1: 中", "nnnnVVV"]);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::expr::Expr;
+use crate::wire::{FieldPath, FieldValue, MessageData};
+
+// a condition on the values of a message type: `price > 1000` for the items of an order
+pub struct Rule {
+    pub type_name: String,
+    pub expr: Expr,
+}
+
+// the values of a message type that match any of its conditions are highlighted with all their lines;
+// a condition that cannot be evaluated for a value does not match it
+#[derive(Default)]
+pub struct Rules(pub Vec<Rule>);
+
+impl Rules {
+    pub fn matches(&self, msg: &MessageData) -> bool {
+        self.0.iter().any(|rule| rule.type_name == msg.def.name && rule.expr.check(msg).unwrap_or(false))
+    }
+
+    // the value at the path or a message containing it matches
+    pub fn contains(&self, data: &MessageData, path: &FieldPath) -> bool {
+        if self.0.is_empty() { return false; }
+        if self.matches(data) { return true; }
+        (1..=path.0.len()).any(|len| match data.get_field(&path.0[..len]).map(|field| &field.value) {
+            Some(FieldValue::MESSAGE(msg)) => self.matches(msg),
+            _ => false,
+        })
+    }
+
+    // the count of the matching values of the type and of all of them in the data
+    pub fn count(&self, data: &MessageData, type_name: &str) -> (usize, usize) {
+        let mut counts = if data.def.name == type_name { (self.matches(data) as usize, 1) } else { (0, 0) };
+        for field in &data.fields {
+            if let FieldValue::MESSAGE(msg) = &field.value {
+                let (matched, total) = self.count(msg, type_name);
+                counts.0 += matched;
+                counts.1 += total;
+            }
+        }
+        counts
+    }
+}
+
+
+#[cfg(test)]
+mod highlight_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::proto::ProtoData;
+    use crate::typedefs::PbReader;
+
+    #[test]
+    fn matching_values() {
+        let proto = ProtoData::new("enum S { OK = 0; ERROR = 1; }\nmessage Order { repeated Item items = 1; }\n\
+            message Item { int32 price = 1; S status = 2; Item part = 3; }").unwrap().finalize().unwrap();
+        let root = proto.get_message_definition("Order").unwrap();
+        let item = proto.get_message_definition("Item").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root, &mut 0).unwrap();
+        apply_script(&mut data, &proto, "set items[0].price = 5\nset items[1].price = 2000\nset items[2].status = ERROR\nset items[2].part.price = 3000").unwrap();
+
+        let mut rules = Rules::default();
+        rules.0.push(Rule { type_name: "Item".into(), expr: Expr::parse("price > 1000", &item, &proto).unwrap() });
+        assert_eq!(rules.count(&data, "Item"), (2, 4));
+        rules.0.push(Rule { type_name: "Item".into(), expr: Expr::parse("status == ERROR", &item, &proto).unwrap() });
+        assert_eq!(rules.count(&data, "Item"), (3, 4));
+
+        // the fields of a matching value are highlighted with it
+        let path = |text: &str| FieldPath::parse(text, &data.def, &proto).unwrap();
+        assert!(!rules.contains(&data, &path("items[0].price")));
+        assert!(rules.contains(&data, &path("items[1].price")));
+        assert!(rules.contains(&data, &path("items[2].part")));
+    }
+}
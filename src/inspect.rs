@@ -0,0 +1,184 @@
+use std::io;
+use crate::proto::ProtoData;
+use crate::typedefs::{PbReader, PbReaderTrait, UnknownFieldDefinition};
+use crate::wire::{wire_type_name, FieldData, FieldValue, ScalarValue, WT_I32, WT_I64, WT_LEN, WT_SGROUP, WT_VARINT};
+
+// the longest text or bytes shown for a length-delimited value
+const SHOWN_LEN: usize = 24;
+
+fn hex(bytes: &[u8]) -> String {
+    let shown = ScalarValue::BYTES(bytes[..bytes.len().min(SHOWN_LEN)].to_vec().into()).to_string();
+    if bytes.len() > SHOWN_LEN { format!("{} …", shown) } else { shown }
+}
+
+// the wire bytes of the field as it is saved and the value read as each scalar type of its wire type,
+// the declared type is marked with *; a packed value is shown as it is encoded alone
+pub fn lines(field: &FieldData, proto: &ProtoData) -> io::Result<Vec<String>> {
+    let mut bytes = vec![];
+    field.write(&mut bytes, proto)?;
+    let mut limit = bytes.len() as u32;
+    let mut reader = PbReader::new(bytes.as_slice());
+    let tag = reader.read_tag(&mut limit)?;
+    let mut res = vec![
+        format!("  wire bytes  {}", hex(&bytes)),
+        format!("  tag         field {}, {}", tag.field_id(), wire_type_name(tag.wire_type())),
+        String::new(),
+    ];
+    let types = match tag.wire_type() {
+        WT_VARINT => varint_types(reader.read_varint(&mut limit)? as u64, field),
+        WT_I32 => fixed32_types(reader.read_len(4, &mut limit)?.try_into().unwrap()),
+        WT_I64 => fixed64_types(reader.read_len(8, &mut limit)?.try_into().unwrap()),
+        WT_LEN => len_types(&reader.read_len(tag.length, &mut limit)?),
+        WT_SGROUP => match &field.value {
+            FieldValue::SCALAR(ScalarValue::UNKNOWN(_, content)) => vec![("group", format!("{} bytes: {}", content.len(), hex(content)))],
+            _ => vec![],
+        },
+        _ => vec![],
+    };
+    let declared = match &field.value {
+        FieldValue::MESSAGE(_) => "message".to_string(),
+        FieldValue::SCALAR(ScalarValue::ENUM(_)) => "enum".to_string(),
+        _ => field.def.typename(),
+    };
+    for (name, value) in types {
+        res.push(format!("{} {:<14} {}", if name == declared { "*" } else { " " }, name, value));
+    }
+    Ok(res)
+}
+
+fn varint_types(value: u64, field: &FieldData) -> Vec<(&'static str, String)> {
+    let truncated = |text: String, fits: bool| if fits { text } else { format!("{} (truncated)", text) };
+    let int32 = value as i64 as i32;
+    let int32_fits = int32 as i64 == value as i64;
+    let zigzag32 = ((value as u32 >> 1) as i32) ^ -((value & 1) as i32);
+    let enum_name = match field.def.get_enum_name_by_index(int32) {
+        Some(name) => format!("{} {}", int32, name),
+        None => int32.to_string(),
+    };
+    vec![
+        ("int32", truncated(int32.to_string(), int32_fits)),
+        ("int64", (value as i64).to_string()),
+        ("uint32", truncated((value as u32).to_string(), value <= u32::MAX as u64)),
+        ("uint64", value.to_string()),
+        ("sint32", truncated(zigzag32.to_string(), value <= u32::MAX as u64)),
+        ("sint64", (((value >> 1) as i64) ^ -((value & 1) as i64)).to_string()),
+        ("bool", if value > 1 { format!("{} (not 0 or 1)", value != 0) } else { (value != 0).to_string() }),
+        ("enum", truncated(enum_name, int32_fits)),
+    ]
+}
+
+fn fixed32_types(bytes: [u8; 4]) -> Vec<(&'static str, String)> {
+    vec![
+        ("fixed32", u32::from_le_bytes(bytes).to_string()),
+        ("sfixed32", i32::from_le_bytes(bytes).to_string()),
+        ("float", ScalarValue::F32(f32::from_le_bytes(bytes)).to_string()),
+    ]
+}
+
+fn fixed64_types(bytes: [u8; 8]) -> Vec<(&'static str, String)> {
+    vec![
+        ("fixed64", u64::from_le_bytes(bytes).to_string()),
+        ("sfixed64", i64::from_le_bytes(bytes).to_string()),
+        ("double", ScalarValue::F64(f64::from_le_bytes(bytes)).to_string()),
+    ]
+}
+
+fn len_types(bytes: &[u8]) -> Vec<(&'static str, String)> {
+    let text: String = String::from_utf8(bytes.to_vec()).map_or("not UTF-8".into(), |text| {
+        let shown: String = text.chars().take(SHOWN_LEN).collect();
+        if shown.len() < text.len() { format!("{:?} …", shown) } else { format!("{:?}", shown) }
+    });
+    let message = match count_fields(bytes) {
+        Some(count) => format!("{} field{}", count, if count == 1 { "" } else { "s" }),
+        None => "not a message".into(),
+    };
+    let varints = match count_varints(bytes) {
+        Some(count) => format!("{} values", count),
+        None => "not varints".into(),
+    };
+    let fixed = |size: usize| if bytes.len() % size == 0 { format!("{} values", bytes.len() / size) } else { format!("not a multiple of {} bytes", size) };
+    vec![
+        ("string", text),
+        ("bytes", format!("{} bytes: {}", bytes.len(), hex(bytes))),
+        ("message", message),
+        ("packed varints", varints),
+        ("packed fixed32", fixed(4)),
+        ("packed fixed64", fixed(8)),
+    ]
+}
+
+// the count of the fields if the bytes are read as a message up to the end
+fn count_fields(bytes: &[u8]) -> Option<usize> {
+    let mut limit = bytes.len() as u32;
+    let mut reader = PbReader::new(bytes);
+    let mut count = 0;
+    while limit > 0 {
+        let tag = reader.read_tag(&mut limit).ok().filter(|tag| tag.field_id() > 0)?;
+        UnknownFieldDefinition::read_unknown(&mut reader, &mut limit, tag).ok()?;
+        count += 1;
+    }
+    Some(count)
+}
+
+fn count_varints(bytes: &[u8]) -> Option<usize> {
+    let mut limit = bytes.len() as u32;
+    let mut reader = PbReader::new(bytes);
+    let mut count = 0;
+    while limit > 0 {
+        reader.read_varint(&mut limit).ok()?;
+        count += 1;
+    }
+    Some(count)
+}
+
+
+#[cfg(test)]
+mod inspect_tests {
+    use super::*;
+    use crate::wire::MessageData;
+
+    fn field_lines(proto: &str, bytes: &[u8]) -> Vec<String> {
+        let proto = ProtoData::new(proto).unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let data = MessageData::new(&mut PbReader::new(bytes), &proto, root_msg, &mut (bytes.len() as u32)).unwrap();
+        lines(&data.fields[0], &proto).unwrap().into_iter().map(|line| line.trim_end().to_string()).collect()
+    }
+
+    #[test]
+    fn varint() {
+        // -3 as sint32 is 5
+        assert_eq!(field_lines("message M { int32 n = 1; }", &[0x08, 0x05]), [
+            "  wire bytes  08 05",
+            "  tag         field 1, VARINT",
+            "",
+            "* int32          5",
+            "  int64          5",
+            "  uint32         5",
+            "  uint64         5",
+            "  sint32         -3",
+            "  sint64         -3",
+            "  bool           true (not 0 or 1)",
+            "  enum           5",
+        ]);
+        let lines = field_lines("message M { int64 n = 1; }", &[0x08, 0x80, 0x80, 0x80, 0x80, 0x10]);
+        assert_eq!(lines[3], "  int32          0 (truncated)");
+        assert_eq!(lines[4], "* int64          4294967296");
+    }
+
+    #[test]
+    fn fixed_and_len() {
+        assert_eq!(field_lines("message M { float f = 1; }", &[0x0D, 0x00, 0x00, 0x80, 0x3F])[3..], [
+            "  fixed32        1065353216",
+            "  sfixed32       1065353216",
+            "* float          1",
+        ]);
+        assert_eq!(field_lines("message M { string s = 1; }", &[0x0A, 0x02, 0x08, 0x01])[3..], [
+            "* string         \"\\u{8}\\u{1}\"",
+            "  bytes          2 bytes: 08 01",
+            "  message        1 field",
+            "  packed varints 2 values",
+            "  packed fixed32 not a multiple of 4 bytes",
+            "  packed fixed64 not a multiple of 8 bytes",
+        ]);
+    }
+}
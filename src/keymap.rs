@@ -0,0 +1,547 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+// what the application does in response to a key, independent of the key itself
+// (the help screen lists the actions in this order)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    Quit,
+    NextFieldOrder,
+    PrevFieldOrder,
+    CollapsedToggle,
+    ExpandAll,
+    CollapseAll,
+    CollapseToDepth(usize),
+    CommentsVisibility,
+    LineUp,
+    LineDown,
+    SiblingUp,
+    SiblingDown,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    ResetSelection,
+    ScrollToBottom,
+    Left,
+    Right,
+    Back,
+    DrillDown,
+    Reload,
+    DeleteData,
+    InsertData,
+    RangeUp,
+    RangeDown,
+    Copy,
+    Undo,
+    Redo,
+    ProtectToggle,
+    Save,
+    SaveAs,
+    GoToPath,
+    GoToRecord,
+    Breadcrumbs,
+    WrapToggle,
+    WrapAllToggle,
+    GroupFirst,
+    GroupLast,
+    NextSet,
+    PrevSet,
+    Stats,
+    HexDump,
+    ReadOnlyToggle,
+    EditValue,
+    PackedToggle,
+    EncodingToggle,
+    WatchToggle,
+    Highlight,
+    PinToggle,
+    GoToOffset,
+    SchemaAudit,
+    ReloadSchema,
+    DecodeBytes,
+    MacroRecord,
+    MacroReplay,
+    MacroReplayAll,
+    SetDefault,
+    ClearField,
+    OffsetsToggle,
+    SizesToggle,
+    BinaryToggle,
+    Columns,
+    FieldFormat,
+    MergeGroups,
+    Overview,
+    Schema,
+    History,
+    MergeFile,
+    ExportBytes,
+    ImportBytes,
+    Replace,
+    SetSiblings,
+    EscapesToggle,
+    Search,
+    Inspect,
+    SidebarToggle,
+    DetailPane,
+    PaneWider,
+    PaneNarrower,
+    UnknownEnums,
+    PerfOverlay,
+    Help,
+}
+
+// the groups of the help screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Navigation,
+    View,
+    Edit,
+    File,
+}
+
+// set of key bindings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum KeyProfile {
+    // function keys, as described in the README
+    Fkeys,
+    // function keys and letter chords for terminals that intercept F4..F6 and Insert
+    Letters,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+pub struct KeyMap {
+    pub profile: KeyProfile,
+    bindings: Vec<(KeyBinding, Action)>,
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> KeyBinding { KeyBinding { code, modifiers } }
+    pub fn key(code: KeyCode) -> KeyBinding { KeyBinding { code, modifiers: KeyModifiers::NONE } }
+    pub fn ctrl(code: KeyCode) -> KeyBinding { KeyBinding { code, modifiers: KeyModifiers::CONTROL } }
+    pub fn shift(code: KeyCode) -> KeyBinding { KeyBinding { code, modifiers: KeyModifiers::SHIFT } }
+
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        if self.code != event.code { return false; }
+        match self.code {
+            // shift is already applied to the char ('O' instead of 'o')
+            KeyCode::Char(_) => (self.modifiers - KeyModifiers::SHIFT) == (event.modifiers - KeyModifiers::SHIFT),
+            _ => self.modifiers == event.modifiers,
+        }
+    }
+}
+
+impl KeyMap {
+    pub fn new(profile: KeyProfile) -> KeyMap {
+        let mut bindings = vec![
+            (KeyBinding::key(KeyCode::F(4)), Action::NextFieldOrder),
+            (KeyBinding::shift(KeyCode::F(4)), Action::PrevFieldOrder),
+            (KeyBinding::key(KeyCode::F(5)), Action::CollapsedToggle),
+            (KeyBinding::key(KeyCode::Enter), Action::CollapsedToggle),
+            (KeyBinding::key(KeyCode::Char('+')), Action::ExpandAll),
+            (KeyBinding::key(KeyCode::Char('-')), Action::CollapseAll),
+            (KeyBinding::key(KeyCode::Char('[')), Action::GroupFirst),
+            (KeyBinding::key(KeyCode::Char(']')), Action::GroupLast),
+            (KeyBinding::key(KeyCode::F(6)), Action::CommentsVisibility),
+            (KeyBinding::key(KeyCode::F(7)), Action::WrapToggle),
+            (KeyBinding::shift(KeyCode::F(7)), Action::WrapAllToggle),
+            (KeyBinding::key(KeyCode::F(8)), Action::Stats),
+            (KeyBinding::key(KeyCode::F(9)), Action::HexDump),
+            (KeyBinding::ctrl(KeyCode::Char('r')), Action::ReadOnlyToggle),
+            (KeyBinding::ctrl(KeyCode::Char('e')), Action::EditValue),
+            (KeyBinding::ctrl(KeyCode::Char('p')), Action::PackedToggle),
+            (KeyBinding::ctrl(KeyCode::Char('k')), Action::EncodingToggle),
+            (KeyBinding::ctrl(KeyCode::Char('w')), Action::WatchToggle),
+            (KeyBinding::new(KeyCode::Char('t'), KeyModifiers::ALT), Action::PinToggle),
+            (KeyBinding::ctrl(KeyCode::Char('o')), Action::GoToOffset),
+            (KeyBinding::ctrl(KeyCode::Char('f')), Action::OffsetsToggle),
+            (KeyBinding::ctrl(KeyCode::Char('n')), Action::SchemaAudit),
+            (KeyBinding::ctrl(KeyCode::Char('b')), Action::DecodeBytes),
+            (KeyBinding::ctrl(KeyCode::Char('u')), Action::Columns),
+            (KeyBinding::ctrl(KeyCode::Char('a')), Action::MergeGroups),
+            (KeyBinding::ctrl(KeyCode::Char('x')), Action::Overview),
+            (KeyBinding::new(KeyCode::Char('p'), KeyModifiers::ALT), Action::Schema),
+            (KeyBinding::new(KeyCode::Char('h'), KeyModifiers::ALT), Action::History),
+            (KeyBinding::new(KeyCode::Char('m'), KeyModifiers::ALT), Action::MergeFile),
+            (KeyBinding::new(KeyCode::Char('e'), KeyModifiers::ALT), Action::ExportBytes),
+            (KeyBinding::new(KeyCode::Char('i'), KeyModifiers::ALT), Action::ImportBytes),
+            (KeyBinding::new(KeyCode::Char('r'), KeyModifiers::ALT), Action::Replace),
+            (KeyBinding::new(KeyCode::Char('a'), KeyModifiers::ALT), Action::SetSiblings),
+            (KeyBinding::new(KeyCode::Char('c'), KeyModifiers::ALT), Action::EscapesToggle),
+            (KeyBinding::new(KeyCode::Char('f'), KeyModifiers::ALT), Action::Search),
+            (KeyBinding::new(KeyCode::Char('w'), KeyModifiers::ALT), Action::Inspect),
+            (KeyBinding::new(KeyCode::Char('b'), KeyModifiers::ALT), Action::SidebarToggle),
+            (KeyBinding::new(KeyCode::Char('k'), KeyModifiers::ALT), Action::Highlight),
+            (KeyBinding::new(KeyCode::Char('v'), KeyModifiers::ALT), Action::DetailPane),
+            (KeyBinding::new(KeyCode::Left, KeyModifiers::ALT), Action::PaneWider),
+            (KeyBinding::new(KeyCode::Right, KeyModifiers::ALT), Action::PaneNarrower),
+            (KeyBinding::new(KeyCode::Char('s'), KeyModifiers::ALT), Action::SizesToggle),
+            (KeyBinding::new(KeyCode::Char('x'), KeyModifiers::ALT), Action::BinaryToggle),
+            (KeyBinding::new(KeyCode::Char('o'), KeyModifiers::ALT), Action::FieldFormat),
+            (KeyBinding::new(KeyCode::Char('n'), KeyModifiers::ALT), Action::UnknownEnums),
+            (KeyBinding::ctrl(KeyCode::Char('t')), Action::MacroRecord),
+            (KeyBinding::ctrl(KeyCode::Char('y')), Action::MacroReplay),
+            (KeyBinding::new(KeyCode::Char('y'), KeyModifiers::ALT), Action::MacroReplayAll),
+            (KeyBinding::key(KeyCode::F(1)), Action::Help),
+            (KeyBinding::key(KeyCode::F(12)), Action::PerfOverlay),
+            (KeyBinding::key(KeyCode::F(10)), Action::Quit),
+            (KeyBinding::key(KeyCode::Esc), Action::Back),
+            (KeyBinding::ctrl(KeyCode::Right), Action::DrillDown),
+            (KeyBinding::ctrl(KeyCode::Char('l')), Action::Reload),
+            (KeyBinding::new(KeyCode::Char('l'), KeyModifiers::ALT), Action::ReloadSchema),
+            (KeyBinding::ctrl(KeyCode::Char('d')), Action::SetDefault),
+            (KeyBinding::new(KeyCode::Char('d'), KeyModifiers::ALT), Action::ClearField),
+            (KeyBinding::key(KeyCode::Up), Action::LineUp),
+            (KeyBinding::key(KeyCode::Down), Action::LineDown),
+            (KeyBinding::ctrl(KeyCode::Up), Action::SiblingUp),
+            (KeyBinding::ctrl(KeyCode::Down), Action::SiblingDown),
+            (KeyBinding::shift(KeyCode::Up), Action::RangeUp),
+            (KeyBinding::shift(KeyCode::Down), Action::RangeDown),
+            (KeyBinding::ctrl(KeyCode::Char('c')), Action::Copy),
+            (KeyBinding::ctrl(KeyCode::Char('z')), Action::Undo),
+            (KeyBinding::new(KeyCode::Char('z'), KeyModifiers::ALT), Action::Redo),
+            (KeyBinding::new(KeyCode::Down, KeyModifiers::ALT), Action::NextSet),
+            (KeyBinding::new(KeyCode::Up, KeyModifiers::ALT), Action::PrevSet),
+            (KeyBinding::key(KeyCode::PageUp), Action::PageUp),
+            (KeyBinding::key(KeyCode::PageDown), Action::PageDown),
+            (KeyBinding::key(KeyCode::Home), Action::Home),
+            (KeyBinding::ctrl(KeyCode::Home), Action::ResetSelection),
+            (KeyBinding::key(KeyCode::End), Action::End),
+            (KeyBinding::ctrl(KeyCode::End), Action::ScrollToBottom),
+            (KeyBinding::key(KeyCode::Left), Action::Left),
+            (KeyBinding::key(KeyCode::Right), Action::Right),
+            (KeyBinding::key(KeyCode::Delete), Action::DeleteData),
+            (KeyBinding::key(KeyCode::Insert), Action::InsertData),
+            (KeyBinding::key(KeyCode::F(3)), Action::ProtectToggle),
+            (KeyBinding::key(KeyCode::F(2)), Action::Save),
+            (KeyBinding::ctrl(KeyCode::Char('s')), Action::Save),
+            (KeyBinding::new(KeyCode::F(2), KeyModifiers::SHIFT), Action::SaveAs),
+            (KeyBinding::ctrl(KeyCode::Char('g')), Action::GoToPath),
+            (KeyBinding::new(KeyCode::Char('g'), KeyModifiers::ALT), Action::GoToRecord),
+            (KeyBinding::new(KeyCode::Char('u'), KeyModifiers::ALT), Action::Breadcrumbs),
+        ];
+        for depth in 1..=9 {
+            bindings.push((KeyBinding::new(KeyCode::Char(char::from(b'0' + depth as u8)), KeyModifiers::ALT), Action::CollapseToDepth(depth)));
+        }
+        if profile == KeyProfile::Letters {
+            bindings.append(&mut vec![
+                (KeyBinding::key(KeyCode::Char('o')), Action::NextFieldOrder),
+                (KeyBinding::shift(KeyCode::Char('O')), Action::PrevFieldOrder),
+                (KeyBinding::key(KeyCode::Char('c')), Action::CollapsedToggle),
+                (KeyBinding::key(KeyCode::Char('m')), Action::CommentsVisibility),
+                (KeyBinding::key(KeyCode::Char('i')), Action::InsertData),
+                (KeyBinding::key(KeyCode::Char('d')), Action::DeleteData),
+                (KeyBinding::key(KeyCode::Char('p')), Action::ProtectToggle),
+                (KeyBinding::key(KeyCode::Char('s')), Action::Save),
+                (KeyBinding::shift(KeyCode::Char('F')), Action::SaveAs),
+                (KeyBinding::key(KeyCode::Char('g')), Action::GoToPath),
+                (KeyBinding::shift(KeyCode::Char('G')), Action::GoToRecord),
+                (KeyBinding::key(KeyCode::Char('>')), Action::Breadcrumbs),
+                (KeyBinding::key(KeyCode::Char('w')), Action::WrapToggle),
+                (KeyBinding::shift(KeyCode::Char('W')), Action::WrapAllToggle),
+                (KeyBinding::key(KeyCode::Char('t')), Action::Stats),
+                (KeyBinding::key(KeyCode::Char('x')), Action::HexDump),
+                (KeyBinding::key(KeyCode::Char('r')), Action::ReadOnlyToggle),
+                (KeyBinding::key(KeyCode::Char('e')), Action::EditValue),
+                (KeyBinding::key(KeyCode::Char('k')), Action::PackedToggle),
+                (KeyBinding::key(KeyCode::Char('u')), Action::EncodingToggle),
+                (KeyBinding::key(KeyCode::Char('a')), Action::WatchToggle),
+                (KeyBinding::shift(KeyCode::Char('T')), Action::PinToggle),
+                (KeyBinding::key(KeyCode::Char('b')), Action::GoToOffset),
+                (KeyBinding::shift(KeyCode::Char('B')), Action::OffsetsToggle),
+                (KeyBinding::key(KeyCode::Char('v')), Action::SchemaAudit),
+                (KeyBinding::key(KeyCode::Char('n')), Action::DecodeBytes),
+                (KeyBinding::shift(KeyCode::Char('C')), Action::Columns),
+                (KeyBinding::shift(KeyCode::Char('M')), Action::MergeGroups),
+                (KeyBinding::shift(KeyCode::Char('I')), Action::Overview),
+                (KeyBinding::shift(KeyCode::Char('P')), Action::Schema),
+                (KeyBinding::shift(KeyCode::Char('Z')), Action::History),
+                (KeyBinding::shift(KeyCode::Char('V')), Action::MergeFile),
+                (KeyBinding::shift(KeyCode::Char('E')), Action::ExportBytes),
+                (KeyBinding::shift(KeyCode::Char('N')), Action::ImportBytes),
+                (KeyBinding::shift(KeyCode::Char('R')), Action::Replace),
+                (KeyBinding::key(KeyCode::Char('=')), Action::SetSiblings),
+                (KeyBinding::key(KeyCode::Char('{')), Action::RangeUp),
+                (KeyBinding::key(KeyCode::Char('}')), Action::RangeDown),
+                (KeyBinding::key(KeyCode::Char('*')), Action::Copy),
+                (KeyBinding::key(KeyCode::Char('(')), Action::Undo),
+                (KeyBinding::key(KeyCode::Char(')')), Action::Redo),
+                (KeyBinding::shift(KeyCode::Char('X')), Action::EscapesToggle),
+                (KeyBinding::key(KeyCode::Char('/')), Action::Search),
+                (KeyBinding::shift(KeyCode::Char('U')), Action::Inspect),
+                (KeyBinding::shift(KeyCode::Char('D')), Action::SidebarToggle),
+                (KeyBinding::shift(KeyCode::Char('Q')), Action::Highlight),
+                (KeyBinding::shift(KeyCode::Char('A')), Action::DetailPane),
+                (KeyBinding::shift(KeyCode::Char('S')), Action::SizesToggle),
+                (KeyBinding::key(KeyCode::Char('!')), Action::BinaryToggle),
+                (KeyBinding::key(KeyCode::Char('%')), Action::FieldFormat),
+                (KeyBinding::shift(KeyCode::Char('K')), Action::UnknownEnums),
+                (KeyBinding::key(KeyCode::Char('f')), Action::DrillDown),
+                (KeyBinding::key(KeyCode::Char('l')), Action::Reload),
+                (KeyBinding::shift(KeyCode::Char('L')), Action::ReloadSchema),
+                (KeyBinding::key(KeyCode::Char('j')), Action::NextSet),
+                (KeyBinding::shift(KeyCode::Char('J')), Action::PrevSet),
+                (KeyBinding::key(KeyCode::Char('z')), Action::MacroRecord),
+                (KeyBinding::key(KeyCode::Char('y')), Action::MacroReplay),
+                (KeyBinding::shift(KeyCode::Char('Y')), Action::MacroReplayAll),
+                (KeyBinding::key(KeyCode::Char('h')), Action::SetDefault),
+                (KeyBinding::shift(KeyCode::Char('H')), Action::ClearField),
+                (KeyBinding::key(KeyCode::Char('?')), Action::Help),
+                (KeyBinding::key(KeyCode::Char('q')), Action::Quit),
+            ]);
+        }
+        KeyMap { profile, bindings }
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings.iter().find(|(binding, _)| binding.matches(event)).map(|(_, action)| *action)
+    }
+
+    pub fn bindings(&self) -> &[(KeyBinding, Action)] { &self.bindings }
+
+    // the help screen: a title line for each category, then the keys of each action and what it does
+    pub fn help_lines(&self) -> Vec<String> {
+        let mut res = vec![];
+        for (category, title) in [(Category::Navigation, "Navigation"), (Category::View, "View"), (Category::Edit, "Edit"), (Category::File, "File")] {
+            let mut items: Vec<(Action, &str, Vec<String>)> = vec![];
+            for (binding, action) in &self.bindings {
+                if action.category() != category { continue; }
+                let key = match action {
+                    Action::CollapseToDepth(1) => "Alt+1..9".to_string(),
+                    Action::CollapseToDepth(_) => continue,
+                    _ => binding.to_string(),
+                };
+                match items.iter_mut().find(|(_, description, _)| *description == action.description()) {
+                    Some((_, _, keys)) => keys.push(key),
+                    None => items.push((*action, action.description(), vec![key])),
+                }
+            }
+            items.sort_by_key(|(action, _, _)| *action);
+            if !res.is_empty() { res.push(String::new()); }
+            res.push(title.to_string());
+            for (_, description, keys) in items {
+                res.push(format!("  {:<20} {}", keys.join(", "), description));
+            }
+        }
+        res
+    }
+}
+
+impl Action {
+    pub fn category(&self) -> Category {
+        match self {
+            Action::LineUp | Action::LineDown | Action::SiblingUp | Action::SiblingDown | Action::PageUp | Action::PageDown |
+            Action::Home | Action::End | Action::ResetSelection | Action::ScrollToBottom | Action::Left | Action::Right |
+            Action::RangeUp | Action::RangeDown | Action::GroupFirst | Action::GroupLast | Action::NextSet | Action::PrevSet |
+            Action::GoToPath | Action::GoToRecord | Action::Breadcrumbs | Action::Search | Action::GoToOffset | Action::DrillDown | Action::Back => Category::Navigation,
+            Action::NextFieldOrder | Action::PrevFieldOrder | Action::CollapsedToggle | Action::ExpandAll | Action::CollapseAll |
+            Action::CollapseToDepth(_) | Action::CommentsVisibility | Action::WrapToggle | Action::WrapAllToggle |
+            Action::EncodingToggle | Action::EscapesToggle | Action::OffsetsToggle | Action::SizesToggle | Action::BinaryToggle | Action::Columns | Action::FieldFormat | Action::DecodeBytes | Action::WatchToggle | Action::Highlight | Action::PinToggle |
+            Action::Stats | Action::SchemaAudit | Action::Overview | Action::Schema | Action::Inspect | Action::SidebarToggle | Action::DetailPane | Action::PaneWider | Action::PaneNarrower | Action::UnknownEnums | Action::PerfOverlay | Action::Help => Category::View,
+            Action::EditValue | Action::InsertData | Action::DeleteData | Action::SetDefault | Action::ClearField |
+            Action::Copy | Action::Undo | Action::Redo | Action::ProtectToggle | Action::PackedToggle | Action::MergeGroups |
+            Action::MacroRecord | Action::MacroReplay | Action::MacroReplayAll | Action::History |
+            Action::MergeFile | Action::ImportBytes | Action::Replace | Action::SetSiblings => Category::Edit,
+            Action::Save | Action::SaveAs | Action::Reload | Action::ReloadSchema | Action::ReadOnlyToggle | Action::HexDump | Action::ExportBytes | Action::Quit => Category::File,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::NextFieldOrder => "next field order",
+            Action::PrevFieldOrder => "previous field order",
+            Action::CollapsedToggle => "expand/collapse the message",
+            Action::ExpandAll => "expand all nested messages",
+            Action::CollapseAll => "collapse all nested messages",
+            Action::CollapseToDepth(_) => "collapse the messages of the level",
+            Action::CommentsVisibility => "comments: hidden, inline, multiline",
+            Action::LineUp => "line up",
+            Action::LineDown => "line down",
+            Action::SiblingUp => "previous field of the message",
+            Action::SiblingDown => "next field of the message",
+            Action::PageUp => "page up",
+            Action::PageDown => "page down",
+            Action::Home => "line start",
+            Action::End => "line end",
+            Action::ResetSelection => "go to the top",
+            Action::ScrollToBottom => "go to the bottom",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Back => "close the opened message, or quit",
+            Action::DrillDown => "open the message as the root",
+            Action::Reload => "read the file again",
+            Action::DeleteData => "delete",
+            Action::InsertData => "insert",
+            Action::RangeUp | Action::RangeDown => "select a range of values",
+            Action::Copy => "copy as edit commands",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::ProtectToggle => "protect/unprotect the field",
+            Action::Save => "review the changes and save",
+            Action::SaveAs => "save to a file, json or text by the extension",
+            Action::GoToPath => "go to a path",
+            Action::GoToRecord => "go to a top-level record by number",
+            Action::WrapToggle => "wrap/cut the string",
+            Action::WrapAllToggle => "wrap/cut all strings",
+            Action::GroupFirst => "first value of the repeated field",
+            Action::GroupLast => "last value of the repeated field",
+            Action::NextSet => "next field set",
+            Action::PrevSet => "previous field set",
+            Action::Stats => "statistics of the repeated field",
+            Action::HexDump => "hex dump to a file",
+            Action::ReadOnlyToggle => "read-only mode on/off",
+            Action::EditValue => "edit the value",
+            Action::PackedToggle => "packed/unpacked",
+            Action::EncodingToggle => "show/hide wire encodings",
+            Action::WatchToggle => "watch/unwatch the field",
+            Action::Highlight => "highlight the values of the message where a condition is true",
+            Action::PinToggle => "pin/unpin the field under the top line",
+            Action::GoToOffset => "go to an offset in bytes",
+            Action::SchemaAudit => "compare with another proto file",
+            Action::ReloadSchema => "read the proto file again, keep the data",
+            Action::DecodeBytes => "decode bytes as a message",
+            Action::MacroRecord => "start/stop recording a macro",
+            Action::MacroReplay => "replay a macro",
+            Action::MacroReplayAll => "replay a macro for each value",
+            Action::SetDefault => "set the default value",
+            Action::ClearField => "clear the field",
+            Action::OffsetsToggle => "bytes offsets: hex, decimal, hidden",
+            Action::SizesToggle => "the bytes of each field in the saved file",
+            Action::BinaryToggle => "mark the values read with another wire type than declared",
+            Action::Columns => "table columns of the message type",
+            Action::FieldFormat => "format of the field: hex, timestamp, base64, utf8...",
+            Action::MergeGroups => "merge the groups of the repeated field",
+            Action::Overview => "message types, sizes and depths of the data",
+            Action::Schema => "messages, fields and enums of the proto file",
+            Action::History => "the changes: revert one, save them as commands",
+            Action::MergeFile => "merge another version of the data file",
+            Action::ExportBytes => "save the bytes to a file, copy as hex or base64",
+            Action::ImportBytes => "replace the bytes with a file, hex or base64",
+            Action::Replace => "replace text in all string values",
+            Action::SetSiblings => "set the value in the same field of all the values around",
+            Action::EscapesToggle => "control chars of strings: escaped or as is",
+            Action::Search => "search the values as the text is typed",
+            Action::Breadcrumbs => "the parents of the field: go to one, copy the path",
+            Action::Inspect => "wire bytes of the value read as each type",
+            Action::SidebarToggle => "sidebar: path, type, comment and raw value of the field",
+            Action::DetailPane => "two panes: the tree and the whole value of the field",
+            Action::PaneWider => "move the divider of the panes left",
+            Action::PaneNarrower => "move the divider of the panes right",
+            Action::UnknownEnums => "enum numbers with no name, set the nearest one",
+            Action::PerfOverlay => "draw time, layouts created and bytes read",
+            Action::Help => "this help",
+        }
+    }
+}
+
+// as in the README: Ctrl+E, Shift+F4, Alt+Down, O
+impl std::fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) { write!(f, "Ctrl+")?; }
+        if self.modifiers.contains(KeyModifiers::ALT) { write!(f, "Alt+")?; }
+        match self.code {
+            // the char is shifted already
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            code => {
+                if self.modifiers.contains(KeyModifiers::SHIFT) { write!(f, "Shift+")?; }
+                match code {
+                    KeyCode::F(n) => write!(f, "F{}", n),
+                    KeyCode::PageUp => write!(f, "PgUp"),
+                    KeyCode::PageDown => write!(f, "PgDn"),
+                    KeyCode::Delete => write!(f, "Del"),
+                    KeyCode::Insert => write!(f, "Ins"),
+                    code => write!(f, "{:?}", code),
+                }
+            }
+        }
+    }
+}
+
+// terminal multiplexers often keep function keys for themselves
+// return the profile worth suggesting to the user, if any
+pub fn suggest_profile(vars: &dyn Fn(&str) -> Option<String>) -> Option<KeyProfile> {
+    if vars("TMUX").is_some() || vars("STY").is_some() {
+        return Some(KeyProfile::Letters);
+    }
+    if let Some(term) = vars("TERM") {
+        if term.starts_with("screen") || term.starts_with("tmux") {
+            return Some(KeyProfile::Letters);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod keymap_tests {
+    use super::*;
+    use crossterm::event::KeyEventKind;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new_with_kind(code, modifiers, KeyEventKind::Press)
+    }
+
+    #[test]
+    fn function_keys() {
+        let keymap = KeyMap::new(KeyProfile::Fkeys);
+        assert_eq!(keymap.action(&press(KeyCode::F(4), KeyModifiers::NONE)), Some(Action::NextFieldOrder));
+        assert_eq!(keymap.action(&press(KeyCode::F(4), KeyModifiers::SHIFT)), Some(Action::PrevFieldOrder));
+        assert_eq!(keymap.action(&press(KeyCode::Up, KeyModifiers::CONTROL)), Some(Action::SiblingUp));
+        assert_eq!(keymap.action(&press(KeyCode::Up, KeyModifiers::SHIFT)), Some(Action::RangeUp));
+        assert_eq!(keymap.action(&press(KeyCode::Char('z'), KeyModifiers::CONTROL)), Some(Action::Undo));
+        assert_eq!(keymap.action(&press(KeyCode::Char('c'), KeyModifiers::NONE)), None);
+        assert_eq!(keymap.action(&press(KeyCode::Char('+'), KeyModifiers::SHIFT)), Some(Action::ExpandAll));
+        assert_eq!(keymap.action(&press(KeyCode::Char('2'), KeyModifiers::ALT)), Some(Action::CollapseToDepth(2)));
+        assert_eq!(keymap.action(&press(KeyCode::Char('2'), KeyModifiers::NONE)), None);
+        assert_eq!(keymap.action(&press(KeyCode::F(7), KeyModifiers::SHIFT)), Some(Action::WrapAllToggle));
+    }
+
+    #[test]
+    fn letter_chords() {
+        let keymap = KeyMap::new(KeyProfile::Letters);
+        assert_eq!(keymap.action(&press(KeyCode::Char('o'), KeyModifiers::NONE)), Some(Action::NextFieldOrder));
+        assert_eq!(keymap.action(&press(KeyCode::Char('O'), KeyModifiers::SHIFT)), Some(Action::PrevFieldOrder));
+        assert_eq!(keymap.action(&press(KeyCode::Char('O'), KeyModifiers::NONE)), Some(Action::PrevFieldOrder));
+        assert_eq!(keymap.action(&press(KeyCode::Char('c'), KeyModifiers::NONE)), Some(Action::CollapsedToggle));
+        assert_eq!(keymap.action(&press(KeyCode::Char('i'), KeyModifiers::NONE)), Some(Action::InsertData));
+        assert_eq!(keymap.action(&press(KeyCode::Char('d'), KeyModifiers::NONE)), Some(Action::DeleteData));
+        assert_eq!(keymap.action(&press(KeyCode::Char('d'), KeyModifiers::CONTROL)), Some(Action::SetDefault));
+        assert_eq!(keymap.action(&press(KeyCode::Char('H'), KeyModifiers::SHIFT)), Some(Action::ClearField));
+        assert_eq!(keymap.action(&press(KeyCode::Char('C'), KeyModifiers::SHIFT)), Some(Action::Columns));
+        assert_eq!(keymap.action(&press(KeyCode::Char('}'), KeyModifiers::SHIFT)), Some(Action::RangeDown));
+        assert_eq!(keymap.action(&press(KeyCode::Char('*'), KeyModifiers::SHIFT)), Some(Action::Copy));
+        assert_eq!(keymap.action(&press(KeyCode::Char('('), KeyModifiers::SHIFT)), Some(Action::Undo));
+        // function keys still work if the terminal passes them through
+        assert_eq!(keymap.action(&press(KeyCode::F(5), KeyModifiers::NONE)), Some(Action::CollapsedToggle));
+    }
+
+    #[test]
+    fn help() {
+        let lines = KeyMap::new(KeyProfile::Fkeys).help_lines();
+        assert_eq!(lines[0], "Navigation");
+        assert!(lines.contains(&"  Up                   line up".to_string()));
+        assert!(lines.contains(&"  Shift+Up, Shift+Down select a range of values".to_string()));
+        assert!(lines.contains(&"  Alt+1..9             collapse the messages of the level".to_string()));
+        assert!(lines.contains(&"  F2, Ctrl+S           review the changes and save".to_string()));
+        assert!(lines.contains(&"File".to_string()));
+        let lines = KeyMap::new(KeyProfile::Letters).help_lines();
+        assert!(lines.contains(&"  Ctrl+E, e            edit the value".to_string()));
+        assert!(lines.contains(&"  Shift+F4, O          previous field order".to_string()));
+        assert!(lines.contains(&"  F1, ?                this help".to_string()));
+    }
+
+    #[test]
+    fn detect_multiplexer() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|v| v.0 == name).map(|v| v.1.to_string())
+        };
+        assert_eq!(suggest_profile(&env(&[("TERM", "xterm-256color")])), None);
+        assert_eq!(suggest_profile(&env(&[("TERM", "screen-256color")])), Some(KeyProfile::Letters));
+        assert_eq!(suggest_profile(&env(&[("TERM", "xterm"), ("TMUX", "/tmp/tmux-0/default,1,0")])), Some(KeyProfile::Letters));
+    }
+}
@@ -1,47 +1,148 @@
 #![allow(warnings)]
 
-mod proto;
-mod wire;
-mod typedefs;
+use pbedit_core::{batch, blob, proto, trz, typedefs, walk, wire};
+
 mod view;
-mod trz;
+mod keymap;
+mod expr;
+mod diff;
+mod prompt;
+mod stats;
+mod autosave;
+mod render;
+mod audit;
+mod config;
+mod query;
+mod overview;
+mod crash;
+mod schema;
+mod merge;
+mod replace;
+mod picker;
+mod fingerprint;
+mod inspect;
+mod sidebar;
+mod generate;
+mod textformat;
+mod compress;
+mod highlight;
+mod timestamp;
+mod formats;
+mod harness;
 
 use std::string::String;
 use crate::ScalarValue::STR;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::ScalarValue::I32;
 use std::fmt::{Debug, Formatter};
 use wire::*;
-use std::io::{self, Read, Stdout, Write};
+use std::io::{self, IsTerminal, Read, Write};
 use std::path::PathBuf;
 use std::process::exit;
+use std::time::{Duration, Instant, SystemTime};
 use crossterm::*;
 use crossterm::style::{Color, Colored, Colors, ContentStyle, Stylize};
-use crate::view::{CommandResult, CommentVisibility, FieldOrder, LayoutConfig, LayoutType, Layouts, ScreenLine, ScreenLines, IndentsCalc, TextStyle, UserCommand, MARGIN_LEFT, MARGIN_RIGHT};
+use crate::view::{CommandResult, CommentVisibility, LayoutConfig, LayoutType, Layouts, ScreenLine, ScreenLines, IndentsCalc, TextStyle, UserCommand, MARGIN_LEFT, MARGIN_RIGHT, WIDE_TAIL};
 
 use clap::Parser;
 
 //#![cfg(feature = "bracketed-paste")]
 use crossterm::{
     event::{
-        read, DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        poll, read, DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
         EnableFocusChange, EnableMouseCapture, Event,
     },
     execute,
 };
-use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEvent, MouseEventKind};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
-use pest::Lines;
-use crate::proto::{FieldProtoPtr, MessageProto, ProtoData, ProtoFile};
-use crate::typedefs::{PbReader};
+use crate::proto::{FieldProtoPtr, MessageProto, MessageProtoPtr, ProtoData, ProtoFile};
+use crate::typedefs::{CommonFieldProto, Overflow, PbReader};
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicBool};
 use crate::view::UserCommand::{ChangeFieldOrder, CollapsedToggle, DeleteData, End, Home, InsertData, ScrollHorizontally, ScrollSibling, ScrollToBottom, ScrollVertically};
 use crate::wire::FieldValue::SCALAR;
-
-const USE_ALTERNATIVE_SCREEN: bool = false;
+use crate::keymap::{Action, KeyBinding, KeyMap, KeyProfile};
+use crate::trz::{Change, ChangeType, History, Step};
+use crate::prompt::{Prompt, PromptHistory, PromptResult};
+use crate::autosave::Journal;
+use crate::render::{Renderer, Renderers};
+use crate::blob::{Source, SourceReader};
+use crate::textformat::Format;
+use crate::compress::Compression;
+use crate::formats::FieldFormat;
+
+// the terminal modes set by App::new, restored once by the drop or by the panic hook (before the panic message)
+static TERMINAL_SET: AtomicBool = AtomicBool::new(false);
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
 
 // 0-hide top line, 1-show
 const TOP_LINE: u16 = 1;
 
+// max time between two clicks at the same position to toggle a message
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+
+// max time between two presses of Up or Down to take them as a held key, scrolling faster
+const KEY_REPEAT_TIME: Duration = Duration::from_millis(100);
+// repeats of a held key before its step is doubled, up to a page
+const REPEATS_TO_DOUBLE: usize = 8;
+
+// shown in the left margin of protected fields
+const PROTECTED_MARK: char = '#';
+const WIRE_CONFLICT_MARK: char = '!';
+
+// shown in the left margin of the values in the range selected with Shift+Up/Down
+const RANGE_MARK: char = '>';
+
+// max lines of the watch list, without its title
+const WATCH_LINES: usize = 5;
+
+// max fields pinned under the top line
+const PINNED_LINES: usize = 3;
+
+// how often the data file is checked for changes by other programs
+const WATCH_FILE_INTERVAL: Duration = Duration::from_secs(2);
+// how often the layouts sized on the sizing thread are taken while no keys are pressed
+const SIZING_POLL: Duration = Duration::from_millis(20);
+
+// the sidebar with the selected field is shown on screens of SIDEBAR_MIN_SCREEN columns and wider
+const SIDEBAR_WIDTH: u16 = 44;
+const SIDEBAR_MIN_SCREEN: u16 = 100;
+// the detail pane starts at half of the screen, the divider moves by PANE_STEP columns and leaves
+// at least TREE_MIN_WIDTH columns to the data and PANE_MIN_WIDTH to the pane
+const PANE_MIN_SCREEN: u16 = 120;
+const PANE_MIN_WIDTH: u16 = 30;
+const TREE_MIN_WIDTH: u16 = 40;
+const PANE_STEP: u16 = 4;
+
+// data files of this size and larger are mapped into memory instead of being read
+const MAPPED_SIZE: u64 = 64 << 20;
+
+// names of the prompts, each one has its own history
+const GO_TO_PATH_PROMPT: &str = "go to";
+const HEX_DUMP_PROMPT: &str = "hex dump";
+const EDIT_PROMPT: &str = "edit";
+const SAVE_PROMPT: &str = "save";
+const GO_TO_OFFSET_PROMPT: &str = "offset";
+const SCHEMA_PROMPT: &str = "schema";
+const RECORD_PROMPT: &str = "record";
+const REPLAY_PROMPT: &str = "replay";
+const REPLAY_ALL_PROMPT: &str = "replay all";
+const DECODE_PROMPT: &str = "decode";
+const COLUMNS_PROMPT: &str = "columns";
+const GO_TO_RECORD_PROMPT: &str = "go to record";
+const EXPORT_CHANGES_PROMPT: &str = "export changes";
+const MERGE_PROMPT: &str = "merge";
+const EXPORT_BYTES_PROMPT: &str = "export bytes";
+const IMPORT_BYTES_PROMPT: &str = "import bytes";
+const REPLACE_PROMPT: &str = "replace";
+const REPLACE_WITH_PROMPT: &str = "replace with";
+const SEARCH_PROMPT: &str = "search";
+const HIGHLIGHT_PROMPT: &str = "highlight";
+const FORMAT_PROMPT: &str = "format";
+
+const READ_ONLY_ERROR: &str = "read-only mode, Ctrl+R to allow changes";
+
 
 struct RepeatedEditorConfig {
     sort_by: Option<i32>, // field index for sort data
@@ -53,6 +154,86 @@ struct RepeatedEditorConfig {
 // UpperUilayer: confirmations (CtrlC exit,etc.), enum/oneof lists
 
 
+// the replace command going through the string values found, each one is confirmed or skipped
+struct Replacing {
+    pattern: replace::Pattern,
+    with: String,
+    // the values not visited yet, in the order of the data
+    paths: Vec<FieldPath>,
+    // the matches replaced, the values changed and skipped
+    replaced: usize,
+    values: usize,
+    skipped: usize,
+}
+
+// differences between the file and the edited data, shown before saving
+struct Review {
+    lines: Vec<String>,
+    scroll: usize,
+    // the byte layout after merging the groups of this repeated field, Y merges them instead of saving
+    merge: Option<FieldPath>,
+    // the first lines are fields of the file not written back as read, only Y saves
+    lossy: usize,
+}
+
+// the keys of the key map by category, Up/Down scroll, other keys close it
+struct Help {
+    lines: Vec<String>,
+    scroll: usize,
+}
+
+// what the keys of a report do besides going to the field
+#[derive(Clone, Copy, PartialEq)]
+enum ReportKind {
+    List,
+    // the history panel: a line for each step of the undo list, Del reverts it, Ctrl+S saves the commands
+    History,
+    // the differences with another version of the data, Right takes the other value, Left keeps this one
+    Merge,
+    // enum numbers with no name, Right sets the nearest variant
+    Enums,
+    // the values set in the siblings of a value, Y applies the changes
+    Siblings,
+    // the parents of the selected field, Ctrl+C copies its path
+    Breadcrumbs,
+}
+
+// lines with the paths of fields (the schema audit, the overview, the schema), Enter goes to the selected one
+struct Report {
+    // for the top line
+    title: String,
+    lines: Vec<(String, Option<String>)>,
+    selected: usize,
+    scroll: usize,
+    // the text typed to find a line, if the report is searched
+    search: Option<String>,
+    kind: ReportKind,
+}
+
+impl Report {
+    // the first line from `start` containing the text, case insensitive, the search continues from the top
+    fn find(&self, text: &str, start: usize) -> Option<usize> {
+        let text = text.to_lowercase();
+        let count = self.lines.len();
+        (0..count).map(|i| (start + i) % count).find(|&i| self.lines[i].0.to_lowercase().contains(&text))
+    }
+}
+
+// a message opened as the root of the view, the parent view is restored when it is closed
+struct Focus {
+    // from the root of the document
+    path: FieldPath,
+    // the path as text, for the top line and the journal
+    text: String,
+    // the data of the parent view, the focused message is taken out of it
+    parent: Arc<MessageData>,
+    layouts: Layouts,
+    selected: Selection,
+    history: History,
+    // the collapsed messages of the parent view
+    collapsed: HashSet<FieldPath>,
+}
+
 #[derive(Default)]
 struct Selection {
     // current active layout index
@@ -65,31 +246,129 @@ struct Selection {
 }
 
 struct App {
-    pub stdout: Stdout,
+    // the terminal, stdout may be redirected to write the data there
+    pub stdout: Box<dyn Write>,
     pub width: u16,
     pub height: u16,
     test_mode: bool,
 
     //- field below for each opened document
 
-    pub data: MessageData,
+    pub data: Arc<MessageData>,
+    pub proto: ProtoData,
+    pub file_path: PathBuf,
+    // the data read from stdin, the changes are shown against it before saving
+    pub stdin_data: Option<Vec<u8>>,
+    // where to save the data instead of the file, "-" for stdout
+    pub output: Option<PathBuf>,
+    // saved data written to stdout after the terminal is restored
+    pub stdout_data: Option<Vec<u8>>,
     pub layouts: Layouts,
     pub layout_config: LayoutConfig,
     pub selected: Selection,
     pub need_update: bool,
     pub need_update_layout_height: bool,
+    pub keymap: KeyMap,
+    // shown in the top line until the next key pressed
+    pub message: Option<String>,
+    // time and position of the last mouse click, to detect double click
+    last_click: Option<(Instant, u16, u16)>,
+    // the last screen row while dragging with the mouse button pressed
+    drag_row: Option<u16>,
+    // the time a key was read from the terminal, keys replayed by macros and sent by tests have none
+    key_time: Option<Instant>,
+    // time, direction and count of the repeats of Up or Down held down
+    scroll_repeat: Option<(Instant, isize, usize)>,
+    // fields (path and amount of repeated values) changed only after confirmation, for this session
+    protected: Vec<(FieldPath, usize)>,
+    // change of a protected field waiting for the confirmation key
+    pending_change: Option<Change>,
+    // the pattern to replace is entered first, then the replacement
+    replacing: Option<Replacing>,
+    // the changes listed by the siblings report, applied as one step
+    sibling_changes: Vec<Change>,
+    // the selection when the search started, it's selected again if the search is canceled
+    search_start: Option<FieldPath>,
+    // the conditions of the values highlighted, and the message type of the one being typed
+    highlights: highlight::Rules,
+    highlight_type: Option<MessageProtoPtr>,
+    // lines printed by the last update, to redraw only changed ones
+    screen: Vec<ScreenLine>,
+    // shown instead of the data until the save is confirmed or canceled
+    review: Option<Review>,
+    help: Option<Help>,
+    // text input in the bottom line
+    prompt: Option<Prompt>,
+    prompt_history: PromptHistory,
+    // data changing commands and saving are refused
+    pub read_only: bool,
+    // an entered integer out of the field type range, waiting for the choice how to store it
+    overflow_edit: Option<(FieldPath, String)>,
+    // changes not saved yet, for the recovery after a crash
+    journal: Journal,
+    // commands of an unfinished session, waiting for the choice to replay them
+    recovery: Option<String>,
+    // fields with their paths as text shown at the bottom of the screen, for this session
+    watches: Vec<(FieldPath, String)>,
+    // fields with their paths as text shown under the top line, for this session
+    pins: Vec<(FieldPath, String)>,
+    // directories to search imports of the proto file chosen for the audit
+    pub proto_path: Vec<PathBuf>,
+    // the proto file of the data, read again by the schema reload
+    pub proto_file: PathBuf,
+    // shown instead of the data until closed
+    report: Option<Report>,
+    // keys pressed since the recording started, with the name of the macro
+    recording: Option<(String, Vec<KeyEvent>)>,
+    // recorded keys by macro names, for this session
+    macros: HashMap<String, Vec<KeyEvent>>,
+    // the last recorded or replayed macro, suggested for the replay
+    last_macro: String,
+    // the value of a repeated field where the range selection started
+    range: Option<FieldPath>,
+    // changes of this session to undo and redo
+    history: History,
+    // another version of the data being merged
+    merge: Option<merge::Merge>,
+    // the last copied values as edit commands
+    clipboard: String,
+    // messages opened as the root, the innermost last
+    focus: Vec<Focus>,
+    // modification time of the data file when it was read or saved, None for stdin
+    disk_time: Option<SystemTime>,
+    // the data file is changed on disk, waiting for the choice to reload it
+    reload_offer: bool,
+    // the settings file where the table columns are saved, None to keep them for this session only
+    pub config_path: Option<PathBuf>,
+    // the file where the formats of the fields are kept by data files, None to keep them for this session only
+    pub formats_path: Option<PathBuf>,
+    // the performance counters are shown in a line above the prompt
+    perf_overlay: bool,
+    // the selected field is described at the right of the data
+    sidebar: bool,
+    // the whole value of the selected field is shown in a pane at the right, in place of the sidebar
+    pane: bool,
+    // the columns of the pane with the divider, 0 until the pane is first shown
+    pane_width: u16,
+    // how long the last screen took to compose and print
+    draw_time: Duration,
 }
 
 impl App {
-    pub fn new(data: MessageData, file_name: PathBuf) -> io::Result<App> {
-        let mut stdout = io::stdout();
+    pub fn new(data: MessageData, proto: ProtoData, file_path: PathBuf, keymap: KeyMap, layout_config: LayoutConfig, alternate_screen: bool) -> io::Result<App> {
+        let mut stdout = terminal_output()?;
         crossterm::terminal::enable_raw_mode()?;
-        if (USE_ALTERNATIVE_SCREEN) { stdout.execute(EnterAlternateScreen)?; }
+        TERMINAL_SET.store(true, atomic::Ordering::SeqCst);
+        ALTERNATE_SCREEN.store(alternate_screen, atomic::Ordering::SeqCst);
+        if alternate_screen { stdout.execute(EnterAlternateScreen)?; }
+        // the title is saved to the stack of the terminal, to restore it at the exit
+        stdout.write_all(b"\x1b[22;0t")?;
+        stdout.execute(terminal::SetTitle(format!("{} - protoedit", file_path.file_name().unwrap_or_default().to_string_lossy())))?;
         stdout.execute(terminal::Clear(terminal::ClearType::All))?;
         stdout.execute(EnableBracketedPaste)?;
         stdout.execute(EnableFocusChange)?;
+        stdout.execute(EnableMouseCapture)?;
         stdout.execute(cursor::Hide)?;
-        let layout_config = LayoutConfig::default();
 
         let mut width = 0;
         let mut height = 0;
@@ -98,66 +377,408 @@ impl App {
             height = sizes.1;
         }
 
-        let mut layouts = Layouts::new(&data, &layout_config, file_name.file_name().unwrap().to_string_lossy().into_owned(), width, height - TOP_LINE);
+        let mut layouts = Layouts::new(&data, &layout_config, file_path.file_name().unwrap().to_string_lossy().into_owned(), width, height - TOP_LINE);
         layouts.ensure_loaded(&data, &layout_config, 0, 0, height as usize, &mut Selection::default());
+        let journal = Journal::new((file_path.as_os_str() != "-").then_some(file_path.as_path()));
+        let disk_time = modified_time(&file_path);
         let mut app = App {
             stdout,
             width,
             height,
-            data,
+            data: Arc::new(data),
+            proto,
+            file_path,
+            stdin_data: None,
+            output: None,
+            stdout_data: None,
             layouts,
             layout_config,
             selected: Selection::default(),
             need_update: true,
             need_update_layout_height: true,
             test_mode: false,
+            keymap,
+            message: None,
+            last_click: None,
+            drag_row: None,
+            key_time: None,
+            scroll_repeat: None,
+            protected: vec![],
+            pending_change: None,
+            replacing: None,
+            sibling_changes: vec![],
+            search_start: None,
+            highlights: highlight::Rules::default(),
+            highlight_type: None,
+            screen: vec![],
+            review: None,
+            help: None,
+            prompt: None,
+            prompt_history: PromptHistory::default(),
+            read_only: false,
+            overflow_edit: None,
+            journal,
+            recovery: None,
+            watches: vec![],
+            pins: vec![],
+            proto_path: vec![],
+            proto_file: PathBuf::new(),
+            report: None,
+            recording: None,
+            macros: HashMap::new(),
+            last_macro: String::new(),
+            range: None,
+            history: History::default(),
+            merge: None,
+            clipboard: String::new(),
+            focus: vec![],
+            disk_time,
+            reload_offer: false,
+            config_path: None,
+            formats_path: None,
+            perf_overlay: false,
+            sidebar: false,
+            pane: false,
+            pane_width: 0,
+            draw_time: Duration::ZERO,
         };
         app.update()?;
         Ok(app)
     }
 
-    #[cfg(test)]
-    pub fn for_tests(data: MessageData, field_order: FieldOrder, width: u16, height: u16) -> io::Result<App> {
-        let layout_config = LayoutConfig {
-            field_order,
-            ..LayoutConfig::default()
-        };
-        let mut layouts = Layouts::new(&data, &layout_config, "test_data.pb".into(), width, height - TOP_LINE);
+    // the editor without a terminal: keys are given by run_keys, the screen is read by snapshot
+    pub fn headless(data: MessageData, proto: ProtoData, file_path: PathBuf, keymap: KeyMap, layout_config: LayoutConfig, width: u16, height: u16) -> App {
+        let mut layouts = Layouts::new(&data, &layout_config, file_path.file_name().unwrap_or_default().to_string_lossy().into_owned(), width, height - TOP_LINE);
         layouts.ensure_loaded(&data, &layout_config, 0, 0, height as usize, &mut Selection::default());
-        let mut app = App {
-            stdout: io::stdout(),
+        App {
+            stdout: Box::new(io::sink()),
             width,
             height,
-            data,
+            data: Arc::new(data),
+            proto,
+            file_path,
+            stdin_data: None,
+            output: None,
+            stdout_data: None,
             layouts,
             layout_config,
             selected: Selection::default(),
             need_update: true,
             need_update_layout_height: true,
             test_mode: true,
+            keymap,
+            message: None,
+            last_click: None,
+            drag_row: None,
+            key_time: None,
+            scroll_repeat: None,
+            protected: vec![],
+            pending_change: None,
+            replacing: None,
+            sibling_changes: vec![],
+            search_start: None,
+            highlights: highlight::Rules::default(),
+            highlight_type: None,
+            screen: vec![],
+            review: None,
+            help: None,
+            prompt: None,
+            prompt_history: PromptHistory::default(),
+            read_only: false,
+            overflow_edit: None,
+            journal: Journal::new(None),
+            recovery: None,
+            watches: vec![],
+            pins: vec![],
+            proto_path: vec![],
+            proto_file: PathBuf::new(),
+            report: None,
+            recording: None,
+            macros: HashMap::new(),
+            last_macro: String::new(),
+            range: None,
+            history: History::default(),
+            merge: None,
+            clipboard: String::new(),
+            focus: vec![],
+            disk_time: None,
+            reload_offer: false,
+            config_path: None,
+            formats_path: None,
+            perf_overlay: false,
+            sidebar: false,
+            pane: false,
+            pane_width: 0,
+            draw_time: Duration::ZERO,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn for_tests(data: MessageData, field_order: FieldOrder, width: u16, height: u16) -> io::Result<App> {
+        let layout_config = LayoutConfig {
+            field_order,
+            ..LayoutConfig::default()
         };
+        let mut app = App::headless(data, ProtoData::new("").unwrap(), "test_data.pb".into(), KeyMap::new(KeyProfile::Fkeys), layout_config, width, height);
         app.to_strings();
         Ok(app)
     }
     pub fn run(&mut self) -> io::Result<()> {
-        while
-        match read()? {
-            Event::FocusGained => self.on_focus(true)?,
-            Event::FocusLost => self.on_focus(false)?,
-            Event::Key(event) => self.on_key(event)?,
-            Event::Mouse(event) => self.on_mouse(event)?,
-            Event::Resize(width, height) => self.on_resize(width, height)?,
-            _ => false,
-        } { self.after_event()?; }
+        loop {
+            // wake up without events to write the recovery file and to check the data file;
+            // the layouts of an expanded message are sized on the sizing thread, the keys are handled meanwhile
+            if self.layouts.pending_sizes.is_some() {
+                if self.layouts.receive_sizes(&self.data, &self.layout_config, false) {
+                    // the top line without the work mark
+                    self.need_update = true;
+                    self.after_event()?;
+                }
+                if !poll(SIZING_POLL)? { continue; }
+            }
+            let go_on = !poll(WATCH_FILE_INTERVAL)? ||
+                match read()? {
+                    Event::FocusGained => self.on_focus(true)?,
+                    Event::FocusLost => self.on_focus(false)?,
+                    Event::Key(event) => {
+                        crash::set_command(self.key_text(&event));
+                        self.key_time = Some(Instant::now());
+                        self.on_key(event)?
+                    }
+                    Event::Mouse(event) => self.on_mouse(event)?,
+                    Event::Resize(width, height) => self.on_resize(width, height)?,
+                    _ => false,
+                };
+            if !go_on { break; }
+            self.autosave();
+            self.check_disk()?;
+            self.after_event()?;
+            crash::set_selected(self.selected_text());
+        }
+        Ok(())
+    }
+
+    // for the crash report: Ctrl+E - edit the value
+    fn key_text(&self, event: &KeyEvent) -> String {
+        let key = KeyBinding::new(event.code, event.modifiers).to_string();
+        match self.keymap.action(event) {
+            Some(action) if self.prompt.is_none() => format!("{} - {}", key, action.description()),
+            _ => key,
+        }
+    }
+
+    // the path of the selected field from the root of the data
+    fn selected_text(&self) -> String {
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return String::new(); };
+        let text = path.to_text(&self.data).unwrap_or_default();
+        match self.focus.last() {
+            Some(focus) if !text.is_empty() => format!("{}.{}", focus.text, text),
+            Some(focus) => focus.text.clone(),
+            None => text,
+        }
+    }
+
+    fn autosave(&mut self) {
+        if let Err(e) = self.journal.autosave(Instant::now()) {
+            self.message = Some(format!("error: cannot write the recovery file: {}", e));
+            self.need_update = true;
+        }
+    }
+
+    // ask to replay the changes of a previous session, if it was not finished normally
+    pub fn offer_recovery(&mut self) -> io::Result<()> {
+        if let Some(script) = self.journal.load() {
+            self.recovery = Some(script);
+            self.after_command(CommandResult::ShowMessage("unsaved changes of a previous session found: Y - replay, other key - discard".into()))?;
+        }
+        self.after_event()
+    }
+
+    fn on_recovery_key(&mut self, event: KeyEvent, script: &str) -> io::Result<bool> {
+        if !matches!(event.code, KeyCode::Char('y' | 'Y')) {
+            self.journal.clear()?;
+            self.after_command(CommandResult::ShowMessage("the changes of the previous session are discarded".into()))?;
+            return Ok(true);
+        }
+        // applied to the file content to keep the data as is if a command fails
+        let result = read_data_file(&self.file_path, &self.proto, self.data.def.clone()).
+            and_then(|mut data| batch::apply_script(&mut data, &self.proto, script).map(|count| (data, count)));
+        match result {
+            Ok((data, count)) => {
+                self.data = Arc::new(data);
+                for line in script.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+                    self.journal.push(line.to_string());
+                }
+                self.selected = Selection::default();
+                self.layouts = Layouts::new(&self.data, &self.layout_config, self.layouts.file_name.clone(), self.layouts.width, self.layouts.height);
+                self.need_update_layout_height = true;
+                self.after_command(CommandResult::ShowMessage(format!("{} changes replayed, F2 - review and save", count)))?;
+            }
+            Err(e) => self.after_command(CommandResult::ShowError(format!("cannot replay the changes: {}", e)))?,
+        }
+        Ok(true)
+    }
+    // offer to reload the data file changed by another program, once for each change
+    fn check_disk(&mut self) -> io::Result<()> {
+        if self.disk_time.is_none() || self.reload_offer || self.review.is_some() || self.help.is_some() || self.report.is_some() || self.prompt.is_some() ||
+            self.recovery.is_some() || self.pending_change.is_some() || self.overflow_edit.is_some() {
+            return Ok(());
+        }
+        let time = modified_time(&self.file_path);
+        if time.is_none() || time == self.disk_time { return Ok(()); }
+        self.disk_time = time;
+        self.offer_reload("the file is changed on disk")
+    }
+
+    fn offer_reload(&mut self, reason: &str) -> io::Result<()> {
+        self.reload_offer = true;
+        let lost = match self.journal.commands.len() {
+            0 => String::new(),
+            count => format!(" ({} unsaved changes are lost)", count),
+        };
+        self.after_command(CommandResult::ShowMessage(format!("{}: Y - reload{}, other key - keep the data", reason, lost)))
+    }
+
+    #[cfg(test)]
+    fn collapsed_paths(&self) -> Vec<FieldPath> {
+        self.layouts.items.iter().
+            filter(|item| item.layout.as_ref().map_or(false, |layout| layout.layout_type() == LayoutType::Collapsed)).
+            map(|item| item.path.clone()).collect()
+    }
+
+    fn on_reload_key(&mut self, event: KeyEvent) -> io::Result<bool> {
+        if let KeyCode::Char('y' | 'Y') = event.code {
+            self.reload()?;
+        } else {
+            self.after_command(CommandResult::ShowMessage("the data is kept, F2 - review the changes against the file".into()))?;
+        }
+        Ok(true)
+    }
+
+    // read the data file again, the selection and collapsed messages are kept where their paths still exist
+    fn reload(&mut self) -> io::Result<()> {
+        let data = match read_data_file(&self.file_path, &self.proto, self.data.def.clone()) {
+            Ok(data) => data,
+            Err(e) => return self.after_command(CommandResult::ShowError(format!("cannot read {}: {}", self.file_path.display(), e))),
+        };
+        while self.close_focus() {}
+        let selected = self.layouts.selected_path(&self.selected).unwrap_or_default();
+
+        self.data = Arc::new(data);
+        self.disk_time = modified_time(&self.file_path);
+        self.history = History::default();
+        self.range = None;
+        self.pending_change = None;
+        // the changes are discarded with the old data, failing to remove the recovery file is not an error of reading
+        let _ = self.journal.clear();
+        self.restore_view(selected)?;
+        self.after_command(CommandResult::ShowMessage("reloaded".into()))
+    }
+
+    // new layouts of the data with the value selected as before, the collapsed messages still in the data stay collapsed
+    fn restore_view(&mut self, mut selected: FieldPath) -> io::Result<()> {
+        self.rebuild_layouts(&FieldPath::new());
+        // the nearest parent of a removed value is selected, a field not set is shown with the default value
+        while !selected.0.is_empty() && self.data.get_field(&selected.0).is_none() &&
+            (selected.0.last().unwrap().index > 0 || self.data.get_field_definition(&selected).is_none()) {
+            selected.0.pop();
+        }
+        self.selected = Selection::default();
+        if !selected.0.is_empty() { self.run_command(UserCommand::GoToPath(selected))?; }
         Ok(())
     }
+
     fn set_sizes(&mut self, width: u16, height: u16) {
         self.width = width;
         self.height = height;
         self.layouts.height = height - TOP_LINE;
-        self.layouts.width = width;
+        self.layouts.width = width - self.right_width();
         self.need_update = true;
     }
+
+    fn sidebar_shown(&self) -> bool { self.sidebar && !self.pane_shown() && self.width >= SIDEBAR_MIN_SCREEN }
+
+    fn pane_shown(&self) -> bool { self.pane && self.width >= PANE_MIN_SCREEN }
+
+    // the columns at the right of the data taken by the pane or the sidebar
+    fn right_width(&self) -> u16 {
+        if self.pane_shown() {
+            self.pane_width.clamp(PANE_MIN_WIDTH, self.width - TREE_MIN_WIDTH)
+        } else if self.sidebar_shown() {
+            SIDEBAR_WIDTH
+        } else {
+            0
+        }
+    }
+
+    fn toggle_pane(&mut self) -> io::Result<()> {
+        self.pane = !self.pane;
+        if self.pane_width == 0 { self.pane_width = self.width / 2; }
+        self.set_sizes(self.width, self.height);
+        self.need_update_layout_height = true;
+        if self.pane && !self.pane_shown() {
+            return self.after_command(CommandResult::ShowMessage(format!("the detail pane is shown on screens of {} columns and wider", PANE_MIN_SCREEN)));
+        }
+        Ok(())
+    }
+
+    // a positive step moves the divider left and makes the pane wider
+    fn move_divider(&mut self, step: i32) -> io::Result<()> {
+        if !self.pane_shown() { return Ok(()); }
+        self.pane_width = (self.right_width() as i32 + step).clamp(PANE_MIN_WIDTH as i32, (self.width - TREE_MIN_WIDTH) as i32) as u16;
+        self.set_sizes(self.width, self.height);
+        self.need_update_layout_height = true;
+        Ok(())
+    }
+
+    // after a change of the layout config all the layouts are sized again; the other updates of the heights
+    // size only the layouts not sized for the width, the indent and the config revision
+    fn config_changed(&mut self) {
+        self.layouts.revision += 1;
+        self.need_update_layout_height = true;
+    }
+
+    fn toggle_sidebar(&mut self) -> io::Result<()> {
+        self.sidebar = !self.sidebar;
+        self.set_sizes(self.width, self.height);
+        self.need_update_layout_height = true;
+        if self.sidebar && !self.sidebar_shown() {
+            return self.after_command(CommandResult::ShowMessage(format!("the sidebar is shown on screens of {} columns and wider", SIDEBAR_MIN_SCREEN)));
+        }
+        Ok(())
+    }
+
+    // the description of the selected field, the definition of a field not set
+    fn sidebar_lines(&self) -> Vec<String> {
+        let width = (SIDEBAR_WIDTH - 2) as usize;
+        let path = self.layouts.selected_path(&self.selected);
+        let Some((path, def)) = path.and_then(|path| self.data.get_field_definition(&path).map(|def| (path, def))) else {
+            return vec!["  no field selected".into()];
+        };
+        let size = self.data.encoded_size(&path, 1, &self.proto);
+        sidebar::lines(&self.selected_text(), &def, self.data.get_field(&path.0), size, &self.proto, width)
+    }
+
+    // the whole value of the selected field, with the other values of a repeated message for its table
+    fn pane_lines(&self) -> Vec<String> {
+        let width = (self.right_width() - 2) as usize;
+        let path = self.layouts.selected_path(&self.selected);
+        let Some((path, def)) = path.and_then(|path| self.data.get_field_definition(&path).map(|def| (path, def))) else {
+            return vec!["  no field selected".into()];
+        };
+        let Some((last, parent)) = path.0.split_last() else { return vec![]; };
+        let values: Vec<&FieldData> = match self.data.get_submessage(parent) {
+            Some(msg) if def.is_message() && def.repeated() => msg.fields.iter().filter(|field| field.def.id() == last.id).collect(),
+            _ => vec![],
+        };
+        let mut lines = sidebar::detail_lines(&self.selected_text(), &def, self.data.get_field(&path.0), &values, last.index, &self.proto, width);
+        let height = self.data_height() as usize;
+        if lines.len() > height {
+            let more = lines.len() - height + 1;
+            lines.truncate(height - 1);
+            lines.push(format!("  … {} more lines", more));
+        }
+        lines
+    }
     fn after_event(&mut self) -> io::Result<()> {
         if self.need_update_layout_height { // after show/hidde comment for example
             self.layouts.update_layouts(&self.data, &self.layout_config);
@@ -185,6 +806,7 @@ impl App {
     pub fn on_resize(&mut self, width: u16, height: u16) -> io::Result<bool> {
         self.set_sizes(width, height);
         self.stdout.execute(terminal::Clear(terminal::ClearType::All))?;
+        self.screen.clear();
         Ok(true)
     }
     pub fn on_focus(&mut self, focus: bool) -> io::Result<bool> {
@@ -192,1028 +814,4132 @@ impl App {
         Ok(true)
     }
     pub fn on_mouse(&mut self, event: MouseEvent) -> io::Result<bool> {
+        if self.review.is_some() || self.help.is_some() || self.report.is_some() || self.prompt.is_some() { return Ok(true); }
         match event.kind {
             MouseEventKind::ScrollUp => { self.run_command(ScrollVertically(-3))?; }
             MouseEventKind::ScrollDown => { self.run_command(ScrollVertically(3))?; }
+            MouseEventKind::Down(MouseButton::Left) => {
+                if self.message.take().is_some() { self.need_update = true; }
+                self.pending_change = None;
+                self.drag_row = Some(event.row);
+                let now = Instant::now();
+                let double_click = match self.last_click {
+                    Some((time, column, row)) => column == event.column && row == event.row && now - time < DOUBLE_CLICK_TIME,
+                    None => false,
+                };
+                self.last_click = if double_click { None } else { Some((now, event.column, event.row)) };
+                self.on_click(event.column, event.row, double_click)?;
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                if let Some(row) = self.drag_row {
+                    // the content follows the mouse pointer
+                    let delta = row as isize - event.row as isize;
+                    if delta != 0 { self.run_command(ScrollVertically(delta))?; }
+                }
+                self.drag_row = Some(event.row);
+                self.last_click = None;
+            }
+            MouseEventKind::Up(MouseButton::Left) => { self.drag_row = None; }
             _ => {}
         }
         Ok(true)
     }
-    pub fn on_key(&mut self, event: KeyEvent) -> io::Result<bool> {
-        if event.kind != KeyEventKind::Press { return Ok(true); }
-        match event.code {
-            KeyCode::F(n) => match n {
-                4 => {
-                    let new_order =
-                        if event.modifiers.contains(KeyModifiers::SHIFT) { self.layout_config.field_order.prev() } else { self.layout_config.field_order.next() };
-                    self.run_command(ChangeFieldOrder(new_order))?;
-                }
-                5 => {
+    fn on_click(&mut self, column: u16, row: u16, double_click: bool) -> io::Result<()> {
+        let top = self.data_top();
+        if row < top || row >= top + self.data_height() { return Ok(()); }
+        let line = self.layouts.scroll + (row - top) as usize;
+        if let Some(selection) = self.layouts.selection_at(&self.data, &self.layout_config, line, column) {
+            self.selected = selection;
+            self.range = None;
+            self.need_update = true;
+            if double_click {
+                if let Some(LayoutType::Message | LayoutType::Collapsed) = self.current_layout_type() {
+                    self.selected.x = 0;
+                    self.selected.y = 0;
                     self.run_command(CollapsedToggle)?;
                 }
-                6 => {
-                    self.layout_config.show_comments = self.layout_config.show_comments.next();
-                    self.need_update_layout_height = true;
-                }
-                10 => return Ok(false),
-                _ => {}
-            },
-            KeyCode::Esc => return Ok(false),
-            KeyCode::Enter => self.run_command(CollapsedToggle)?,
-            KeyCode::Up => {
-                self.run_command(if event.modifiers.contains(KeyModifiers::CONTROL) { ScrollSibling(-1) } else { ScrollVertically(-1) })?;
             }
-            KeyCode::Down => {
-                self.run_command(if event.modifiers.contains(KeyModifiers::CONTROL) { ScrollSibling(1) } else { ScrollVertically(1) })?;
+        }
+        Ok(())
+    }
+    fn current_layout_type(&self) -> Option<LayoutType> {
+        self.layouts.items.get(self.selected.layout)?.layout.as_ref().map(|layout| layout.layout_type())
+    }
+    pub fn on_key(&mut self, event: KeyEvent) -> io::Result<bool> {
+        let time = self.key_time.take();
+        // any other key, or the release of the key held, ends the repeats
+        let repeat = self.scroll_repeat.take();
+        if event.kind != KeyEventKind::Press { return Ok(true); }
+        if self.message.take().is_some() { self.need_update = true; }
+        if let Some((_, keys)) = &mut self.recording {
+            let macro_key = self.prompt.is_none() && self.review.is_none() && self.help.is_none() && self.report.is_none() && self.pending_change.is_none() &&
+                matches!(self.keymap.action(&event), Some(Action::MacroRecord | Action::MacroReplay | Action::MacroReplayAll));
+            if !macro_key { keys.push(event); }
+        }
+        if self.review.is_some() {
+            return self.on_review_key(event);
+        }
+        if self.help.is_some() {
+            return self.on_help_key(event);
+        }
+        if self.report.is_some() {
+            return self.on_report_key(event);
+        }
+        if self.prompt.is_some() {
+            return self.on_prompt_key(event);
+        }
+        if let Some(script) = self.recovery.take() {
+            return self.on_recovery_key(event, &script);
+        }
+        if std::mem::take(&mut self.reload_offer) {
+            return self.on_reload_key(event);
+        }
+        if let Some(change) = self.pending_change.take() {
+            if let KeyCode::Char('y' | 'Y') = event.code {
+                self.apply_change(change);
+            } else {
+                self.after_command(CommandResult::ShowMessage("canceled".into()))?;
             }
-            KeyCode::PageUp => { self.run_command(ScrollVertically(-((self.height - TOP_LINE - 1) as isize)))?; }
-            KeyCode::PageDown => { self.run_command(ScrollVertically((self.height - TOP_LINE - 1) as isize))?; }
-            KeyCode::Home => if event.modifiers.contains(KeyModifiers::CONTROL) {
-                self.selected = Selection::default();
-                self.need_update = true;
-            } else { self.run_command(crate::UserCommand::Home)?; }
-            KeyCode::End => self.run_command(if event.modifiers.contains(KeyModifiers::CONTROL) { ScrollToBottom } else { End })?,
-            KeyCode::Left => { self.run_command(ScrollHorizontally(-1))?; }
-            KeyCode::Right => { self.run_command(ScrollHorizontally(1))?; }
-
-            KeyCode::Delete => self.run_command(DeleteData)?,
-            KeyCode::Insert => self.run_command(InsertData)?,
-            _ => {}
+            return Ok(true);
+        }
+        if let Some((path, text)) = self.overflow_edit.take() {
+            return self.on_overflow_key(event, path, &text);
+        }
+        if self.replacing.as_ref().is_some_and(|replacing| !replacing.paths.is_empty()) {
+            return self.on_replace_key(event);
+        }
+        if let Some(action) = self.keymap.action(&event) {
+            self.scroll_repeat = Self::next_repeat(repeat, action, time);
+            return self.run_action(action);
         }
         Ok(true)
     }
 
-    fn run_command(&mut self, command: UserCommand) -> io::Result<()> {
-        let result =
-            match command {
-                ChangeFieldOrder(order) => {
-                    self.layout_config.field_order = order;
-                    self.selected = Selection::default();
-                    self.need_update_layout_height = true;
-                    self.layouts = Layouts::new(&self.data, &self.layout_config, self.layouts.file_name.clone(), self.layouts.width, self.layouts.height);
-                    CommandResult::Redraw
-                }
-                ScrollVertically(delta) => {
-                    if delta < 0 {
-                        self.layouts.ensure_loaded(&self.data, &self.layout_config, self.selected.layout, -delta as usize + 1 + self.height as usize, 0, &mut self.selected);
-                    } else {
-                        self.layouts.ensure_loaded(&self.data, &self.layout_config, self.selected.layout, 0, delta as usize + 1, &mut self.selected);
-                    }
-                    self.layouts.run_command(command, &self.data, &self.layout_config, &mut self.selected)
-                }
-                _ => self.layouts.run_command(command, &self.data, &self.layout_config, &mut self.selected)
-            };
+    fn next_repeat(repeat: Option<(Instant, isize, usize)>, action: Action, time: Option<Instant>) -> Option<(Instant, isize, usize)> {
+        let direction = match action {
+            Action::LineUp => -1,
+            Action::LineDown => 1,
+            _ => return None,
+        };
+        let now = time?;
+        match repeat {
+            Some((last, last_direction, count)) if last_direction == direction && now.saturating_duration_since(last) < KEY_REPEAT_TIME => Some((now, direction, count + 1)),
+            _ => Some((now, direction, 0)),
+        }
+    }
 
-        self.after_command(result)
+    // lines scrolled by Up or Down: one, doubled while the key is held, at most a page
+    fn scroll_step(&self) -> isize {
+        let count = self.scroll_repeat.map_or(0, |(_, _, count)| count);
+        let page = self.data_height().saturating_sub(1).max(1) as usize;
+        (1usize << (count / REPEATS_TO_DOUBLE).min(16)).min(page) as isize
     }
 
-    fn after_command(&mut self, result: CommandResult) -> io::Result<()> {
-        match result {
-            CommandResult::Redraw => {
+    // return false to quit
+    fn run_action(&mut self, action: Action) -> io::Result<bool> {
+        // the range is kept only by the commands working with it
+        if !matches!(action, Action::RangeUp | Action::RangeDown | Action::DeleteData | Action::Copy | Action::HexDump) {
+            self.range = None;
+        }
+        match action {
+            Action::Quit => {
+                while self.close_focus() {}
+                return Ok(false);
+            }
+            Action::Back => if !self.close_focus() { return Ok(false); },
+            Action::DrillDown => self.open_focus()?,
+            Action::Reload => if self.journal.commands.is_empty() { self.reload()? } else { self.offer_reload("the changes are not saved")? },
+            Action::NextFieldOrder => self.run_command(ChangeFieldOrder(self.layout_config.field_order.next()))?,
+            Action::PrevFieldOrder => self.run_command(ChangeFieldOrder(self.layout_config.field_order.prev()))?,
+            Action::CollapsedToggle => self.run_command(CollapsedToggle)?,
+            Action::ExpandAll => self.run_command(UserCommand::ExpandAll)?,
+            Action::CollapseAll => self.run_command(UserCommand::CollapseAll)?,
+            Action::CollapseToDepth(depth) => self.run_command(UserCommand::CollapseToDepth(depth))?,
+            Action::CommentsVisibility => {
+                self.layout_config.show_comments = self.layout_config.show_comments.next();
+                self.config_changed();
+            }
+            Action::LineUp => self.run_command(ScrollVertically(-self.scroll_step()))?,
+            Action::LineDown => self.run_command(ScrollVertically(self.scroll_step()))?,
+            Action::SiblingUp => self.run_command(ScrollSibling(-1))?,
+            Action::SiblingDown => self.run_command(ScrollSibling(1))?,
+            Action::PageUp => self.run_command(ScrollVertically(-(self.data_height().saturating_sub(1) as isize)))?,
+            Action::PageDown => self.run_command(ScrollVertically(self.data_height().saturating_sub(1) as isize))?,
+            Action::Home => self.run_command(crate::UserCommand::Home)?,
+            Action::ResetSelection => {
+                self.selected = Selection::default();
                 self.need_update = true;
             }
-            CommandResult::ChangeData(mut change) => {
-                self.data.apply(&mut change);
-                self.layouts.update_after_data_changed(&self.data, &self.layout_config, self.selected.layout);
+            Action::End => self.run_command(End)?,
+            Action::ScrollToBottom => self.run_command(ScrollToBottom)?,
+            Action::Left => self.run_command(ScrollHorizontally(-1))?,
+            Action::Right => self.run_command(ScrollHorizontally(1))?,
+            Action::DeleteData => self.delete_range()?,
+            Action::RangeUp => self.extend_range(-1)?,
+            Action::RangeDown => self.extend_range(1)?,
+            Action::Copy => self.copy()?,
+            Action::Undo => self.undo(false)?,
+            Action::Redo => self.undo(true)?,
+            Action::InsertData => self.run_command(InsertData)?,
+            Action::ProtectToggle => self.protect_toggle()?,
+            Action::Save => self.start_review()?,
+            Action::SaveAs => self.save_as()?,
+            Action::GoToPath => self.start_go_to_path(),
+            Action::GoToRecord => self.start_go_to_record()?,
+            Action::GroupFirst => self.run_command(UserCommand::GroupFirst)?,
+            Action::GroupLast => self.run_command(UserCommand::GroupLast)?,
+            Action::NextSet => self.run_command(UserCommand::NextSet)?,
+            Action::PrevSet => self.run_command(UserCommand::PrevSet)?,
+            Action::Stats => self.show_stats()?,
+            Action::HexDump => self.start_hex_dump()?,
+            Action::EditValue => self.start_edit()?,
+            Action::PackedToggle => self.toggle_packed()?,
+            Action::MergeGroups => self.start_merge()?,
+            Action::SetDefault => self.set_presence(true)?,
+            Action::ClearField => self.set_presence(false)?,
+            Action::WatchToggle => self.toggle_watch()?,
+            Action::Highlight => self.start_highlight(),
+            Action::PinToggle => self.toggle_pin()?,
+            Action::GoToOffset => self.start_go_to_offset()?,
+            Action::SchemaAudit => self.start_schema_audit()?,
+            Action::ReloadSchema => self.reload_schema()?,
+            Action::DecodeBytes => self.toggle_decoded()?,
+            Action::Columns => self.start_columns()?,
+            Action::FieldFormat => self.start_field_format()?,
+            Action::Overview => self.show_overview(),
+            Action::Schema => self.show_schema(),
+            Action::History => self.show_history()?,
+            Action::MergeFile => self.start_merge_file()?,
+            Action::ExportBytes => self.start_export_bytes()?,
+            Action::ImportBytes => self.start_import_bytes()?,
+            Action::Replace => self.start_replace()?,
+            Action::SetSiblings => self.start_set_siblings()?,
+            Action::Breadcrumbs => self.show_breadcrumbs(),
+            Action::Search => self.start_search(),
+            Action::Inspect => self.show_encoding()?,
+            Action::SidebarToggle => self.toggle_sidebar()?,
+            Action::DetailPane => self.toggle_pane()?,
+            Action::PaneWider => self.move_divider(PANE_STEP as i32)?,
+            Action::PaneNarrower => self.move_divider(-(PANE_STEP as i32))?,
+            Action::UnknownEnums => self.show_unknown_enums(0)?,
+            Action::PerfOverlay => {
+                self.perf_overlay = !self.perf_overlay;
+                self.need_update = true;
+            }
+            Action::Help => {
+                self.help = Some(Help { lines: self.keymap.help_lines(), scroll: 0 });
+                self.need_update = true;
+            }
+            Action::MacroRecord => self.toggle_recording()?,
+            Action::MacroReplay => self.start_replay(false)?,
+            Action::MacroReplayAll => self.start_replay(true)?,
+            Action::ReadOnlyToggle => {
+                self.read_only = !self.read_only;
+                let text = if self.read_only { "read-only mode" } else { "changes allowed" };
+                self.after_command(CommandResult::ShowMessage(text.into()))?;
+            }
+            Action::WrapToggle => {
+                self.run_command(UserCommand::WrapToggle)?;
                 self.need_update_layout_height = true;
             }
-
-            _ => {}
+            Action::WrapAllToggle => {
+                self.layout_config.wrap_strings = !self.layout_config.wrap_strings;
+                if self.current_layout_type() == Some(LayoutType::Str) { self.selected.y = 0; }
+                self.config_changed();
+            }
+            Action::EncodingToggle => {
+                self.layout_config.show_encoding = !self.layout_config.show_encoding;
+                self.config_changed();
+            }
+            Action::EscapesToggle => {
+                self.layout_config.raw_strings = !self.layout_config.raw_strings;
+                self.config_changed();
+                let text = if self.layout_config.raw_strings { "control chars of strings are printed as is" } else { "control chars of strings are escaped" };
+                self.after_command(CommandResult::ShowMessage(text.into()))?;
+            }
+            Action::OffsetsToggle => {
+                // hex offsets, then decimal ones, then none
+                let config = &mut self.layout_config;
+                let text = match (config.bytes_offsets, config.hex_addresses) {
+                    (false, _) => { config.bytes_offsets = true; "hex" }
+                    (true, true) => { config.hex_addresses = false; "decimal" }
+                    (true, false) => { config.bytes_offsets = false; config.hex_addresses = true; "hidden" }
+                };
+                if self.current_layout_type() == Some(LayoutType::Bytes) { self.selected = Selection { layout: self.selected.layout, ..Selection::default() }; }
+                self.config_changed();
+                self.after_command(CommandResult::ShowMessage(format!("bytes offsets: {}", text)))?;
+            }
+            Action::SizesToggle => {
+                self.layout_config.show_sizes = !self.layout_config.show_sizes;
+                self.need_update = true;
+                let text = if self.layout_config.show_sizes { "the saved size of each field is shown" } else { "sizes are hidden" };
+                self.after_command(CommandResult::ShowMessage(text.into()))?;
+            }
+            Action::BinaryToggle => {
+                self.layout_config.show_binary = !self.layout_config.show_binary;
+                self.need_update = true;
+                let text = if self.layout_config.show_binary { "values of another wire type than declared are marked" } else { "wire type marks are hidden" };
+                self.after_command(CommandResult::ShowMessage(text.into()))?;
+            }
         }
-        Ok(())
+        Ok(true)
     }
-    fn get_top_line(&self, width: u16, config: &LayoutConfig) -> String {
-        let mut parts = Vec::with_capacity(3);
-
-        parts.push(self.layouts.file_name.clone());
-        if let Some(current) = self.layouts.items.get(self.selected.layout) {
-            debug_assert!(current.layout.is_some());
-            let percent = 100.0 * self.layouts.calc_relative_pos(self.selected.layout);
-            parts.push(current.get_status_string(self.selected.x, self.selected.y));
-            parts.push(format!("{:.0}% {}", percent, config.field_order.first_letter()));
-        }
-
-        loop {
-            let total_len: u16 = parts.iter().map(|s| s.len() as u16).sum();
-            if total_len < width - MARGIN_LEFT - MARGIN_RIGHT {
-                let avail_len = width - total_len - MARGIN_LEFT - MARGIN_RIGHT;
-                let span = avail_len / (parts.len() as u16 - 1);
-                let last_span = avail_len - span * (parts.len() as u16 - 2);
-
-                let mut res = " ".repeat(MARGIN_LEFT as usize);
-                for i in 0..parts.len() {
-                    res += &parts[i];
-
-                    if i < parts.len() - 1 {
-                        let span = if i == parts.len() - 2 { last_span } else { span };
-                        res += &" ".repeat(span as usize);
-                    }
-                }
 
-                res += &" ".repeat(MARGIN_RIGHT as usize);
-                return res;
-            } else {
-                match parts.len() { // remove parts of the line if no room
-                    3 => { parts.remove(0); }
-                    2 => { parts.remove(1); }
-                    _ => return String::new(),
+    fn on_prompt_key(&mut self, event: KeyEvent) -> io::Result<bool> {
+        let Some(prompt) = &mut self.prompt else { return Ok(true); };
+        self.need_update = true;
+        match prompt.on_key(&event) {
+            PromptResult::Continue if prompt.name == SEARCH_PROMPT => {
+                let text = prompt.text();
+                self.search_next(&text)?;
+            }
+            PromptResult::Continue => {}
+            PromptResult::Cancel => {
+                let name = prompt.name;
+                self.prompt = None;
+                if let Some(path) = self.search_start.take().filter(|_| name == SEARCH_PROMPT) {
+                    self.run_command(UserCommand::GoToPath(path))?;
                 }
             }
+            PromptResult::Accept(text) => {
+                let name = prompt.name;
+                self.prompt = None;
+                self.prompt_history.add(name, text.clone());
+                self.on_prompt_accept(name, &text)?;
+            }
         }
+        Ok(true)
     }
 
-    // find out the line number with active cursor
-    fn calc_scroll_pos(&self) -> usize { // move to layouts
-        let mut selected_line = 0;
-        let mut y = 0;
-        for index in 0..self.layouts.items.len() {
-            let item = &self.layouts.items[index];
-            if self.selected.layout == index {
-                //-                debug_assert!(self.selected.x == 0); // for other columns algorithm more complex
-                selected_line = y + self.selected.y;
-                break;
+    fn on_prompt_accept(&mut self, name: &str, text: &str) -> io::Result<()> {
+        match name {
+            GO_TO_PATH_PROMPT => match FieldPath::parse_in(text, &self.data, &self.proto) {
+                Ok(path) => self.run_command(UserCommand::GoToPath(path)),
+                Err(e) => self.after_command(CommandResult::ShowError(e.to_string())),
+            },
+            EDIT_PROMPT => self.on_value_entered(text),
+            HEX_DUMP_PROMPT => match self.write_hex_dump(text) {
+                Ok(_) => self.after_command(CommandResult::ShowMessage(format!("saved to {}", text))),
+                Err(e) => self.after_command(CommandResult::ShowError(format!("cannot save: {}", e))),
+            },
+            GO_TO_RECORD_PROMPT => self.go_to_record(text),
+            MERGE_PROMPT => self.load_merge(text.trim()),
+            EXPORT_BYTES_PROMPT => self.export_bytes(text.trim()),
+            IMPORT_BYTES_PROMPT => self.import_bytes(text.trim()),
+            REPLACE_PROMPT => self.start_replace_with(text),
+            HIGHLIGHT_PROMPT => self.add_highlight(text.trim()),
+            SEARCH_PROMPT => { self.search_start = None; Ok(()) }
+            REPLACE_WITH_PROMPT => self.start_replacing(text),
+            EXPORT_CHANGES_PROMPT => match self.export_changes(text) {
+                Ok(count) => self.after_command(CommandResult::ShowMessage(format!("{} commands saved to {}", count, text.trim()))),
+                Err(e) => self.after_command(CommandResult::ShowError(format!("cannot save: {}", e))),
+            },
+            GO_TO_OFFSET_PROMPT => match parse_offset(text) {
+                Some(offset) => self.run_command(UserCommand::GoToOffset(offset)),
+                None => Ok(()),
+            },
+            SCHEMA_PROMPT => self.run_schema_audit(text),
+            RECORD_PROMPT => {
+                self.recording = Some((text.trim().to_string(), vec![]));
+                self.after_command(CommandResult::ShowMessage(format!("recording {}, Ctrl+T - stop", text.trim())))
             }
-            y += item.height;
-        }
-        // correct scroll position if active cursor is above/below visible window
-        if selected_line + 1 >= self.layouts.scroll + (self.height - TOP_LINE) as usize {
-            return selected_line + 1 - (self.height - TOP_LINE) as usize;
-        }
-        if selected_line < self.layouts.scroll {
-            return selected_line;
+            DECODE_PROMPT => self.decode_bytes(text.trim()),
+            COLUMNS_PROMPT => self.set_columns(text),
+            FORMAT_PROMPT => self.set_field_format(text.trim()),
+            REPLAY_PROMPT => self.replay(text.trim(), false),
+            REPLAY_ALL_PROMPT => self.replay(text.trim(), true),
+            SAVE_PROMPT => {
+                self.output = Some(text.into());
+                self.save()
+            }
+            _ => Ok(()),
         }
-        self.layouts.scroll
     }
 
-    fn print_top_line(&mut self) -> io::Result<()> {
-        if TOP_LINE > 0 {
-            let mut last_pos = 0;
-            let mut current_pos = 0;
-            for index in 0..self.layouts.items.len() {
-                let item = &self.layouts.items[index];
-                if self.selected.layout == index {
-                    current_pos = last_pos + self.selected.y;
-                }
-                last_pos += item.height;
-            }
-            self.stdout.queue(TextStyle::TopLine.activate())?;
-            self.stdout.queue(style::Print(self.get_top_line(self.width, &self.layout_config)))?;
-        }
-        Ok(())
+    // count, min, max, mean and sum of the selected repeated field in the top line
+    fn show_stats(&mut self) -> io::Result<()> {
+        let Some(item) = self.layouts.items.get(self.selected.layout) else { return Ok(()); };
+        let path = &item.path.0;
+        let def = self.data.get_field_definition(&item.path).filter(|def| def.repeated() && !def.is_message());
+        let message = match (def, self.data.get_submessage(&path[..path.len() - 1])) {
+            (Some(def), Some(parent)) => match stats::Stats::calc(parent, def.id()) {
+                Some(stats) => format!("{}: {}", def.name(), stats),
+                None => format!("{}: no numeric values", def.name()),
+            },
+            _ => "select a repeated numeric field".into(),
+        };
+        self.after_command(CommandResult::ShowMessage(message))
     }
 
+    // add the selected field (or value of a repeated one) to the watch list, or remove it
+    fn toggle_watch(&mut self) -> io::Result<()> {
+        let Some(relative) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let path = self.absolute(&relative);
+        let text = if let Some(pos) = self.watches.iter().position(|(watched, _)| watched.0 == path.0) {
+            format!("{} is not watched", self.watches.remove(pos).1)
+        } else {
+            let Some(name) = self.absolute_text(&relative) else {
+                return self.after_command(CommandResult::ShowMessage("unknown fields cannot be watched".into()));
+            };
+            self.watches.push((path, name.clone()));
+            format!("{} is watched", name)
+        };
+        self.after_command(CommandResult::ShowMessage(text))
+    }
 
-    fn first_visible_line(&self) -> (usize, usize) {
-        let mut skip_lines = self.layouts.scroll;
-        let mut lines_len = 0;
-        for layout_index in 0..self.layouts.items.len() {
-            let item = &self.layouts.items[layout_index];
+    // the condition is for the values of the repeated message containing the selection, of the root if there is none
+    fn start_highlight(&mut self) {
+        let path = self.layouts.selected_path(&self.selected).unwrap_or_default();
+        let def = (1..=path.0.len()).rev().map(|len| FieldPath(path.0[..len].to_vec())).
+            filter_map(|prefix| self.data.get_field_definition(&prefix)).find(|def| def.is_message() && def.repeated()).
+            and_then(|def| self.proto.get_message_definition(&def.typename())).unwrap_or(self.data.def.clone());
+        let count = self.highlights.0.iter().filter(|rule| rule.type_name == def.name).count();
+        let title = match count {
+            0 => format!("highlight the {} values where", def.name),
+            count => format!("highlight the {} values where ({} set, empty - remove them)", def.name, count),
+        };
+        self.prompt = Some(Prompt::new(HIGHLIGHT_PROMPT, &title, &self.prompt_history));
+        self.highlight_type = Some(def);
+        self.need_update = true;
+    }
 
-            //if item.layout.is_none() { return (0, 0); }
-            //            if item.layout.is_none() { panic!("layout is not loaded: {}", layout_index); }
-            //            debug_assert!(item.layout.is_some());   // TODO
-
-            debug_assert!(item.height > 0);
-            lines_len = item.height;
-            if lines_len > skip_lines {
-                return (layout_index, skip_lines);
-            }
-            skip_lines -= lines_len;
+    fn add_highlight(&mut self, text: &str) -> io::Result<()> {
+        let Some(def) = self.highlight_type.take() else { return Ok(()); };
+        if text.is_empty() {
+            self.highlights.0.retain(|rule| rule.type_name != def.name);
+            self.need_update = true;
+            return self.after_command(CommandResult::ShowMessage(format!("the {} values are not highlighted", def.name)));
         }
+        let expr = match expr::Expr::parse(text, &def, &self.proto) {
+            Ok(expr) => expr,
+            Err(e) => return self.after_command(CommandResult::ShowError(e.to_string())),
+        };
+        self.highlights.0.push(highlight::Rule { type_name: def.name.clone(), expr });
+        let (matched, total) = self.highlights.count(&self.data, &def.name);
+        self.need_update = true;
+        self.after_command(CommandResult::ShowMessage(format!("{} of {} {} values highlighted", matched, total, def.name)))
+    }
 
-        if self.layouts.items.is_empty() {
-            (0, 0)
+    fn toggle_pin(&mut self) -> io::Result<()> {
+        let Some(relative) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let path = self.absolute(&relative);
+        let text = if let Some(pos) = self.pins.iter().position(|(pinned, _)| pinned.0 == path.0) {
+            format!("{} is not pinned", self.pins.remove(pos).1)
+        } else if self.pins.len() >= PINNED_LINES {
+            return self.after_command(CommandResult::ShowMessage(format!("{} fields are pinned already", PINNED_LINES)));
         } else {
-            (self.layouts.items.len() - 1, lines_len - 1)
-        }
+            let Some(name) = self.absolute_text(&relative) else {
+                return self.after_command(CommandResult::ShowMessage("unknown fields cannot be pinned".into()));
+            };
+            self.pins.push((path, name.clone()));
+            format!("{} is pinned", name)
+        };
+        self.after_command(CommandResult::ShowMessage(text))
     }
 
-    // output data to the screen
-    fn update(&mut self) -> io::Result<()> {
-        self.stdout.queue(cursor::MoveTo(0, 0))?;
+    // the path of the field of the focused message from the root of the document, None for unknown fields
+    fn absolute_text(&self, relative: &FieldPath) -> Option<String> {
+        let name = relative.to_text(&self.data)?;
+        Some(match self.focus.last() {
+            Some(focus) => format!("{}.{}", focus.text, name),
+            None => name,
+        })
+    }
 
-        let (layout_index, mut skip_lines) = self.first_visible_line();
-        self.layouts.ensure_loaded(&self.data, &self.layout_config, layout_index, 0, self.height as usize + skip_lines, &mut self.selected);
+    // lines of the data, the rest is used by the pinned fields, the watch list and the performance counters
+    fn data_height(&self) -> u16 {
+        (self.height - TOP_LINE).saturating_sub((self.pin_lines().len() + self.watch_lines().len()) as u16 + self.perf_overlay as u16)
+    }
 
-        self.print_top_line()?;
-        let mut y = TOP_LINE;
+    // the first line of the data on the screen
+    fn data_top(&self) -> u16 {
+        TOP_LINE + self.pin_lines().len() as u16
+    }
 
-        let mut current_style = TextStyle::Unknown;
-        for index in layout_index..self.layouts.items.len() {
-            let item = &mut self.layouts.items[index];
-            let cursor = if index == self.selected.layout { Some((self.selected.x, self.selected.y)) } else { None };
-            let indent = self.layouts.indents[item.level() - 1];
+    // the current values of the pinned fields and a divider below them
+    fn pin_lines(&self) -> Vec<ScreenLine> {
+        if self.pins.is_empty() { return vec![]; }
+        let mut res: Vec<ScreenLine> = self.pins.iter().map(|(path, name)| self.value_line(path, name, ": ")).collect();
+        let mut divider = ScreenLine::new(self.width);
+        divider.add_string("─".repeat(self.width as usize), TextStyle::Divider);
+        divider.fix_length(self.width);
+        res.push(divider);
+        res
+    }
+
+    fn value_line(&self, path: &FieldPath, name: &str, separator: &str) -> ScreenLine {
+        let value = self.field_at(path).map_or("no data".into(), diff::value_to_string);
+        let mut line = ScreenLine::new(self.width);
+        line.add_string(" ".repeat(self.layout_config.margin_left as usize), TextStyle::Divider);
+        line.add_string(name.to_string(), TextStyle::FieldName);
+        line.add_string(separator.to_string(), TextStyle::Divider);
+        line.add_string(value, TextStyle::Value);
+        line.fix_length(self.width.saturating_sub(self.layout_config.margin_right));
+        line.fix_length(self.width);
+        line
+    }
+
+    // the title and current values of the watched fields
+    fn watch_lines(&self) -> Vec<ScreenLine> {
+        if self.watches.is_empty() { return vec![]; }
+        let mut res = vec![];
+        let mut title = ScreenLine::new(self.width);
+        title.add_string(format!(" watch ({}) ", self.watches.len()), TextStyle::Divider);
+        title.add_string("─".repeat(self.width as usize), TextStyle::Divider);
+        title.fix_length(self.width);
+        res.push(title);
+        let count = WATCH_LINES.min((self.height / 3) as usize).max(1);
+        for (path, name) in self.watches.iter().take(count) {
+            res.push(self.value_line(path, name, " → "));
+        }
+        res
+    }
 
-            let mut lines = item.get_screen(&self.data, self.layouts.width, indent, &self.layout_config, cursor);
+    // the encoding of the selected repeated field for saving, the data itself does not change
+    fn toggle_packed(&mut self) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        let Some(item) = self.layouts.items.get(self.selected.layout) else { return Ok(()); };
+        let path = item.path.0.clone();
+        let Some(def) = self.data.get_field_definition(&item.path).filter(MessageData::packable) else {
+            return self.after_command(CommandResult::ShowMessage("select a repeated numeric field".into()));
+        };
+        let Some(parent) = Arc::make_mut(&mut self.data).get_submessage_mut(&path[..path.len() - 1]) else {
+            return self.after_command(CommandResult::ShowError("the parent message has no data".into()));
+        };
+        let packed = parent.toggle_packed(def.id());
+        self.layouts.update_after_value_changed(&self.data, &self.layout_config, self.selected.layout);
+        self.need_update_layout_height = true;
+        self.after_command(CommandResult::ShowMessage(format!("{} will be saved {}", def.name(), if packed { "packed" } else { "unpacked" })))
+    }
 
-            if skip_lines > 0 {
-                lines.0.drain(..skip_lines);
-                skip_lines = 0;
+    // the byte layout of the parent message after merging the groups of the selected repeated field is shown first
+    fn start_merge(&mut self) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let Some(def) = self.data.get_field_definition(&path).filter(|def| def.repeated()) else {
+            return self.after_command(CommandResult::ShowMessage("select a repeated field".into()));
+        };
+        let (last, parent_path) = path.0.split_last().unwrap();
+        let Some(parent) = self.data.get_submessage(parent_path) else { return Ok(()); };
+        if parent.group_count(last.id) < 2 {
+            return self.after_command(CommandResult::ShowMessage(format!("the values of {} are together already", def.name())));
+        }
+        match parent.wire_runs(&self.proto, Some(last.id)) {
+            Ok(runs) => {
+                let mut lines = vec!["  offset   size  field".to_string()];
+                lines.extend(runs.iter().map(|run| run.to_string()));
+                let path = FieldPath(parent_path.iter().cloned().chain([FieldPos { id: last.id, index: 0 }]).collect());
+                self.review = Some(Review { lines, scroll: 0, merge: Some(path), lossy: 0 });
+                self.need_update = true;
+                Ok(())
             }
+            Err(e) => self.after_command(CommandResult::ShowError(e.to_string())),
+        }
+    }
 
-            for line in lines.0 {
-                let mut text = String::new();
-                for (c, s) in line.0 {
-                    if s != current_style {
-                        if !text.is_empty() {
-                            self.stdout.queue(current_style.activate())?;
-                            self.stdout.queue(style::Print(text))?;
-                            text = String::new();
-                        }
-                        current_style = s;
-                    }
-                    text.push(c);
+    fn merge_groups(&mut self, path: FieldPath) -> io::Result<()> {
+        let command = path.to_text(&self.data).map(|text| format!("merge {}", text));
+        let (last, parent_path) = path.0.split_last().unwrap();
+        let Some(parent) = Arc::make_mut(&mut self.data).get_submessage_mut(parent_path) else { return Ok(()); };
+        let groups = parent.merge_groups(last.id);
+        if let Some(command) = command { self.journal.push(command); }
+        self.rebuild_layouts(&path);
+        let name = self.data.get_field_definition(&path).map_or(String::new(), |def| def.name());
+        self.after_command(CommandResult::ShowMessage(format!("{}: {} groups merged", name, groups)))
+    }
+
+    // the selected field gets its default value kept in the data, or it is removed from the data
+    fn set_presence(&mut self, present: bool) -> io::Result<()> {
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let Some(def) = self.data.get_field_definition(&path).filter(|def| !def.repeated()) else {
+            return self.after_command(CommandResult::ShowMessage("select a field that is not repeated".into()));
+        };
+        if self.data.get_submessage(&path.0[..path.0.len() - 1]).is_none() {
+            return self.after_command(CommandResult::ShowError("the parent message has no data".into()));
+        }
+        let change = match (present, self.data.get_field(&path.0)) {
+            (true, None) => Change { path, action: ChangeType::Insert(def.default()) },
+            (true, Some(field)) if !def.is_message() && field.value != def.default() => Change { path, action: ChangeType::Overwrite(def.default()) },
+            (false, Some(_)) => Change::delete_value(path),
+            _ => return self.after_command(CommandResult::ShowMessage(format!("{} is {}", def.name(), if present { "set already" } else { "not set" }))),
+        };
+        self.after_command(CommandResult::ChangeData(change))
+    }
+
+    // the layouts of bytes fields with renderers are replaced by text ones
+    fn set_renderers(&mut self, renderers: Renderers) {
+        self.layout_config.renderers = renderers;
+        self.selected = Selection::default();
+        self.layouts = Layouts::new(&self.data, &self.layout_config, self.layouts.file_name.clone(), self.layouts.width, self.layouts.height);
+        self.need_update_layout_height = true;
+    }
+
+    // the selected scalar value in the prompt
+    fn start_edit(&mut self) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let Some(def) = self.data.get_field_definition(&path).filter(|def| !def.is_message()) else {
+            return self.after_command(CommandResult::ShowMessage("select a value to edit".into()));
+        };
+        let renderer = self.layout_config.renderers.find(&self.data, &path);
+        let range_def = def.clone();
+        let text = self.edit_text(&path).unwrap_or_default();
+        let prompt = Prompt::new(EDIT_PROMPT, &def.name(), &self.prompt_history).with_text(&text).
+            with_validator(move |text| {
+                if let Some(renderer) = renderer {
+                    return if renderer.parse_value(text, &def.typename()).is_some() { None } else { Some(format!("not a {} value", renderer)) };
                 }
-                if !text.is_empty() {
-                    self.stdout.queue(current_style.activate())?;
-                    self.stdout.queue(style::Print(text))?;
+                let valid = def.parse_value(text).is_some() || CommonFieldProto::out_of_range(def.as_ref(), text);
+                if valid { None } else { Some(format!("not a {} value", def.typename())) }
+            }).
+            with_warning(move |text| CommonFieldProto::range_error(range_def.as_ref(), text));
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
+
+    // the text of the value in the edit prompt, None if there is no value
+    fn edit_text(&self, path: &FieldPath) -> Option<String> {
+        let def = self.data.get_field_definition(path)?;
+        let renderer = self.layout_config.renderers.find(&self.data, path);
+        Some(match &self.data.get_field(&path.0)?.value {
+            FieldValue::SCALAR(ScalarValue::ENUM(value)) => def.get_enum_name_by_index(*value).map_or(value.to_string(), |name| name.to_string()),
+            FieldValue::SCALAR(ScalarValue::STR(value)) if renderer.is_some() => renderer.unwrap().edit_text(value.as_bytes()).unwrap_or(value.to_string()),
+            FieldValue::SCALAR(value @ ScalarValue::BYTES(bytes)) if renderer.is_some() => renderer.unwrap().edit_text(bytes).unwrap_or(value.to_string()),
+            FieldValue::SCALAR(value) => self.layout_config.float_format.format(value).unwrap_or(value.to_string()),
+            FieldValue::MESSAGE(_) => String::new(),
+        })
+    }
+
+    fn on_value_entered(&mut self, text: &str) -> io::Result<()> {
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let Some(def) = self.data.get_field_definition(&path) else { return Ok(()); };
+        // the value is kept as is when the text is not changed, e.g. the payload of a NaN
+        if self.edit_text(&path).is_some_and(|old| old == text) {
+            return Ok(());
+        }
+        if let Some(renderer) = self.layout_config.renderers.find(&self.data, &path) {
+            return match renderer.parse_value(text, &def.typename()) {
+                Some(value) => self.set_value(path, value),
+                None => Ok(()),
+            };
+        }
+        if let Some(value) = def.parse_value(text) {
+            return self.set_value(path, value);
+        }
+        if let Some(error) = CommonFieldProto::range_error(def.as_ref(), text) {
+            let signed = CommonFieldProto::integer_range(&def.typename()).map_or(false, |(min, _, _)| min < 0);
+            self.message = Some(format!("{}: C - clamp, W - wrap{}, other key - cancel", error, if signed { ", U - show as unsigned" } else { "" }));
+            self.overflow_edit = Some((path, text.to_string()));
+            self.need_update = true;
+        }
+        Ok(())
+    }
+
+    // C - the nearest allowed value, W - lower bits of the value, U - the same bits shown as unsigned
+    fn on_overflow_key(&mut self, event: KeyEvent, path: FieldPath, text: &str) -> io::Result<bool> {
+        let Some(def) = self.data.get_field_definition(&path) else { return Ok(true); };
+        let signed = CommonFieldProto::integer_range(&def.typename()).map_or(false, |(min, _, _)| min < 0);
+        let overflow = match event.code {
+            KeyCode::Char('c' | 'C') => Overflow::Clamp,
+            KeyCode::Char('w' | 'W') => Overflow::Wrap,
+            KeyCode::Char('u' | 'U') if signed => {
+                if !self.layout_config.unsigned.iter().any(|field| Arc::ptr_eq(field, &def)) {
+                    self.layout_config.unsigned.push(def.clone());
                 }
-                self.stdout.queue(cursor::MoveToNextLine(1))?;
-                y += 1;
-                if y >= self.height { break; }
+                Overflow::Wrap
             }
-            if y >= self.height { break; }
+            _ => {
+                self.after_command(CommandResult::ShowMessage("canceled".into()))?;
+                return Ok(true);
+            }
+        };
+        if let Some(value) = CommonFieldProto::parse_with_overflow(def.as_ref(), text, overflow) {
+            self.set_value(path, value)?;
         }
-        if y < self.height { // fill the free space below if any
-            self.stdout.queue(style::ResetColor)?;
-            // ?           self.stdout.execute(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        Ok(true)
+    }
 
-            self.stdout.queue(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+    fn set_value(&mut self, path: FieldPath, value: ScalarValue) -> io::Result<()> {
+        let change = if self.data.get_field(&path.0).is_some() {
+            Change::change_value(path, value)
+        } else if self.data.get_submessage(&path.0[..path.0.len() - 1]).is_some() {
+            Change::insert_scalar(path, value)
+        } else {
+            return self.after_command(CommandResult::ShowError("the parent message has no data".into()));
+        };
+        self.after_command(CommandResult::ChangeData(change))
+    }
+
+    // the selected field does not change while the prompt is shown
+    fn start_hex_dump(&mut self) -> io::Result<()> {
+        if !self.selected_values().iter().any(|path| self.data.get_field(&path.0).is_some()) {
+            return self.after_command(CommandResult::ShowMessage("the field has no data".into()));
         }
-        self.stdout.flush()
+        let prompt = Prompt::new(HEX_DUMP_PROMPT, "hex dump to", &self.prompt_history).
+            with_text(&format!("{}.hex", self.file_path.display())).
+            with_validator(|text| if text.trim().is_empty() { Some("a file name expected".into()) } else { None });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
     }
 
-    #[cfg(test)]
-    fn to_strings(&mut self) -> Vec<String> {
-        let mut y = TOP_LINE;
-        let mut res = vec![];
+    // the tag, length and value of the selected field (or of the values of the range) as encoded for saving
+    fn write_hex_dump(&self, file_name: &str) -> io::Result<()> {
+        let fields: Vec<&FieldData> = self.selected_values().iter().filter_map(|path| self.data.get_field(&path.0)).collect();
+        let field = fields.first().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the field has no data"))?;
+        let mut bytes = vec![];
+        for field in &fields { field.write(&mut bytes, &self.proto)?; }
+        // packed values are encoded one by one, their offsets are not the same as in the file
+        let offset = match &field.wire {
+            Some(wire) if !field.packed() => wire.tag_pos,
+            _ => 0,
+        };
+        let status = if fields.len() > 1 { format!("{} values", fields.len()) } else { field.wire_status() };
+        let text = format!("# {} {}\n{}", field.def.name(), status, hex_dump(&bytes, offset));
+        std::fs::write(file_name.trim(), text)
+    }
 
-        let (layout_index, mut skip_lines) = self.first_visible_line();
-        self.layouts.ensure_loaded(&self.data, &self.layout_config, layout_index, 0, self.height as usize + skip_lines, &mut self.selected);
+    // the path and the content of the selected bytes value
+    fn selected_bytes(&self) -> Option<(FieldPath, &[u8])> {
+        let path = self.layouts.selected_path(&self.selected)?;
+        match &self.data.get_field(&path.0)?.value {
+            FieldValue::SCALAR(ScalarValue::BYTES(bytes)) => Some((path, &bytes[..])),
+            _ => None,
+        }
+    }
 
-        for index in layout_index..self.layouts.items.len() {
-            let item = &self.layouts.items[index];
-            let cursor = if index == self.selected.layout { Some((self.selected.x, self.selected.y)) } else { None };
-            let indent = self.layouts.indents[item.level() - 1];
+    fn start_export_bytes(&mut self) -> io::Result<()> {
+        if self.selected_bytes().is_none() {
+            return self.after_command(CommandResult::ShowMessage("select a bytes value".into()));
+        }
+        let prompt = Prompt::new(EXPORT_BYTES_PROMPT, "save the bytes to a file, or copy as hex, base64", &self.prompt_history).
+            with_text(&format!("{}.bin", self.file_path.display())).
+            with_validator(|text| if text.trim().is_empty() { Some("a file name expected".into()) } else { None });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
 
-            let mut lines = item.get_screen(&self.data, self.layouts.width, indent, &self.layout_config, cursor);
+    // "hex" and "base64" copy the text to the terminal clipboard, other text is a file name for the raw bytes
+    fn export_bytes(&mut self, text: &str) -> io::Result<()> {
+        let Some((_, bytes)) = self.selected_bytes() else { return Ok(()); };
+        let size = overview::size_text(bytes.len());
+        let copied = match text {
+            "hex" => bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            "base64" => render::base64_encode(bytes),
+            _ => return match std::fs::write(text, bytes) {
+                Ok(()) => self.after_command(CommandResult::ShowMessage(format!("{} saved to {}", size, text))),
+                Err(e) => self.after_command(CommandResult::ShowError(format!("cannot save: {}", e))),
+            },
+        };
+        self.to_clipboard(copied)?;
+        self.after_command(CommandResult::ShowMessage(format!("{} copied as {}", size, text)))
+    }
 
-            if skip_lines > 0 {
-                lines.0.drain(..skip_lines);
-                skip_lines = 0;
-            }
+    // a bytes field without data can be imported too
+    fn start_import_bytes(&mut self) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        let def = self.layouts.selected_path(&self.selected).and_then(|path| self.data.get_field_definition(&path));
+        if def.map_or(true, |def| def.typename() != "bytes") {
+            return self.after_command(CommandResult::ShowMessage("select a bytes field".into()));
+        }
+        let prompt = Prompt::new(IMPORT_BYTES_PROMPT, "replace the bytes with a file, or hex, base64 text", &self.prompt_history).
+            with_validator(|text| parse_bytes(text.trim()).err());
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
 
-            for line in lines.0 {
-                res.push(line.0.into_iter().map(|v| v.0).collect());
-                y += 1;
-                if y >= self.height { break; }
+    // the old and the new sizes are shown
+    fn import_bytes(&mut self, text: &str) -> io::Result<()> {
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let bytes = match parse_bytes(text) {
+            Ok(bytes) => bytes,
+            Err(e) => return self.after_command(CommandResult::ShowError(e)),
+        };
+        let old_size = self.selected_bytes().map_or(0, |(_, old)| old.len());
+        let message = format!("{} imported, was {}", overview::size_text(bytes.len()), overview::size_text(old_size));
+        self.set_value(path, ScalarValue::BYTES(bytes.into()))?;
+        if self.pending_change.is_none() && !self.message.as_ref().is_some_and(|text| text.starts_with("error:")) {
+            self.after_command(CommandResult::ShowMessage(message))?;
+        }
+        Ok(())
+    }
+
+    // the first value containing the typed text is selected as it's typed, from the selection on
+    fn start_search(&mut self) {
+        self.search_start = Some(self.layouts.selected_path(&self.selected).unwrap_or_default());
+        self.prompt = Some(Prompt::new(SEARCH_PROMPT, "search", &self.prompt_history));
+        self.need_update = true;
+    }
+
+    fn search_next(&mut self, text: &str) -> io::Result<()> {
+        let Some(start) = self.search_start.clone() else { return Ok(()); };
+        if text.is_empty() { return self.run_command(UserCommand::GoToPath(start)); }
+        match self.data.find_text_from(text, &start) {
+            Some(path) => self.run_command(UserCommand::GoToPath(path)),
+            None => {
+                if let Some(prompt) = &mut self.prompt { prompt.set_error("not found".into()); }
+                Ok(())
             }
-            if y >= self.height { break; }
         }
-        res
     }
-}
 
-impl Drop for App {
-    fn drop(&mut self) {
-        if !self.test_mode {
-            let _ = self.stdout.execute(DisableBracketedPaste);
-            let _ = self.stdout.execute(DisableFocusChange);
-            if USE_ALTERNATIVE_SCREEN { let _ = self.stdout.execute(LeaveAlternateScreen); }
-            let _ = crossterm::terminal::disable_raw_mode();
-            let _ = self.stdout.execute(cursor::Show);
+    fn start_replace(&mut self) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
         }
+        // the whole document is searched
+        while self.close_focus() {}
+        let prompt = Prompt::new(REPLACE_PROMPT, "replace in strings (/regex/ for a regular expression)", &self.prompt_history).
+            with_validator(|text| replace::Pattern::parse(text).err());
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
     }
-}
 
-fn exit_with_error<T: std::fmt::Display>(message: T, code: i32) {
-    let _ = io::stderr().execute(style::SetForegroundColor(Color::Red));
-    eprint!("error");
-    let _ = io::stderr().execute(style::ResetColor);
-    eprintln!(": {}", message);
-    exit(code);
-}
+    fn start_replace_with(&mut self, text: &str) -> io::Result<()> {
+        let pattern = match replace::Pattern::parse(text) {
+            Ok(pattern) => pattern,
+            Err(e) => return self.after_command(CommandResult::ShowError(e)),
+        };
+        self.replacing = Some(Replacing { pattern, with: String::new(), paths: vec![], replaced: 0, values: 0, skipped: 0 });
+        self.prompt = Some(Prompt::new(REPLACE_WITH_PROMPT, &format!("replace {} with", text), &self.prompt_history));
+        self.need_update = true;
+        Ok(())
+    }
 
+    // protected values are not changed
+    fn start_replacing(&mut self, text: &str) -> io::Result<()> {
+        let Some(mut replacing) = self.replacing.take() else { return Ok(()); };
+        replacing.with = text.to_string();
+        replacing.paths = replace::find_values(&self.data, &replacing.pattern).into_iter().filter(|path| !self.is_protected(path)).collect();
+        if replacing.paths.is_empty() {
+            return self.after_command(CommandResult::ShowMessage("not found".into()));
+        }
+        self.replacing = Some(replacing);
+        self.ask_replace()
+    }
 
-/// Protobuf editor
-#[derive(Parser, Debug)]
-#[command(
-    version,
-    about,
-    long_about = "\nTerminal-based protobuf data files editor.\nhttps://github.com/friend2025/protoedit"
-)]
-struct Args {
-    /// Input file: data.pb{;format.proto{;message_name}}
-    file: String,
+    // the next value found is selected, the question shows it replaced
+    fn ask_replace(&mut self) -> io::Result<()> {
+        let Some(path) = self.replacing.as_ref().and_then(|replacing| replacing.paths.first().cloned()) else {
+            return self.finish_replace(false);
+        };
+        self.run_command(UserCommand::GoToPath(path.clone()))?;
+        let Some((old, new)) = self.replaced_value(&path) else { return Ok(()); };
+        let name = path.to_text(&self.data).unwrap_or_default();
+        self.message = Some(format!("{}: {:?} → {:?}, Y - replace, N - skip, A - all, Esc - stop", name, old, new));
+        self.need_update = true;
+        Ok(())
+    }
 
-    /// Set of directories for proto files search
-    #[arg(short = 'I', long = "proto_path")]
-    proto_path: Vec<PathBuf>,
-}
+    fn replaced_value(&self, path: &FieldPath) -> Option<(String, String)> {
+        let replacing = self.replacing.as_ref()?;
+        let FieldValue::SCALAR(ScalarValue::STR(old)) = &self.data.get_field(&path.0)?.value else { return None; };
+        Some((old.to_string(), replacing.pattern.replace_all(old, &replacing.with).0))
+    }
 
+    // Y replaces the value, N skips it, A replaces all the values left, other keys stop
+    fn on_replace_key(&mut self, event: KeyEvent) -> io::Result<bool> {
+        let Some(replacing) = &mut self.replacing else { return Ok(true); };
+        match event.code {
+            KeyCode::Char('y' | 'Y') => {
+                let path = replacing.paths.remove(0);
+                self.replace_value(path);
+                self.layouts.update_after_data_changed(&self.data, &self.layout_config, self.selected.layout);
+            }
+            KeyCode::Char('n' | 'N') => {
+                replacing.paths.remove(0);
+                replacing.skipped += 1;
+            }
+            KeyCode::Char('a' | 'A') => {
+                for path in std::mem::take(&mut replacing.paths) { self.replace_value(path); }
+                self.layouts.update_after_data_changed(&self.data, &self.layout_config, self.selected.layout);
+            }
+            _ => return self.finish_replace(true).map(|_| true),
+        }
+        self.ask_replace()?;
+        Ok(true)
+    }
 
-fn main() -> io::Result<()> {
-    let args = Args::parse();
+    // each value is a step, they are joined at the end
+    fn replace_value(&mut self, path: FieldPath) {
+        let Some(replacing) = &self.replacing else { return; };
+        let FieldValue::SCALAR(ScalarValue::STR(old)) = &self.data.get_field(&path.0).unwrap().value else { return; };
+        let (new, count) = replacing.pattern.replace_all(old, &replacing.with);
+        self.apply_step(vec![Change::change_value(path, ScalarValue::STR(new.into()))]);
+        if let Some(replacing) = &mut self.replacing {
+            replacing.replaced += count;
+            replacing.values += 1;
+        }
+    }
 
-    let mut it = args.file.split(";");
-    let binary_file = it.next().unwrap();
-    let mut proto_file = String::new();
-    let mut root_message_name = String::new();
-    if let Some(path) = it.next() {
-        proto_file = path.to_string();
-        if let Some(path) = it.next() {
-            root_message_name = path.to_string();
+    // all the replacements are undone at once
+    fn finish_replace(&mut self, stopped: bool) -> io::Result<()> {
+        let Some(replacing) = self.replacing.take() else { return Ok(()); };
+        if replacing.values > 1 {
+            let steps = self.history.undo.split_off(self.history.undo.len() - replacing.values);
+            let mut step = Step { changes: vec![], commands: vec![] };
+            for mut other in steps {
+                step.changes.append(&mut other.changes);
+                step.commands.append(&mut other.commands);
+            }
+            self.history.undo.push(step);
         }
-        assert!(it.next().is_none());
+        let plural = |count: usize| if count == 1 { "" } else { "s" };
+        let mut text = format!("{} replacement{} in {} value{}", replacing.replaced, plural(replacing.replaced), replacing.values, plural(replacing.values));
+        if replacing.skipped > 0 { text += &format!(", {} skipped", replacing.skipped); }
+        if stopped && !replacing.paths.is_empty() { text += &format!(", stopped before {} more", replacing.paths.len()); }
+        self.after_command(CommandResult::ShowMessage(text))
     }
 
-    // if no proto file provided, use the file with the same name as data file but with proto extension
-    if proto_file.is_empty() {
-        proto_file = binary_file.trim_end_matches(".pb").to_string() + ".proto";
+    // the selected value is set in the same field of the other values of the repeated message around it;
+    // the changes are listed first, missing parent messages are inserted, protected values are left
+    fn start_set_siblings(&mut self) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let Some(FieldData { def, value: FieldValue::SCALAR(value), .. }) = self.data.get_field(&path.0) else {
+            return self.after_command(CommandResult::ShowError("a value set in the data expected".into()));
+        };
+        let (def, value) = (def.clone(), value.clone());
+        let Some(len) = (1..path.0.len()).rev().find(|&len| self.data.get_field_definition(&FieldPath(path.0[..len].to_vec())).
+            is_some_and(|def| def.repeated() && def.is_message())) else {
+            return self.after_command(CommandResult::ShowError("the field is not in a repeated message".into()));
+        };
+        let group = FieldPath(path.0[..len].to_vec());
+        let id = group.0[len - 1].id;
+        let count = self.data.get_submessage(&group.0[..len - 1]).map_or(0, |msg| msg.fields.iter().filter(|f| f.id() == id).count());
+        let text = |scalar: &ScalarValue| batch::value_text(&def, scalar).unwrap_or_else(|| scalar.to_string());
+
+        let (mut lines, mut same, mut left) = (vec![], 0, 0);
+        self.sibling_changes.clear();
+        for index in (0..count).filter(|&index| index != group.0[len - 1].index) {
+            let mut target = path.clone();
+            target.0[len - 1].index = index;
+            let Some(target_text) = target.to_text(&self.data).or_else(|| group.with_last_index(index).to_text(&self.data)) else { continue; };
+            match self.data.get_field(&target.0).map(|field| &field.value) {
+                Some(FieldValue::SCALAR(old)) if *old == value => same += 1,
+                _ if self.is_protected(&target) => left += 1,
+                Some(FieldValue::SCALAR(old)) => {
+                    lines.push((format!("{}: {} → {}", target_text, text(old), text(&value)), Some(target_text)));
+                    self.sibling_changes.push(Change::change_value(target, value.clone()));
+                }
+                _ => match self.missing_changes(&path, &target, &value) {
+                    Some(mut changes) => {
+                        let nearest = group.with_last_index(index).to_text(&self.data);
+                        lines.push((format!("{}: not set → {}", target_text, text(&value)), nearest));
+                        self.sibling_changes.append(&mut changes);
+                    }
+                    None => left += 1,
+                },
+            }
+        }
+        let name = self.data.get_field_definition(&group).map_or(String::new(), |def| def.name().to_string());
+        let mut title = format!("{} = {} in {} of {} other {} values", def.name(), text(&value), lines.len(), count - 1, name);
+        if same > 0 { title += &format!(", {} the same", same); }
+        if left > 0 { title += &format!(", {} left", left); }
+        if lines.is_empty() {
+            return self.after_command(CommandResult::ShowMessage(title));
+        }
+        title += ", Y - apply, Enter - go to, Esc - cancel";
+        self.report = Some(Report { title, lines, selected: 0, scroll: 0, search: None, kind: ReportKind::Siblings });
+        self.need_update = true;
+        Ok(())
     }
 
-    if !std::fs::exists(&binary_file)? {
-        exit_with_error(format!("file \"{}\" is not available", binary_file), 101);
+    // the value and the parent messages of it missing in a sibling are inserted, the first missing one must be
+    // the next value of its field; None if the sibling has fewer values of a repeated field on the way
+    fn missing_changes(&self, path: &FieldPath, target: &FieldPath, value: &ScalarValue) -> Option<Vec<Change>> {
+        let mut changes = vec![];
+        for len in 1..=target.0.len() {
+            let prefix = FieldPath(target.0[..len].to_vec());
+            if changes.is_empty() && self.data.get_field(&prefix.0).is_some() { continue; }
+            let pos = &target.0[len - 1];
+            let existing = self.data.get_submessage(&target.0[..len - 1]).map_or(0, |msg| msg.fields.iter().filter(|f| f.id() == pos.id).count());
+            if pos.index != if changes.is_empty() { existing } else { 0 } { return None; }
+            if len == target.0.len() {
+                changes.push(Change::insert_scalar(prefix, value.clone()));
+            } else {
+                // the type of the message as in the selected value
+                let def = self.data.get_submessage(&path.0[..len])?.def.clone();
+                changes.push(Change::insert_message(prefix, MessageData { def, fields: vec![], packed: vec![] }));
+            }
+        }
+        Some(changes)
     }
-    if !std::fs::exists(&proto_file)? {
-        exit_with_error(format!("proto definitions file \"{}\" is not available", proto_file), 102);
+
+    // the listed changes are undone at once
+    fn set_siblings(&mut self) -> io::Result<()> {
+        let changes = std::mem::take(&mut self.sibling_changes);
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let count = self.report.take().map_or(0, |report| report.lines.len());
+        self.apply_step(changes);
+        self.rebuild_layouts(&path);
+        let name = self.data.get_field_definition(&path).map_or(String::new(), |def| def.name().to_string());
+        self.after_command(CommandResult::ShowMessage(format!("{} set in {} value{}", name, count, if count == 1 { "" } else { "s" })))
     }
 
-    for dir in &args.proto_path {
-        if !dir.is_absolute() {
-            eprintln!("The proto_path argument should contain an absolute path.");
-            break;
-        }
-        if !dir.is_dir() {
-            eprintln!("The proto_path is not a directory: {}", dir.display());
+    pub fn go_to(&mut self, text: &str) -> io::Result<()> {
+        self.on_prompt_accept(GO_TO_PATH_PROMPT, text)?;
+        self.after_event()
+    }
+
+    pub fn find(&mut self, text: &str) -> io::Result<()> {
+        match self.data.find_text(text) {
+            Some(path) => self.run_command(UserCommand::GoToPath(path))?,
+            None => self.after_command(CommandResult::ShowMessage(format!("\"{}\" not found", text)))?,
         }
+        self.after_event()
+    }
+
+    fn start_go_to_path(&mut self) {
+        let (root, proto) = (self.data.def.clone(), self.proto.clone());
+        let prompt = Prompt::new(GO_TO_PATH_PROMPT, "go to", &self.prompt_history).
+            with_validator(move |text| FieldPath::parse(text, &root, &proto).err().map(|e| e.to_string()));
+        self.prompt = Some(prompt);
+        self.need_update = true;
     }
 
-    let mut proto_files = ProtoFile::new_with_imports(proto_file.into(), args.proto_path);
+    // the number of a value of the top-level repeated message field, from 1 as in the top line
+    fn start_go_to_record(&mut self) -> io::Result<()> {
+        let path = self.layouts.selected_path(&self.selected);
+        let Some((id, count)) = Layouts::records(&self.data, path.as_ref()) else {
+            return self.after_command(CommandResult::ShowMessage("no repeated messages at the top level".into()));
+        };
+        let name = self.data.def.get_field(id).map_or(String::new(), |def| def.name());
+        let prompt = Prompt::new(GO_TO_RECORD_PROMPT, &format!("go to {} (1-{})", name, count), &self.prompt_history).
+            with_validator(move |text| match text.trim().parse::<usize>() {
+                Ok(number) if (1..=count).contains(&number) => None,
+                _ => Some(format!("a number from 1 to {} expected", count)),
+            });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
 
-    let mut proto = ProtoData::new(&proto_files.remove(0).content)?;
+    fn go_to_record(&mut self, text: &str) -> io::Result<()> {
+        let path = self.layouts.selected_path(&self.selected);
+        let (Some((id, count)), Ok(number)) = (Layouts::records(&self.data, path.as_ref()), text.trim().parse::<usize>()) else { return Ok(()); };
+        if number == 0 || number > count { return Ok(()); }
+        self.run_command(UserCommand::GoToPath(FieldPath(vec![FieldPos { id, index: number - 1 }])))
+    }
 
-    let mut root_msg = None;
-    if root_message_name.is_empty() {
-        root_msg = proto.auto_detect_root_message(); // search only in the main proto file
-        if root_msg.is_none() {
-            exit_with_error("cannot choose the root message in the proto definition file; please provide it manually", 103);
+    fn start_go_to_offset(&mut self) -> io::Result<()> {
+        if self.current_layout_type() != Some(LayoutType::Bytes) {
+            return self.after_command(CommandResult::ShowMessage("select a bytes field".into()));
         }
+        let prompt = Prompt::new(GO_TO_OFFSET_PROMPT, "go to offset", &self.prompt_history).
+            with_validator(|text| if parse_offset(text).is_none() { Some("a number expected, 0x for hex".into()) } else { None });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
     }
 
-    // merge imported proto files
-    for file in proto_files.into_iter() {
-        proto.append(ProtoData::new(&file.content)?);
+    // the review screen: scroll, Y (or Enter, F2) to save, any other key to cancel
+    fn on_review_key(&mut self, event: KeyEvent) -> io::Result<bool> {
+        let page = (self.height - TOP_LINE - 1) as isize;
+        let lossy = self.review.as_ref().is_some_and(|review| review.lossy > 0);
+        let delta = match self.keymap.action(&event) {
+            _ if matches!(event.code, KeyCode::Char('y' | 'Y')) => 0,
+            Some(Action::Save | Action::CollapsedToggle) if !lossy => 0,
+            Some(Action::LineUp) => -1,
+            Some(Action::LineDown) => 1,
+            Some(Action::PageUp) => -page,
+            Some(Action::PageDown) => page,
+            _ => {
+                let text = if self.review.take().is_some_and(|review| review.merge.is_some()) { "merge canceled" } else { "save canceled" };
+                self.after_command(CommandResult::ShowMessage(text.into()))?;
+                return Ok(true);
+            }
+        };
+        if delta == 0 {
+            match self.review.take().and_then(|review| review.merge) {
+                Some(path) => self.merge_groups(path)?,
+                None => self.save()?,
+            }
+        } else if let Some(review) = &mut self.review {
+            let max_scroll = review.lines.len().saturating_sub((self.height - TOP_LINE) as usize);
+            review.scroll = review.scroll.saturating_add_signed(delta).min(max_scroll);
+            self.need_update = true;
+        }
+        Ok(true)
     }
-    proto = proto.finalize()?;
 
-    if root_msg.is_none() {
-        root_msg = proto.get_message_definition(&root_message_name);
-        if root_msg.is_none() {
-            exit_with_error(format!("root message \"{}\" not found", root_message_name), 104);
+    fn on_help_key(&mut self, event: KeyEvent) -> io::Result<bool> {
+        let page = (self.height - TOP_LINE - 1) as isize;
+        let delta = match self.keymap.action(&event) {
+            Some(Action::LineUp) => -1,
+            Some(Action::LineDown) => 1,
+            Some(Action::PageUp) => -page,
+            Some(Action::PageDown) => page,
+            _ => {
+                self.help = None;
+                self.need_update = true;
+                return Ok(true);
+            }
+        };
+        if let Some(help) = &mut self.help {
+            let max_scroll = help.lines.len().saturating_sub((self.height - TOP_LINE) as usize);
+            help.scroll = help.scroll.saturating_add_signed(delta).min(max_scroll);
+            self.need_update = true;
         }
+        Ok(true)
     }
 
-    println!("loading...");
-    let file = std::fs::File::open(binary_file)?;
-    let mut limit = file.metadata()?.len() as u32;
-    let mut reader = PbReader::new(file);
-    let data = MessageData::new(&mut reader, &proto, root_msg.unwrap(), &mut limit)?;
+    // the type of the selected bytes field is asked, a decoded field is shown as bytes again
+    fn toggle_decoded(&mut self) -> io::Result<()> {
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let (last, parent) = path.0.split_last().unwrap();
+        let proto_def = self.data.get_submessage(parent).and_then(|msg| msg.def.get_field(last.id));
+        if proto_def.as_ref().map_or(true, |def| def.typename() != "bytes") {
+            return self.after_command(CommandResult::ShowMessage("select a bytes field".into()));
+        }
+        if self.data.get_field_definition(&path).map_or(false, |def| def.is_message()) {
+            let command = path.to_text(&self.data).map(|text| format!("encode {}", text));
+            return match Arc::make_mut(&mut self.data).encode_bytes(&path, &self.proto) {
+                Ok(_) => {
+                    if let Some(command) = command { self.journal.push(command); }
+                    self.rebuild_layouts(&path);
+                    self.after_command(CommandResult::ShowMessage(format!("{} is shown as bytes", proto_def.unwrap().name())))
+                }
+                Err(e) => self.after_command(CommandResult::ShowError(e.to_string())),
+            };
+        }
+        let proto = self.proto.clone();
+        let prompt = Prompt::new(DECODE_PROMPT, "decode as message", &self.prompt_history).
+            with_validator(move |text| if proto.get_message_definition(text.trim()).is_some() { None } else { Some("a message type expected".into()) });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
 
-    App::new(data, binary_file.into())?.run()
-}
+    // the type of the selected message field, present in the data or not
+    fn selected_message_type(&self) -> Option<MessageProtoPtr> {
+        let path = self.layouts.selected_path(&self.selected)?;
+        let def = self.data.get_field_definition(&path).filter(|def| def.is_message())?;
+        self.proto.get_message_definition(&def.typename())
+    }
 
+    fn start_columns(&mut self) -> io::Result<()> {
+        let Some(msg_def) = self.selected_message_type() else {
+            return self.after_command(CommandResult::ShowMessage("select a message field".into()));
+        };
+        let text = self.layout_config.messages.get(&msg_def.name).map(config::columns_text).unwrap_or_default();
+        let prompt = Prompt::new(COLUMNS_PROMPT, &format!("columns of {}", msg_def.name), &self.prompt_history).with_text(&text).
+            with_validator(move |text| match config::parse_columns(text) {
+                Ok(columns) => columns.columns.iter().find(|name| !msg_def.fields.iter().any(|f| f.name() == **name)).
+                    map(|name| format!("no field {} in {}", name, msg_def.name)),
+                Err(e) => Some(e),
+            });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
 
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-/**************************************************************************************************/
-
-#[cfg(test)]
-mod app_tests {
-    use std::path::Iter;
-    use super::*;
-    use crate::App;
-    use crate::proto::ProtoData;
-    use crate::wire::FieldValue::MESSAGE;
-    use crate::wire::ScalarValue::{BYTES, ENUM, F64, STR};
+    // collapsed messages of the selected type are shown as table rows with these columns, none to show them as before
+    fn set_columns(&mut self, text: &str) -> io::Result<()> {
+        let (Some(msg_def), Ok(columns)) = (self.selected_message_type(), config::parse_columns(text)) else { return Ok(()); };
+        let name = msg_def.name.clone();
+        if columns.columns.is_empty() {
+            self.layout_config.messages.remove(&name);
+        } else {
+            self.layout_config.messages.insert(name.clone(), columns);
+        }
+        self.config_changed();
+        let Some(path) = self.config_path.clone() else {
+            return self.after_command(CommandResult::ShowMessage(format!("columns of {} changed", name)));
+        };
+        let saved = match std::fs::read_to_string(&path) {
+            Ok(text) => Ok(text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e),
+        }.and_then(|text| {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) { std::fs::create_dir_all(dir)?; }
+            std::fs::write(&path, config::with_columns(&text, &name, self.layout_config.messages.get(&name)))
+        });
+        match saved {
+            Ok(_) => self.after_command(CommandResult::ShowMessage(format!("columns of {} saved to {}", name, path.display()))),
+            Err(e) => self.after_command(CommandResult::ShowError(format!("cannot save the settings: {}", e))),
+        }
+    }
 
-    fn make_minimal_test_data() -> MessageData {
-        let binary_input = [];
-        let proto = ProtoData::new("message M { int32 f1 = 1; }").unwrap().finalize().unwrap();
-        let mut limit = binary_input.len() as u32;
-        let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap()
+    // the path of the field without indexes, as the formats and the renderers name it
+    fn field_names(&self, path: &FieldPath) -> Option<String> {
+        let names: Option<Vec<String>> = (1..=path.0.len()).map(|len| self.data.get_field_definition(&FieldPath(path.0[..len].to_vec())).map(|def| def.name())).collect();
+        names.map(|names| names.join("."))
     }
 
-    fn make_test_data_1() -> MessageData {
-        let proto_str = r#"
-message M { int32 f1 = 1; repeated int32 f2 = 2; M3 m3 = 3; int32 f4 = 4; }
-message M3 { int32 f5 = 5; repeated M6 m6 = 6; int32 f7 = 7; }
-message M6 { int32 f8 = 8; int32 f9 = 9; }
-"#;
+    fn field_by_names(&self, field: &str) -> Option<FieldProtoPtr> {
+        let mut msg_def = self.data.def.clone();
+        let mut res: Option<FieldProtoPtr> = None;
+        for name in field.split('.') {
+            if let Some(def) = &res {
+                msg_def = self.proto.get_message_definition(&def.typename())?;
+            }
+            res = Some(msg_def.fields.iter().find(|def| def.name() == name)?.clone());
+        }
+        res
+    }
 
-        let binary_input = [
-            0x08, 1,  // f1: 1 int32
-            0x10, 20,  // f2: 20 int32     repeated scalar
-            0x10, 21,  // f2: 21 int32
-            //0x20,  4,  // f4: 4 int32    optional scalar
-            0x1A, 16,  // m3: M3           nested message
-            0x28, 5,  //   f5: 5 int32
+    fn start_field_format(&mut self) -> io::Result<()> {
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let (Some(def), Some(field)) = (self.data.get_field_definition(&path), self.field_names(&path)) else { return Ok(()); };
+        let names = FieldFormat::names_for(&def.typename());
+        if names.len() == 1 {
+            return self.after_command(CommandResult::ShowMessage(format!("no formats for {} values", def.typename())));
+        }
+        let current = match self.layout_config.field_format(&def) {
+            Some(format) => format.name(),
+            None => self.layout_config.renderers.0.iter().find(|(other, _)| *other == field).map_or(names[0], |(_, renderer)| renderer.name()),
+        };
+        let prompt = Prompt::new(FORMAT_PROMPT, &format!("format of {} ({})", field, names.join(", ")), &self.prompt_history).with_text(current).
+            with_validator(move |text| (!names.contains(&text.trim())).then(|| format!("one of {} expected", names.join(", "))));
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
 
-            0x32, 4,  //   m6: M6          repeated message
-            0x40, 8,  //     f8: 8 int32
-            0x48, 9,  //     f9: 9 int32
+    // the format of the field in all its values, kept for the data file in the formats file
+    fn set_field_format(&mut self, name: &str) -> io::Result<()> {
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let Some(field) = self.field_names(&path) else { return Ok(()); };
+        let format = FieldFormat::from_name(name);
+        self.apply_field_format(&field, format);
+        self.rebuild_layouts(&path);
+        let (Some(formats_path), Some(data_file)) = (self.formats_path.clone(), self.formats_key()) else {
+            return self.after_command(CommandResult::ShowMessage(format!("{} shown as {}", field, name)));
+        };
+        let saved = match std::fs::read_to_string(&formats_path) {
+            Ok(text) => Ok(text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            Err(e) => Err(e),
+        }.and_then(|text| {
+            if let Some(dir) = formats_path.parent().filter(|dir| !dir.as_os_str().is_empty()) { std::fs::create_dir_all(dir)?; }
+            std::fs::write(&formats_path, formats::with_format(&text, &data_file, &field, format))
+        });
+        match saved {
+            Ok(_) => self.after_command(CommandResult::ShowMessage(format!("{} shown as {}, kept in {}", field, name, formats_path.display()))),
+            Err(e) => self.after_command(CommandResult::ShowError(format!("cannot save the formats: {}", e))),
+        }
+    }
 
-            0x32, 4,  //   m6: M6
-            0x40, 10,  //     f8: 10 int32
-            0x48, 11,  //     f9: 11 int32
+    // None shows the field as by default; a field not in the schema (a format of an older one) is left
+    fn apply_field_format(&mut self, field: &str, format: Option<FieldFormat>) {
+        let Some(def) = self.field_by_names(field) else { return; };
+        let config = &mut self.layout_config;
+        config.field_formats.retain(|(_, other, _)| !Arc::ptr_eq(other, &def));
+        config.renderers.0.retain(|(other, _)| other != field);
+        match format {
+            Some(FieldFormat::Render(renderer)) => config.renderers.0.push((field.to_string(), renderer)),
+            Some(format) => config.field_formats.push((field.to_string(), def, format)),
+            None => {}
+        }
+    }
 
-            0x38, 7,  //   f7: 7 int32
-        ];
+    // the absolute path of the data file, the data from stdin has no formats kept
+    fn formats_key(&self) -> Option<String> {
+        if self.stdin_data.is_some() { return None; }
+        std::path::absolute(&self.file_path).ok().map(|path| path.display().to_string())
+    }
 
-        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
-        let mut limit = binary_input.len() as u32;
-        let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
+    // the formats kept for the data file in the previous sessions
+    pub fn load_formats(&mut self) {
+        let (Some(formats_path), Some(data_file)) = (&self.formats_path, self.formats_key()) else { return; };
+        let formats = formats::read(&std::fs::read_to_string(formats_path).unwrap_or_default(), &data_file);
+        if formats.is_empty() { return; }
+        for (field, format) in formats {
+            self.apply_field_format(&field, Some(format));
+        }
+        self.rebuild_layouts(&FieldPath(vec![]));
+    }
 
-        //        let mut f = std::fs::File::create("test_data_1.pb").unwrap();
-        //        f.write_all(binary_input.as_slice()).unwrap();
+    fn decode_bytes(&mut self, typename: &str) -> io::Result<()> {
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let Some(msg_def) = self.proto.get_message_definition(typename) else { return Ok(()); };
+        let command = path.to_text(&self.data).map(|text| format!("decode {} as {}", text, typename));
+        match Arc::make_mut(&mut self.data).decode_bytes(&path, msg_def, &self.proto) {
+            Ok(count) => {
+                if let Some(command) = command { self.journal.push(command); }
+                self.rebuild_layouts(&path);
+                let name = self.data.get_field_definition(&path).map_or(String::new(), |def| def.name());
+                let values = if count == 1 { String::new() } else { format!("{} values of ", count) };
+                self.after_command(CommandResult::ShowMessage(format!("{}{} decoded as {}", values, name, typename)))
+            }
+            Err(e) => self.after_command(CommandResult::ShowError(e.to_string())),
+        }
+    }
 
-        MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap()
+    // after the type of a field is changed, the selection stays at the path
+    fn rebuild_layouts(&mut self, path: &FieldPath) {
+        self.selected = Selection::default();
+        self.layouts = Layouts::new(&self.data, &self.layout_config, self.layouts.file_name.clone(), self.layouts.width, self.layouts.height);
+        self.need_update_layout_height = true;
+        if !path.0.is_empty() { let _ = self.run_command(UserCommand::GoToPath(path.clone())); }
     }
 
-    fn make_no_field_data(proto: &str) -> MessageData {
-        let binary_input = [];
-        let proto = ProtoData::new(proto).unwrap().finalize().unwrap();
-        let mut limit = binary_input.len() as u32;
-        let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+    // the name is asked before the recording starts
+    fn toggle_recording(&mut self) -> io::Result<()> {
+        if let Some((name, keys)) = self.recording.take() {
+            let text = format!("macro {} recorded, {} keys", name, keys.len());
+            self.macros.insert(name.clone(), keys);
+            self.last_macro = name;
+            return self.after_command(CommandResult::ShowMessage(text));
+        }
+        let prompt = Prompt::new(RECORD_PROMPT, "record macro", &self.prompt_history).
+            with_validator(|text| if text.trim().is_empty() { Some("a name expected".into()) } else { None });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
 
-        data
+    // for all values: the macro is replayed from each value of the repeated message containing the selection
+    fn start_replay(&mut self, all_values: bool) -> io::Result<()> {
+        if self.macros.is_empty() {
+            return self.after_command(CommandResult::ShowMessage("no macros recorded, Ctrl+T - record".into()));
+        }
+        let names: Vec<String> = self.macros.keys().cloned().collect();
+        let (name, title) = if all_values { (REPLAY_ALL_PROMPT, "replay macro for each value") } else { (REPLAY_PROMPT, "replay macro") };
+        let prompt = Prompt::new(name, title, &self.prompt_history).with_text(&self.last_macro).
+            with_validator(move |text| if names.iter().any(|name| name == text.trim()) { None } else { Some("no such macro".into()) });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
     }
 
-    fn make_one_field_data(proto: &str, value: ScalarValue) -> MessageData {
-        let mut data = make_no_field_data(proto);
-        let mut field = data.add_field(&[(1, 0).into()]).unwrap();
-        field.value = FieldValue::SCALAR(value);
+    fn replay(&mut self, name: &str, all_values: bool) -> io::Result<()> {
+        let Some(keys) = self.macros.get(name).cloned() else { return Ok(()); };
+        self.last_macro = name.to_string();
+        if !all_values {
+            if self.replay_keys(&keys)? {
+                self.after_command(CommandResult::ShowMessage(format!("macro {} replayed", name)))?;
+            }
+            return Ok(());
+        }
+        let group = self.layouts.selected_path(&self.selected).and_then(|path| self.enclosing_message_group(&path));
+        let Some((group, _, count)) = group else {
+            return self.after_command(CommandResult::ShowMessage("select a value of a repeated message".into()));
+        };
+        for index in 0..count {
+            // the macro may delete values
+            if self.data.get_field(&group.with_last_index(index).0).is_none() { break; }
+            self.run_command(UserCommand::GoToPath(group.with_last_index(index)))?;
+            self.after_event()?;
+            if !self.replay_keys(&keys)? { return Ok(()); }
+        }
+        let field = self.data.get_field_definition(&group).map_or(String::new(), |def| def.name());
+        self.after_command(CommandResult::ShowMessage(format!("macro {} replayed for {} values of {}", name, count, field)))
+    }
 
-        data
+    // false if an error stopped the replay, the error is shown
+    fn replay_keys(&mut self, keys: &[KeyEvent]) -> io::Result<bool> {
+        // the replayed keys are not recorded again
+        let recording = self.recording.take();
+        let mut res = true;
+        for key in keys {
+            let quit = self.keymap.action(key) == Some(Action::Quit) && self.prompt.is_none();
+            if quit || !self.on_key(*key)? { break; }
+            self.after_event()?;
+            if self.message.as_ref().map_or(false, |text| text.starts_with("error: ")) {
+                res = false;
+                break;
+            }
+        }
+        self.recording = recording;
+        Ok(res)
     }
 
-    fn make_repeated_message_data(messages_count: usize) -> MessageData {
-        let proto_str = r#"
-message M { repeated M2 m1 = 1; }
-message M2 { int32 i2 = 2; int32 i3 = 3; }
-"#;
+    // the nearest repeated message of the path or its parents
+    fn enclosing_message_group(&self, path: &FieldPath) -> Option<(FieldPath, usize, usize)> {
+        let mut path = path.clone();
+        loop {
+            let (group, index, count) = Layouts::enclosing_group(&self.data, &path)?;
+            if self.data.get_field_definition(&group)?.is_message() { return Some((group, index, count)); }
+            path = FieldPath(group.0[..group.0.len() - 1].to_vec());
+            if path.0.is_empty() { return None; }
+        }
+    }
 
-        let binary_input = [
-            //0x0A, 12, // m1: M2           nested message
+    fn start_schema_audit(&mut self) -> io::Result<()> {
+        let prompt = Prompt::new(SCHEMA_PROMPT, "compare with proto", &self.prompt_history).
+            with_validator(|text| if std::path::Path::new(text.trim()).is_file() { None } else { Some("a proto file expected".into()) });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
 
-            //0x0A, 4,  //   m2: M2          repeated message
-            //0x10, 2,  //     i2: 2 int32
-            //0x18, 3,  //     i3: 3 int32
-            ////
-            //0x0A, 4,  //   m2: M2          repeated message
-            //0x10, 4,  //     i2: 4 int32
-            //0x18, 5,  //     i3: 5 int32
-            //
-            //0x0A, 4,  //   m2: M2          repeated message
-            //0x10, 6,  //     i2: 6 int32
-            //0x18, 7,  //     i3: 7 int32
-        ];
+    // the root message of the same name, or detected in the new file
+    fn run_schema_audit(&mut self, file_name: &str) -> io::Result<()> {
+        let file_name = file_name.trim();
+        let new_proto = match load_proto(file_name.as_ref(), &self.proto_path) {
+            Ok(proto) => proto,
+            Err(e) => return self.after_command(CommandResult::ShowError(format!("cannot read {}: {}", file_name, e))),
+        };
+        let Some(root) = new_proto.get_message_definition(&self.data.def.name).or_else(|| new_proto.auto_detect_root_message()) else {
+            return self.after_command(CommandResult::ShowError(format!("no root message {} in {}", self.data.def.name, file_name)));
+        };
+        let findings = audit::audit(&self.data, &new_proto, root);
+        if findings.is_empty() {
+            return self.after_command(CommandResult::ShowMessage(format!("the data is read the same way with {}", file_name)));
+        }
+        let schema = std::path::Path::new(file_name).file_name().map_or(file_name.into(), |name| name.to_string_lossy().into_owned());
+        let title = format!("{} fields differ in {}, Enter - go to, Esc - close", findings.len(), schema);
+        let lines = findings.into_iter().map(|finding| (finding.to_string(), Some(finding.text))).collect();
+        self.report = Some(Report { title, lines, selected: 0, scroll: 0, search: None, kind: ReportKind::List });
+        self.need_update = true;
+        Ok(())
+    }
 
-        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
-        let mut limit = binary_input.len() as u32;
-        let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+    // read the proto file again and the data with it, the fields it does not define are kept as unknown;
+    // the fields read differently are listed, the undo history of the old definitions is cleared
+    fn reload_schema(&mut self) -> io::Result<()> {
+        let file_name = self.proto_file.display().to_string();
+        let proto = match load_proto(&self.proto_file, &self.proto_path) {
+            Ok(proto) => proto,
+            Err(e) => return self.after_command(CommandResult::ShowError(format!("cannot read {}: {}", file_name, e))),
+        };
+        while self.close_focus() {}
+        let Some(root) = proto.get_message_definition(&self.data.def.name) else {
+            return self.after_command(CommandResult::ShowError(format!("no root message {} in {}", self.data.def.name, file_name)));
+        };
+        let findings = audit::audit(&self.data, &proto, root.clone());
+        let rebound = rebind(&self.data, &self.proto, &proto, root.clone()).and_then(|data| {
+            let other = self.merge.as_ref().map(|merge| rebind(&merge.other, &self.proto, &proto, root)).transpose()?;
+            Ok((data, other))
+        });
+        let (data, other) = match rebound {
+            Ok(rebound) => rebound,
+            Err(e) => return self.after_command(CommandResult::ShowError(format!("cannot read the data with {}: {}", file_name, e))),
+        };
+        let selected = self.layouts.selected_path(&self.selected).unwrap_or_default();
+
+        self.data = Arc::new(data);
+        self.proto = proto;
+        if let (Some(merge), Some(other)) = (&mut self.merge, other) { merge.other = other; }
+        // the formats of the fields go to their new definitions, the fields removed from the schema lose them
+        for (field, _, format) in std::mem::take(&mut self.layout_config.field_formats) {
+            self.apply_field_format(&field, Some(format));
+        }
+        self.history = History::default();
+        self.range = None;
+        self.pending_change = None;
+        self.restore_view(selected)?;
+        if findings.is_empty() {
+            return match unresolved_warning(&self.proto) {
+                Some(warning) => self.after_command(CommandResult::ShowError(format!("schema reloaded, {}", warning))),
+                None => self.after_command(CommandResult::ShowMessage("schema reloaded".into())),
+            };
+        }
+        let title = format!("schema reloaded, {} fields read differently, Enter - go to, Esc - close", findings.len());
+        let lines = findings.into_iter().map(|finding| {
+            let path = finding.path.to_text(&self.data).unwrap_or(finding.text.clone());
+            (finding.to_string(), Some(path))
+        }).collect();
+        self.report = Some(Report { title, lines, selected: 0, scroll: 0, search: None, kind: ReportKind::List });
+        self.need_update = true;
+        Ok(())
+    }
 
-        let mut int_value = 2;
-        for index in 0..messages_count {
-            let mut field = data.add_field(&[(1, index).into()]).unwrap();
-            if let MESSAGE(msg) = &mut field.value {
-                let mut i2 = msg.add_field(&[(2, 0).into()]).unwrap();
-                i2.value = SCALAR(I32(int_value));
-                int_value += 1;
-                let mut i3 = msg.add_field(&[(3, 0).into()]).unwrap();
-                i3.value = SCALAR(I32(int_value));
-                int_value += 1;
-            }
+    // the file name, the focused message and each parent of the selected field with its path in the view, if it has a name
+    fn breadcrumbs(&self) -> Vec<(String, Option<String>)> {
+        let mut res = vec![(self.layouts.file_name.clone(), None)];
+        if let Some(focus) = self.focus.last() { res.push((focus.text.clone(), None)); }
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return res; };
+        let mut parent = String::new();
+        for len in 1..=path.0.len() {
+            let Some(text) = FieldPath(path.0[..len].to_vec()).to_text(&self.data) else { break; };
+            let name = text[if parent.is_empty() { 0 } else { parent.len() + 1 }..].to_string();
+            res.push((name, Some(text.clone())));
+            parent = text;
         }
-        data
+        res
     }
 
-    #[test]
-    fn match_testing_requirements() {
-        // these settings values required for correct test data formating
-        assert_eq!(MARGIN_LEFT, 1);
-        assert_eq!(MARGIN_RIGHT, 1);
+    fn show_breadcrumbs(&mut self) {
+        let crumbs = self.breadcrumbs();
+        let lines: Vec<(String, Option<String>)> = crumbs.into_iter().enumerate().map(|(level, (name, path))| (format!("{}{}", "  ".repeat(level), name), path)).collect();
+        let Some(path) = self.layouts.selected_path(&self.selected).and_then(|path| self.absolute_text(&path)) else { return; };
+        let title = format!("{}, Enter - go to, Ctrl+C - copy the path, Esc - close", path);
+        self.report = Some(Report { title, selected: lines.len() - 1, lines, scroll: 0, search: None, kind: ReportKind::Breadcrumbs });
+        self.need_update = true;
     }
 
-    #[test]
-    fn simple() {
-        let mut data = make_one_field_data("message M { int32 i1=1; }", I32(1));
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" i1: 1                  int32 "]);
+    fn show_overview(&mut self) {
+        let overview = overview::Overview::calc(&self.data);
+        let title = format!("{} in the data, Enter - go to, Esc - close", overview::size_text(overview.size));
+        // the first line is a title
+        self.report = Some(Report { title, lines: overview.lines(&self.data), selected: 1, scroll: 0, search: None, kind: ReportKind::List });
+        self.need_update = true;
     }
 
-    #[test]
-    fn app_test_1() {
-        let data = make_test_data_1();
-        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
-        let expected = [
-            " f1: 1                                      int32 ",
-            " f2: 20 21                                 int32* ",
-            " m3:                                           M3 ",
-            "   f5: 5                                    int32 ",
-            "   m6:                                        M6* ",
-            "     f8: 8                                  int32 ",
-            "     f9: 9                                  int32 ",
-            "   m6:                                        M6* ",
-            "     f8: 10                                 int32 ",
-            "     f9: 11                                 int32 ",
-            "   f7: 7                                    int32 ",
-            " f4: 0                                     -int32 "];
-        assert_eq!(app.to_strings(), expected);
+    // the messages and enums of the proto file, the definition of the selected field is selected
+    fn show_schema(&mut self) {
+        let schema = schema::Schema::new(&self.proto, &self.data);
+        let selected = self.layouts.selected_path(&self.selected).and_then(|path| {
+            let (last, parent) = path.0.split_last()?;
+            let type_name = if parent.is_empty() { self.data.def.name.clone() } else { self.data.get_field_definition(&FieldPath(parent.to_vec()))?.typename() };
+            schema.find(&type_name, Some(last.id)).or_else(|| schema.find(&type_name, None))
+        }).unwrap_or(0);
+        let page = (self.height - TOP_LINE) as usize;
+        let scroll = (selected + 1).saturating_sub(page / 2).min(schema.lines.len().saturating_sub(page));
+        self.report = Some(Report { title: schema_title(""), lines: schema.lines, selected, scroll, search: Some(String::new()), kind: ReportKind::List });
+        self.need_update = true;
     }
 
+    // enum values with no variant in the schema
+    fn show_unknown_enums(&mut self, selected: usize) -> io::Result<()> {
+        let findings = audit::unknown_enums(&self.data, &self.proto);
+        if findings.is_empty() {
+            self.report = None;
+            return self.after_command(CommandResult::ShowMessage("all enum values have names".into()));
+        }
+        let title = format!("{} enum values with no name, Right - set the nearest, Enter - go to, Esc - close", findings.len());
+        let lines: Vec<(String, Option<String>)> = findings.into_iter().map(|finding| (finding.to_string(), Some(finding.text))).collect();
+        let selected = selected.min(lines.len() - 1);
+        let scroll = (selected + 1).saturating_sub((self.height - TOP_LINE) as usize);
+        self.report = Some(Report { title, lines, selected, scroll, search: None, kind: ReportKind::Enums });
+        self.need_update = true;
+        Ok(())
+    }
 
-    #[test]
-    fn scroll_limits() {
-        let expected_start = [
-            " f1: 1                                      int32 ",
-            " f2: 20 21                                 int32* "];
-        let expected_end = [
-            "   f7: 7                                    int32 ",
-            " f4: 0                                     -int32 "];
+    // the value of the line is replaced with the nearest variant of its enum
+    fn set_nearest_enum(&mut self, index: usize) -> io::Result<()> {
+        let findings = audit::unknown_enums(&self.data, &self.proto);
+        let Some(finding) = findings.into_iter().nth(index) else { return Ok(()); };
+        let audit::Issue::NoName(typename, _, nearest) = finding.issue else { return Ok(()); };
+        let Some((_, number)) = nearest else {
+            return self.after_command(CommandResult::ShowError(format!("{} has no values", typename)));
+        };
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        let change = Change { path: finding.path.clone(), action: ChangeType::Overwrite(FieldValue::SCALAR(ScalarValue::ENUM(number))) };
+        self.apply_step(vec![change]);
+        self.rebuild_layouts(&finding.path);
+        self.show_unknown_enums(index)
+    }
 
-        let data = make_test_data_1();
-        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 2 + TOP_LINE).unwrap();
-        assert_eq!(app.to_strings(), expected_start);
+    // the wire bytes of the selected value and the value read as each type of its wire type
+    fn show_encoding(&mut self) -> io::Result<()> {
+        let Some(path) = self.layouts.selected_path(&self.selected) else { return Ok(()); };
+        let Some(field) = self.data.get_field(&path.0) else {
+            return self.after_command(CommandResult::ShowMessage("the field has no data".into()));
+        };
+        let lines = inspect::lines(field, &self.proto)?.into_iter().map(|line| (line, None)).collect();
+        let title = format!("wire encoding of {}, * - the declared type, Esc - close", path.to_text(&self.data).unwrap_or_default());
+        self.report = Some(Report { title, lines, selected: 0, scroll: 0, search: None, kind: ReportKind::List });
+        self.need_update = true;
+        Ok(())
+    }
 
-        for _ in 0..100 {
-            app.run_command(UserCommand::ScrollVertically(1)).unwrap();
-            app.after_event().unwrap();
+    // the steps of the undo list, the oldest first
+    fn show_history(&mut self) -> io::Result<()> {
+        if self.history.undo.is_empty() {
+            return self.after_command(CommandResult::ShowMessage("no changes".into()));
         }
-        assert_eq!(app.to_strings(), expected_end);
+        let lines: Vec<(String, Option<String>)> = self.history.undo.iter().enumerate().map(|(index, step)| {
+            let path = step.changes.first().map(|change| &change.path).filter(|path| self.data.get_field(&path.0).is_some()).and_then(|path| path.to_text(&self.data));
+            (format!("{:>4}. {}", index + 1, self.step_text(step)), path)
+        }).collect();
+        let title = format!("{} changes, Del - revert, Ctrl+S - export, Enter - go to, Esc - close", lines.len());
+        let selected = lines.len() - 1;
+        let scroll = (selected + 1).saturating_sub((self.height - TOP_LINE) as usize);
+        self.report = Some(Report { title, lines, selected, scroll, search: None, kind: ReportKind::History });
+        self.need_update = true;
+        Ok(())
+    }
 
-        for _ in 0..100 {
-            app.run_command(UserCommand::ScrollVertically(-1)).unwrap();
-            app.after_event().unwrap();
+    // set a.b: 1 → 2, delete a.c[0], was 5; the count of the other changes of the step
+    fn step_text(&self, step: &Step) -> String {
+        let command = step.commands.first().cloned().unwrap_or_default();
+        let old_text = |def: &FieldProtoPtr, value: &FieldValue| match value {
+            FieldValue::SCALAR(scalar) => batch::value_text(def, scalar),
+            FieldValue::MESSAGE(_) => Some("{…}".to_string()),
+        };
+        let text = match step.changes.first().map(|change| (change, &change.action)) {
+            Some((change, ChangeType::Overwrite(old))) => {
+                let old = self.data.get_field_definition(&change.path).and_then(|def| old_text(&def, old));
+                match (command.split_once(" = "), old) {
+                    (Some((target, new)), Some(old)) => format!("{}: {} → {}", target, old, new),
+                    _ => command,
+                }
+            }
+            Some((_, ChangeType::Restore(field, _))) => match old_text(&field.def, &field.value) {
+                Some(old) => format!("{}, was {}", command, old),
+                None => command,
+            },
+            _ => command,
+        };
+        match step.changes.len() {
+            0 | 1 => text,
+            count => format!("{} and {} more", text, count - 1),
         }
-        assert_eq!(app.to_strings(), expected_start);
+    }
 
-        for _ in 0..100 {
-            app.run_command(UserCommand::ScrollSibling(1)).unwrap();
-            app.after_event().unwrap();
+    // a step is reverted with a new step, the later steps changing the same fields are undone first
+    fn revert_step(&mut self, index: usize) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
         }
-        assert_eq!(app.to_strings(), expected_end);
-
-        for _ in 0..100 {
-            app.run_command(UserCommand::ScrollSibling(-1)).unwrap();
-            app.after_event().unwrap();
+        if let Some(later) = self.history.conflict(index) {
+            return self.after_command(CommandResult::ShowError(format!("change {} is changed by change {}, undo it first", index + 1, later + 1)));
         }
-        assert_eq!(app.to_strings(), expected_start);
-
-        app.run_command(UserCommand::ScrollVertically(100)).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected_end);
+        let mut step = self.history.undo.remove(index);
+        step.changes.reverse();
+        let mut path = step.changes.last().unwrap().path.clone();
+        self.apply_step(step.changes);
+        // a reverted insert: the previous value or the parent
+        while !path.0.is_empty() && self.data.get_field(&path.0).is_none() {
+            let last = path.0.last_mut().unwrap();
+            if last.index > 0 { last.index -= 1; } else { path.0.pop(); }
+        }
+        self.rebuild_layouts(&path);
+        self.after_command(CommandResult::ShowMessage(format!("change {} reverted", index + 1)))
+    }
 
-        app.run_command(UserCommand::ScrollVertically(-100)).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected_start);
+    fn start_export_changes(&mut self) -> io::Result<()> {
+        let prompt = Prompt::new(EXPORT_CHANGES_PROMPT, "save changes to", &self.prompt_history).
+            with_text(&format!("{}.edits", self.file_path.display())).
+            with_validator(|text| if text.trim().is_empty() { Some("a file name expected".into()) } else { None });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
     }
 
-    #[test]
-    fn empty_repeated_message() {
-        let mut data = make_repeated_message_data(0);
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" m1:                     -M2* "]);
+    // the commands of the undo list, a script for --apply
+    fn export_changes(&self, file_name: &str) -> io::Result<usize> {
+        let commands: Vec<&String> = self.history.undo.iter().flat_map(|step| &step.commands).collect();
+        let mut text = format!("# changes of {}\n", self.file_path.display());
+        for command in &commands {
+            text += command;
+            text.push('\n');
+        }
+        std::fs::write(file_name.trim(), text)?;
+        Ok(commands.len())
     }
 
-    #[test]
-    fn insert_repeated_message() {
-        let mut data = make_repeated_message_data(0);
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" m1:                     -M2* "]);
+    // the file of the other version is asked, the last one is suggested
+    fn start_merge_file(&mut self) -> io::Result<()> {
+        let text = self.merge.as_ref().map_or(String::new(), |merge| merge.file_name.clone());
+        let prompt = Prompt::new(MERGE_PROMPT, "merge with", &self.prompt_history).
+            with_text(&text).
+            with_validator(|text| if std::path::Path::new(text.trim()).is_file() { None } else { Some("a data file expected".into()) });
+        self.prompt = Some(prompt);
+        self.need_update = true;
+        Ok(())
+    }
 
-        app.run_command(UserCommand::InsertData).unwrap();
-        app.after_event().unwrap();
-        let expected = [
-            " m1:                      M2* ", // created a message with empty fields
-            "   i2: 0               -int32 ",
-            "   i3: 0               -int32 "];
-        assert_eq!(app.to_strings(), expected);
+    // the values kept for the same file are not listed again
+    fn load_merge(&mut self, file_name: &str) -> io::Result<()> {
+        // the whole document is compared
+        while self.close_focus() {}
+        let other = match read_data_file(file_name.as_ref(), &self.proto, self.data.def.clone()) {
+            Ok(other) => other,
+            Err(e) => return self.after_command(CommandResult::ShowError(format!("cannot read {}: {}", file_name, e))),
+        };
+        match &mut self.merge {
+            Some(merge) if merge.file_name == file_name => merge.other = other,
+            _ => self.merge = Some(merge::Merge::new(file_name.to_string(), other)),
+        }
+        self.show_merge(0)
     }
 
-    #[test]
-    fn delete_message_field() {
-        let mut data = make_repeated_message_data(1);
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        app.to_strings();
+    // the differences as on saving: this value → the other one
+    pub fn show_merge(&mut self, selected: usize) -> io::Result<()> {
+        let Some(merge) = &self.merge else { return Ok(()); };
+        // a field missing here: its parent message
+        let nearest = |text: &str| {
+            let mut path = FieldPath::parse(text, &self.data.def, &self.proto).ok()?;
+            while !path.0.is_empty() && self.data.get_field(&path.0).is_none() { path.0.pop(); }
+            path.to_text(&self.data)
+        };
+        let lines: Vec<(String, Option<String>)> = merge.differences(&self.data).iter().map(|difference| (difference.to_string(), nearest(difference.path()))).collect();
+        if lines.is_empty() {
+            self.report = None;
+            return self.after_command(CommandResult::ShowMessage(format!("no differences with {} left", merge.file_name)));
+        }
+        let title = format!("{} differences with {}, Right - take theirs, Left - keep this, Enter - go to, Esc - close", lines.len(), merge.file_name);
+        let selected = selected.min(lines.len() - 1);
+        let scroll = (selected + 1).saturating_sub((self.height - TOP_LINE) as usize);
+        self.report = Some(Report { title, lines, selected, scroll, search: None, kind: ReportKind::Merge });
+        self.need_update = true;
+        Ok(())
+    }
 
-        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
-        app.after_event().unwrap();
-        app.run_command(UserCommand::DeleteData).unwrap();
-        app.after_event().unwrap();
-        let expected = [
-            " m1:                      M2* ",
-            "   i2: 0               -int32 ", // deleted
-            "   i3: 3                int32 "];
-        assert_eq!(app.to_strings(), expected);
+    // the value of the other version is taken as a change of the data, or this value is kept
+    fn resolve_difference(&mut self, index: usize, take: bool) -> io::Result<()> {
+        let Some(merge) = &mut self.merge else { return Ok(()); };
+        let differences = merge.differences(&self.data);
+        let Some(difference) = differences.get(index) else { return Ok(()); };
+        if !take {
+            merge.kept.push(difference.path().to_string());
+            return self.show_merge(index);
+        }
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        let change = match merge.take(&self.data, &self.proto, difference) {
+            Ok(change) => change,
+            Err(e) => return self.after_command(CommandResult::ShowError(e.to_string())),
+        };
+        let mut path = change.path.clone();
+        self.apply_step(vec![change]);
+        while !path.0.is_empty() && self.data.get_field(&path.0).is_none() { path.0.pop(); }
+        self.rebuild_layouts(&path);
+        self.show_merge(index)
     }
 
-    #[test]
-    fn delete_message() {
-        let mut data = make_repeated_message_data(2);
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+    // the report screen: Up/Down to select a line, Enter to go to the field, any other key to close
+    fn on_report_key(&mut self, event: KeyEvent) -> io::Result<bool> {
+        let Some(report) = &mut self.report else { return Ok(true); };
+        let page = (self.height - TOP_LINE) as isize;
+        // typed chars are searched from the selected line, Tab finds the next line
+        if let Some(search) = &mut report.search {
+            let start = match event.code {
+                KeyCode::Char(c) if !event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => { search.push(c); Some(report.selected) }
+                KeyCode::Backspace => { search.pop(); Some(report.selected) }
+                KeyCode::Tab => Some(report.selected + 1),
+                _ => None,
+            };
+            if let Some(start) = start {
+                let text = search.clone();
+                let found = if text.is_empty() { None } else { report.find(&text, start) };
+                report.title = schema_title(&if text.is_empty() || found.is_some() { text } else { format!("{} not found", text) });
+                if let Some(found) = found {
+                    report.selected = found;
+                    report.scroll = report.scroll.min(found).max((found + 1).saturating_sub(page as usize));
+                }
+                self.need_update = true;
+                return Ok(true);
+            }
+        }
+        if report.kind == ReportKind::Siblings && matches!(event.code, KeyCode::Char('y' | 'Y')) {
+            self.set_siblings()?;
+            return Ok(true);
+        }
+        let index = report.selected;
+        match (report.kind, self.keymap.action(&event)) {
+            (ReportKind::History, Some(Action::DeleteData)) => {
+                self.report = None;
+                self.revert_step(index)?;
+                return Ok(true);
+            }
+            (ReportKind::History, Some(Action::Save)) => {
+                self.report = None;
+                self.start_export_changes()?;
+                return Ok(true);
+            }
+            (ReportKind::Merge, Some(action @ (Action::Right | Action::Left))) => {
+                self.resolve_difference(index, action == Action::Right)?;
+                return Ok(true);
+            }
+            (ReportKind::Enums, Some(Action::Right)) => {
+                self.set_nearest_enum(index)?;
+                return Ok(true);
+            }
+            (ReportKind::Breadcrumbs, Some(Action::Copy)) => {
+                self.report = None;
+                let Some(text) = self.layouts.selected_path(&self.selected).and_then(|path| self.absolute_text(&path)) else { return Ok(true); };
+                self.to_clipboard(text.clone())?;
+                self.after_command(CommandResult::ShowMessage(format!("{} copied", text)))?;
+                return Ok(true);
+            }
+            _ => {}
+        }
+        let delta = match self.keymap.action(&event) {
+            Some(Action::LineUp) => -1,
+            Some(Action::LineDown) => 1,
+            Some(Action::PageUp) => -page,
+            Some(Action::PageDown) => page,
+            Some(Action::CollapsedToggle) => {
+                // a title line
+                let Some(text) = report.lines[report.selected].1.clone() else { return Ok(true); };
+                self.report = None;
+                self.go_to(&text)?;
+                return Ok(true);
+            }
+            _ => {
+                self.report = None;
+                self.need_update = true;
+                return Ok(true);
+            }
+        };
+        report.selected = report.selected.saturating_add_signed(delta).min(report.lines.len() - 1);
+        report.scroll = report.scroll.min(report.selected).max((report.selected + 1).saturating_sub(page as usize));
+        self.need_update = true;
+        Ok(true)
+    }
 
-        app.to_strings();
-        app.run_command(UserCommand::DeleteData).unwrap();
-        app.after_event().unwrap();
-        let expected = [
-            " m1:                      M2* ", // only one message remains
-            "   i2: 4                int32 ",
-            "   i3: 5                int32 "];
-        assert_eq!(app.to_strings(), expected);
+    // compare the data with the file content (or stdin) before saving
+    fn start_review(&mut self) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        // the whole document is compared and saved
+        while self.close_focus() {}
+        let bytes = match &self.stdin_data {
+            Some(bytes) => Ok(bytes.clone()),
+            None => compress::read(&self.file_path),
+        };
+        let format = if self.stdin_data.is_some() { Format::Binary } else { Format::of_path(&self.file_path) };
+        match bytes.and_then(|bytes| Ok((textformat::decode(&bytes, &self.proto, self.data.def.clone(), format)?, bytes))) {
+            Ok((mut old, bytes)) => {
+                // the fields the proto file does not describe exactly are changed by saving, even if they are not edited;
+                // json and text are read by the names of the fields, they are written back as read
+                let mut lines = if format == Format::Binary { audit::round_trip(&old, &bytes, &self.proto) } else { vec![] };
+                let lossy = lines.len();
+                // decoded bytes are compared by their content
+                for (path, msg_def) in self.data.decoded_fields() {
+                    let _ = old.decode_bytes(&path, msg_def, &self.proto);
+                }
+                lines.extend(diff::compare(&old, &self.data).iter().map(|d| d.to_string()));
+                if lines.is_empty() && self.save_target().as_ref() == Some(&self.file_path) {
+                    self.after_command(CommandResult::ShowMessage("no changes to save".into()))?;
+                } else if lines.len() == lossy && self.save_target().as_ref() == Some(&self.file_path) {
+                    self.after_command(CommandResult::ShowMessage(format!("no changes to save, {} fields would not be saved as read", lossy)))?;
+                } else if lines.is_empty() {
+                    self.save()?;
+                } else {
+                    self.review = Some(Review { lines, scroll: 0, merge: None, lossy });
+                    self.need_update = true;
+                }
+            }
+            Err(e) => self.after_command(CommandResult::ShowError(format!("cannot read {}: {}", self.file_path.display(), e)))?,
+        }
+        Ok(())
     }
 
-    #[test]
-    fn collapse_empty_message() {
-        let mut data = make_repeated_message_data(0);
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+    // the data file, the --output path, or None for the data from stdin (asked before saving)
+    fn save_target(&self) -> Option<PathBuf> {
+        self.output.clone().or_else(|| self.stdin_data.is_none().then(|| self.file_path.clone()))
+    }
 
-        app.to_strings();
-        app.run_command(UserCommand::CollapsedToggle).unwrap();
-        //        app.after_event().unwrap();
-        //        assert_eq!(app.to_strings(), [" m1: ... 0               -M2* "]);
-        //
-        //        app.run_command(UserCommand::CollapsedToggle).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [" m1:                     -M2* "]);
+    fn ask_save_target(&mut self, title: &str) {
+        let prompt = Prompt::new(SAVE_PROMPT, title, &self.prompt_history).
+            with_validator(|text| if text.trim().is_empty() { Some("a file name expected".into()) } else { None });
+        self.prompt = Some(prompt);
+        self.need_update = true;
     }
 
+    // the whole document is saved to the file in the format of its extension, the next saves go there too
+    fn save_as(&mut self) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        self.ask_save_target("save as (.json, .textproto, other files binary, - for stdout)");
+        Ok(())
+    }
 
-    // single line string displayed within apostrophes to show trailing spaces
-    #[test]
-    fn single_line_string() {
-        let data = make_one_field_data("message M { string f1=1; }", STR("abc".to_string()));
-        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
-        let expected = [" f1: 'abc'                                 string "];
-        assert_eq!(app.to_strings(), expected);
+    fn save(&mut self) -> io::Result<()> {
+        let Some(target) = self.save_target() else {
+            self.ask_save_target("save to (- for stdout)");
+            return Ok(());
+        };
+        if target.as_os_str() == "-" {
+            let mut bytes = vec![];
+            self.data.write(&mut bytes, &self.proto, self.data.def.clone())?;
+            self.stdout_data = Some(bytes);
+            return self.after_command(CommandResult::ShowMessage("the data will be written to stdout on exit".into()));
+        }
+        let result = write_data_file(&target, &self.data, &self.proto, self.layout_config.original_json_names);
+        // the fields with no names are not written in json and text
+        let left_out = match Format::of_path(&target) {
+            Format::Binary => String::new(),
+            _ => match textformat::unknown_count(&self.data) {
+                0 => String::new(),
+                count => format!(", {} unknown field{} left out", count, if count == 1 { "" } else { "s" }),
+            },
+        };
+        match result {
+            Ok(_) if target == self.file_path => {
+                // the recovery file is not needed anymore, failing to remove it is not an error of saving
+                let _ = self.journal.clear();
+                self.disk_time = modified_time(&target);
+                self.after_command(CommandResult::ShowMessage(format!("saved{}", left_out)))
+            }
+            Ok(_) => self.after_command(CommandResult::ShowMessage(format!("saved to {}{}", target.display(), left_out))),
+            Err(e) => self.after_command(CommandResult::ShowError(format!("cannot save: {}", e))),
+        }
     }
 
-    // multiline string displayed without apostrophe
-    #[test]
-    fn multiline_string() {
-        let data = make_one_field_data(
-            "message M { string f1=1; }",
-            STR("abc\ndef".to_string()));
+    fn run_command(&mut self, command: UserCommand) -> io::Result<()> {
+        let result =
+            match command {
+                ChangeFieldOrder(order) => {
+                    self.layout_config.field_order = order;
+                    // the selected field is kept in the new order, the collapsed messages by the config
+                    let mut selected = self.layouts.selected_path(&self.selected).unwrap_or_default();
+                    while self.layout_config.field_order == FieldOrder::Wire && !selected.0.is_empty() && self.data.get_field(&selected.0).is_none() {
+                        selected.0.pop();
+                    }
+                    self.rebuild_layouts(&FieldPath::new());
+                    self.selected = Selection::default();
+                    if !selected.0.is_empty() {
+                        self.layouts.run_command(UserCommand::GoToPath(selected), &self.data, &mut self.layout_config, &mut self.selected);
+                    }
+                    CommandResult::Redraw
+                }
+                ScrollVertically(delta) => {
+                    if delta < 0 {
+                        self.layouts.ensure_loaded(&self.data, &self.layout_config, self.selected.layout, -delta as usize + 1 + self.height as usize, 0, &mut self.selected);
+                    } else {
+                        self.layouts.ensure_loaded(&self.data, &self.layout_config, self.selected.layout, 0, delta as usize + 1, &mut self.selected);
+                    }
+                    self.layouts.run_command(command, &self.data, &mut self.layout_config, &mut self.selected)
+                }
+                _ => self.layouts.run_command(command, &self.data, &mut self.layout_config, &mut self.selected)
+            };
 
-        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
-        let expected = [
-            " f1: abc                                   string ",
-            "  2: def                                          "];
-        assert_eq!(app.to_strings(), expected);
+        self.after_command(result)
     }
 
-    #[test]
-    fn long_strings() {
-        {
-            let data = make_one_field_data(
-                "message M { string s1=1; }",
-                STR("abcdefghijklmnopqrstuvwxyz".to_string()));
+    fn after_command(&mut self, result: CommandResult) -> io::Result<()> {
+        match result {
+            CommandResult::Redraw => {
+                self.need_update = true;
+            }
+            CommandResult::ChangeData(change) => {
+                if self.read_only {
+                    self.message = Some(format!("error: {}", READ_ONLY_ERROR));
+                    self.need_update = true;
+                } else if self.is_protected(&change.path) {
+                    self.pending_change = Some(change);
+                    self.message = Some("the field is protected, press Y to change it".into());
+                    self.need_update = true;
+                } else if let Some(text) = self.reserved_insert(&change) {
+                    self.pending_change = Some(change);
+                    self.message = Some(format!("{}, press Y to insert it anyway", text));
+                    self.need_update = true;
+                } else {
+                    let deprecated = self.data.get_field_definition(&change.path).filter(|def| def.deprecated() && !matches!(change.action, ChangeType::Delete));
+                    self.apply_change(change);
+                    if let Some(def) = deprecated {
+                        self.message = Some(format!("{} is deprecated", def.name()));
+                    }
+                }
+            }
+            CommandResult::ShowMessage(text) => {
+                self.message = Some(text);
+                self.need_update = true;
+            }
+            CommandResult::ShowError(text) => {
+                self.message = Some(format!("error: {}", text));
+                self.need_update = true;
+            }
 
-            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-            let expected = [
-                " s1: abcdefghijklmnopq string ",
-                "   : rstuvwxyz                "];
-            assert_eq!(app.to_strings(), expected);
+            _ => {}
         }
-        {
-            let data = make_one_field_data(
-                "message M { string s1=1; }",
-                STR("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string()));
+        Ok(())
+    }
+    // the field number of an inserted value is reserved by its message
+    fn reserved_insert(&self, change: &Change) -> Option<String> {
+        let ChangeType::Insert(_) = change.action else { return None; };
+        let (last, parents) = change.path.0.split_last()?;
+        let msg = self.data.get_submessage(parents)?;
+        msg.def.is_reserved(last.id).then(|| format!("field number {} is reserved in {}", last.id, msg.def.name))
+    }
 
-            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-            let expected = [
-                " s1: abcdefghijklmnopq string ",
-                "   : rstuvwxyzABCDEFGHIJKLMNO ",
-                "   : PQRSTUVWXYZ              "];
-            assert_eq!(app.to_strings(), expected);
+    fn apply_change(&mut self, change: Change) {
+        // a new scalar value of the selected layout does not change the other layouts
+        let in_place = matches!(&change.action, ChangeType::Overwrite(FieldValue::SCALAR(_))) &&
+            matches!(self.current_layout_type(), Some(LayoutType::Scalar | LayoutType::Str | LayoutType::Bytes)) &&
+            self.layouts.items.get(self.selected.layout).map_or(false, |item| item.path.0.len() == change.path.0.len() &&
+                item.path.0.last().unwrap().id == change.path.0.last().unwrap().id);
+        self.apply_step(vec![change]);
+        if in_place {
+            self.layouts.update_after_value_changed(&self.data, &self.layout_config, self.selected.layout);
+        } else {
+            self.layouts.update_after_data_changed(&self.data, &self.layout_config, self.selected.layout);
         }
-        {
-            let data = make_one_field_data(
-                "message M { string s1=1; }",
-                STR("abcdefghijklmnopqrstuvwxyz\nABCDEFGHIJKLMNOPQRSTUVWXYZ".to_string()));
+        self.need_update = true;
+    }
 
-            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-            let expected = [
-                " s1: abcdefghijklmnopq string ",
-                "   : rstuvwxyz                ",
-                "  2: ABCDEFGHIJKLMNOPQRSTUVWX ",
-                "   : YZ                       "];
-            assert_eq!(app.to_strings(), expected);
-            //    data.add_field(&[(2, 0).into(), (6, 0).into()]).unwrap().value = FieldValue::SCALAR(STR("Leonardo's Life and Times\nLeonardo was, first of all, a painter and an artist.\nBut he was also a great thinker.".to_string()));
+    // the changes are applied, journaled and undone together
+    fn apply_step(&mut self, changes: Vec<Change>) {
+        let mut step = Step { changes: Vec::with_capacity(changes.len()), commands: vec![] };
+        for mut change in changes {
+            let command = batch::change_to_command(&self.data, &change);
+            if Arc::make_mut(&mut self.data).apply(&mut change).is_none() { continue; }
+            self.move_paths(&change);
+            step.commands.append(&mut self.journal_change(command, &change));
+            step.changes.push(change);
+        }
+        if !step.changes.is_empty() {
+            self.history.undo.push(step);
+            self.history.redo.clear();
         }
     }
 
-    #[test]
-    fn scroll_multiline_string() {
-        let data = make_one_field_data(
-            "message M { string f1=1; }",
-            STR("11\n22\n33\n44\n55\n66\n77\n88\n99".to_string()));
+    // the change is applied already, an inserted message is recorded with its content; the commands recorded are returned
+    fn journal_change(&mut self, command: Option<String>, applied: &Change) -> Vec<String> {
+        let commands = command.map(|command| vec![command]).or_else(|| match applied.action {
+            ChangeType::Delete => batch::field_commands(&self.data, &applied.path),
+            _ => None,
+        });
+        let mut res = vec![];
+        for command in commands.unwrap_or_else(|| vec!["# a change of an unknown field is not recorded".into()]) {
+            // the paths of a focused message are recorded from the root of the document
+            let command = match (self.focus.last(), command.split_once(' ')) {
+                (Some(focus), Some((name, args))) if !command.starts_with('#') => format!("{} {}.{}", name, focus.text, args),
+                _ => command,
+            };
+            self.journal.push(command.clone());
+            res.push(command);
+        }
+        res
+    }
 
-        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 2 + TOP_LINE).unwrap();
-        let expected0 = [
-            " f1: 11      string ",
-            "  2: 22             "];
-        let expected1 = [
-            "  2: 22             ",
-            "  3: 33             "];
-        assert_eq!(app.to_strings(), expected0);
+    // the last step of changes is reversed, or the last undone one is repeated
+    fn undo(&mut self, redo: bool) -> io::Result<()> {
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        let step = if redo { self.history.redo.pop() } else { self.history.undo.pop() };
+        let Some(mut step) = step else {
+            return self.after_command(CommandResult::ShowMessage(format!("nothing to {}", if redo { "redo" } else { "undo" })));
+        };
+        // the step is stored in the order of applying, it is undone from the end
+        if !redo { step.changes.reverse(); }
+        for change in step.changes.iter_mut() {
+            let command = batch::change_to_command(&self.data, change);
+            if Arc::make_mut(&mut self.data).apply(change).is_some() { self.move_paths(change); }
+            self.journal_change(command, change);
+        }
+        let mut path = step.changes.last().unwrap().path.clone();
+        if !redo { step.changes.reverse(); }
+        let count = step.changes.len();
+        if redo { self.history.undo.push(step); } else { self.history.redo.push(step); }
+
+        // the nearest field left in the data is selected
+        while !path.0.is_empty() && self.data.get_field(&path.0).is_none() {
+            let last = path.0.last_mut().unwrap();
+            if last.index > 0 { last.index -= 1; } else { path.0.pop(); }
+        }
+        self.rebuild_layouts(&path);
+        let text = format!("{} change{} {}", count, if count == 1 { "" } else { "s" }, if redo { "redone" } else { "undone" });
+        self.after_command(CommandResult::ShowMessage(text))
+    }
 
-        app.run_command(ScrollVertically(1)).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected0);
-
-        app.run_command(ScrollVertically(1)).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected1);
+    // the selection is extended over the values of the repeated field containing it
+    fn extend_range(&mut self, delta: isize) -> io::Result<()> {
+        let Some((group, index, count)) = self.layouts.selected_path(&self.selected).and_then(|path| Layouts::enclosing_group(&self.data, &path)) else {
+            return self.after_command(CommandResult::ShowMessage("select a value of a repeated field".into()));
+        };
+        if !self.range.as_ref().map_or(false, |anchor| same_group(anchor, &group)) {
+            self.range = Some(group.clone());
+        }
+        let Some(next) = index.checked_add_signed(delta).filter(|next| *next < count) else {
+            let text = format!("the range is at the {} value", if delta < 0 { "first" } else { "last" });
+            return self.after_command(CommandResult::ShowMessage(text));
+        };
+        self.run_command(UserCommand::GoToPath(group.with_last_index(next)))
+    }
 
-        app.run_command(ScrollVertically(-1)).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected1);
+    // the repeated field of the range with its first and last index
+    fn selected_range(&self) -> Option<(FieldPath, usize, usize)> {
+        let anchor = self.range.as_ref()?;
+        let (group, index, _) = self.layouts.selected_path(&self.selected).and_then(|path| Layouts::enclosing_group(&self.data, &path))?;
+        if !same_group(anchor, &group) { return None; }
+        let start = anchor.0.last().unwrap().index;
+        Some((group, start.min(index), start.max(index)))
+    }
 
-        app.run_command(ScrollVertically(-1)).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected0);
+    // the values of the range, or the selected one
+    fn selected_values(&self) -> Vec<FieldPath> {
+        match self.selected_range() {
+            Some((group, first, last)) => (first..=last).map(|index| group.with_last_index(index)).collect(),
+            None => self.layouts.selected_path(&self.selected).into_iter().collect(),
+        }
     }
 
-    #[test]
-    fn repeated_strings() {
-        let binary_input = [
-            0x0A, 0x03, 0x61, 0x62, 0x63,
-            0x0A, 0x03, 0x64, 0x65, 0x66];
-        let proto = ProtoData::new("message M { repeated string f1=1; }").unwrap().finalize().unwrap();
-        let mut limit = binary_input.len() as u32;
-        let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        let data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+    // all values of the range are deleted in one step
+    fn delete_range(&mut self) -> io::Result<()> {
+        let Some((group, first, last)) = self.selected_range() else { return self.run_command(DeleteData); };
+        self.range = None;
+        if self.read_only {
+            return self.after_command(CommandResult::ShowError(READ_ONLY_ERROR.into()));
+        }
+        let paths: Vec<FieldPath> = (first..=last).rev().map(|index| group.with_last_index(index)).collect();
+        if paths.iter().any(|path| self.is_protected(path)) {
+            return self.after_command(CommandResult::ShowError("the range contains protected values".into()));
+        }
+        // the layouts of the values are recreated with their siblings
+        self.run_command(UserCommand::GoToPath(group.with_last_index(first)))?;
+        self.apply_step(paths.into_iter().map(Change::delete_value).collect());
+        self.layouts.update_after_data_changed(&self.data, &self.layout_config, self.selected.layout);
+        let (_, _, count) = Layouts::enclosing_group(&self.data, &group.with_last_index(0)).unwrap_or_default();
+        if count > 0 {
+            self.run_command(UserCommand::GoToPath(group.with_last_index(first.min(count - 1))))?;
+        }
+        self.after_command(CommandResult::ShowMessage(format!("{} values deleted", last - first + 1)))
+    }
 
-        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
-        let expected = [
-            " f1: 'abc'                                string* ",
-            " f1: 'def'                                string* "
-        ];
-        assert_eq!(app.to_strings(), expected);
+    // the selected values as edit commands, to the terminal clipboard
+    fn copy(&mut self) -> io::Result<()> {
+        let paths = self.selected_values();
+        let commands: Option<Vec<Vec<String>>> = paths.iter().map(|path| batch::field_commands(&self.data, path)).collect();
+        let Some(commands) = commands.filter(|commands| !commands.is_empty()) else {
+            return self.after_command(CommandResult::ShowMessage("the field has no data or unknown fields".into()));
+        };
+        self.to_clipboard(commands.concat().join("\n"))?;
+        let text = format!("{} value{} copied as edit commands", paths.len(), if paths.len() == 1 { "" } else { "s" });
+        self.after_command(CommandResult::ShowMessage(text))
     }
 
-    #[test]
-    fn repeated_bytes() {
-        let binary_input = [
-            0x0A, 0x02, 0x01, 0x02,
-            0x0A, 0x03, 0x03, 0x04, 0x05];
-        let proto = ProtoData::new("message M { repeated bytes f1=1; }").unwrap().finalize().unwrap();
-        let mut limit = binary_input.len() as u32;
-        let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        let data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+    fn to_clipboard(&mut self, text: String) -> io::Result<()> {
+        self.clipboard = text;
+        if !self.test_mode {
+            // OSC 52: the terminal puts the text to the system clipboard
+            let encoded = Renderer::Base64.render(self.clipboard.as_bytes()).unwrap_or_default();
+            write!(self.stdout, "\x1b]52;c;{}\x07", encoded)?;
+            self.stdout.flush()?;
+        }
+        Ok(())
+    }
 
-        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
-        let expected = [
-            " f1: 01 02                                 bytes* ",
-            " f1: 03 04 05                              bytes* "
-        ];
-        assert_eq!(app.to_strings(), expected);
+    // values of the range are marked in the left margin
+    fn mark_range(range: &Option<(FieldPath, usize, usize)>, path: &FieldPath, amount: usize, lines: &mut ScreenLines) {
+        let Some((group, first, last)) = range else { return; };
+        let len = group.0.len();
+        if path.0.len() < len || !same_group(&FieldPath(path.0[..len].to_vec()), group) { return; }
+        let start = path.0[len - 1].index;
+        // a layout of several scalar values is marked if any of them is in the range
+        let end = if path.0.len() == len { start + amount.max(1) - 1 } else { start };
+        if end < *first || start > *last { return; }
+        for line in lines.0.iter_mut() {
+            if let Some(first @ (' ', _)) = line.0.first_mut() { *first = (RANGE_MARK, TextStyle::Bookmark); }
+        }
     }
 
+    // the selected message is shown as the root of the document, its path is shown in the top line
+    fn open_focus(&mut self) -> io::Result<()> {
+        let Some(path) = self.layouts.items.get(self.selected.layout).filter(|item| !item.is_page()).map(|item| item.path.clone()).
+            filter(|path| matches!(self.data.get_field(&path.0).map(|field| &field.value), Some(FieldValue::MESSAGE(_)))) else {
+            return self.after_command(CommandResult::ShowMessage("select a message with data".into()));
+        };
+        let Some(name) = path.to_text(&self.data) else {
+            return self.after_command(CommandResult::ShowMessage("an unknown message cannot be opened".into()));
+        };
+        let field = Arc::make_mut(&mut self.data).get_field_mut(&path.0).unwrap();
+        let FieldValue::MESSAGE(msg) = &field.value else { unreachable!() };
+        let hole = FieldValue::MESSAGE(Arc::new(MessageData { def: msg.def.clone(), fields: vec![], packed: vec![] }));
+        let FieldValue::MESSAGE(msg) = std::mem::replace(&mut field.value, hole) else { unreachable!() };
+
+        let text = match self.focus.last() {
+            Some(focus) => format!("{}.{}", focus.text, name),
+            None => name,
+        };
+        // the collapsed messages inside keep their state, with the paths from the focused message
+        let inner = self.layout_config.collapsed.iter().filter(|other| other.0.len() > path.0.len() && other.0.starts_with(&path.0)).
+            map(|other| FieldPath(other.0[path.0.len()..].to_vec())).collect();
+        let collapsed = std::mem::replace(&mut self.layout_config.collapsed, inner);
+        let layouts = Layouts::new(&msg, &self.layout_config, self.layouts.file_name.clone(), self.layouts.width, self.layouts.height);
+        self.focus.push(Focus {
+            path: self.absolute(&path),
+            text,
+            parent: std::mem::replace(&mut self.data, msg),
+            layouts: std::mem::replace(&mut self.layouts, layouts),
+            selected: std::mem::take(&mut self.selected),
+            history: std::mem::take(&mut self.history),
+            collapsed,
+        });
+        self.need_update_layout_height = true;
+        self.need_update = true;
+        Ok(())
+    }
 
-    #[test]
-    fn fit_bytes_width() {
-        {
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 16]));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 60, 25).unwrap();
-            let expected = [" f1: 00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00 bytes "];
-            assert_eq!(app.to_strings(), expected);
-        }
-        { // all the same but repeated field add '*'
-            let data = make_one_field_data("message M { repeated bytes f1=1; }", BYTES(vec![0; 16]));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 60, 25).unwrap();
-            let expected = [
-                " f1: 00 00 00 00 00 00 00 00                         bytes* ",
-                "  8: 00 00 00 00 00 00 00 00                                "];
-            assert_eq!(app.to_strings(), expected);
-        }
-        {
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 16]));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 59, 25).unwrap();
-            let expected = [
-                " f1: 00 00 00 00 00 00 00 00                         bytes ",
-                "  8: 00 00 00 00 00 00 00 00                               "];
-            assert_eq!(app.to_strings(), expected);
-        }
-        {
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 9]));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 39, 25).unwrap();
-            let expected = [
-                " f1: 00 00 00 00 00 00 00 00  00 bytes ",
-            ];
-            assert_eq!(app.to_strings(), expected);
+    // back to the parent view, false if the whole document is shown
+    fn close_focus(&mut self) -> bool {
+        let Some(focus) = self.focus.pop() else { return false; };
+        let msg = std::mem::replace(&mut self.data, focus.parent);
+        let path = FieldPath(focus.path.0[self.absolute(&FieldPath::new()).0.len()..].to_vec());
+        if let Some(field) = Arc::make_mut(&mut self.data).get_field_mut(&path.0) { field.value = FieldValue::MESSAGE(msg); }
+
+        // the changes made in the message are undone from the parent view
+        let inner = std::mem::replace(&mut self.history, focus.history);
+        let moved = |steps: Vec<Step>| -> Vec<Step> {
+            steps.into_iter().map(|step| Step { changes: step.changes.into_iter().map(|mut change| {
+                change.path = FieldPath([path.0.as_slice(), &change.path.0].concat());
+                change
+            }).collect(), commands: step.commands }).collect()
+        };
+        if !inner.undo.is_empty() || !inner.redo.is_empty() {
+            self.history.redo = moved(inner.redo);
         }
-        {
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 9]));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 32, 25).unwrap();
-            let expected = [
-                " f1: 00 00 00 00 00 00 00 bytes ",
-                "  7: 00 00                      ",
-            ];
-            assert_eq!(app.to_strings(), expected);
+        self.history.undo.extend(moved(inner.undo));
+
+        let inner = std::mem::replace(&mut self.layout_config.collapsed, focus.collapsed);
+        self.layout_config.collapsed.retain(|other| !(other.0.len() > path.0.len() && other.0.starts_with(&path.0)));
+        self.layout_config.collapsed.extend(inner.into_iter().map(|other| FieldPath([path.0.as_slice(), &other.0].concat())));
+        self.layouts = focus.layouts;
+        self.selected = focus.selected;
+        self.layouts.update_after_data_changed(&self.data, &self.layout_config, self.selected.layout);
+        self.range = None;
+        self.need_update_layout_height = true;
+        self.need_update = true;
+        true
+    }
+
+    // the path from the root of the document
+    fn absolute(&self, path: &FieldPath) -> FieldPath {
+        match self.focus.last() {
+            Some(focus) => FieldPath([focus.path.0.as_slice(), &path.0].concat()),
+            None => path.clone(),
         }
-        {
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 9]));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 33, 25).unwrap();
-            let expected = [
-                " f1: 00 00 00 00 00 00 00  bytes ",
-                "  7: 00 00                       ",
-            ];
-            assert_eq!(app.to_strings(), expected);
+    }
+
+    // a field of the document by the path from its root, also outside of the focused message
+    fn field_at(&self, path: &FieldPath) -> Option<&FieldData> {
+        for level in (0..=self.focus.len()).rev() {
+            let base = if level == 0 { &[][..] } else { self.focus[level - 1].path.0.as_slice() };
+            let data = if level == self.focus.len() { &self.data } else { &self.focus[level].parent };
+            if path.0.starts_with(base) { return data.get_field(&path.0[base.len()..]); }
         }
+        None
     }
 
-    //    #[test]
-    //    fn trim_bytes_width() {
-    //        {
-    //            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 16]));
-    //            let mut app = App::for_tests(data, FieldOrder::Proto, 25, 25).unwrap();
-    //            assert_eq!(app.to_strings()[0], " f1: 00 00 00 00 00bytes ");
-    //        }
-    //    }
+    // protect the selected field with all its content, or remove the protection
+    fn protect_toggle(&mut self) -> io::Result<()> {
+        let Some(item) = self.layouts.items.get(self.selected.layout).filter(|item| !item.is_page()) else { return Ok(()); };
+        // values of a repeated scalar shown together are protected till the end of the field
+        let (path, amount) = (item.path.clone(), if item.amount > 1 { usize::MAX } else { 1 });
+        let entry = (self.absolute(&path), amount);
+        let text = if let Some(pos) = self.protected.iter().position(|p| p.0.0 == entry.0.0) {
+            self.protected.remove(pos);
+            "protection removed"
+        } else if self.is_protected(&path) {
+            "the parent field is protected"
+        } else {
+            self.protected.push(entry);
+            "protected"
+        };
+        self.after_command(CommandResult::ShowMessage(text.into()))
+    }
 
-    #[test]
-    fn delete_byte() {
-        {
-            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::ScrollVertically(1)).unwrap();
-            app.after_event().unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            let expected = [
-                " f1: 01 02 03 04 05 06  bytes ", // data left unchanged if address row was selected
-                "  6: 07 08                    "];
-            assert_eq!(app.to_strings(), expected);
-        }
+    // the paths kept from the root of the document follow their values when a value is inserted or deleted before
+    // them in the same field, the paths of a deleted value are dropped; the collapsed ones are from the focused message,
+    // the protected, pinned and watched ones from the root
+    fn move_paths(&mut self, applied: &Change) {
+        if !applied.layout_changed() { return; }
+        self.layout_config.collapsed = std::mem::take(&mut self.layout_config.collapsed).iter().filter_map(|path| applied.moved_path(path)).collect();
+        let base = self.absolute(&FieldPath::new());
+        let moved = |path: &FieldPath| match path.0.strip_prefix(base.0.as_slice()) {
+            Some(relative) => applied.moved_path(&FieldPath(relative.to_vec())).map(|moved| FieldPath([base.0.as_slice(), &moved.0].concat())),
+            None => Some(path.clone()),
+        };
+        self.protected = std::mem::take(&mut self.protected).into_iter().filter_map(|(path, amount)| match moved(&path) {
+            Some(path) => Some((path, amount)),
+            // the next value takes the place of the first one of protected repeated values
+            None if amount > 1 => Some((path, amount)),
+            None => None,
+        }).collect();
+        // the pinned and watched fields are shown with their new paths
+        let (pins, watches) = (std::mem::take(&mut self.pins), std::mem::take(&mut self.watches));
+        let follow = |fields: Vec<(FieldPath, String)>| -> Vec<(FieldPath, String)> {
+            fields.into_iter().filter_map(|(path, name)| {
+                let new = moved(&path)?;
+                if new == path { return Some((path, name)); }
+                let name = self.absolute_text(&FieldPath(new.0[base.0.len()..].to_vec())).unwrap_or(name);
+                Some((new, name))
+            }).collect()
+        };
+        let (pins, watches) = (follow(pins), follow(watches));
+        self.pins = pins;
+        self.watches = watches;
+    }
 
-        {
-            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-            let expected = [
-                " f1: 01 02 03 04 05 06  bytes ",
-                "  6: 07 08                    "];
-            assert_eq!(app.to_strings(), expected);
-            app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
-            app.after_event().unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            let expected = [
-                " f1: 02 03 04 05 06 07  bytes ",
-                "  6: 08                       "];
-            assert_eq!(app.to_strings(), expected);
+    // the path is inside of a protected field
+    fn is_protected(&self, path: &FieldPath) -> bool {
+        let path = &self.absolute(path);
+        self.protected.iter().any(|(protected, amount)| {
+            match protected.0.split_last() {
+                Some((last, parents)) => path.0.len() > parents.len() && path.0[..parents.len()] == *parents && {
+                    let pos = &path.0[parents.len()];
+                    pos.id == last.id && pos.index >= last.index && pos.index < last.index.saturating_add(*amount)
+                },
+                None => true,
+            }
+        })
+    }
+
+    // the bytes of the values in the saved file before the type name of the first line
+    fn mark_size(&self, path: &FieldPath, amount: usize, lines: &mut ScreenLines) {
+        if !self.layout_config.show_sizes { return; }
+        let Some(size) = self.data.encoded_size(path, amount, &self.proto) else { return; };
+        if let Some(line) = lines.0.first_mut() {
+            line.insert_before_typename(&format!(" {} ", overview::size_text(size)), TextStyle::DataSize);
         }
-        {
-            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::ScrollHorizontally(2)).unwrap();
-            app.after_event().unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            let expected = [
-                " f1: 01 03 04 05 06 07  bytes ",
-                "  6: 08                       "];
-            assert_eq!(app.to_strings(), expected);
+    }
+
+    // a value of a declared field read with another wire type is kept as unknown bytes: its lines get
+    // a mark, the first one the declared and the read wire types before the type name
+    fn mark_wire_conflict(&self, path: &FieldPath, amount: usize, lines: &mut ScreenLines) {
+        if !self.layout_config.show_binary { return; }
+        let conflict = (0..amount).find_map(|i| {
+            let path = if amount > 1 { path.with_last_index(path.0.last()?.index + i) } else { path.clone() };
+            let Some(SCALAR(ScalarValue::UNKNOWN(tag, _))) = self.data.get_field(&path.0).map(|field| &field.value) else { return None; };
+            if tag.wire_type() == WT_SGROUP { return None; }
+            let def = self.data.get_field_definition(&path)?;
+            Some(format!(" read as {} ({}), declared {} ", wire_type_name(tag.wire_type()), wire_type_values(tag.wire_type()), wire_type_name(def.wire_type())))
+        });
+        let Some(text) = conflict else { return; };
+        for line in lines.0.iter_mut() {
+            if let Some(first @ (' ', _)) = line.0.first_mut() { *first = (WIRE_CONFLICT_MARK, TextStyle::Warning); }
         }
-        {
-            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::ScrollHorizontally(22)).unwrap();
-            app.after_event().unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            let expected = [
-                " f1: 01 02 03 04 05 07  bytes ",
-                "  6: 08                       "];
-            assert_eq!(app.to_strings(), expected);
+        if let Some(line) = lines.0.first_mut() { line.insert_before_typename(&text, TextStyle::Warning); }
+    }
 
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            let expected = [
-                " f1: 01 02 03 04 05 08  bytes "];
-            assert_eq!(app.to_strings(), expected);
+    fn mark_protected(&self, path: &FieldPath, lines: &mut ScreenLines) {
+        if self.is_protected(path) {
+            for line in lines.0.iter_mut() {
+                if let Some(first @ (' ', _)) = line.0.first_mut() { *first = (PROTECTED_MARK, TextStyle::Protected); }
+            }
+        }
+    }
 
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            let expected = [
-                " f1: 01 02 03 04 05     bytes "];
-            assert_eq!(app.to_strings(), expected);
+    fn get_top_line(&self, width: u16, config: &LayoutConfig) -> String {
+        let mut parts = Vec::with_capacity(3);
+        // the path of the selected field after the file name, shortened from the left if there is no room
+        let mut crumbs = vec![];
+
+        parts.push(match self.focus.last() {
+            Some(focus) => format!("{} > {}", self.layouts.file_name, focus.text),
+            None => self.layouts.file_name.clone(),
+        });
+        if let Some(Review { merge: Some(path), .. }) = &self.review {
+            let name = self.data.get_field_definition(path).map_or(String::new(), |def| def.name());
+            parts.push(format!("{} after merging its groups, Y - merge, Esc - cancel", name));
+        } else if let Some(review) = &self.review {
+            if review.lossy > 0 {
+                parts.push(format!("{} changes, {} fields not saved as read, Y - save anyway, Esc - cancel", review.lines.len() - review.lossy, review.lossy));
+            } else {
+                parts.push(format!("{} changes, Y - save, Esc - cancel", review.lines.len()));
+            }
+        } else if self.help.is_some() {
+            parts.push("keys, Up/Down - scroll, other key - close".into());
+        } else if let Some(report) = &self.report {
+            parts.push(report.title.clone());
+        } else if let Some(message) = &self.message {
+            parts.push(message.clone());
+        } else if let Some(current) = self.layouts.items.get(self.selected.layout) {
+            debug_assert!(current.layout.is_some());
+            let percent = 100.0 * self.layouts.calc_relative_pos(self.selected.layout);
+            let enclosing = self.layouts.selected_path(&self.selected).and_then(|path| Layouts::enclosing_group(&self.data, &path));
+            let group = enclosing.as_ref().map_or(String::new(), |(_, index, count)| format!("{} of {}", index + 1, count));
+            let group_path = enclosing.map(|(path, _, _)| path);
+            let range = self.selected_range().map_or(String::new(), |(_, first, last)| format!("{} selected", last - first + 1));
+            // the top-level value containing the selection, if the group above is another one
+            let record = self.layouts.selected_path(&self.selected).filter(|path| path.0.len() > 1 && group_path.as_ref().map_or(true, |group| group.0.len() > 1)).
+                and_then(|path| Layouts::records(&self.data, Some(&path)).filter(|(id, _)| *id == path.0[0].id).map(|(_, count)| format!("record {}/{}", path.0[0].index + 1, count))).
+                unwrap_or_default();
+            crumbs = self.breadcrumbs().into_iter().map(|(name, _)| name).collect();
+            parts[0] = crumbs.join(" > ");
+            let status = [current.get_status_string(self.selected.x, self.selected.y), record, group, range, current.get_wire_status(&self.data, self.selected.x, self.selected.y)];
+            parts.push(status.into_iter().filter(|s| !s.is_empty()).collect::<Vec<_>>().join(" "));
+            parts.push(format!("{:.0}% {}{}{}{}", percent, if self.layouts.pending_sizes.is_some() { "working... " } else { "" },
+                if self.recording.is_some() { "REC " } else { "" }, if self.read_only { "RO " } else { "" }, config.field_order.first_letter()));
         }
-        {
-            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes));
-            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
-            app.after_event().unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::ScrollVertically(1)).unwrap();
-            app.after_event().unwrap();
-            app.to_strings();
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            let expected = [
-                " f1: 01 02 03 04 05 06  bytes ",
-                "  6: 08                       "];
-            assert_eq!(app.to_strings(), expected);
 
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            let expected = [
-                " f1: 01 02 03 04 05 06  bytes "];
-            assert_eq!(app.to_strings(), expected);
+        loop {
+            let total_len: u16 = parts.iter().map(|s| s.len() as u16).sum();
+            let margins = config.margin_left + config.margin_right;
+            if total_len + margins < width {
+                let avail_len = width - total_len - margins;
+                let gaps = (parts.len() as u16).max(2) - 1;
+                let span = avail_len / gaps;
+                let last_span = avail_len - span * (gaps - 1);
+
+                let mut res = " ".repeat(config.margin_left as usize);
+                for i in 0..parts.len() {
+                    res += &parts[i];
 
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            let expected = [
-                " f1: 01 02 03 04 05     bytes "];
-            assert_eq!(app.to_strings(), expected);
+                    if i < parts.len() - 1 {
+                        let span = if i == parts.len() - 2 { last_span } else { span };
+                        res += &" ".repeat(span as usize);
+                    }
+                }
+                if parts.len() == 1 { res += &" ".repeat(avail_len as usize); }
+
+                res += &" ".repeat(config.margin_right as usize);
+                return res;
+            } else if crumbs.len() > 1 {
+                crumbs.remove(0);
+                parts[0] = format!("… > {}", crumbs.join(" > "));
+            } else {
+                match parts.len() { // remove parts of the line if no room
+                    3 => { parts.remove(0); }
+                    2 => { parts.remove(if self.message.is_some() { 0 } else { 1 }); }
+                    _ => return String::new(),
+                }
+            }
         }
-        {
-            let bytes = (1..=3).into_iter().collect::<Vec<u8>>();
-            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes));
+    }
+
+    // find out the line number with active cursor
+    fn calc_scroll_pos(&self) -> usize { // move to layouts
+        let mut selected_line = 0;
+        let mut y = 0;
+        for index in 0..self.layouts.items.len() {
+            let item = &self.layouts.items[index];
+            if self.selected.layout == index {
+                //-                debug_assert!(self.selected.x == 0); // for other columns algorithm more complex
+                selected_line = y + self.selected.y;
+                break;
+            }
+            y += item.height;
+        }
+        // correct scroll position if active cursor is above/below visible window
+        let height = self.data_height() as usize;
+        if selected_line + 1 >= self.layouts.scroll + height {
+            return selected_line + 1 - height;
+        }
+        if selected_line < self.layouts.scroll {
+            return selected_line;
+        }
+        self.layouts.scroll
+    }
+
+    fn first_visible_line(&self) -> (usize, usize) {
+        let mut skip_lines = self.layouts.scroll;
+        let mut lines_len = 0;
+        for layout_index in 0..self.layouts.items.len() {
+            let item = &self.layouts.items[layout_index];
+
+            //if item.layout.is_none() { return (0, 0); }
+            //            if item.layout.is_none() { panic!("layout is not loaded: {}", layout_index); }
+            //            debug_assert!(item.layout.is_some());   // TODO
+
+            debug_assert!(item.height > 0);
+            lines_len = item.height;
+            if lines_len > skip_lines {
+                return (layout_index, skip_lines);
+            }
+            skip_lines -= lines_len;
+        }
+
+        if self.layouts.items.is_empty() {
+            (0, 0)
+        } else {
+            (self.layouts.items.len() - 1, lines_len - 1)
+        }
+    }
+
+    // lines of the data visible on the screen, below the top line
+    fn compose_screen(&mut self) -> Vec<ScreenLine> {
+        let mut y = self.data_top();
+        let mut res = vec![];
+
+        let (margin_left, margin_right) = (self.layout_config.margin_left, self.layout_config.margin_right);
+        let text = self.review.as_ref().map(|review| (&review.lines, review.scroll)).or(self.help.as_ref().map(|help| (&help.lines, help.scroll)));
+        if let Some((lines, scroll)) = text {
+            let width = self.width.saturating_sub(margin_left + margin_right) as usize;
+            for text in lines.iter().skip(scroll).take((self.height - TOP_LINE) as usize) {
+                let text: String = text.chars().take(width).collect();
+                let mut line = ScreenLine::new(self.width);
+                line.add_string(" ".repeat(margin_left as usize) + &format!("{text:width$}") + &" ".repeat(margin_right as usize), TextStyle::Value);
+                res.push(line);
+            }
+            return res;
+        }
+
+        if let Some(report) = &self.report {
+            let width = self.width.saturating_sub(margin_left + margin_right) as usize;
+            for (index, (text, _)) in report.lines.iter().enumerate().skip(report.scroll).take((self.height - TOP_LINE) as usize) {
+                let text: String = text.chars().take(width).collect();
+                let style = if index == report.selected { TextStyle::SelectedValue } else { TextStyle::Value };
+                let mut line = ScreenLine::new(self.width);
+                line.add_string(" ".repeat(margin_left as usize), TextStyle::Value);
+                line.add_string(format!("{text:width$}"), style);
+                line.add_string(" ".repeat(margin_right as usize), TextStyle::Value);
+                res.push(line);
+            }
+            return res;
+        }
+
+        let (layout_index, mut skip_lines) = self.first_visible_line();
+        self.layouts.ensure_loaded(&self.data, &self.layout_config, layout_index, 0, self.height as usize + skip_lines, &mut self.selected);
+        if self.layouts.fit_indents(&self.layout_config, layout_index) {
+            // the layouts on the screen are sized again with the new indents
+            self.layouts.ensure_loaded(&self.data, &self.layout_config, layout_index, 0, self.height as usize + skip_lines, &mut self.selected);
+        }
+        let range = self.selected_range();
+
+        for index in layout_index..self.layouts.items.len() {
+            let item = &self.layouts.items[index];
+            let cursor = if index == self.selected.layout { Some((self.selected.x, self.selected.y)) } else { None };
+            let indent = self.layouts.indents[item.level() - 1];
+
+            let count = (self.height - y) as usize;
+            let mut lines = item.get_screen_window(&self.data, self.layouts.width, indent, &self.layout_config, cursor, skip_lines, count);
+            if skip_lines == 0 { self.mark_size(&item.path, item.amount, &mut lines); }
+            self.mark_wire_conflict(&item.path, item.amount, &mut lines);
+            self.mark_protected(&item.path, &mut lines);
+            Self::mark_range(&range, &item.path, item.amount, &mut lines);
+            if self.highlights.contains(&self.data, &item.path) {
+                for line in lines.0.iter_mut() { line.mark_highlight(); }
+            }
+            skip_lines = 0;
+            if let Some(prompt) = self.prompt.as_ref().filter(|prompt| prompt.name == SEARCH_PROMPT) {
+                let text = prompt.text();
+                for line in lines.0.iter_mut() { line.mark_matches(&text); }
+            }
+
+            for line in lines.0 {
+                res.push(line);
+                y += 1;
+                if y >= self.height { break; }
+            }
+            if y >= self.height { break; }
+        }
+
+        if self.right_width() > 0 {
+            let height = self.data_height() as usize;
+            let mut texts = if self.pane_shown() { self.pane_lines() } else { self.sidebar_lines() }.into_iter();
+            res.truncate(height);
+            while res.len() < height { res.push(ScreenLine::new(self.layouts.width)); }
+            for line in res.iter_mut() {
+                line.fix_length(self.layouts.width);
+                line.add_string("│ ".into(), TextStyle::Divider);
+                line.add_string(texts.next().unwrap_or_default(), TextStyle::Value);
+                line.fix_length(self.width);
+            }
+        }
+
+        // the pinned fields are above the data, the watch list, the performance counters and the prompt cover the last lines
+        let pin_lines = self.pin_lines();
+        let watch_lines = self.watch_lines();
+        if self.prompt.is_some() || !pin_lines.is_empty() || !watch_lines.is_empty() || self.perf_overlay {
+            let height = (self.data_height() as usize).saturating_sub(if self.prompt.is_some() { 1 } else { 0 });
+            res.truncate(height);
+            while res.len() < height {
+                let mut line = ScreenLine::new(self.width);
+                line.fix_length(self.width);
+                res.push(line);
+            }
+            res.extend(watch_lines);
+            if self.perf_overlay { res.push(self.perf_line()); }
+        }
+        res.splice(0..0, pin_lines);
+        if let Some(prompt) = &self.prompt {
+            res.push(prompt.get_screen(self.width, &self.layout_config));
+        }
+        res
+    }
+
+    // the top line and the lines of the data as they are drawn
+    fn screen_lines(&mut self) -> Vec<ScreenLine> {
+        let mut lines = Vec::with_capacity(self.height as usize);
+        if TOP_LINE > 0 {
+            let mut top_line = ScreenLine::new(self.width);
+            top_line.add_string(self.get_top_line(self.width, &self.layout_config), TextStyle::TopLine);
+            lines.push(top_line);
+        }
+        lines.append(&mut self.compose_screen());
+        lines
+    }
+
+    // the counters of the performance overlay: "draw 1.2 ms, layouts 120 of 5000, read 1.4 MiB"
+    fn perf_line(&self) -> ScreenLine {
+        let created = self.layouts.items.iter().filter(|item| item.layout.is_some()).count();
+        let text = format!(" draw {:.1} ms, layouts {} of {}, read {} ", self.draw_time.as_secs_f64() * 1000.0,
+            created, self.layouts.items.len(), overview::size_text(typedefs::bytes_read()));
+        let mut line = ScreenLine::new(self.width);
+        line.add_string(text, TextStyle::Divider);
+        line.add_string("─".repeat(self.width as usize), TextStyle::Divider);
+        line.fix_length(self.width);
+        line
+    }
+
+    // output data to the screen, only lines changed since the previous update are printed
+    fn update(&mut self) -> io::Result<()> {
+        let start = Instant::now();
+        let lines = self.screen_lines();
+
+        let mut current_style = TextStyle::Unknown;
+        for (y, line) in lines.iter().enumerate() {
+            let empty = ScreenLine::new(0);
+            let old_line = self.screen.get(y).unwrap_or(&empty);
+            let Some((start, end)) = changed_range(old_line, line) else { continue; };
+            self.stdout.queue(cursor::MoveTo(start as u16, y as u16))?;
+            let mut text = String::new();
+            for (c, s) in &line.0[start..end] {
+                if *c == WIDE_TAIL { continue; }
+                if *s != current_style {
+                    if !text.is_empty() {
+                        self.stdout.queue(current_style.activate())?;
+                        self.stdout.queue(style::Print(text))?;
+                        text = String::new();
+                    }
+                    current_style = *s;
+                }
+                text.push(*c);
+            }
+            if !text.is_empty() {
+                self.stdout.queue(current_style.activate())?;
+                self.stdout.queue(style::Print(text))?;
+            }
+            if line.0.len() < old_line.0.len() {
+                self.stdout.queue(style::ResetColor)?;
+                self.stdout.queue(terminal::Clear(terminal::ClearType::UntilNewLine))?;
+                current_style = TextStyle::Unknown;
+            }
+        }
+        if lines.len() < self.screen.len() { // clear the free space below if any
+            self.stdout.queue(cursor::MoveTo(0, lines.len() as u16))?;
+            self.stdout.queue(style::ResetColor)?;
+            self.stdout.queue(terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        }
+        self.screen = lines;
+        self.stdout.flush()?;
+        self.draw_time = start.elapsed();
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn to_strings(&mut self) -> Vec<String> {
+        self.compose_screen().into_iter().map(|line| line.0.into_iter().map(|v| v.0).filter(|c| *c != WIDE_TAIL).collect()).collect()
+    }
+
+    // each key is handled as typed, the layouts left by expanding are sized before the next one; false if the editor is closed
+    pub fn run_keys(&mut self, keys: &[KeyEvent]) -> io::Result<bool> {
+        for key in keys {
+            if !self.on_key(*key)? { return Ok(false); }
+            self.layouts.receive_sizes(&self.data, &self.layout_config, true);
+            self.after_event()?;
+        }
+        Ok(true)
+    }
+
+    // the screen with the top line, with the styles of the chars below each line if `styles`
+    pub fn snapshot(&mut self, styles: bool) -> Vec<String> {
+        let lines = self.screen_lines();
+        if styles { return harness::styled_lines(&lines); }
+        lines.into_iter().map(|line| line.0.into_iter().map(|v| v.0).filter(|c| *c != WIDE_TAIL).collect()).collect()
+    }
+}
+
+// part of the new line to print over the old one, None if nothing changed
+fn changed_range(old: &ScreenLine, new: &ScreenLine) -> Option<(usize, usize)> {
+    if old.0 == new.0 { return None; }
+    let mut start = old.0.iter().zip(new.0.iter()).take_while(|(a, b)| a == b).count();
+    let mut end = new.0.len();
+    if old.0.len() == new.0.len() {
+        end -= old.0[start..].iter().rev().zip(new.0[start..].iter().rev()).take_while(|(a, b)| a == b).count();
+    }
+    // a wide char is printed as a whole
+    if start > 0 && new.0.get(start).map_or(false, |(c, _)| *c == WIDE_TAIL) { start -= 1; }
+    if new.0.get(end).map_or(false, |(c, _)| *c == WIDE_TAIL) { end += 1; }
+    Some((start, end))
+}
+
+impl Drop for App {
+    fn drop(&mut self) {
+        // keep all the changes for the recovery
+        if std::thread::panicking() { let _ = self.journal.write(); }
+        if !self.test_mode {
+            let _ = self.stdout.flush();
+            restore_terminal();
+        }
+    }
+}
+
+// the modes set by App::new are reset, the screen and the title are restored; only the first call does it
+fn restore_terminal() {
+    if !TERMINAL_SET.swap(false, atomic::Ordering::SeqCst) { return; }
+    let _ = crossterm::terminal::disable_raw_mode();
+    let Ok(mut out) = terminal_output() else { return; };
+    let _ = out.execute(DisableBracketedPaste);
+    let _ = out.execute(DisableFocusChange);
+    let _ = out.execute(DisableMouseCapture);
+    if ALTERNATE_SCREEN.load(atomic::Ordering::SeqCst) { let _ = out.execute(LeaveAlternateScreen); }
+    let _ = out.write_all(b"\x1b[23;0t");
+    let _ = out.execute(cursor::Show);
+}
+
+// the top line of the schema browser with the text searched
+fn schema_title(search: &str) -> String {
+    let search = if search.is_empty() { "type to search".to_string() } else { format!("search: {}", search) };
+    format!("schema, {}, Tab - next, Enter - go to the data, Esc - close", search)
+}
+
+// the types written with the wire type
+fn wire_type_values(wire_type: u8) -> &'static str {
+    match wire_type {
+        WT_VARINT => "integer, bool or enum",
+        WT_I64 => "fixed64 or double",
+        WT_LEN => "string, bytes, message or packed",
+        WT_I32 => "fixed32 or float",
+        _ => "group",
+    }
+}
+
+// decimal or hex (0x1f) offset in a bytes field
+fn parse_offset(text: &str) -> Option<usize> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+// stdout if it is the terminal, otherwise the terminal device, to leave stdout for the data
+fn terminal_output() -> io::Result<Box<dyn Write>> {
+    if io::stdout().is_terminal() {
+        return Ok(Box::new(io::stdout()));
+    }
+    let device = if cfg!(windows) { "CONOUT$" } else { "/dev/tty" };
+    Ok(Box::new(std::fs::OpenOptions::new().write(true).open(device)?))
+}
+
+// the content of an existing file, or the text as hex if it is hex, otherwise as base64
+// "-;format.proto" reads stdin, other values starting with '-' are unknown options
+fn file_arg(text: &str) -> Result<String, String> {
+    match text == "-" || text.starts_with("-;") || !text.starts_with('-') {
+        true => Ok(text.to_string()),
+        false => Err(format!("unexpected option {}", text)),
+    }
+}
+
+fn parse_bytes(text: &str) -> Result<Vec<u8>, String> {
+    if text.is_empty() { return Err("a file, hex or base64 expected".into()); }
+    if std::path::Path::new(text).is_file() {
+        return std::fs::read(text).map_err(|e| format!("cannot read {}: {}", text, e));
+    }
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    CommonFieldProto::parse_hex(&compact).or_else(|| render::base64_decode(&compact)).
+        ok_or_else(|| "not a file, hex or base64".into())
+}
+
+fn exit_with_error<T: std::fmt::Display>(message: T, code: i32) -> ! {
+    let _ = io::stderr().execute(style::SetForegroundColor(Color::Red));
+    eprint!("error");
+    let _ = io::stderr().execute(style::ResetColor);
+    eprintln!(": {}", message);
+    exit(code);
+}
+
+
+/// Protobuf editor
+#[derive(Parser, Debug)]
+#[command(
+    version,
+    about,
+    long_about = "\nTerminal-based protobuf data files editor.\nhttps://github.com/friend2025/protoedit",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Args {
+    /// Input file: data.pb{;format.proto{;message_name}}, the recent files and the file picker are shown without it
+    #[arg(allow_hyphen_values = true, value_parser = file_arg)]
+    file: Option<String>,
+
+    /// Set of directories for proto files search
+    #[arg(short = 'I', long = "proto_path", global = true)]
+    proto_path: Vec<PathBuf>,
+
+    /// Key bindings: function keys, or additional letter chords for terminals that intercept F-keys
+    #[arg(long = "keys", value_enum)]
+    keys: Option<KeyProfile>,
+
+    /// Apply edit commands from the file ("-" for stdin) and save the data without starting the editor
+    #[arg(long = "apply", value_name = "EDITS", requires = "file")]
+    apply: Option<PathBuf>,
+
+    /// Save with --apply even if some fields of the file would not be written back as read (the proto file does not match the data)
+    #[arg(long = "force", requires = "apply")]
+    force: bool,
+
+    /// Open the data for viewing only, editing commands and saving are disabled
+    #[arg(long = "read-only", conflicts_with = "apply")]
+    read_only: bool,
+
+    /// Open the editor at the field: a.b[2].name
+    #[arg(long = "goto", value_name = "PATH")]
+    goto: Option<String>,
+
+    /// Open the editor at the first value containing the text
+    #[arg(long = "find", value_name = "TEXT")]
+    find: Option<String>,
+
+    /// Open the editor with the values differing from another version of the data file, to take or keep each of them
+    #[arg(long = "merge", value_name = "OTHER", conflicts_with = "apply")]
+    merge: Option<PathBuf>,
+
+    /// Save the data to the file instead of the input one ("-" for stdout)
+    #[arg(long = "output", value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Show a field (a.b.name) or all fields of a type (bytes, string) with a renderer:
+    /// utf8, json-pretty, uuid, base64, ip-address
+    #[arg(long = "render", value_name = "FIELD=RENDERER", value_parser = Renderers::parse_rule)]
+    render: Vec<(String, Renderer)>,
+
+    /// Show the wire encoding after the field type: ~z zigzag (sint32~z), ~f fixed size (fixed64~f, double~f)
+    #[arg(long = "show-encoding")]
+    show_encoding: bool,
+
+    /// Draw in the main screen of the terminal instead of the alternate one, the last screen is kept after the exit
+    #[arg(long = "no-alt-screen")]
+    no_alt_screen: bool,
+
+    /// Settings file (indent, margins, addresses), ~/.config/protoedit.conf by default
+    #[arg(long = "config", value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Check the data with expressions, exit code is 1 if any of them is false
+    Assert {
+        /// Input file: data.pb{;format.proto{;message_name}}
+        file: String,
+
+        /// Expression like "settings.timeout > 0 && items.len() >= 1"
+        #[arg(long = "expr", required = true)]
+        expr: Vec<String>,
+    },
+    /// Start the editor with an empty document of the root message, all fields have their defaults
+    New {
+        /// Proto file: format.proto{;message_name}
+        proto: String,
+
+        /// The file to save the document to, saving asks for it if it is not given
+        #[arg(long = "output", value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Write a document of the root message filled with random values, for testing the readers of the format
+    Generate {
+        /// Proto file: format.proto{;message_name}
+        proto: String,
+
+        /// The file to write the document to
+        #[arg(long = "out", value_name = "FILE")]
+        out: PathBuf,
+
+        /// Values of each repeated field of the root message
+        #[arg(long = "count", default_value_t = 10)]
+        count: usize,
+
+        /// The same seed gives the same document, taken from the clock if not given
+        #[arg(long = "seed")]
+        seed: Option<u64>,
+    },
+    /// Check that the data is written back byte for byte when saved without changes, exit code is 1 if it is not
+    Verify {
+        /// Input file: data.pb{;format.proto{;message_name}}
+        file: String,
+    },
+    /// Print the screen of the editor after the keys are pressed, without a terminal
+    Screen {
+        /// Input file: data.pb{;format.proto{;message_name}}
+        file: String,
+
+        /// Keys as in the help screen: Down F5 Ctrl+E "text" Enter
+        #[arg(long = "press", value_name = "KEYS", default_value = "")]
+        press: String,
+
+        /// File with the keys, one or more on a line, # starts a comment
+        #[arg(long = "script", value_name = "FILE", conflicts_with = "press")]
+        script: Option<PathBuf>,
+
+        #[arg(long = "width", default_value_t = 80)]
+        width: u16,
+
+        #[arg(long = "height", default_value_t = 24)]
+        height: u16,
+
+        /// Print the styles of the chars below each line
+        #[arg(long = "styles")]
+        styles: bool,
+    },
+    /// Print the values matching a path like "orders[].total", one per line
+    Query {
+        /// Input file: data.pb{;format.proto{;message_name}}
+        file: String,
+
+        /// Path with wildcards: name[] or name[*] for all values, * for any field
+        query: String,
+
+        /// Print a JSON array of the values
+        #[arg(long = "json")]
+        json: bool,
+    },
+}
+
+// data.proto for data.pb
+fn default_proto_file(binary_file: &str) -> String {
+    binary_file.trim_end_matches(".pb").to_string() + ".proto"
+}
+
+// read proto definitions and the data, the file argument is data.pb{;format.proto{;message_name}},
+// "-" instead of data.pb reads stdin, its content is returned too; if the root message is not clear,
+// the one chosen for the files before is taken, otherwise the candidates ranked by how the data fits them
+// are offered to choose one (interactive, the choice is returned) or listed in the error
+fn load_data(file_arg: &str, proto_path: Vec<PathBuf>, interactive: bool) -> io::Result<(ProtoData, MessageData, String, Option<Vec<u8>>, Option<String>)> {
+    let mut it = file_arg.split(";");
+    let binary_file = it.next().unwrap().to_string();
+    let mut proto_file = String::new();
+    let mut root_message_name = String::new();
+    if let Some(path) = it.next() {
+        proto_file = path.to_string();
+        if let Some(path) = it.next() {
+            root_message_name = path.to_string();
+        }
+        assert!(it.next().is_none());
+    }
+
+    let from_stdin = binary_file == "-";
+    if from_stdin && proto_file.is_empty() {
+        exit_with_error("the proto definitions file is required for the data from stdin: -;format.proto", 102);
+    }
+
+    // if no proto file provided, use the file with the same name as data file but with proto extension
+    if proto_file.is_empty() {
+        proto_file = default_proto_file(&binary_file);
+    }
+
+    if !from_stdin && !std::fs::exists(&binary_file)? {
+        exit_with_error(format!("file \"{}\" is not available", binary_file), 101);
+    }
+    if !std::fs::exists(&proto_file)? {
+        exit_with_error(format!("proto definitions file \"{}\" is not available", proto_file), 102);
+    }
+
+    for dir in &proto_path {
+        if !dir.is_absolute() {
+            eprintln!("The proto_path argument should contain an absolute path.");
+            break;
+        }
+        if !dir.is_dir() {
+            eprintln!("The proto_path is not a directory: {}", dir.display());
+        }
+    }
+
+    let mut proto_files = ProtoFile::new_with_imports(proto_file.clone().into(), proto_path).unwrap_or_else(|e| exit_with_error(e, 102));
+
+    let mut proto = proto_files.remove(0).parse()?;
+
+    let mut root_msg = None;
+    if root_message_name.is_empty() {
+        root_msg = proto.auto_detect_root_message(); // search only in the main proto file
+    }
+
+    // merge imported proto files
+    for file in proto_files.into_iter() {
+        proto.append(file.parse()?);
+    }
+    proto = proto.finalize().unwrap_or_else(|e| exit_with_error(e, 102));
+
+    let mut stdin_data = None;
+    if from_stdin {
+        let mut bytes = vec![];
+        io::stdin().read_to_end(&mut bytes)?;
+        if let Some(compression) = Compression::detect(&bytes) { bytes = compression.decompress(&bytes)?; }
+        stdin_data = Some(bytes);
+    }
+
+    let mut chosen_root = None;
+    let remembered = match interactive && !from_stdin {
+        true => {
+            let recent = picker::recent_path(&|name| std::env::var(name).ok()).map(|path| picker::read_recent(&path)).unwrap_or_default();
+            picker::remembered_root(&recent, &picker::absolute_arg(&format!("{};{}", binary_file, proto_file), &std::env::current_dir()?))
+        }
+        false => None,
+    };
+    if let Some(def) = remembered.filter(|_| root_msg.is_none() && root_message_name.is_empty()).and_then(|name| proto.get_message_definition(&name)) {
+        root_msg = Some(def);
+    }
+    if root_msg.is_none() && root_message_name.is_empty() {
+        if !from_stdin && Format::of_path(binary_file.as_ref()) != Format::Binary {
+            exit_with_error("cannot choose the root message for a json or text file; please provide it: data.json;format.proto;Message", 103);
+        }
+        let bytes = match &stdin_data {
+            Some(bytes) => bytes.clone(),
+            None => compress::read(binary_file.as_ref())?,
+        };
+        let fits = fingerprint::rank_roots(&bytes, &proto);
+        let lines: Vec<String> = fits.iter().map(fingerprint::Fit::text).collect();
+        if !interactive || fits.is_empty() {
+            let best = if lines.is_empty() { String::new() } else { format!(", the data fits best: {}", lines[..lines.len().min(3)].join("; ")) };
+            exit_with_error(format!("cannot choose the root message in the proto definition file; please provide it manually{}", best), 103);
+        }
+        match picker::choose("choose the root message, the best fitting first, Enter - open, Esc - quit", &lines)? {
+            Some(index) => {
+                root_msg = Some(fits[index].def.clone());
+                chosen_root = Some(fits[index].def.name.clone());
+            }
+            None => exit(0),
+        }
+    }
+
+    if root_msg.is_none() {
+        root_msg = proto.get_message_definition(&root_message_name);
+        if root_msg.is_none() {
+            exit_with_error(format!("root message \"{}\" not found", root_message_name), 104);
+        }
+    }
+
+    if let Some(bytes) = stdin_data {
+        let data = read_data_bytes(&bytes, &proto, root_msg.unwrap())?;
+        return Ok((proto, data, binary_file, Some(bytes), chosen_root));
+    }
+    let data = read_data_file(binary_file.as_ref(), &proto, root_msg.unwrap())?;
+    Ok((proto, data, binary_file, None, chosen_root))
+}
+
+// an empty document of the root message of the proto file, the argument is format.proto{;message_name};
+// the root is chosen if it is not clear; it is returned as the data from stdin, saving asks for the file
+fn new_document(proto_arg: &str, proto_path: &[PathBuf]) -> io::Result<(ProtoData, MessageData, String, Option<Vec<u8>>, Option<String>)> {
+    let (proto_file, root_message_name) = proto_arg.split_once(';').unwrap_or((proto_arg, ""));
+    if !std::fs::exists(proto_file)? {
+        exit_with_error(format!("proto definitions file \"{}\" is not available", proto_file), 102);
+    }
+    let proto = load_proto(proto_file.as_ref(), proto_path).unwrap_or_else(|e| exit_with_error(e, 102));
+    let root_msg = match root_message_name {
+        "" => match proto.auto_detect_root_message() {
+            Some(root_msg) => root_msg,
+            None => {
+                let mut candidates = proto.root_candidates();
+                if candidates.is_empty() { candidates = proto.messages().iter().filter(|m| !m.name.contains(',')).cloned().collect(); }
+                let names: Vec<String> = candidates.iter().map(|m| format!("{} ({} field{})", m.name, m.fields.len(), if m.fields.len() == 1 { "" } else { "s" })).collect();
+                if !io::stdout().is_terminal() || names.is_empty() {
+                    exit_with_error("cannot choose the root message in the proto definition file; please provide it manually", 103);
+                }
+                match picker::choose("choose the root message of the new document, Enter - open, Esc - quit", &names)? {
+                    Some(index) => candidates[index].clone(),
+                    None => exit(0),
+                }
+            }
+        },
+        name => proto.get_message_definition(name).unwrap_or_else(|| exit_with_error(format!("root message \"{}\" not found", name), 104)),
+    };
+    let data = read_data_bytes(&[], &proto, root_msg)?;
+    Ok((proto, data, "-".into(), Some(vec![]), None))
+}
+
+// the proto file with its imports
+fn load_proto(path: &std::path::Path, proto_path: &[PathBuf]) -> io::Result<ProtoData> {
+    let mut proto_files = ProtoFile::new_with_imports(path.into(), proto_path.to_vec())?;
+    let mut proto = proto_files.remove(0).parse()?;
+    for file in proto_files.into_iter() {
+        proto.append(file.parse()?);
+    }
+    proto.finalize()
+}
+
+fn read_data_file(path: &std::path::Path, proto: &ProtoData, root_msg: MessageProtoPtr) -> io::Result<MessageData> {
+    let format = Format::of_path(path);
+    if format != Format::Binary || Compression::of_file(path).is_some() {
+        return textformat::decode(&compress::read(path)?, proto, root_msg, format);
+    }
+    let file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    if len >= MAPPED_SIZE {
+        return read_mapped_file(path, proto, root_msg);
+    }
+    let mut limit = textformat::read_limit(len)?;
+    let mut reader = PbReader::new(file);
+    MessageData::new(&mut reader, proto, root_msg, &mut limit)
+}
+
+// the bytes values are not copied, they are read from the file when shown or saved
+fn read_mapped_file(path: &std::path::Path, proto: &ProtoData, root_msg: MessageProtoPtr) -> io::Result<MessageData> {
+    let source = Source::map(path)?;
+    let mut limit = textformat::read_limit(source.len() as u64)?;
+    MessageData::new(&mut SourceReader::new(source), proto, root_msg, &mut limit)
+}
+
+// a mapped file must not be truncated while its values are in use, it is replaced by a new one
+// a .json or .textproto file is written in its format, a compressed one is compressed again the same way
+fn write_data_file(path: &std::path::Path, data: &MessageData, proto: &ProtoData, original_json_names: bool) -> io::Result<()> {
+    let format = Format::of_path(path);
+    if let Some(compression) = Compression::of_file(path) {
+        let bytes = compression.compress(&textformat::encode(data, proto, format, original_json_names)?)?;
+        return replace_file(path, |writer| writer.write_all(&bytes));
+    }
+    if format != Format::Binary {
+        return std::fs::write(path, textformat::encode(data, proto, format, original_json_names)?);
+    }
+    if std::fs::metadata(path).is_ok_and(|metadata| metadata.len() >= MAPPED_SIZE) {
+        return replace_file(path, |writer| data.write(writer, proto, data.def.clone()));
+    }
+    let mut writer = io::BufWriter::new(std::fs::File::create(path)?);
+    data.write(&mut writer, proto, data.def.clone())?;
+    writer.flush()
+}
+
+// written next to the file and renamed over it, the old content stays readable by the mappings of it
+fn replace_file(path: &std::path::Path, write: impl FnOnce(&mut io::BufWriter<std::fs::File>) -> io::Result<()>) -> io::Result<()> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let temp = path.with_file_name(name);
+    let result = std::fs::File::create(&temp).and_then(|file| {
+        if let Ok(metadata) = std::fs::metadata(path) { file.set_permissions(metadata.permissions())?; }
+        let mut writer = io::BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()
+    }).and_then(|_| std::fs::rename(&temp, path));
+    if result.is_err() { let _ = std::fs::remove_file(&temp); }
+    result
+}
+
+// the file given is required, the default one is read if it exists; the path is returned to save the columns
+fn load_config(file: Option<&std::path::Path>) -> (LayoutConfig, Option<PathBuf>) {
+    let mut config = LayoutConfig::default();
+    let path = match file {
+        Some(path) => path.to_path_buf(),
+        None => match config::default_path(&|name| std::env::var(name).ok()) {
+            Some(path) if path.exists() => path,
+            path => return (config, path),
+        },
+    };
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path.display(), e), 107));
+    config::apply(&text, &mut config).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path.display(), e), 107));
+    (config, Some(path))
+}
+
+// the json_names setting for the commands without the editor: the settings file is read only if it is given, as for the screen
+fn original_json_names(config: Option<&std::path::Path>) -> bool {
+    config.is_some_and(|path| load_config(Some(path)).0.original_json_names)
+}
+
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn read_data_bytes(bytes: &[u8], proto: &ProtoData, root_msg: MessageProtoPtr) -> io::Result<MessageData> {
+    let mut limit = textformat::read_limit(bytes.len() as u64)?;
+    MessageData::new(&mut PbReader::new(bytes), proto, root_msg, &mut limit)
+}
+
+// the data written with its schema and read with the new one
+fn rebind(data: &MessageData, proto: &ProtoData, new_proto: &ProtoData, root_msg: MessageProtoPtr) -> io::Result<MessageData> {
+    let mut bytes = vec![];
+    data.write(&mut bytes, proto, data.def.clone())?;
+    read_data_bytes(&bytes, new_proto, root_msg)
+}
+
+// values of the same repeated field of the same message
+fn same_group(a: &FieldPath, b: &FieldPath) -> bool {
+    let len = a.0.len();
+    len > 0 && len == b.0.len() && a.0[..len - 1] == b.0[..len - 1] && a.0[len - 1].id == b.0[len - 1].id
+}
+
+// fields of types not defined in the proto file and its imports
+fn unresolved_warning(proto: &ProtoData) -> Option<String> {
+    if proto.unresolved.is_empty() { return None; }
+    Some(format!("unknown types, the fields are read as unknown: {}", proto.unresolved.join(", ")))
+}
+
+fn run_assert(file_arg: &str, proto_path: Vec<PathBuf>, expressions: &Vec<String>) -> io::Result<()> {
+    let (proto, data, _, _, _) = load_data(file_arg, proto_path, false)?;
+    if let Some(warning) = unresolved_warning(&proto) { eprintln!("{}", warning); }
+    let mut failed = 0;
+    for text in expressions {
+        match expr::Expr::parse(text, &data.def, &proto).and_then(|e| e.check(&data)) {
+            Ok(true) => {}
+            Ok(false) => {
+                eprintln!("assertion failed: {}", text.trim());
+                failed += 1;
+            }
+            Err(e) => exit_with_error(format!("{}: {}", text.trim(), e), 106),
+        }
+    }
+    if failed > 0 { exit(1); }
+    Ok(())
+}
+
+// the fields written differently are printed with the offset of the first byte that differs
+fn run_verify(file_arg: &str, proto_path: Vec<PathBuf>) -> io::Result<()> {
+    let (proto, data, binary_file, stdin_data, _) = load_data(file_arg, proto_path, false)?;
+    if let Some(warning) = unresolved_warning(&proto) { eprintln!("{}", warning); }
+    let format = if stdin_data.is_some() { Format::Binary } else { Format::of_path(binary_file.as_ref()) };
+    let original = match stdin_data {
+        Some(bytes) => bytes,
+        None => compress::read(binary_file.as_ref())?,
+    };
+    let written = textformat::encode(&data, &proto, format, false)?;
+    let Some(offset) = audit::first_difference(&original, &written) else {
+        println!("{} bytes are written back the same", original.len());
+        return Ok(());
+    };
+    if format == Format::Binary {
+        for line in audit::round_trip(&data, &original, &proto) {
+            println!("{}", line);
+        }
+    }
+    println!("the first difference at offset {:#x} ({}), {} bytes in the file, {} bytes written", offset, offset, original.len(), written.len());
+    exit(1);
+}
+
+fn run_generate(proto_arg: &str, proto_path: &[PathBuf], out: &std::path::Path, count: usize, seed: Option<u64>) -> io::Result<()> {
+    let (proto, data, _, _, _) = new_document(proto_arg, proto_path)?;
+    let seed = seed.unwrap_or_else(|| SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64));
+    let data = generate::document(&proto, data.def, count, &mut generate::Random::new(seed));
+    write_data_file(out, &data, &proto, false).unwrap_or_else(|e| exit_with_error(format!("{}: {}", out.display(), e), 101));
+    // the seed repeats the document
+    println!("{} bytes written to {}, seed {}", std::fs::metadata(out)?.len(), out.display(), seed);
+    Ok(())
+}
+
+fn run_query(file_arg: &str, proto_path: Vec<PathBuf>, text: &str, json: bool, original_json_names: bool) -> io::Result<()> {
+    let (proto, data, _, _, _) = load_data(file_arg, proto_path, false)?;
+    if let Some(warning) = unresolved_warning(&proto) { eprintln!("{}", warning); }
+    let query = query::Query::parse(text, &data.def, &proto).unwrap_or_else(|e| exit_with_error(format!("{}: {}", text.trim(), e), 108));
+    let found = query.run(&data);
+    let mut out = io::stdout().lock();
+    if json {
+        let values: Vec<String> = found.iter().map(|(_, field)| query::value_json(field, original_json_names)).collect();
+        writeln!(out, "[{}]", values.join(","))?;
+    } else {
+        for (_, field) in found {
+            writeln!(out, "{}", query::value_text(field, original_json_names))?;
+        }
+    }
+    Ok(())
+}
+
+// the fields of unknown numbers have no names in the text format, their count is written to err (stderr)
+fn run_dump(data: &MessageData, proto: &ProtoData, out: &mut dyn Write, err: &mut dyn Write) -> io::Result<()> {
+    if let Some(warning) = unresolved_warning(proto) { writeln!(err, "{}", warning)?; }
+    out.write_all(&textformat::encode(data, proto, Format::Text, false)?)?;
+    match textformat::unknown_count(data) {
+        0 => {}
+        count => writeln!(err, "{} unknown field{} left out", count, if count == 1 { "" } else { "s" })?,
+    }
+    Ok(())
+}
+
+// the settings file is read only if it is given, the screen does not depend on the user settings
+fn run_screen(args: &Args) -> io::Result<()> {
+    let Some(Command::Screen { file, press, script, width, height, styles }) = &args.command else { return Ok(()); };
+    let text = match script {
+        Some(path) => std::fs::read_to_string(path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path.display(), e), 109)),
+        None => press.clone(),
+    };
+    let keys = harness::parse_keys(&text).unwrap_or_else(|e| exit_with_error(e, 109));
+    let (proto, data, binary_file, _, _) = load_data(file, args.proto_path.clone(), false)?;
+    let layout_config = args.config.as_deref().map_or(LayoutConfig::default(), |path| load_config(Some(path)).0);
+    let keymap = KeyMap::new(args.keys.unwrap_or(KeyProfile::Fkeys));
+    let mut app = App::headless(data, proto, binary_file.into(), keymap, layout_config, (*width).max(20), (*height).max(2));
+    app.read_only = args.read_only;
+    app.after_event()?;
+    app.run_keys(&keys)?;
+    let mut out = io::stdout().lock();
+    for line in app.snapshot(*styles) {
+        // the style lines keep the style of the spaces
+        writeln!(out, "{}", if *styles { &line } else { line.trim_end() })?;
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+
+    if let Some(Command::Assert { file, expr }) = &args.command {
+        return run_assert(file, args.proto_path, expr);
+    }
+    if let Some(Command::Verify { file }) = &args.command {
+        return run_verify(file, args.proto_path);
+    }
+    if let Some(Command::Query { file, query, json }) = &args.command {
+        let original_json_names = original_json_names(args.config.as_deref());
+        return run_query(file, args.proto_path, query, *json, original_json_names);
+    }
+    if let Some(Command::Screen { .. }) = &args.command {
+        return run_screen(&args);
+    }
+    if let Some(Command::Generate { proto, out, count, seed }) = &args.command {
+        return run_generate(proto, &args.proto_path, out, *count, *seed);
+    }
+
+    let recent_path = picker::recent_path(&|name| std::env::var(name).ok());
+    let mut output = args.output.clone();
+    let (file_arg, (proto, mut data, binary_file, stdin_data, chosen_root)) = match &args.command {
+        Some(Command::New { proto, output: new_output }) => {
+            output = new_output.clone();
+            (proto.clone(), new_document(proto, &args.proto_path)?)
+        }
+        _ => {
+            let file_arg = match &args.file {
+                Some(file) => file.clone(),
+                None if !io::stdout().is_terminal() => exit_with_error("a data file expected, stdout is not a terminal", 101),
+                None => {
+                    let recent = recent_path.as_deref().map(picker::read_recent).unwrap_or_default();
+                    match picker::Picker::new(std::env::current_dir()?, recent).run()? {
+                        Some(file) => file,
+                        None => return Ok(()),
+                    }
+                }
+            };
+            if args.apply.is_none() && io::stdout().is_terminal() { println!("loading..."); }
+            let loaded = load_data(&file_arg, args.proto_path.clone(), args.apply.is_none() && io::stdout().is_terminal())?;
+            (file_arg, loaded)
+        }
+    };
+
+    if let Some(edits_file) = args.apply {
+        if let Some(warning) = unresolved_warning(&proto) { eprintln!("{}", warning); }
+        let mut script = String::new();
+        if edits_file.as_os_str() == "-" && stdin_data.is_some() {
+            exit_with_error("stdin is used for the data, the edits should be in a file", 105);
+        }
+        let original = match &stdin_data {
+            Some(bytes) => bytes.clone(),
+            None => compress::read(binary_file.as_ref())?,
+        };
+        let lossy = match Format::of_path(binary_file.as_ref()) {
+            Format::Binary => audit::round_trip(&data, &original, &proto),
+            _ if stdin_data.is_some() => audit::round_trip(&data, &original, &proto),
+            _ => vec![],
+        };
+        if !lossy.is_empty() && !args.force {
+            for line in &lossy { eprintln!("{}", line); }
+            exit_with_error(format!("{} fields would not be saved as read, --force saves anyway", lossy.len()), 105);
+        }
+        if edits_file.as_os_str() == "-" {
+            io::stdin().read_to_string(&mut script)?;
+        } else {
+            script = std::fs::read_to_string(&edits_file)?;
+        }
+        match batch::apply_script(&mut data, &proto, &script) {
+            Ok(count) => {
+                // the data from stdin goes to stdout if no output file is given
+                let output = output.unwrap_or(PathBuf::from(&binary_file));
+                if output.as_os_str() == "-" {
+                    let mut writer = io::BufWriter::new(io::stdout());
+                    data.write(&mut writer, &proto, data.def.clone())?;
+                    writer.flush()?;
+                    eprintln!("{} changes applied", count);
+                } else {
+                    write_data_file(&output, &data, &proto, original_json_names(args.config.as_deref()))?;
+                    println!("{} changes applied", count);
+                }
+            }
+            Err(e) => exit_with_error(e, 105),
+        }
+        return Ok(());
+    }
+
+    // the output piped to another program and not asked for the saved data: no editor, the document printed as text
+    if !io::stdout().is_terminal() && output.as_ref().is_none_or(|path| path.as_os_str() != "-") {
+        return run_dump(&data, &proto, &mut io::stdout().lock(), &mut io::stderr());
+    }
+
+    let proto_file = match &args.command {
+        Some(Command::New { .. }) => file_arg.split(';').next().unwrap_or_default().to_string(),
+        _ => file_arg.split(';').nth(1).map_or(default_proto_file(&binary_file), String::from),
+    };
+    // the file is offered when the editor is started without arguments next time, with the root message chosen
+    if let Some(path) = recent_path.filter(|_| stdin_data.is_none()) {
+        let file_arg = match &chosen_root {
+            Some(root) => format!("{};{};{}", binary_file, proto_file, root),
+            None => file_arg,
+        };
+        let _ = picker::add_recent(&path, &picker::absolute_arg(&file_arg, &std::env::current_dir()?));
+    }
+    let other = args.merge.as_ref().map(|path| read_data_file(path, &proto, data.def.clone()).
+        unwrap_or_else(|e| exit_with_error(format!("{}: {}", path.display(), e), 101)));
+    let (layout_config, config_path) = load_config(args.config.as_deref());
+    let profile = args.keys.unwrap_or(KeyProfile::Fkeys);
+    // the terminal is restored before the error is printed, the message is not lost in the alternate screen
+    crash::install(restore_terminal);
+    let mut app = App::new(data, proto, binary_file.into(), KeyMap::new(profile), layout_config, !args.no_alt_screen).unwrap_or_else(|e| {
+        restore_terminal();
+        exit_with_error(format!("no terminal for the editor: {}", e), 101)
+    });
+    app.config_path = config_path;
+    app.formats_path = formats::default_path(&|name| std::env::var(name).ok());
+    if let Some(compression) = Compression::of_file(&app.file_path).filter(|_| stdin_data.is_none()) {
+        app.layouts.file_name = format!("{} ({})", app.layouts.file_name, compression.name());
+    }
+    if args.keys.is_none() && keymap::suggest_profile(&|name| std::env::var(name).ok()) == Some(KeyProfile::Letters) {
+        app.message = Some("F-keys may be intercepted by this terminal, try --keys letters".into());
+        app.need_update = true;
+        app.after_event()?;
+    }
+    if let Some(warning) = unresolved_warning(&app.proto) {
+        app.message = Some(warning);
+        app.need_update = true;
+        app.after_event()?;
+    }
+    app.read_only = args.read_only;
+    if !args.render.is_empty() { app.set_renderers(Renderers(args.render)); }
+    if args.show_encoding {
+        app.layout_config.show_encoding = true;
+        app.config_changed();
+    }
+    app.stdin_data = stdin_data;
+    app.output = output;
+    app.proto_path = args.proto_path;
+    app.proto_file = proto_file.into();
+    app.load_formats();
+    if let Some(path) = &args.goto {
+        app.go_to(path)?;
+    } else if let Some(text) = &args.find {
+        app.find(text)?;
+    }
+    if let (Some(path), Some(other)) = (&args.merge, other) {
+        app.merge = Some(merge::Merge::new(path.display().to_string(), other));
+        app.show_merge(0)?;
+    }
+    if !app.read_only { app.offer_recovery()?; }
+    app.run()?;
+    // closed normally, the changes of this session are not needed for the recovery
+    if !app.journal.commands.is_empty() { app.journal.clear()?; }
+
+    // after the terminal is restored
+    let stdout_data = app.stdout_data.take();
+    drop(app);
+    if let Some(bytes) = stdout_data {
+        io::stdout().write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+/**************************************************************************************************/
+
+#[cfg(test)]
+mod app_tests {
+    use std::path::Iter;
+    use super::*;
+    use crate::App;
+    use crate::proto::ProtoData;
+    use crate::wire::FieldValue::MESSAGE;
+    use crate::wire::ScalarValue::{BYTES, ENUM, F64, STR};
+    use crate::view::FloatFormat;
+
+    fn make_minimal_test_data() -> MessageData {
+        let binary_input = [];
+        let proto = ProtoData::new("message M { int32 f1 = 1; }").unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap()
+    }
+
+    const TEST_PROTO_1: &str = r#"
+message M { int32 f1 = 1; repeated int32 f2 = 2; M3 m3 = 3; int32 f4 = 4; }
+message M3 { int32 f5 = 5; repeated M6 m6 = 6; int32 f7 = 7; }
+message M6 { int32 f8 = 8; int32 f9 = 9; }
+"#;
+
+    fn make_test_data_1() -> MessageData {
+        let proto_str = TEST_PROTO_1;
+
+        let binary_input = [
+            0x08, 1,  // f1: 1 int32
+            0x10, 20,  // f2: 20 int32     repeated scalar
+            0x10, 21,  // f2: 21 int32
+            //0x20,  4,  // f4: 4 int32    optional scalar
+            0x1A, 16,  // m3: M3           nested message
+            0x28, 5,  //   f5: 5 int32
+
+            0x32, 4,  //   m6: M6          repeated message
+            0x40, 8,  //     f8: 8 int32
+            0x48, 9,  //     f9: 9 int32
+
+            0x32, 4,  //   m6: M6
+            0x40, 10,  //     f8: 10 int32
+            0x48, 11,  //     f9: 11 int32
+
+            0x38, 7,  //   f7: 7 int32
+        ];
+
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+
+        //        let mut f = std::fs::File::create("test_data_1.pb").unwrap();
+        //        f.write_all(binary_input.as_slice()).unwrap();
+
+        MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap()
+    }
+
+    fn make_no_field_data(proto: &str) -> MessageData {
+        let binary_input = [];
+        let proto = ProtoData::new(proto).unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+
+        data
+    }
+
+    fn make_one_field_data(proto: &str, value: ScalarValue) -> MessageData {
+        let mut data = make_no_field_data(proto);
+        let mut field = data.add_field(&[(1, 0).into()]).unwrap();
+        field.value = FieldValue::SCALAR(value);
+
+        data
+    }
+
+    fn make_repeated_message_data(messages_count: usize) -> MessageData {
+        let proto_str = r#"
+message M { repeated M2 m1 = 1; }
+message M2 { int32 i2 = 2; int32 i3 = 3; }
+"#;
+
+        let binary_input = [
+            //0x0A, 12, // m1: M2           nested message
+
+            //0x0A, 4,  //   m2: M2          repeated message
+            //0x10, 2,  //     i2: 2 int32
+            //0x18, 3,  //     i3: 3 int32
+            ////
+            //0x0A, 4,  //   m2: M2          repeated message
+            //0x10, 4,  //     i2: 4 int32
+            //0x18, 5,  //     i3: 5 int32
+            //
+            //0x0A, 4,  //   m2: M2          repeated message
+            //0x10, 6,  //     i2: 6 int32
+            //0x18, 7,  //     i3: 7 int32
+        ];
+
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+
+        let mut int_value = 2;
+        for index in 0..messages_count {
+            let mut field = data.add_field(&[(1, index).into()]).unwrap();
+            if let MESSAGE(msg) = &mut field.value {
+                let msg = Arc::make_mut(msg);
+                let mut i2 = msg.add_field(&[(2, 0).into()]).unwrap();
+                i2.value = SCALAR(I32(int_value));
+                int_value += 1;
+                let mut i3 = msg.add_field(&[(3, 0).into()]).unwrap();
+                i3.value = SCALAR(I32(int_value));
+                int_value += 1;
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn match_testing_requirements() {
+        // these settings values required for correct test data formating
+        assert_eq!(MARGIN_LEFT, 1);
+        assert_eq!(MARGIN_RIGHT, 1);
+    }
+
+    #[test]
+    fn simple() {
+        let mut data = make_one_field_data("message M { int32 i1=1; }", I32(1));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" i1: 1                  int32 "]);
+    }
+
+    #[test]
+    fn app_test_1() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        let expected = [
+            " f1: 1                                      int32 ",
+            " f2: 20 21                                 int32* ",
+            " m3:                                           M3 ",
+            "   f5: 5                                    int32 ",
+            "   m6:                                        M6* ",
+            "     f8: 8                                  int32 ",
+            "     f9: 9                                  int32 ",
+            "   m6:                                        M6* ",
+            "     f8: 10                                 int32 ",
+            "     f9: 11                                 int32 ",
+            "   f7: 7                                    int32 ",
+            " f4: 0                                     -int32 "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+
+    #[test]
+    fn config_indents_and_margins() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 40, 25).unwrap();
+        app.layout_config.level_indent = 1;
+        app.layout_config.margin_left = 0;
+        app.layout_config.margin_right = 0;
+        app.layouts = Layouts::new(&app.data, &app.layout_config, "test_data.pb".into(), 40, 24);
+        let expected = [
+            "f1: 1                              int32",
+            "f2: 20 21                         int32*",
+            "m3:                                   M3",
+            " f5: 5                             int32",
+            " m6:                                 M6*",
+            "  f8: 8                            int32",
+            "  f9: 9                            int32",
+            " m6:                                 M6*",
+            "  f8: 10                           int32",
+            "  f9: 11                           int32",
+            " f7: 7                             int32",
+            "f4: 0                             -int32"];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+
+    #[test]
+    fn scroll_limits() {
+        let expected_start = [
+            " f1: 1                                      int32 ",
+            " f2: 20 21                                 int32* "];
+        let expected_end = [
+            "   f7: 7                                    int32 ",
+            " f4: 0                                     -int32 "];
+
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 2 + TOP_LINE).unwrap();
+        assert_eq!(app.to_strings(), expected_start);
+
+        for _ in 0..100 {
+            app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+            app.after_event().unwrap();
+        }
+        assert_eq!(app.to_strings(), expected_end);
+
+        for _ in 0..100 {
+            app.run_command(UserCommand::ScrollVertically(-1)).unwrap();
+            app.after_event().unwrap();
+        }
+        assert_eq!(app.to_strings(), expected_start);
+
+        for _ in 0..100 {
+            app.run_command(UserCommand::ScrollSibling(1)).unwrap();
+            app.after_event().unwrap();
+        }
+        assert_eq!(app.to_strings(), expected_end);
+
+        for _ in 0..100 {
+            app.run_command(UserCommand::ScrollSibling(-1)).unwrap();
+            app.after_event().unwrap();
+        }
+        assert_eq!(app.to_strings(), expected_start);
+
+        app.run_command(UserCommand::ScrollVertically(100)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected_end);
+
+        app.run_command(UserCommand::ScrollVertically(-100)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected_start);
+    }
+
+    #[test]
+    fn empty_repeated_message() {
+        let mut data = make_repeated_message_data(0);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" m1:                     -M2* "]);
+    }
+
+    #[test]
+    fn insert_repeated_message() {
+        let mut data = make_repeated_message_data(0);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" m1:                     -M2* "]);
+
+        app.run_command(UserCommand::InsertData).unwrap();
+        app.after_event().unwrap();
+        let expected = [
+            " m1:                      M2* ", // created a message with empty fields
+            "   i2: 0               -int32 ",
+            "   i3: 0               -int32 "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    #[test]
+    fn insert_scaffolded_message() {
+        let proto = ProtoData::new("message M { repeated M2 m1 = 1; }\nmessage M2 { int32 i2 = 2; M3 m3 = 3; }\nmessage M3 { int32 i4 = 4; }").unwrap().finalize().unwrap();
+        let data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.auto_detect_root_message().unwrap(), &mut 0).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.layout_config.scaffold = true;
+        app.to_strings();
+
+        app.run_command(UserCommand::InsertData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " m1:                      M2* ",
+            "   i2: 0               -int32 ",
+            "   m3:                     M3 ",
+            "     i4: 0             -int32 "]);
+        assert_eq!(app.journal.commands, ["insert m1[0]", "insert m1[0].m3"]);
+    }
+
+    #[test]
+    fn declared_default() {
+        let proto = ProtoData::new("message M { optional int32 n = 1 [default = 7]; }").unwrap().finalize().unwrap();
+        let data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.auto_detect_root_message().unwrap(), &mut 0).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 40, 25).unwrap();
+        assert_eq!(app.to_strings(), [" n: 7                            -int32 "]);
+        assert!(app.get_top_line(40, &LayoutConfig::default()).contains("#1 [default = 7]"));
+
+        app.run_command(UserCommand::InsertData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.data.to_string(), "message M {\n  n = 7\n}\n");
+    }
+
+    #[test]
+    fn reserved_and_deprecated_fields() {
+        let proto = ProtoData::new("message M { int32 old = 1 [deprecated = true]; repeated int32 n = 2; reserved 2; }").unwrap().finalize().unwrap();
+        let data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.auto_detect_root_message().unwrap(), &mut 0).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 40, 25).unwrap();
+        app.to_strings();
+        app.run_command(UserCommand::InsertData).unwrap();
+        assert_eq!(app.message.as_deref(), Some("old is deprecated"));
+        assert_eq!(app.data.to_string(), "message M {\n  old = 0\n}\n");
+
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        app.run_command(UserCommand::InsertData).unwrap();
+        assert_eq!(app.message.as_deref(), Some("field number 2 is reserved in M, press Y to insert it anyway"));
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("canceled"));
+        app.run_command(UserCommand::InsertData).unwrap();
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.data.to_string(), "message M {\n  old = 0\n  n = 0\n}\n");
+    }
+
+    #[test]
+    fn delete_message_field() {
+        let mut data = make_repeated_message_data(1);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.to_strings();
+
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        let expected = [
+            " m1:                      M2* ",
+            "   i2: 0               -int32 ", // deleted
+            "   i3: 3                int32 "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    #[test]
+    fn delete_message() {
+        let mut data = make_repeated_message_data(2);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+
+        app.to_strings();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        let expected = [
+            " m1:                      M2* ", // only one message remains
+            "   i2: 4                int32 ",
+            "   i3: 5                int32 "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    #[test]
+    fn collapse_empty_message() {
+        let mut data = make_repeated_message_data(0);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+
+        app.to_strings();
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        //        app.after_event().unwrap();
+        //        assert_eq!(app.to_strings(), [" m1: ... 0               -M2* "]);
+        //
+        //        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [" m1:                     -M2* "]);
+    }
+
+
+    // single line string displayed within apostrophes to show trailing spaces
+    #[test]
+    fn single_line_string() {
+        let data = make_one_field_data("message M { string f1=1; }", STR("abc".into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        let expected = [" f1: 'abc'                                 string "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    // multiline string displayed without apostrophe
+    #[test]
+    fn multiline_string() {
+        let data = make_one_field_data(
+            "message M { string f1=1; }",
+            STR("abc\ndef".into()));
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        let expected = [
+            " f1: abc                                   string ",
+            "  2: def                                          "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    #[test]
+    fn long_strings() {
+        {
+            let data = make_one_field_data(
+                "message M { string s1=1; }",
+                STR("abcdefghijklmnopqrstuvwxyz".into()));
+
+            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+            let expected = [
+                " s1: abcdefghijklmnopq string ",
+                "   : rstuvwxyz                "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let data = make_one_field_data(
+                "message M { string s1=1; }",
+                STR("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ".into()));
+
+            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+            let expected = [
+                " s1: abcdefghijklmnopq string ",
+                "   : rstuvwxyzABCDEFGHIJKLMNO ",
+                "   : PQRSTUVWXYZ              "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let data = make_one_field_data(
+                "message M { string s1=1; }",
+                STR("abcdefghijklmnopqrstuvwxyz\nABCDEFGHIJKLMNOPQRSTUVWXYZ".into()));
+
+            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+            let expected = [
+                " s1: abcdefghijklmnopq string ",
+                "   : rstuvwxyz                ",
+                "  2: ABCDEFGHIJKLMNOPQRSTUVWX ",
+                "   : YZ                       "];
+            assert_eq!(app.to_strings(), expected);
+            //    data.add_field(&[(2, 0).into(), (6, 0).into()]).unwrap().value = FieldValue::SCALAR(STR("Leonardo's Life and Times\nLeonardo was, first of all, a painter and an artist.\nBut he was also a great thinker.".into()));
+        }
+    }
+
+    #[test]
+    fn string_cursor() {
+        let data = make_one_field_data("message M { string s1=1; }", STR("ab中defghijklmnopqrstuvwxyz".into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 4).unwrap();
+        // the cursor moves by chars, a wide char is selected with its tail
+        app.run_keys(&harness::parse_keys("Right Right Right").unwrap()).unwrap();
+        assert_eq!(app.snapshot(true)[2..4], [" s1: ab中defghijklmnop string ", "nnn||vvVVvvvvvvvvvvvvv|ttttttt"]);
+        assert!(app.get_top_line(100, &app.layout_config).contains(" 2/26 "));
+        app.run_keys(&harness::parse_keys("Down End").unwrap()).unwrap();
+        assert_eq!(app.snapshot(true)[5], "iii||vvvvvvvvvV|||||||||||||||");
+        assert!(app.get_top_line(100, &app.layout_config).contains(" 25/26 "));
+        // the column is kept on a longer line
+        app.run_keys(&harness::parse_keys("Up Left").unwrap()).unwrap();
+        assert_eq!(app.snapshot(true)[3], "nnn||vvvvvvvvvVvvvvvvv|ttttttt");
+        app.run_keys(&harness::parse_keys("Home Home").unwrap()).unwrap();
+        assert_eq!(app.selected.x, 0);
+    }
+
+    #[test]
+    fn scroll_multiline_string() {
+        let data = make_one_field_data(
+            "message M { string f1=1; }",
+            STR("11\n22\n33\n44\n55\n66\n77\n88\n99".into()));
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 2 + TOP_LINE).unwrap();
+        let expected0 = [
+            " f1: 11      string ",
+            "  2: 22             "];
+        let expected1 = [
+            "  2: 22             ",
+            "  3: 33             "];
+        assert_eq!(app.to_strings(), expected0);
+
+        app.run_command(ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected0);
+
+        app.run_command(ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected1);
+
+        app.run_command(ScrollVertically(-1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected1);
+
+        app.run_command(ScrollVertically(-1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected0);
+    }
+
+    #[test]
+    fn repeated_strings() {
+        let binary_input = [
+            0x0A, 0x03, 0x61, 0x62, 0x63,
+            0x0A, 0x03, 0x64, 0x65, 0x66];
+        let proto = ProtoData::new("message M { repeated string f1=1; }").unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        let data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        let expected = [
+            " f1: 'abc'                                string* ",
+            " f1: 'def'                                string* "
+        ];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    #[test]
+    fn repeated_bytes() {
+        let binary_input = [
+            0x0A, 0x02, 0x01, 0x02,
+            0x0A, 0x03, 0x03, 0x04, 0x05];
+        let proto = ProtoData::new("message M { repeated bytes f1=1; }").unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        let data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        let expected = [
+            " f1: 01 02                                 bytes* ",
+            " f1: 03 04 05                              bytes* "
+        ];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+
+    #[test]
+    fn fit_bytes_width() {
+        {
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 16].into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 60, 25).unwrap();
+            let expected = [" f1: 00 00 00 00 00 00 00 00  00 00 00 00 00 00 00 00 bytes "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        { // all the same but repeated field add '*'
+            let data = make_one_field_data("message M { repeated bytes f1=1; }", BYTES(vec![0; 16].into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 60, 25).unwrap();
+            let expected = [
+                " f1: 00 00 00 00 00 00 00 00                         bytes* ",
+                "  8: 00 00 00 00 00 00 00 00                                "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 16].into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 59, 25).unwrap();
+            let expected = [
+                " f1: 00 00 00 00 00 00 00 00                         bytes ",
+                "  8: 00 00 00 00 00 00 00 00                               "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 9].into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 39, 25).unwrap();
+            let expected = [
+                " f1: 00 00 00 00 00 00 00 00  00 bytes ",
+            ];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 9].into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 32, 25).unwrap();
+            let expected = [
+                " f1: 00 00 00 00 00 00 00 bytes ",
+                "  7: 00 00                      ",
+            ];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 9].into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 33, 25).unwrap();
+            let expected = [
+                " f1: 00 00 00 00 00 00 00  bytes ",
+                "  7: 00 00                       ",
+            ];
+            assert_eq!(app.to_strings(), expected);
+        }
+    }
+
+    //    #[test]
+    //    fn trim_bytes_width() {
+    //        {
+    //            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![0; 16]));
+    //            let mut app = App::for_tests(data, FieldOrder::Proto, 25, 25).unwrap();
+    //            assert_eq!(app.to_strings()[0], " f1: 00 00 00 00 00bytes ");
+    //        }
+    //    }
+
+    #[test]
+    fn delete_byte() {
+        {
+            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes.into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+            app.after_event().unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            let expected = [
+                " f1: 01 02 03 04 05 06  bytes ", // data left unchanged if address row was selected
+                "  6: 07 08                    "];
+            assert_eq!(app.to_strings(), expected);
+        }
+
+        {
+            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes.into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+            let expected = [
+                " f1: 01 02 03 04 05 06  bytes ",
+                "  6: 07 08                    "];
+            assert_eq!(app.to_strings(), expected);
+            app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
+            app.after_event().unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            let expected = [
+                " f1: 02 03 04 05 06 07  bytes ",
+                "  6: 08                       "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes.into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::ScrollHorizontally(2)).unwrap();
+            app.after_event().unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            let expected = [
+                " f1: 01 03 04 05 06 07  bytes ",
+                "  6: 08                       "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes.into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::ScrollHorizontally(22)).unwrap();
+            app.after_event().unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            let expected = [
+                " f1: 01 02 03 04 05 07  bytes ",
+                "  6: 08                       "];
+            assert_eq!(app.to_strings(), expected);
+
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            let expected = [
+                " f1: 01 02 03 04 05 08  bytes "];
+            assert_eq!(app.to_strings(), expected);
+
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            let expected = [
+                " f1: 01 02 03 04 05     bytes "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let bytes = (1..=8).into_iter().collect::<Vec<u8>>();
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes.into()));
+            let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
+            app.after_event().unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+            app.after_event().unwrap();
+            app.to_strings();
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            let expected = [
+                " f1: 01 02 03 04 05 06  bytes ",
+                "  6: 08                       "];
+            assert_eq!(app.to_strings(), expected);
+
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            let expected = [
+                " f1: 01 02 03 04 05 06  bytes "];
+            assert_eq!(app.to_strings(), expected);
+
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            let expected = [
+                " f1: 01 02 03 04 05     bytes "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let bytes = (1..=3).into_iter().collect::<Vec<u8>>();
+            let data = make_one_field_data("message M { bytes f1=1; }", BYTES(bytes.into()));
             let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
             assert_eq!(app.to_strings(), [" f1: 01 02 03           bytes "]);
             app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
@@ -1221,801 +4947,3251 @@ message M2 { int32 i2 = 2; int32 i3 = 3; }
             app.to_strings();
             app.run_command(UserCommand::DeleteData).unwrap();
             app.after_event().unwrap();
-            assert_eq!(app.to_strings(), [" f1: 02 03              bytes "]);
+            assert_eq!(app.to_strings(), [" f1: 02 03              bytes "]);
+
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            assert_eq!(app.to_strings(), [" f1: 03                 bytes "]);
+
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            assert_eq!(app.to_strings(), [" f1:                    bytes "]);
+
+            app.run_command(UserCommand::DeleteData).unwrap();
+            app.after_event().unwrap();
+            assert_eq!(app.to_strings(), [" f1:                   -bytes "]);
+        }
+    }
+
+
+    #[test]
+    fn collapse_scalar() { // scalar layouts is not collapsable
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+
+        app.to_strings();
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.after_event().unwrap();
+
+        let expected = [
+            " f1: 1                                      int32 ",
+            " f2: 20 21                                 int32* ",
+            " m3:                                           M3 ",
+            "   f5: 5                                    int32 ",
+            "   m6:                                        M6* ",
+            "     f8: 8                                  int32 ",
+            "     f9: 9                                  int32 ",
+            "   m6:                                        M6* ",
+            "     f8: 10                                 int32 ",
+            "     f9: 11                                 int32 ",
+            "   f7: 7                                    int32 ",
+            " f4: 0                                     -int32 "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    #[test]
+    fn collapse_expand_message() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+
+        app.to_strings();
+        app.run_command(UserCommand::ScrollVertically(2)).unwrap();
+        app.after_event().unwrap();
+
+
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.after_event().unwrap();
+
+        let expected = [
+            " f1: 1                                      int32 ",
+            " f2: 20 21                                 int32* ",
+            " m3: ... 14                                    M3 ",
+            " f4: 0                                     -int32 "];
+        assert_eq!(app.to_strings(), expected);
+
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.after_event().unwrap();
+
+        let expected = [
+            " f1: 1                                      int32 ",
+            " f2: 20 21                                 int32* ",
+            " m3:                                           M3 ",
+            "   f5: 5                                    int32 ",
+            "   m6:                                        M6* ",
+            "     f8: 8                                  int32 ",
+            "     f9: 9                                  int32 ",
+            "   m6:                                        M6* ",
+            "     f8: 10                                 int32 ",
+            "     f9: 11                                 int32 ",
+            "   f7: 7                                    int32 ",
+            " f4: 0                                     -int32 "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+
+    #[test]
+    fn collapsed_kept_in_new_layouts() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 50, 25).unwrap();
+        let collapsed = |app: &mut App| { app.to_strings(); app.collapsed_paths().iter().map(|path| path.to_text(&app.data).unwrap()).collect::<Vec<_>>() };
+        app.run_command(UserCommand::GoToPath([(3, 0), (6, 1)].into())).unwrap();
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(collapsed(&mut app), ["m3.m6[1]"]);
+
+        // a value inserted in the root message and undone: the layouts are created again
+        app.run_command(UserCommand::GoToPath([(2, 0)].into())).unwrap();
+        app.run_keys(&harness::parse_keys("Ins").unwrap()).unwrap();
+        assert_eq!(collapsed(&mut app), ["m3.m6[1]"]);
+        app.run_keys(&harness::parse_keys("Ctrl+Z F4").unwrap()).unwrap();
+        assert_eq!(collapsed(&mut app), ["m3.m6[1]"]);
+
+        // the focused message keeps the state of its content
+        app.run_keys(&harness::parse_keys("F4 F4 F4").unwrap()).unwrap();
+        app.run_command(UserCommand::GoToPath([(3, 0)].into())).unwrap();
+        app.run_keys(&harness::parse_keys("Ctrl+Right").unwrap()).unwrap();
+        assert_eq!(collapsed(&mut app), ["m6[1]"]);
+        app.run_command(UserCommand::GoToPath([(6, 1)].into())).unwrap();
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.run_command(UserCommand::GoToPath([(6, 0)].into())).unwrap();
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.run_keys(&harness::parse_keys("Esc").unwrap()).unwrap();
+        assert_eq!(collapsed(&mut app), ["m3.m6[0]"]);
+    }
+
+    #[test]
+    fn collapsed_follow_the_value() {
+        let mut app = App::for_tests(make_repeated_message_data(4), FieldOrder::Proto, 30, 25).unwrap();
+        let collapsed = |app: &mut App| { app.to_strings(); app.collapsed_paths().iter().map(|path| path.to_text(&app.data).unwrap()).collect::<Vec<_>>() };
+        app.run_command(UserCommand::GoToPath([(1, 2)].into())).unwrap();
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        let lines = app.to_strings();
+        assert_eq!(collapsed(&mut app), ["m1[2]"]);
+
+        // the value above deleted, and inserted again by the undo
+        app.run_command(UserCommand::GoToPath([(1, 0)].into())).unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        assert_eq!(collapsed(&mut app), ["m1[1]"]);
+        assert_eq!(app.layout_config.collapsed, HashSet::from([[(1, 1)].into()]));
+        app.run_keys(&harness::parse_keys("Ctrl+Z").unwrap()).unwrap();
+        assert_eq!(collapsed(&mut app), ["m1[2]"]);
+        assert_eq!(app.to_strings(), lines);
+
+        // the collapsed value deleted
+        app.run_command(UserCommand::GoToPath([(1, 2)].into())).unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        assert!(collapsed(&mut app).is_empty());
+        assert!(app.layout_config.collapsed.is_empty());
+    }
+
+    #[test]
+    fn delete_in_proto_order() {
+        let binary_input = [0x08, 0x01, 0x10, 0x02, 0x18, 0x03];
+        let proto = ProtoData::new("message M { int32 f1=1; int32 f2=2; int32 f3=3; }").unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        let data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        let expected = [
+            " f1: 1                                      int32 ",
+            " f2: 2                                      int32 ",
+            " f3: 3                                      int32 "];
+        assert_eq!(app.to_strings(), expected);
+
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        let expected = [
+            " f1: 1                                      int32 ",
+            " f2: 0                                     -int32 ",
+            " f3: 3                                      int32 "];
+        assert_eq!(app.to_strings(), expected);
+
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        let expected = [
+            " f1: 1                                      int32 ",
+            " f2: 0                                     -int32 ",
+            " f3: 0                                     -int32 "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+
+    #[test]
+    fn delete_in_wire_order() {
+        let binary_input = [0x08, 0x01, 0x10, 0x02, 0x18, 0x03];
+        let proto = ProtoData::new("message M { int32 f1=1; int32 f2=2; int32 f3=3; }").unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        let data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+
+        let mut app = App::for_tests(data, FieldOrder::Wire, 30, 25).unwrap();
+        let expected = [
+            " f1: 1                  int32 ",
+            " f2: 2                  int32 ",
+            " f3: 3                  int32 "];
+        assert_eq!(app.to_strings(), expected);
+
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        let expected = [
+            " f1: 1                  int32 ",
+            " f3: 3                  int32 "];
+        assert_eq!(app.to_strings(), expected);
+
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        let expected = [
+            " f1: 1                  int32 "];
+        assert_eq!(app.to_strings(), expected);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        let expected: [&str; 0] = [];
+        assert_eq!(app.to_strings(), expected);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        let expected: [&str; 0] = [];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    #[test]
+    fn delete_string() {
+        let data = make_one_field_data("message M { string f1=1; }", STR("abc".into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.to_strings();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [" f1: ''               -string "]);
+    }
+
+    #[test]
+    fn delete_bytes() {
+        let data = make_one_field_data("message M { bytes f1=1; }", BYTES([].to_vec().into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.to_strings();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [" f1:                   -bytes "]);
+    }
+
+    #[test]
+    fn set_default_and_clear() {
+        let data = make_no_field_data("message M { int32 i1=1; optional int32 i2=2; string s3=3; }");
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        let ctrl_d = KeyEvent::new_with_kind(KeyCode::Char('d'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        let alt_d = KeyEvent::new_with_kind(KeyCode::Char('d'), KeyModifiers::ALT, KeyEventKind::Press);
+        app.on_key(ctrl_d).unwrap();
+        app.run_command(ScrollVertically(1)).unwrap();
+        app.on_key(ctrl_d).unwrap();
+        app.run_command(ScrollVertically(1)).unwrap();
+        app.on_key(ctrl_d).unwrap();
+        app.after_event().unwrap();
+        // the field without explicit presence is marked, the optional one is not
+        assert_eq!(app.to_strings(), [
+            " i1:+0                  int32 ",
+            " i2: 0                  int32 ",
+            " s3:+''                string "]);
+        app.on_key(ctrl_d).unwrap();
+        assert_eq!(app.message.as_deref(), Some("s3 is set already"));
+
+        app.on_key(alt_d).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[2], " s3: ''               -string ");
+        app.on_key(alt_d).unwrap();
+        assert_eq!(app.message.as_deref(), Some("s3 is not set"));
+    }
+
+    #[test]
+    fn bytes_offsets_column() {
+        let data = make_one_field_data("message M { bytes f1=1; }", BYTES((0..20).collect::<Vec<u8>>().into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 40, 25).unwrap();
+        let ctrl_f = KeyEvent::new_with_kind(KeyCode::Char('f'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.on_key(ctrl_f).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " f1:  0 00 01 02 03 04 05 06 07   bytes ",
+            "   :  8 08 09 0A 0B 0C 0D 0E 0F         ",
+            "   : 10 10 11 12 13                     ",
+            "   : 20 bytes (0x14)                    "]);
+        app.on_key(ctrl_f).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.message.as_deref(), Some("bytes offsets: decimal"));
+        assert_eq!(app.to_strings()[1..], [
+            "   :  8 08 09 0A 0B 0C 0D 0E 0F         ",
+            "   : 16 10 11 12 13                     ",
+            "   : 20 bytes                           "]);
+        // no value on the line of the length
+        app.run_command(ScrollVertically(3)).unwrap();
+        app.run_command(ScrollHorizontally(1)).unwrap();
+        app.run_command(DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings().len(), 4);
+
+        app.on_key(ctrl_f).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " f1: 00 01 02 03 04 05 06 07      bytes ",
+            "  8: 08 09 0A 0B 0C 0D 0E 0F            ",
+            " 10: 10 11 12 13                        "]);
+    }
+
+    #[test]
+    fn merge_wire_groups() {
+        let binary_input = [0x08, 0x01, 0x10, 0x02, 0x08, 0x03, 0x08, 0x04, 0x10, 0x05];
+        let proto = ProtoData::new("message M { repeated int32 f1=1; repeated int32 f2=2; }").unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let data = MessageData::new(&mut PbReader::new(binary_input.as_slice()), &proto, root_msg, &mut limit).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Wire, 30, 25).unwrap();
+        app.proto = proto;
+        assert_eq!(app.to_strings(), [
+            " f1: 1                int32*↓ ",
+            " f2: 2                int32*↓ ",
+            " f1: 3 4              int32*↑ ",
+            " f2: 5                int32*↑ "]);
+
+        let ctrl_a = KeyEvent::new_with_kind(KeyCode::Char('a'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.run_command(ScrollVertically(2)).unwrap();
+        app.on_key(ctrl_a).unwrap();
+        assert!(app.get_top_line(100, &LayoutConfig::default()).contains("f1 after merging its groups, Y - merge, Esc - cancel"));
+        assert_eq!(app.review.as_ref().unwrap().lines, [
+            "  offset   size  field",
+            "       0      6  f1[0..2]",
+            "       6      4  f2[0..1]"]);
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.message.as_deref(), Some("f1: 2 groups merged"));
+        assert_eq!(app.to_strings(), [
+            " f1: 1 3 4             int32* ",
+            " f2: 2 5               int32* "]);
+        app.on_key(ctrl_a).unwrap();
+        assert_eq!(app.message.as_deref(), Some("the values of f1 are together already"));
+    }
+
+    #[test]
+    fn message_columns() {
+        let proto = ProtoData::new("message M { repeated N n=1; }\nmessage N { int32 id=1; string name=2; bool ok=3; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set n[0].id = 7\nset n[0].name = abcdefgh\nset n[1].name = x").unwrap();
+        let config_path = std::env::temp_dir().join(format!("protoedit_columns_{}.conf", std::process::id()));
+        std::fs::write(&config_path, "indent = 2\n").unwrap();
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.proto = proto;
+        app.config_path = Some(config_path.clone());
+        let ctrl_u = KeyEvent::new_with_kind(KeyCode::Char('u'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.on_key(ctrl_u).unwrap();
+        for c in "id:3, nam".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        // wrong field name, the prompt stays open
+        assert!(app.prompt.is_some());
+        for c in "e:5".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.message, Some(format!("columns of N saved to {}", config_path.display())));
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), "indent = 2\ncolumns.N = id:3, name:5\n");
+
+        app.run_command(CollapsedToggle).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[0], " n: 7   abcd…              N* ");
+
+        // no columns: the size of the collapsed message again
+        app.on_key(ctrl_u).unwrap();
+        for _ in 0.."id:3, name:5".len() { app.on_key(press(KeyCode::Backspace)).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[0], " n: ... 11                 N* ");
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), "indent = 2\n");
+        std::fs::remove_file(&config_path).unwrap();
+    }
+
+    #[test]
+    fn field_formats() {
+        // t: 1700000000, flags: 255, id: "abcd"
+        let binary_input = [0x08, 0x80, 0xe2, 0xcf, 0xaa, 0x06, 0x10, 0xff, 0x01, 0x1a, 0x04, b'a', b'b', b'c', b'd'];
+        let proto = ProtoData::new("message M { int64 t = 1; int32 flags = 2; bytes id = 3; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let read = || MessageData::new(&mut PbReader::new(binary_input.as_slice()), &proto, root_msg.clone(), &mut (binary_input.len() as u32)).unwrap();
+        let formats_path = std::env::temp_dir().join(format!("protoedit_formats_{}", std::process::id()));
+        let mut app = App::for_tests(read(), FieldOrder::Proto, 40, 25).unwrap();
+        app.proto = proto.clone();
+        app.formats_path = Some(formats_path.clone());
+        let set_format = |app: &mut App, old: &str, new: &str| {
+            app.run_keys(&harness::parse_keys("Alt+O").unwrap()).unwrap();
+            for _ in 0..old.len() { app.on_key(press(KeyCode::Backspace)).unwrap(); }
+            for c in new.chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+            app.on_key(press(KeyCode::Enter)).unwrap();
+        };
+
+        app.run_keys(&harness::parse_keys("Down").unwrap()).unwrap();
+        set_format(&mut app, "dec", "octal");
+        assert!(app.prompt.is_some());
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        set_format(&mut app, "dec", "hex");
+        assert_eq!(app.message, Some(format!("flags shown as hex, kept in {}", formats_path.display())));
+        app.run_keys(&harness::parse_keys("Up").unwrap()).unwrap();
+        set_format(&mut app, "dec", "timestamp");
+        app.run_keys(&harness::parse_keys("Down Down").unwrap()).unwrap();
+        set_format(&mut app, "default", "utf8");
+        let formatted = [
+            "     t: 2023-11-14 22:13:20 UTC   int64 ",
+            " flags: 0xFF                      int32 ",
+            "    id: 'abcd'                    bytes "];
+        assert_eq!(app.to_strings(), formatted);
+        let data_file = std::path::absolute("test_data.pb").unwrap().display().to_string();
+        assert_eq!(std::fs::read_to_string(&formats_path).unwrap(),
+            format!("{0}\tflags=hex\n{0}\tt=timestamp\n{0}\tid=utf8\n", data_file));
+
+        // the next session of the file
+        let mut app = App::for_tests(read(), FieldOrder::Proto, 40, 25).unwrap();
+        app.proto = proto.clone();
+        app.formats_path = Some(formats_path.clone());
+        app.load_formats();
+        assert_eq!(app.to_strings(), formatted);
+        app.run_keys(&harness::parse_keys("Down").unwrap()).unwrap();
+        set_format(&mut app, "hex", "dec");
+        assert_eq!(app.to_strings()[1], " flags: 255                       int32 ");
+        assert_eq!(std::fs::read_to_string(&formats_path).unwrap(), format!("{0}\tt=timestamp\n{0}\tid=utf8\n", data_file));
+        std::fs::remove_file(&formats_path).unwrap();
+    }
+
+    #[test]
+    fn go_to_bytes_offset() {
+        let data = make_one_field_data("message M { bytes f1=1; }", BYTES((0..=255).cycle().take(100_000).collect::<Vec<u8>>().into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 40, 6).unwrap();
+        let expected = [
+            "    f1: 00 01 02 03 04 05 06 07   bytes ",
+            "     8: 08 09 0A 0B 0C 0D 0E 0F         ",
+            "    10: 10 11 12 13 14 15 16 17         ",
+            "    18: 18 19 1A 1B 1C 1D 1E 1F         ",
+            "    20: 20 21 22 23 24 25 26 27         "];
+        assert_eq!(app.to_strings(), expected);
+        assert!(app.get_top_line(40, &app.layout_config).contains("100000 bytes"));
+
+        let ctrl_o = KeyEvent::new_with_kind(KeyCode::Char('o'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.on_key(ctrl_o).unwrap();
+        assert!(app.prompt.is_some());
+        for c in "0x40".chars() {
+            app.on_key(press(KeyCode::Char(c))).unwrap();
+        }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        app.after_event().unwrap();
+        assert!(app.get_top_line(40, &app.layout_config).contains("64/100000"));
+        assert!(app.to_strings().iter().any(|line| line.starts_with("    40: 40 41")));
+
+        app.on_key(ctrl_o).unwrap();
+        for c in "100000".chars() {
+            app.on_key(press(KeyCode::Char(c))).unwrap();
+        }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("error: the offset is out of the 100000 bytes"));
+
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 50, 25).unwrap();
+        app.on_key(ctrl_o).unwrap();
+        assert_eq!(app.message.as_deref(), Some("select a bytes field"));
+    }
+
+    #[test]
+    fn repeated_int() {
+        let mut data = make_one_field_data("message M { repeated int32 i1=1; }", I32(1));
+
+        data.add_field(&[(1, 1).into()]).unwrap().value = FieldValue::SCALAR(I32(2));
+        data.add_field(&[(1, 2).into()]).unwrap().value = FieldValue::SCALAR(I32(3));
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" i1: 1 2 3             int32* "]);
+
+        //app.run_command(UserCommand::DeleteData).unwrap();
+        //app.after_event().unwrap();
+    }
+
+    fn make_repeated_int_data() -> App {
+        let mut data = make_no_field_data("message M { repeated int32 i1=1; }");
+        for v in 1..=6 {
+            data.add_field(&[(1, usize::MAX).into()]).unwrap().value = SCALAR(I32(v));
+        }
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 25).unwrap();
+        assert_eq!(app.to_strings(), [" i1: 1 2 3 4 int32* ", "  4: 5 6            "]);
+        app
+    }
+
+    #[test]
+    fn delete_repeated_int() {
+        let mut app = make_repeated_int_data();
+
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
+        app.after_event().unwrap();
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " i1: 1 2 3 4 int32* ",
+            "  4: 6              "]);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " i1: 1 2 3 4 int32* "]);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " i1: 1 2 3   int32* "]);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " i1: 1 2     int32* "]);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " i1: 1       int32* "]);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " i1: 0      -int32* "]);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " i1: 0      -int32* "]);
+    }
+
+
+    #[test]
+    fn insert_int() {
+        fn test_fn(scroll_x: usize, scroll_y: usize, expected: Vec<&str>) {
+            let mut app = make_repeated_int_data();
+
+            for _ in 0..scroll_y {
+                app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+                app.after_event().unwrap();
+            }
+            for _ in 0..scroll_x {
+                app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
+                app.after_event().unwrap();
+            }
+
+            app.run_command(UserCommand::InsertData).unwrap();
+            app.after_event().unwrap();
+            assert_eq!(app.to_strings(), expected);
+        }
+
+        let expected = [
+            " i1: 0 1 2 3 int32* ",
+            "  4: 4 5 6          "].to_vec();
+        test_fn(0, 0, expected);
+
+        let expected = [
+            " i1: 1 0 2 3 int32* ",
+            "  4: 4 5 6          "].to_vec();
+        test_fn(1, 0, expected);
+
+        let expected = [
+            " i1: 1 2 0 3 int32* ",
+            "  4: 4 5 6          "].to_vec();
+        test_fn(2, 0, expected);
+
+        let expected = [
+            " i1: 1 2 3 4 int32* ",
+            "  4: 5 0 6          "].to_vec();
+        test_fn(1, 1, expected);
+
+        let expected = [
+            " i1: 1 2 3 4 int32* ",
+            "  4: 5 6 0          "].to_vec();
+        test_fn(5, 1, expected);
+    }
+
+    //    #[test]
+    //    fn insert_int() {
+    //        let mut data = make_no_field_data("message M { repeated int32 i1=1; }");
+    //
+    //        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+    //        assert_eq!(app.to_strings(), [" i1: 0                -int32* "]);
+    //
+    //        app.run_command(UserCommand::InsertData).unwrap();
+    //        app.after_event().unwrap();
+    //        assert_eq!(app.to_strings(), [" i1: 0                 int32* "]);
+    //
+    //        app.run_command(UserCommand::InsertData).unwrap();
+    //        app.after_event().unwrap();
+    //        assert_eq!(app.to_strings(), [" i1: 0 0               int32* "]);
+    //    }
 
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            assert_eq!(app.to_strings(), [" f1: 03                 bytes "]);
 
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            assert_eq!(app.to_strings(), [" f1:                    bytes "]);
+    #[test]
+    fn repeated_multiline_int() {
+        fn test_data() -> MessageData {
+            let mut data = make_one_field_data("message M { repeated int32 i1=1; }", I32(2));
+            for v in 2..10 {
+                data.add_field(&[(1, usize::MAX).into()]).unwrap().value = FieldValue::SCALAR(I32(v * 2));
+            }
+            data
+        }
+        {
+            let mut app = App::for_tests(test_data(), FieldOrder::Proto, 25, 25).unwrap();
+            let expected = [
+                " i1: 2 4 6 8 10   int32* ",
+                "  5: 12 14 16 18         "];
+            assert_eq!(app.to_strings(), expected);
+        }
+        {
+            let mut app = App::for_tests(test_data(), FieldOrder::Proto, 26, 25).unwrap();
+            let expected = [
+                " i1: 2 4 6 8 10 12 int32* ",
+                "  6: 14 16 18             "];
+            assert_eq!(app.to_strings(), expected);
+        }
+    }
+
+    #[test]
+    fn nested_repeated_strings() {
+        let proto_str = "message M { M2 m2 = 2; }\nmessage M2 { repeated string s1 = 1; }";
+        let binary_input = [
+            0x12, 8,  //              m2: M2
+            0x0A, 2, 0x77, 0x77, //     s1: 2 "ww"
+            0x0A, 2, 0x78, 0x78, //     s1: 2 "xx"
+        ];
+
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        let expected = [
+            " m2:                       M2 ",
+            "   s1: 'ww'           string* ",
+            "   s1: 'xx'           string* "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    #[test]
+    fn nested_repeated_int() {
+        let proto_str = "message M { M2 m2 = 2; }\nmessage M2 { repeated int32 i1 = 1; }";
+        let binary_input = [
+            0x12, 4, // m2: M2
+            0x08, 1, //   i1: 1
+            0x08, 2, //   i1: 2
+        ];
+
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        let expected = [
+            " m2:                       M2 ",
+            "   i1: 1 2             int32* "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+
+    #[test]
+    fn nested_long_name() {
+        let proto_str = "message M { M2 m2 = 2; }\nmessage M2 { M3 m3 = 2; int32 longname = 3; }\nmessage M3 { M4 m4 = 2; }\nmessage M4 { int32 i = 1; }";
+        let binary_input = [
+            0x12, 4, // m2: M2
+            0x12, 2, // m3: M3
+            0x12, 0, // m4: M4
+        ];
+
+        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new(binary_input.as_slice());
+        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+
+        //println!("{:?}", proto);
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+
+        println!("{:?}", app.layouts.indents);
+
+        let expected = [
+            " m2:                       M2 ",
+            "       m3:                 M3 ",
+            "         m4:               M4 ",
+            "            i: 0       -int32 ",
+            " longname: 0           -int32 "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+    #[test]
+    fn empty_string() {
+        let mut data = make_no_field_data("message M {  string s1=1; }");
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" s1: ''               -string "]);
+    }
+
+
+    #[test]
+    fn insert_string() {
+        let mut data = make_one_field_data("message M { repeated string s1=1; }", STR("1".into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.to_strings();
+        app.run_command(UserCommand::InsertData).unwrap();
+        app.after_event().unwrap();
+        let expected = [
+            " s1: ''               string* ", // default value inserted
+            " s1: '1'              string* "];
+        assert_eq!(app.to_strings(), expected);
+    }
+
+
+    #[test]
+    fn delete_repeated_string() {
+        let mut data = make_one_field_data("message M { repeated string s1=1; }", STR("1".into()));
+        data.add_field(&[(1, 1).into()]).unwrap().value = FieldValue::SCALAR(STR("2".into()));
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        let expected = [
+            " s1: '1'              string* ",
+            " s1: '2'              string* "];
+        assert_eq!(app.to_strings(), expected);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [" s1: '2'              string* "]);
+
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [" s1: ''              -string* "]);
+    }
+
+    #[test]
+    fn empty_enum() {
+        let mut data = make_no_field_data("enum E1 { V1=0; V2=1; }\nmessage M { E1 e1=1; }");
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" e1: V1                   -E1 "]);
+    }
+
+    #[test]
+    fn repeated_enum() {
+        let mut data = make_one_field_data("enum E1 { V1=0; V2=1; }\nmessage M { repeated E1 e1=1; }", ENUM(1));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" e1: V2                   E1* "]);
+
+        app.run_command(UserCommand::InsertData).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [" e1: V1 V2                E1* "]);
+    }
+
+    #[test]
+    fn empty_float() {
+        let mut data = make_no_field_data("message M { float f1=1; }");
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" f1: 0                 -float "]);
+    }
+
+    #[test]
+    fn simple_double() {
+        let mut data = make_one_field_data("message M { double f1=1; }", F64(11.1));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" f1: 11.1              double "]);
+    }
+
+    #[test]
+    fn scroll_repeated_message() {
+        let mut data = make_repeated_message_data(3);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 3 + TOP_LINE).unwrap();
+
+        let expected0 = [
+            " m1:            M2* ",
+            "   i2: 2      int32 ",
+            "   i3: 3      int32 "];
+        assert_eq!(app.to_strings(), expected0);
+
+        app.run_command(ScrollVertically(1)).unwrap(); // next line
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected0);
+
+        app.run_command(ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected0);
+
+        app.run_command(ScrollVertically(1)).unwrap(); // scroll one line down
+        app.after_event().unwrap();
+        let expected1 = [
+            "   i2: 2      int32 ",
+            "   i3: 3      int32 ",
+            " m1:            M2* "];
+        assert_eq!(app.to_strings(), expected1);
+
+        app.run_command(ScrollVertically(-1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected1);
+
+        app.run_command(ScrollVertically(-1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected1);
+
+        app.run_command(ScrollVertically(-1)).unwrap(); // scroll one line up
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected0);
+
+        app.run_command(ScrollVertically(99)).unwrap(); // scroll to end
+        app.after_event().unwrap();
+        let expected_end = [
+            " m1:            M2* ",
+            "   i2: 6      int32 ",
+            "   i3: 7      int32 "];
+        assert_eq!(app.to_strings(), expected_end);
+
+        app.run_command(ScrollVertically(1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected_end);
+
+        app.run_command(ScrollVertically(-99)).unwrap(); // scroll to start
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected0);
+
+        app.run_command(ScrollVertically(-1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), expected0);
+    }
+
+    #[test]
+    fn scroll_up_repeated_message() {
+        let mut data = make_repeated_message_data(10);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 3 + TOP_LINE).unwrap();
+
+        let expected_start = [
+            " m1:            M2* ",
+            "   i2: 2      int32 ",
+            "   i3: 3      int32 "];
+        assert_eq!(app.to_strings(), expected_start);
+
+        app.run_command(ScrollToBottom).unwrap(); // end of the file
+        app.after_event().unwrap();
+
+        let expected_end = [
+            " m1:            M2* ",
+            "   i2: 20     int32 ",
+            "   i3: 21     int32 "];
+        assert_eq!(app.to_strings(), expected_end);
+
+        app.run_command(ScrollVertically(-3)).unwrap(); // scroll up
+        app.after_event().unwrap();
 
-            app.run_command(UserCommand::DeleteData).unwrap();
-            app.after_event().unwrap();
-            assert_eq!(app.to_strings(), [" f1:                   -bytes "]);
-        }
+        let expected_end = [
+            "   i3: 19     int32 ",
+            " m1:            M2* ",
+            "   i2: 20     int32 "];
+        assert_eq!(app.to_strings(), expected_end);
     }
 
-
     #[test]
-    fn collapse_scalar() { // scalar layouts is not collapsable
-        let data = make_test_data_1();
-        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
-
+    fn scroll_empty() {
+        let mut data = make_no_field_data("message M { string f1=1; }");
+        let mut app = App::for_tests(data, FieldOrder::Wire, 30, 25).unwrap();
         app.to_strings();
+        app.run_command(UserCommand::ScrollVertically(-1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), Vec::<String>::new());
         app.run_command(UserCommand::ScrollVertically(1)).unwrap();
         app.after_event().unwrap();
-
-        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        assert_eq!(app.to_strings(), Vec::<String>::new());
+        app.run_command(UserCommand::ScrollSibling(1)).unwrap();
         app.after_event().unwrap();
-
-        let expected = [
-            " f1: 1                                      int32 ",
-            " f2: 20 21                                 int32* ",
-            " m3:                                           M3 ",
-            "   f5: 5                                    int32 ",
-            "   m6:                                        M6* ",
-            "     f8: 8                                  int32 ",
-            "     f9: 9                                  int32 ",
-            "   m6:                                        M6* ",
-            "     f8: 10                                 int32 ",
-            "     f9: 11                                 int32 ",
-            "   f7: 7                                    int32 ",
-            " f4: 0                                     -int32 "];
-        assert_eq!(app.to_strings(), expected);
+        assert_eq!(app.to_strings(), Vec::<String>::new());
+        app.run_command(UserCommand::ScrollSibling(-1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), Vec::<String>::new());
     }
 
+
     #[test]
-    fn collapse_expand_message() {
-        let data = make_test_data_1();
-        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+    fn change_field_order() {
+        let mut data = make_one_field_data("message M { int32 x=2; int32 y=1; }", ScalarValue::I32(3));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 25).unwrap();
 
-        app.to_strings();
-        app.run_command(UserCommand::ScrollVertically(2)).unwrap();
+        let expected_start = [
+            " x: 0        -int32 ",
+            " y: 3         int32 "];
+        assert_eq!(app.to_strings(), expected_start);
+
+        app.run_command(UserCommand::ChangeFieldOrder(FieldOrder::ById)).unwrap();
+        app.after_event().unwrap();
+        let expected_start = [
+            " y: 3         int32 ",
+            " x: 0        -int32 "
+        ];
+        assert_eq!(app.to_strings(), expected_start);
+
+        app.run_command(UserCommand::ChangeFieldOrder(FieldOrder::Wire)).unwrap();
         app.after_event().unwrap();
+        let expected_start = [
+            " y: 3         int32 "];
+        assert_eq!(app.to_strings(), expected_start);
 
+        app.run_command(UserCommand::ChangeFieldOrder(FieldOrder::ByName)).unwrap();
+        app.after_event().unwrap();
+        let expected_start = [
+            " x: 0        -int32 ",
+            " y: 3         int32 "];
+        assert_eq!(app.to_strings(), expected_start);
+    }
 
+    #[test]
+    fn field_order_keeps_selection() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 30, 25).unwrap();
+        app.to_strings();
+        app.run_command(UserCommand::GoToPath([(3, 0), (6, 0)].into())).unwrap();
         app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.run_command(UserCommand::GoToPath([(3, 0), (6, 1), (9, 0)].into())).unwrap();
         app.after_event().unwrap();
 
-        let expected = [
-            " f1: 1                                      int32 ",
-            " f2: 20 21                                 int32* ",
-            " m3: ... 14                                    M3 ",
-            " f4: 0                                     -int32 "];
-        assert_eq!(app.to_strings(), expected);
+        app.run_command(UserCommand::ChangeFieldOrder(FieldOrder::ByName)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("m3.m6[1].f9"));
+        assert_eq!(app.collapsed_paths().iter().map(|path| path.to_text(&app.data).unwrap()).collect::<Vec<_>>(), ["m3.m6[0]"]);
 
-        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.run_action(Action::CommentsVisibility).unwrap();
         app.after_event().unwrap();
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("m3.m6[1].f9"));
 
-        let expected = [
-            " f1: 1                                      int32 ",
-            " f2: 20 21                                 int32* ",
-            " m3:                                           M3 ",
-            "   f5: 5                                    int32 ",
-            "   m6:                                        M6* ",
-            "     f8: 8                                  int32 ",
-            "     f9: 9                                  int32 ",
-            "   m6:                                        M6* ",
-            "     f8: 10                                 int32 ",
-            "     f9: 11                                 int32 ",
-            "   f7: 7                                    int32 ",
-            " f4: 0                                     -int32 "];
-        assert_eq!(app.to_strings(), expected);
+        // n.b is not set, so it is not shown in the wire order
+        let mut data = make_no_field_data("message M { int32 a = 1; N n = 2; }\nmessage N { int32 b = 1; int32 c = 2; }");
+        data.add_field(&[(2, 0).into()]).unwrap();
+        data.add_field(&[(2, 0).into(), (2, 0).into()]).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.to_strings();
+        app.run_command(UserCommand::GoToPath([(2, 0), (1, 0)].into())).unwrap();
+        app.after_event().unwrap();
+        app.run_command(UserCommand::ChangeFieldOrder(FieldOrder::Wire)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("n"));
     }
 
-
     #[test]
-    fn delete_in_proto_order() {
-        let binary_input = [0x08, 0x01, 0x10, 0x02, 0x18, 0x03];
-        let proto = ProtoData::new("message M { int32 f1=1; int32 f2=2; int32 f3=3; }").unwrap().finalize().unwrap();
-        let mut limit = binary_input.len() as u32;
-        let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        let data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
-
+    fn layout_percent() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 25).unwrap();
 
+        let data = make_test_data_1();
         let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
-        let expected = [
-            " f1: 1                                      int32 ",
-            " f2: 2                                      int32 ",
-            " f3: 3                                      int32 "];
+        let expected = [                                   // children count
+            " f1: 1                                      int32 ",   // 0
+            " f2: 20 21                                 int32* ",   // 0
+            " m3:                                           M3 ",   // 4
+            "   f5: 5                                    int32 ",   // 0
+            "   m6:                                        M6* ",   // 2
+            "     f8: 8                                  int32 ",   // 0
+            "     f9: 9                                  int32 ",   // 0
+            "   m6:                                        M6* ",   // 2
+            "     f8: 10                                 int32 ",   // 0
+            "     f9: 11                                 int32 ",   // 0
+            "   f7: 7                                    int32 ",   // 0
+            " f4: 0                                     -int32 "];  // 0
         assert_eq!(app.to_strings(), expected);
 
-        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        assert_eq!(app.layouts.items.len(), 12);
+        assert_eq!(app.layouts.calc_relative_pos(0), 0.0);
+        assert_eq!(app.layouts.calc_relative_pos(1), 0.25);
+        assert_eq!(app.layouts.calc_relative_pos(2), 0.5);
+        assert_eq!(app.layouts.calc_relative_pos(3), 0.55);
+        assert_eq!(app.layouts.calc_relative_pos(4), 0.6);
+        assert_eq!(app.layouts.calc_relative_pos(7), 0.65);
+        assert_eq!(app.layouts.calc_relative_pos(10), 0.7);
+        assert_eq!(app.layouts.calc_relative_pos(11), 0.75);
+        assert_eq!(app.layouts.items[0].children_count, 0);
+        assert_eq!(app.layouts.items[1].children_count, 0);
+        assert_eq!(app.layouts.items[2].children_count, 4);
+        assert_eq!(app.layouts.items[3].children_count, 0);
+        assert_eq!(app.layouts.items[4].children_count, 2);
+        assert_eq!(app.layouts.items[5].children_count, 0);
+        assert_eq!(app.layouts.items[6].children_count, 0);
+        assert_eq!(app.layouts.items[7].children_count, 2);
+        assert_eq!(app.layouts.items[8].children_count, 0);
+        assert_eq!(app.layouts.items[9].children_count, 0);
+        assert_eq!(app.layouts.items[10].children_count, 0);
+        assert_eq!(app.layouts.items[11].children_count, 0);
+    }
+
+    #[test]
+    fn layout_percent_after_expand() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+
+        app.to_strings();
+        app.run_command(UserCommand::ScrollVertically(2)).unwrap();
         app.after_event().unwrap();
-        app.run_command(UserCommand::DeleteData).unwrap();
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
         app.after_event().unwrap();
+
         let expected = [
-            " f1: 1                                      int32 ",
-            " f2: 0                                     -int32 ",
-            " f3: 3                                      int32 "];
+            " f1: 1                  int32 ",   // 0
+            " f2: 20 21             int32* ",   // 0
+            " m3: ... 14                M3 ",   // 4
+            " f4: 0                 -int32 "];  // 0
         assert_eq!(app.to_strings(), expected);
 
-        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
-        app.after_event().unwrap();
-        app.run_command(UserCommand::DeleteData).unwrap();
+        assert_eq!(app.layouts.items.len(), 4);
+        assert_eq!(app.layouts.items[0].children_count, 0);
+        assert_eq!(app.layouts.items[1].children_count, 0);
+        assert_eq!(app.layouts.items[2].children_count, 0);
+        assert_eq!(app.layouts.items[3].children_count, 0);
+        assert_eq!(app.layouts.calc_relative_pos(0), 0.0);
+        assert_eq!(app.layouts.calc_relative_pos(1), 0.25);
+        assert_eq!(app.layouts.calc_relative_pos(2), 0.5);
+        assert_eq!(app.layouts.calc_relative_pos(3), 0.75);
+
+
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
         app.after_event().unwrap();
+
         let expected = [
-            " f1: 1                                      int32 ",
-            " f2: 0                                     -int32 ",
-            " f3: 0                                     -int32 "];
+            " f1: 1                  int32 ",   // 0
+            " f2: 20 21             int32* ",   // 0
+            " m3:                       M3 ",   // 4
+            "   f5: 5                int32 ",   // 0
+            "   m6:                    M6* ",   // 2
+            "     f8: 8              int32 ",   // 0
+            "     f9: 9              int32 ",   // 0
+            "   m6:                    M6* ",   // 2
+            "     f8: 10             int32 ",   // 0
+            "     f9: 11             int32 ",   // 0
+            "   f7: 7                int32 ",   // 0
+            " f4: 0                 -int32 "];  // 0
         assert_eq!(app.to_strings(), expected);
-    }
 
+        assert_eq!(app.layouts.items.len(), 12);
+        assert_eq!(app.layouts.items[0].children_count, 0);
+        assert_eq!(app.layouts.items[1].children_count, 0);
+        assert_eq!(app.layouts.items[2].children_count, 4);
+        assert_eq!(app.layouts.items[3].children_count, 0);
+        assert_eq!(app.layouts.items[4].children_count, 2);
+        assert_eq!(app.layouts.items[5].children_count, 0);
+        assert_eq!(app.layouts.items[6].children_count, 0);
+        assert_eq!(app.layouts.items[7].children_count, 2);
+        assert_eq!(app.layouts.items[8].children_count, 0);
+        assert_eq!(app.layouts.items[9].children_count, 0);
+        assert_eq!(app.layouts.items[10].children_count, 0);
+        assert_eq!(app.layouts.items[11].children_count, 0);
+        assert_eq!(app.layouts.calc_relative_pos(7), 0.65);
+        assert_eq!(app.layouts.calc_relative_pos(10), 0.7);
+        assert_eq!(app.layouts.calc_relative_pos(11), 0.75);
+    }
 
     #[test]
-    fn delete_in_wire_order() {
-        let binary_input = [0x08, 0x01, 0x10, 0x02, 0x18, 0x03];
-        let proto = ProtoData::new("message M { int32 f1=1; int32 f2=2; int32 f3=3; }").unwrap().finalize().unwrap();
-        let mut limit = binary_input.len() as u32;
-        let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        let data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
-
-        let mut app = App::for_tests(data, FieldOrder::Wire, 30, 25).unwrap();
-        let expected = [
-            " f1: 1                  int32 ",
-            " f2: 2                  int32 ",
-            " f3: 3                  int32 "];
-        assert_eq!(app.to_strings(), expected);
+    fn layout_percent_after_data_change() {
+        let mut data = make_repeated_message_data(0);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" m1:                     -M2* "]);
 
-        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.run_command(UserCommand::InsertData).unwrap();
         app.after_event().unwrap();
-        app.run_command(UserCommand::DeleteData).unwrap();
+
+        app.run_command(UserCommand::InsertData).unwrap();
         app.after_event().unwrap();
+
         let expected = [
-            " f1: 1                  int32 ",
-            " f3: 3                  int32 "];
+            " m1:                      M2* ",
+            "   i2: 0               -int32 ",
+            "   i3: 0               -int32 ",
+            " m1:                      M2* ",
+            "   i2: 0               -int32 ",
+            "   i3: 0               -int32 "];
         assert_eq!(app.to_strings(), expected);
 
-        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
-        app.after_event().unwrap();
+        assert_eq!(app.layouts.items.len(), 6);
+        assert_eq!(app.layouts.items[0].children_count, 2);
+        assert_eq!(app.layouts.items[1].children_count, 0);
+        assert_eq!(app.layouts.items[3].children_count, 2);
+        assert_eq!(app.layouts.calc_relative_pos(0), 0.0);
+        assert_eq!(app.layouts.calc_relative_pos(3), 0.5);
+        assert_eq!(app.layouts.calc_relative_pos(4), 2.0 / 3.0);
+
         app.run_command(UserCommand::DeleteData).unwrap();
         app.after_event().unwrap();
         let expected = [
-            " f1: 1                  int32 "];
+            " m1:                      M2* ",
+            "   i2: 0               -int32 ",
+            "   i3: 0               -int32 "];
         assert_eq!(app.to_strings(), expected);
 
-        app.run_command(UserCommand::DeleteData).unwrap();
+        assert_eq!(app.layouts.items.len(), 3);
+        assert_eq!(app.layouts.items[0].children_count, 2);
+        assert_eq!(app.layouts.items[1].children_count, 0);
+        assert_eq!(app.layouts.items[2].children_count, 0);
+        assert_eq!(app.layouts.calc_relative_pos(0), 0.0);
+        assert_eq!(app.layouts.calc_relative_pos(1), 0.25);
+        assert_eq!(app.layouts.calc_relative_pos(2), 0.5);
+    }
+
+    // TODO unknown field layout
+    // TODO delete a field of a submessage
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new_with_kind(code, KeyModifiers::NONE, KeyEventKind::Press)
+    }
+
+    #[test]
+    fn letter_chords_profile() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.keymap = KeyMap::new(KeyProfile::Letters);
+
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::Char('c'))).unwrap();
         app.after_event().unwrap();
-        let expected: [&str; 0] = [];
+        let expected = [
+            " f1: 1                  int32 ",
+            " f2: 20 21             int32* ",
+            " m3: ... 14                M3 ",
+            " f4: 0                 -int32 "];
         assert_eq!(app.to_strings(), expected);
 
-        app.run_command(UserCommand::DeleteData).unwrap();
+        app.on_key(press(KeyCode::Char('o'))).unwrap();
         app.after_event().unwrap();
-        let expected: [&str; 0] = [];
-        assert_eq!(app.to_strings(), expected);
+        assert_eq!(app.layout_config.field_order, FieldOrder::Wire);
+        assert!(!app.on_key(press(KeyCode::Char('q'))).unwrap());
     }
 
     #[test]
-    fn delete_string() {
-        let data = make_one_field_data("message M { string f1=1; }", STR("abc".to_string()));
+    fn letters_ignored_by_default() {
+        let data = make_test_data_1();
         let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        app.to_strings();
-        app.run_command(UserCommand::DeleteData).unwrap();
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::Char('c'))).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [" f1: ''               -string "]);
+        assert_eq!(app.to_strings().len(), 12);
+        assert!(app.on_key(press(KeyCode::Char('q'))).unwrap());
     }
 
     #[test]
-    fn delete_bytes() {
-        let data = make_one_field_data("message M { bytes f1=1; }", BYTES([].to_vec()));
+    fn top_line_message() {
+        let data = make_test_data_1();
         let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        app.to_strings();
+        app.after_command(CommandResult::ShowMessage("saved".into())).unwrap();
+        assert_eq!(app.get_top_line(30, &LayoutConfig::default()), " test_data.pb           saved ");
+        app.on_key(press(KeyCode::Down)).unwrap();
+        assert!(app.message.is_none());
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent { kind, column, row, modifiers: KeyModifiers::NONE }
+    }
+
+    #[test]
+    fn click_selects_value() {
+        let mut app = make_repeated_int_data();
+        app.on_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 8, TOP_LINE + 1)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!((app.selected.layout, app.selected.x, app.selected.y), (0, 2, 1));
+
         app.run_command(UserCommand::DeleteData).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [" f1:                   -bytes "]);
+        assert_eq!(app.to_strings(), [" i1: 1 2 3 4 int32* ", "  4: 5              "]);
+
+        // click on the field name
+        app.on_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 2, TOP_LINE)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!((app.selected.layout, app.selected.x, app.selected.y), (0, 0, 0));
     }
 
     #[test]
-    fn repeated_int() {
-        let mut data = make_one_field_data("message M { repeated int32 i1=1; }", I32(1));
-
-        data.add_field(&[(1, 1).into()]).unwrap().value = FieldValue::SCALAR(I32(2));
-        data.add_field(&[(1, 2).into()]).unwrap().value = FieldValue::SCALAR(I32(3));
+    fn double_click_toggles_message() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        let click = mouse(MouseEventKind::Down(MouseButton::Left), 20, TOP_LINE + 2);
+        app.on_mouse(click).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings().len(), 12);
+        app.on_mouse(click).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " f1: 1                                      int32 ",
+            " f2: 20 21                                 int32* ",
+            " m3: ... 14                                    M3 ",
+            " f4: 0                                     -int32 "]);
 
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" i1: 1 2 3             int32* "]);
+        app.on_mouse(click).unwrap();
+        app.on_mouse(click).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings().len(), 12);
 
-        //app.run_command(UserCommand::DeleteData).unwrap();
-        //app.after_event().unwrap();
+        // below the data
+        app.on_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 20, 20)).unwrap();
+        assert_eq!(app.selected.layout, 2);
     }
 
-    fn make_repeated_int_data() -> App {
-        let mut data = make_no_field_data("message M { repeated int32 i1=1; }");
-        for v in 1..=6 {
-            data.add_field(&[(1, usize::MAX).into()]).unwrap().value = SCALAR(I32(v));
-        }
-
-        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 25).unwrap();
-        assert_eq!(app.to_strings(), [" i1: 1 2 3 4 int32* ", "  4: 5 6            "]);
-        app
+    #[test]
+    fn drag_to_scroll() {
+        let data = make_repeated_message_data(10);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 5 + TOP_LINE).unwrap();
+        app.on_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 5, 5)).unwrap();
+        app.on_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), 5, 2)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!((app.selected.layout, app.selected.y), (7, 0));
+        app.on_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), 5, 3)).unwrap();
+        app.on_mouse(mouse(MouseEventKind::Up(MouseButton::Left), 5, 3)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!((app.selected.layout, app.selected.y), (6, 0));
+        app.on_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), 5, 5)).unwrap();
+        assert_eq!((app.selected.layout, app.selected.y), (6, 0));
     }
 
     #[test]
-    fn delete_repeated_int() {
+    fn protected_field_needs_confirmation() {
         let mut app = make_repeated_int_data();
-
-        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
-        app.after_event().unwrap();
-        app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
+        app.on_key(press(KeyCode::F(3))).unwrap();
         app.after_event().unwrap();
+        assert_eq!(app.to_strings(), ["#i1: 1 2 3 4 int32* ", "# 4: 5 6            "]);
 
+        app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
         app.run_command(UserCommand::DeleteData).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [
-            " i1: 1 2 3 4 int32* ",
-            "  4: 6              "]);
+        assert_eq!(app.message.as_deref(), Some("the field is protected, press Y to change it"));
+        app.on_key(press(KeyCode::Char('n'))).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.message.as_deref(), Some("canceled"));
+        assert_eq!(app.to_strings(), ["#i1: 1 2 3 4 int32* ", "# 4: 5 6            "]);
 
         app.run_command(UserCommand::DeleteData).unwrap();
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [
-            " i1: 1 2 3 4 int32* "]);
+        assert_eq!(app.to_strings(), ["#i1: 2 3 4 5 int32* ", "# 4: 6              "]);
 
+        app.on_key(press(KeyCode::F(3))).unwrap();
         app.run_command(UserCommand::DeleteData).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [
-            " i1: 1 2 3   int32* "]);
+        assert_eq!(app.to_strings(), [" i1: 3 4 5 6 int32* "]);
+    }
 
-        app.run_command(UserCommand::DeleteData).unwrap();
+    #[test]
+    fn protected_message_content() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::F(3))).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [
-            " i1: 1 2     int32* "]);
+        let lines = app.to_strings();
+        assert!(lines[0].starts_with(' ') && lines[1].starts_with(' '));
+        assert!(lines[2..lines.len() - 1].iter().all(|line| line.starts_with(PROTECTED_MARK)), "{:?}", lines);
+
+        // a field inside of the protected message
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::F(3))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("the parent field is protected"));
+        app.run_command(UserCommand::DeleteData).unwrap();
+        assert!(app.pending_change.is_some());
+    }
 
+    #[test]
+    fn protection_follows_the_value() {
+        let mut app = App::for_tests(make_repeated_message_data(4), FieldOrder::Proto, 30, 25).unwrap();
+        let protected = |app: &App| app.protected.iter().map(|(path, _)| path.to_text(&app.data).unwrap()).collect::<Vec<_>>();
+        app.run_command(UserCommand::GoToPath([(1, 2)].into())).unwrap();
+        app.on_key(press(KeyCode::F(3))).unwrap();
+        assert_eq!(protected(&app), ["m1[2]"]);
+
+        // a value deleted before the protected one, then inserted again by the undo
+        app.run_command(UserCommand::GoToPath([(1, 0)].into())).unwrap();
         app.run_command(UserCommand::DeleteData).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [
-            " i1: 1       int32* "]);
+        assert_eq!(protected(&app), ["m1[1]"]);
+        assert!(app.is_protected(&[(1, 1), (2, 0)].into()) && !app.is_protected(&[(1, 2)].into()));
+        app.run_keys(&harness::parse_keys("Ctrl+Z").unwrap()).unwrap();
+        assert_eq!(protected(&app), ["m1[2]"]);
 
+        // a value deleted after it changes nothing, the protected value deleted is not protected anymore
+        app.run_command(UserCommand::GoToPath([(1, 3)].into())).unwrap();
         app.run_command(UserCommand::DeleteData).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [
-            " i1: 0      -int32* "]);
+        assert_eq!(protected(&app), ["m1[2]"]);
+        app.run_command(UserCommand::GoToPath([(1, 2)].into())).unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert!(app.protected.is_empty());
+        assert!(!app.is_protected(&[(1, 2)].into()));
+    }
+
+    #[test]
+    fn redraw_changed_part() {
+        let line = |text: &str| {
+            let mut line = ScreenLine::new(10);
+            line.add_string(text.into(), TextStyle::Value);
+            line
+        };
+        assert_eq!(changed_range(&line(" f1: 1 "), &line(" f1: 1 ")), None);
+        assert_eq!(changed_range(&line(" f1: 1 2 "), &line(" f1: 3 2 ")), Some((5, 6)));
+        assert_eq!(changed_range(&line(" f1: 1 2 "), &line(" f1: 1")), Some((6, 6)));
+        assert_eq!(changed_range(&line(""), &line(" f1: 1")), Some((0, 6)));
+        // both columns of a wide char
+        assert_eq!(changed_range(&line(" f1: 日本"), &line(" f1: 日木")), Some((7, 9)));
+
+        let mut selected = line(" f1: 1 ");
+        selected.0[5].1 = TextStyle::SelectedValue;
+        assert_eq!(changed_range(&line(" f1: 1 "), &selected), Some((5, 6)));
+    }
 
+    #[test]
+    fn review_before_save() {
+        let proto = ProtoData::new("message M { repeated int32 i1=1; string s2=2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set i1[0] = 1\nset i1[1] = 2\nset s2 = abc").unwrap();
+        let file_path = std::env::temp_dir().join(format!("protoedit_review_{}.pb", std::process::id()));
+        let mut file = std::fs::File::create(&file_path).unwrap();
+        data.write(&mut file, &proto, root_msg.clone()).unwrap();
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.proto = proto;
+        app.file_path = file_path.clone();
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("no changes to save"));
+
+        app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
         app.run_command(UserCommand::DeleteData).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [
-            " i1: 0      -int32* "]);
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        assert_eq!(app.to_strings(), [" ~ i1[0]: 1 → 2               ", " - i1[1] = 2                  "]);
+        assert_eq!(app.get_top_line(60, &app.layout_config), " test_data.pb             2 changes, Y - save, Esc - cancel ");
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("save canceled"));
+        assert_eq!(app.to_strings()[0], " i1: 2                 int32* ");
+
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("saved"));
+        let saved = read_data_file(&file_path, &app.proto, root_msg).unwrap();
+        assert_eq!(saved.to_string(), "message M {\n  i1 = 2\n  s2 = abc\n}\n");
+        std::fs::remove_file(&file_path).unwrap();
     }
 
+    #[test]
+    fn save_json_file() {
+        let proto = ProtoData::new("message M { int32 a=1; repeated string names=2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let file_path = std::env::temp_dir().join(format!("protoedit_json_{}.json", std::process::id()));
+        std::fs::write(&file_path, "{\"a\": 1, \"names\": [\"x\"]}").unwrap();
+        let mut data = read_data_file(&file_path, &proto, root_msg.clone()).unwrap();
+        assert_eq!(data.to_string(), "message M {\n  a = 1\n  names = x\n}\n");
+
+        // the changes are compared with the file read as json and saved in its format
+        batch::apply_script(&mut data, &proto, "set a = 2").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 60, 25).unwrap();
+        app.proto = proto;
+        app.file_path = file_path.clone();
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        assert_eq!(app.review.as_ref().unwrap().lines, ["~ a: 1 → 2"]);
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("saved"));
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "{\n  \"a\": 2,\n  \"names\": [\"x\"]\n}\n");
+
+        // saved as binary to another file, the next saves go there
+        let binary_path = file_path.with_extension("pb");
+        app.on_key(KeyEvent::new_with_kind(KeyCode::F(2), KeyModifiers::SHIFT, KeyEventKind::Press)).unwrap();
+        for c in binary_path.to_str().unwrap().chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.message.as_deref(), Some(format!("saved to {}", binary_path.display()).as_str()));
+        assert_eq!(std::fs::read(&binary_path).unwrap(), [0x08, 0x02, 0x12, 0x01, b'x']);
+        assert_eq!(app.save_target(), Some(binary_path.clone()));
+        std::fs::remove_file(&file_path).unwrap();
+        std::fs::remove_file(&binary_path).unwrap();
+    }
 
     #[test]
-    fn insert_int() {
-        fn test_fn(scroll_x: usize, scroll_y: usize, expected: Vec<&str>) {
-            let mut app = make_repeated_int_data();
+    fn compressed_file() {
+        let proto = ProtoData::new("message M { int32 a=1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let file_path = std::env::temp_dir().join(format!("protoedit_compressed_{}.pb", std::process::id()));
+        std::fs::write(&file_path, Compression::Gzip.compress(&[0x08, 0x01]).unwrap()).unwrap();
+        let mut data = read_data_file(&file_path, &proto, root_msg.clone()).unwrap();
+        assert_eq!(data.to_string(), "message M {\n  a = 1\n}\n");
+
+        // compressed again when saved, a json file keeps its format inside
+        batch::apply_script(&mut data, &proto, "set a = 2").unwrap();
+        write_data_file(&file_path, &data, &proto, false).unwrap();
+        assert_eq!(Compression::of_file(&file_path), Some(Compression::Gzip));
+        assert_eq!(compress::read(&file_path).unwrap(), [0x08, 0x02]);
+        let json_path = file_path.with_extension("json.gz");
+        write_data_file(&json_path, &data, &proto, false).unwrap();
+        assert_eq!(compress::read(&json_path).unwrap(), b"{\n  \"a\": 2\n}\n");
+        assert_eq!(read_data_file(&json_path, &proto, root_msg.clone()).unwrap().to_string(), data.to_string());
+        let zstd_path = file_path.with_extension("pb.zst");
+        write_data_file(&zstd_path, &data, &proto, false).unwrap();
+        assert_eq!(Compression::of_file(&zstd_path), Some(Compression::Zstd));
+        assert_eq!(read_data_file(&zstd_path, &proto, root_msg).unwrap().to_string(), data.to_string());
+        std::fs::remove_file(&file_path).unwrap();
+        std::fs::remove_file(&json_path).unwrap();
+        std::fs::remove_file(&zstd_path).unwrap();
+    }
 
-            for _ in 0..scroll_y {
-                app.run_command(UserCommand::ScrollVertically(1)).unwrap();
-                app.after_event().unwrap();
-            }
-            for _ in 0..scroll_x {
-                app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
-                app.after_event().unwrap();
-            }
+    #[test]
+    fn save_not_round_trip() {
+        let proto = ProtoData::new("message M { int32 a=1; int32 b=2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        // a is 1 in an overlong varint
+        let bytes = [0x08, 0x81, 0x00, 0x10, 0x01];
+        let file_path = std::env::temp_dir().join(format!("protoedit_lossy_{}.pb", std::process::id()));
+        std::fs::write(&file_path, bytes).unwrap();
+        let data = read_data_file(&file_path, &proto, root_msg.clone()).unwrap();
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 100, 25).unwrap();
+        app.proto = proto;
+        app.file_path = file_path.clone();
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("no changes to save, 1 fields would not be saved as read"));
+
+        app.go_to("b").unwrap();
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('e'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('u'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        app.on_key(press(KeyCode::Char('5'))).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        assert_eq!(app.review.as_ref().unwrap().lines, ["! a: 3 bytes in the file, 2 bytes written", "~ b: 1 → 5"]);
+        assert!(app.get_top_line(100, &app.layout_config).contains("1 changes, 1 fields not saved as read, Y - save anyway, Esc - cancel"));
+        // only Y saves
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("save canceled"));
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("saved"));
+        assert_eq!(std::fs::read(&file_path).unwrap(), [0x08, 0x01, 0x10, 0x05]);
+        std::fs::remove_file(&file_path).unwrap();
+    }
 
-            app.run_command(UserCommand::InsertData).unwrap();
-            app.after_event().unwrap();
-            assert_eq!(app.to_strings(), expected);
-        }
+    #[test]
+    fn save_mapped_file() {
+        let proto = ProtoData::new("message M { bytes b=1; int32 n=2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let file_path = std::env::temp_dir().join(format!("protoedit_mapped_{}.pb", std::process::id()));
+        std::fs::write(&file_path, [0x0a, 0x02, 0xab, 0xcd, 0x10, 0x01]).unwrap();
+        let mut data = read_mapped_file(&file_path, &proto, root_msg.clone()).unwrap();
+        let SCALAR(ScalarValue::BYTES(blob)) = &data.fields[0].value else { panic!() };
+        assert!(blob.is_shared());
+
+        batch::apply_script(&mut data, &proto, "set n = 2").unwrap();
+        replace_file(&file_path, |writer| data.write(writer, &proto, root_msg.clone())).unwrap();
+        assert_eq!(std::fs::read(&file_path).unwrap(), [0x0a, 0x02, 0xab, 0xcd, 0x10, 0x02]);
+        // the values are still read from the old content
+        let SCALAR(ScalarValue::BYTES(blob)) = &data.fields[0].value else { panic!() };
+        assert_eq!(**blob, [0xab, 0xcd]);
+        assert!(!std::env::temp_dir().join(format!("protoedit_mapped_{}.pb.tmp", std::process::id())).exists());
+        std::fs::remove_file(&file_path).unwrap();
+    }
 
-        let expected = [
-            " i1: 0 1 2 3 int32* ",
-            "  4: 4 5 6          "].to_vec();
-        test_fn(0, 0, expected);
+    #[test]
+    fn file_over_4_gib() {
+        let proto = ProtoData::new("message M { bytes b=1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let file_path = std::env::temp_dir().join(format!("protoedit_huge_{}.pb", std::process::id()));
+        // sparse, no space is taken
+        std::fs::File::create(&file_path).unwrap().set_len(5 << 30).unwrap();
+        let error = read_data_file(&file_path, &proto, root_msg).unwrap_err();
+        std::fs::remove_file(&file_path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(error.to_string(), "5368709120 bytes of data, more than the 4 GiB that can be read");
+        assert_eq!(textformat::read_limit(u32::MAX as u64).unwrap(), u32::MAX);
+    }
 
-        let expected = [
-            " i1: 1 0 2 3 int32* ",
-            "  4: 4 5 6          "].to_vec();
-        test_fn(1, 0, expected);
+    #[test]
+    fn save_data_from_stdin() {
+        let proto = ProtoData::new("message M { repeated int32 i1=1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set i1[0] = 1").unwrap();
+        let mut bytes = vec![];
+        data.write(&mut bytes, &proto, root_msg.clone()).unwrap();
 
-        let expected = [
-            " i1: 1 2 0 3 int32* ",
-            "  4: 4 5 6          "].to_vec();
-        test_fn(2, 0, expected);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.proto = proto;
+        app.file_path = "-".into();
+        app.stdin_data = Some(bytes);
+        // nothing changed, but there is no file to compare with
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        assert_eq!(app.to_strings().last().unwrap(), " save to (- for stdout):      ");
+        app.on_key(press(KeyCode::Char('-'))).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("the data will be written to stdout on exit"));
+        assert_eq!(app.stdout_data.as_deref(), Some([8u8, 1].as_slice()));
+
+        let file_path = std::env::temp_dir().join(format!("protoedit_output_{}.pb", std::process::id()));
+        app.output = Some(file_path.clone());
+        app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.after_event().unwrap();
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        assert_eq!(app.to_strings()[0], " - i1[0] = 1                  ");
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.message, Some(format!("saved to {}", file_path.display())));
+        assert_eq!(std::fs::read(&file_path).unwrap(), [0u8; 0]);
+        std::fs::remove_file(&file_path).unwrap();
+    }
 
-        let expected = [
-            " i1: 1 2 3 4 int32* ",
-            "  4: 5 0 6          "].to_vec();
-        test_fn(1, 1, expected);
+    #[test]
+    fn recovery_of_changes() {
+        let proto = ProtoData::new("message M { repeated int32 i1=1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set i1[0] = 1\nset i1[1] = 2").unwrap();
+        let file_path = std::env::temp_dir().join(format!("protoedit_recovery_{}.pb", std::process::id()));
+        data.write(&mut std::fs::File::create(&file_path).unwrap(), &proto, root_msg).unwrap();
+        let recovery_path = Journal::recovery_path(&file_path);
+        std::fs::write(&recovery_path, "# comment\nset i1[0] = 7\n").unwrap();
 
-        let expected = [
-            " i1: 1 2 3 4 int32* ",
-            "  4: 5 6 0          "].to_vec();
-        test_fn(5, 1, expected);
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.proto = proto;
+        app.file_path = file_path.clone();
+        app.journal = Journal::new(Some(&file_path));
+        app.offer_recovery().unwrap();
+        assert_eq!(app.message.as_deref(), Some("unsaved changes of a previous session found: Y - replay, other key - discard"));
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("1 changes replayed, F2 - review and save"));
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[0], " i1: 7 2               int32* ");
+
+        app.run_command(UserCommand::ScrollHorizontally(1)).unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        assert_eq!(app.journal.commands, ["set i1[0] = 7", "delete i1[0]"]);
+        app.journal.write().unwrap();
+        assert_eq!(std::fs::read_to_string(&recovery_path).unwrap().lines().skip(1).collect::<Vec<_>>(), ["set i1[0] = 7", "delete i1[0]"]);
+        app.after_event().unwrap();
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("saved"));
+        assert!(!recovery_path.exists());
+
+        std::fs::write(&recovery_path, "delete i1[0]\n").unwrap();
+        app.offer_recovery().unwrap();
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("the changes of the previous session are discarded"));
+        assert!(!recovery_path.exists());
+        std::fs::remove_file(&file_path).unwrap();
     }
 
-    //    #[test]
-    //    fn insert_int() {
-    //        let mut data = make_no_field_data("message M { repeated int32 i1=1; }");
-    //
-    //        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-    //        assert_eq!(app.to_strings(), [" i1: 0                -int32* "]);
-    //
-    //        app.run_command(UserCommand::InsertData).unwrap();
-    //        app.after_event().unwrap();
-    //        assert_eq!(app.to_strings(), [" i1: 0                 int32* "]);
-    //
-    //        app.run_command(UserCommand::InsertData).unwrap();
-    //        app.after_event().unwrap();
-    //        assert_eq!(app.to_strings(), [" i1: 0 0               int32* "]);
-    //    }
+    #[test]
+    fn reload_changed_file() {
+        let proto = ProtoData::new("message M { N n=1; N k=2; int32 b=3; }\nmessage N { int32 a=1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let write = |script: &str| {
+            let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+            batch::apply_script(&mut data, &proto, script).unwrap();
+            data
+        };
+        let file_path = std::env::temp_dir().join(format!("protoedit_reload_{}.pb", std::process::id()));
+        let data = write("set n.a = 1\nset k.a = 2\nset b = 3");
+        data.write(&mut std::fs::File::create(&file_path).unwrap(), &proto, root_msg.clone()).unwrap();
+
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.file_path = file_path.clone();
+        app.run_command(UserCommand::GoToPath([(3, 0)].into())).unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.run_command(UserCommand::GoToPath([(2, 0)].into())).unwrap();
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        app.run_command(UserCommand::GoToPath([(3, 0)].into())).unwrap();
+        app.after_event().unwrap();
 
+        // not changed since it was read
+        app.disk_time = modified_time(&file_path);
+        app.check_disk().unwrap();
+        assert!(!app.reload_offer);
+        write("set n.a = 5\nset k.a = 6\nset b = 7").write(&mut std::fs::File::create(&file_path).unwrap(), &proto, root_msg.clone()).unwrap();
+        app.disk_time = Some(SystemTime::UNIX_EPOCH);
+        app.proto = proto;
+        app.check_disk().unwrap();
+        assert_eq!(app.message.as_deref(), Some("the file is changed on disk: Y - reload (1 unsaved changes are lost), other key - keep the data"));
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("reloaded"));
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [" n:                         N ", "   a: 5                 int32 ", " k: ... 2                   N ", " b: 7                   int32 "]);
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("b"));
+        assert!(app.journal.commands.is_empty());
+        // asked once for each change
+        app.check_disk().unwrap();
+        assert!(!app.reload_offer);
+        std::fs::remove_file(&file_path).unwrap();
+    }
 
     #[test]
-    fn repeated_multiline_int() {
-        fn test_data() -> MessageData {
-            let mut data = make_one_field_data("message M { repeated int32 i1=1; }", I32(2));
-            for v in 2..10 {
-                data.add_field(&[(1, usize::MAX).into()]).unwrap().value = FieldValue::SCALAR(I32(v * 2));
-            }
-            data
-        }
-        {
-            let mut app = App::for_tests(test_data(), FieldOrder::Proto, 25, 25).unwrap();
-            let expected = [
-                " i1: 2 4 6 8 10   int32* ",
-                "  5: 12 14 16 18         "];
-            assert_eq!(app.to_strings(), expected);
-        }
-        {
-            let mut app = App::for_tests(test_data(), FieldOrder::Proto, 26, 25).unwrap();
-            let expected = [
-                " i1: 2 4 6 8 10 12 int32* ",
-                "  6: 14 16 18             "];
-            assert_eq!(app.to_strings(), expected);
-        }
+    fn breadcrumbs() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 60, 25).unwrap();
+        app.proto = ProtoData::new(TEST_PROTO_1).unwrap().finalize().unwrap();
+        app.run_command(UserCommand::GoToPath([(3, 0), (6, 1), (9, 0)].into())).unwrap();
+        assert!(app.get_top_line(80, &app.layout_config).starts_with(" test_data.pb > m3 > m6[1] > f9 "));
+        assert!(app.get_top_line(60, &app.layout_config).starts_with(" … > m3 > m6[1] > f9 "));
+
+        app.run_keys(&harness::parse_keys("Alt+U").unwrap()).unwrap();
+        let report = app.report.as_ref().unwrap();
+        assert_eq!(report.title, "m3.m6[1].f9, Enter - go to, Ctrl+C - copy the path, Esc - close");
+        assert_eq!(report.lines.iter().map(|(line, _)| line.as_str()).collect::<Vec<_>>(), ["test_data.pb", "  m3", "    m6[1]", "      f9"]);
+        app.run_keys(&harness::parse_keys("Ctrl+C").unwrap()).unwrap();
+        assert_eq!(app.clipboard, "m3.m6[1].f9");
+        assert_eq!(app.message.as_deref(), Some("m3.m6[1].f9 copied"));
+
+        // a parent is selected
+        app.run_keys(&harness::parse_keys("Alt+U Up Enter").unwrap()).unwrap();
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("m3.m6[1]"));
+
+        // the path of a focused message is copied from the root of the document
+        app.run_keys(&harness::parse_keys("Ctrl+Right Alt+U Ctrl+C").unwrap()).unwrap();
+        assert_eq!(app.clipboard, "m3.m6[1].f8");
+        app.run_keys(&harness::parse_keys("Up").unwrap()).unwrap();
+        assert!(app.get_top_line(80, &app.layout_config).starts_with(" test_data.pb > m3.m6[1] > f8 "));
     }
 
     #[test]
-    fn nested_repeated_strings() {
-        let proto_str = "message M { M2 m2 = 2; }\nmessage M2 { repeated string s1 = 1; }";
-        let binary_input = [
-            0x12, 8,  //              m2: M2
-            0x0A, 2, 0x77, 0x77, //     s1: 2 "ww"
-            0x0A, 2, 0x78, 0x78, //     s1: 2 "xx"
-        ];
+    fn wire_status_in_top_line() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        assert_eq!(app.get_top_line(50, &app.layout_config), " test_data.pb > f1     #1 VARINT 0x0 +2      0% P ");
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.run_command(UserCommand::ScrollHorizontally(2)).unwrap();
+        assert_eq!(app.get_top_line(50, &app.layout_config), " … > f2[1]    2 of 2 #2 VARINT 0x4 +2     25% P ");
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        assert_eq!(app.get_top_line(50, &app.layout_config), " test_data.pb > m3      #3 LEN 0x6 +18      50% P ");
+        app.run_command(UserCommand::ScrollVertically(9)).unwrap();
+        assert_eq!(app.get_top_line(50, &app.layout_config), " test_data.pb > f4            #4            75% P ");
+    }
 
-        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+    #[test]
+    fn dump_as_text() {
+        // n: 5, s: "hi", an unknown field 3
+        let binary_input = [0x08, 0x05, 0x12, 0x02, b'h', b'i', 0x18, 0x07];
+        let proto = ProtoData::new("message M { int32 n = 1; string s = 2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("M").unwrap();
+        let data = MessageData::new(&mut PbReader::new(binary_input.as_slice()), &proto, root_msg, &mut (binary_input.len() as u32)).unwrap();
+        let (mut out, mut err) = (vec![], vec![]);
+        run_dump(&data, &proto, &mut out, &mut err).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "n: 5\ns: \"hi\"\n");
+        assert_eq!(String::from_utf8(err).unwrap(), "1 unknown field left out\n");
+    }
+
+    #[test]
+    fn localized_values() {
+        // t: 2023-11-14 22:13:20.5 UTC, d: 2.5, n: 1234567
+        let binary_input = [0x0a, 0x0c, 0x08, 0x80, 0xe2, 0xcf, 0xaa, 0x06, 0x10, 0x80, 0xca, 0xb5, 0xee, 0x01,
+            0x11, 0, 0, 0, 0, 0, 0, 0x04, 0x40, 0x18, 0x87, 0xad, 0x4b];
+        let proto = ProtoData::new("message M { Timestamp t = 1; double d = 2; int32 n = 3; }\n\
+            message Timestamp { int64 seconds = 1; int32 nanos = 2; }").unwrap().finalize().unwrap();
+        let mut limit = binary_input.len() as u32;
+        let root_msg = proto.get_message_definition("M").unwrap();
+        let data = MessageData::new(&mut PbReader::new(binary_input.as_slice()), &proto, root_msg, &mut limit).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        app.proto = proto;
+        config::apply("digits = space\ndecimal = comma\ntimestamps = +01:00", &mut app.layout_config).unwrap();
+        app.config_changed();
+        assert_eq!(app.to_strings(), [
+            " t: 2023-11-14 23:13:20.5 +01:00        Timestamp ",
+            " seconds: 1 700 000 000                     int64 ",
+            "   nanos: 500 000 000                       int32 ",
+            " d: 2,5                                    double ",
+            " n: 1 234 567                               int32 "]);
+        app.run_command(CollapsedToggle).unwrap();
+        assert_eq!(app.to_strings()[..2], [
+            " t: 2023-11-14 23:13:20.5 +01:00        Timestamp ",
+            " d: 2,5                                    double "]);
+
+        // edited in the canonical format
+        assert_eq!(app.edit_text(&FieldPath(vec![FieldPos { id: 2, index: 0 }])).as_deref(), Some("2.5"));
+        assert_eq!(app.edit_text(&FieldPath(vec![FieldPos { id: 3, index: 0 }])).as_deref(), Some("1234567"));
+    }
+
+    #[test]
+    fn held_key_scrolls_faster() {
+        // a line for each value in the wire order
+        let binary_input: Vec<u8> = (0..200).flat_map(|i| [if i % 2 == 0 { 0x08 } else { 0x10 }, i as u8 & 0x7f]).collect();
+        let proto = ProtoData::new("message M { repeated int32 a = 1; repeated int32 b = 2; }").unwrap().finalize().unwrap();
         let mut limit = binary_input.len() as u32;
         let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+        let data = MessageData::new(&mut PbReader::new(binary_input.as_slice()), &proto, root_msg, &mut limit).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Wire, 30, 12).unwrap();
+        app.proto = proto;
+        let index = |app: &App| app.selected.layout;
+        let start = Instant::now();
+        let mut press_at = |app: &mut App, ms: u64, code: KeyCode| {
+            app.key_time = Some(start + Duration::from_millis(ms));
+            app.on_key(press(code)).unwrap();
+        };
 
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        let expected = [
-            " m2:                       M2 ",
-            "   s1: 'ww'           string* ",
-            "   s1: 'xx'           string* "];
-        assert_eq!(app.to_strings(), expected);
+        // the step doubles every 8 repeats, up to a page of 10 lines
+        assert_eq!(app.data_height(), 11);
+        for i in 0..16 { press_at(&mut app, i * 30, KeyCode::Down); }
+        assert_eq!(index(&app), 8 + 8 * 2);
+        for i in 16..32 { press_at(&mut app, i * 30, KeyCode::Down); }
+        assert_eq!(index(&app), 24 + 8 * 4 + 8 * 8);
+        press_at(&mut app, 32 * 30, KeyCode::Down);
+        assert_eq!(index(&app), 120 + app.data_height() as usize - 1);
+
+        // a pause or the other direction starts from one line
+        let index_before = index(&app);
+        press_at(&mut app, 2000, KeyCode::Down);
+        press_at(&mut app, 2030, KeyCode::Up);
+        press_at(&mut app, 2060, KeyCode::Up);
+        assert_eq!(index(&app), index_before - 1);
+
+        // keys without the time of reading, as replayed by macros, scroll by one line
+        for _ in 0..20 { app.on_key(press(KeyCode::Up)).unwrap(); }
+        assert_eq!(index(&app), index_before - 21);
     }
 
     #[test]
-    fn nested_repeated_int() {
-        let proto_str = "message M { M2 m2 = 2; }\nmessage M2 { repeated int32 i1 = 1; }";
-        let binary_input = [
-            0x12, 4, // m2: M2
-            0x08, 1, //   i1: 1
-            0x08, 2, //   i1: 2
-        ];
-
-        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
+    fn wire_conflict_marks() {
+        // a: "hi" of an int32 field, b: 5 of a string field, c: 7 as declared
+        let binary_input = [0x0a, 0x02, 0x68, 0x69, 0x10, 0x05, 0x18, 0x07];
+        let proto = ProtoData::new("message M { int32 a = 1; string b = 2; int32 c = 3; }").unwrap().finalize().unwrap();
         let mut limit = binary_input.len() as u32;
         let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+        let data = MessageData::new(&mut PbReader::new(binary_input.as_slice()), &proto, root_msg, &mut limit).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Wire, 90, 25).unwrap();
+        app.proto = proto;
+        let plain = app.to_strings();
+        assert!(plain.iter().all(|line| line.starts_with(' ')), "{:?}", plain);
+
+        app.run_keys(&harness::parse_keys("Alt+X").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("values of another wire type than declared are marked"));
+        let lines = app.to_strings();
+        assert!(lines[0].starts_with("!a: 1.2: 68 69") && lines[0].ends_with(" read as LEN (string, bytes, message or packed), declared VARINT int32 "), "{:?}", lines);
+        assert!(lines[1].starts_with("!b: 2.0: 05") && lines[1].contains(" read as VARINT (integer, bool or enum), declared LEN "), "{:?}", lines);
+        assert_eq!(lines[2], plain[2]);
+
+        app.run_keys(&harness::parse_keys("Alt+X").unwrap()).unwrap();
+        assert_eq!(app.to_strings(), plain);
+    }
 
+    #[test]
+    fn collapse_to_depth() {
+        let data = make_test_data_1();
         let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        let expected = [
-            " m2:                       M2 ",
-            "   i1: 1 2             int32* "];
-        assert_eq!(app.to_strings(), expected);
+        let top_level = [
+            " f1: 1                  int32 ",
+            " f2: 20 21             int32* ",
+            " m3: ... 14                M3 ",
+            " f4: 0                 -int32 "];
+        let second_level = [
+            " f1: 1                  int32 ",
+            " f2: 20 21             int32* ",
+            " m3:                       M3 ",
+            "   f5: 5                int32 ",
+            "   m6: ... 4              M6* ",
+            "   m6: ... 4              M6* ",
+            "   f7: 7                int32 ",
+            " f4: 0                 -int32 "];
+        app.run_command(UserCommand::CollapseToDepth(2)).unwrap();
+        assert_eq!(app.to_strings(), second_level);
+        app.run_command(UserCommand::CollapseToDepth(1)).unwrap();
+        assert_eq!(app.to_strings(), top_level);
+        app.run_command(UserCommand::CollapseToDepth(9)).unwrap();
+        assert_eq!(app.to_strings().len(), 12);
     }
 
-
     #[test]
-    fn nested_long_name() {
-        let proto_str = "message M { M2 m2 = 2; }\nmessage M2 { M3 m3 = 2; int32 longname = 3; }\nmessage M3 { M4 m4 = 2; }\nmessage M4 { int32 i = 1; }";
-        let binary_input = [
-            0x12, 4, // m2: M2
-            0x12, 2, // m3: M3
-            0x12, 0, // m4: M4
-        ];
+    fn expand_collapse_all() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.run_command(UserCommand::ScrollVertically(2)).unwrap();
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        assert_eq!(app.to_strings().len(), 4);
 
-        let proto = ProtoData::new(proto_str).unwrap().finalize().unwrap();
-        let mut limit = binary_input.len() as u32;
-        let root_msg = proto.auto_detect_root_message().unwrap();
-        let mut read = PbReader::new(binary_input.as_slice());
-        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut limit).unwrap();
+        // the selected message shows only its fields
+        app.run_command(UserCommand::CollapseAll).unwrap();
+        assert_eq!(app.to_strings(), [
+            " f1: 1                  int32 ",
+            " f2: 20 21             int32* ",
+            " m3:                       M3 ",
+            "   f5: 5                int32 ",
+            "   m6: ... 4              M6* ",
+            "   m6: ... 4              M6* ",
+            "   f7: 7                int32 ",
+            " f4: 0                 -int32 "]);
+        assert_eq!(app.selected.layout, 2);
+
+        app.run_command(UserCommand::ExpandAll).unwrap();
+        assert_eq!(app.to_strings().len(), 12);
+
+        // not a message
+        app.run_command(UserCommand::ScrollVertically(-1)).unwrap();
+        app.run_command(UserCommand::CollapseAll).unwrap();
+        assert_eq!(app.to_strings().len(), 12);
+    }
 
-        //println!("{:?}", proto);
+    #[test]
+    fn go_to_path_prompt() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 8).unwrap();
+        app.proto = ProtoData::new("message M { int32 f1 = 1; repeated int32 f2 = 2; M3 m3 = 3; int32 f4 = 4; }\n\
+            message M3 { int32 f5 = 5; repeated M6 m6 = 6; int32 f7 = 7; }\nmessage M6 { int32 f8 = 8; int32 f9 = 9; }").unwrap().finalize().unwrap();
+        app.run_command(UserCommand::CollapseToDepth(1)).unwrap();
+        let ctrl_g = KeyEvent::new_with_kind(KeyCode::Char('g'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.on_key(ctrl_g).unwrap();
+        for c in "m3.m7".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        assert_eq!(app.to_strings(), [
+            " f1: 1                  int32 ",
+            " f2: 20 21             int32* ",
+            " m3: ... 14                M3 ",
+            " f4: 0                 -int32 ",
+            "                              ",
+            "                              ",
+            " go to: m3.m7  field \"m7\" not ",
+        ]);
+        // Enter is ignored while the path is wrong
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert!(app.prompt.is_some());
+
+        app.on_key(press(KeyCode::Backspace)).unwrap();
+        for c in "6[1].f9".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert!(app.prompt.is_none());
+        assert_eq!(format!("{:?}", app.layouts.items[app.selected.layout].path), format!("{:?}", FieldPath::from([(3, 0), (6, 1), (9, 0)])));
+        assert_eq!(app.to_strings()[2..7], [
+            " m3:                       M3 ",
+            "   f5: 5                int32 ",
+            "   m6:                    M6* ",
+            "     f8: 8              int32 ",
+            "     f9: 9              int32 ",
+        ]);
+
+        // the entered path is recalled with Up
+        app.on_key(ctrl_g).unwrap();
+        app.on_key(press(KeyCode::Up)).unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().text(), "m3.m6[1].f9");
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        assert!(app.prompt.is_none());
+    }
 
+    #[test]
+    fn string_wrap_toggle() {
+        let proto = "message M { string s1=1; int32 i2=2; }";
+        let data = make_one_field_data(proto, STR("abcdefghijklmnopqrstuvwxyz\nend".into()));
         let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        let wrapped = app.to_strings();
+        assert_eq!(wrapped.len(), 4);
 
-        println!("{:?}", app.layouts.indents);
+        app.on_key(press(KeyCode::F(7))).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [
+            " s1: 'abcdefghijklmno… string ",
+            " i2: 0                 -int32 "]);
+        app.on_key(press(KeyCode::F(7))).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), wrapped);
 
-        let expected = [
-            " m2:                       M2 ",
-            "       m3:                 M3 ",
-            "         m4:               M4 ",
-            "            i: 0       -int32 ",
-            " longname: 0           -int32 "];
-        assert_eq!(app.to_strings(), expected);
+        // all strings
+        app.on_key(KeyEvent::new_with_kind(KeyCode::F(7), KeyModifiers::SHIFT, KeyEventKind::Press)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings().len(), 2);
     }
 
     #[test]
-    fn empty_string() {
-        let mut data = make_no_field_data("message M {  string s1=1; }");
+    fn next_set_field() {
+        let proto = ProtoData::new("message M { int32 f1=1; int32 f2=2; N n=3; int32 f4=4; }\nmessage N { int32 a=1; int32 b=2; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set f2 = 0\nset n.b = 5").unwrap();
         let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" s1: ''               -string "]);
+        let alt = |code| KeyEvent::new_with_kind(code, KeyModifiers::ALT, KeyEventKind::Press);
+        let selected = |app: &App| app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).unwrap();
+        for path in ["f2", "n", "n.b"] {
+            app.on_key(alt(KeyCode::Down)).unwrap();
+            assert_eq!(selected(&app), path);
+        }
+        app.on_key(alt(KeyCode::Down)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("no set fields below"));
+        assert_eq!(selected(&app), "n.b");
+        app.on_key(alt(KeyCode::Up)).unwrap();
+        app.on_key(alt(KeyCode::Up)).unwrap();
+        assert_eq!(selected(&app), "f2");
+        app.on_key(alt(KeyCode::Up)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("no set fields above"));
     }
 
-
     #[test]
-    fn insert_string() {
-        let mut data = make_one_field_data("message M { repeated string s1=1; }", STR("1".to_string()));
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        app.to_strings();
-        app.run_command(UserCommand::InsertData).unwrap();
+    fn range_delete_undo() {
+        let proto = ProtoData::new("message M { repeated N n = 1; int32 b = 2; }\nmessage N { int32 x = 1; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set n[0].x = 0\nset n[1].x = 1\nset n[2].x = 2\nset n[3].x = 3\nset b = 9").unwrap();
+        let original = data.to_string();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 20).unwrap();
+        app.proto = proto;
+        let key = |code, modifiers| KeyEvent::new_with_kind(code, modifiers, KeyEventKind::Press);
+        app.go_to("n[1].x").unwrap();
+        app.on_key(key(KeyCode::Down, KeyModifiers::SHIFT)).unwrap();
+        app.on_key(key(KeyCode::Down, KeyModifiers::SHIFT)).unwrap();
         app.after_event().unwrap();
-        let expected = [
-            " s1: ''               string* ", // default value inserted
-            " s1: '1'              string* "];
-        assert_eq!(app.to_strings(), expected);
+        assert!(app.get_top_line(30, &app.layout_config).contains("3 selected"));
+        let marked: Vec<String> = app.to_strings().into_iter().filter(|line| line.starts_with(RANGE_MARK)).collect();
+        assert_eq!(marked, [">n:                        N* ", ">  x: 1                 int32 ", ">n:                        N* ", ">  x: 2                 int32 ",
+            ">n:                        N* ", ">  x: 3                 int32 "]);
+
+        app.on_key(key(KeyCode::Char('c'), KeyModifiers::CONTROL)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("3 values copied as edit commands"));
+        assert_eq!(app.clipboard, "insert n[1]\ninsert n[1].x = 1\ninsert n[2]\ninsert n[2].x = 2\ninsert n[3]\ninsert n[3].x = 3");
+
+        app.on_key(key(KeyCode::Delete, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("3 values deleted"));
+        assert_eq!(app.data.to_string(), "message M {\n  n = message N {\n  x = 0\n}\n\n  b = 9\n}\n");
+        assert_eq!(app.journal.commands, ["delete n[3]", "delete n[2]", "delete n[1]"]);
+
+        // the step is undone at once, the content of the messages is restored
+        app.on_key(key(KeyCode::Char('z'), KeyModifiers::CONTROL)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("3 changes undone"));
+        assert_eq!(app.data.to_string(), original);
+        assert_eq!(app.journal.commands[3..], ["insert n[1]", "insert n[1].x = 1", "insert n[2]", "insert n[2].x = 2", "insert n[3]", "insert n[3].x = 3"]);
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[2..4], [" n:                        N* ", "   x: 1                 int32 "]);
+        app.on_key(key(KeyCode::Char('z'), KeyModifiers::ALT)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("3 changes redone"));
+        assert_eq!(app.data.to_string(), "message M {\n  n = message N {\n  x = 0\n}\n\n  b = 9\n}\n");
+        app.on_key(key(KeyCode::Char('z'), KeyModifiers::CONTROL)).unwrap();
+        app.on_key(key(KeyCode::Char('z'), KeyModifiers::CONTROL)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("nothing to undo"));
     }
 
-
     #[test]
-    fn delete_repeated_string() {
-        let mut data = make_one_field_data("message M { repeated string s1=1; }", STR("1".to_string()));
-        data.add_field(&[(1, 1).into()]).unwrap().value = FieldValue::SCALAR(STR("2".to_string()));
+    fn drill_down() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 40, 25).unwrap();
+        let before = app.data.to_string();
+        let key = |code, modifiers| KeyEvent::new_with_kind(code, modifiers, KeyEventKind::Press);
+        app.run_command(UserCommand::GoToPath([(3, 0), (6, 1)].into())).unwrap();
+        app.on_key(key(KeyCode::Right, KeyModifiers::CONTROL)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[..2], [" f8: 10                           int32 ", " f9: 11                           int32 "]);
+        assert!(app.get_top_line(80, &app.layout_config).contains(" > m3.m6[1] "));
+
+        // paths are relative to the focused message, the journal and the watch list use the full ones
+        app.run_command(UserCommand::GoToPath([(9, 0)].into())).unwrap();
+        app.on_key(key(KeyCode::Char('w'), KeyModifiers::CONTROL)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("m3.m6[1].f9 is watched"));
+        app.on_key(key(KeyCode::Delete, KeyModifiers::NONE)).unwrap();
+        assert_eq!(app.journal.commands, ["delete m3.m6[1].f9"]);
+        app.after_event().unwrap();
 
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        let expected = [
-            " s1: '1'              string* ",
-            " s1: '2'              string* "];
-        assert_eq!(app.to_strings(), expected);
+        assert!(app.on_key(key(KeyCode::Esc, KeyModifiers::NONE)).unwrap());
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[8..10], ["     f8: 10                       int32 ", "     f9: 0                       -int32 "]);
+        assert!(!app.data.to_string().contains("f9 = 11"));
+        app.on_key(key(KeyCode::Char('z'), KeyModifiers::CONTROL)).unwrap();
+        assert_eq!(app.data.to_string(), before);
+        assert!(!app.on_key(key(KeyCode::Esc, KeyModifiers::NONE)).unwrap());
+    }
 
+    #[test]
+    fn undo_edit() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 50, 25).unwrap();
+        let before = app.data.to_string();
         app.run_command(UserCommand::DeleteData).unwrap();
+        assert_ne!(app.data.to_string(), before);
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('z'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("1 change undone"));
+        assert_eq!(app.data.to_string(), before);
+    }
+
+    #[test]
+    fn encoding_marks() {
+        let proto = ProtoData::new("message M { sint32 s=1; repeated sfixed64 f=2; double d=3; int32 i=4; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set s = -5\nset f[0] = -1\nset d = -0.5\nset i = -3").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 10).unwrap();
+        let ctrl_k = KeyEvent::new_with_kind(KeyCode::Char('k'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.on_key(ctrl_k).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [" s1: '2'              string* "]);
+        assert_eq!(app.to_strings()[..4], [" s: -5               sint32~z ", " f: -1            sfixed64~f* ",
+            " d: -0.5             double~f ", " i: -3                  int32 "]);
+        app.on_key(ctrl_k).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[0], " s: -5                 sint32 ");
+    }
 
-        app.run_command(UserCommand::DeleteData).unwrap();
+    #[test]
+    fn indents_fit_screen() {
+        let proto = ProtoData::new("message M { repeated N n = 1; int32 a_very_long_field_name = 2; }\nmessage N { int32 x = 1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        let script: Vec<String> = (0..40).map(|i| format!("set n[{}].x = {}", i, i)).collect();
+        batch::apply_script(&mut data, &proto, &(script.join("\n") + "\nset a_very_long_field_name = 5")).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 40, 6).unwrap();
+        let narrow = app.layouts.indents[0];
+        assert!(narrow < 10);
+
+        // the long name widens the column before it is shown
+        app.run_keys(&harness::parse_keys("Ctrl+End").unwrap()).unwrap();
+        let wide = app.layouts.indents[0];
+        assert_eq!(wide, 1 + "a_very_long_field_name".len() as u16);
+        assert!(app.to_strings().last().unwrap().starts_with(" a_very_long_field_name: 5"));
+        // and keeps it until the name is two screens away
+        app.run_keys(&harness::parse_keys("PgUp PgUp").unwrap()).unwrap();
+        app.to_strings();
+        assert_eq!(app.layouts.indents[0], wide);
+        app.run_keys(&harness::parse_keys("Ctrl+Home").unwrap()).unwrap();
+        let lines = app.to_strings();
+        assert_eq!(app.layouts.indents[0], narrow);
+        assert_eq!(lines[..2], [" n:                                  N* ", "   x:+0                           int32 "]);
+    }
+
+    #[test]
+    fn sizes_cached() {
+        let mut app = App::for_tests(make_repeated_message_data(1000), FieldOrder::Proto, 30, 10).unwrap();
+        app.to_strings();
+        let sized = |app: &App, key| app.layouts.items.iter().filter(|item| item.sized == Some(key)).count();
+        let visible = sized(&app, (30, 0));
+        assert!(visible > 0 && visible < 20);
+
+        // only the edited layout is sized again
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        let ctrl = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.on_key(ctrl('e')).unwrap();
+        app.on_key(ctrl('u')).unwrap();
+        app.on_key(press(KeyCode::Char('7'))).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.data.get_field(&[(1, 0).into(), (2, 0).into()]).unwrap().value, SCALAR(I32(7)));
+        assert_eq!(sized(&app, (30, 0)), visible);
+        assert_eq!(app.layouts.revision, 0);
+
+        // the visible layouts are sized for the new width
+        app.set_sizes(40, 10);
+        app.to_strings();
+        let resized = sized(&app, (40, 0));
+        assert!(resized > 0 && resized <= visible);
+        // the sidebar changes the width, not the config
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('b'), KeyModifiers::ALT, KeyEventKind::Press)).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [" s1: ''              -string* "]);
+        assert_eq!(app.layouts.revision, 0);
+        // the comments shown change the config, all the layouts are sized again
+        app.on_key(press(KeyCode::F(6))).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.layouts.revision, 1);
     }
 
     #[test]
-    fn empty_enum() {
-        let mut data = make_no_field_data("enum E1 { V1=0; V2=1; }\nmessage M { E1 e1=1; }");
+    fn non_ascii_strings() {
+        let proto = "message M { string s1=1; }";
+        let data = make_one_field_data(proto, STR("исполняющий обязанности премьер-министра".into()));
         let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" e1: V1                   -E1 "]);
+        assert_eq!(app.to_strings(), [" s1: исполняющий обяза string ", "   : нности премьер-министра  "]);
+
+        // wide chars take two columns
+        let data = make_one_field_data(proto, STR("日本語のテキストです".into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        assert_eq!(app.to_strings(), [" s1: 日本語のテキスト  string ", "   : です                     "]);
+        let lines = app.compose_screen();
+        assert!(lines.iter().all(|line| line.0.len() == 30));
+        app.on_key(press(KeyCode::F(7))).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [" s1: '日本語のテキス…  string "]);
     }
 
     #[test]
-    fn repeated_enum() {
-        let mut data = make_one_field_data("enum E1 { V1=0; V2=1; }\nmessage M { repeated E1 e1=1; }", ENUM(1));
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" e1: V2                   E1* "]);
+    fn repeated_group_jumps() {
+        let data = make_test_data_1();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
+        let selected_path = |app: &App| format!("{:?}", app.layouts.selected_path(&app.selected).unwrap());
+        app.run_command(UserCommand::ScrollVertically(5)).unwrap();
+        assert_eq!(selected_path(&app), format!("{:?}", FieldPath::from([(3, 0), (6, 0), (8, 0)])));
+        assert!(app.get_top_line(50, &app.layout_config).contains(" 1 of 2 #8 "));
+        app.on_key(press(KeyCode::Char(']'))).unwrap();
+        assert_eq!(selected_path(&app), format!("{:?}", FieldPath::from([(3, 0), (6, 1)])));
+        app.on_key(press(KeyCode::Char('['))).unwrap();
+        assert_eq!(selected_path(&app), format!("{:?}", FieldPath::from([(3, 0), (6, 0)])));
+
+        // values of a repeated scalar
+        app.selected = Selection::default();
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.on_key(press(KeyCode::Char(']'))).unwrap();
+        assert_eq!(selected_path(&app), format!("{:?}", FieldPath::from([(2, 1)])));
+        assert_eq!((app.selected.x, app.selected.y), (2, 0));
 
-        app.run_command(UserCommand::InsertData).unwrap();
+        app.selected = Selection::default();
+        app.on_key(press(KeyCode::Char('['))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("the field is not in a repeated one"));
+    }
+
+    #[test]
+    fn repeated_stats() {
+        let mut app = make_repeated_int_data();
+        app.on_key(press(KeyCode::F(8))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("i1: count 6, min 1, max 6, mean 3.5, sum 21"));
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 50, 25).unwrap();
+        app.on_key(press(KeyCode::F(8))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("select a repeated numeric field"));
+    }
+
+    #[test]
+    fn packed_toggle() {
+        let mut app = make_repeated_int_data();
+        let ctrl_p = KeyEvent::new_with_kind(KeyCode::Char('p'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.on_key(ctrl_p).unwrap();
+        assert_eq!(app.message.as_deref(), Some("i1 will be saved packed"));
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), [" e1: V1 V2                E1* "]);
+        assert_eq!(app.to_strings(), [" i1: 1 2 3  int32*p ", "  3: 4 5 6          "]);
+        app.on_key(ctrl_p).unwrap();
+        assert_eq!(app.message.as_deref(), Some("i1 will be saved unpacked"));
+
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 50, 25).unwrap();
+        app.on_key(ctrl_p).unwrap();
+        assert_eq!(app.message.as_deref(), Some("select a repeated numeric field"));
     }
 
     #[test]
-    fn empty_float() {
-        let mut data = make_no_field_data("message M { float f1=1; }");
+    fn schema_audit() {
+        let proto = ProtoData::new("message M { repeated int32 i1=1; N n=2; }\nmessage N { string s=1; int32 x=2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set i1[0] = 1\nset i1[1] = 2\nset n.s = a\nset n.x = 3").unwrap();
+        let schema = std::env::temp_dir().join(format!("protoedit_audit_{}.proto", std::process::id()));
+        std::fs::write(&schema, "message M { repeated sint32 i1=1; N n=2; }\nmessage N { string s=1; }").unwrap();
+
         let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" f1: 0                 -float "]);
+        app.proto = proto;
+        let ctrl_n = KeyEvent::new_with_kind(KeyCode::Char('n'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.on_key(ctrl_n).unwrap();
+        for c in schema.to_str().unwrap().chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.to_strings(), [" ~ i1[0]: int32 → sint32      ", " ? n.x: becomes unknown       "]);
+        assert!(app.get_top_line(100, &app.layout_config).contains(" 2 fields differ in protoedit_audit_"));
+
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert!(app.report.is_none());
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).unwrap(), "n.x");
+
+        std::fs::write(&schema, "message M { repeated int32 i1=1; N n=2; }\nmessage N { string s=1; int32 x=2; }").unwrap();
+        app.on_key(ctrl_n).unwrap();
+        for c in schema.to_str().unwrap().chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert!(app.report.is_none());
+        assert!(app.message.as_deref().unwrap().starts_with("the data is read the same way with "));
+        std::fs::remove_file(&schema).unwrap();
     }
 
     #[test]
-    fn simple_double() {
-        let mut data = make_one_field_data("message M { double f1=1; }", F64(11.1));
+    fn schema_reload() {
+        let proto = ProtoData::new("message M { int32 a=1; N n=3; }\nmessage N { int32 x=1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        // s is not in the schema yet
+        let bytes = [0x08, 0x01, 0x12, 0x02, b'h', b'i', 0x1a, 0x02, 0x08, 0x05];
+        let data = read_data_bytes(&bytes, &proto, root_msg).unwrap();
+        let schema = std::env::temp_dir().join(format!("protoedit_reload_{}.proto", std::process::id()));
+        std::fs::write(&schema, "message M { int32 a=1; string s=2; N n=3; }\nmessage N { int32 x=1; }").unwrap();
+
         let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" f1: 11.1              double "]);
+        app.proto = proto;
+        app.proto_file = schema.clone();
+        app.run_keys(&harness::parse_keys("Down Down Down Alt+L").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("schema reloaded"));
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).unwrap(), "n.x");
+        let mut written = vec![];
+        app.data.write(&mut written, &app.proto, app.data.def.clone()).unwrap();
+        assert_eq!(written, bytes);
+        assert_eq!(app.data.to_string(), "message M {\n  a = 1\n  s = hi\n  n = message N {\n  x = 5\n}\n\n}\n");
+
+        // x becomes unknown, a is read as another type
+        std::fs::write(&schema, "message M { sint32 a=1; string s=2; N n=3; }\nmessage N { }").unwrap();
+        app.run_keys(&harness::parse_keys("Alt+L").unwrap()).unwrap();
+        let report = app.report.as_ref().unwrap();
+        assert_eq!(report.title, "schema reloaded, 2 fields read differently, Enter - go to, Esc - close");
+        assert_eq!(report.lines.iter().map(|(line, _)| line.as_str()).collect::<Vec<_>>(), ["~ a: int32 → sint32", "? n.x: becomes unknown"]);
+        app.run_keys(&harness::parse_keys("Esc").unwrap()).unwrap();
+        let mut written = vec![];
+        app.data.write(&mut written, &app.proto, app.data.def.clone()).unwrap();
+        assert_eq!(written, bytes);
+
+        // the formats of the fields are kept
+        app.run_keys(&harness::parse_keys("Ctrl+Home Alt+O Ctrl+U h e x Enter").unwrap()).unwrap();
+        assert_eq!(app.to_strings()[0], " a: -0x1               sint32 ");
+        app.run_keys(&harness::parse_keys("Alt+L").unwrap()).unwrap();
+        assert_eq!(app.to_strings()[0], " a: -0x1               sint32 ");
+        std::fs::write(&schema, "message M { string s=2; N n=3; }\nmessage N { }").unwrap();
+        app.run_keys(&harness::parse_keys("Alt+L Esc").unwrap()).unwrap();
+        assert!(app.layout_config.field_formats.is_empty());
+
+        std::fs::write(&schema, "message Other { }").unwrap();
+        app.run_keys(&harness::parse_keys("Alt+L").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some(format!("error: no root message M in {}", schema.display()).as_str()));
+        std::fs::remove_file(&schema).unwrap();
     }
 
     #[test]
-    fn scroll_repeated_message() {
-        let mut data = make_repeated_message_data(3);
-        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 3 + TOP_LINE).unwrap();
+    fn selection_sidebar() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 100, 6).unwrap();
+        app.run_keys(&harness::parse_keys("Down Alt+B").unwrap()).unwrap();
+        let lines = app.to_strings();
+        assert_eq!(lines.len(), 5);
+        assert!(lines.iter().all(|line| line.chars().count() == 100 && line.chars().nth(56) == Some('│')));
+        let sidebar: Vec<&str> = lines.iter().map(|line| line.split_once('│').unwrap().1.trim_end()).collect();
+        assert_eq!(sidebar, [
+            "   path        f2[0]",
+            "   type        repeated int32",
+            "   size        2 B",
+            "",
+            "   wire bytes  10 14",
+        ]);
+        assert!(lines[1].starts_with(" f2: 20 21"));
+
+        // on a narrow screen the data takes all the columns
+        app.set_sizes(60, 6);
+        assert!(app.to_strings().iter().all(|line| !line.contains('│')));
+        app.run_keys(&harness::parse_keys("Alt+B Alt+B").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("the sidebar is shown on screens of 100 columns and wider"));
+    }
 
-        let expected0 = [
-            " m1:            M2* ",
-            "   i2: 2      int32 ",
-            "   i3: 3      int32 "];
-        assert_eq!(app.to_strings(), expected0);
+    #[test]
+    fn detail_pane() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 160, 6).unwrap();
+        app.run_keys(&harness::parse_keys("Down Alt+B Alt+V").unwrap()).unwrap();
+        let lines = app.to_strings();
+        assert!(lines.iter().all(|line| line.chars().count() == 160 && line.chars().nth(80) == Some('│')));
+        assert_eq!(lines[0].split_once('│').unwrap().1.trim_end(), "   path        f2[0]");
+
+        // the divider moves by 4 columns and stops at the least width of the data and of the pane
+        app.run_keys(&harness::parse_keys("Alt+Left").unwrap()).unwrap();
+        assert_eq!(app.layouts.width, 76);
+        app.run_keys(&harness::parse_keys(&["Alt+Left"; 20].join(" ")).unwrap()).unwrap();
+        assert_eq!(app.layouts.width, TREE_MIN_WIDTH);
+        app.run_keys(&harness::parse_keys(&["Alt+Right"; 40].join(" ")).unwrap()).unwrap();
+        assert_eq!(app.layouts.width, 160 - PANE_MIN_WIDTH);
+
+        // the sidebar is back when the pane is hidden, neither is shown on a narrow screen
+        app.run_keys(&harness::parse_keys("Alt+V").unwrap()).unwrap();
+        assert_eq!(app.layouts.width, 160 - SIDEBAR_WIDTH);
+        app.set_sizes(110, 6);
+        app.run_keys(&harness::parse_keys("Alt+B Alt+V").unwrap()).unwrap();
+        assert_eq!(app.layouts.width, 110);
+        assert_eq!(app.message.as_deref(), Some("the detail pane is shown on screens of 120 columns and wider"));
+    }
 
-        app.run_command(ScrollVertically(1)).unwrap(); // next line
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected0);
+    #[test]
+    fn set_siblings() {
+        let proto = ProtoData::new("enum S { OK = 0; ERROR = 1; }\nmessage Order { repeated Item items = 1; }\n\
+            message Item { int32 price = 1; S status = 2; Info info = 3; }\nmessage Info { string note = 1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Order").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set items[0].price = 5\nset items[1].price = 2000\nset items[2].status = ERROR\n\
+            set items[3].price = 2000\nset items[1].info.note = \"x\"").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 60, 20).unwrap();
+        app.proto = proto;
+        let value = |app: &App, text: &str| {
+            let path = FieldPath::parse(text, &app.data.def, &app.proto).unwrap();
+            match app.data.get_field(&path.0).map(|field| &field.value) {
+                Some(FieldValue::SCALAR(scalar)) => Some(scalar.clone()),
+                _ => None,
+            }
+        };
 
-        app.run_command(ScrollVertically(1)).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected0);
+        app.go_to("items[1].price").unwrap();
+        app.run_keys(&harness::parse_keys("Alt+A").unwrap()).unwrap();
+        let report = app.report.as_ref().unwrap();
+        assert_eq!(report.title, "price = 2000 in 2 of 3 other items values, 1 the same, Y - apply, Enter - go to, Esc - cancel");
+        assert_eq!(report.lines.iter().map(|(line, _)| line.as_str()).collect::<Vec<_>>(), ["items[0].price: 5 → 2000", "items[2].price: not set → 2000"]);
+        app.run_keys(&harness::parse_keys("Esc").unwrap()).unwrap();
+        assert!(app.report.is_none());
+        assert_eq!(value(&app, "items[0].price"), Some(ScalarValue::I32(5)));
+
+        app.run_keys(&harness::parse_keys("Alt+A \"y\"").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("price set in 2 values"));
+        assert_eq!(value(&app, "items[0].price"), Some(ScalarValue::I32(2000)));
+        assert_eq!(value(&app, "items[2].price"), Some(ScalarValue::I32(2000)));
+        app.run_keys(&harness::parse_keys("Ctrl+Z").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("2 changes undone"));
+        assert_eq!(value(&app, "items[2].price"), None);
+
+        // the missing parent messages are inserted
+        app.go_to("items[1].info.note").unwrap();
+        app.run_keys(&harness::parse_keys("Alt+A \"y\"").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("note set in 3 values"));
+        assert_eq!(value(&app, "items[3].info.note"), Some(ScalarValue::STR("x".into())));
+        assert_eq!(app.history.undo.last().unwrap().commands[4..], ["insert items[3].info", "insert items[3].info.note = \"x\""]);
+
+        app.go_to("items[1]").unwrap();
+        app.run_keys(&harness::parse_keys("Alt+A").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("error: a value set in the data expected"));
+    }
 
-        app.run_command(ScrollVertically(1)).unwrap(); // scroll one line down
-        app.after_event().unwrap();
-        let expected1 = [
-            "   i2: 2      int32 ",
-            "   i3: 3      int32 ",
-            " m1:            M2* "];
-        assert_eq!(app.to_strings(), expected1);
+    #[test]
+    fn highlight_values() {
+        let proto = ProtoData::new("enum S { OK = 0; ERROR = 1; }\nmessage Order { repeated Item items = 1; }\n\
+            message Item { int32 price = 1; S status = 2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Order").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set items[0].price = 5\nset items[1].price = 2000\nset items[2].status = ERROR").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 40, 12).unwrap();
+        app.proto = proto;
+        let highlighted = |app: &mut App| app.compose_screen().iter().
+            map(|line| line.0.iter().any(|(_, style)| *style == TextStyle::Highlight)).collect::<Vec<bool>>();
+
+        app.run_keys(&harness::parse_keys("Down Down Alt+K \"price > 1000\" Enter").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("1 of 3 Item values highlighted"));
+        let lines = app.to_strings();
+        assert!(lines[4].trim().starts_with("price: 2000"));
+        assert_eq!(highlighted(&mut app)[..8], [false, false, false, false, true, true, false, false]);
+        app.run_keys(&harness::parse_keys("Alt+K \"status == ERROR\" Enter").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("2 of 3 Item values highlighted"));
+        assert_eq!(highlighted(&mut app).iter().filter(|line| **line).count(), 4);
+
+        app.run_keys(&harness::parse_keys("Alt+K \"price >\" Enter").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("error: unexpected end of expression"));
+        app.run_keys(&harness::parse_keys("Alt+K Enter").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("the Item values are not highlighted"));
+        assert!(highlighted(&mut app).iter().all(|line| !line));
+    }
 
-        app.run_command(ScrollVertically(-1)).unwrap();
-        app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected1);
+    #[test]
+    fn macro_replay() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 30, 25).unwrap();
+        let ctrl = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::CONTROL, KeyEventKind::Press);
+        let type_text = |app: &mut App, text: &str| {
+            for c in text.chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+            app.on_key(press(KeyCode::Enter)).unwrap();
+        };
+        app.on_key(ctrl('y')).unwrap();
+        assert_eq!(app.message.as_deref(), Some("no macros recorded, Ctrl+T - record"));
 
-        app.run_command(ScrollVertically(-1)).unwrap();
+        app.run_command(UserCommand::GoToPath([(3, 0), (6, 0)].into())).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected1);
+        app.on_key(ctrl('t')).unwrap();
+        type_text(&mut app, "zero");
+        assert_eq!(app.message.as_deref(), Some("recording zero, Ctrl+T - stop"));
+        app.on_key(press(KeyCode::Down)).unwrap();
+        assert!(app.get_top_line(50, &app.layout_config).contains("REC P"));
+        app.on_key(ctrl('e')).unwrap();
+        app.on_key(ctrl('u')).unwrap();
+        type_text(&mut app, "0");
+        app.on_key(ctrl('t')).unwrap();
+        assert_eq!(app.message.as_deref(), Some("macro zero recorded, 5 keys"));
+        assert_eq!(app.data.get_field(&[(3, 0).into(), (6, 0).into(), (8, 0).into()]).unwrap().value, SCALAR(I32(0)));
+        assert_eq!(app.data.get_field(&[(3, 0).into(), (6, 1).into(), (8, 0).into()]).unwrap().value, SCALAR(I32(10)));
+
+        Arc::make_mut(&mut app.data).get_field_mut(&[(3, 0).into(), (6, 0).into(), (8, 0).into()]).unwrap().value = SCALAR(I32(1));
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('y'), KeyModifiers::ALT, KeyEventKind::Press)).unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().text(), "zero");
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("macro zero replayed for 2 values of m6"));
+        assert_eq!(app.data.get_field(&[(3, 0).into(), (6, 0).into(), (8, 0).into()]).unwrap().value, SCALAR(I32(0)));
+        assert_eq!(app.data.get_field(&[(3, 0).into(), (6, 1).into(), (8, 0).into()]).unwrap().value, SCALAR(I32(0)));
+    }
 
-        app.run_command(ScrollVertically(-1)).unwrap(); // scroll one line up
+    #[test]
+    fn watch_list() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 30, 8).unwrap();
+        let ctrl_w = KeyEvent::new_with_kind(KeyCode::Char('w'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        app.run_command(UserCommand::ScrollHorizontally(2)).unwrap();
+        app.on_key(ctrl_w).unwrap();
+        assert_eq!(app.message.as_deref(), Some("f2[1] is watched"));
+        app.run_command(UserCommand::ScrollVertically(3)).unwrap();
+        app.on_key(ctrl_w).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected0);
+        assert_eq!(app.to_strings(), [" f2: 20 21             int32* ", " m3:                       M3 ", "   f5: 5                int32 ", "   m6:                    M6* ",
+                                      " watch (2) ───────────────────", " f2[1] → 21                   ", " m3.m6[0] → message M6        "]);
+        // values are updated after changes
+        Arc::make_mut(&mut app.data).get_field_mut(&[(2, 1).into()]).unwrap().value = SCALAR(I32(7));
+        assert_eq!(app.to_strings()[5], " f2[1] → 7                    ");
+        app.on_key(ctrl_w).unwrap();
+        assert_eq!(app.message.as_deref(), Some("m3.m6[0] is not watched"));
+        assert_eq!(app.to_strings()[5..], [" watch (1) ───────────────────", " f2[1] → 7                    "]);
+    }
 
-        app.run_command(ScrollVertically(99)).unwrap(); // scroll to end
+    #[test]
+    fn pinned_fields() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 30, 6).unwrap();
+        app.run_keys(&harness::parse_keys("Alt+T").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("f1 is pinned"));
+        assert_eq!(app.to_strings(), [" f1: 1                        ", "──────────────────────────────", " f1: 1                  int32 ",
+                                      " f2: 20 21             int32* ", " m3:                       M3 "]);
+        // the pinned field stays while the data scrolls, the values are updated after changes
+        app.run_keys(&harness::parse_keys("Down Down Down Down").unwrap()).unwrap();
+        Arc::make_mut(&mut app.data).get_field_mut(&[(1, 0).into()]).unwrap().value = SCALAR(I32(7));
+        let lines = app.to_strings();
+        assert_eq!(lines[..2], [" f1: 7                        ", "──────────────────────────────"]);
+        assert_eq!(lines.len(), 5);
+        assert!(!lines[2..].iter().any(|line| line.starts_with(" f1:")));
+
+        // clicks below the pinned fields select the data
+        app.run_keys(&harness::parse_keys("Ctrl+Home").unwrap()).unwrap();
+        app.on_mouse(MouseEvent { kind: MouseEventKind::Down(MouseButton::Left), column: 3, row: TOP_LINE + 3, modifiers: KeyModifiers::NONE }).unwrap();
+        assert_eq!(app.selected_text(), "f2[0]");
+        app.run_keys(&harness::parse_keys("Up Alt+T").unwrap()).unwrap();
+        assert_eq!(app.message.as_deref(), Some("f1 is not pinned"));
+        assert_eq!(app.to_strings()[0], " f1: 7                  int32 ");
+    }
+
+    #[test]
+    fn pins_follow_the_value() {
+        let mut app = App::for_tests(make_repeated_message_data(4), FieldOrder::Proto, 30, 25).unwrap();
+        let names = |fields: &[(FieldPath, String)]| fields.iter().map(|(_, name)| name.clone()).collect::<Vec<_>>();
+        app.run_command(UserCommand::GoToPath([(1, 2), (2, 0)].into())).unwrap();
+        app.run_keys(&harness::parse_keys("Alt+T Ctrl+W").unwrap()).unwrap();
+        let pinned = app.to_strings()[0].clone();
+        assert!(pinned.starts_with(" m1[2].i2: "), "{}", pinned);
+
+        // the value above deleted: the same value is shown with its new path
+        app.run_command(UserCommand::GoToPath([(1, 0)].into())).unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        assert_eq!((names(&app.pins), names(&app.watches)), (vec!["m1[1].i2".to_string()], vec!["m1[1].i2".to_string()]));
+        assert_eq!(app.to_strings()[0], pinned.replacen("m1[2]", "m1[1]", 1));
+        app.run_keys(&harness::parse_keys("Ctrl+Z").unwrap()).unwrap();
+        assert_eq!(app.to_strings()[0], pinned);
+
+        // the pinned value deleted
+        app.run_command(UserCommand::GoToPath([(1, 2)].into())).unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        assert!(app.pins.is_empty() && app.watches.is_empty());
+    }
+
+    #[test]
+    fn hex_dump_of_field() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 50, 25).unwrap();
+        let dump_path = std::env::temp_dir().join(format!("protoedit_dump_{}.hex", std::process::id()));
+        app.run_command(UserCommand::ScrollVertically(2)).unwrap();
+        app.on_key(press(KeyCode::F(9))).unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().text(), "test_data.pb.hex");
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('u'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        for c in dump_path.to_str().unwrap().chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.message, Some(format!("saved to {}", dump_path.display())));
+        let text = std::fs::read_to_string(&dump_path).unwrap();
+        std::fs::remove_file(&dump_path).unwrap();
+        assert_eq!(text, "# m3 #3 LEN 0x6 +18\n\
+                          00000006  1a 10 28 05 32 04 40 08  48 09 32 04 40 0a 48 0b  |..(.2.@.H.2.@.H.|\n\
+                          00000016  38 07                                             |8.|\n");
+    }
+
+    #[test]
+    fn open_at_path_or_text() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 50, 25).unwrap();
+        let selected_path = |app: &App| format!("{:?}", app.layouts.selected_path(&app.selected).unwrap());
+        app.find("10").unwrap();
+        assert_eq!(selected_path(&app), format!("{:?}", FieldPath::from([(3, 0), (6, 1), (8, 0)])));
+        app.find("100").unwrap();
+        assert_eq!(app.message.as_deref(), Some("\"100\" not found"));
+        app.go_to("f2[1]").unwrap();
+        assert_eq!(selected_path(&app), format!("{:?}", FieldPath::from([(2, 1)])));
+        app.go_to("f3").unwrap();
+        assert_eq!(app.message.as_deref(), Some("error: field \"f3\" not found in message M"));
+    }
+
+    #[test]
+    fn read_only_mode() {
+        let mut app = make_repeated_int_data();
+        app.width = 40;
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('r'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("read-only mode"));
+        app.on_key(press(KeyCode::Right)).unwrap();
+        app.on_key(press(KeyCode::Delete)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("error: read-only mode, Ctrl+R to allow changes"));
+        app.on_key(press(KeyCode::F(2))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("error: read-only mode, Ctrl+R to allow changes"));
+        assert_eq!(app.to_strings()[0], " i1: 1 2 3 4 int32* ");
+        app.message = None;
+        assert!(app.get_top_line(40, &app.layout_config).ends_with("% RO P "));
+
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('r'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        app.on_key(press(KeyCode::Delete)).unwrap();
+        assert_eq!(app.to_strings()[0], " i1: 2 3 4 5 int32* ");
+    }
+
+    #[test]
+    fn value_renderers() {
+        let data = make_one_field_data("message M { bytes f1=1; }", BYTES(vec![10, 0, 0, 1].into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 40, 25).unwrap();
+        app.set_renderers(Renderers(vec![("f1".into(), Renderer::IpAddress)]));
         app.after_event().unwrap();
-        let expected_end = [
-            " m1:            M2* ",
-            "   i2: 6      int32 ",
-            "   i3: 7      int32 "];
-        assert_eq!(app.to_strings(), expected_end);
+        assert_eq!(app.to_strings(), [" f1: '10.0.0.1'                   bytes "]);
+
+        let ctrl_e = KeyEvent::new_with_kind(KeyCode::Char('e'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        app.on_key(ctrl_e).unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().text(), "10.0.0.1");
+        app.on_key(press(KeyCode::Char('x'))).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert!(app.prompt.is_some());
+        app.on_key(press(KeyCode::Backspace)).unwrap();
+        app.on_key(press(KeyCode::Backspace)).unwrap();
+        app.on_key(press(KeyCode::Char('2'))).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.data.get_field(&[(1, 0).into()]).unwrap().value, SCALAR(BYTES(vec![10, 0, 0, 2].into())));
+        // not in the renderer format
+        Arc::make_mut(&mut app.data).get_field_mut(&[(1, 0).into()]).unwrap().value = SCALAR(BYTES(vec![1, 2].into()));
+        assert_eq!(app.to_strings(), [" f1: '01 02'                      bytes "]);
+
+        let data = make_one_field_data("message M { string f1=1; }", STR(r#"{"a":[1]}"#.into()));
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.set_renderers(Renderers(vec![("string".into(), Renderer::JsonPretty)]));
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings(), [" f1: {                 string ", "  2:   \"a\": [                 ",
+                                      "  3:     1                    ", "  4:   ]                      ", "  5: }                        "]);
+    }
 
-        app.run_command(ScrollVertically(1)).unwrap();
+    #[test]
+    fn edit_value_overflow() {
+        let mut app = App::for_tests(make_one_field_data("message M { int32 f1=1; }", I32(5)), FieldOrder::Proto, 30, 25).unwrap();
+        let ctrl_e = KeyEvent::new_with_kind(KeyCode::Char('e'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        let enter_value = |app: &mut App, text: &str| {
+            app.on_key(ctrl_e).unwrap();
+            app.on_key(KeyEvent::new_with_kind(KeyCode::Char('u'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+            for c in text.chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+            app.on_key(press(KeyCode::Enter)).unwrap();
+        };
+        app.on_key(ctrl_e).unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().text(), "5");
+        app.on_key(press(KeyCode::Esc)).unwrap();
+
+        enter_value(&mut app, "-7");
+        assert_eq!(app.to_strings()[0], " f1: -7                 int32 ");
+        enter_value(&mut app, "x");
+        assert!(app.prompt.is_some());
+        app.on_key(press(KeyCode::Esc)).unwrap();
+
+        enter_value(&mut app, "3000000000");
+        assert_eq!(app.message.as_deref(), Some("3000000000 is out of the int32 range -2147483648..2147483647: C - clamp, W - wrap, U - show as unsigned, other key - cancel"));
+        app.on_key(press(KeyCode::Char('n'))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("canceled"));
+        assert_eq!(app.to_strings()[0], " f1: -7                 int32 ");
+
+        enter_value(&mut app, "3000000000");
+        app.on_key(press(KeyCode::Char('c'))).unwrap();
+        assert_eq!(app.to_strings()[0], " f1: 2147483647         int32 ");
+        enter_value(&mut app, "3000000000");
+        app.on_key(press(KeyCode::Char('w'))).unwrap();
+        assert_eq!(app.to_strings()[0], " f1: -1294967296        int32 ");
+        enter_value(&mut app, "3000000000");
+        app.on_key(press(KeyCode::Char('u'))).unwrap();
+        assert_eq!(app.to_strings()[0], " f1: 3000000000         int32 ");
+
+        enter_value(&mut app, "-0x1_00");
+        // shown as unsigned after U
+        assert_eq!(app.to_strings()[0], " f1: 4294967040         int32 ");
+        // the range is shown while typing, Enter still offers the choices
+        app.on_key(ctrl_e).unwrap();
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('u'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        for c in "3e9".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        assert_eq!(app.prompt.as_ref().unwrap().get_screen(50, &app.layout_config).0.iter().map(|c| c.0).collect::<String>().trim_end(), " f1: 3e9  not a int32 value");
+        app.on_key(press(KeyCode::Backspace)).unwrap();
+        app.on_key(press(KeyCode::Backspace)).unwrap();
+        for c in "_000_000_000".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        assert_eq!(app.prompt.as_ref().unwrap().get_screen(100, &app.layout_config).0.iter().map(|c| c.0).collect::<String>().trim_end(),
+                   " f1: 3_000_000_000  3_000_000_000 is out of the int32 range -2147483648..2147483647");
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert!(app.message.as_deref().unwrap().starts_with("3_000_000_000 is out of the int32 range"));
+    }
+
+    #[test]
+    fn edit_float_keeps_bits() {
+        let nan = f64::from_bits(0x7FF8_0000_0000_0001);
+        let mut app = App::for_tests(make_one_field_data("message M { double f1=1; }", F64(nan)), FieldOrder::Proto, 40, 25).unwrap();
+        let ctrl_e = KeyEvent::new_with_kind(KeyCode::Char('e'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        let bits = |app: &App| match &app.data.fields[0].value { FieldValue::SCALAR(F64(v)) => v.to_bits(), _ => 0 };
+        app.on_key(ctrl_e).unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().text(), "NaN");
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(bits(&app), 0x7FF8_0000_0000_0001);
+
+        app.on_key(ctrl_e).unwrap();
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('u'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        for c in "1e20".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert!(app.to_strings()[0].starts_with(" f1: 1e20 "));
+        app.layout_config.float_format = FloatFormat::Decimal;
+        app.config_changed();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected_end);
+        assert!(app.to_strings()[0].starts_with(" f1: 100000000000000000000 "));
+        app.on_key(ctrl_e).unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().text(), "100000000000000000000");
+    }
 
-        app.run_command(ScrollVertically(-99)).unwrap(); // scroll to start
+    #[test]
+    fn help_screen() {
+        let mut app = App::for_tests(make_one_field_data("message M { int32 f1=1; }", I32(5)), FieldOrder::Proto, 50, 6).unwrap();
+        app.on_key(press(KeyCode::F(1))).unwrap();
+        app.after_event().unwrap();
+        let lines = app.to_strings();
+        assert!(app.get_top_line(80, &app.layout_config).ends_with("keys, Up/Down - scroll, other key - close "));
+        assert_eq!(lines[0].trim_end(), " Navigation");
+        assert_eq!(lines[1].trim_end(), "   Up                   line up");
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[0].trim_end(), "   Up                   line up");
+        app.on_key(press(KeyCode::Esc)).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected0);
+        assert!(app.help.is_none());
+        assert!(app.to_strings()[0].starts_with(" f1: 5"));
+    }
 
-        app.run_command(ScrollVertically(-1)).unwrap();
+    #[test]
+    fn crash_context() {
+        let mut app = App::for_tests(make_one_field_data("message M { int32 f1=1; }", I32(5)), FieldOrder::Proto, 50, 6).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), expected0);
+        assert_eq!(app.selected_text(), "f1");
+        let ctrl_e = KeyEvent::new_with_kind(KeyCode::Char('e'), KeyModifiers::CONTROL, KeyEventKind::Press);
+        assert_eq!(app.key_text(&ctrl_e), "Ctrl+E - edit the value");
+        app.on_key(ctrl_e).unwrap();
+        // typed in the prompt
+        assert_eq!(app.key_text(&press(KeyCode::Char('e'))), "e");
     }
 
     #[test]
-    fn scroll_up_repeated_message() {
-        let mut data = make_repeated_message_data(10);
-        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 3 + TOP_LINE).unwrap();
-
-        let expected_start = [
-            " m1:            M2* ",
-            "   i2: 2      int32 ",
-            "   i3: 3      int32 "];
-        assert_eq!(app.to_strings(), expected_start);
-
-        app.run_command(ScrollToBottom).unwrap(); // end of the file
+    fn overview_screen() {
+        let proto = ProtoData::new("message M { repeated N items = 1; int32 f2 = 2; }\nmessage N { string s = 1; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set items[0].s = a\nset items[1].s = abcdef\nset f2 = 1").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 60, 12).unwrap();
+        app.proto = proto;
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('x'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
         app.after_event().unwrap();
+        assert!(app.get_top_line(80, &app.layout_config).contains("17 B in the data, Enter - go to, Esc - close"));
+        let lines = app.to_strings();
+        assert_eq!(lines[0].trim_end(), " Message types");
+        assert_eq!(lines[1].trim_end(), "   N                                                   2");
+        // Enter on the largest value
+        for _ in 0..5 { app.on_key(press(KeyCode::Down)).unwrap(); }
+        assert_eq!(app.report.as_ref().unwrap().lines[6].1.as_deref(), Some("items[1]"));
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        app.after_event().unwrap();
+        assert!(app.report.is_none());
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("items[1]"));
+    }
 
-        let expected_end = [
-            " m1:            M2* ",
-            "   i2: 20     int32 ",
-            "   i3: 21     int32 "];
-        assert_eq!(app.to_strings(), expected_end);
-
-        app.run_command(ScrollVertically(-3)).unwrap(); // scroll up
+    #[test]
+    fn go_to_record() {
+        let proto = ProtoData::new("message M { string name = 1; repeated N recs = 2; }\nmessage N { int32 a = 1; repeated int32 b = 2; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set name = x\nset recs[0].a = 1\nset recs[1].a = 2\nset recs[1].b[0] = 7\nset recs[2].a = 3").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 80, 12).unwrap();
+        app.proto = proto;
+        let alt_g = KeyEvent::new_with_kind(KeyCode::Char('g'), KeyModifiers::ALT, KeyEventKind::Press);
+        app.on_key(alt_g).unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().get_screen(60, &app.layout_config).0.iter().map(|c| c.0).collect::<String>().trim_end(), " go to recs (1-3):");
+        app.on_key(press(KeyCode::Char('4'))).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert!(app.prompt.is_some());
+        app.on_key(press(KeyCode::Backspace)).unwrap();
+        app.on_key(press(KeyCode::Char('3'))).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
         app.after_event().unwrap();
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("recs[2]"));
+        assert!(app.get_top_line(80, &app.layout_config).contains("3 of 3"));
 
-        let expected_end = [
-            "   i3: 19     int32 ",
-            " m1:            M2* ",
-            "   i2: 20     int32 "];
-        assert_eq!(app.to_strings(), expected_end);
+        app.go_to("recs[1].a").unwrap();
+        assert!(app.get_top_line(80, &app.layout_config).contains("2 of 3"));
+        app.go_to("recs[1].b[0]").unwrap();
+        assert!(app.get_top_line(80, &app.layout_config).contains("record 2/3 1 of 1"));
     }
 
     #[test]
-    fn scroll_empty() {
-        let mut data = make_no_field_data("message M { string f1=1; }");
-        let mut app = App::for_tests(data, FieldOrder::Wire, 30, 25).unwrap();
-        app.to_strings();
-        app.run_command(UserCommand::ScrollVertically(-1)).unwrap();
+    fn pages() {
+        let proto = ProtoData::new("message M { repeated N recs = 1; repeated string tags = 2; }\nmessage N { int32 a = 1; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        let script = (0..25).map(|i| format!("set recs[{}].a = {}\n", i, i)).chain((0..12).map(|i| format!("set tags[{}] = t{}\n", i, i))).collect::<String>();
+        batch::apply_script(&mut data, &proto, &script).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 60, 60).unwrap();
+        app.proto = proto;
+        app.layout_config.page_size = 10;
+        app.rebuild_layouts(&FieldPath::new());
+        assert_eq!(app.layouts.items.len(), 10 + 1 + 10 + 1);
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), Vec::<String>::new());
-        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
+        let lines = app.to_strings();
+        assert!(lines[20].contains("recs: ▼ 15 values after, Enter - next page"));
+        assert!(lines[31].contains("tags: ▼ 2 values after, Enter - next page"));
+
+        // the next page, the first value of it is selected
+        app.go_to("recs[9].a").unwrap();
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), Vec::<String>::new());
-        app.run_command(UserCommand::ScrollSibling(1)).unwrap();
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("recs[10]"));
+        assert!(app.to_strings()[0].contains("recs: ▲ 10 values before, Enter - previous page"));
+
+        // going to a value shows its page, the value is edited there
+        app.go_to("recs[23].a").unwrap();
+        assert!(app.to_strings().iter().any(|line| line.contains("recs: ▲ 20 values before")));
+        app.on_key(press(KeyCode::Up)).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), Vec::<String>::new());
-        app.run_command(UserCommand::ScrollSibling(-1)).unwrap();
+        app.on_key(press(KeyCode::Delete)).unwrap();
         app.after_event().unwrap();
-        assert_eq!(app.to_strings(), Vec::<String>::new());
+        assert_eq!(app.data.fields.iter().filter(|field| field.id() == 1).count(), 24);
+        assert!(app.to_strings().iter().any(|line| line.contains("recs: ▲ 20 values before")));
+
+        // the page rows are not values
+        app.go_to("recs[20]").unwrap();
+        app.on_key(press(KeyCode::Up)).unwrap();
+        assert!(app.layouts.items[app.selected.layout].is_page());
+        assert!(app.layouts.selected_path(&app.selected).is_none());
+        app.on_key(press(KeyCode::Delete)).unwrap();
+        assert_eq!(app.data.fields.iter().filter(|field| field.id() == 1).count(), 24);
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("recs[19]"));
     }
 
+    #[test]
+    fn schema_browser() {
+        let proto = ProtoData::new("message M { string name = 1; repeated N recs = 2; }\nmessage N { int32 a = 1; Kind kind = 2; }\nenum Kind { SMALL = 0; BIG = 1; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set name = x\nset recs[0].a = 1\nset recs[1].kind = BIG").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 80, 20).unwrap();
+        app.proto = proto;
+        let alt_p = KeyEvent::new_with_kind(KeyCode::Char('p'), KeyModifiers::ALT, KeyEventKind::Press);
+        let selected = |app: &App| { let report = app.report.as_ref().unwrap(); report.lines[report.selected].0.trim_end().to_string() };
+
+        // the definition of the selected field
+        app.go_to("recs[1].kind").unwrap();
+        app.on_key(alt_p).unwrap();
+        assert_eq!(selected(&app), "      2 kind                 Kind");
+        assert!(app.get_top_line(120, &app.layout_config).contains("schema, type to search, Tab - next"));
+
+        for c in "big".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        assert_eq!(selected(&app), "      1 BIG");
+        app.on_key(press(KeyCode::Char('x'))).unwrap();
+        assert!(app.get_top_line(120, &app.layout_config).contains("search: bigx not found"));
+        for _ in 0..4 { app.on_key(press(KeyCode::Backspace)).unwrap(); }
+        // the first data value of the field
+        for c in "  1 a".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        assert_eq!(selected(&app), "      1 a                    int32");
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert!(app.report.is_none());
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("recs[0].a"));
+
+        // the next line found with Tab, Esc closes
+        app.on_key(alt_p).unwrap();
+        for c in "message".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        assert_eq!(selected(&app), "message M");
+        app.on_key(press(KeyCode::Tab)).unwrap();
+        assert_eq!(selected(&app), "message N");
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        assert!(app.report.is_none());
+    }
 
     #[test]
-    fn change_field_order() {
-        let mut data = make_one_field_data("message M { int32 x=2; int32 y=1; }", ScalarValue::I32(3));
-        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 25).unwrap();
+    fn new_document_of_root() {
+        let path = std::env::temp_dir().join(format!("protoedit-new-{}.proto", std::process::id()));
+        std::fs::write(&path, "message A { B b = 1; }\nmessage B { int32 n = 1; }\nmessage C { }").unwrap();
+        let (proto, data, file, stdin_data, _) = new_document(&format!("{};B", path.display()), &[]).unwrap();
+        assert_eq!(data.def.name, "B");
+        assert!(data.fields.is_empty());
+        assert_eq!((file.as_str(), stdin_data), ("-", Some(vec![])));
+        assert_eq!(data.def.fields.len(), 1);
+
+        // saving asks for the file
+        let mut app = App::for_tests(data, FieldOrder::Proto, 80, 20).unwrap();
+        app.proto = proto;
+        app.stdin_data = Some(vec![]);
+        app.go_to("n").unwrap();
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('e'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        app.on_key(press(KeyCode::Char('7'))).unwrap();
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(app.data.get_field(&[(1, 0).into()]).unwrap().value, SCALAR(I32(7)));
+        app.save().unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().name, SAVE_PROMPT);
+        std::fs::remove_file(&path).unwrap();
+    }
 
-        let expected_start = [
-            " x: 0        -int32 ",
-            " y: 3         int32 "];
-        assert_eq!(app.to_strings(), expected_start);
+    #[test]
+    fn encoding_panel() {
+        let proto = ProtoData::new("message M { int32 a = 1; sint32 b = 2; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set b = -3").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 80, 20).unwrap();
+        app.proto = proto;
+        let alt_w = KeyEvent::new_with_kind(KeyCode::Char('w'), KeyModifiers::ALT, KeyEventKind::Press);
+        app.go_to("a").unwrap();
+        app.on_key(alt_w).unwrap();
+        assert_eq!(app.message.as_deref(), Some("the field has no data"));
+        app.go_to("b").unwrap();
+        app.on_key(alt_w).unwrap();
+        let lines: Vec<String> = app.report.as_ref().unwrap().lines.iter().map(|(text, _)| text.trim_end().to_string()).collect();
+        assert_eq!(lines[..2], ["  wire bytes  10 05", "  tag         field 2, VARINT"]);
+        assert!(lines.contains(&"* sint32         -3".to_string()));
+        assert!(app.get_top_line(120, &app.layout_config).contains("wire encoding of b"));
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        assert!(app.report.is_none());
+    }
 
-        app.run_command(UserCommand::ChangeFieldOrder(FieldOrder::ById)).unwrap();
-        app.after_event().unwrap();
-        let expected_start = [
-            " y: 3         int32 ",
-            " x: 0        -int32 "
-        ];
-        assert_eq!(app.to_strings(), expected_start);
+    #[test]
+    fn history_panel() {
+        let proto = ProtoData::new("message M { int32 a = 1; int32 b = 2; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set a = 1\nset b = 2").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 80, 20).unwrap();
+        app.proto = proto;
+        let ctrl = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::CONTROL, KeyEventKind::Press);
+        let alt_h = KeyEvent::new_with_kind(KeyCode::Char('h'), KeyModifiers::ALT, KeyEventKind::Press);
+        app.on_key(alt_h).unwrap();
+        assert_eq!(app.message.as_deref(), Some("no changes"));
+        for (path, value) in [("a", '5'), ("b", '6'), ("a", '7')] {
+            app.go_to(path).unwrap();
+            app.on_key(ctrl('e')).unwrap();
+            app.on_key(ctrl('u')).unwrap();
+            app.on_key(press(KeyCode::Char(value))).unwrap();
+            app.on_key(press(KeyCode::Enter)).unwrap();
+        }
+        let lines = |app: &App| app.report.as_ref().unwrap().lines.iter().map(|(text, _)| text.clone()).collect::<Vec<_>>();
+        app.on_key(alt_h).unwrap();
+        assert_eq!(lines(&app), ["   1. set a: 1 → 5", "   2. set b: 2 → 6", "   3. set a: 5 → 7"]);
+        assert!(app.get_top_line(120, &app.layout_config).contains("3 changes, Del - revert"));
+
+        // the first change is changed again by the last one
+        app.on_key(press(KeyCode::Up)).unwrap();
+        app.on_key(press(KeyCode::Up)).unwrap();
+        app.on_key(press(KeyCode::Delete)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("error: change 1 is changed by change 3, undo it first"));
+        assert_eq!(app.data.get_field(&[(1, 0).into()]).unwrap().value, SCALAR(I32(7)));
+
+        // a change in the middle, the revert is a new change
+        app.on_key(alt_h).unwrap();
+        app.on_key(press(KeyCode::Up)).unwrap();
+        app.on_key(press(KeyCode::Delete)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("change 2 reverted"));
+        assert_eq!(app.data.get_field(&[(2, 0).into()]).unwrap().value, SCALAR(I32(2)));
+        app.on_key(alt_h).unwrap();
+        assert_eq!(lines(&app), ["   1. set a: 1 → 5", "   2. set a: 5 → 7", "   3. set b: 6 → 2"]);
+
+        // the commands are saved as a script
+        app.on_key(ctrl('s')).unwrap();
+        assert!(app.prompt.is_some());
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        let file_path = std::env::temp_dir().join(format!("protoedit_history_{}.edits", std::process::id()));
+        assert_eq!(app.export_changes(file_path.to_str().unwrap()).unwrap(), 3);
+        let text = std::fs::read_to_string(&file_path).unwrap();
+        std::fs::remove_file(&file_path).unwrap();
+        assert_eq!(text, "# changes of test_data.pb\nset a = 5\nset a = 7\nset b = 2\n");
+    }
 
-        app.run_command(UserCommand::ChangeFieldOrder(FieldOrder::Wire)).unwrap();
-        app.after_event().unwrap();
-        let expected_start = [
-            " y: 3         int32 "];
-        assert_eq!(app.to_strings(), expected_start);
+    #[test]
+    fn merge_file() {
+        let proto = ProtoData::new("message M { int32 a = 1; repeated int32 r = 2; N n = 3; }\nmessage N { string s = 1; }").unwrap().finalize().unwrap();
+        let new_data = |script: &str| {
+            let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+            batch::apply_script(&mut data, &proto, script).unwrap();
+            data
+        };
+        let other = new_data("set a = 2\nset r[0] = 5\nset n.s = x");
+        let file_path = std::env::temp_dir().join(format!("protoedit_merge_{}.pb", std::process::id()));
+        let mut bytes = vec![];
+        other.write(&mut bytes, &proto, other.def.clone()).unwrap();
+        std::fs::write(&file_path, bytes).unwrap();
+        let mut app = App::for_tests(new_data("set a = 1\nset r[0] = 5\nset r[1] = 6"), FieldOrder::Proto, 80, 20).unwrap();
+        app.proto = proto.clone();
+        let lines = |app: &App| app.report.as_ref().unwrap().lines.iter().map(|(text, _)| text.clone()).collect::<Vec<_>>();
+
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('m'), KeyModifiers::ALT, KeyEventKind::Press)).unwrap();
+        for c in file_path.to_str().unwrap().chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        std::fs::remove_file(&file_path).unwrap();
+        assert_eq!(lines(&app), ["~ a: 1 → 2", "- r[1] = 6", "+ n = message N"]);
+        assert!(app.get_top_line(200, &app.layout_config).contains("3 differences with "));
+
+        // the other value is taken as a change, the next difference is selected
+        app.on_key(press(KeyCode::Right)).unwrap();
+        assert_eq!(lines(&app), ["- r[1] = 6", "+ n = message N"]);
+        app.on_key(press(KeyCode::Left)).unwrap();
+        assert_eq!(lines(&app), ["+ n = message N"]);
+        app.on_key(press(KeyCode::Right)).unwrap();
+        assert!(app.report.is_none());
+        assert!(app.message.as_deref().unwrap().starts_with("no differences with "));
+        let script = "set a = 2\nset r[0] = 5\nset r[1] = 6\nset n.s = x";
+        assert_eq!(diff::compare(&app.data, &new_data(script)).len(), 0);
+
+        // each one is undone
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('z'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        assert!(app.data.get_field(&[(3, 0).into()]).is_none());
+    }
 
-        app.run_command(UserCommand::ChangeFieldOrder(FieldOrder::ByName)).unwrap();
-        app.after_event().unwrap();
-        let expected_start = [
-            " x: 0        -int32 ",
-            " y: 3         int32 "];
-        assert_eq!(app.to_strings(), expected_start);
+    #[test]
+    fn bytes_import_export() {
+        let proto = ProtoData::new("message M { bytes raw = 1; int32 n = 2; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set raw = 01 02 ff\nset n = 1").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 80, 20).unwrap();
+        app.proto = proto;
+        let alt = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::ALT, KeyEventKind::Press);
+        let type_text = |app: &mut App, text: &str| {
+            app.on_key(KeyEvent::new_with_kind(KeyCode::Char('u'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+            for c in text.chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+            app.on_key(press(KeyCode::Enter)).unwrap();
+        };
+        let raw = |app: &App| match &app.data.get_field(&[(1, 0).into()]).unwrap().value { SCALAR(ScalarValue::BYTES(bytes)) => bytes.to_vec(), _ => vec![] };
+
+        // copied as text or saved as is
+        app.on_key(alt('e')).unwrap();
+        type_text(&mut app, "base64");
+        assert_eq!(app.message.as_deref(), Some("3 B copied as base64"));
+        assert_eq!(app.clipboard, "AQL/");
+        let file_path = std::env::temp_dir().join(format!("protoedit_bytes_{}.bin", std::process::id()));
+        app.on_key(alt('e')).unwrap();
+        type_text(&mut app, file_path.to_str().unwrap());
+        assert_eq!(std::fs::read(&file_path).unwrap(), [1, 2, 0xff]);
+
+        // hex, base64 or a file, a wrong text is not accepted
+        app.on_key(alt('i')).unwrap();
+        type_text(&mut app, "0a0b 0c 0d");
+        assert_eq!(app.message.as_deref(), Some("4 B imported, was 3 B"));
+        assert_eq!(raw(&app), [10, 11, 12, 13]);
+        app.on_key(alt('i')).unwrap();
+        type_text(&mut app, "aGVsbG8=");
+        assert_eq!(raw(&app), b"hello");
+        app.on_key(alt('i')).unwrap();
+        type_text(&mut app, "%%");
+        assert!(app.prompt.is_some());
+        type_text(&mut app, file_path.to_str().unwrap());
+        std::fs::remove_file(&file_path).unwrap();
+        assert_eq!(raw(&app), [1, 2, 0xff]);
+
+        // not a bytes field
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(alt('e')).unwrap();
+        assert_eq!(app.message.as_deref(), Some("select a bytes value"));
     }
 
     #[test]
-    fn layout_percent() {
-        let data = make_test_data_1();
-        let mut app = App::for_tests(data, FieldOrder::Proto, 20, 25).unwrap();
+    fn replace_in_strings() {
+        let proto = ProtoData::new("message M { repeated N n = 1; string s = 2; }\nmessage N { string name = 1; int32 id = 2; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set n[0].name = foo\nset n[0].id = 100\nset n[1].name = bar\nset n[2].name = good food\nset s = zoo").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 100, 20).unwrap();
+        app.proto = proto;
+        let before = app.data.to_string();
+        let type_text = |app: &mut App, text: &str| {
+            for c in text.chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+            app.on_key(press(KeyCode::Enter)).unwrap();
+        };
+        let value = |app: &App, path: &[(i32, usize)]| match &app.data.get_field(&path.iter().map(|pos| (*pos).into()).collect::<Vec<_>>()).unwrap().value {
+            SCALAR(STR(text)) => text.to_string(),
+            _ => String::new(),
+        };
+        let alt_r = KeyEvent::new_with_kind(KeyCode::Char('r'), KeyModifiers::ALT, KeyEventKind::Press);
+
+        app.on_key(alt_r).unwrap();
+        type_text(&mut app, "/o+/");
+        type_text(&mut app, "0");
+        assert_eq!(app.message.as_deref(), Some("n[0].name: \"foo\" → \"f0\", Y - replace, N - skip, A - all, Esc - stop"));
+        assert_eq!(app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).as_deref(), Some("n[0].name"));
+        app.on_key(press(KeyCode::Char('n'))).unwrap();
+        assert!(app.message.as_deref().unwrap().starts_with("n[2].name: \"good food\" → \"g0d f0d\""));
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        assert_eq!(value(&app, &[(1, 2), (1, 0)]), "g0d f0d");
+        app.on_key(press(KeyCode::Char('a'))).unwrap();
+        assert_eq!(app.message.as_deref(), Some("3 replacements in 2 values, 1 skipped"));
+        assert_eq!((value(&app, &[(1, 0), (1, 0)]), value(&app, &[(2, 0)])), ("foo".to_string(), "z0".to_string()));
+
+        // undone at once
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('z'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        assert_eq!(app.data.to_string(), before);
+
+        // a plain text, Esc stops
+        app.on_key(alt_r).unwrap();
+        type_text(&mut app, "oo");
+        type_text(&mut app, "");
+        app.on_key(press(KeyCode::Char('y'))).unwrap();
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        assert_eq!(app.message.as_deref(), Some("1 replacement in 1 value, stopped before 2 more"));
+        assert_eq!(value(&app, &[(1, 0), (1, 0)]), "f");
+        app.on_key(alt_r).unwrap();
+        type_text(&mut app, "xyz");
+        type_text(&mut app, "a");
+        assert_eq!(app.message.as_deref(), Some("not found"));
+    }
 
-        let data = make_test_data_1();
-        let mut app = App::for_tests(data, FieldOrder::Proto, 50, 25).unwrap();
-        let expected = [                                   // children count
-            " f1: 1                                      int32 ",   // 0
-            " f2: 20 21                                 int32* ",   // 0
-            " m3:                                           M3 ",   // 4
-            "   f5: 5                                    int32 ",   // 0
-            "   m6:                                        M6* ",   // 2
-            "     f8: 8                                  int32 ",   // 0
-            "     f9: 9                                  int32 ",   // 0
-            "   m6:                                        M6* ",   // 2
-            "     f8: 10                                 int32 ",   // 0
-            "     f9: 11                                 int32 ",   // 0
-            "   f7: 7                                    int32 ",   // 0
-            " f4: 0                                     -int32 "];  // 0
-        assert_eq!(app.to_strings(), expected);
+    #[test]
+    fn control_chars_escaped() {
+        let proto = ProtoData::new("message M { string s = 1; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set s = \"a\\tb\"").unwrap();
+        if let Some(field) = data.fields.first_mut() { field.value = SCALAR(STR("a\tb\x1b[2J\u{85}".into())); }
+        let mut app = App::for_tests(data, FieldOrder::Proto, 40, 5).unwrap();
+        let screen = app.compose_screen();
+        assert_eq!(app.to_strings()[0], " s: 'a\\tb\\x1b[2J\\u{85}'          string ");
+        // the escapes are marked, the text is not
+        let styles: Vec<TextStyle> = screen[0].0[5..21].iter().map(|(_, style)| *style).collect();
+        assert_eq!(styles[..4], [TextStyle::Value, TextStyle::Escape, TextStyle::Escape, TextStyle::Value]);
+        assert_eq!(styles[8..10], [TextStyle::Value, TextStyle::Value]);
+
+        // printed as is, the value is the same
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('c'), KeyModifiers::ALT, KeyEventKind::Press)).unwrap();
+        app.after_event().unwrap();
+        assert_eq!(app.message.as_deref(), Some("control chars of strings are printed as is"));
+        assert!(app.to_strings()[0].starts_with(" s: 'a\tb\x1b[2J"));
+        assert_eq!(app.data.fields[0].value, SCALAR(STR("a\tb\x1b[2J\u{85}".into())));
+    }
 
-        assert_eq!(app.layouts.items.len(), 12);
-        assert_eq!(app.layouts.calc_relative_pos(0), 0.0);
-        assert_eq!(app.layouts.calc_relative_pos(1), 0.25);
-        assert_eq!(app.layouts.calc_relative_pos(2), 0.5);
-        assert_eq!(app.layouts.calc_relative_pos(3), 0.55);
-        assert_eq!(app.layouts.calc_relative_pos(4), 0.6);
-        assert_eq!(app.layouts.calc_relative_pos(7), 0.65);
-        assert_eq!(app.layouts.calc_relative_pos(10), 0.7);
-        assert_eq!(app.layouts.calc_relative_pos(11), 0.75);
-        assert_eq!(app.layouts.items[0].children_count, 0);
-        assert_eq!(app.layouts.items[1].children_count, 0);
-        assert_eq!(app.layouts.items[2].children_count, 4);
-        assert_eq!(app.layouts.items[3].children_count, 0);
-        assert_eq!(app.layouts.items[4].children_count, 2);
-        assert_eq!(app.layouts.items[5].children_count, 0);
-        assert_eq!(app.layouts.items[6].children_count, 0);
-        assert_eq!(app.layouts.items[7].children_count, 2);
-        assert_eq!(app.layouts.items[8].children_count, 0);
-        assert_eq!(app.layouts.items[9].children_count, 0);
-        assert_eq!(app.layouts.items[10].children_count, 0);
-        assert_eq!(app.layouts.items[11].children_count, 0);
+    #[test]
+    fn search_as_typed() {
+        let proto = ProtoData::new("message M { repeated N n = 1; }\nmessage N { string name = 1; int32 id = 2; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set n[0].name = apple\nset n[0].id = 1\nset n[1].name = pear pie\nset n[1].id = 2\nset n[2].name = plum").unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 40, 12).unwrap();
+        app.proto = proto;
+        let selected = |app: &App| app.layouts.selected_path(&app.selected).unwrap().to_text(&app.data).unwrap();
+        let matches = |app: &mut App| app.compose_screen().iter().map(|line| line.0.iter().filter(|(_, style)| *style == TextStyle::Match).map(|(c, _)| *c).collect::<String>()).
+            filter(|text| !text.is_empty()).collect::<Vec<_>>();
+        app.go_to("n[0].id").unwrap();
+
+        // from the selection on, all visible occurrences are marked
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('f'), KeyModifiers::ALT, KeyEventKind::Press)).unwrap();
+        app.on_key(press(KeyCode::Char('p'))).unwrap();
+        assert_eq!(selected(&app), "n[1].name");
+        assert_eq!(matches(&mut app), ["pp", "pp", "p"]);
+        app.on_key(press(KeyCode::Char('l'))).unwrap();
+        assert_eq!(selected(&app), "n[2].name");
+        assert_eq!(matches(&mut app), ["pl", "pl"]);
+        // the search goes on from the beginning
+        app.on_key(press(KeyCode::Backspace)).unwrap();
+        app.on_key(press(KeyCode::Char('p'))).unwrap();
+        assert_eq!(selected(&app), "n[0].name");
+        app.on_key(press(KeyCode::Char('x'))).unwrap();
+        assert!(app.prompt.as_ref().unwrap().get_screen(40, &app.layout_config).0.iter().map(|(c, _)| *c).collect::<String>().contains("not found"));
+
+        // Esc goes back, Enter stays at the value found
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        assert_eq!(selected(&app), "n[0].id");
+        assert!(matches(&mut app).is_empty());
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('f'), KeyModifiers::ALT, KeyEventKind::Press)).unwrap();
+        for c in "pie".chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+        app.on_key(press(KeyCode::Enter)).unwrap();
+        assert_eq!(selected(&app), "n[1].name");
     }
 
     #[test]
-    fn layout_percent_after_expand() {
-        let data = make_test_data_1();
+    fn decode_bytes_field() {
+        let proto = ProtoData::new("message M { bytes f1=1; int32 f2=2; }\nmessage N { int32 a=1; string b=2; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        batch::apply_script(&mut data, &proto, "set f1 = 08 05 12 01 78\nset f2 = 1").unwrap();
         let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
+        app.proto = proto;
+        let ctrl = |c| KeyEvent::new_with_kind(KeyCode::Char(c), KeyModifiers::CONTROL, KeyEventKind::Press);
+        let type_text = |app: &mut App, text: &str| {
+            for c in text.chars() { app.on_key(press(KeyCode::Char(c))).unwrap(); }
+            app.on_key(press(KeyCode::Enter)).unwrap();
+        };
+        app.on_key(ctrl('b')).unwrap();
+        type_text(&mut app, "X");
+        assert!(app.prompt.is_some());
+        app.on_key(press(KeyCode::Backspace)).unwrap();
+        type_text(&mut app, "N");
+        assert_eq!(app.message.as_deref(), Some("f1 decoded as N"));
+        assert_eq!(app.to_strings(), [" f1:                        N ", "    a: 5                int32 ",
+                                      "    b: 'x'             string ", " f2: 1                  int32 "]);
+
+        // the value inside is edited, the field is saved as bytes
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(ctrl('e')).unwrap();
+        app.on_key(ctrl('u')).unwrap();
+        type_text(&mut app, "7");
+        app.on_key(press(KeyCode::Up)).unwrap();
+        app.on_key(ctrl('b')).unwrap();
+        assert_eq!(app.message.as_deref(), Some("f1 is shown as bytes"));
+        assert_eq!(app.data.get_field(&[(1, 0).into()]).unwrap().value, SCALAR(BYTES(vec![8, 7, 0x12, 1, 0x78].into())));
+
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.on_key(ctrl('b')).unwrap();
+        assert_eq!(app.message.as_deref(), Some("select a bytes field"));
+    }
 
-        app.to_strings();
-        app.run_command(UserCommand::ScrollVertically(2)).unwrap();
-        app.after_event().unwrap();
-        app.run_command(UserCommand::CollapsedToggle).unwrap();
-        app.after_event().unwrap();
-
-        let expected = [
-            " f1: 1                  int32 ",   // 0
-            " f2: 20 21             int32* ",   // 0
-            " m3: ... 14                M3 ",   // 4
-            " f4: 0                 -int32 "];  // 0
-        assert_eq!(app.to_strings(), expected);
-
-        assert_eq!(app.layouts.items.len(), 4);
-        assert_eq!(app.layouts.items[0].children_count, 0);
-        assert_eq!(app.layouts.items[1].children_count, 0);
-        assert_eq!(app.layouts.items[2].children_count, 0);
-        assert_eq!(app.layouts.items[3].children_count, 0);
-        assert_eq!(app.layouts.calc_relative_pos(0), 0.0);
-        assert_eq!(app.layouts.calc_relative_pos(1), 0.25);
-        assert_eq!(app.layouts.calc_relative_pos(2), 0.5);
-        assert_eq!(app.layouts.calc_relative_pos(3), 0.75);
+    #[test]
+    fn large_expansion_sized_later() {
+        let proto = ProtoData::new("message R { W w = 1; }\nmessage W { repeated M m = 1; }\nmessage M { int32 a = 1; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.auto_detect_root_message().unwrap(), &mut 0).unwrap();
+        data.add_field(&[(1, 0).into()]).unwrap();
+        for i in 0..400 {
+            data.add_field(&[(1, 0).into(), (1, i).into()]).unwrap();
+            data.add_field(&[(1, 0).into(), (1, i).into(), (1, 0).into()]).unwrap();
+        }
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 4).unwrap();
+        app.run_command(UserCommand::CollapseToDepth(1)).unwrap();
+        app.run_command(UserCommand::ExpandAll).unwrap();
+        // the screen is sized, the rest is left for the time without keys
+        assert!(app.layouts.pending_sizes.is_some());
+        assert_eq!(app.to_strings()[..2], [" w:                         W ", "   m:                      M* "]);
+        assert!(app.layouts.receive_sizes(&app.data, &app.layout_config, true));
+        assert!(app.layouts.pending_sizes.is_none());
+        assert!(app.layouts.items.iter().all(|item| item.height > 0));
+    }
 
+    // the layouts sized on the thread are sized for the data as it is when they are taken
+    #[test]
+    fn sized_on_thread_after_edit() {
+        let proto = ProtoData::new("message R { W w = 1; }\nmessage W { repeated M m = 1; }\nmessage M { string s = 1; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.auto_detect_root_message().unwrap(), &mut 0).unwrap();
+        data.add_field(&[(1, 0).into()]).unwrap();
+        for i in 0..400 {
+            data.add_field(&[(1, 0).into(), (1, i).into()]).unwrap();
+            data.add_field(&[(1, 0).into(), (1, i).into(), (1, 0).into()]).unwrap().value = SCALAR(STR("a".repeat(40).into()));
+        }
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 4).unwrap();
+        app.run_command(UserCommand::CollapseToDepth(1)).unwrap();
+        app.run_command(UserCommand::ExpandAll).unwrap();
+        assert!(!app.layouts.receive_sizes(&app.data, &app.layout_config, false));
+
+        // the snapshot the thread sizes has the long value
+        let last = [(1, 0).into(), (1, 399).into(), (1, 0).into()];
+        Arc::make_mut(&mut app.data).get_field_mut(&last).unwrap().value = SCALAR(STR("a".into()));
+        assert!(app.layouts.receive_sizes(&app.data, &app.layout_config, true));
+        let height = |app: &App, path: &[FieldPos]| app.layouts.items.iter().find(|item| item.path.0 == path).unwrap().height;
+        assert_eq!(height(&app, &last), 1);
+        assert_eq!(height(&app, &[(1, 0).into(), (1, 398).into(), (1, 0).into()]), 3);
+    }
 
+    #[test]
+    fn pending_sizes_follow_layouts() {
+        let proto = ProtoData::new("message R { W w = 1; }\nmessage W { repeated M m = 1; }\nmessage M { int32 a = 1; }").unwrap().finalize().unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.auto_detect_root_message().unwrap(), &mut 0).unwrap();
+        data.add_field(&[(1, 0).into()]).unwrap();
+        for i in 0..400 {
+            data.add_field(&[(1, 0).into(), (1, i).into()]).unwrap();
+            data.add_field(&[(1, 0).into(), (1, i).into(), (1, 0).into()]).unwrap();
+        }
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 4).unwrap();
+        app.run_command(UserCommand::CollapseToDepth(1)).unwrap();
+        app.run_command(UserCommand::ExpandAll).unwrap();
+        let pending = app.layouts.pending_sizes.clone().unwrap();
+        // the first value above the pending layouts is collapsed, they move up by its content
+        app.run_command(UserCommand::ScrollVertically(1)).unwrap();
         app.run_command(UserCommand::CollapsedToggle).unwrap();
-        app.after_event().unwrap();
+        assert_eq!(app.layouts.pending_sizes, Some(pending.start - 1..pending.end - 1));
+        app.layouts.receive_sizes(&app.data, &app.layout_config, true);
+        assert!(app.layouts.items[pending.start - 1..pending.end - 1].iter().all(|item| item.sized.is_some()));
 
-        let expected = [
-            " f1: 1                  int32 ",   // 0
-            " f2: 20 21             int32* ",   // 0
-            " m3:                       M3 ",   // 4
-            "   f5: 5                int32 ",   // 0
-            "   m6:                    M6* ",   // 2
-            "     f8: 8              int32 ",   // 0
-            "     f9: 9              int32 ",   // 0
-            "   m6:                    M6* ",   // 2
-            "     f8: 10             int32 ",   // 0
-            "     f9: 11             int32 ",   // 0
-            "   f7: 7                int32 ",   // 0
-            " f4: 0                 -int32 "];  // 0
-        assert_eq!(app.to_strings(), expected);
+        // the pending layouts removed by collapsing are not sized anymore
+        app.run_command(UserCommand::ScrollVertically(-1)).unwrap();
+        app.run_command(UserCommand::ExpandAll).unwrap();
+        assert!(app.layouts.pending_sizes.is_some());
+        app.run_command(UserCommand::CollapsedToggle).unwrap();
+        assert_eq!(app.layouts.pending_sizes, None);
+    }
 
-        assert_eq!(app.layouts.items.len(), 12);
-        assert_eq!(app.layouts.items[0].children_count, 0);
-        assert_eq!(app.layouts.items[1].children_count, 0);
-        assert_eq!(app.layouts.items[2].children_count, 4);
-        assert_eq!(app.layouts.items[3].children_count, 0);
-        assert_eq!(app.layouts.items[4].children_count, 2);
-        assert_eq!(app.layouts.items[5].children_count, 0);
-        assert_eq!(app.layouts.items[6].children_count, 0);
-        assert_eq!(app.layouts.items[7].children_count, 2);
-        assert_eq!(app.layouts.items[8].children_count, 0);
-        assert_eq!(app.layouts.items[9].children_count, 0);
-        assert_eq!(app.layouts.items[10].children_count, 0);
-        assert_eq!(app.layouts.items[11].children_count, 0);
-        assert_eq!(app.layouts.calc_relative_pos(7), 0.65);
-        assert_eq!(app.layouts.calc_relative_pos(10), 0.7);
-        assert_eq!(app.layouts.calc_relative_pos(11), 0.75);
+    #[test]
+    fn key_script_snapshot() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 30, 5).unwrap();
+        let keys = harness::parse_keys("Down Down F5 Up Ctrl+E Ctrl+U \"9\" Enter").unwrap();
+        assert!(app.run_keys(&keys).unwrap());
+        assert_eq!(app.snapshot(true), [
+            " 1 of 2 #2 VARINT 0x2 +2      ",
+            "^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^",
+            " f1: 1                  int32 ",
+            "nnn||vtttttttttttttttttttttttt",
+            " f2: 9 21              int32* ",
+            "NNNN|v|vvttttttttttttttttttttt",
+            " m3: ... 14                M3 ",
+            "nnn|sssssssttttttttttttttttttt",
+            " f4: 0                 -int32 ",
+            "nnn||etttttttttttttttttttttttt"]);
+        // the editor is closed by the key
+        assert!(!app.run_keys(&harness::parse_keys("Esc Down").unwrap()).unwrap());
     }
 
     #[test]
-    fn layout_percent_after_data_change() {
-        let mut data = make_repeated_message_data(0);
-        let mut app = App::for_tests(data, FieldOrder::Proto, 30, 25).unwrap();
-        assert_eq!(app.to_strings(), [" m1:                     -M2* "]);
+    fn bench_repeated_string() {
+        let proto = ProtoData::new("message M { repeated string i1 = 1;  }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let mut read = PbReader::new([].as_slice());
+        let mut data = MessageData::new(&mut read, &proto, root_msg, &mut 0).unwrap();
+
+        // for now, without optimization app works with 1e4 lines,
+        // the optimized version will be able to open at least 18000 messages * 100 lines per message (2e6)
+        const COUNT: usize = 10000;
+        for _ in 0..COUNT {
+            data.add_field(&[(1, 0).into()]).unwrap();
+        }
 
-        app.run_command(UserCommand::InsertData).unwrap();
-        app.after_event().unwrap();
+        assert_eq!(data.fields.len(), COUNT);
 
-        app.run_command(UserCommand::InsertData).unwrap();
-        app.after_event().unwrap();
+        const CONTENT_HEIGHT: u16 = 10;
+        let mut app = App::for_tests(data, FieldOrder::Proto, 30, CONTENT_HEIGHT + TOP_LINE).unwrap();
+        let screen = app.to_strings();
 
-        let expected = [
-            " m1:                      M2* ",
-            "   i2: 0               -int32 ",
-            "   i3: 0               -int32 ",
-            " m1:                      M2* ",
-            "   i2: 0               -int32 ",
-            "   i3: 0               -int32 "];
-        assert_eq!(app.to_strings(), expected);
+        assert_eq!(screen.len(), (CONTENT_HEIGHT as usize).min(COUNT));
+        for line in screen {
+            assert_eq!(line, " i1: ''               string* ");
+        }
+    }
 
-        assert_eq!(app.layouts.items.len(), 6);
-        assert_eq!(app.layouts.items[0].children_count, 2);
-        assert_eq!(app.layouts.items[1].children_count, 0);
-        assert_eq!(app.layouts.items[3].children_count, 2);
-        assert_eq!(app.layouts.calc_relative_pos(0), 0.0);
-        assert_eq!(app.layouts.calc_relative_pos(3), 0.5);
-        assert_eq!(app.layouts.calc_relative_pos(4), 2.0 / 3.0);
+    #[test]
+    fn perf_overlay() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 60, 4).unwrap();
+        app.run_command(UserCommand::CollapseToDepth(1)).unwrap();
+        app.on_key(press(KeyCode::F(12))).unwrap();
+        let screen = app.to_strings();
+        assert_eq!(screen.len(), 3);
+        assert_eq!(screen[0], " f1: 1                                                int32 ");
+        assert!(screen[2].starts_with(" draw 0.0 ms, layouts 4 of 4, read "), "{}", screen[2]);
+        app.on_key(press(KeyCode::F(12))).unwrap();
+        assert_eq!(app.to_strings().len(), 3);
+        assert!(!app.to_strings()[2].contains("draw"));
+    }
 
-        app.run_command(UserCommand::DeleteData).unwrap();
+    #[test]
+    fn grouped_digits_and_sizes() {
+        let mut app = App::for_tests(make_one_field_data("message M { int64 f1=1; }", ScalarValue::I64(-1234567)), FieldOrder::Proto, 40, 4).unwrap();
+        app.layout_config.digit_separator = Some(',');
+        app.config_changed();
         app.after_event().unwrap();
-        let expected = [
-            " m1:                      M2* ",
-            "   i2: 0               -int32 ",
-            "   i3: 0               -int32 "];
-        assert_eq!(app.to_strings(), expected);
+        assert!(app.to_strings()[0].starts_with(" f1: -1,234,567 "));
+        // edited and copied as is
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('e'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        assert_eq!(app.prompt.as_ref().unwrap().text(), "-1234567");
+        app.on_key(press(KeyCode::Esc)).unwrap();
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('c'), KeyModifiers::CONTROL, KeyEventKind::Press)).unwrap();
+        assert_eq!(app.clipboard, "insert f1 = -1234567");
+
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 30, 6).unwrap();
+        app.run_command(UserCommand::CollapseToDepth(1)).unwrap();
+        app.layout_config.human_sizes = true;
+        app.config_changed();
+        app.after_event().unwrap();
+        assert_eq!(app.to_strings()[2], " m3: ... 14 B              M3 ");
+        assert_eq!(view::group_digits("123", '_'), "123");
+        assert_eq!(view::group_digits("1234", '_'), "1_234");
+    }
 
-        assert_eq!(app.layouts.items.len(), 3);
-        assert_eq!(app.layouts.items[0].children_count, 2);
-        assert_eq!(app.layouts.items[1].children_count, 0);
-        assert_eq!(app.layouts.items[2].children_count, 0);
-        assert_eq!(app.layouts.calc_relative_pos(0), 0.0);
-        assert_eq!(app.layouts.calc_relative_pos(1), 0.25);
-        assert_eq!(app.layouts.calc_relative_pos(2), 0.5);
+    #[test]
+    fn field_sizes() {
+        let mut app = App::for_tests(make_test_data_1(), FieldOrder::Proto, 30, 4).unwrap();
+        app.on_key(KeyEvent::new_with_kind(KeyCode::Char('s'), KeyModifiers::ALT, KeyEventKind::Press)).unwrap();
+        assert_eq!(app.to_strings(), [" f1: 1              2 B int32 ", " f2: 20 21         4 B int32* ", " m3:                  18 B M3 "]);
+        // the size follows the edits
+        app.run_command(UserCommand::InsertData).unwrap();
+        assert_eq!(app.to_strings()[0], " f1:+0 1            4 B int32 ");
+        app.run_command(UserCommand::DeleteData).unwrap();
+        app.on_key(press(KeyCode::Down)).unwrap();
+        app.run_command(UserCommand::DeleteData).unwrap();
+        assert_eq!(app.to_strings()[1], " f2: 21            2 B int32* ");
     }
 
-    // TODO unknown field layout
-    // TODO delete a field of a submessage
-}
\ No newline at end of file
+    #[test]
+    fn unknown_enum_values() {
+        let proto = ProtoData::new("message M { E e = 1; E g = 2; }\nenum E { A = 0; B = 4; }").unwrap().finalize().unwrap();
+        let bytes = [0x08, 0x07, 0x10, 0x03];
+        let data = MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut (bytes.len() as u32)).unwrap();
+        let mut app = App::for_tests(data, FieldOrder::Proto, 60, 6).unwrap();
+        app.proto = proto;
+        assert!(app.to_strings()[0].starts_with(" e: ?7 ") && app.to_strings()[1].starts_with(" g: ?3 "));
+        // edited as a number, ?N as it is shown
+        assert!(app.run_keys(&harness::parse_keys("Ctrl+E Ctrl+U \"?9\" Enter").unwrap()).unwrap());
+        assert!(app.to_strings()[0].starts_with(" e: ?9 "));
+
+        assert!(app.run_keys(&harness::parse_keys("Alt+N").unwrap()).unwrap());
+        assert_eq!(app.to_strings()[..2].iter().map(|line| line.trim_end()).collect::<Vec<_>>(),
+            [" ? e: 9 is not a value of E, nearest B = 4", " ? g: 3 is not a value of E, nearest B = 4"]);
+        assert!(app.get_top_line(100, &app.layout_config).contains("2 enum values with no name"));
+        app.run_keys(&harness::parse_keys("Right Right").unwrap()).unwrap();
+        assert!(app.report.is_none());
+        assert_eq!(app.message.as_deref(), Some("all enum values have names"));
+        let mut buf = vec![];
+        app.data.write(&mut buf, &app.proto, app.data.def.clone()).unwrap();
+        assert_eq!(buf, [0x08, 0x04, 0x10, 0x04]);
+    }
+}
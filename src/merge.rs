@@ -0,0 +1,73 @@
+use std::io;
+use std::sync::Arc;
+use crate::diff::{self, Difference};
+use crate::proto::ProtoData;
+use crate::trz::{Change, ChangeType};
+use crate::wire::{FieldPath, FieldValue, MessageData};
+
+// another version of the data merged into the edited one: each differing value is taken from it or kept
+pub struct Merge {
+    pub file_name: String,
+    pub other: MessageData,
+    // the paths of the differences resolved by keeping the edited value
+    pub kept: Vec<String>,
+}
+
+impl Merge {
+    pub fn new(file_name: String, other: MessageData) -> Merge {
+        Merge { file_name, other, kept: vec![] }
+    }
+
+    // the values differing from the other version and not kept, the encoding of repeated fields is not merged
+    pub fn differences(&self, data: &MessageData) -> Vec<Difference> {
+        diff::compare(data, &self.other).into_iter().
+            filter(|difference| matches!(difference, Difference::Added(..) | Difference::Removed(..) | Difference::Changed(..))).
+            filter(|difference| !self.kept.iter().any(|path| path == difference.path())).
+            collect()
+    }
+
+    // the change making the value the same as in the other version
+    pub fn take(&self, data: &MessageData, proto: &ProtoData, difference: &Difference) -> io::Result<Change> {
+        let path = FieldPath::parse(difference.path(), &data.def, proto)?;
+        if let Difference::Removed(..) = difference {
+            return Ok(Change::delete_value(path));
+        }
+        let field = self.other.get_field(&path.0).ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} not found", difference.path())))?;
+        let value = match &field.value {
+            FieldValue::SCALAR(scalar) => FieldValue::SCALAR(scalar.clone()),
+            FieldValue::MESSAGE(msg) => FieldValue::MESSAGE(Arc::new(msg.copy(proto)?)),
+        };
+        let action = if let Difference::Added(..) = difference { ChangeType::Insert(value) } else { ChangeType::Overwrite(value) };
+        Ok(Change { path, action })
+    }
+}
+
+
+#[cfg(test)]
+mod merge_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::typedefs::PbReader;
+
+    #[test]
+    fn take_and_keep() {
+        let proto = ProtoData::new("message Root { string name = 1; Item item = 2; repeated int32 ids = 3; }\nmessage Item { int32 n = 1; bool ok = 2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        let mut other = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        apply_script(&mut data, &proto, "set name = a\nset ids[0] = 1\nset ids[1] = 2").unwrap();
+        apply_script(&mut other, &proto, "set name = b\nset ids[0] = 1\nset item.n = 5\nset item.ok = true").unwrap();
+        let mut merge = Merge::new("other.pb".into(), other);
+        let lines = |merge: &Merge, data: &MessageData| merge.differences(data).iter().map(|d| d.to_string()).collect::<Vec<_>>();
+        assert_eq!(lines(&merge, &data), ["~ name: \"a\" → \"b\"", "- ids[1] = 2", "+ item = message Item"]);
+
+        // a message is taken with its content, a removed value is deleted
+        for index in [2, 1] {
+            let mut change = merge.take(&data, &proto, &merge.differences(&data)[index]).unwrap();
+            data.apply(&mut change).unwrap();
+        }
+        merge.kept.push("name".into());
+        assert!(lines(&merge, &data).is_empty());
+        assert_eq!(diff::compare(&data, &merge.other).iter().map(|d| d.to_string()).collect::<Vec<_>>(), ["~ name: \"a\" → \"b\""]);
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use crate::wire::{FieldPath, FieldPos, FieldValue, MessageData, ScalarValue};
+
+// the number of the largest values and of the deepest fields listed
+const TOP_COUNT: usize = 10;
+const DEEPEST_COUNT: usize = 5;
+
+// what the document is made of: messages by type, the size of each top-level field,
+// the largest values and the deepest fields; sizes are as encoded in the file
+#[derive(Default)]
+pub struct Overview {
+    pub size: usize,
+    // type name, count, the path of the first message of the type
+    pub types: Vec<(String, usize, FieldPath)>,
+    // id, count of values, size
+    pub top_fields: Vec<(i32, usize, usize)>,
+    // by size, the largest first
+    pub largest: Vec<(usize, FieldPath)>,
+    // by depth, one field of a message
+    pub deepest: Vec<FieldPath>,
+}
+
+fn tag_size(id: i32) -> usize { ScalarValue::varint_size((id as i128) << 3) }
+
+fn len_size(len: usize) -> usize { ScalarValue::varint_size(len as i128) + len }
+
+impl Overview {
+    pub fn calc(data: &MessageData) -> Overview {
+        let mut res = Overview::default();
+        let mut types: HashMap<String, (usize, FieldPath)> = HashMap::new();
+        let mut path = vec![];
+        res.size = res.visit(data, &mut path, &mut types);
+        res.types = types.into_iter().map(|(name, (count, path))| (name, count, path)).collect();
+        res.types.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        res.top_fields.sort_by(|a, b| b.2.cmp(&a.2));
+        res
+    }
+
+    // the encoded size of the message content; the values of a packed field share one tag
+    fn visit(&mut self, msg: &MessageData, path: &mut Vec<FieldPos>, types: &mut HashMap<String, (usize, FieldPath)>) -> usize {
+        let mut total = 0;
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        let mut packed: HashMap<i32, usize> = HashMap::new();
+        for field in &msg.fields {
+            let id = field.id();
+            let index = counts.entry(id).or_default();
+            path.push(FieldPos { id, index: *index });
+            *index += 1;
+            let size = match &field.value {
+                FieldValue::MESSAGE(child) => {
+                    types.entry(child.def.name.clone()).or_insert((0, FieldPath(path.clone()))).0 += 1;
+                    tag_size(id) + len_size(self.visit(child, path, types))
+                }
+                FieldValue::SCALAR(value) if msg.is_packed(&field.def) => {
+                    *packed.entry(id).or_default() += value.len();
+                    value.len()
+                }
+                FieldValue::SCALAR(value @ (ScalarValue::STR(_) | ScalarValue::BYTES(_))) => tag_size(id) + len_size(value.len()),
+                FieldValue::SCALAR(_) => field.len(),
+            };
+            if path.len() == 1 {
+                match self.top_fields.iter_mut().find(|(top_id, _, _)| *top_id == id) {
+                    Some((_, count, total)) => { *count += 1; *total += size; }
+                    None => self.top_fields.push((id, 1, size)),
+                }
+            }
+            self.add_largest(size, path);
+            if !matches!(field.value, FieldValue::MESSAGE(_)) { self.add_deepest(path); }
+            path.pop();
+            total += size;
+        }
+        total + packed.iter().map(|(id, len)| tag_size(*id) + len_size(*len) - len).sum::<usize>()
+    }
+
+    fn add_largest(&mut self, size: usize, path: &[FieldPos]) {
+        if self.largest.len() == TOP_COUNT && self.largest.last().is_some_and(|(last, _)| *last >= size) { return; }
+        let pos = self.largest.partition_point(|(other, _)| *other >= size);
+        self.largest.insert(pos, (size, FieldPath(path.to_vec())));
+        self.largest.truncate(TOP_COUNT);
+    }
+
+    fn add_deepest(&mut self, path: &[FieldPos]) {
+        let parent = &path[..path.len() - 1];
+        if self.deepest.iter().any(|other| other.0[..other.0.len() - 1] == *parent) { return; }
+        if self.deepest.len() == DEEPEST_COUNT && self.deepest.last().is_some_and(|last| last.0.len() >= path.len()) { return; }
+        let pos = self.deepest.partition_point(|other| other.0.len() >= path.len());
+        self.deepest.insert(pos, FieldPath(path.to_vec()));
+        self.deepest.truncate(DEEPEST_COUNT);
+    }
+
+    // the lines of the screen with the paths to go to, titles have no path;
+    // fields unknown to the proto file are not listed
+    pub fn lines(&self, data: &MessageData) -> Vec<(String, Option<String>)> {
+        let title = |text: &str| (text.to_string(), None);
+        let line = |name: String, value: String| (format!("  {:<40} {:>12}", name, value), Some(name));
+        let mut res = vec![title("Message types")];
+        for (name, count, path) in &self.types {
+            if let Some(path) = path.to_text(data) {
+                res.push((format!("  {:<40} {:>12}", name, count), Some(path)));
+            }
+        }
+        res.push(title("Top-level fields"));
+        for (id, count, size) in &self.top_fields {
+            let Some(def) = data.def.get_field(*id) else { continue; };
+            let name = def.name();
+            let values = if def.repeated() { format!("{} values", count) } else { String::new() };
+            res.push((format!("  {:<27} {:>12} {:>12}", name, values, size_text(*size)), Some(name)));
+        }
+        res.push(title("Largest values"));
+        for (size, path) in &self.largest {
+            if let Some(text) = path.to_text(data) { res.push(line(text, size_text(*size))); }
+        }
+        res.push(title("Deepest fields"));
+        for path in &self.deepest {
+            if let Some(text) = path.to_text(data) { res.push(line(text, format!("depth {}", path.0.len()))); }
+        }
+        res
+    }
+}
+
+// 812 B, 12.4 KiB, 800.0 MiB
+pub fn size_text(size: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if size < 1024 { return format!("{} B", size); }
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+
+#[cfg(test)]
+mod overview_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::proto::ProtoData;
+    use crate::typedefs::PbReader;
+
+    #[test]
+    fn document() {
+        let proto = ProtoData::new("message Root { string name = 1; repeated Order orders = 2; }\nmessage Order { int32 id = 1; bytes raw = 2; Item item = 3; }\nmessage Item { int32 n = 1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        apply_script(&mut data, &proto, "set name = shop\nset orders[0].id = 1\nset orders[1].id = 2\nset orders[1].raw = 00010203040506070809\nset orders[1].item.n = 5").unwrap();
+        let overview = Overview::calc(&data);
+        let mut buf = vec![];
+        data.write(&mut buf, &proto, data.def.clone()).unwrap();
+        assert_eq!(overview.size, buf.len());
+
+        let lines: Vec<String> = overview.lines(&data).into_iter().map(|(text, _)| text.trim_end().to_string()).collect();
+        assert_eq!(lines[..9], [
+            "Message types",
+            "  Order                                               2",
+            "  Item                                                1",
+            "Top-level fields",
+            "  orders                          2 values         24 B",
+            "  name                                              6 B",
+            "Largest values",
+            "  orders[1]                                        20 B",
+            "  orders[1].raw                                    12 B",
+        ]);
+        // one field of a message
+        assert_eq!(lines[15..], [
+            "Deepest fields",
+            "  orders[1].item.n                              depth 3",
+            "  orders[0].id                                  depth 2",
+            "  orders[1].id                                  depth 2",
+            "  name                                          depth 1",
+        ]);
+        assert_eq!(overview.lines(&data)[1].1.as_deref(), Some("orders[0]"));
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(size_text(812), "812 B");
+        assert_eq!(size_text(12_700), "12.4 KiB");
+        assert_eq!(size_text(800 * 1024 * 1024), "800.0 MiB");
+    }
+}
@@ -0,0 +1,340 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use crossterm::event::{Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, style, terminal, ExecutableCommand, QueueableCommand};
+use crate::view::TextStyle;
+
+// the files opened last, one file argument (data.pb;format.proto) per line, the latest first
+pub const RECENT_FILE_NAME: &str = "protoedit.recent";
+const RECENT_COUNT: usize = 20;
+
+// next to the settings file
+pub fn recent_path(vars: &dyn Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    crate::config::default_path(vars).map(|path| path.with_file_name(RECENT_FILE_NAME))
+}
+
+pub fn read_recent(path: &Path) -> Vec<String> {
+    let text = std::fs::read_to_string(path).unwrap_or_default();
+    text.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect()
+}
+
+// the file argument goes to the top of the list, the oldest ones are dropped
+pub fn add_recent(path: &Path, file_arg: &str) -> io::Result<()> {
+    let mut files = read_recent(path);
+    files.retain(|file| file != file_arg);
+    files.insert(0, file_arg.to_string());
+    files.truncate(RECENT_COUNT);
+    if let Some(dir) = path.parent() { std::fs::create_dir_all(dir)?; }
+    std::fs::write(path, files.join("\n") + "\n")
+}
+
+// the data and proto files of the argument made absolute, to be opened from any directory
+pub fn absolute_arg(file_arg: &str, dir: &Path) -> String {
+    file_arg.split(';').enumerate().
+        map(|(index, part)| if index < 2 && !part.is_empty() { dir.join(part).display().to_string() } else { part.to_string() }).
+        collect::<Vec<_>>().join(";")
+}
+
+// the root message of an entry with the data and proto files given ("/d.pb;/d.proto"), if it has one
+pub fn remembered_root(recent: &[String], files: &str) -> Option<String> {
+    recent.iter().find_map(|entry| entry.strip_prefix(files)?.strip_prefix(';').filter(|root| !root.is_empty()).map(String::from))
+}
+
+#[derive(Debug, PartialEq)]
+enum Entry {
+    Title(String),
+    Recent(String),
+    Dir(PathBuf),
+    File(PathBuf),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PickerResult {
+    Continue,
+    // the file argument to open
+    Open(String),
+    Quit,
+}
+
+// the screen shown when the editor is started without a file: the recent files and the files of a directory;
+// a data file is opened with the proto file of the same name, if there is none the proto file is chosen next
+pub struct Picker {
+    dir: PathBuf,
+    recent: Vec<String>,
+    // the data file chosen, waiting for its proto file
+    data_file: Option<PathBuf>,
+    entries: Vec<Entry>,
+    selected: usize,
+    scroll: usize,
+    pub error: Option<String>,
+}
+
+impl Picker {
+    pub fn new(dir: PathBuf, recent: Vec<String>) -> Picker {
+        let mut res = Picker { dir, recent, data_file: None, entries: vec![], selected: 0, scroll: 0, error: None };
+        res.read_dir();
+        res
+    }
+
+    fn read_dir(&mut self) {
+        self.entries.clear();
+        if self.data_file.is_none() && !self.recent.is_empty() {
+            self.entries.push(Entry::Title("Recent files".into()));
+            self.entries.extend(self.recent.iter().cloned().map(Entry::Recent));
+        }
+        self.entries.push(Entry::Title(self.dir.display().to_string()));
+        if let Some(parent) = self.dir.parent() { self.entries.push(Entry::Dir(parent.to_path_buf())); }
+        let (mut dirs, mut files) = (vec![], vec![]);
+        match std::fs::read_dir(&self.dir) {
+            Ok(items) => for item in items.flatten() {
+                let path = item.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if self.data_file.is_none() || path.extension().is_some_and(|ext| ext == "proto") {
+                    files.push(path);
+                }
+            },
+            Err(e) => self.error = Some(format!("{}: {}", self.dir.display(), e)),
+        }
+        dirs.sort();
+        files.sort();
+        self.entries.extend(dirs.into_iter().map(Entry::Dir));
+        self.entries.extend(files.into_iter().map(Entry::File));
+        self.selected = self.entries.iter().position(|entry| !matches!(entry, Entry::Title(_))).unwrap_or(0);
+        self.scroll = 0;
+    }
+
+    pub fn title(&self) -> String {
+        if let Some(error) = &self.error { return format!("error: {}", error); }
+        match &self.data_file {
+            Some(file) => format!("the proto file for {}, Enter - open, Backspace - parent directory, Esc - back", file.display()),
+            None => "open a file, Enter - open, Tab - choose the proto file, Backspace - parent directory, Esc - quit".into(),
+        }
+    }
+
+    // the text of each line and if it is a title
+    pub fn lines(&self) -> Vec<(String, bool)> {
+        self.entries.iter().map(|entry| match entry {
+            Entry::Title(text) => (text.clone(), true),
+            Entry::Recent(file) => (format!("  {}", file), false),
+            Entry::Dir(path) if Some(path.as_path()) == self.dir.parent() => ("  ../".into(), false),
+            Entry::Dir(path) | Entry::File(path) => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                (if path.is_dir() { format!("  {}/", name) } else { format!("  {}", name) }, false)
+            }
+        }).collect()
+    }
+
+    pub fn on_key(&mut self, code: KeyCode, page: usize) -> PickerResult {
+        self.error = None;
+        let last = self.entries.len().saturating_sub(1);
+        let selected = match code {
+            KeyCode::Up => self.selected.saturating_sub(1),
+            KeyCode::Down => (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected.saturating_sub(page),
+            KeyCode::PageDown => (self.selected + page).min(last),
+            KeyCode::Home => 0,
+            KeyCode::End => last,
+            KeyCode::Enter => return self.open(false),
+            KeyCode::Tab => return self.open(true),
+            KeyCode::Backspace | KeyCode::Left => {
+                if let Some(parent) = self.dir.parent() { self.enter(parent.to_path_buf()); }
+                return PickerResult::Continue;
+            }
+            KeyCode::Esc if self.data_file.is_some() => {
+                self.data_file = None;
+                self.read_dir();
+                return PickerResult::Continue;
+            }
+            KeyCode::Esc => return PickerResult::Quit,
+            _ => return PickerResult::Continue,
+        };
+        // titles are skipped in the direction of the move
+        let down = selected >= self.selected;
+        let mut selected = selected;
+        while matches!(self.entries.get(selected), Some(Entry::Title(_))) {
+            selected = match down {
+                true if selected < last => selected + 1,
+                false if selected > 0 => selected - 1,
+                _ => self.selected,
+            };
+            if selected == self.selected { break; }
+        }
+        self.selected = selected;
+        self.scroll = self.scroll.min(self.selected).max((self.selected + 1).saturating_sub(page));
+        PickerResult::Continue
+    }
+
+    fn enter(&mut self, dir: PathBuf) {
+        let previous = std::mem::replace(&mut self.dir, dir);
+        self.read_dir();
+        // back to the parent, the directory left is selected
+        if let Some(index) = self.entries.iter().position(|entry| *entry == Entry::Dir(previous.clone())) { self.selected = index; }
+    }
+
+    // Tab asks for the proto file even if there is one of the same name
+    fn open(&mut self, choose_proto: bool) -> PickerResult {
+        match self.entries.get(self.selected) {
+            Some(Entry::Recent(file)) => PickerResult::Open(file.clone()),
+            Some(Entry::Dir(path)) => {
+                self.enter(path.clone());
+                PickerResult::Continue
+            }
+            Some(Entry::File(path)) => match &self.data_file {
+                Some(data_file) => PickerResult::Open(format!("{};{}", data_file.display(), path.display())),
+                None if path.extension().is_some_and(|ext| ext == "proto") => {
+                    self.error = Some("a data file expected, the proto file is chosen after it".into());
+                    PickerResult::Continue
+                }
+                None => {
+                    let proto = PathBuf::from(path.display().to_string().trim_end_matches(".pb").to_string() + ".proto");
+                    if !choose_proto && proto.is_file() { return PickerResult::Open(path.display().to_string()); }
+                    self.data_file = Some(path.clone());
+                    self.read_dir();
+                    PickerResult::Continue
+                }
+            },
+            Some(Entry::Title(_)) | None => PickerResult::Continue,
+        }
+    }
+
+    // shown in the alternate screen until a file is chosen, None if the picker is closed
+    pub fn run(&mut self) -> io::Result<Option<String>> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        out.execute(EnterAlternateScreen)?;
+        out.execute(cursor::Hide)?;
+        let res = self.event_loop(&mut out);
+        let _ = out.execute(cursor::Show);
+        let _ = out.execute(LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+        res
+    }
+
+    fn event_loop(&mut self, out: &mut impl Write) -> io::Result<Option<String>> {
+        loop {
+            let (width, height) = terminal::size()?;
+            let page = height.saturating_sub(1).max(1) as usize;
+            self.draw(out, width as usize, page)?;
+            let Event::Key(event) = crossterm::event::read()? else { continue; };
+            if event.kind == KeyEventKind::Release { continue; }
+            match self.on_key(event.code, page) {
+                PickerResult::Continue => {}
+                PickerResult::Open(file) => return Ok(Some(file)),
+                PickerResult::Quit => return Ok(None),
+            }
+        }
+    }
+
+    fn draw(&mut self, out: &mut impl Write, width: usize, page: usize) -> io::Result<()> {
+        self.scroll = self.scroll.min(self.selected).max((self.selected + 1).saturating_sub(page));
+        draw(out, &self.title(), &self.lines(), self.selected, self.scroll, width, page)
+    }
+}
+
+// the top line and the lines from `scroll`, titles in another color
+fn draw(out: &mut impl Write, top_line: &str, lines: &[(String, bool)], selected: usize, scroll: usize, width: usize, page: usize) -> io::Result<()> {
+    let fit = |text: &str| format!("{:width$}", text.chars().take(width).collect::<String>());
+    out.queue(terminal::Clear(terminal::ClearType::All))?;
+    out.queue(cursor::MoveTo(0, 0))?;
+    out.queue(TextStyle::TopLine.activate())?;
+    out.queue(style::Print(fit(top_line)))?;
+    for (y, (index, (text, title))) in lines.iter().enumerate().skip(scroll).take(page).enumerate() {
+        let style = if index == selected { TextStyle::SelectedValue } else if *title { TextStyle::FieldName } else { TextStyle::Value };
+        out.queue(cursor::MoveTo(0, y as u16 + 1))?;
+        out.queue(style.activate())?;
+        out.queue(style::Print(fit(text)))?;
+    }
+    out.queue(style::ResetColor)?;
+    out.flush()
+}
+
+// one of the lines chosen with Up/Down and Enter, None if Esc is pressed
+pub fn choose(top_line: &str, items: &[String]) -> io::Result<Option<usize>> {
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    out.execute(EnterAlternateScreen)?;
+    out.execute(cursor::Hide)?;
+    let lines: Vec<(String, bool)> = items.iter().map(|item| (format!("  {}", item), false)).collect();
+    let (mut selected, mut scroll): (usize, usize) = (0, 0);
+    let res = loop {
+        let (width, height) = terminal::size()?;
+        let page = height.saturating_sub(1).max(1) as usize;
+        scroll = scroll.min(selected).max((selected + 1).saturating_sub(page));
+        draw(&mut out, top_line, &lines, selected, scroll, width as usize, page)?;
+        let Event::Key(event) = crossterm::event::read()? else { continue; };
+        if event.kind == KeyEventKind::Release { continue; }
+        match event.code {
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected = (selected + 1).min(items.len().saturating_sub(1)),
+            KeyCode::Enter if !items.is_empty() => break Some(selected),
+            KeyCode::Esc => break None,
+            _ => {}
+        }
+    };
+    let _ = out.execute(cursor::Show);
+    let _ = out.execute(LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+    Ok(res)
+}
+
+#[cfg(test)]
+mod picker_tests {
+    use super::*;
+
+    #[test]
+    fn recent_files() {
+        let dir = std::env::temp_dir().join(format!("protoedit-recent-{}", std::process::id()));
+        let path = dir.join(RECENT_FILE_NAME);
+        add_recent(&path, "/a.pb").unwrap();
+        add_recent(&path, "/b.pb;/b.proto").unwrap();
+        add_recent(&path, "/a.pb").unwrap();
+        assert_eq!(read_recent(&path), ["/a.pb", "/b.pb;/b.proto"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(absolute_arg("d.pb;p/f.proto;M", Path::new("/w")), "/w/d.pb;/w/p/f.proto;M");
+        assert_eq!(absolute_arg("/d.pb", Path::new("/w")), "/d.pb");
+        let recent = ["/a.pb;/a.proto".to_string(), "/b.pb;/b.proto;B".into()];
+        assert_eq!(remembered_root(&recent, "/b.pb;/b.proto").as_deref(), Some("B"));
+        assert_eq!(remembered_root(&recent, "/a.pb;/a.proto"), None);
+    }
+
+    #[test]
+    fn choose_files() {
+        let dir = std::env::temp_dir().join(format!("protoedit-picker-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["a.pb", "a.proto", "b.bin", "sub/c.proto"] { std::fs::write(dir.join(name), "").unwrap(); }
+        let mut picker = Picker::new(dir.clone(), vec!["/x.pb".into()]);
+        let names = |picker: &Picker| picker.lines().into_iter().map(|(text, _)| text.trim().to_string()).collect::<Vec<_>>();
+        assert_eq!(names(&picker)[..2], ["Recent files", "/x.pb"]);
+        assert_eq!(names(&picker)[3..], ["../", "sub/", "a.pb", "a.proto", "b.bin"]);
+        assert_eq!(picker.on_key(KeyCode::Enter, 10), PickerResult::Open("/x.pb".into()));
+
+        // the title is skipped
+        picker.on_key(KeyCode::Down, 10);
+        assert_eq!(picker.selected, 3);
+        picker.on_key(KeyCode::Down, 10);
+        picker.on_key(KeyCode::Down, 10);
+        let a = dir.join("a.pb").display().to_string();
+        assert_eq!(picker.on_key(KeyCode::Enter, 10), PickerResult::Open(a.clone()));
+
+        // no proto file of the same name
+        picker.on_key(KeyCode::End, 10);
+        assert_eq!(picker.on_key(KeyCode::Enter, 10), PickerResult::Continue);
+        assert!(picker.title().starts_with("the proto file for"));
+        assert_eq!(names(&picker)[1..], ["../", "sub/", "a.proto"]);
+        picker.on_key(KeyCode::Down, 10);
+        picker.on_key(KeyCode::Enter, 10);
+        assert_eq!(names(&picker)[1..], ["../", "c.proto"]);
+        picker.on_key(KeyCode::Down, 10);
+        assert_eq!(picker.on_key(KeyCode::Enter, 10), PickerResult::Open(format!("{};{}", dir.join("b.bin").display(), dir.join("sub/c.proto").display())));
+
+        // back to the parent directory with the one left selected
+        picker.on_key(KeyCode::Backspace, 10);
+        assert_eq!(names(&picker)[picker.selected], "sub/");
+        picker.on_key(KeyCode::Esc, 10);
+        assert_eq!(names(&picker)[0], "Recent files");
+        assert_eq!(picker.on_key(KeyCode::Esc, 10), PickerResult::Quit);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
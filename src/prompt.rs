@@ -0,0 +1,225 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::view::{LayoutConfig, ScreenLine, TextStyle};
+
+// max count of entered texts kept for a prompt
+const HISTORY_LIMIT: usize = 100;
+
+// one line text input shown at the bottom of the screen
+pub struct Prompt {
+    // identifies the history of entered texts
+    pub name: &'static str,
+    title: String,
+    text: Vec<char>,
+    cursor: usize,
+    history: Vec<String>,
+    // position in the history while recalling with Up/Down, history.len() for the edited text
+    history_pos: usize,
+    edited: String,
+    // returns an error message for a wrong input, Enter is ignored while there is an error
+    validator: Option<Box<dyn Fn(&str) -> Option<String>>>,
+    error: Option<String>,
+    // a note about the input shown like an error, Enter is allowed
+    checker: Option<Box<dyn Fn(&str) -> Option<String>>>,
+    warning: Option<String>,
+}
+
+pub enum PromptResult {
+    Continue,
+    Accept(String),
+    Cancel,
+}
+
+impl Prompt {
+    pub fn new(name: &'static str, title: &str, history: &PromptHistory) -> Prompt {
+        let history = history.get(name);
+        Prompt { name, title: title.into(), text: vec![], cursor: 0, history_pos: history.len(), history, edited: String::new(), validator: None, error: None, checker: None, warning: None }
+    }
+
+    pub fn with_validator(mut self, validator: impl Fn(&str) -> Option<String> + 'static) -> Prompt {
+        self.validator = Some(Box::new(validator));
+        self
+    }
+
+    pub fn with_warning(mut self, checker: impl Fn(&str) -> Option<String> + 'static) -> Prompt {
+        self.checker = Some(Box::new(checker));
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Prompt {
+        self.set_text(text.into());
+        self
+    }
+
+    pub fn text(&self) -> String { self.text.iter().collect() }
+
+    fn set_text(&mut self, text: String) {
+        self.text = text.chars().collect();
+        self.cursor = self.text.len();
+    }
+
+    fn validate(&mut self) {
+        self.error = self.validator.as_ref().and_then(|validator| validator(&self.text()));
+        self.warning = self.checker.as_ref().filter(|_| self.error.is_none()).and_then(|checker| checker(&self.text()));
+    }
+
+    // show an error found after the input was accepted, the prompt stays open
+    pub fn set_error(&mut self, error: String) { self.error = Some(error); }
+
+    pub fn on_key(&mut self, event: &KeyEvent) -> PromptResult {
+        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+        match event.code {
+            KeyCode::Enter => {
+                self.validate();
+                if self.error.is_none() {
+                    return PromptResult::Accept(self.text());
+                }
+                return PromptResult::Continue;
+            }
+            KeyCode::Esc => return PromptResult::Cancel,
+            KeyCode::Char('u') if ctrl => {
+                self.text.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char('k') if ctrl => { self.text.truncate(self.cursor); }
+            KeyCode::Char('a') if ctrl => { self.cursor = 0; }
+            KeyCode::Char('e') if ctrl => { self.cursor = self.text.len(); }
+            KeyCode::Char(_) if ctrl => {}
+            KeyCode::Char(c) => {
+                self.text.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace => {
+                if self.cursor > 0 {
+                    self.cursor -= 1;
+                    self.text.remove(self.cursor);
+                }
+            }
+            KeyCode::Delete => {
+                if self.cursor < self.text.len() { self.text.remove(self.cursor); }
+            }
+            KeyCode::Left => { self.cursor = self.cursor.saturating_sub(1); }
+            KeyCode::Right => { self.cursor = (self.cursor + 1).min(self.text.len()); }
+            KeyCode::Home => { self.cursor = 0; }
+            KeyCode::End => { self.cursor = self.text.len(); }
+            KeyCode::Up => {
+                if self.history_pos > 0 {
+                    if self.history_pos == self.history.len() { self.edited = self.text(); }
+                    self.history_pos -= 1;
+                    self.set_text(self.history[self.history_pos].clone());
+                }
+            }
+            KeyCode::Down => {
+                if self.history_pos < self.history.len() {
+                    self.history_pos += 1;
+                    let text = self.history.get(self.history_pos).cloned().unwrap_or_else(|| self.edited.clone());
+                    self.set_text(text);
+                }
+            }
+            _ => return PromptResult::Continue,
+        }
+        self.validate();
+        PromptResult::Continue
+    }
+
+    pub fn get_screen(&self, width: u16, config: &LayoutConfig) -> ScreenLine {
+        let mut line = ScreenLine::new(width);
+        line.add_string(" ".repeat(config.margin_left as usize), TextStyle::Divider);
+        line.add_string(format!("{}: ", self.title), TextStyle::FieldName);
+
+        // the text is scrolled to keep the cursor visible, the error is cut if there is no room
+        let avail = (width as usize).saturating_sub(line.0.len() + config.margin_right as usize);
+        let start = (self.cursor + 1).saturating_sub(avail);
+        for (index, c) in self.text.iter().enumerate().skip(start).take(avail) {
+            line.add_string(c.to_string(), if index == self.cursor { TextStyle::SelectedValue } else { TextStyle::Value });
+        }
+        if self.cursor == self.text.len() { line.0.push((' ', TextStyle::SelectedValue)); }
+        if let Some(error) = self.error.as_ref().or(self.warning.as_ref()) {
+            line.0.push((' ', TextStyle::Divider));
+            line.add_string(error.clone(), TextStyle::Comment);
+        }
+        line.fix_length(width.saturating_sub(config.margin_right));
+        line.fix_length(width);
+        line
+    }
+}
+
+// entered texts of all prompts, for the session
+#[derive(Default)]
+pub struct PromptHistory(std::collections::HashMap<&'static str, Vec<String>>);
+
+impl PromptHistory {
+    pub fn get(&self, name: &str) -> Vec<String> { self.0.get(name).cloned().unwrap_or_default() }
+
+    pub fn add(&mut self, name: &'static str, text: String) {
+        let items = self.0.entry(name).or_default();
+        items.retain(|item| *item != text);
+        items.push(text);
+        if items.len() > HISTORY_LIMIT { items.remove(0); }
+    }
+}
+
+
+#[cfg(test)]
+mod prompt_tests {
+    use super::*;
+    use crossterm::event::KeyEventKind;
+
+    fn press(prompt: &mut Prompt, code: KeyCode) -> PromptResult {
+        prompt.on_key(&KeyEvent::new_with_kind(code, KeyModifiers::NONE, KeyEventKind::Press))
+    }
+
+    fn type_text(prompt: &mut Prompt, text: &str) {
+        for c in text.chars() { press(prompt, KeyCode::Char(c)); }
+    }
+
+    fn screen(prompt: &Prompt, width: u16) -> String { prompt.get_screen(width, &LayoutConfig::default()).0.into_iter().map(|v| v.0).collect() }
+
+    #[test]
+    fn editing() {
+        let mut prompt = Prompt::new("test", "path", &PromptHistory::default());
+        type_text(&mut prompt, "ac");
+        press(&mut prompt, KeyCode::Left);
+        type_text(&mut prompt, "b");
+        assert_eq!(prompt.text(), "abc");
+        press(&mut prompt, KeyCode::Home);
+        press(&mut prompt, KeyCode::Delete);
+        press(&mut prompt, KeyCode::End);
+        press(&mut prompt, KeyCode::Backspace);
+        assert_eq!(prompt.text(), "b");
+        assert_eq!(screen(&prompt, 20), " path: b            ");
+        type_text(&mut prompt, "0123456789abcdef");
+        assert_eq!(screen(&prompt, 20), " path: 56789abcdef  ");
+        assert!(matches!(press(&mut prompt, KeyCode::Enter), PromptResult::Accept(text) if text == "b0123456789abcdef"));
+        assert!(matches!(press(&mut prompt, KeyCode::Esc), PromptResult::Cancel));
+    }
+
+    #[test]
+    fn history() {
+        let mut history = PromptHistory::default();
+        history.add("test", "one".into());
+        history.add("test", "two".into());
+        history.add("test", "one".into());
+        history.add("other", "three".into());
+        let mut prompt = Prompt::new("test", "path", &history);
+        type_text(&mut prompt, "x");
+        press(&mut prompt, KeyCode::Up);
+        assert_eq!(prompt.text(), "one");
+        press(&mut prompt, KeyCode::Up);
+        press(&mut prompt, KeyCode::Up);
+        assert_eq!(prompt.text(), "two");
+        press(&mut prompt, KeyCode::Down);
+        press(&mut prompt, KeyCode::Down);
+        assert_eq!(prompt.text(), "x");
+    }
+
+    #[test]
+    fn validation() {
+        let mut prompt = Prompt::new("test", "n", &PromptHistory::default()).
+            with_validator(|text| text.parse::<u32>().err().map(|_| "a number expected".into()));
+        type_text(&mut prompt, "1z");
+        assert_eq!(screen(&prompt, 30), " n: 1z  a number expected     ");
+        assert!(matches!(press(&mut prompt, KeyCode::Enter), PromptResult::Continue));
+        press(&mut prompt, KeyCode::Backspace);
+        assert!(matches!(press(&mut prompt, KeyCode::Enter), PromptResult::Accept(text) if text == "1"));
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::io;
+use crate::proto::{MessageProtoPtr, ProtoData};
+use crate::render::base64_encode;
+use crate::wire::{FieldData, FieldPath, FieldPos, FieldValue, MessageData, ScalarValue};
+
+// Paths with wildcards selecting the values to print:
+//   orders[].total      the total of each order ([*] is the same as [])
+//   orders[2].items     the items of the third order
+//   settings.*          all fields of the message present in the data
+// the index is 0 if omitted, as in batch commands; only the values present in the data are matched
+
+struct Step {
+    name: Option<String>, // None for any field
+    index: Option<usize>, // None for all values
+}
+
+pub struct Query {
+    steps: Vec<Step>,
+}
+
+fn error(msg: String) -> io::Error { io::Error::new(io::ErrorKind::InvalidInput, msg) }
+
+impl Query {
+    // field names are checked with the proto definitions until the first '*'
+    pub fn parse(text: &str, root: &MessageProtoPtr, proto: &ProtoData) -> io::Result<Query> {
+        let mut steps = vec![];
+        let mut def = Some(root.clone());
+        let mut checked = true; // false after '*'
+        for item in FieldPath::split_items(text.trim()) {
+            let (name, index) = match item.split_once('[') {
+                Some((name, rest)) => {
+                    let index = match rest.strip_suffix(']').map(str::trim) {
+                        Some("" | "*") => None,
+                        Some(index) => Some(index.parse::<usize>().map_err(|_| error(format!("wrong index in \"{}\"", item)))?),
+                        None => return Err(error(format!("wrong index in \"{}\"", item))),
+                    };
+                    (name.trim(), index)
+                }
+                None => (item.trim(), Some(0)),
+            };
+            if name == "*" {
+                steps.push(Step { name: None, index: None });
+                checked = false;
+                continue;
+            }
+            if name.is_empty() {
+                return Err(error(format!("field name expected in \"{}\"", text.trim())));
+            }
+            if checked {
+                let Some(msg) = def else {
+                    return Err(error(format!("field \"{}\" is not a message", steps.last().and_then(|s: &Step| s.name.clone()).unwrap_or_default())));
+                };
+                let field = msg.fields.iter().find(|f| f.name() == name).
+                    ok_or_else(|| error(format!("field \"{}\" not found in message {}", name, msg.name)))?;
+                if index != Some(0) && !field.repeated() {
+                    return Err(error(format!("field \"{}\" is not repeated", name)));
+                }
+                def = proto.get_message_definition(&field.typename());
+            }
+            steps.push(Step { name: Some(name.to_string()), index });
+        }
+        Ok(Query { steps })
+    }
+
+    // the matching fields in the order of the data
+    pub fn run<'a>(&self, data: &'a MessageData) -> Vec<(FieldPath, &'a FieldData)> {
+        let mut res = vec![];
+        Self::collect(data, &self.steps, FieldPath::new(), &mut res);
+        res
+    }
+
+    fn collect<'a>(msg: &'a MessageData, steps: &[Step], path: FieldPath, res: &mut Vec<(FieldPath, &'a FieldData)>) {
+        let Some(step) = steps.first() else { return; };
+        let mut counts: HashMap<i32, usize> = HashMap::new();
+        for field in &msg.fields {
+            let id = field.id();
+            let count = counts.entry(id).or_default();
+            let index = *count;
+            *count += 1;
+            if step.name.as_ref().is_some_and(|name| *name != field.def.name()) || step.index.is_some_and(|i| i != index) {
+                continue;
+            }
+            let path = path.add(FieldPos { id, index });
+            match &field.value {
+                _ if steps.len() == 1 => res.push((path, field)),
+                FieldValue::MESSAGE(child) => Self::collect(child, &steps[1..], path, res),
+                _ => {}
+            }
+        }
+    }
+}
+
+// the value for a shell script: strings as is, messages as json in one line
+pub fn value_text(field: &FieldData, original_names: bool) -> String {
+    match &field.value {
+        FieldValue::SCALAR(ScalarValue::STR(text)) => text.to_string(),
+        FieldValue::SCALAR(ScalarValue::ENUM(value)) => field.def.get_enum_name_by_index(*value).map_or(value.to_string(), str::to_string),
+        FieldValue::SCALAR(scalar) => scalar.to_string(),
+        FieldValue::MESSAGE(_) => value_json(field, original_names),
+    }
+}
+
+// as in the protobuf json mapping: bytes in base64, enums by name, repeated fields as arrays,
+// the fields by their json names or by their names in the proto file (original_names)
+pub fn value_json(field: &FieldData, original_names: bool) -> String {
+    match &field.value {
+        FieldValue::MESSAGE(msg) => {
+            let mut names: Vec<String> = vec![];
+            let mut values: Vec<Vec<String>> = vec![];
+            for child in &msg.fields {
+                let name = match &child.value {
+                    FieldValue::SCALAR(ScalarValue::UNKNOWN(tag, _)) => tag.field_id().to_string(),
+                    _ if original_names => child.def.name(),
+                    _ => child.def.json_name(),
+                };
+                match names.iter().position(|n| *n == name) {
+                    Some(pos) => values[pos].push(value_json(child, original_names)),
+                    None => {
+                        names.push(name);
+                        values.push(vec![value_json(child, original_names)]);
+                    }
+                }
+            }
+            let items: Vec<String> = names.iter().zip(&values).map(|(name, values)| {
+                let repeated = msg.fields.iter().find(|f| f.def.name() == *name || f.def.json_name() == *name).is_some_and(|f| f.def.repeated()) || values.len() > 1;
+                let value = if repeated { format!("[{}]", values.join(",")) } else { values[0].clone() };
+                format!("{}:{}", json_string(name), value)
+            }).collect();
+            format!("{{{}}}", items.join(","))
+        }
+        FieldValue::SCALAR(scalar) => match scalar {
+            ScalarValue::STR(text) => json_string(text),
+            ScalarValue::BYTES(bytes) => json_string(&base64_encode(bytes)),
+            ScalarValue::UNKNOWN(_, bytes) => json_string(&base64_encode(bytes)),
+            ScalarValue::ENUM(value) => field.def.get_enum_name_by_index(*value).map_or(value.to_string(), json_string),
+            ScalarValue::F32(v) if !v.is_finite() => json_string(&v.to_string()),
+            ScalarValue::F64(v) if !v.is_finite() => json_string(&v.to_string()),
+            // 64-bit integers are strings in the json mapping, they may not fit in a double
+            ScalarValue::I64(_) | ScalarValue::U64(_) | ScalarValue::S64(_) | ScalarValue::UF64(_) | ScalarValue::SF64(_) => json_string(&scalar.to_string()),
+            _ => scalar.to_string(),
+        },
+    }
+}
+
+pub fn json_string(text: &str) -> String {
+    let mut res = String::with_capacity(text.len() + 2);
+    res.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => res += "\\\"",
+            '\\' => res += "\\\\",
+            '\n' => res += "\\n",
+            '\r' => res += "\\r",
+            '\t' => res += "\\t",
+            c if (c as u32) < 0x20 => res += &format!("\\u{:04x}", c as u32),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+
+#[cfg(test)]
+mod query_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::typedefs::PbReader;
+
+    const PROTO: &str = "enum E { A = 0; B = 1; }\nmessage Root { string name = 1; repeated Order orders = 2; }\nmessage Order { int64 id = 1; double total = 2; E kind = 3; repeated string tags = 4; bytes raw = 5; }";
+
+    fn query(script: &str, text: &str) -> io::Result<Vec<String>> {
+        let proto = ProtoData::new(PROTO).unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        apply_script(&mut data, &proto, script).unwrap();
+        Ok(Query::parse(text, &root_msg, &proto)?.run(&data).iter().map(|(_, field)| value_text(field, false)).collect())
+    }
+
+    #[test]
+    fn wildcards() {
+        let script = "set name = shop\nset orders[0].total = 1.5\nset orders[1].total = 2\nset orders[1].tags[0] = a\nset orders[1].tags[1] = \"x y\"";
+        assert_eq!(query(script, "orders[].total").unwrap(), ["1.5", "2"]);
+        assert_eq!(query(script, "orders[*].total").unwrap(), ["1.5", "2"]);
+        assert_eq!(query(script, "orders[1].tags[]").unwrap(), ["a", "x y"]);
+        assert_eq!(query(script, "orders.total").unwrap(), ["1.5"]);
+        assert_eq!(query(script, "orders[5].total").unwrap(), [""; 0]);
+        assert_eq!(query(script, "*").unwrap(), ["shop", "{\"total\":1.5}", "{\"total\":2,\"tags\":[\"a\",\"x y\"]}"]);
+        assert_eq!(query(script, "orders[].*").unwrap(), ["1.5", "2", "a", "x y"]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(query("", "orders[].sum").unwrap_err().to_string(), "field \"sum\" not found in message Order");
+        assert_eq!(query("", "name[1]").unwrap_err().to_string(), "field \"name\" is not repeated");
+        assert_eq!(query("", "name.size").unwrap_err().to_string(), "field \"name\" is not a message");
+        assert_eq!(query("", "orders[x]").unwrap_err().to_string(), "wrong index in \"orders[x]\"");
+    }
+
+    #[test]
+    fn json_values() {
+        let proto = ProtoData::new(PROTO).unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        apply_script(&mut data, &proto, "set orders[0].id = 7\nset orders[0].kind = B\nset orders[0].raw = 01ff\nset name = \"a\\\"b\"").unwrap();
+        let json: Vec<String> = Query::parse("*", &root_msg, &proto).unwrap().run(&data).iter().map(|(_, field)| value_json(field, false)).collect();
+        assert_eq!(json, ["{\"id\":\"7\",\"kind\":\"B\",\"raw\":\"Af8=\"}", "\"a\\\"b\""]);
+
+        // json names as for saved json files
+        let proto = ProtoData::new("message M { Item last_item = 1; }\nmessage Item { int32 item_count = 1 [json_name = \"count\"]; repeated int32 part_ids = 2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("M").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg.clone(), &mut 0).unwrap();
+        apply_script(&mut data, &proto, "set last_item.item_count = 2\nset last_item.part_ids[0] = 5").unwrap();
+        let json = |original_names| value_json(&data.fields[0], original_names);
+        assert_eq!(json(false), "{\"count\":2,\"partIds\":[5]}");
+        assert_eq!(json(true), "{\"item_count\":2,\"part_ids\":[5]}");
+    }
+}
@@ -0,0 +1,248 @@
+use std::fmt::{Display, Formatter};
+use std::net::IpAddr;
+use crate::wire::{FieldPath, MessageData, ScalarValue};
+
+// how the value of a string or bytes field is shown instead of the text or hex
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Renderer {
+    Utf8,
+    JsonPretty,
+    Uuid,
+    Base64,
+    IpAddress,
+}
+
+const NAMES: [(&str, Renderer); 5] = [
+    ("utf8", Renderer::Utf8),
+    ("json-pretty", Renderer::JsonPretty),
+    ("uuid", Renderer::Uuid),
+    ("base64", Renderer::Base64),
+    ("ip-address", Renderer::IpAddress),
+];
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+impl Renderer {
+    pub fn from_name(name: &str) -> Option<Renderer> {
+        NAMES.iter().find(|(n, _)| *n == name).map(|(_, r)| *r)
+    }
+
+    pub fn name(&self) -> &'static str {
+        NAMES.iter().find(|(_, r)| r == self).unwrap().0
+    }
+
+    // a string is already shown as text, only json is reformatted
+    pub fn applies_to(&self, typename: &str) -> bool {
+        match typename {
+            "bytes" => true,
+            "string" => *self == Renderer::JsonPretty,
+            _ => false,
+        }
+    }
+
+    // None if the bytes are not in the format, the default view is used then
+    pub fn render(&self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Renderer::Utf8 => String::from_utf8(bytes.to_vec()).ok(),
+            Renderer::JsonPretty => format_json(std::str::from_utf8(bytes).ok()?, true),
+            Renderer::Uuid => {
+                if bytes.len() != 16 { return None; }
+                let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                Some(format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]))
+            }
+            Renderer::Base64 => Some(base64_encode(bytes)),
+            Renderer::IpAddress => match bytes.len() {
+                4 => Some(IpAddr::from(<[u8; 4]>::try_from(bytes).unwrap()).to_string()),
+                16 => Some(IpAddr::from(<[u8; 16]>::try_from(bytes).unwrap()).to_string()),
+                _ => None,
+            },
+        }
+    }
+
+    // text for the edit prompt, json in one line
+    pub fn edit_text(&self, bytes: &[u8]) -> Option<String> {
+        match self {
+            Renderer::JsonPretty => format_json(std::str::from_utf8(bytes).ok()?, false),
+            _ => self.render(bytes),
+        }
+    }
+
+    // reverse of render, for editing
+    pub fn parse(&self, text: &str) -> Option<Vec<u8>> {
+        let text = text.trim();
+        match self {
+            Renderer::Utf8 => Some(text.as_bytes().to_vec()),
+            Renderer::JsonPretty => format_json(text, false).map(String::into_bytes),
+            Renderer::Uuid => {
+                let hex: String = text.chars().filter(|c| *c != '-').collect();
+                if hex.len() != 32 { return None; }
+                (0..16).map(|i| u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()).collect()
+            }
+            Renderer::Base64 => base64_decode(text),
+            Renderer::IpAddress => match text.parse::<IpAddr>().ok()? {
+                IpAddr::V4(ip) => Some(ip.octets().to_vec()),
+                IpAddr::V6(ip) => Some(ip.octets().to_vec()),
+            },
+        }
+    }
+
+    // the value to store in the field, the same type as the old one
+    pub fn parse_value(&self, text: &str, typename: &str) -> Option<ScalarValue> {
+        let bytes = self.parse(text)?;
+        if typename == "string" { String::from_utf8(bytes).ok().map(|text| ScalarValue::STR(text.into())) } else { Some(ScalarValue::BYTES(bytes.into())) }
+    }
+}
+
+impl Display for Renderer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// renderers of fields by their paths without indexes (a.b.id) or by the type (bytes, string)
+#[derive(Default, Clone)]
+pub struct Renderers(pub Vec<(String, Renderer)>);
+
+impl Renderers {
+    // FIELD=RENDERER from the command line
+    pub fn parse_rule(text: &str) -> Result<(String, Renderer), String> {
+        let (field, name) = text.split_once('=').ok_or_else(|| format!("FIELD=RENDERER expected: {}", text))?;
+        let renderer = Renderer::from_name(name.trim()).ok_or_else(|| {
+            let names: Vec<&str> = NAMES.iter().map(|(n, _)| *n).collect();
+            format!("unknown renderer {}, one of {} expected", name.trim(), names.join(", "))
+        })?;
+        Ok((field.trim().to_string(), renderer))
+    }
+
+    // a rule for the path is preferred to the rule for the type
+    pub fn find(&self, root: &MessageData, path: &FieldPath) -> Option<Renderer> {
+        if self.0.is_empty() { return None; }
+        let def = root.get_field_definition(path)?;
+        let typename = def.typename();
+        let mut names = vec![];
+        for len in 1..=path.0.len() {
+            names.push(root.get_field_definition(&FieldPath(path.0[..len].to_vec()))?.name());
+        }
+        let names = names.join(".");
+        let by_path = self.0.iter().find(|(field, _)| *field == names);
+        let by_type = || self.0.iter().find(|(field, _)| *field == typename);
+        by_path.or_else(by_type).map(|(_, r)| *r).filter(|r| r.applies_to(&typename))
+    }
+}
+
+// reformat json text in one line or with 2 spaces indents, None if brackets or quotes are not matched
+fn format_json(text: &str, pretty: bool) -> Option<String> {
+    let mut res = String::new();
+    let mut stack = vec![];
+    let mut chars = text.trim().chars().peekable();
+    let new_line = |res: &mut String, depth: usize| if pretty {
+        res.push('\n');
+        res.push_str(&"  ".repeat(depth));
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                res.push(c);
+                loop {
+                    let c = chars.next()?;
+                    res.push(c);
+                    match c {
+                        '\\' => res.push(chars.next()?),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '{' | '[' => {
+                res.push(c);
+                stack.push(if c == '{' { '}' } else { ']' });
+                while chars.peek().map_or(false, |c| c.is_whitespace()) { chars.next(); }
+                if chars.peek() == stack.last() {
+                    res.push(chars.next().unwrap());
+                    stack.pop();
+                } else {
+                    new_line(&mut res, stack.len());
+                }
+            }
+            '}' | ']' => {
+                if stack.pop() != Some(c) { return None; }
+                new_line(&mut res, stack.len());
+                res.push(c);
+            }
+            ',' => {
+                res.push(c);
+                new_line(&mut res, stack.len());
+            }
+            ':' => res.push_str(if pretty { ": " } else { ":" }),
+            c if c.is_whitespace() => {}
+            c => res.push(c),
+        }
+    }
+    if stack.is_empty() && !res.is_empty() { Some(res) } else { None }
+}
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut res = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - i * 8));
+        for i in 0..4 {
+            res.push(if i <= chunk.len() { BASE64[(n >> (18 - i * 6)) as usize & 0x3F] as char } else { '=' });
+        }
+    }
+    res
+}
+
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut res = vec![];
+    let (mut n, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        n = n << 6 | BASE64.iter().position(|b| *b == c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            res.push((n >> bits) as u8);
+        }
+    }
+    Some(res)
+}
+
+
+#[cfg(test)]
+mod render_tests {
+    use super::*;
+
+    #[test]
+    fn render_and_parse() {
+        let uuid = [0x12, 0x3e, 0x45, 0x67, 0xe8, 0x9b, 0x12, 0xd3, 0xa4, 0x56, 0x42, 0x66, 0x14, 0x17, 0x40, 0x00];
+        assert_eq!(Renderer::Uuid.render(&uuid).unwrap(), "123e4567-e89b-12d3-a456-426614174000");
+        assert_eq!(Renderer::Uuid.parse("123e4567-e89b-12d3-a456-426614174000").unwrap(), uuid);
+        assert_eq!(Renderer::Uuid.render(&uuid[1..]), None);
+        assert_eq!(Renderer::IpAddress.render(&[192, 168, 0, 1]).unwrap(), "192.168.0.1");
+        assert_eq!(Renderer::IpAddress.parse("::1").unwrap(), [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        for text in ["", "f", "fo", "foo", "foob"] {
+            let encoded = Renderer::Base64.render(text.as_bytes()).unwrap();
+            assert_eq!(Renderer::Base64.parse(&encoded).unwrap(), text.as_bytes());
+        }
+        assert_eq!(Renderer::Base64.render(b"fo").unwrap(), "Zm8=");
+        assert_eq!(Renderer::Base64.parse("Zm!="), None);
+        assert_eq!(Renderer::Utf8.render(&[0xFF]), None);
+    }
+
+    #[test]
+    fn json() {
+        let text = r#"{"a": [1, 2], "b": {}, "c": "x, {y}"}"#;
+        assert_eq!(Renderer::JsonPretty.render(text.as_bytes()).unwrap(),
+                   "{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": {},\n  \"c\": \"x, {y}\"\n}");
+        assert_eq!(Renderer::JsonPretty.edit_text(text.as_bytes()).unwrap(), r#"{"a":[1,2],"b":{},"c":"x, {y}"}"#);
+        assert_eq!(Renderer::JsonPretty.render(b"{\"a\": [1}"), None);
+        assert_eq!(Renderer::JsonPretty.render(b"\"open"), None);
+    }
+
+    #[test]
+    fn rules() {
+        assert_eq!(Renderers::parse_rule("m.id = uuid").unwrap(), ("m.id".to_string(), Renderer::Uuid));
+        assert!(Renderers::parse_rule("m.id").is_err());
+        assert_eq!(Renderers::parse_rule("bytes=hex").unwrap_err(), "unknown renderer hex, one of utf8, json-pretty, uuid, base64, ip-address expected");
+    }
+}
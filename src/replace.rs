@@ -0,0 +1,190 @@
+use crate::wire::{FieldPath, FieldValue, MessageData, ScalarValue};
+
+// the text searched by the replace command: plain, or a regular expression written as /expr/ with
+// . [abc] [^a-z] \d \w \s, the repeats * + ? of one item, ^ $ anchors and \ escapes (no groups and alternatives)
+pub enum Pattern {
+    Plain(String),
+    Regex { items: Vec<Item>, start: bool, end: bool },
+}
+
+pub struct Item {
+    atom: Atom,
+    min: usize,
+    max: usize,
+}
+
+enum Atom {
+    Char(char),
+    Any,
+    // ranges of chars, true if negated
+    Class(Vec<(char, char)>, bool),
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Char(expected) => c == *expected,
+            Atom::Any => true,
+            Atom::Class(ranges, negated) => ranges.iter().any(|(from, to)| (*from..=*to).contains(&c)) != *negated,
+        }
+    }
+}
+
+// \d \w \s, other escaped chars are themselves
+fn escaped(c: char) -> Atom {
+    match c {
+        'd' => Atom::Class(vec![('0', '9')], false),
+        'w' => Atom::Class(vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')], false),
+        's' => Atom::Class(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')], false),
+        'n' => Atom::Char('\n'),
+        't' => Atom::Char('\t'),
+        c => Atom::Char(c),
+    }
+}
+
+impl Pattern {
+    // /expr/ is a regular expression, other text is searched as is
+    pub fn parse(text: &str) -> Result<Pattern, String> {
+        if text.is_empty() { return Err("a text to find expected".into()); }
+        match text.strip_prefix('/').and_then(|rest| rest.strip_suffix('/')).filter(|expr| !expr.is_empty()) {
+            Some(expr) => Self::regex(expr),
+            None => Ok(Pattern::Plain(text.to_string())),
+        }
+    }
+
+    fn regex(expr: &str) -> Result<Pattern, String> {
+        let mut chars = expr.chars().peekable();
+        let start = chars.next_if_eq(&'^').is_some();
+        let mut items: Vec<Item> = vec![];
+        let mut end = false;
+        while let Some(c) = chars.next() {
+            if end { return Err("$ is allowed only at the end".into()); }
+            let atom = match c {
+                '$' => { end = true; continue; }
+                '.' => Atom::Any,
+                '\\' => escaped(chars.next().ok_or("an escaped char expected after \\")?),
+                '[' => {
+                    let negated = chars.next_if_eq(&'^').is_some();
+                    let mut ranges = vec![];
+                    loop {
+                        let from = match chars.next().ok_or("] expected")? {
+                            ']' if !ranges.is_empty() => break,
+                            '\\' => match escaped(chars.next().ok_or("] expected")?) {
+                                Atom::Class(class, _) => { ranges.extend(class); continue; }
+                                Atom::Char(c) => c,
+                                Atom::Any => unreachable!(),
+                            },
+                            c => c,
+                        };
+                        let to = if chars.peek() == Some(&'-') && chars.clone().nth(1).is_some_and(|c| c != ']') {
+                            chars.next();
+                            chars.next().unwrap()
+                        } else { from };
+                        if to < from { return Err(format!("wrong range {}-{}", from, to)); }
+                        ranges.push((from, to));
+                    }
+                    Atom::Class(ranges, negated)
+                }
+                '*' | '+' | '?' => {
+                    let item = items.last_mut().filter(|item| item.min == 1 && item.max == 1).ok_or(format!("nothing to repeat with {}", c))?;
+                    (item.min, item.max) = match c { '*' => (0, usize::MAX), '+' => (1, usize::MAX), _ => (0, 1) };
+                    continue;
+                }
+                c => Atom::Char(c),
+            };
+            items.push(Item { atom, min: 1, max: 1 });
+        }
+        Ok(Pattern::Regex { items, start, end })
+    }
+
+    // char ranges of the matches not overlapping each other, empty matches are skipped
+    fn find_all(&self, chars: &[char]) -> Vec<(usize, usize)> {
+        let mut res = vec![];
+        let mut pos = 0;
+        while pos < chars.len() {
+            let found = match self {
+                Pattern::Plain(text) => {
+                    let text: Vec<char> = text.chars().collect();
+                    chars[pos..].starts_with(&text).then_some(pos + text.len())
+                }
+                Pattern::Regex { items, start, end } if !*start || pos == 0 => match_here(items, *end, chars, pos),
+                Pattern::Regex { .. } => break,
+            };
+            match found {
+                Some(found) if found > pos => {
+                    res.push((pos, found));
+                    pos = found;
+                }
+                _ => pos += 1,
+            }
+        }
+        res
+    }
+
+    pub fn is_found(&self, text: &str) -> bool {
+        !self.find_all(&text.chars().collect::<Vec<_>>()).is_empty()
+    }
+
+    // the text with all matches replaced, the count of them
+    pub fn replace_all(&self, text: &str, with: &str) -> (String, usize) {
+        let chars: Vec<char> = text.chars().collect();
+        let found = self.find_all(&chars);
+        let mut res = String::new();
+        let mut pos = 0;
+        for (from, to) in &found {
+            res.extend(&chars[pos..*from]);
+            res.push_str(with);
+            pos = *to;
+        }
+        res.extend(&chars[pos..]);
+        (res, found.len())
+    }
+}
+
+// the end of the longest match at the position, the repeats take as much as they can
+fn match_here(items: &[Item], end: bool, chars: &[char], pos: usize) -> Option<usize> {
+    let Some((item, rest)) = items.split_first() else {
+        return (!end || pos == chars.len()).then_some(pos);
+    };
+    let count = chars[pos..].iter().take(item.max).take_while(|c| item.atom.matches(**c)).count();
+    (item.min..=count).rev().find_map(|count| match_here(rest, end, chars, pos + count))
+}
+
+// the string values containing the pattern, in the order of the data
+pub fn find_values(data: &MessageData, pattern: &Pattern) -> Vec<FieldPath> {
+    data.iter_depth_first().filter(|item| matches!(item.value(), FieldValue::SCALAR(ScalarValue::STR(text)) if pattern.is_found(text))).
+        map(|item| item.path).collect()
+}
+
+
+#[cfg(test)]
+mod replace_tests {
+    use super::*;
+
+    fn replace(pattern: &str, text: &str, with: &str) -> (String, usize) {
+        Pattern::parse(pattern).unwrap().replace_all(text, with)
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(replace("ab", "abcabab", "x"), ("xcxx".into(), 3));
+        assert_eq!(replace("aa", "aaa", "b"), ("ba".into(), 1));
+        assert_eq!(replace("/", "a/b", "-"), ("a-b".into(), 1));
+        assert_eq!(replace("ё", "ёжё", "e"), ("eжe".into(), 2));
+    }
+
+    #[test]
+    fn regex() {
+        assert_eq!(replace("/\\d+/", "a12b3", "#"), ("a#b#".into(), 2));
+        assert_eq!(replace("/^a./", "abab", "-"), ("-ab".into(), 1));
+        assert_eq!(replace("/b$/", "abab", "-"), ("aba-".into(), 1));
+        assert_eq!(replace("/[a-c]x?/", "axbd", "_"), ("__d".into(), 2));
+        assert_eq!(replace("/[^ ]+/", "one two", "w"), ("w w".into(), 2));
+        assert_eq!(replace("/x*/", "ab", "-"), ("ab".into(), 0));
+        assert_eq!(replace("/a.*b/", "a1b2b3", "-"), ("-3".into(), 1));
+        assert_eq!(replace("/\\./", "a.b", "!"), ("a!b".into(), 1));
+        assert_eq!(Pattern::parse("/+a/").err().as_deref(), Some("nothing to repeat with +"));
+        assert_eq!(Pattern::parse("/[z-a]/").err().as_deref(), Some("wrong range z-a"));
+        assert_eq!(Pattern::parse("/[ab/").err().as_deref(), Some("] expected"));
+    }
+}
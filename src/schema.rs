@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use crate::proto::{FieldProtoPtr, MessageProto, ProtoData};
+use crate::query::json_string;
+use crate::walk::DepthFirst;
+use crate::wire::{FieldPath, FieldValue, MessageData};
+
+// the lines of the schema browser: each message with its fields, each enum with its values;
+// a line of a message or a field has the path of its first value in the data, if there is one
+pub struct Schema {
+    pub lines: Vec<(String, Option<String>)>,
+    // the message or enum name and the field id (or the enum value) of each line
+    keys: Vec<(String, Option<i32>)>,
+}
+
+impl Schema {
+    pub fn new(proto: &ProtoData, data: &MessageData) -> Schema {
+        let first = first_values(data);
+        let path_text = |key: &(String, Option<i32>)| first.get(key).and_then(|path| path.to_text(data));
+        let mut res = Schema { lines: vec![], keys: vec![] };
+        // the messages of map entries are shown as the map fields
+        for msg in proto.messages().iter().filter(|msg| !msg.name.contains(',')) {
+            let key = (msg.name.clone(), None);
+            res.lines.push((with_comment(format!("message {}", msg.name), &msg.comment), path_text(&key)));
+            res.keys.push(key);
+            for field in &msg.fields {
+                let key = (msg.name.clone(), Some(field.id()));
+                res.lines.push((with_comment(format!("  {:>5} {:<20} {}", field.id(), field.name(), type_text(field)), &field.comment()), path_text(&key)));
+                res.keys.push(key);
+            }
+            if let Some(text) = reserved_text(msg) {
+                res.lines.push((format!("  reserved {}", text), None));
+                res.keys.push((msg.name.clone(), None));
+            }
+        }
+        for enm in proto.enums() {
+            res.lines.push((with_comment(format!("enum {}", enm.name), &enm.comment), None));
+            res.keys.push((enm.name.clone(), None));
+            for (name, id, comment) in &enm.variants {
+                res.lines.push((with_comment(format!("  {:>5} {}", id, name), comment), None));
+                res.keys.push((enm.name.clone(), Some(*id)));
+            }
+        }
+        res
+    }
+
+    // the line of the field definition, or of the message if the field is not set
+    pub fn find(&self, type_name: &str, id: Option<i32>) -> Option<usize> {
+        self.keys.iter().position(|(name, other)| name == type_name && *other == id)
+    }
+
+}
+
+// repeated int32, map<string,Item>, int32 [default = 5], the options as in the proto file: [deprecated = true, json_name = "count"]
+pub fn type_text(field: &FieldProtoPtr) -> String {
+    let typename = field.typename();
+    let common = field.get_common_definition();
+    let mut options = vec![];
+    let text = if typename.contains(',') { format!("map<{}>", typename) }
+    else if field.repeated() { format!("repeated {}", typename) }
+    else if field.required() { format!("required {}", typename) }
+    else {
+        if let Some(default) = &common.default { options.push(format!("default = {}", default)); }
+        typename
+    };
+    if field.deprecated() { options.push("deprecated = true".into()); }
+    if let Some(json_name) = &common.json_name { options.push(format!("json_name = {}", json_string(json_name))); }
+    if options.is_empty() { text } else { format!("{} [{}]", text, options.join(", ")) }
+}
+
+// 2, 5 to 7, "old"; None if nothing is reserved
+fn reserved_text(msg: &MessageProto) -> Option<String> {
+    let ranges = msg.reserved.iter().map(|range| if range.start() == range.end() { range.start().to_string() } else { format!("{} to {}", range.start(), range.end()) });
+    let items: Vec<String> = ranges.chain(msg.reserved_names.iter().map(|name| format!("\"{}\"", name))).collect();
+    (!items.is_empty()).then(|| items.join(", "))
+}
+
+// the first line of the comment after the text
+fn with_comment(text: String, comment: &str) -> String {
+    match comment.lines().map(str::trim).find(|line| !line.is_empty()) {
+        Some(line) => format!("{:<50} // {}", text, line),
+        None => text,
+    }
+}
+
+// the first value of each field by the message type and field id, the first message of each type by the type
+fn first_values(data: &MessageData) -> HashMap<(String, Option<i32>), FieldPath> {
+    let mut res = HashMap::new();
+    // the types of the messages containing the item, by depth
+    let mut parents = vec![data.def.name.clone()];
+    let mut it = DepthFirst::new(data);
+    while let Some(item) = it.next() {
+        parents.truncate(item.depth());
+        res.entry((parents.last().unwrap().clone(), Some(item.def().id()))).or_insert_with(|| item.path.clone());
+        if let FieldValue::MESSAGE(msg) = item.value() {
+            res.entry((msg.def.name.clone(), None)).or_insert_with(|| item.path.clone());
+            parents.push(msg.def.name.clone());
+        }
+    }
+    res
+}
+
+
+#[cfg(test)]
+mod schema_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::typedefs::PbReader;
+
+    #[test]
+    fn browse() {
+        let proto = ProtoData::new("message Root { string name = 1; repeated Order orders = 2; }\n// an order\nmessage Order {\n  // the number\n  int32 id = 1; Color color = 2; }\nenum Color { RED = 0; GREEN = 1; }").unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("Root").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        apply_script(&mut data, &proto, "set orders[0].id = 1\nset orders[1].id = 2\nset orders[1].color = GREEN").unwrap();
+        let schema = Schema::new(&proto, &data);
+        let lines: Vec<String> = schema.lines.iter().map(|(text, _)| text.trim_end().to_string()).collect();
+        assert_eq!(lines[0], format!("{:<50} // an order", "message Order"));
+        assert_eq!(lines[1], format!("{:<50} // the number", "      1 id                   int32"));
+        assert_eq!(lines[2], "      2 color                Color");
+        assert_eq!(lines[3..], [
+            "message Root",
+            "      1 name                 string",
+            "      2 orders               repeated Order",
+            "enum Color",
+            "      0 RED",
+            "      1 GREEN",
+        ]);
+        let paths: Vec<Option<&str>> = schema.lines.iter().map(|(_, path)| path.as_deref()).collect();
+        assert_eq!(paths[..6], [Some("orders[0]"), Some("orders[0].id"), Some("orders[1].color"), None, None, Some("orders[0]")]);
+
+        assert_eq!(schema.find("Order", Some(2)), Some(2));
+        assert_eq!(schema.find("Root", None), Some(3));
+        assert_eq!(schema.find("Color", Some(1)), Some(8));
+    }
+
+    #[test]
+    fn deprecated_and_reserved() {
+        let proto = ProtoData::new("message M { int32 old = 1 [deprecated = true, json_name = \"was\"]; reserved 2, 5 to 7, \"gone\"; }").unwrap().finalize().unwrap();
+        let data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, proto.get_message_definition("M").unwrap(), &mut 0).unwrap();
+        let schema = Schema::new(&proto, &data);
+        let lines: Vec<String> = schema.lines.iter().map(|(text, _)| text.trim_end().to_string()).collect();
+        assert_eq!(lines, ["message M", "      1 old                  int32 [deprecated = true, json_name = \"was\"]", "  reserved 2, 5 to 7, \"gone\""]);
+    }
+}
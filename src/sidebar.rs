@@ -0,0 +1,221 @@
+use crate::diff;
+use crate::inspect;
+use crate::overview;
+use crate::proto::{FieldProtoPtr, ProtoData};
+use crate::schema;
+use crate::wire::{FieldData, FieldValue, MessageData, ScalarValue};
+
+// the widest column of a table in the detail pane, longer values are cut
+const CELL_WIDTH: usize = 24;
+
+// the lines of the sidebar for the selected field: the full path, the declared type with its options,
+// the bytes it takes in the saved file, the comment of the definition and the value read as each type
+// of its wire type (not for messages); a field not set has only its definition
+pub fn lines(path: &str, def: &FieldProtoPtr, field: Option<&FieldData>, size: Option<usize>, proto: &ProtoData, width: usize) -> Vec<String> {
+    let mut res = vec![];
+    wrap(&format!("  {:<12}{}", "path", path), width, &mut res);
+    wrap(&format!("  {:<12}{}", "type", schema::type_text(def)), width, &mut res);
+    res.push(format!("  {:<12}{}", "size", size.map_or("not set".into(), overview::size_text)));
+    let comment = def.comment();
+    let comment: Vec<&str> = comment.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    if !comment.is_empty() {
+        res.push(String::new());
+        for line in comment { wrap(&format!("  // {}", line), width, &mut res); }
+    }
+    let raw = field.filter(|field| !matches!(field.value, FieldValue::MESSAGE(_))).and_then(|field| inspect::lines(field, proto).ok());
+    if let Some(raw) = raw {
+        res.push(String::new());
+        res.extend(raw.into_iter().map(|line| line.chars().take(width).collect()));
+    }
+    res
+}
+
+// the detail pane of the two-pane mode: the whole value of the selected field, the lines of a string,
+// a hex dump of bytes, the fields of a message; the values of a repeated message are a table with
+// a row for each value (`values`, the one at `selected` is marked) and a column for each scalar field
+pub fn detail_lines(path: &str, def: &FieldProtoPtr, field: Option<&FieldData>, values: &[&FieldData], selected: usize, proto: &ProtoData, width: usize) -> Vec<String> {
+    let mut res = vec![];
+    wrap(&format!("  {:<12}{}", "path", path), width, &mut res);
+    wrap(&format!("  {:<12}{}", "type", schema::type_text(def)), width, &mut res);
+    let Some(field) = field else {
+        res.push(format!("  {:<12}not set", "value"));
+        return res;
+    };
+    let inner = width.saturating_sub(2);
+    match &field.value {
+        FieldValue::SCALAR(ScalarValue::STR(text)) => {
+            res.push(format!("  {:<12}{} chars, {} lines", "size", text.chars().count(), text.split('\n').count()));
+            res.push(String::new());
+            for line in text.split('\n') {
+                let chars: Vec<char> = line.chars().collect();
+                if chars.is_empty() { res.push(String::new()); }
+                for chunk in chars.chunks(inner.max(1)) { res.push(format!("  {}", chunk.iter().collect::<String>())); }
+            }
+        }
+        FieldValue::SCALAR(ScalarValue::BYTES(bytes)) => {
+            res.push(format!("  {:<12}{}", "size", overview::size_text(bytes.len())));
+            res.push(String::new());
+            res.extend(dump(bytes, inner).into_iter().map(|line| format!("  {}", line)));
+        }
+        FieldValue::MESSAGE(_) if values.len() > 1 => {
+            res.push(format!("  {:<12}{} values", "size", values.len()));
+            res.push(String::new());
+            res.extend(table(values, selected, width));
+        }
+        FieldValue::MESSAGE(msg) => {
+            res.push(String::new());
+            let names: Vec<String> = msg.fields.iter().map(|child| {
+                if !child.def.repeated() { return child.def.name(); }
+                let index = msg.fields.iter().take_while(|other| !std::ptr::eq(*other, child)).filter(|other| other.def.id() == child.def.id()).count();
+                format!("{}[{}]", child.def.name(), index)
+            }).collect();
+            let name_width = names.iter().map(|name| name.chars().count()).max().unwrap_or(0).min(CELL_WIDTH);
+            for (name, child) in names.iter().zip(&msg.fields) {
+                let line = format!("  {:<2$}  {}", name, diff::value_to_string(child), name_width);
+                res.push(line.chars().take(width).collect());
+            }
+        }
+        FieldValue::SCALAR(_) => {
+            res.push(String::new());
+            wrap(&format!("  {:<12}{}", "value", diff::value_to_string(field)), width, &mut res);
+            if let Ok(raw) = inspect::lines(field, proto) {
+                res.push(String::new());
+                res.extend(raw.into_iter().map(|line| line.chars().take(width).collect()));
+            }
+        }
+    }
+    res
+}
+
+// the offset, the bytes in hex and as text, as many bytes on a line as fit the width (a multiple of 4)
+fn dump(bytes: &[u8], width: usize) -> Vec<String> {
+    let mut per_line = (width.saturating_sub(10) / 4).clamp(1, 16);
+    if per_line > 4 { per_line -= per_line % 4; }
+    bytes.chunks(per_line).enumerate().map(|(i, chunk)| {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let text: String = chunk.iter().map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' }).collect();
+        format!("{:06x}  {:<3$} {}", i * per_line, hex.join(" "), text, per_line * 3 - 1)
+    }).collect()
+}
+
+// a row for each value of the repeated message and a column for each scalar field set in any of them,
+// in the order of the definition; the columns that do not fit the width are left out
+fn table(values: &[&FieldData], selected: usize, width: usize) -> Vec<String> {
+    let messages: Vec<_> = values.iter().filter_map(|value| match &value.value { FieldValue::MESSAGE(msg) => Some(msg), _ => None }).collect();
+    let Some(first) = messages.first() else { return vec![]; };
+    let cell = |msg: &MessageData, id: i32| -> String {
+        let values: Vec<String> = msg.fields.iter().filter(|f| f.def.id() == id).map(diff::value_to_string).collect();
+        let text = values.join(", ");
+        if text.chars().count() > CELL_WIDTH { format!("{}…", text.chars().take(CELL_WIDTH - 1).collect::<String>()) } else { text }
+    };
+    let index_width = (values.len() - 1).to_string().len();
+    let mut used = 2 + index_width;
+    let mut columns: Vec<(String, Vec<String>, usize)> = vec![];
+    for def in first.def.fields.iter().filter(|def| !def.is_message()) {
+        if !messages.iter().any(|msg| msg.fields.iter().any(|f| f.def.id() == def.id())) { continue; }
+        let cells: Vec<String> = messages.iter().map(|msg| cell(msg, def.id())).collect();
+        let column_width = cells.iter().map(|text| text.chars().count()).chain([def.name().chars().count()]).max().unwrap_or(0);
+        if used + 2 + column_width > width { break; }
+        used += 2 + column_width;
+        columns.push((def.name(), cells, column_width));
+    }
+    let row = |mark: &str, index: String, texts: Vec<&str>| {
+        let mut line = format!("{}{:>2$}", mark, index, index_width);
+        for (text, (_, _, column_width)) in texts.iter().zip(&columns) { line.push_str(&format!("  {:<1$}", text, column_width)); }
+        line
+    };
+    let mut res = vec![row("  ", "#".into(), columns.iter().map(|(name, _, _)| name.as_str()).collect())];
+    res.push(format!("  {}", "─".repeat(used - 2)));
+    for i in 0..messages.len() {
+        res.push(row(if i == selected { "> " } else { "  " }, i.to_string(), columns.iter().map(|(_, cells, _)| cells[i].as_str()).collect()));
+    }
+    res
+}
+
+// the text cut into lines of the width, the next lines are indented as the values after the labels
+fn wrap(text: &str, width: usize, res: &mut Vec<String>) {
+    const INDENT: usize = 14;
+    let mut chars: Vec<char> = text.chars().collect();
+    while chars.len() > width && width > INDENT + 1 {
+        // at the last space of the value, a long word is cut
+        let end = (INDENT + 1..width).rev().find(|i| chars[*i] == ' ').unwrap_or(width);
+        let rest: Vec<char> = chars.split_off(end).into_iter().skip_while(|c| *c == ' ').collect();
+        res.push(chars.into_iter().collect());
+        chars = std::iter::repeat(' ').take(INDENT).chain(rest).collect();
+    }
+    res.push(chars.into_iter().collect());
+}
+
+
+#[cfg(test)]
+mod sidebar_tests {
+    use super::*;
+    use crate::typedefs::PbReader;
+    use crate::wire::MessageData;
+
+    #[test]
+    fn field_info() {
+        let proto = ProtoData::new("message M {\n  // the number\n  // of items\n  repeated int32 n = 1 [deprecated = true];\n  M m = 2; }").unwrap().finalize().unwrap();
+        let root_msg = proto.auto_detect_root_message().unwrap();
+        let bytes = [0x08, 0x05, 0x12, 0x02, 0x08, 0x01];
+        let data = MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, root_msg, &mut (bytes.len() as u32)).unwrap();
+        let n = &data.fields[0];
+        let lines: Vec<String> = lines("n[0]", &n.def, Some(n), Some(2), &proto, 30).into_iter().map(|line| line.trim_end().to_string()).collect();
+        assert_eq!(lines[..10], [
+            "  path        n[0]",
+            "  type        repeated int32",
+            "              [deprecated =",
+            "              true]",
+            "  size        2 B",
+            "",
+            "  // the number",
+            "  // of items",
+            "",
+            "  wire bytes  08 05",
+        ]);
+        assert_eq!(lines[12], "* int32          5");
+
+        // a message has no raw value
+        let m = &data.fields[1];
+        assert_eq!(super::lines("m", &m.def, Some(m), Some(4), &proto, 30).len(), 3);
+    }
+
+    #[test]
+    fn detail_pane() {
+        let proto = ProtoData::new("message M { string s = 1; bytes b = 2; repeated Item items = 3; }\n\
+            message Item { int32 id = 1; string name = 2; repeated int32 tags = 3; Item child = 4; }").unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("M").unwrap();
+        let bytes = [
+            0x0a, 0x07, b'a', b'b', b'\n', b'c', b'd', b'e', b'f',
+            0x12, 0x0a, 0x00, 0x01, 0x02, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47,
+            0x1a, 0x07, 0x08, 0x01, 0x12, 0x01, b'x', 0x18, 0x02,
+            0x1a, 0x04, 0x08, 0x02, 0x18, 0x03,
+        ];
+        let data = MessageData::new(&mut PbReader::new(bytes.as_slice()), &proto, root_msg, &mut (bytes.len() as u32)).unwrap();
+        let pane = |i: usize, values: &[&FieldData], selected: usize, width: usize| -> Vec<String> {
+            let field = &data.fields[i];
+            detail_lines("f", &field.def, Some(field), values, selected, &proto, width).into_iter().map(|line| line.trim_end().to_string()).collect()
+        };
+
+        // a string cut at the newlines and the width
+        assert_eq!(pane(0, &[], 0, 5)[2..], ["  size        7 chars, 2 lines", "", "  ab", "  cde", "  f"]);
+        // bytes on lines of 4 on a narrow pane
+        assert_eq!(pane(1, &[], 0, 30)[4..], ["  000000  00 01 02 41 ...A", "  000004  42 43 44 45 BCDE", "  000008  46 47       FG"]);
+        // one message as its fields, the values of a repeated message as a table
+        assert_eq!(pane(2, &[], 0, 30)[3..], ["  id       1", "  name     \"x\"", "  tags[0]  2"]);
+        let items: Vec<&FieldData> = data.fields[2..].iter().collect();
+        assert_eq!(pane(3, &items, 1, 30)[2..], [
+            "  size        2 values",
+            "",
+            "  #  id  name  tags",
+            "  ─────────────────",
+            "  0  1   \"x\"   2",
+            "> 1  2         3",
+        ]);
+        // the columns that do not fit are left out
+        assert_eq!(pane(3, &items, 1, 14)[4], "  #  id  name");
+
+        let unset = detail_lines("s", &data.fields[0].def, None, &[], 0, &proto, 30);
+        assert_eq!(unset[2].trim_end(), "  value       not set");
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt::{Display, Formatter};
+use crate::wire::{FieldValue, MessageData, ScalarValue};
+
+// integers are summed exactly, floats as f64
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+// summary of the values of a repeated numeric field
+#[derive(Debug, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub min: Number,
+    pub max: Number,
+    pub sum: Number,
+}
+
+impl Number {
+    fn from_scalar(value: &ScalarValue) -> Option<Number> {
+        match value {
+            ScalarValue::I32(v) | ScalarValue::S32(v) | ScalarValue::SF32(v) => Some(Number::Int(*v as i128)),
+            ScalarValue::U32(v) | ScalarValue::UF32(v) => Some(Number::Int(*v as i128)),
+            ScalarValue::I64(v) | ScalarValue::S64(v) | ScalarValue::SF64(v) => Some(Number::Int(*v as i128)),
+            ScalarValue::U64(v) | ScalarValue::UF64(v) => Some(Number::Int(*v as i128)),
+            ScalarValue::F32(v) => Some(Number::Float(*v as f64)),
+            ScalarValue::F64(v) => Some(Number::Float(*v)),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> f64 {
+        match self {
+            Number::Int(v) => *v as f64,
+            Number::Float(v) => *v,
+        }
+    }
+
+    // integers are compared exactly, 64-bit values above 2^53 differ as f64 too
+    fn less(&self, other: &Number) -> bool {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => a < b,
+            (a, b) => a.as_f64() < b.as_f64(),
+        }
+    }
+
+    fn add(self, other: Number) -> Number {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => Number::Int(a + b),
+            (a, b) => Number::Float(a.as_f64() + b.as_f64()),
+        }
+    }
+}
+
+impl Display for Number {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Int(v) => write!(f, "{}", v),
+            Number::Float(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl Stats {
+    // None if the field has no values or they are not numbers
+    pub fn calc(parent: &MessageData, id: i32) -> Option<Stats> {
+        let mut stats: Option<Stats> = None;
+        for field in parent.fields.iter().filter(|f| f.id() == id) {
+            let FieldValue::SCALAR(value) = &field.value else { return None; };
+            let number = Number::from_scalar(value)?;
+            stats = Some(match stats {
+                None => Stats { count: 1, min: number, max: number, sum: number },
+                Some(s) => Stats {
+                    count: s.count + 1,
+                    min: if number.less(&s.min) { number } else { s.min },
+                    max: if s.max.less(&number) { number } else { s.max },
+                    sum: s.sum.add(number),
+                },
+            });
+        }
+        stats
+    }
+
+    pub fn mean(&self) -> f64 { self.sum.as_f64() / self.count as f64 }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // up to 4 decimals, without trailing zeros
+        let mean = format!("{:.4}", self.mean());
+        let mean = mean.trim_end_matches('0').trim_end_matches('.');
+        write!(f, "count {}, min {}, max {}, mean {}, sum {}", self.count, self.min, self.max, mean, self.sum)
+    }
+}
+
+
+#[cfg(test)]
+mod stats_tests {
+    use super::*;
+    use crate::batch::apply_script;
+    use crate::proto::ProtoData;
+    use crate::typedefs::PbReader;
+
+    fn stats(script: &str, id: i32) -> Option<String> {
+        let proto = ProtoData::new("message M { repeated int64 a = 1; repeated double b = 2; repeated string c = 3; }").unwrap().finalize().unwrap();
+        let root_msg = proto.get_message_definition("M").unwrap();
+        let mut data = MessageData::new(&mut PbReader::new([].as_slice()), &proto, root_msg, &mut 0).unwrap();
+        apply_script(&mut data, &proto, script).unwrap();
+        Stats::calc(&data, id).map(|s| s.to_string())
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(stats("set a[0] = 3\nset a[1] = -1\nset a[2] = 9223372036854775807\nset a[3] = 9223372036854775807", 1).unwrap(),
+                   "count 4, min -1, max 9223372036854775807, mean 4611686018427387904, sum 18446744073709551616");
+        // equal as f64
+        assert_eq!(stats("set a[0] = 9007199254740993\nset a[1] = 9007199254740992", 1).unwrap(),
+                   "count 2, min 9007199254740992, max 9007199254740993, mean 9007199254740992, sum 18014398509481985");
+        assert_eq!(stats("set b[0] = 1.5\nset b[1] = 0.25\nset b[2] = 1", 2).unwrap(), "count 3, min 0.25, max 1.5, mean 0.9167, sum 2.75");
+    }
+
+    #[test]
+    fn not_numbers() {
+        assert_eq!(stats("", 1), None);
+        assert_eq!(stats("set c[0] = x", 3), None);
+    }
+}
@@ -0,0 +1,811 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use crate::proto::{FieldProtoPtr, MessageProtoPtr, ProtoData};
+use crate::query::json_string;
+use crate::render::{base64_decode, base64_encode};
+use crate::typedefs::{CommonFieldProto, PbReader};
+use crate::wire::{FieldData, FieldValue, MessageData, ScalarValue, MAX_NESTING};
+
+// the format of a data file by its extension (before .gz or .zst), other files are binary
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format {
+    Binary,
+    Json,
+    Text,
+}
+
+impl Format {
+    pub fn of_path(path: &Path) -> Format {
+        let path = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz" | "zst" | "zstd") => Path::new(path.file_stem().unwrap_or_default()),
+            _ => path,
+        };
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("json") => Format::Json,
+            Some("textproto" | "txtpb" | "pbtxt" | "prototxt") => Format::Text,
+            _ => Format::Binary,
+        }
+    }
+}
+
+fn invalid(text: String) -> io::Error { io::Error::new(io::ErrorKind::InvalidData, text) }
+
+// the limit to read binary data of the length by, positions in the data are kept in 32 bits
+pub fn read_limit(len: u64) -> io::Result<u32> {
+    u32::try_from(len).map_err(|_| invalid(format!("{} bytes of data, more than the 4 GiB that can be read", len)))
+}
+
+// the data of the root message read from the content of a file in the format
+pub fn decode(bytes: &[u8], proto: &ProtoData, root: MessageProtoPtr, format: Format) -> io::Result<MessageData> {
+    if format == Format::Binary {
+        return MessageData::new(&mut PbReader::new(bytes), proto, root, &mut read_limit(bytes.len() as u64)?);
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| invalid("the text is not UTF-8".into()))?;
+    match format {
+        Format::Json => {
+            let value = JsonParser { chars: text.chars().collect(), pos: 0, depth: 0 }.document()?;
+            message_from_json(&value, root, "", 0)
+        }
+        _ => {
+            let tokens = tokenize(text)?;
+            let mut pos = 0;
+            message_from_text(&tokens, &mut pos, root, None, 0)
+        }
+    }
+}
+
+// the content of the file in the format; unknown fields have no names, they are not written as text;
+// json members are named by the json names of the fields or by their names in the proto file (original_names)
+pub fn encode(data: &MessageData, proto: &ProtoData, format: Format, original_names: bool) -> io::Result<Vec<u8>> {
+    let mut res = String::new();
+    match format {
+        Format::Binary => {
+            let mut bytes = vec![];
+            data.write(&mut bytes, proto, data.def.clone())?;
+            return Ok(bytes);
+        }
+        Format::Json => {
+            write_json(data, proto, 0, original_names, &mut res)?;
+            res.push('\n');
+        }
+        Format::Text => write_text(data, proto, 0, &mut res)?,
+    }
+    Ok(res.into_bytes())
+}
+
+// the fields with no definition in all the messages, they are lost in json and text
+pub fn unknown_count(data: &MessageData) -> usize {
+    data.fields.iter().map(|field| match &field.value {
+        FieldValue::SCALAR(ScalarValue::UNKNOWN(..)) => 1,
+        FieldValue::MESSAGE(msg) => unknown_count(msg),
+        _ => 0,
+    }).sum()
+}
+
+fn is_map(def: &FieldProtoPtr) -> bool { def.typename().contains(',') }
+
+// the fields of the message with their values, in the order the first value of each field is found
+fn field_groups(msg: &MessageData) -> Vec<(&FieldProtoPtr, Vec<&FieldData>)> {
+    let mut groups: Vec<(&FieldProtoPtr, Vec<&FieldData>)> = vec![];
+    for field in msg.fields.iter().filter(|field| !matches!(field.value, FieldValue::SCALAR(ScalarValue::UNKNOWN(..)))) {
+        match groups.iter_mut().find(|(def, _)| def.id() == field.def.id()) {
+            Some((_, values)) => values.push(field),
+            None => groups.push((&field.def, vec![field])),
+        }
+    }
+    groups
+}
+
+// bytes decoded as a message are saved as the bytes of the message
+fn decoded_bytes(msg: &MessageData, proto: &ProtoData) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    msg.write(&mut bytes, proto, msg.def.clone())?;
+    Ok(bytes)
+}
+
+fn write_json(msg: &MessageData, proto: &ProtoData, indent: usize, original_names: bool, res: &mut String) -> io::Result<()> {
+    let groups = field_groups(msg);
+    if groups.is_empty() {
+        res.push_str("{}");
+        return Ok(());
+    }
+    let pad = " ".repeat(indent + 2);
+    res.push_str("{\n");
+    for (i, (def, values)) in groups.iter().enumerate() {
+        let name = if original_names { def.name() } else { def.json_name() };
+        res.push_str(&format!("{}{}: ", pad, json_string(&name)));
+        if is_map(def) {
+            res.push_str("{\n");
+            for (j, entry) in values.iter().enumerate() {
+                let FieldValue::MESSAGE(entry) = &entry.value else { continue; };
+                let part = |id: i32| entry.fields.iter().find(|f| f.def.id() == id);
+                let key = part(1).map_or(String::new(), |key| match &key.value {
+                    FieldValue::SCALAR(ScalarValue::STR(text)) => text.to_string(),
+                    FieldValue::SCALAR(scalar) => scalar.to_string(),
+                    _ => String::new(),
+                });
+                res.push_str(&format!("{}  {}: ", pad, json_string(&key)));
+                match part(2) {
+                    Some(value) => json_value(value, proto, indent + 4, original_names, res)?,
+                    None => res.push_str(&entry.def.fields.iter().find(|f| f.id() == 2).map_or("null".into(), |def| zero_json(def))),
+                }
+                res.push_str(if j + 1 < values.len() { ",\n" } else { "\n" });
+            }
+            res.push_str(&format!("{}}}", pad));
+        } else if def.repeated() {
+            let messages = def.is_message();
+            res.push('[');
+            for (j, value) in values.iter().enumerate() {
+                if messages { res.push_str(&format!("\n{}  ", pad)); }
+                json_value(value, proto, indent + 4, original_names, res)?;
+                if j + 1 < values.len() { res.push_str(if messages { "," } else { ", " }); }
+            }
+            if messages { res.push_str(&format!("\n{}", pad)); }
+            res.push(']');
+        } else {
+            json_value(values[0], proto, indent + 2, original_names, res)?;
+        }
+        res.push_str(if i + 1 < groups.len() { ",\n" } else { "\n" });
+    }
+    res.push_str(&format!("{}}}", " ".repeat(indent)));
+    Ok(())
+}
+
+// the value of a map entry that is not set
+fn zero_json(def: &FieldProtoPtr) -> String {
+    match def.zero() {
+        FieldValue::MESSAGE(_) => "{}".into(),
+        FieldValue::SCALAR(scalar) => scalar_json(def, &scalar),
+    }
+}
+
+fn json_value(field: &FieldData, proto: &ProtoData, indent: usize, original_names: bool, res: &mut String) -> io::Result<()> {
+    match &field.value {
+        FieldValue::MESSAGE(msg) if field.def.is_message() => write_json(msg, proto, indent, original_names, res)?,
+        FieldValue::MESSAGE(msg) => res.push_str(&json_string(&base64_encode(&decoded_bytes(msg, proto)?))),
+        FieldValue::SCALAR(scalar) => res.push_str(&scalar_json(&field.def, scalar)),
+    }
+    Ok(())
+}
+
+// as in the protobuf json mapping: 64-bit integers and bytes (base64) are strings, enums by name
+fn scalar_json(def: &FieldProtoPtr, scalar: &ScalarValue) -> String {
+    let special = |v: f64| if v.is_nan() { "\"NaN\"" } else if v > 0.0 { "\"Infinity\"" } else { "\"-Infinity\"" };
+    match scalar {
+        ScalarValue::STR(text) => json_string(text),
+        ScalarValue::BYTES(bytes) => json_string(&base64_encode(bytes)),
+        ScalarValue::ENUM(value) => def.get_enum_name_by_index(*value).map_or(value.to_string(), json_string),
+        ScalarValue::F32(v) if !v.is_finite() => special(*v as f64).into(),
+        ScalarValue::F64(v) if !v.is_finite() => special(*v).into(),
+        ScalarValue::I64(_) | ScalarValue::U64(_) | ScalarValue::S64(_) | ScalarValue::UF64(_) | ScalarValue::SF64(_) => json_string(&scalar.to_string()),
+        _ => scalar.to_string(),
+    }
+}
+
+// the fields of a map entry are key and value, an extension field (package.name) is [package.name]
+fn text_name(msg_def: &MessageProtoPtr, def: &FieldProtoPtr) -> String {
+    let name = def.name();
+    if msg_def.name.contains(',') {
+        return if def.id() == 1 { "key".into() } else { "value".into() };
+    }
+    match name.strip_prefix('(').and_then(|name| name.strip_suffix(')')) {
+        Some(extension) => format!("[{}]", extension),
+        None => name,
+    }
+}
+
+fn write_text(msg: &MessageData, proto: &ProtoData, indent: usize, res: &mut String) -> io::Result<()> {
+    let pad = " ".repeat(indent);
+    for field in &msg.fields {
+        let name = text_name(&msg.def, &field.def);
+        match &field.value {
+            FieldValue::SCALAR(ScalarValue::UNKNOWN(..)) => {}
+            FieldValue::MESSAGE(child) if field.def.is_message() => {
+                res.push_str(&format!("{}{} {{\n", pad, name));
+                write_text(child, proto, indent + 2, res)?;
+                res.push_str(&format!("{}}}\n", pad));
+            }
+            FieldValue::MESSAGE(child) => res.push_str(&format!("{}{}: {}\n", pad, name, quote(&decoded_bytes(child, proto)?))),
+            FieldValue::SCALAR(scalar) => res.push_str(&format!("{}{}: {}\n", pad, name, scalar_text(&field.def, scalar))),
+        }
+    }
+    Ok(())
+}
+
+fn scalar_text(def: &FieldProtoPtr, scalar: &ScalarValue) -> String {
+    let special = |v: f64| if v.is_nan() { "nan" } else if v > 0.0 { "inf" } else { "-inf" };
+    match scalar {
+        ScalarValue::STR(text) => quote(text.as_bytes()),
+        ScalarValue::BYTES(bytes) => quote(bytes),
+        ScalarValue::ENUM(value) => def.get_enum_name_by_index(*value).map_or(value.to_string(), str::to_string),
+        ScalarValue::F32(v) if !v.is_finite() => special(*v as f64).into(),
+        ScalarValue::F64(v) if !v.is_finite() => special(*v).into(),
+        _ => scalar.to_string(),
+    }
+}
+
+// a string of the text format, the bytes that are not printable ASCII or UTF-8 are octal escapes
+fn quote(bytes: &[u8]) -> String {
+    let mut res = String::from("\"");
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '"' => res.push_str("\\\""),
+                '\\' => res.push_str("\\\\"),
+                '\n' => res.push_str("\\n"),
+                '\r' => res.push_str("\\r"),
+                '\t' => res.push_str("\\t"),
+                c if (c as u32) < 0x20 || c as u32 == 0x7f => res.push_str(&format!("\\{:03o}", c as u32)),
+                c => res.push(c),
+            }
+        }
+        for byte in chunk.invalid() { res.push_str(&format!("\\{:03o}", byte)); }
+    }
+    res.push('"');
+    res
+}
+
+// the field values are built as new ones, repeated numbers are packed as proto3 writes them by default
+fn push_value(msg: &mut MessageData, def: &FieldProtoPtr, value: FieldValue) {
+    if MessageData::packable(def) && !msg.packed.contains(&def.id()) { msg.packed.push(def.id()); }
+    msg.fields.push(FieldData { def: def.clone(), pos: usize::MAX, value, wire: None });
+}
+
+fn message_def(def: &FieldProtoPtr) -> Option<MessageProtoPtr> {
+    match def.zero() {
+        FieldValue::MESSAGE(msg) => Some(msg.def.clone()),
+        _ => None,
+    }
+}
+
+// a scalar from its text, with the range of the type in the error
+fn parse_scalar(def: &FieldProtoPtr, text: &str, path: &str) -> io::Result<ScalarValue> {
+    def.parse_value(text).ok_or_else(|| {
+        let reason = CommonFieldProto::range_error(def.as_ref(), text).unwrap_or_else(|| format!("{} is not a {}", text, def.typename()));
+        invalid(format!("{}: {}", path, reason))
+    })
+}
+
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn kind(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool(_) => "a bool",
+            Json::Number(_) => "a number",
+            Json::Str(_) => "a string",
+            Json::Array(_) => "an array",
+            Json::Object(_) => "an object",
+        }
+    }
+}
+
+// a message takes two levels at most, its object and the array or map object holding it
+const JSON_NESTING: usize = 2 * MAX_NESTING + 1;
+
+struct JsonParser {
+    chars: Vec<char>,
+    pos: usize,
+    // of the arrays and objects being read, each level is read by recursion
+    depth: usize,
+}
+
+impl JsonParser {
+    fn error(&self, text: &str) -> io::Error {
+        let line = self.chars[..self.pos.min(self.chars.len())].iter().filter(|c| **c == '\n').count() + 1;
+        invalid(format!("line {}: {}", line, text))
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) { self.pos += 1; }
+    }
+
+    fn expect(&mut self, c: char) -> io::Result<()> {
+        self.skip_spaces();
+        if self.chars.get(self.pos) != Some(&c) { return Err(self.error(&format!("'{}' expected", c))); }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn document(&mut self) -> io::Result<Json> {
+        let value = self.value()?;
+        self.skip_spaces();
+        if self.pos < self.chars.len() { return Err(self.error("the text after the value")); }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> io::Result<Json> {
+        self.skip_spaces();
+        let Some(c) = self.chars.get(self.pos).copied() else { return Err(self.error("a value expected")); };
+        match c {
+            '{' | '[' => {
+                if self.depth == JSON_NESTING {
+                    return Err(self.error(&format!("values nested deeper than {} levels", JSON_NESTING)));
+                }
+                self.pos += 1;
+                self.depth += 1;
+                let value = if c == '{' { self.object() } else { self.array() };
+                self.depth -= 1;
+                value
+            }
+            '"' => Ok(Json::Str(self.string()?)),
+            c if c == '-' || c.is_ascii_digit() => {
+                let start = self.pos;
+                while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) { self.pos += 1; }
+                Ok(Json::Number(self.chars[start..self.pos].iter().collect()))
+            }
+            _ => {
+                let start = self.pos;
+                while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_alphabetic()) { self.pos += 1; }
+                match self.chars[start..self.pos].iter().collect::<String>().as_str() {
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    "null" => Ok(Json::Null),
+                    _ => {
+                        self.pos = start;
+                        Err(self.error("a value expected"))
+                    }
+                }
+            }
+        }
+    }
+
+    // the members after '{'
+    fn object(&mut self) -> io::Result<Json> {
+        let mut members = vec![];
+        self.skip_spaces();
+        if self.chars.get(self.pos) == Some(&'}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_spaces();
+            let name = self.string()?;
+            self.expect(':')?;
+            members.push((name, self.value()?));
+            self.skip_spaces();
+            match self.chars.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some('}') => { self.pos += 1; return Ok(Json::Object(members)); }
+                _ => return Err(self.error("',' or '}' expected")),
+            }
+        }
+    }
+
+    // the items after '['
+    fn array(&mut self) -> io::Result<Json> {
+        let mut items = vec![];
+        self.skip_spaces();
+        if self.chars.get(self.pos) == Some(&']') {
+            self.pos += 1;
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_spaces();
+            match self.chars.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some(']') => { self.pos += 1; return Ok(Json::Array(items)); }
+                _ => return Err(self.error("',' or ']' expected")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        if self.chars.get(self.pos) != Some(&'"') { return Err(self.error("a string expected")); }
+        self.pos += 1;
+        let mut res = String::new();
+        loop {
+            let Some(c) = self.chars.get(self.pos).copied() else { return Err(self.error("the string is not closed")); };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(res),
+                '\\' => {
+                    let Some(escape) = self.chars.get(self.pos).copied() else { return Err(self.error("the string is not closed")); };
+                    self.pos += 1;
+                    match escape {
+                        'n' => res.push('\n'),
+                        'r' => res.push('\r'),
+                        't' => res.push('\t'),
+                        'b' => res.push('\u{8}'),
+                        'f' => res.push('\u{c}'),
+                        'u' => {
+                            let mut code = self.hex4()?;
+                            // a character out of the basic plane is a pair of surrogates
+                            if (0xd800..0xdc00).contains(&code) && self.chars.get(self.pos..self.pos + 2) == Some(&['\\', 'u']) {
+                                self.pos += 2;
+                                let low = self.hex4()?;
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                            }
+                            res.push(char::from_u32(code).ok_or_else(|| self.error("not a character"))?);
+                        }
+                        other => res.push(other), // \" \\ \/
+                    }
+                }
+                c => res.push(c),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> io::Result<u32> {
+        let digits: String = self.chars.get(self.pos..self.pos + 4).unwrap_or_default().iter().collect();
+        let code = u32::from_str_radix(&digits, 16).map_err(|_| self.error("4 hex digits expected after \\u"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+}
+
+// the nesting is limited as for the binary data, it is written as that
+fn message_from_json(value: &Json, def: MessageProtoPtr, path: &str, depth: usize) -> io::Result<MessageData> {
+    let Json::Object(members) = value else {
+        return Err(invalid(format!("{}: an object expected, not {}", if path.is_empty() { "the root" } else { path }, value.kind())));
+    };
+    if depth > MAX_NESTING {
+        return Err(invalid(format!("messages nested deeper than {} levels", MAX_NESTING)));
+    }
+    let mut msg = MessageData { def: def.clone(), fields: vec![], packed: vec![] };
+    for (name, value) in members {
+        let field_path = if path.is_empty() { name.clone() } else { format!("{}.{}", path, name) };
+        // the json name or the name in the proto file
+        let Some(field_def) = def.fields.iter().find(|f| f.id() != 0 && (f.name() == *name || f.json_name() == *name)) else {
+            return Err(invalid(format!("{}: no such field in {}", field_path, def.name)));
+        };
+        match value {
+            Json::Null => {}
+            Json::Object(entries) if is_map(field_def) => {
+                let entry_def = message_def(field_def).unwrap();
+                let (Some(key_def), Some(value_def)) = (entry_def.get_field(1), entry_def.get_field(2)) else { continue; };
+                for (key, value) in entries {
+                    let entry_path = format!("{}[{}]", field_path, json_string(key));
+                    let key = match key_def.typename().as_str() {
+                        "string" => ScalarValue::STR(key.as_str().into()),
+                        _ => parse_scalar(&key_def, key, &entry_path)?,
+                    };
+                    let mut entry = MessageData { def: entry_def.clone(), fields: vec![], packed: vec![] };
+                    push_value(&mut entry, &key_def, FieldValue::SCALAR(key));
+                    push_value(&mut entry, &value_def, value_from_json(&value_def, value, &entry_path, depth + 2)?);
+                    push_value(&mut msg, field_def, FieldValue::MESSAGE(Arc::new(entry)));
+                }
+            }
+            Json::Array(items) if field_def.repeated() => {
+                for (i, item) in items.iter().enumerate() {
+                    let value = value_from_json(field_def, item, &format!("{}[{}]", field_path, i), depth + 1)?;
+                    push_value(&mut msg, field_def, value);
+                }
+            }
+            value if !field_def.repeated() => {
+                let value = value_from_json(field_def, value, &field_path, depth + 1)?;
+                push_value(&mut msg, field_def, value);
+            }
+            value => return Err(invalid(format!("{}: an array expected, not {}", field_path, value.kind()))),
+        }
+    }
+    Ok(msg)
+}
+
+// a message value is at the depth
+fn value_from_json(def: &FieldProtoPtr, value: &Json, path: &str, depth: usize) -> io::Result<FieldValue> {
+    if let Some(msg_def) = message_def(def) {
+        return Ok(FieldValue::MESSAGE(Arc::new(message_from_json(value, msg_def, path, depth)?)));
+    }
+    let mismatch = || invalid(format!("{}: {} is not a {}", path, value.kind(), def.typename()));
+    let scalar = match (def.typename().as_str(), value) {
+        ("string", Json::Str(text)) => ScalarValue::STR(text.as_str().into()),
+        // the url-safe alphabet is accepted too
+        ("bytes", Json::Str(text)) => {
+            let text = text.replace('-', "+").replace('_', "/");
+            ScalarValue::BYTES(base64_decode(&text).ok_or_else(|| invalid(format!("{}: not base64", path)))?.into())
+        }
+        ("bool", Json::Bool(value)) => ScalarValue::BOOL(*value),
+        ("float" | "double", Json::Str(text)) if ["NaN", "Infinity", "-Infinity"].contains(&text.as_str()) => {
+            parse_scalar(def, &text.replace("Infinity", "inf"), path)?
+        }
+        ("string" | "bytes" | "bool", _) => return Err(mismatch()),
+        // numbers may be strings, enums are names or numbers
+        (_, Json::Number(text) | Json::Str(text)) => parse_scalar(def, text, path)?,
+        _ => return Err(mismatch()),
+    };
+    Ok(FieldValue::SCALAR(scalar))
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Number(String),
+    Str(Vec<u8>),
+    Punct(char),
+}
+
+// the tokens of the text format with their lines, # starts a comment
+fn tokenize(text: &str) -> io::Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut res = vec![];
+    let mut line = 1;
+    let mut pos = 0;
+    let error = |line: usize, text: &str| invalid(format!("line {}: {}", line, text));
+    while let Some(c) = chars.get(pos).copied() {
+        match c {
+            '\n' => { line += 1; pos += 1; }
+            c if c.is_whitespace() => pos += 1,
+            '#' => while chars.get(pos).is_some_and(|c| *c != '\n') { pos += 1; },
+            '{' | '}' | '<' | '>' | ':' | '[' | ']' | ',' | ';' => { res.push((Token::Punct(c), line)); pos += 1; }
+            '"' | '\'' => {
+                pos += 1;
+                let mut bytes = vec![];
+                loop {
+                    let Some(next) = chars.get(pos).copied().filter(|next| *next != '\n') else { return Err(error(line, "the string is not closed")); };
+                    pos += 1;
+                    if next == c { break; }
+                    if next != '\\' {
+                        bytes.extend(next.to_string().as_bytes());
+                        continue;
+                    }
+                    let Some(escape) = chars.get(pos).copied() else { return Err(error(line, "the string is not closed")); };
+                    pos += 1;
+                    let digits = |pos: usize, radix: u32, max: usize| chars[pos..].iter().take(max).take_while(|c| c.is_digit(radix)).count();
+                    match escape {
+                        'n' => bytes.push(b'\n'),
+                        'r' => bytes.push(b'\r'),
+                        't' => bytes.push(b'\t'),
+                        'a' => bytes.push(7),
+                        'b' => bytes.push(8),
+                        'f' => bytes.push(12),
+                        'v' => bytes.push(11),
+                        '0'..='7' => {
+                            let len = digits(pos - 1, 8, 3);
+                            let text: String = chars[pos - 1..pos - 1 + len].iter().collect();
+                            bytes.push(u32::from_str_radix(&text, 8).unwrap() as u8);
+                            pos += len - 1;
+                        }
+                        'x' | 'u' | 'U' => {
+                            let max = match escape { 'x' => 2, 'u' => 4, _ => 8 };
+                            let len = digits(pos, 16, max);
+                            if len == 0 || (escape != 'x' && len < max) { return Err(error(line, &format!("hex digits expected after \\{}", escape))); }
+                            let code = u32::from_str_radix(&chars[pos..pos + len].iter().collect::<String>(), 16).unwrap();
+                            pos += len;
+                            if escape == 'x' {
+                                bytes.push(code as u8);
+                            } else {
+                                let c = char::from_u32(code).ok_or_else(|| error(line, "not a character"))?;
+                                bytes.extend(c.to_string().as_bytes());
+                            }
+                        }
+                        other => bytes.extend(other.to_string().as_bytes()), // \" \' \\ \?
+                    }
+                }
+                res.push((Token::Str(bytes), line));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = pos;
+                while chars.get(pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '.') { pos += 1; }
+                res.push((Token::Name(chars[start..pos].iter().collect()), line));
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' || c == '+' => {
+                let start = pos;
+                pos += 1;
+                while let Some(next) = chars.get(pos).copied() {
+                    let exponent = (next == '-' || next == '+') && matches!(chars[pos - 1], 'e' | 'E') && !chars[start..pos].iter().any(|c| *c == 'x' || *c == 'X');
+                    if !(next.is_ascii_alphanumeric() || next == '.' || next == '_' || exponent) { break; }
+                    pos += 1;
+                }
+                res.push((Token::Number(chars[start..pos].iter().collect()), line));
+            }
+            c => return Err(error(line, &format!("unexpected '{}'", c))),
+        }
+    }
+    Ok(res)
+}
+
+// the fields up to the closing bracket, or to the end of the text for the root; nested as deep as the binary data
+fn message_from_text(tokens: &[(Token, usize)], pos: &mut usize, def: MessageProtoPtr, end: Option<char>, depth: usize) -> io::Result<MessageData> {
+    let mut msg = MessageData { def: def.clone(), fields: vec![], packed: vec![] };
+    let last_line = tokens.last().map_or(1, |(_, line)| *line);
+    if depth > MAX_NESTING {
+        let line = tokens.get(*pos).map_or(last_line, |(_, line)| *line);
+        return Err(invalid(format!("line {}: messages nested deeper than {} levels", line, MAX_NESTING)));
+    }
+    loop {
+        let Some((token, line)) = tokens.get(*pos) else {
+            return match end {
+                Some(end) => Err(invalid(format!("line {}: '{}' expected", last_line, end))),
+                None => Ok(msg),
+            };
+        };
+        let error = |text: String| invalid(format!("line {}: {}", line, text));
+        *pos += 1;
+        let name = match token {
+            Token::Punct(c) if Some(*c) == end => return Ok(msg),
+            Token::Punct(',' | ';') => continue,
+            Token::Name(name) => name.clone(),
+            Token::Punct('[') => match (tokens.get(*pos), tokens.get(*pos + 1)) {
+                (Some((Token::Name(name), _)), Some((Token::Punct(']'), _))) => {
+                    *pos += 2;
+                    format!("[{}]", name)
+                }
+                _ => return Err(error("an extension name and ']' expected".into())),
+            },
+            _ => return Err(error("a field name expected".into())),
+        };
+        let Some(field_def) = def.fields.iter().find(|f| f.id() != 0 && text_name(&def, f) == name) else {
+            return Err(error(format!("no field {} in {}", name, def.name)));
+        };
+        let colon = matches!(tokens.get(*pos), Some((Token::Punct(':'), _)));
+        if colon { *pos += 1; }
+        if matches!(tokens.get(*pos), Some((Token::Punct('['), _))) {
+            *pos += 1;
+            if matches!(tokens.get(*pos), Some((Token::Punct(']'), _))) {
+                *pos += 1;
+                continue;
+            }
+            loop {
+                let value = value_from_text(tokens, pos, field_def, &name, depth + 1)?;
+                push_value(&mut msg, field_def, value);
+                match tokens.get(*pos) {
+                    Some((Token::Punct(','), _)) => *pos += 1,
+                    Some((Token::Punct(']'), _)) => { *pos += 1; break; }
+                    _ => return Err(error(format!("',' or ']' expected in the values of {}", name))),
+                }
+            }
+        } else {
+            if !colon && !field_def.is_message() { return Err(error(format!("':' expected after {}", name))); }
+            let value = value_from_text(tokens, pos, field_def, &name, depth + 1)?;
+            push_value(&mut msg, field_def, value);
+        }
+    }
+}
+
+// a message value is at the depth
+fn value_from_text(tokens: &[(Token, usize)], pos: &mut usize, def: &FieldProtoPtr, name: &str, depth: usize) -> io::Result<FieldValue> {
+    let Some((token, line)) = tokens.get(*pos) else { return Err(invalid(format!("a value of {} expected at the end", name))); };
+    let path = format!("line {}: {}", line, name);
+    *pos += 1;
+    if let Some(msg_def) = message_def(def) {
+        let end = match token {
+            Token::Punct('{') => '}',
+            Token::Punct('<') => '>',
+            _ => return Err(invalid(format!("{}: '{{' expected", path))),
+        };
+        return Ok(FieldValue::MESSAGE(Arc::new(message_from_text(tokens, pos, msg_def, Some(end), depth)?)));
+    }
+    let scalar = match token {
+        // adjacent strings are one value
+        Token::Str(first) => {
+            let mut bytes = first.clone();
+            while let Some((Token::Str(next), _)) = tokens.get(*pos) {
+                bytes.extend(next);
+                *pos += 1;
+            }
+            match def.typename().as_str() {
+                "string" => ScalarValue::STR(String::from_utf8(bytes).map_err(|_| invalid(format!("{}: the string is not UTF-8", path)))?.into()),
+                "bytes" => ScalarValue::BYTES(bytes.into()),
+                typename => return Err(invalid(format!("{}: a string is not a {}", path, typename))),
+            }
+        }
+        Token::Name(text) | Token::Number(text) => {
+            let lower = text.to_ascii_lowercase();
+            let text = match def.typename().as_str() {
+                "bool" if ["t", "true"].contains(&lower.as_str()) => "true",
+                "bool" if ["f", "false"].contains(&lower.as_str()) => "false",
+                // 1.5f is a float
+                "float" | "double" if !lower.contains("inf") => text.strip_suffix(['f', 'F']).unwrap_or(text),
+                _ => text,
+            };
+            parse_scalar(def, text, &path)?
+        }
+        Token::Punct(c) => return Err(invalid(format!("{}: a value expected, not '{}'", path, c))),
+    };
+    Ok(FieldValue::SCALAR(scalar))
+}
+
+
+#[cfg(test)]
+mod textformat_tests {
+    use super::*;
+
+    const PROTO: &str = "enum E { ZERO = 0; FIVE = 5; }\n\
+        message M { int32 id = 1; string item_name = 2; repeated Item items = 3; map<string, int64> tags = 4; bytes b = 5; E e = 6; repeated float f = 7; }\n\
+        message Item { uint64 n = 1; bool ok = 2; }";
+
+    fn proto() -> ProtoData { ProtoData::new(PROTO).unwrap().finalize().unwrap() }
+
+    fn binary(data: &MessageData, proto: &ProtoData) -> Vec<u8> { encode(data, proto, Format::Binary, false).unwrap() }
+
+    #[test]
+    fn json() {
+        let proto = proto();
+        let root = proto.get_message_definition("M").unwrap();
+        let text = r#"{"id": 7, "itemName": "a\"bé", "items": [{"n": "12", "ok": true}, {}], "tags": {"x": "-3"},
+            "b": "AQL/", "e": "FIVE", "f": [1.5, "NaN"], "unset": null}"#;
+        assert_eq!(decode(text.as_bytes(), &proto, root.clone(), Format::Json).unwrap_err().to_string(), "unset: no such field in M");
+        let text = text.replace(", \"unset\": null", "");
+        let data = decode(text.as_bytes(), &proto, root.clone(), Format::Json).unwrap();
+        let written = String::from_utf8(encode(&data, &proto, Format::Json, false).unwrap()).unwrap();
+        assert_eq!(written, "{\n  \"id\": 7,\n  \"itemName\": \"a\\\"bé\",\n  \"items\": [\n    {\n      \"n\": \"12\",\n      \"ok\": true\n    },\n    {}\n  ],\n  \
+            \"tags\": {\n    \"x\": \"-3\"\n  },\n  \"b\": \"AQL/\",\n  \"e\": \"FIVE\",\n  \"f\": [1.5, \"NaN\"]\n}\n");
+
+        // read back as the same data
+        let again = decode(written.as_bytes(), &proto, root.clone(), Format::Json).unwrap();
+        assert_eq!(binary(&again, &proto), binary(&data, &proto));
+
+        let error = |text: &str| decode(text.as_bytes(), &proto, root.clone(), Format::Json).unwrap_err().to_string();
+        assert_eq!(error("{\"id\": 3000000000}"), "id: 3000000000 is out of the int32 range -2147483648..2147483647");
+        assert_eq!(error("{\"items\": [{\"ok\": 1}]}"), "items[0].ok: a number is not a bool");
+        assert_eq!(error("{\"items\": {}}"), "items: an array expected, not an object");
+        assert_eq!(error("{\n\"id\": 1,\n}"), "line 3: a string expected");
+    }
+
+    #[test]
+    fn json_names() {
+        let proto = ProtoData::new("message M { int32 item_count = 1 [json_name = \"count\"]; string last_name = 2; }").unwrap().finalize().unwrap();
+        let root = proto.get_message_definition("M").unwrap();
+        // the declared json name, the lowerCamelCase one and the original name are all read
+        let data = decode(b"{\"count\": 2, \"lastName\": \"a\"}", &proto, root.clone(), Format::Json).unwrap();
+        let again = decode(b"{\"item_count\": 2, \"last_name\": \"a\"}", &proto, root.clone(), Format::Json).unwrap();
+        assert_eq!(binary(&again, &proto), binary(&data, &proto));
+
+        let written = |original_names| String::from_utf8(encode(&data, &proto, Format::Json, original_names).unwrap()).unwrap();
+        assert_eq!(written(false), "{\n  \"count\": 2,\n  \"lastName\": \"a\"\n}\n");
+        assert_eq!(written(true), "{\n  \"item_count\": 2,\n  \"last_name\": \"a\"\n}\n");
+    }
+
+    #[test]
+    fn text() {
+        let proto = proto();
+        let root = proto.get_message_definition("M").unwrap();
+        let text = "# a comment\nid: 0x10 item_name: 'a' \"b\\n\"\nitems { n: 1 ok: t } items < >\n\
+            tags { key: \"x\" value: -3 }\nb: \"\\001\\xff\"; e: FIVE f: [1.5f, -inf]";
+        let data = decode(text.as_bytes(), &proto, root.clone(), Format::Text).unwrap();
+        let written = String::from_utf8(encode(&data, &proto, Format::Text, false).unwrap()).unwrap();
+        assert_eq!(written, "id: 16\nitem_name: \"ab\\n\"\nitems {\n  n: 1\n  ok: true\n}\nitems {\n}\n\
+            tags {\n  key: \"x\"\n  value: -3\n}\nb: \"\\001\\377\"\ne: FIVE\nf: 1.5\nf: -inf\n");
+        let again = decode(written.as_bytes(), &proto, root.clone(), Format::Text).unwrap();
+        assert_eq!(binary(&again, &proto), binary(&data, &proto));
+
+        let error = |text: &str| decode(text.as_bytes(), &proto, root.clone(), Format::Text).unwrap_err().to_string();
+        assert_eq!(error("id: 1\nitems { n: -1 }"), "line 2: n: -1 is out of the uint64 range 0..18446744073709551615");
+        assert_eq!(error("id 1"), "line 1: ':' expected after id");
+        assert_eq!(error("items {\n n: 1"), "line 2: '}' expected");
+        assert_eq!(error("e: SIX"), "line 1: e: SIX is not a E");
+    }
+
+    #[test]
+    fn nesting() {
+        let proto = ProtoData::new("message N { N n = 1; repeated int32 ids = 2; }").unwrap().finalize().unwrap();
+        let root = proto.get_message_definition("N").unwrap();
+        let error = |text: String, format| decode(text.as_bytes(), &proto, root.clone(), format).unwrap_err().to_string();
+        let json = |depth| format!("{}{}", "{\"n\": ".repeat(depth), "{}".to_string() + &"}".repeat(depth));
+        let text = |depth| format!("{}{}", "n { ".repeat(depth), "}".repeat(depth));
+
+        // as deep as the binary reader reads, and written the same
+        let data = decode(json(MAX_NESTING).into_bytes().as_slice(), &proto, root.clone(), Format::Json).unwrap();
+        let again = decode(text(MAX_NESTING).into_bytes().as_slice(), &proto, root.clone(), Format::Text).unwrap();
+        let bytes = binary(&data, &proto);
+        assert_eq!(binary(&again, &proto), bytes);
+        assert!(decode(&bytes, &proto, root.clone(), Format::Binary).is_ok());
+
+        assert_eq!(error(json(MAX_NESTING + 1), Format::Json), "messages nested deeper than 100 levels");
+        assert_eq!(error(text(MAX_NESTING + 1), Format::Text), "line 1: messages nested deeper than 100 levels");
+        // an error, not an overflow of the stack
+        assert_eq!(error(json(100_000), Format::Json), "line 1: values nested deeper than 201 levels");
+        assert_eq!(error(text(100_000), Format::Text), "line 1: messages nested deeper than 100 levels");
+        assert_eq!(error(format!("{{\"ids\": {}", "[".repeat(200_000)), Format::Json), "line 1: values nested deeper than 201 levels");
+    }
+
+    #[test]
+    fn format_of_path() {
+        assert_eq!(Format::of_path(Path::new("a/data.JSON")), Format::Json);
+        assert_eq!(Format::of_path(Path::new("data.txtpb")), Format::Text);
+        assert_eq!(Format::of_path(Path::new("data.pb")), Format::Binary);
+        assert_eq!(Format::of_path(Path::new("data.json.gz")), Format::Json);
+        assert_eq!(Format::of_path(Path::new("json")), Format::Binary);
+    }
+}
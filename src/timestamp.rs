@@ -0,0 +1,299 @@
+use crate::wire::{FieldValue, MessageData, ScalarValue};
+
+// the offset from UTC in seconds of a time zone at a time: fixed, or the transitions of a TZif file
+// (/etc/localtime or the zone of $TZ) with the POSIX rule of its footer for the times after them
+#[derive(Clone)]
+pub enum TimeZone {
+    Fixed(i32),
+    Rules { transitions: Vec<(i64, i32)>, initial: i32, rule: Option<Rule> },
+}
+
+// std offset, dst offset and the local starts of the daylight saving time: month, week (5 - last), weekday, seconds
+#[derive(Clone)]
+pub struct Rule {
+    std: i32,
+    dst: Option<(i32, (u32, u32, u32, i64), (u32, u32, u32, i64))>,
+}
+
+impl TimeZone {
+    // $TZ as a zone name, a file or a rule, otherwise /etc/localtime; UTC if there is none
+    pub fn local() -> TimeZone {
+        let tz = std::env::var("TZ").ok().map(|tz| tz.trim_start_matches(':').to_string()).filter(|tz| !tz.is_empty());
+        let file = match &tz {
+            Some(tz) if tz.starts_with('/') => tz.clone(),
+            Some(tz) => format!("/usr/share/zoneinfo/{}", tz),
+            None => "/etc/localtime".to_string(),
+        };
+        if let Some(zone) = std::fs::read(file).ok().and_then(|bytes| Self::parse_tzif(&bytes)) {
+            return zone;
+        }
+        match tz.as_deref().and_then(Rule::parse) {
+            Some(rule) => TimeZone::Rules { transitions: vec![], initial: rule.std, rule: Some(rule) },
+            None => TimeZone::Fixed(0),
+        }
+    }
+
+    // "+02:00", "-0530", "utc"
+    pub fn parse_offset(text: &str) -> Option<TimeZone> {
+        if text.eq_ignore_ascii_case("utc") { return Some(TimeZone::Fixed(0)); }
+        let (sign, digits) = match text.as_bytes().first()? {
+            b'+' => (1, &text[1..]),
+            b'-' => (-1, &text[1..]),
+            _ => return None,
+        };
+        let digits = digits.replace(':', "");
+        if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) { return None; }
+        let (hours, minutes) = (digits[..2].parse::<i32>().ok()?, digits[2..].parse::<i32>().ok()?);
+        if hours > 14 || minutes > 59 { return None; }
+        Some(TimeZone::Fixed(sign * (hours * 3600 + minutes * 60)))
+    }
+
+    // version 1 data is skipped for the 64-bit times of version 2 and later, with the rule in the footer
+    pub fn parse_tzif(bytes: &[u8]) -> Option<TimeZone> {
+        let (data, counts) = Self::tzif_block(bytes, 4)?;
+        let v1_len = 44 + Self::block_len(&counts, 4);
+        let (data, counts, time_size, footer) = if bytes[4] >= b'2' {
+            let (data, counts) = Self::tzif_block(&bytes[v1_len..], 8)?;
+            (data, counts, 8, Some(&bytes[v1_len + 44 + Self::block_len(&counts, 8)..]))
+        } else {
+            (data, counts, 4, None)
+        };
+        let [_, _, _, time_count, type_count, _] = counts;
+        let time = |i: usize| {
+            let b = &data[i * time_size..(i + 1) * time_size];
+            if time_size == 8 { i64::from_be_bytes(b.try_into().unwrap()) } else { i32::from_be_bytes(b.try_into().unwrap()) as i64 }
+        };
+        let types = &data[time_count * (time_size + 1)..];
+        let offset = |index: usize| (index < type_count).then(|| i32::from_be_bytes(types[index * 6..index * 6 + 4].try_into().unwrap()));
+        let mut transitions = vec![];
+        for i in 0..time_count {
+            transitions.push((time(i), offset(data[time_count * time_size + i] as usize)?));
+        }
+        let rule = footer.and_then(|footer| Rule::parse(String::from_utf8_lossy(footer).trim()));
+        Some(TimeZone::Rules { transitions, initial: offset(0)?, rule })
+    }
+
+    // the counts of the header and the data after it
+    fn tzif_block(bytes: &[u8], time_size: usize) -> Option<(&[u8], [usize; 6])> {
+        if bytes.len() < 44 || &bytes[..4] != b"TZif" { return None; }
+        let mut counts = [0; 6];
+        for (i, count) in counts.iter_mut().enumerate() {
+            *count = u32::from_be_bytes(bytes[20 + i * 4..24 + i * 4].try_into().unwrap()) as usize;
+        }
+        let data = &bytes[44..];
+        if data.len() < Self::block_len(&counts, time_size) { return None; }
+        Some((data, counts))
+    }
+
+    fn block_len(counts: &[usize; 6], time_size: usize) -> usize {
+        let [ut_count, std_count, leap_count, time_count, type_count, char_count] = *counts;
+        time_count * (time_size + 1) + type_count * 6 + char_count + leap_count * (time_size + 4) + std_count + ut_count
+    }
+
+    pub fn offset_at(&self, time: i64) -> i32 {
+        match self {
+            TimeZone::Fixed(offset) => *offset,
+            TimeZone::Rules { transitions, initial, rule } => {
+                let after_last = transitions.last().is_none_or(|(last, _)| time >= *last);
+                match (rule, after_last) {
+                    (Some(rule), true) => rule.offset_at(time),
+                    _ => transitions.iter().take_while(|(start, _)| *start <= time).last().map_or(*initial, |(_, offset)| *offset),
+                }
+            }
+        }
+    }
+}
+
+impl Rule {
+    // "CET-1CEST,M3.5.0,M10.5.0/3": the offsets are west of UTC, the dst one is an hour less if omitted
+    fn parse(text: &str) -> Option<Rule> {
+        let mut rest = text;
+        Self::name(&mut rest)?;
+        let std = -Self::time(&mut rest)?;
+        if rest.is_empty() { return Some(Rule { std: std as i32, dst: None }); }
+        Self::name(&mut rest)?;
+        let dst = if rest.starts_with(',') { std + 3600 } else { -Self::time(&mut rest)? };
+        let (start, end) = rest.strip_prefix(',')?.split_once(',')?;
+        Some(Rule { std: std as i32, dst: Some((dst as i32, Self::date(start)?, Self::date(end)?)) })
+    }
+
+    fn name(rest: &mut &str) -> Option<()> {
+        let len = match rest.strip_prefix('<') {
+            Some(quoted) => quoted.find('>')? + 2,
+            None => rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len()),
+        };
+        if len < 3 { return None; }
+        *rest = &rest[len..];
+        Some(())
+    }
+
+    // [+-]hh[:mm[:ss]] in seconds
+    fn time(rest: &mut &str) -> Option<i64> {
+        let len = rest.find(|c: char| !c.is_ascii_digit() && !"+-:".contains(c)).unwrap_or(rest.len());
+        let (text, after) = rest.split_at(len);
+        *rest = after;
+        let (sign, text) = match text.strip_prefix('-') {
+            Some(text) => (-1, text),
+            None => (1, text.trim_start_matches('+')),
+        };
+        let mut seconds = 0;
+        for (i, part) in text.split(':').enumerate() {
+            if i > 2 { return None; }
+            seconds += part.parse::<i64>().ok()? * [3600, 60, 1][i];
+        }
+        Some(sign * seconds)
+    }
+
+    // Mm.w.d[/time], the time is 2:00 by default
+    fn date(text: &str) -> Option<(u32, u32, u32, i64)> {
+        let (date, time) = match text.split_once('/') {
+            Some((date, mut time)) => (date, Self::time(&mut time)?),
+            None => (text, 7200),
+        };
+        let mut parts = date.strip_prefix('M')?.split('.').map(|part| part.parse::<u32>().ok());
+        let (month, week, weekday) = (parts.next()??, parts.next()??, parts.next()??);
+        if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 { return None; }
+        Some((month, week, weekday, time))
+    }
+
+    fn offset_at(&self, time: i64) -> i32 {
+        let Some((dst, start, end)) = self.dst else { return self.std; };
+        let (year, _, _) = civil_from_days((time + self.std as i64).div_euclid(86400));
+        // the start is in the standard time, the end in the daylight saving time
+        let start = Self::day(year, start) * 86400 + start.3 - self.std as i64;
+        let end = Self::day(year, end) * 86400 + end.3 - dst as i64;
+        let in_dst = if start < end { start <= time && time < end } else { !(end <= time && time < start) };
+        if in_dst { dst } else { self.std }
+    }
+
+    // the days since 1970 of the weekday of the week in the month, the 5th is the last one
+    fn day(year: i64, (month, week, weekday, _): (u32, u32, u32, i64)) -> i64 {
+        let first = days_from_civil(year, month, 1);
+        let next_month = if month == 12 { days_from_civil(year + 1, 1, 1) } else { days_from_civil(year, month + 1, 1) };
+        // 1970-01-01 was a Thursday
+        let mut day = first + (weekday as i64 - (first + 4).rem_euclid(7)).rem_euclid(7) + (week as i64 - 1) * 7;
+        while day >= next_month { day -= 7; }
+        day
+    }
+}
+
+// the days since 1970-01-01, http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) as i64 + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    (year_of_era + era * 400 + (month <= 2) as i64, month, day)
+}
+
+// "2024-03-31 03:00:00.25 +02:00", the fraction only if there is one
+pub fn format(seconds: i64, nanos: i32, zone: &TimeZone) -> String {
+    let offset = zone.offset_at(seconds);
+    let local = seconds + offset as i64;
+    let (year, month, day) = civil_from_days(local.div_euclid(86400));
+    let time = local.rem_euclid(86400);
+    let mut res = format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60);
+    if nanos > 0 {
+        res += format!(".{:09}", nanos).trim_end_matches('0');
+    }
+    match offset {
+        0 => res += " UTC",
+        _ => res += &format!(" {}{:02}:{:02}", if offset < 0 { '-' } else { '+' }, offset.abs() / 3600, offset.abs() / 60 % 60),
+    }
+    res
+}
+
+// seconds since 1970; a number too large for seconds (after the year 5138) is taken as milliseconds,
+// microseconds or nanoseconds
+pub fn of_number(number: i128, zone: &TimeZone) -> Option<String> {
+    let scale = match number.unsigned_abs() {
+        n if n < 100_000_000_000 => 1,
+        n if n < 100_000_000_000_000 => 1_000,
+        n if n < 100_000_000_000_000_000 => 1_000_000,
+        _ => 1_000_000_000,
+    };
+    let nanos = number.rem_euclid(scale) * (1_000_000_000 / scale);
+    Some(format(i64::try_from(number.div_euclid(scale)).ok()?, nanos as i32, zone))
+}
+
+// a google.protobuf.Timestamp value: the seconds and the nanos fields, as the package is not kept the type is found by them
+pub fn of_message(msg: &MessageData, zone: &TimeZone) -> Option<String> {
+    if msg.def.name != "Timestamp" { return None; }
+    let (mut seconds, mut nanos) = (0, 0);
+    for field in &msg.fields {
+        match (field.def.id(), &field.value) {
+            (1, FieldValue::SCALAR(ScalarValue::I64(value))) => seconds = *value,
+            (2, FieldValue::SCALAR(ScalarValue::I32(value))) => nanos = *value,
+            _ => return None,
+        }
+    }
+    Some(format(seconds, nanos, zone))
+}
+
+
+#[cfg(test)]
+mod timestamp_tests {
+    use super::*;
+
+    #[test]
+    fn dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(days_from_civil(2024, 2, 29)), (2024, 2, 29));
+        assert_eq!(days_from_civil(1969, 12, 31), -1);
+        assert_eq!(format(1_700_000_000, 0, &TimeZone::Fixed(0)), "2023-11-14 22:13:20 UTC");
+        assert_eq!(format(-1, 250_000_000, &TimeZone::parse_offset("+05:30").unwrap()), "1970-01-01 05:29:59.25 +05:30");
+        assert_eq!(format(0, 0, &TimeZone::parse_offset("-0800").unwrap()), "1969-12-31 16:00:00 -08:00");
+        assert!(TimeZone::parse_offset("2:00").is_none());
+        assert_eq!(of_number(1_700_000_000_250, &TimeZone::Fixed(0)).unwrap(), "2023-11-14 22:13:20.25 UTC");
+        assert_eq!(of_number(-1, &TimeZone::Fixed(0)).unwrap(), "1969-12-31 23:59:59 UTC");
+    }
+
+    #[test]
+    fn daylight_saving() {
+        let zone = TimeZone::Rules { transitions: vec![], initial: 3600, rule: Rule::parse("CET-1CEST,M3.5.0,M10.5.0/3") };
+        // 2024-03-31 01:00 UTC: the clocks go from 02:00 to 03:00
+        let start = days_from_civil(2024, 3, 31) * 86400 + 3600;
+        assert_eq!(format(start - 1, 0, &zone), "2024-03-31 01:59:59 +01:00");
+        assert_eq!(format(start, 0, &zone), "2024-03-31 03:00:00 +02:00");
+        // 2024-10-27 01:00 UTC: from 03:00 back to 02:00
+        let end = days_from_civil(2024, 10, 27) * 86400 + 3600;
+        assert_eq!(format(end - 1, 0, &zone), "2024-10-27 02:59:59 +02:00");
+        assert_eq!(format(end, 0, &zone), "2024-10-27 02:00:00 +01:00");
+
+        // the southern hemisphere: the daylight saving time over the new year
+        let zone = TimeZone::Rules { transitions: vec![], initial: 36000, rule: Rule::parse("<+10>-10<+11>,M10.1.0,M4.1.0/3") };
+        assert_eq!(zone.offset_at(days_from_civil(2024, 1, 15) * 86400), 39600);
+        assert_eq!(zone.offset_at(days_from_civil(2024, 7, 15) * 86400), 36000);
+    }
+
+    #[test]
+    fn tzif_file() {
+        // version 1: a transition to +01:00 at 1000, the footer is only in version 2 files
+        let mut bytes = b"TZif\0".to_vec();
+        bytes.extend([0; 15]);
+        for count in [0u32, 0, 0, 1, 2, 4] { bytes.extend(count.to_be_bytes()); }
+        bytes.extend(1000i32.to_be_bytes());
+        bytes.push(1);
+        bytes.extend(0i32.to_be_bytes());
+        bytes.extend([0, 0]);
+        bytes.extend(3600i32.to_be_bytes());
+        bytes.extend([0, 0]);
+        bytes.extend(b"UTC\0");
+        let zone = TimeZone::parse_tzif(&bytes).unwrap();
+        assert_eq!((zone.offset_at(999), zone.offset_at(1000), zone.offset_at(i64::MAX)), (0, 3600, 3600));
+        assert!(TimeZone::parse_tzif(&bytes[..50]).is_none());
+    }
+}
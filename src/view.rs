@@ -1,19 +1,42 @@
 use std::string::String;
 use std::cmp::{Ordering, PartialEq};
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::{atomic, Arc};
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::fmt::{Debug, Formatter};
 use std::{io, iter, mem};
 use crossterm::event::{KeyEvent};
 use crossterm::style;
 use crossterm::style::Color;
 use crate::proto::FieldProtoPtr;
+use crate::overview::size_text;
+use crate::render::{Renderer, Renderers};
 use crate::Selection;
+use crate::timestamp::{self, TimeZone};
+use crate::formats::FieldFormat;
 use crate::trz::{Change, ChangeType};
-use crate::wire::{FieldPath, FieldValue, MessageData, ScalarValue};
+use crate::wire::{FieldOrder, FieldPath, FieldValue, MessageData, ScalarValue};
 use crate::wire::ScalarValue::{BYTES, STR};
 
+// defaults of LayoutConfig, the config file may change them
 pub(crate) const MARGIN_RIGHT: u16 = 1;
 pub(crate) const MARGIN_LEFT: u16 = 1;
+pub(crate) const NEXT_LEVEL_INDENT: u16 = 2;
+// of a table column if the config has no width
+pub(crate) const COLUMN_WIDTH: u16 = 10;
+// values of a long repeated field shown at once
+pub(crate) const PAGE_SIZE: usize = 1000;
+// lines of an expanded message sized at once, more than a screen, the rest is sized on the sizing thread
+const EAGER_SIZED_LINES: usize = 500;
+// the layouts sized on the sizing thread are sent back at least this often
+const SIZING_BATCH: Duration = Duration::from_millis(20);
+
+// before a default value present in the data of a field without explicit presence (proto3 int32 set to 0)
+pub(crate) const PRESENCE_MARK: char = '+';
 
 
 pub enum UserCommand
@@ -41,6 +64,30 @@ pub enum UserCommand
     DataTypeVisibility,
     // hotkey: Enter/F5 on collapsed field name
     CollapsedToggle,
+    // hotkeys: '+', '-'
+    // expand or collapse all nested messages of the selected one
+    ExpandAll,
+    CollapseAll,
+    // hotkeys: Alt+1..Alt+9
+    // messages of the level are collapsed, messages above are expanded
+    CollapseToDepth(usize),
+    // hotkey: Ctrl+G, the path entered in the prompt
+    // collapsed parent messages are expanded to show the field
+    GoToPath(FieldPath),
+    // hotkeys: '[', ']'
+    // the first or the last value of the repeated field containing the selection
+    GroupFirst,
+    GroupLast,
+    // hotkeys: Alt+Down, Alt+Up
+    // the next or the previous field present in the data, unset fields are skipped
+    NextSet,
+    PrevSet,
+    // hotkey: F7
+    // wrap the selected string to several lines or cut it to one line
+    WrapToggle,
+    // hotkey: Ctrl+O, the offset entered in the prompt
+    // the cursor is moved to the byte of the selected bytes field
+    GoToOffset(usize),
     // hotkey: 'T'
     // tree / table mode switch) (vert/horiz auto select by content)
     TableTreeToggle,
@@ -90,6 +137,7 @@ pub enum LayoutType {
     Message,
     Table,
     Collapsed,
+    Page,
 }
 
 pub struct Layouts { // rename Document
@@ -100,6 +148,41 @@ pub struct Layouts { // rename Document
     pub file_name: String,
     pub indents: Vec<u16>,
     pub top_layouts_count: usize,
+    // changed with the config, the sizes of layouts calculated before are outdated
+    pub revision: u64,
+    // the path of the first value of a paged group and the offset of the page shown
+    pub pages: Vec<(FieldPath, usize)>,
+    // the layouts of an expanded content not sized yet, they are sized on the sizing thread;
+    // the range follows the layouts inserted or removed before it and shrinks when they are removed
+    pub pending_sizes: Option<Range<usize>>,
+    // the sizing thread of the pending layouts, dropped when they are moved, removed or rebuilt
+    sizing: Option<Sizing>,
+}
+
+// copies of the pending layouts sized on another thread with a snapshot of the data, sent back in batches;
+// the results are dropped when the data, the width or the config is changed, and the copies are sized again
+struct Sizing {
+    // the data the copies are sized with, an edit copies the root of the data the editor keeps
+    root: Arc<MessageData>,
+    width: u16,
+    revision: u64,
+    // the layouts not received yet
+    pending: Range<usize>,
+    results: Receiver<SizedLayouts>,
+    // set when the sizing is dropped, the thread stops before the next layout
+    cancel: Arc<AtomicBool>,
+}
+
+// the layouts sized in turn and the indents of the levels after them
+struct SizedLayouts {
+    items: Vec<LayoutParams>,
+    indents: Vec<u16>,
+}
+
+impl Drop for Sizing {
+    fn drop(&mut self) {
+        self.cancel.store(true, atomic::Ordering::Relaxed);
+    }
 }
 
 pub struct LayoutParams {
@@ -109,66 +192,112 @@ pub struct LayoutParams {
     pub amount: usize, // how many repeated data shown by this layout, starting from path
     pub layout: Option<Box<dyn ViewLayout>>,
     pub children_count: usize,
+    // (width, config revision) the height was calculated for
+    pub sized: Option<(u16, u64)>,
+    // the indent of the level the height was calculated with
+    sized_indent: u16,
+    // the first column asked by the layout when it was sized, with the left margin
+    pub name_width: u16,
 }
 
 // does not store data, only params how to display it
 // in next versions: multiple cursor, selection and highlight (found item, etc.)
-pub trait ViewLayout {
+pub trait ViewLayout: Send {
     fn layout_type(&self) -> LayoutType;
+    // an unchanged copy to be sized on the sizing thread
+    fn copy(&self) -> Box<dyn ViewLayout>;
     // return how many screen lines take this layout
     fn calc_sizes(&mut self, root: &MessageData, path: &FieldPath, amount: usize, config: &LayoutConfig, width: u16, negotiator: &mut IndentsCalc) -> usize;
     // TODO first_line: usize, line_count: u16
     fn get_screen(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, cursor: Option<(u16, usize)>) -> ScreenLines;
+    // only the visible part of the layout, for layouts with many lines
+    fn get_screen_window(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, cursor: Option<(u16, usize)>, skip: usize, count: usize) -> ScreenLines {
+        let mut lines = self.get_screen(root, path, amount, width, indent, config, cursor);
+        lines.0.drain(..skip.min(lines.0.len()));
+        lines.0.truncate(count);
+        lines
+    }
     fn on_command(&mut self, root: &MessageData, path: &FieldPath, amount: usize, command: UserCommand, config: &LayoutConfig, width: u16, indent: u16, cursor_x: &mut u16, cursor_pos: &mut usize) -> CommandResult;
     // get ids of children fields already shown in this layout
     fn get_consumed_fields(&self, root: &MessageData, path: &FieldPath, config: &LayoutConfig) -> HashSet<i32> { HashSet::new() }
     fn get_status_string(&self, cursor_x: u16, cursor_y: usize) -> String { String::new() }
+    // reverse of get_screen: cursor x for a column of the layout line (mouse click), 0 for the first column
+    fn cursor_x_at(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, column: u16, line: usize) -> u16 { 0 }
+    // which of the repeated values shown by the layout is under the cursor
+    fn selected_index(&self, cursor_x: u16, cursor_y: usize) -> usize { 0 }
+    // reverse of selected_index
+    fn cursor_at_index(&self, index: usize) -> (u16, usize) { (0, 0) }
+    // the rows going to the other pages of a long repeated field
+    fn page(&self) -> Option<&PageLayout> { None }
 }
 
 
 // bool, enum, integral, or real value: single, none or repeated
 // there are special layouts for text and hex field types
+#[derive(Clone)]
 pub struct ScalarLayout {
     //amount: usize, // how many data with the same id, started from the provided index
     line_lens: Vec<usize>, // how many scalar values of each line on the screen
 }
+#[derive(Clone)]
 pub struct StringLayout {
     //has_value: bool,
     visible_lines_count: usize, // TODO
+    // wrapped to several lines or cut to one, LayoutConfig::wrap_strings if not set for this field
+    wrap: Option<bool>,
+    // the value of a bytes or string field shown as text of the renderer
+    renderer: Option<Renderer>,
+    // how many chars of the value on each line on the screen, the cursor moves by chars
+    line_lens: Vec<usize>,
 }
+#[derive(Clone)]
 pub struct BytesLayout {
     //has_value: bool,
     bytes_per_line: u16,
     data_size: usize,
     //visible_lines_count: usize, // TODO
 }
+#[derive(Clone)]
 pub struct MessageLayout { // with columns or title only
     //amount: usize,
     scroll: usize, // first visible column index
 }
+#[derive(Clone)]
 pub struct TableLayout { // for repeated messages
     //amount: usize,
     vertical: bool,
     scroll: (usize, usize), // column and row indexes of top-left visible cell
 }
 
+#[derive(Clone)]
 pub struct CollapsedLayout {
     display_size: usize,
 }
 
+// one row for the values before the page shown and one for the values after it, Enter shows the next page
+#[derive(Clone)]
+pub struct PageLayout {
+    group: FieldPath,
+    total: usize,
+    forward: bool,
+}
+
+#[derive(Clone)]
 pub enum CommentVisibility {
     Hidden,
     Multiline, // before data, possible multiline
     Inline,    // in the same line, after data and type, only one line of comment
 }
-#[derive(PartialEq, Debug)]
-pub enum FieldOrder {
-    Proto,  // as in proto file (default)
-    Wire,   // as the data read from the file, repeated may be in several groups
-    ByName, // alphabetically by the name of the field
-    ById,   // by numerical field id
+
+// float and double values, each format is read back to the same bits
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum FloatFormat {
+    Shortest,   // the shorter of decimal and scientific (default)
+    Decimal,    // 0.30000000000000004, 100000000000000000000
+    Scientific, // 3.0000000000000004e-1, 1e20
 }
 
+#[derive(Clone)]
 pub struct LayoutConfig {
     pub show_comments: CommentVisibility,
     pub show_binary: bool,
@@ -176,12 +305,56 @@ pub struct LayoutConfig {
     pub field_order: FieldOrder,
     pub messages: HashMap<String, MessageLayoutConfig>,
     pub hex: bool,
+    // long and multiline strings are shown in several lines, otherwise cut with '…'
+    pub wrap_strings: bool,
+    // signed integer fields shown as unsigned ones with the same bits
+    pub unsigned: Vec<FieldProtoPtr>,
+    // string and bytes fields shown as json, uuid, etc.
+    pub renderers: Renderers,
+    // the wire encoding after the type: ~z zigzag, ~f fixed size
+    pub show_encoding: bool,
+    // control chars of strings printed as they are, otherwise shown as escapes: \t, \x1b
+    pub raw_strings: bool,
+    // empty columns at the screen edges
+    pub margin_left: u16,
+    pub margin_right: u16,
+    // columns added to the first column width for each nesting level
+    pub level_indent: u16,
+    // offsets of the bytes lines in hex, otherwise decimal
+    pub hex_addresses: bool,
+    // the offset of each bytes line in its own column, with the total length at the end
+    pub bytes_offsets: bool,
+    pub float_format: FloatFormat,
+    // a repeated message, string or bytes field with more values is shown by pages
+    pub page_size: usize,
+    // an inserted message gets its nested messages, see MessageData::scaffold
+    pub scaffold: bool,
+    // between the groups of three digits of integers (1,234,567), the values are edited and copied without it
+    pub digit_separator: Option<char>,
+    // the decimal point of floats shown as a comma (2,5), the values are edited and copied with the point
+    pub decimal_comma: bool,
+    // google.protobuf.Timestamp messages shown as the date and time in the zone after the field name
+    pub time_zone: Option<TimeZone>,
+    // the size of a collapsed message in B, KiB, MiB, otherwise the count of bytes
+    pub human_sizes: bool,
+    // json files written with the field names of the proto file, otherwise with json_name or lowerCamelCase
+    pub original_json_names: bool,
+    // the bytes each field takes in the saved file, before its type
+    pub show_sizes: bool,
+    // integer fields shown in hex or as times, Timestamp messages as dates whatever the time zone setting,
+    // the formats of string and bytes fields are kept as renderers; by the names of the fields from the root (a.b.c)
+    // with the definitions found by them, the definitions are found again when the schema is read again
+    pub field_formats: Vec<(String, FieldProtoPtr, FieldFormat)>,
+    // the messages collapsed by the user, they stay collapsed when the layouts are created again
+    pub collapsed: HashSet<FieldPath>,
 }
 
 // How to show a message or table of a certain type
+// a collapsed message is shown as a table row with the values of the columns
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct MessageLayoutConfig {
-    columns: Vec<i32>,
-    columns_width: Vec<u16>,
+    pub columns: Vec<String>, // field names
+    pub columns_width: Vec<u16>,
 }
 
 #[derive(PartialEq, Debug, Copy, Clone)]
@@ -192,6 +365,7 @@ pub enum TextStyle {
     Filename,
     FieldName, // column header
     SelectedFieldName,
+    DeprecatedFieldName, // dimmed, marked [deprecated = true] in the proto
     FieldIndex, // left column
     SelectedFieldIndex,
     Value, // data content
@@ -202,12 +376,55 @@ pub enum TextStyle {
     SelectedTypename, // for oneof
     Divider,
     Bookmark,
+    Protected, // mark of a field changed only after confirmation
+    Escape, // a control char of a string shown as \t, \x1b
+    Match, // the text searched, while it's typed
+    Highlight, // a value of a message matching a highlight condition
+    Warning, // a value read with another wire type than declared
     TopLine, // top line with different status information
     Unknown,
 }
 
 pub struct ScreenLine(pub Vec<(char, TextStyle)>);
 
+// the second column of a wide char, not printed
+pub const WIDE_TAIL: char = '\0';
+
+// columns taken by the char in a terminal: 0 for combining marks, 2 for CJK and emoji
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0 | 0x300..=0x36F | 0x483..=0x489 | 0x591..=0x5BD | 0x610..=0x61A | 0x64B..=0x65F |
+        0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x200B..=0x200F | 0x20D0..=0x20FF | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F => 0,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xA000..=0xA4CF |
+        0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 |
+        0x1F300..=0x1F64F | 0x1F900..=0x1F9FF | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+pub fn text_width(text: &str) -> usize { text.chars().map(char_width).sum() }
+
+// the chars of an escape are moved to a private use plane to be drawn in the Escape style, see ScreenLine::add_string
+const ESCAPE_BASE: u32 = 0xF0000;
+
+// tabs, terminal escapes and other control chars as \t, \x1b, \u{85}; new lines split the text, they are kept
+pub fn escape_controls(text: &str) -> String {
+    let mark = |escape: String| escape.chars().map(|c| char::from_u32(ESCAPE_BASE + c as u32).unwrap()).collect::<String>();
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' => res.push(c),
+            '\t' => res += &mark("\\t".into()),
+            '\r' => res += &mark("\\r".into()),
+            c if c.is_ascii_control() => res += &mark(format!("\\x{:02x}", c as u32)),
+            // chars of the private use plane are escaped too, not to be taken for the marked ones
+            c if c.is_control() || (ESCAPE_BASE..ESCAPE_BASE + 0x80).contains(&(c as u32)) => res += &mark(format!("\\u{{{:x}}}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
 impl Default for LayoutConfig {
     fn default() -> Self {
         LayoutConfig {
@@ -217,42 +434,87 @@ impl Default for LayoutConfig {
             field_order: FieldOrder::Proto,
             messages: HashMap::new(),
             hex: false,
+            wrap_strings: true,
+            unsigned: vec![],
+            renderers: Renderers::default(),
+            show_encoding: false,
+            raw_strings: false,
+            margin_left: MARGIN_LEFT,
+            margin_right: MARGIN_RIGHT,
+            level_indent: NEXT_LEVEL_INDENT,
+            hex_addresses: true,
+            bytes_offsets: false,
+            float_format: FloatFormat::Shortest,
+            page_size: PAGE_SIZE,
+            scaffold: false,
+            digit_separator: None,
+            decimal_comma: false,
+            time_zone: None,
+            human_sizes: false,
+            original_json_names: false,
+            show_sizes: false,
+            field_formats: vec![],
+            collapsed: HashSet::new(),
         }
     }
 }
 
-impl CommentVisibility {
-    pub fn next(&self) -> CommentVisibility {
-        match self {
-            CommentVisibility::Hidden => CommentVisibility::Inline,
-            CommentVisibility::Inline => CommentVisibility::Multiline,
-            CommentVisibility::Multiline => CommentVisibility::Hidden,
+impl LayoutConfig {
+    // the date and time of a Timestamp message if a zone is set or the field has the format, in UTC then
+    fn timestamp(&self, msg: &MessageData, def: &FieldProtoPtr) -> Option<String> {
+        match (&self.time_zone, self.field_format(def)) {
+            (Some(zone), _) => timestamp::of_message(msg, zone),
+            (None, Some(FieldFormat::Timestamp)) => timestamp::of_message(msg, &TimeZone::Fixed(0)),
+            _ => None,
         }
     }
-}
-impl FieldOrder {
-    pub fn next(&self) -> FieldOrder {
-        match self {
-            FieldOrder::Proto => FieldOrder::Wire,
-            FieldOrder::Wire => FieldOrder::ByName,
-            FieldOrder::ByName => FieldOrder::ById,
-            FieldOrder::ById => FieldOrder::Proto,
-        }
+
+    pub fn field_format(&self, def: &FieldProtoPtr) -> Option<FieldFormat> {
+        self.field_formats.iter().find(|(_, field, _)| Arc::ptr_eq(field, def)).map(|(_, _, format)| *format)
     }
-    pub fn prev(&self) -> FieldOrder {
-        match self {
-            FieldOrder::Proto => FieldOrder::ById,
-            FieldOrder::Wire => FieldOrder::Proto,
-            FieldOrder::ByName => FieldOrder::Wire,
-            FieldOrder::ById => FieldOrder::ByName,
+
+    // the value of a new element of the field
+    pub fn inserted_value(&self, def: &FieldProtoPtr) -> FieldValue {
+        match def.default() {
+            FieldValue::MESSAGE(msg) if self.scaffold => FieldValue::MESSAGE(Arc::new(MessageData::scaffold(msg.def.clone()))),
+            value => value,
         }
     }
-    pub fn first_letter(&self) -> char {
+}
+
+// 1234567 as 1,234,567, the sign is kept in front
+pub fn group_digits(text: &str, separator: char) -> String {
+    let (sign, digits) = text.split_at(if text.starts_with('-') { 1 } else { 0 });
+    let mut res = sign.to_string();
+    for (index, c) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index) % 3 == 0 { res.push(separator); }
+        res.push(c);
+    }
+    res
+}
+
+impl FloatFormat {
+    // None for other values
+    pub fn format(&self, value: &ScalarValue) -> Option<String> {
+        let (decimal, scientific) = match value {
+            ScalarValue::F32(v) => (format!("{}", v), format!("{:e}", v)),
+            ScalarValue::F64(v) => (format!("{}", v), format!("{:e}", v)),
+            _ => return None,
+        };
+        Some(match self {
+            FloatFormat::Shortest => if decimal.len() > scientific.len() { scientific } else { decimal },
+            FloatFormat::Decimal => decimal,
+            FloatFormat::Scientific => scientific,
+        })
+    }
+}
+
+impl CommentVisibility {
+    pub fn next(&self) -> CommentVisibility {
         match self {
-            FieldOrder::Proto => 'P',
-            FieldOrder::Wire => 'W',
-            FieldOrder::ByName => 'N',
-            FieldOrder::ById => 'I',
+            CommentVisibility::Hidden => CommentVisibility::Inline,
+            CommentVisibility::Inline => CommentVisibility::Multiline,
+            CommentVisibility::Multiline => CommentVisibility::Hidden,
         }
     }
 }
@@ -260,13 +522,50 @@ impl FieldOrder {
 impl ScreenLine {
     pub fn new(width: u16) -> ScreenLine { ScreenLine(Vec::with_capacity(width as usize)) }
 
+    // one item per screen column: a wide char is followed by WIDE_TAIL, zero width chars are not shown
     pub fn add_string(&mut self, text: String, style: TextStyle) {
-        let mut new_item = text.chars().map(|c| (c, style)).collect::<Vec<(char, TextStyle)>>();
-        self.0.append(&mut new_item);
+        for c in text.chars() {
+            if let Some(escaped) = (c as u32).checked_sub(ESCAPE_BASE).filter(|code| *code < 0x80).and_then(char::from_u32) {
+                self.0.push((escaped, TextStyle::Escape));
+                continue;
+            }
+            match char_width(c) {
+                0 => {}
+                1 => self.0.push((c, style)),
+                _ => self.0.extend([(c, style), (WIDE_TAIL, style)]),
+            }
+        }
+    }
+
+    // the occurrences of the text in the values of the line are shown in the Match style
+    pub fn mark_matches(&mut self, text: &str) {
+        let text: Vec<char> = text.chars().collect();
+        if text.is_empty() { return; }
+        let cells: Vec<usize> = (0..self.0.len()).filter(|index| self.0[*index].0 != WIDE_TAIL).collect();
+        let is_value = |style: TextStyle| matches!(style, TextStyle::Value | TextStyle::SelectedValue | TextStyle::DefaultValue);
+        let mut start = 0;
+        while start + text.len() <= cells.len() {
+            let found = cells[start..start + text.len()].iter().zip(&text).all(|(index, c)| self.0[*index].0 == *c && is_value(self.0[*index].1));
+            if !found {
+                start += 1;
+                continue;
+            }
+            let end = cells.get(start + text.len()).copied().unwrap_or(self.0.len());
+            for cell in &mut self.0[cells[start]..end] { cell.1 = TextStyle::Match; }
+            start += text.len();
+        }
     }
 
-    pub fn add_field_name(&mut self, text: String, indent: u16, cursor: &Option<(u16, usize)>) {
-        self.add_first_column_item([TextStyle::FieldName, TextStyle::SelectedFieldName], text, indent, cursor, 0);
+    // the values of the line, the selected one keeps its style
+    pub fn mark_highlight(&mut self) {
+        for cell in self.0.iter_mut().filter(|cell| matches!(cell.1, TextStyle::Value | TextStyle::DefaultValue)) {
+            cell.1 = TextStyle::Highlight;
+        }
+    }
+
+    pub fn add_field_name(&mut self, def: &FieldProtoPtr, indent: u16, cursor: &Option<(u16, usize)>) {
+        let style = if def.deprecated() { TextStyle::DeprecatedFieldName } else { TextStyle::FieldName };
+        self.add_first_column_item([style, TextStyle::SelectedFieldName], def.name(), indent, cursor, 0);
     }
     pub fn add_value_address(&mut self, text: String, indent: u16, cursor: &Option<(u16, usize)>, cursor_pos: usize) {
         self.add_first_column_item([TextStyle::FieldIndex, TextStyle::SelectedFieldIndex], text, indent, cursor, cursor_pos);
@@ -277,10 +576,11 @@ impl ScreenLine {
             selected = *x == 0 && *pos == cursor_pos;
         }
         if selected {
-            for _ in 1..indent as usize - text.len() {
-                self.0.push((' ', styles[0]));
+            // the space before the name is selected too, if there is a margin
+            let pad = (indent as usize).saturating_sub(text.len());
+            for index in 0..pad {
+                self.0.push((' ', styles[if index + 1 == pad { 1 } else { 0 }]));
             }
-            self.0.push((' ', styles[1]));
             self.add_string(text, styles[1]);
             self.0.push((':', styles[1]));
         } else {
@@ -289,23 +589,87 @@ impl ScreenLine {
             self.0.push((':', TextStyle::Divider));
         }
     }
-    pub fn add_field_size(&mut self, value: usize, screen_width: u16) {
-        //self.data_size = Some(value);
-        //let width = screen_width - self.0.len() as u16 - MARGIN_RIGHT;
-        let s = format!(" ... {}", value);
+    pub fn add_field_size(&mut self, value: usize, config: &LayoutConfig) {
+        let s = if config.human_sizes { format!(" ... {}", size_text(value)) } else { format!(" ... {}", value) };
         self.add_string(s, TextStyle::DataSize);
     }
-    pub fn add_typename(&mut self, field_def: FieldProtoPtr, screen_width: u16, empty: bool) {
-        let mut text = field_def.typename();
-        if field_def.repeated() { text = text + "*" }
+    // the first value of each column field, cut or padded to the column width
+    pub fn add_columns(&mut self, msg: &MessageData, columns: &MessageLayoutConfig, config: &LayoutConfig) {
+        for (name, width) in columns.columns.iter().zip(&columns.columns_width) {
+            let width = *width as usize;
+            let (mut text, style) = match msg.def.fields.iter().find(|def| def.name() == *name) {
+                Some(def) => match msg.fields.iter().find(|field| field.id() == def.id()).map(|field| &field.value) {
+                    Some(FieldValue::SCALAR(value)) => (ScalarLayout::scalar_to_string(value, def, config), TextStyle::Value),
+                    Some(FieldValue::MESSAGE(_)) => ("{…}".to_string(), TextStyle::Value),
+                    None => match def.default() {
+                        FieldValue::SCALAR(value) => (ScalarLayout::scalar_to_string(&value, def, config), TextStyle::DefaultValue),
+                        FieldValue::MESSAGE(_) => (String::new(), TextStyle::DefaultValue),
+                    },
+                },
+                None => (String::new(), TextStyle::DefaultValue),
+            };
+            text = text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+            if text_width(&text) > width {
+                let mut used = 0;
+                text = text.chars().take_while(|c| { used += char_width(*c); used < width }).collect::<String>() + "…";
+            }
+            let pad = width.saturating_sub(text_width(&text));
+            self.0.push((' ', TextStyle::Divider));
+            self.add_string(text, style);
+            self.0.extend(iter::repeat_n((' ', TextStyle::Divider), pad));
+        }
+    }
+    pub fn add_typename(&mut self, field_def: FieldProtoPtr, screen_width: u16, empty: bool, config: &LayoutConfig) {
+        self.add_typename_marked(field_def, screen_width, empty, "", "", config);
+    }
+    // the encoding goes right after the type (sint32~z), the mark after '*', like 'p' for a packed field
+    pub fn add_typename_marked(&mut self, field_def: FieldProtoPtr, screen_width: u16, empty: bool, encoding: &str, mark: &str, config: &LayoutConfig) {
+        let mut text = field_def.typename() + encoding;
+        if field_def.repeated() { text = text + "*" + mark }
         if empty { text = "-".to_string() + text.as_str() }
-        let max_allowed_len = (screen_width - MARGIN_RIGHT) as usize - text.len();
+        let right = screen_width.saturating_sub(config.margin_right) as usize;
+        let max_allowed_len = right.saturating_sub(text_width(&text));
         if self.0.len() > max_allowed_len {
             self.0.truncate(max_allowed_len);
         }
-        let width = (screen_width - MARGIN_RIGHT) as usize - self.0.len();
+        let width = right - self.0.len();
         self.add_string(format!("{text:>width$}"), TextStyle::Typename);
-        for _ in 0..MARGIN_RIGHT { self.0.push((' ', TextStyle::Typename)); }
+        for _ in 0..config.margin_right { self.0.push((' ', TextStyle::Typename)); }
+    }
+
+    // the text right before the type name at the end of the line, over the spaces or the end of the content
+    pub fn insert_before_typename(&mut self, text: &str, style: TextStyle) {
+        let end = self.0.len();
+        let run = self.0.iter().rev().take_while(|(_, style)| matches!(style, TextStyle::Typename | TextStyle::SelectedTypename)).count();
+        let Some(type_start) = (end - run..end).find(|index| self.0[*index].0 != ' ') else { return; };
+        let chars: Vec<(char, TextStyle)> = text.chars().map(|c| (c, style)).collect();
+        let start = type_start.saturating_sub(chars.len());
+        // a wide char cut in the middle
+        if start > 0 && self.0[start].0 == WIDE_TAIL { self.0[start - 1].0 = ' '; }
+        self.0.splice(start..type_start, chars[chars.len() - (type_start - start)..].iter().copied());
+    }
+
+    // the n-th char (1-based) added from the column `start` is shown selected, a wide char with its tail
+    pub fn select_char(&mut self, start: usize, n: u16) {
+        let mut count = 0;
+        for cell in self.0.iter_mut().skip(start) {
+            if cell.0 != WIDE_TAIL { count += 1; }
+            if count == n as usize { cell.1 = TextStyle::SelectedValue; }
+            if count > n as usize { break; }
+        }
+    }
+
+    // 1-based number of the value shown at the column (or the nearest value before it), 0 if none
+    pub fn value_index_at(&self, column: u16) -> u16 {
+        let mut index = 0;
+        let mut in_value = false;
+        for (pos, (c, style)) in self.0.iter().enumerate() {
+            let is_value = (*style == TextStyle::Value || *style == TextStyle::SelectedValue) && *c != ' ';
+            if is_value && !in_value { index += 1; }
+            in_value = is_value;
+            if pos >= column as usize { break; }
+        }
+        index
     }
 
     pub fn fix_length(&mut self, len: u16) {
@@ -316,6 +680,8 @@ impl ScreenLine {
                 self.0.append(&mut spaces);
             }
             Ordering::Greater => {
+                // a wide char does not fit in the last column
+                if self.0[len].0 == WIDE_TAIL { self.0[len - 1].0 = ' '; }
                 self.0.truncate(len);
             }
             Ordering::Equal => {}
@@ -330,20 +696,33 @@ impl ScreenLines {
 
 pub struct IndentsCalc {
     level_indents: Vec<u16>,
+    margin_left: u16,
+    level_indent: u16,
+    // the widest first column asked since it was taken, with the left margin
+    requested: u16,
 }
 
 impl IndentsCalc {
-    const NEXT_LEVEL_INDENT: u16 = 2;
+    pub fn new(config: &LayoutConfig) -> IndentsCalc {
+        Self::new_for_update(Vec::with_capacity(8), config)
+    }
 
-    pub fn new() -> IndentsCalc {
+    pub fn new_for_update(indents: Vec<u16>, config: &LayoutConfig) -> IndentsCalc {
         IndentsCalc {
-            level_indents: Vec::with_capacity(8)
+            level_indents: indents,
+            margin_left: config.margin_left,
+            level_indent: config.level_indent,
+            requested: 0,
         }
     }
 
-    pub fn new_for_update(indents: Vec<u16>) -> IndentsCalc {
-        IndentsCalc {
-            level_indents: indents
+    pub fn take_requested(&mut self) -> u16 { mem::take(&mut self.requested) }
+
+    // the indent of the level (from 1) for the layouts sized now, the levels not seen yet get the step of a level
+    pub fn indent(&self, level: usize) -> u16 {
+        match self.level_indents.get(level - 1) {
+            Some(indent) => *indent,
+            None => self.level_indents.last().unwrap_or(&0) + self.level_indent * (level - self.level_indents.len()) as u16,
         }
     }
 
@@ -352,14 +731,15 @@ impl IndentsCalc {
         level -= 1;
         while self.level_indents.len() <= level {
             let new_indent = self.level_indents.last().unwrap_or(&0);
-            self.level_indents.push(Self::NEXT_LEVEL_INDENT + *new_indent);
+            self.level_indents.push(self.level_indent + *new_indent);
         }
 
-        let new_width = MARGIN_LEFT + first_column_width as u16;
+        let new_width = self.margin_left + first_column_width as u16;
+        self.requested = self.requested.max(new_width);
         if self.level_indents[level] < new_width {
             self.level_indents[level] = new_width;
             for i in level + 1..self.level_indents.len() {
-                self.level_indents[i] = self.level_indents[i - 1] + Self::NEXT_LEVEL_INDENT;
+                self.level_indents[i] = self.level_indents[i - 1] + self.level_indent;
             }
         }
         self.level_indents[level]
@@ -374,14 +754,14 @@ impl Into<Vec<u16>> for IndentsCalc {
 
 
 impl ScalarLayout {
-    const MARGIN: u16 = MARGIN_LEFT + MARGIN_RIGHT;
+    fn margin(config: &LayoutConfig) -> u16 { config.margin_left + config.margin_right }
 
     fn new() -> Self {
         ScalarLayout { line_lens: vec![] }
     }
-    fn add_scalar_value(line: &mut ScreenLine, value: &ScalarValue, def: &FieldProtoPtr, config: &LayoutConfig, selected: bool) {
-        line.0.push((' ', TextStyle::Divider));
-        let style = if selected { TextStyle::SelectedValue } else { TextStyle::Value };
+    // the separator is PRESENCE_MARK for a default value present in the data
+    fn add_scalar_value(line: &mut ScreenLine, value: &ScalarValue, def: &FieldProtoPtr, config: &LayoutConfig, style: TextStyle, separator: char) {
+        line.0.push((separator, TextStyle::Divider));
         line.add_string(Self::scalar_to_string(value, def, config), style);
     }
     fn scalar_to_string(value: &ScalarValue, def: &FieldProtoPtr, config: &LayoutConfig) -> String {
@@ -391,6 +771,22 @@ impl ScalarLayout {
             } else {
                 format!("?{}", *value)
             }
+        } else if let Some(text) = config.field_format(def).filter(|_| value.is_integer()).and_then(|format| Self::format_integer(value, format, config)) {
+            text
+        } else if config.unsigned.iter().any(|field| Arc::ptr_eq(field, def)) {
+            let text = match value {
+                ScalarValue::I32(v) | ScalarValue::S32(v) | ScalarValue::SF32(v) => (*v as u32).to_string(),
+                ScalarValue::I64(v) | ScalarValue::S64(v) | ScalarValue::SF64(v) => (*v as u64).to_string(),
+                _ => format!("{}", value),
+            };
+            match config.digit_separator {
+                Some(separator) => group_digits(&text, separator),
+                None => text,
+            }
+        } else if let Some(separator) = config.digit_separator.filter(|_| value.is_integer()) {
+            group_digits(&value.to_string(), separator)
+        } else if let Some(text) = config.float_format.format(value) {
+            if config.decimal_comma { text.replace('.', ",") } else { text }
         } else {
             //            if config.hex {
             //                format!("{:X}", value) // TODO
@@ -401,10 +797,47 @@ impl ScalarLayout {
         }
     }
 
+    // in hex (-0x80) or as the time since 1970 it counts, in the zone of the settings or UTC
+    fn format_integer(value: &ScalarValue, format: FieldFormat, config: &LayoutConfig) -> Option<String> {
+        let number = value.to_string().parse::<i128>().ok()?;
+        match format {
+            FieldFormat::Hex if number < 0 => Some(format!("-0x{:X}", number.unsigned_abs())),
+            FieldFormat::Hex => Some(format!("0x{:X}", number)),
+            FieldFormat::Timestamp => timestamp::of_number(number, config.time_zone.as_ref().unwrap_or(&TimeZone::Fixed(0))),
+            FieldFormat::Render(_) => None,
+        }
+    }
+
+    // after the '*' of a repeated field: 'p' packed, arrows for a group of values continued above or below
+    // with other fields between them (in the wire order)
+    fn marks(msg: &MessageData, def: &FieldProtoPtr, path: &FieldPath, amount: usize) -> String {
+        let mut res = if msg.is_packed(def) { "p".to_string() } else { String::new() };
+        if let Some(pos) = path.0.last().filter(|_| def.repeated() && amount > 0) {
+            let total = msg.fields.iter().filter(|field| field.id() == pos.id).count();
+            match (pos.index > 0, pos.index + amount < total) {
+                (true, true) => res.push('↕'),
+                (true, false) => res.push('↑'),
+                (false, true) => res.push('↓'),
+                (false, false) => {}
+            }
+        }
+        res
+    }
+
+    // how the value is written: zigzag varint or little-endian fixed size, nothing for plain varints
+    pub fn encoding_mark(def: &FieldProtoPtr, config: &LayoutConfig) -> &'static str {
+        if !config.show_encoding { return ""; }
+        match def.typename().as_str() {
+            "sint32" | "sint64" => "~z",
+            "fixed32" | "sfixed32" | "fixed64" | "sfixed64" | "float" | "double" => "~f",
+            _ => "",
+        }
+    }
+
     fn get_line_lens(&self, full_width: u16, indent: u16, def: &FieldProtoPtr, msg: &MessageData, path: &FieldPath, amount: usize, config: &LayoutConfig) -> Vec<usize> {
-        let mut avail_width = (full_width - indent - Self::MARGIN) as usize;
+        let mut avail_width = (full_width - indent - Self::margin(config)) as usize;
         if def.repeated() { avail_width -= 1 }
-        avail_width -= def.typename().len();
+        avail_width -= def.typename().len() + Self::encoding_mark(def, config).len() + text_width(&Self::marks(msg, def, path, amount));
 
         debug_assert!(amount > 0);
         let mut cur_len = 0;
@@ -425,7 +858,7 @@ impl ScalarLayout {
                             //line_count += 1;
                             starts.push(index - prv_line_end);
                             prv_line_end = index;
-                            avail_width = (full_width - indent - Self::MARGIN) as usize;
+                            avail_width = (full_width - indent - Self::margin(config)) as usize;
                         }
                     }
                 }
@@ -457,10 +890,26 @@ impl ScalarLayout {
 }
 impl ViewLayout for ScalarLayout {
     fn layout_type(&self) -> LayoutType { LayoutType::Scalar }
+    fn copy(&self) -> Box<dyn ViewLayout> { Box::new(self.clone()) }
+    fn cursor_x_at(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, column: u16, line: usize) -> u16 {
+        let lines = self.get_screen(root, path, amount, width, indent, config, None);
+        lines.0.get(line).map_or(0, |l| l.value_index_at(column))
+    }
     fn get_status_string(&self, cursor_x: u16, cursor_y: usize) -> String {
         //format!("/{}", self.amount)
         String::new()
     }
+    fn selected_index(&self, cursor_x: u16, cursor_y: usize) -> usize {
+        if cursor_x == 0 { 0 } else { self.data_index_at_cursor(cursor_x, cursor_y) }
+    }
+    fn cursor_at_index(&self, index: usize) -> (u16, usize) {
+        let mut sum = 0;
+        for (line_index, line_len) in self.line_lens.iter().enumerate() {
+            if index < sum + line_len { return ((index - sum + 1) as u16, line_index); }
+            sum += line_len;
+        }
+        (0, 0)
+    }
     fn calc_sizes(&mut self, root: &MessageData, path: &FieldPath, amount: usize, config: &LayoutConfig, width: u16, negotiator: &mut IndentsCalc) -> usize {
         if let Some(field_proto) = root.get_field_definition(path) {
             let field_name_length = field_proto.name().len();
@@ -492,20 +941,22 @@ impl ViewLayout for ScalarLayout {
         let mut lines = ScreenLines::new();
         let mut line = ScreenLine::new(width);
         if let Some(field_def) = root.get_field_definition(path) {
-            line.add_field_name(field_def.name().clone(), indent, &cursor);
-
+            line.add_field_name(&field_def, indent, &cursor);
+            let mark = root.get_submessage(&path.0[..path.0.len() - 1]).map_or(String::new(), |msg| Self::marks(msg, &field_def, path, amount));
+            let encoding = Self::encoding_mark(&field_def, config);
 
             let selected_index = cursor.map_or(usize::MAX, |(x, y)| self.data_index_at_cursor(x, y));
 
             if amount == 0 {
                 // no data was read, show default value
                 if let FieldValue::SCALAR(value) = field_def.default() {
-                    Self::add_scalar_value(&mut line, &value, &field_def, config, selected_index == 0);
+                    let style = if selected_index == 0 { TextStyle::SelectedValue } else { TextStyle::DefaultValue };
+                    Self::add_scalar_value(&mut line, &value, &field_def, config, style, ' ');
                 }
             } else {
-                let mut avail_width = (width - indent - Self::MARGIN) as usize;
+                let mut avail_width = (width - indent - Self::margin(config)) as usize;
                 if field_def.repeated() { avail_width -= 1 }
-                avail_width -= field_def.typename().len();
+                avail_width -= field_def.typename().len() + encoding.len() + text_width(&mark);
 
                 debug_assert!(amount > 0);
                 let mut cur_len = 0;
@@ -522,15 +973,17 @@ impl ViewLayout for ScalarLayout {
                                 line_count += 1;
 
                                 if lines.0.is_empty() {
-                                    avail_width = (width - indent - Self::MARGIN) as usize;
-                                    line.add_typename(field.def.clone(), width, false);
+                                    avail_width = (width - indent - Self::margin(config)) as usize;
+                                    line.add_typename_marked(field.def.clone(), width, false, encoding, &mark, config);
                                 }
 
                                 lines.0.push(line);
                                 line = ScreenLine::new(width);
                                 line.add_value_address(format!("{}", index), indent, &cursor, lines.0.len());
                             }
-                            Self::add_scalar_value(&mut line, value, &field.def, config, selected_index == index);
+                            let style = if selected_index == index { TextStyle::SelectedValue } else { TextStyle::Value };
+                            let separator = if field.explicit_default() { PRESENCE_MARK } else { ' ' };
+                            Self::add_scalar_value(&mut line, value, &field.def, config, style, separator);
                         }
                     }
                     p.last_mut().unwrap().index += 1;
@@ -538,7 +991,7 @@ impl ViewLayout for ScalarLayout {
             }
 
             if lines.0.is_empty() {
-                line.add_typename(field_def.clone(), width, amount == 0);
+                line.add_typename_marked(field_def.clone(), width, amount == 0, encoding, &mark, config);
             }
             line.fix_length(width);
         }
@@ -565,7 +1018,7 @@ impl ViewLayout for ScalarLayout {
                 (*cursor_x, *cursor_pos) = self.cursor_at_data_index(index + 1);
                 self.line_lens.clear();
                 let def = root.get_field_definition(&path).unwrap();
-                CommandResult::ChangeData(Change { path: path.clone(), action: ChangeType::Insert(def.default()) })
+                CommandResult::ChangeData(Change { path: path.clone(), action: ChangeType::Insert(config.inserted_value(&def)) })
             }
             UserCommand::ScrollHorizontally(delta) => {
                 if let Some(len) = self.line_lens.get(*cursor_pos) {
@@ -594,29 +1047,95 @@ impl ViewLayout for ScalarLayout {
 }
 
 impl StringLayout {
-    const MARGIN: u16 = 8 + MARGIN_LEFT + MARGIN_RIGHT;
-    fn get_lines_formated<'t>(&self, full_width: u16, indent: u16, repeated: bool, empty_field: bool, text: &'t String) -> Vec<(&'t str, bool)> {
+    fn margin(config: &LayoutConfig) -> u16 { 8 + config.margin_left + config.margin_right }
+    const CUT_MARK: char = '…';
+
+    fn wrapped(&self, config: &LayoutConfig) -> bool { self.wrap.unwrap_or(config.wrap_strings) }
+
+    // the text shown for the value, bytes not in the renderer format are shown in hex
+    fn text(&self, value: &FieldValue, config: &LayoutConfig) -> Option<String> {
+        let FieldValue::SCALAR(value) = value else { return None; };
+        let text = match (value, self.renderer) {
+            (STR(text), None) => Some(text.to_string()),
+            (STR(text), Some(renderer)) => Some(renderer.render(text.as_bytes()).unwrap_or_else(|| text.to_string())),
+            (BYTES(bytes), Some(renderer)) => Some(renderer.render(bytes).unwrap_or_else(|| value.to_string())),
+            _ => None,
+        };
+        if config.raw_strings { text } else { text.map(|text| escape_controls(&text)) }
+    }
+
+    // the value in one line with room for the type name, true if it's cut
+    fn get_line_truncated(&self, line: &ScreenLine, width: u16, field_def: &FieldProtoPtr, empty_field: bool, text: &str, config: &LayoutConfig) -> (String, bool) {
+        let type_len = field_def.typename().len() + field_def.repeated() as usize + empty_field as usize;
+        // a space and quotes around the value, a space before the type name
+        let avail = (width as usize).saturating_sub(line.0.len() + 4 + type_len + config.margin_right as usize);
+        let first_line = text.lines().next().unwrap_or("");
+        if first_line.len() == text.len() && text_width(text) <= avail {
+            return (text.to_string(), false);
+        }
+        let end = Self::split_at_width(first_line, avail);
+        (first_line[..end].to_string(), true)
+    }
+
+    // byte offset of the first char not fitting in the width, combining marks stay with their char
+    fn split_at_width(text: &str, width: usize) -> usize {
+        let mut used = 0;
+        for (pos, c) in text.char_indices() {
+            used += char_width(c);
+            if used > width { return pos; }
+        }
+        text.len()
+    }
+
+    // the chars the cursor moves by: zero width chars are not shown
+    fn char_count(text: &str) -> usize {
+        let mut line = ScreenLine::new(0);
+        line.add_string(text.to_string(), TextStyle::Value);
+        line.0.iter().filter(|(c, _)| *c != WIDE_TAIL).count()
+    }
+
+    // the part of the value on each screen line, without quotes
+    fn shown_lines(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig) -> Vec<String> {
+        let Some(field) = root.get_field(&path.0) else { return vec![]; };
+        let Some(value) = self.text(&field.value, config) else { return vec![]; };
+        if !self.wrapped(config) {
+            let mut line = ScreenLine::new(width);
+            line.add_field_name(&field.def, indent, &None);
+            return vec![self.get_line_truncated(&line, width, &field.def, amount == 0, &value, config).0];
+        }
+        let lines = self.get_lines_formated(width, indent, field.def.repeated(), amount == 0, &value, config);
+        if lines.len() <= 1 { return vec![value]; }
+        lines.into_iter().map(|(text, _)| text.to_string()).collect()
+    }
+
+    // the 0-based index of the selected char in the shown text
+    fn char_index(&self, cursor_x: u16, cursor_y: usize) -> Option<usize> {
+        let len = *self.line_lens.get(cursor_y)?;
+        if cursor_x == 0 || len == 0 { return None; }
+        Some(self.line_lens[..cursor_y].iter().sum::<usize>() + (cursor_x as usize).min(len) - 1)
+    }
+
+    fn get_lines_formated<'t>(&self, full_width: u16, indent: u16, repeated: bool, empty_field: bool, text: &'t String, config: &LayoutConfig) -> Vec<(&'t str, bool)> {
         let mut res = vec![];
 
-        let mut avail_width = (full_width - indent - Self::MARGIN) as usize;
-        if repeated { avail_width -= 1 }
-        if empty_field { avail_width -= 1 }
+        let mut avail_width = full_width.saturating_sub(indent + Self::margin(config)) as usize;
+        if repeated { avail_width = avail_width.saturating_sub(1) }
+        if empty_field { avail_width = avail_width.saturating_sub(1) }
 
         for line in text.lines() {
             let mut start_pos = 0;
-            let mut end_pos = line.len();
             loop {
-                if avail_width < end_pos - start_pos {
-                    end_pos = start_pos + avail_width;
+                // lines are split by the screen width of chars, at least one char on a line
+                let rest = &line[start_pos..];
+                let mut end_pos = start_pos + Self::split_at_width(rest, avail_width);
+                if end_pos == start_pos && !rest.is_empty() {
+                    end_pos += rest.chars().next().unwrap().len_utf8();
                 }
-
-                // byte index 76 is not a char boundary; it is inside 'а' (bytes 75..77) of `исполняющий обязанности премьер-министра` note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
                 res.push((&line[start_pos..end_pos], start_pos == 0));
-                avail_width = (full_width - indent - 3) as usize;
+                avail_width = full_width.saturating_sub(indent + 3) as usize;
 
                 if end_pos >= line.len() { break; }
                 start_pos = end_pos;
-                end_pos = line.len();
             }
         }
         res
@@ -624,16 +1143,24 @@ impl StringLayout {
 }
 impl ViewLayout for StringLayout {
     fn layout_type(&self) -> LayoutType { LayoutType::Str }
+    fn copy(&self) -> Box<dyn ViewLayout> { Box::new(self.clone()) }
+    // the char at the column, the last one of the line after its end
+    fn cursor_x_at(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, column: u16, line: usize) -> u16 {
+        let lines = self.get_screen(root, path, amount, width, indent, config, None);
+        let Some(line) = lines.0.get(line) else { return 0; };
+        line.0.iter().take(column as usize + 1).filter(|(c, style)| *c != WIDE_TAIL && matches!(style, TextStyle::Value | TextStyle::Escape)).count() as u16
+    }
+    fn get_status_string(&self, cursor_x: u16, cursor_y: usize) -> String {
+        self.char_index(cursor_x, cursor_y).map_or(String::new(), |index| format!("{}/{}", index, self.line_lens.iter().sum::<usize>()))
+    }
     fn calc_sizes(&mut self, root: &MessageData, path: &FieldPath, amount: usize, config: &LayoutConfig, width: u16, negotiator: &mut IndentsCalc) -> usize {
 
         // calculate width of first column as maximum length of field name and address
         let mut def: Option<FieldProtoPtr> = None;
-        let mut value: Option<&String> = None;
+        let mut value: Option<String> = None;
         if let Some(field) = root.get_field(&path.0) {
-            if let FieldValue::SCALAR(ScalarValue::STR(data)) = &field.value {
-                def = Some(field.def.clone());
-                value = Some(&data);
-            }
+            value = self.text(&field.value, config);
+            def = value.as_ref().map(|_| field.def.clone());
         }
         if def.is_none() { // no data was read, get field name from proto file
             if let Some(field_def) = root.get_field_definition(path) {
@@ -645,18 +1172,19 @@ impl ViewLayout for StringLayout {
         if let Some(field_def) = def {
             let indent = negotiator.add(field_def.name().len(), path.0.len());
 
-            if let Some(text) = value {
-                line_count = self.get_lines_formated(width, indent, field_def.repeated(), amount==0, text).len();
+            if let Some(text) = value.as_ref().filter(|_| self.wrapped(config)) {
+                line_count = self.get_lines_formated(width, indent, field_def.repeated(), amount==0, text, config).len();
 
                 let mut address_len = 0;
                 address_len = format!("{}", line_count).len() as u16;
 
                 if address_len > indent {
                     negotiator.add(address_len as usize, path.0.len());
-                    line_count = self.get_lines_formated(width, indent, field_def.repeated(), amount==0, text).len();
+                    line_count = self.get_lines_formated(width, indent, field_def.repeated(), amount==0, text, config).len();
                     // if line count changed, address length may be increased
                 }
             }
+            self.line_lens = self.shown_lines(root, path, amount, width, indent, config).iter().map(|text| Self::char_count(text)).collect();
         }
         return line_count.max(1);
     }
@@ -666,15 +1194,35 @@ impl ViewLayout for StringLayout {
         let mut line = ScreenLine::new(width);
 
         if let Some(field_def) = root.get_field_definition(path) {
-            line.add_field_name(field_def.name().clone(), indent, &cursor);
+            line.add_field_name(&field_def, indent, &cursor);
 
             if let Some(field) = root.get_field(&path.0) {
-                if let FieldValue::SCALAR(ScalarValue::STR(value)) = &field.value {
-                    let line_by_line = self.get_lines_formated(width, indent, field_def.repeated(), amount==0, value);
+                if let Some(value) = &self.text(&field.value, config) {
+                    let separator = if field.explicit_default() { PRESENCE_MARK } else { ' ' };
+                    // the selected char of a line
+                    let selected = |line_index: usize| cursor.filter(|(x, y)| *x > 0 && *y == line_index).map(|(x, _)| x);
+                    let add_text = |line: &mut ScreenLine, text: String, line_index: usize| {
+                        let start = line.0.len();
+                        let len = Self::char_count(&text) as u16;
+                        line.add_string(text, TextStyle::Value);
+                        if let Some(x) = selected(line_index) { line.select_char(start, x.min(len)); }
+                    };
+                    if !self.wrapped(config) {
+                        let (text, cut) = self.get_line_truncated(&line, width, &field_def, amount==0, value, config);
+                        line.0.push((separator, TextStyle::Divider));
+                        line.0.push(('\'', TextStyle::Divider));
+                        add_text(&mut line, text, 0);
+                        line.0.push((if cut { Self::CUT_MARK } else { '\'' }, TextStyle::Divider));
+                        line.fix_length(width);
+                        lines.push(line);
+                        lines.first_mut().unwrap().add_typename(field_def, width, amount==0, config);
+                        return ScreenLines(lines);
+                    }
+                    let line_by_line = self.get_lines_formated(width, indent, field_def.repeated(), amount==0, value, config);
                     if line_by_line.len() <= 1 {
-                        line.0.push((' ', TextStyle::Divider));
+                        line.0.push((separator, TextStyle::Divider));
                         line.0.push(('\'', TextStyle::Divider));
-                        line.add_string(value.to_string(), TextStyle::Value);
+                        add_text(&mut line, value.to_string(), 0);
                         line.0.push(('\'', TextStyle::Divider));
                         line.fix_length(width);
                     } else { // multiline
@@ -691,7 +1239,7 @@ impl ViewLayout for StringLayout {
                                     }, indent, &cursor, lines.len());
                             }
                             line.0.push((' ', TextStyle::Divider));
-                            line.add_string(text.0.to_string(), TextStyle::Value);
+                            add_text(&mut line, text.0.to_string(), lines.len());
                             line.fix_length(width);
                             if text.1 { index += 1 }
                         }
@@ -703,7 +1251,7 @@ impl ViewLayout for StringLayout {
                 line.0.push(('\'', TextStyle::Divider));
             }
             lines.push(line);
-            lines.first_mut().unwrap().add_typename(field_def, width, amount==0);
+            lines.first_mut().unwrap().add_typename(field_def, width, amount==0, config);
         }
         ScreenLines(lines)
     }
@@ -717,18 +1265,51 @@ impl ViewLayout for StringLayout {
         //        if self.visible_lines_count < 1 { self.visible_lines_count = 1 }
 
         match command {
+            UserCommand::WrapToggle => {
+                // back to the common setting if it's the same
+                let wrap = !self.wrapped(config);
+                self.wrap = if wrap == config.wrap_strings { None } else { Some(wrap) };
+                *cursor_pos = 0;
+                *cursor_x = 0;
+                CommandResult::Redraw
+            }
+            UserCommand::ScrollHorizontally(delta) => {
+                let Some(len) = self.line_lens.get(*cursor_pos).map(|len| *len as u16) else { return CommandResult::None; };
+                // the cursor may be past the end of a shorter line after moving up or down
+                *cursor_x = (*cursor_x).min(len);
+                if delta > 0 {
+                    *cursor_x = (*cursor_x + delta as u16).min(len);
+                } else {
+                    *cursor_x -= (-delta as u16).min(*cursor_x);
+                }
+                CommandResult::Redraw
+            }
+            UserCommand::Home => {
+                if self.line_lens.get(*cursor_pos).map_or(true, |len| *len == 0) { return CommandResult::None; }
+                *cursor_x = if *cursor_x == 1 { 0 } else { 1 };
+                CommandResult::Redraw
+            }
+            UserCommand::End => {
+                let Some(len) = self.line_lens.get(*cursor_pos) else { return CommandResult::None; };
+                *cursor_x = *len as u16;
+                CommandResult::Redraw
+            }
             _ => CommandResult::None  // TODO
         }
     }
 }
 
 impl BytesLayout {
-    fn calc_sizes_internal(&self, mut width: u16, indent: u16, repeated: bool, empty_field: bool) -> (usize, u16) {
+    fn calc_sizes_internal(&self, mut width: u16, indent: u16, repeated: bool, empty_field: bool, config: &LayoutConfig) -> (usize, u16) {
+        // sizes are compared as usize, a blob can be larger than u16::MAX
         let mut free_width = width;
         free_width -= indent + 1; // field and ':'
         free_width -= 5; // "bytes".len()
+        // the default right margin fits in the gap after the last block
+        free_width = free_width.saturating_sub(config.margin_right.saturating_sub(MARGIN_RIGHT));
         if empty_field { free_width -= 1 } // '-' before type name
         if repeated { free_width -= 1 } // '*' after type name
+        if let Some(gutter) = self.gutter_width(config) { free_width = free_width.saturating_sub(gutter as u16 + 1) }
 
         let mut blocks_count = free_width / (8 * 3 + 1); // each block 8 bytes wide
 
@@ -743,9 +1324,9 @@ impl BytesLayout {
                 (free_width - 1) / 3
             } else {
                 // if possible, concatenate the last short line with the first line
-                if self.data_size as u16 > blocks_count * 8 {
-                    let one_line_len = blocks_count * (8 * 3 + 1) + 1 + (self.data_size as u16 - blocks_count * 8) * 3;
-                    if one_line_len <= free_width {
+                if self.data_size > blocks_count as usize * 8 {
+                    let one_line_len = blocks_count as usize * (8 * 3 + 1) + 1 + (self.data_size - blocks_count as usize * 8) * 3;
+                    if one_line_len <= free_width as usize {
                         self.data_size as u16
                     } else { blocks_count * 8 }
                 } else { blocks_count * 8 }
@@ -757,13 +1338,25 @@ impl BytesLayout {
             height += 1;
         }
         height = height.max(1); // one line always shown, even if there is no data
+        if config.bytes_offsets { height += 1 } // the total length
 
         (height, bytes_on_line)
     }
 
+    // the column of the line offsets, if shown
+    fn gutter_width(&self, config: &LayoutConfig) -> Option<usize> {
+        config.bytes_offsets.then(|| Self::address(self.data_size, config).len())
+    }
+
+    // offset of a line in the first column
+    fn address(offset: usize, config: &LayoutConfig) -> String {
+        if config.hex_addresses { format!("{:X}", offset) } else { offset.to_string() }
+    }
+
+    // None for the first column and the line of the total length
     fn data_index_from_cursor(&self, cursor_x: u16, cursor_y: usize) -> Option<usize> {
         if cursor_x == 0 { None } else {
-            Some(cursor_x as usize + self.bytes_per_line as usize * cursor_y - 1)
+            Some(cursor_x as usize + self.bytes_per_line as usize * cursor_y - 1).filter(|index| *index < self.data_size)
         }
     }
 
@@ -776,6 +1369,11 @@ impl BytesLayout {
 
 impl ViewLayout for BytesLayout {
     fn layout_type(&self) -> LayoutType { LayoutType::Bytes }
+    fn copy(&self) -> Box<dyn ViewLayout> { Box::new(self.clone()) }
+    fn cursor_x_at(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, column: u16, line: usize) -> u16 {
+        let lines = self.get_screen_window(root, path, amount, width, indent, config, None, line, 1);
+        lines.0.first().map_or(0, |l| l.value_index_at(column))
+    }
     fn calc_sizes(&mut self, root: &MessageData, path: &FieldPath, amount: usize, config: &LayoutConfig, width: u16, negotiator: &mut IndentsCalc) -> usize {
 
         // calculate width of first column as maximum length of field name and address
@@ -787,7 +1385,8 @@ impl ViewLayout for BytesLayout {
             debug_assert!(amount > 0);
             if let FieldValue::SCALAR(ScalarValue::BYTES(data)) = &field.value {
                 self.data_size = data.len();
-                address_len = format!("{:x}", self.data_size).len();
+                // the offsets column replaces the addresses in the first one
+                if !config.bytes_offsets { address_len = Self::address(self.data_size, config).len(); }
                 name_len = field.def.name().len();
                 repeated = field.def.repeated();
                 debug_assert!(name_len > 0);
@@ -800,44 +1399,63 @@ impl ViewLayout for BytesLayout {
             }
         }
         let indent = negotiator.add(address_len.max(name_len), path.0.len());
-        let (height, len) = self.calc_sizes_internal(width, indent, repeated, amount==0);
+        let (height, len) = self.calc_sizes_internal(width, indent, repeated, amount==0, config);
         self.bytes_per_line = len;
         height
     }
 
     fn get_screen(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, cursor: Option<(u16, usize)>) -> ScreenLines {
-        let mut lines = vec![];
-        let mut line = ScreenLine::new(width);
+        self.get_screen_window(root, path, amount, width, indent, config, cursor, 0, usize::MAX)
+    }
 
+    // hex lines are made only for the visible part, a blob may have millions of them
+    fn get_screen_window(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, cursor: Option<(u16, usize)>, skip: usize, count: usize) -> ScreenLines {
+        let mut lines = vec![];
         let selected_index = cursor.map_or(usize::MAX, |(x, y)| {
             self.data_index_from_cursor(x, y).unwrap_or(usize::MAX)
         });
 
         if let Some(field_def) = root.get_field_definition(path) {
-            line.add_field_name(field_def.name().clone(), indent, &cursor);
-
-            if let Some(field) = root.get_field(&path.0) {
-                if let FieldValue::SCALAR(BYTES(value)) = &field.value {
-                    for index in 0..value.len() {
-                        if 0 != index {
-                            if 0 == index % self.bytes_per_line as usize { // create new line
-                                line.fix_length(width);
-                                lines.push(line);
-                                line = ScreenLine::new(width);
-                                line.add_value_address(format!("{:X}", index), indent, &cursor, lines.len());
-                            } else { // add space between every 8 bytes
-                                if self.bytes_per_line > 8 && 0 == index & 7 { line.add_string(" ".to_string(), TextStyle::Value) }
-                            }
-                        }
-                        let style = if selected_index == index { TextStyle::SelectedValue } else { TextStyle::Value };
-                        line.add_string(" ".to_string(), TextStyle::Divider);
-                        line.add_string(format!("{:02X}", value[index]), style);
-                    }
+            let value = match root.get_field(&path.0).map(|field| &field.value) {
+                Some(FieldValue::SCALAR(BYTES(value))) => &value[..],
+                _ => &[],
+            };
+            let per_line = self.bytes_per_line.max(1) as usize;
+            let line_count = value.len().div_ceil(per_line).max(1);
+            let gutter = self.gutter_width(config);
+            let total_count = line_count + gutter.is_some() as usize;
+            for line_index in skip..total_count.min(skip.saturating_add(count)) {
+                let mut line = ScreenLine::new(width);
+                if line_index == line_count {
+                    line.add_value_address(String::new(), indent, &cursor, line_index);
+                    let hex = if config.hex_addresses { format!(" ({:#X})", value.len()) } else { String::new() };
+                    line.add_string(format!(" {} bytes{}", value.len(), hex), TextStyle::DataSize);
+                    line.fix_length(width);
+                    lines.push(line);
+                    continue;
+                }
+                let start = line_index * per_line;
+                if line_index == 0 {
+                    line.add_field_name(&field_def, indent, &cursor);
+                } else if gutter.is_some() {
+                    line.add_value_address(String::new(), indent, &cursor, line_index);
+                } else {
+                    line.add_value_address(Self::address(start, config), indent, &cursor, line_index);
+                }
+                if let Some(gutter) = gutter {
+                    line.add_string(format!(" {:>gutter$}", Self::address(start, config)), TextStyle::FieldIndex);
                 }
+                for index in start..value.len().min(start + per_line) {
+                    // add space between every 8 bytes
+                    if index != start && self.bytes_per_line > 8 && 0 == index & 7 { line.add_string(" ".to_string(), TextStyle::Value) }
+                    let style = if selected_index == index { TextStyle::SelectedValue } else { TextStyle::Value };
+                    line.add_string(" ".to_string(), TextStyle::Divider);
+                    line.add_string(format!("{:02X}", value[index]), style);
+                }
+                line.fix_length(width);
+                if line_index == 0 { line.add_typename(field_def.clone(), width, amount == 0, config); }
+                lines.push(line);
             }
-            line.fix_length(width);
-            lines.push(line);
-            lines.first_mut().unwrap().add_typename(field_def, width, amount==0);
         }
         ScreenLines(lines)
     }
@@ -848,13 +1466,13 @@ impl ViewLayout for BytesLayout {
                 if let Some(field) = root.get_field(&path.0) {
                     if let FieldValue::SCALAR(BYTES(value)) = &field.value {
                         if let Some(index) = self.data_index_from_cursor(*cursor_x, *cursor_pos) {
-                            let mut value = value.clone();
+                            let mut value = value.to_vec();
                             value.remove(index);
                             self.data_size = value.len();
                             if self.data_size > 0 {
                                 (*cursor_x, *cursor_pos) = self.cursor_from_data_index(index.min(self.data_size - 1));
                             } else { *cursor_x = 0 }
-                            return CommandResult::ChangeData(Change { path: path.clone(), action: ChangeType::Overwrite(FieldValue::SCALAR(BYTES(value))) });
+                            return CommandResult::ChangeData(Change { path: path.clone(), action: ChangeType::Overwrite(FieldValue::SCALAR(BYTES(value.into()))) });
                         }
                     }
                 }
@@ -865,10 +1483,10 @@ impl ViewLayout for BytesLayout {
                 if let Some(field) = root.get_field(&path.0) {
                     if let FieldValue::SCALAR(BYTES(value)) = &field.value {
                         if let Some(index) = self.data_index_from_cursor(*cursor_x, *cursor_pos) {
-                            let mut value = value.clone();
+                            let mut value = value.to_vec();
                             value.insert(index + 1, 0);
                             (*cursor_x, *cursor_pos) = self.cursor_from_data_index(index + 1);
-                            return CommandResult::ChangeData(Change { path: path.clone(), action: ChangeType::Overwrite(FieldValue::SCALAR(BYTES(value))) });
+                            return CommandResult::ChangeData(Change { path: path.clone(), action: ChangeType::Overwrite(FieldValue::SCALAR(BYTES(value.into()))) });
                         }
                     }
                 }
@@ -894,12 +1512,20 @@ impl ViewLayout for BytesLayout {
             }
 
             UserCommand::End => {
-                *cursor_x = self.bytes_per_line;
-                let index = self.data_index_from_cursor((*cursor_x).max(1), *cursor_pos).unwrap();
+                if self.data_size == 0 { return CommandResult::None; }
+                let index = (*cursor_pos + 1) * self.bytes_per_line as usize - 1;
                 (*cursor_x, *cursor_pos) = self.cursor_from_data_index(index.min(self.data_size - 1));
                 CommandResult::Redraw
             }
 
+            UserCommand::GoToOffset(offset) => {
+                if offset >= self.data_size {
+                    return CommandResult::ShowError(format!("the offset is out of the {} bytes", self.data_size));
+                }
+                (*cursor_x, *cursor_pos) = self.cursor_from_data_index(offset);
+                CommandResult::Redraw
+            }
+
             _ => CommandResult::None
         }
     }
@@ -909,7 +1535,7 @@ impl ViewLayout for BytesLayout {
     }
 
     fn get_status_string(&self, cursor_x: u16, cursor_y: usize) -> String {
-        self.data_index_from_cursor(cursor_x, cursor_y).map_or(String::new(), |index| format!("{}/{}", index, self.data_size))
+        self.data_index_from_cursor(cursor_x, cursor_y).map_or(format!("{} bytes", self.data_size), |index| format!("{}/{}", index, self.data_size))
     }
 }
 
@@ -920,6 +1546,7 @@ impl MessageLayout {
 }
 impl ViewLayout for MessageLayout {
     fn layout_type(&self) -> LayoutType { LayoutType::Message }
+    fn copy(&self) -> Box<dyn ViewLayout> { Box::new(self.clone()) }
     fn calc_sizes(&mut self, root: &MessageData, path: &FieldPath, amount: usize, config: &LayoutConfig, width: u16, negotiator: &mut IndentsCalc) -> usize {
         if let Some(field_def) = root.get_field_definition(path) {
             negotiator.add(field_def.name().len(), path.0.len());
@@ -930,8 +1557,11 @@ impl ViewLayout for MessageLayout {
         debug_assert!(amount <= 1);
         let mut line = ScreenLine::new(width);
         if let Some(field_def) = root.get_field_definition(path) {
-            line.add_field_name(field_def.name().clone(), indent, &cursor);
-            line.add_typename(field_def, width, amount == 0);
+            line.add_field_name(&field_def, indent, &cursor);
+            if let Some(time) = root.get_submessage(&path.0).filter(|_| amount > 0).and_then(|msg| config.timestamp(msg, &field_def)) {
+                line.add_string(format!(" {}", time), TextStyle::Value);
+            }
+            line.add_typename(field_def, width, amount == 0, config);
         }
         ScreenLines(vec![line])
     }
@@ -951,14 +1581,15 @@ impl TableLayout {
 }
 impl ViewLayout for TableLayout {
     fn layout_type(&self) -> LayoutType { LayoutType::Table }
+    fn copy(&self) -> Box<dyn ViewLayout> { Box::new(self.clone()) }
     fn calc_sizes(&mut self, root: &MessageData, path: &FieldPath, amount: usize, config: &LayoutConfig, width: u16, negotiator: &mut IndentsCalc) -> usize {
         todo!()
     }
     fn get_screen(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, cursor: Option<(u16, usize)>) -> ScreenLines {
         let mut line = ScreenLine::new(width);
         if let Some(field) = root.get_field(&path.0) {
-            line.add_field_name(field.def.name().clone(), indent, &cursor);
-            line.add_typename(field.def.clone(), width, amount == 0);
+            line.add_field_name(&field.def, indent, &cursor);
+            line.add_typename(field.def.clone(), width, amount == 0, config);
         }
         ScreenLines(vec![line])
     }
@@ -972,6 +1603,7 @@ impl ViewLayout for TableLayout {
 
 impl ViewLayout for CollapsedLayout {
     fn layout_type(&self) -> LayoutType { LayoutType::Collapsed }
+    fn copy(&self) -> Box<dyn ViewLayout> { Box::new(self.clone()) }
     fn calc_sizes(&mut self, root: &MessageData, path: &FieldPath, amount: usize, config: &LayoutConfig, width: u16, negotiator: &mut IndentsCalc) -> usize {
         let def = root.get_field_definition(path).unwrap();
         negotiator.add(def.name().len(), path.0.len());
@@ -981,16 +1613,23 @@ impl ViewLayout for CollapsedLayout {
         let mut line = ScreenLine::new(width);
 
         if let Some(field_def) = root.get_field_definition(path) {
-            line.add_field_name(field_def.name().clone(), indent, &cursor);
-            line.add_field_size(self.display_size, width);
-            line.add_typename(field_def.clone(), width, self.display_size == 0);
+            line.add_field_name(&field_def, indent, &cursor);
+            let msg = root.get_submessage(&path.0);
+            match msg.and_then(|msg| config.messages.get(&msg.def.name).map(|columns| (msg, columns))) {
+                Some((msg, columns)) => line.add_columns(msg, columns, config),
+                None => match msg.and_then(|msg| config.timestamp(msg, &field_def)) {
+                    Some(time) => line.add_string(format!(" {}", time), TextStyle::Value),
+                    None => line.add_field_size(self.display_size, config),
+                },
+            }
+            line.add_typename(field_def.clone(), width, self.display_size == 0, config);
         }
 
 
         //        if let Some(field) = root.get_field(&path.0) {
-        //            line.add_field_name(field.def.name().clone(), indent, &cursor);
+        //            line.add_field_name(&field.def, indent, &cursor);
         //            line.add_field_size(self.display_size, width);
-        //            line.add_typename(field.def.clone(), width, self.display_size == 0);
+        //            line.add_typename(field.def.clone(), width, self.display_size == 0, config);
         //        }
         ScreenLines(vec![line])
     }
@@ -1008,10 +1647,40 @@ impl ViewLayout for CollapsedLayout {
     }
 }
 
+impl ViewLayout for PageLayout {
+    fn layout_type(&self) -> LayoutType { LayoutType::Page }
+    fn copy(&self) -> Box<dyn ViewLayout> { Box::new(self.clone()) }
+    fn calc_sizes(&mut self, root: &MessageData, path: &FieldPath, amount: usize, config: &LayoutConfig, width: u16, negotiator: &mut IndentsCalc) -> usize {
+        if let Some(def) = root.get_field_definition(path) {
+            negotiator.add(def.name().len(), path.0.len());
+        }
+        1
+    }
+    fn get_screen(&self, root: &MessageData, path: &FieldPath, amount: usize, width: u16, indent: u16, config: &LayoutConfig, cursor: Option<(u16, usize)>) -> ScreenLines {
+        let mut line = ScreenLine::new(width);
+        if let Some(def) = root.get_field_definition(path) {
+            line.add_field_name(&def, indent, &cursor);
+            let text = if self.forward {
+                format!(" ▼ {} values after, Enter - next page", amount)
+            } else {
+                format!(" ▲ {} values before, Enter - previous page", amount)
+            };
+            line.add_string(text, TextStyle::DataSize);
+            line.add_typename(def, width, false, config);
+        }
+        ScreenLines(vec![line])
+    }
+    fn on_command(&mut self, root: &MessageData, path: &FieldPath, amount: usize, command: UserCommand, config: &LayoutConfig, width: u16, indent: u16, cursor_x: &mut u16, cursor_pos: &mut usize) -> CommandResult {
+        CommandResult::None
+    }
+    fn page(&self) -> Option<&PageLayout> { Some(self) }
+}
+
 impl TextStyle {
     pub fn first_column(&self) -> bool {
         match self {
             TextStyle::FieldName |
+            TextStyle::DeprecatedFieldName |
             TextStyle::FieldIndex |
             TextStyle::SelectedFieldIndex |
             TextStyle::SelectedFieldName => true,
@@ -1019,6 +1688,35 @@ impl TextStyle {
         }
     }
 
+    // the style in a screen snapshot, the selected variant is the upper case letter
+    pub fn letter(&self) -> char {
+        match self {
+            TextStyle::Comment => 'c',
+            TextStyle::Binary => 'b',
+            TextStyle::Filename => 'f',
+            TextStyle::FieldName => 'n',
+            TextStyle::SelectedFieldName => 'N',
+            TextStyle::DeprecatedFieldName => 'd',
+            TextStyle::FieldIndex => 'i',
+            TextStyle::SelectedFieldIndex => 'I',
+            TextStyle::Value => 'v',
+            TextStyle::SelectedValue => 'V',
+            TextStyle::DefaultValue => 'e',
+            TextStyle::DataSize => 's',
+            TextStyle::Typename => 't',
+            TextStyle::SelectedTypename => 'T',
+            TextStyle::Divider => '|',
+            TextStyle::Bookmark => 'k',
+            TextStyle::Protected => 'p',
+            TextStyle::Escape => 'x',
+            TextStyle::Match => 'm',
+            TextStyle::Highlight => 'h',
+            TextStyle::Warning => 'w',
+            TextStyle::TopLine => '^',
+            TextStyle::Unknown => '?',
+        }
+    }
+
     pub fn activate(&self) -> impl crossterm::Command {
 
         // color theme may use 16 color, 256 color or true color mode,
@@ -1031,11 +1729,17 @@ impl TextStyle {
             TextStyle::SelectedFieldIndex |
             TextStyle::SelectedFieldName => Color::Black,
             TextStyle::FieldIndex |
+            TextStyle::DeprecatedFieldName |
             TextStyle::Divider => Color::DarkGrey,
             TextStyle::Value => Color::White, // Color::AnsiValue(230), // https://www.ditig.com/256-colors-cheat-sheet
             TextStyle::DefaultValue => Color::Grey,
             TextStyle::Typename => Color::DarkCyan,
             TextStyle::Bookmark => Color::Black,
+            TextStyle::Match => Color::Black,
+            TextStyle::Highlight => Color::Black,
+            TextStyle::Protected => Color::Red,
+            TextStyle::Escape => Color::DarkYellow,
+            TextStyle::Warning => Color::Yellow,
             TextStyle::Unknown => Color::Reset,
             _ => Color::Grey,
         };
@@ -1047,6 +1751,8 @@ impl TextStyle {
             TextStyle::SelectedFieldIndex |
             TextStyle::SelectedTypename => Color::DarkCyan,
             TextStyle::Bookmark => Color::Yellow,
+            TextStyle::Match => Color::DarkYellow,
+            TextStyle::Highlight => Color::DarkMagenta,
             _ => Color::Reset,
         };
 
@@ -1059,25 +1765,65 @@ impl TextStyle {
 
 impl LayoutParams {
     pub fn new(path: FieldPath, amount: usize, layout: Box<dyn ViewLayout>) -> LayoutParams {
-        LayoutParams { height: 1, path, amount, layout: Some(layout), children_count: 0 }
+        LayoutParams { height: 1, path, amount, layout: Some(layout), children_count: 0, sized: None, sized_indent: 0, name_width: 0 }
     }
     pub fn new_empty(path: FieldPath, amount: usize) -> LayoutParams {
-        LayoutParams { height: 1, path, amount, layout: None, children_count: 0 }
+        LayoutParams { height: 1, path, amount, layout: None, children_count: 0, sized: None, sized_indent: 0, name_width: 0 }
+    }
+    // the same layout with its sizes, the copy is sized on the sizing thread and replaces it
+    fn copy(&self) -> LayoutParams {
+        LayoutParams { layout: self.layout.as_ref().map(|layout| layout.copy()), path: self.path.clone(), ..*self }
     }
     pub fn level(&self) -> usize {
         self.path.0.len()
     }
+    pub fn is_page(&self) -> bool {
+        self.layout.as_ref().is_some_and(|layout| layout.page().is_some())
+    }
     pub fn get_status_string(&self, cursor_x: u16, cursor_y: usize) -> String {
         if let Some(layout) = self.layout.as_ref() {
             return layout.get_status_string(cursor_x, cursor_y);
         }
         String::new()
     }
+    // tag number and position in the file of the selected value
+    pub fn get_wire_status(&self, root: &MessageData, cursor_x: u16, cursor_y: usize) -> String {
+        let index = self.layout.as_ref().map_or(0, |layout| layout.selected_index(cursor_x, cursor_y));
+        let Some(last) = self.path.0.last().filter(|_| !self.is_page()) else { return String::new(); };
+        let path = self.path.with_last_index(last.index + index);
+        let status = match root.get_field(&path.0) {
+            Some(field) => field.wire_status(),
+            // the default shown is declared by the proto
+            None => root.get_field_definition(&path).map_or(String::new(), |def| match &def.get_common_definition().default {
+                Some(text) => format!("#{} [default = {}]", def.id(), text),
+                None => format!("#{}", def.id()),
+            }),
+        };
+        match root.get_field_definition(&path) {
+            Some(def) if def.deprecated() => format!("{} deprecated", status),
+            _ => status,
+        }
+    }
+    pub fn cursor_x_at(&self, root: &MessageData, width: u16, indent: u16, config: &LayoutConfig, column: u16, line: usize) -> u16 {
+        match &self.layout {
+            Some(layout) if column > indent => layout.cursor_x_at(root, &self.path, self.amount, width, indent, config, column, line),
+            _ => 0,
+        }
+    }
     pub fn calc_sizes(&mut self, root: &MessageData, config: &LayoutConfig, width: u16, negotiator: &mut IndentsCalc) {
         if let Some(layout) = &mut self.layout {
+            negotiator.take_requested();
             self.height = layout.as_mut().calc_sizes(root, &self.path, self.amount, config, width, negotiator);
+            self.name_width = negotiator.take_requested();
         }
     }
+    // the height is kept until the width, the indent of the level or the config is changed, or the data of the layout
+    pub fn ensure_sizes(&mut self, root: &MessageData, config: &LayoutConfig, width: u16, revision: u64, negotiator: &mut IndentsCalc) {
+        if self.layout.is_none() || (self.sized == Some((width, revision)) && self.sized_indent == negotiator.indent(self.level())) { return; }
+        self.calc_sizes(root, config, width, negotiator);
+        self.sized = Some((width, revision));
+        self.sized_indent = negotiator.indent(self.level());
+    }
 
     pub fn get_screen(&self, root: &MessageData, width: u16, indent: u16, config: &LayoutConfig, cursor: Option<(u16, usize)>) -> ScreenLines
     {
@@ -1089,6 +1835,14 @@ impl LayoutParams {
         }
     }
 
+    pub fn get_screen_window(&self, root: &MessageData, width: u16, indent: u16, config: &LayoutConfig, cursor: Option<(u16, usize)>, skip: usize, count: usize) -> ScreenLines
+    {
+        match &self.layout {
+            Some(layout) => layout.get_screen_window(root, &self.path, self.amount, width, indent, config, cursor, skip, count),
+            None => ScreenLines::new(),
+        }
+    }
+
     pub fn on_command(&mut self, root: &MessageData, command: UserCommand, config: &LayoutConfig, width: u16, indent: u16, cursor_x: &mut u16, cursor_pos: &mut usize) -> CommandResult {
         if let Some(layout) = &mut self.layout {
             match command {
@@ -1103,10 +1857,10 @@ impl Layouts {
         let sorted_fields = root.get_sorted_fields(&config.field_order);
         let mut items: Vec<LayoutParams> =
             sorted_fields.into_iter().enumerate().
-                map(|(layout_index, pos_ex)| Self::create_field_layouts(root, &config, &FieldPath([pos_ex.0].into()), pos_ex.1, false)).
+                map(|(layout_index, pos_ex)| Self::create_field_layouts(root, &config, &[], &FieldPath([pos_ex.0].into()), pos_ex.1, false)).
                 flatten().collect();
 
-        let mut negotiator = IndentsCalc::new();
+        let mut negotiator = IndentsCalc::new(config);
 
         for item in &mut items {
             item.calc_sizes(root, config, width, &mut negotiator); // for scalar field only, messages are empty
@@ -1114,42 +1868,63 @@ impl Layouts {
 
         let top_layouts_count = Self::calc_top_layouts_count(&items);
 
-        Layouts { items, file_name: opened_file_name, indents: negotiator.level_indents, scroll: 0, top_layouts_count, width, height }
+        Layouts { items, file_name: opened_file_name, indents: negotiator.level_indents, scroll: 0, top_layouts_count, width, height, revision: 0, pages: vec![], pending_sizes: None, sizing: None }
     }
 
-    fn create_field_layouts(root: &MessageData, config: &LayoutConfig, path: &FieldPath, amount: usize, load_all: bool) -> Vec<LayoutParams> {
+    fn create_field_layouts(root: &MessageData, config: &LayoutConfig, pages: &[(FieldPath, usize)], path: &FieldPath, amount: usize, load_all: bool) -> Vec<LayoutParams> {
         let mut items: Vec<LayoutParams> = vec![];
         let last_pos = path.0.last().unwrap().clone();
         if let Some(field) = root.get_field(&path.0) {
+            // each value has its own layouts, only a page of them is created
+            if amount > config.page_size && (field.def.is_message() || matches!(field.def.typename().as_str(), "string" | "bytes")) {
+                return Self::create_page_layouts(root, config, pages, path, amount, load_all);
+            }
             match &field.value {
                 FieldValue::MESSAGE(msg) => {
                     if amount == 0 {
-                        items.append(&mut Self::create_message_layouts(root, config, path, amount, load_all));
+                        items.append(&mut Self::create_message_layouts(root, config, pages, path, amount, load_all));
                     } else {
                         for index in last_pos.index..last_pos.index + amount { // message layout does not support repeated data
-                            items.append(&mut Self::create_message_layouts(root, config, &path.with_last_index(index), 1, load_all));
+                            items.append(&mut Self::create_message_layouts(root, config, pages, &path.with_last_index(index), 1, load_all));
                         }
                     }
                 }
                 FieldValue::SCALAR(scalar) => {
-                    items.append(&mut Self::create_scalar_layouts(field.def.clone(), path.clone(), amount));
+                    items.append(&mut Self::create_scalar_layouts(root, config, field.def.clone(), path.clone(), amount));
                 }
             }
         } else { // no data was read, show empty field
             let field_def = root.get_field_definition(&path).unwrap();
             debug_assert!(amount == 0);
             if field_def.is_message() {
-                items.append(&mut Self::create_message_layouts(root, config, path, amount, load_all));
+                items.append(&mut Self::create_message_layouts(root, config, pages, path, amount, load_all));
             } else {
-                items.append(&mut Self::create_scalar_layouts(field_def, path.clone(), amount));
+                items.append(&mut Self::create_scalar_layouts(root, config, field_def, path.clone(), amount));
             }
         }
         items
     }
 
-    pub fn create_message_layouts(root: &MessageData, config: &LayoutConfig, path: &FieldPath, amount: usize, load_all: bool) -> Vec<LayoutParams> {
+    // the page row of the values before, the values of the page shown and the page row of the values after
+    fn create_page_layouts(root: &MessageData, config: &LayoutConfig, pages: &[(FieldPath, usize)], path: &FieldPath, amount: usize, load_all: bool) -> Vec<LayoutParams> {
+        let first = path.0.last().unwrap().index;
+        let start = pages.iter().find(|(group, _)| group.0 == path.0).map_or(0, |(_, start)| *start).
+            min((amount - 1) / config.page_size * config.page_size);
+        let end = (start + config.page_size).min(amount);
+        let row = |index: usize, count: usize, forward: bool|
+            LayoutParams::new(path.with_last_index(index), count, Box::new(PageLayout { group: path.clone(), total: amount, forward }));
+        let mut items = vec![];
+        if start > 0 { items.push(row(first, start, false)); }
+        items.append(&mut Self::create_field_layouts(root, config, pages, &path.with_last_index(first + start), end - start, load_all));
+        if end < amount { items.push(row(first + end, amount - end, true)); }
+        items
+    }
+
+    pub fn create_message_layouts(root: &MessageData, config: &LayoutConfig, pages: &[(FieldPath, usize)], path: &FieldPath, amount: usize, load_all: bool) -> Vec<LayoutParams> {
         let mut items: Vec<LayoutParams> = vec![];
-        if load_all {
+        if let Some(msg) = root.get_submessage(&path.0).filter(|_| load_all && amount > 0 && config.collapsed.contains(path)) {
+            items.push(LayoutParams::new(path.clone(), amount, Box::new(CollapsedLayout { display_size: msg.len() })));
+        } else if load_all {
             let msg_layout = MessageLayout::new();
             let consumed_fields = msg_layout.get_consumed_fields(root, path, config);
             items.push(LayoutParams::new(path.clone(), amount, Box::new(msg_layout)));
@@ -1158,7 +1933,7 @@ impl Layouts {
                 let sorted_fields = msg.get_sorted_fields(&config.field_order);
                 let mut descendants = sorted_fields.into_iter().
                     filter(|(pos, _)| !consumed_fields.contains(&pos.id)).
-                    map(|(pos, amount)| Self::create_field_layouts(root, config, &path.add(pos), amount, load_all)).
+                    map(|(pos, amount)| Self::create_field_layouts(root, config, pages, &path.add(pos), amount, load_all)).
                     flatten().collect::<Vec<LayoutParams>>();
                 items.last_mut().unwrap().children_count = Self::calc_top_layouts_count(&descendants);
                 items.append(&mut descendants);
@@ -1169,11 +1944,12 @@ impl Layouts {
         items
     }
 
-    fn create_scalar_layouts(field_def: FieldProtoPtr, path: FieldPath, amount: usize) -> Vec<LayoutParams> {
+    fn create_scalar_layouts(root: &MessageData, config: &LayoutConfig, field_def: FieldProtoPtr, path: FieldPath, amount: usize) -> Vec<LayoutParams> {
         let mut items: Vec<LayoutParams> = vec![];
+        let renderer = config.renderers.find(root, &path);
         match field_def.typename().as_str() {
             // repeated strings and bytes always shown separately
-            "bytes" => {
+            "bytes" if renderer.is_none() => {
                 let start = path.0.last().unwrap().index;
                 for index in start..start + amount.max(1) {
                     items.push(LayoutParams::new(path.with_last_index(index), amount.min(1)
@@ -1183,12 +1959,15 @@ impl Layouts {
                                                                      })))
                 }
             }
-            "string" => {
+            "string" | "bytes" => {
                 let start = path.0.last().unwrap().index;
                 for index in start..start + amount.max(1) {
                     items.push(LayoutParams::new(path.with_last_index(index), amount.min(1)
                                                  , Box::new(StringLayout {
                                                                          visible_lines_count: 0,
+                                                                         wrap: None,
+                                                                         renderer,
+                                                                         line_lens: vec![],
                                                                      })))
                 }
             }
@@ -1197,15 +1976,57 @@ impl Layouts {
         items
     }
 
-    pub fn start_indent_update(&mut self) -> IndentsCalc {
+    pub fn start_indent_update(&mut self, config: &LayoutConfig) -> IndentsCalc {
         let indents = mem::replace(&mut self.indents, vec![]);
-        IndentsCalc::new_for_update(indents)
+        IndentsCalc::new_for_update(indents, config)
+    }
+
+    // the indents fit the names of the layouts around the screen starting with the `first` one: a level is widened
+    // for a longer name a screen away, it is narrowed when the longer names are two screens away, so the columns
+    // do not move back and forth while scrolling; true if the indents are changed
+    pub fn fit_indents(&mut self, config: &LayoutConfig, first: usize) -> bool {
+        let lines = self.height as usize;
+        let (near, far) = (self.names_width(first, lines), self.names_width(first, 2 * lines));
+        let mut indents: Vec<u16> = Vec::with_capacity(self.indents.len());
+        for level in 0..self.indents.len().max(near.len()) {
+            let current = self.indents.get(level).copied().unwrap_or(0);
+            let width = |widths: &Vec<u16>| widths.get(level).copied().unwrap_or(0);
+            let min = indents.last().map_or(config.level_indent, |indent| indent + config.level_indent);
+            indents.push(width(&near).max(current.min(width(&far))).max(min));
+        }
+        let changed = indents != self.indents;
+        self.indents = indents;
+        changed
     }
 
+    // the widest first column of each level in the layouts on the screen and `lines` above and below it
+    fn names_width(&self, first: usize, lines: usize) -> Vec<u16> {
+        let mut res = vec![];
+        let mut add = |item: &LayoutParams| {
+            let level = item.level() - 1;
+            if res.len() <= level { res.resize(level + 1, 0); }
+            res[level] = res[level].max(item.name_width);
+        };
+        let mut remain = (self.height as usize + lines) as isize;
+        for item in self.items.iter().skip(first) {
+            if remain <= 0 { break; }
+            add(item);
+            remain -= item.height as isize;
+        }
+        let mut remain = lines as isize;
+        for item in self.items[..first.min(self.items.len())].iter().rev() {
+            if remain <= 0 { break; }
+            add(item);
+            remain -= item.height as isize;
+        }
+        res
+    }
+
+    // after the config (a new revision), the width or the indents are changed; the layouts sized for them are kept
     pub fn update_layouts(&mut self, root: &MessageData, config: &LayoutConfig) {
-        let mut negotiator = self.start_indent_update();
+        let mut negotiator = self.start_indent_update(config);
         for item in &mut self.items {
-            item.calc_sizes(root, config, self.width, &mut negotiator);
+            item.ensure_sizes(root, config, self.width, self.revision, &mut negotiator);
         }
         self.indents = negotiator.into();
     }
@@ -1217,15 +2038,18 @@ impl Layouts {
         while i < self.items.len() {
             //
             if self.items[i].layout.is_some() {
-                let mut indent_calc = self.start_indent_update();
+                let mut indent_calc = self.start_indent_update(config);
                 let item = &mut self.items[i];
-                item.calc_sizes(root, config, self.width, &mut indent_calc);
+                item.ensure_sizes(root, config, self.width, self.revision, &mut indent_calc);
                 self.indents = indent_calc.into();
                 remain -= item.height as isize;
                 i += 1;
             } else {
                 let (count, lines_count) = self.expand_collapsed(root, config, i);
                 remain -= lines_count as isize;
+                if selection.layout > i {
+                    selection.layout += count - 1;
+                }
                 i += count;
             }
             if remain <= 0 { break; }
@@ -1236,16 +2060,16 @@ impl Layouts {
         while i > 0 {
             i -= 1; // [i=0] already processed above
             if self.items[i].layout.is_some() {
-                let mut indent_calc = self.start_indent_update();
+                let mut indent_calc = self.start_indent_update(config);
                 let item = &mut self.items[i];
-                item.calc_sizes(root, config, self.width, &mut indent_calc);
+                item.ensure_sizes(root, config, self.width, self.revision, &mut indent_calc);
                 self.indents = indent_calc.into();
                 remain -= item.height as isize;
             } else {
                 let (count, lines_count) = self.expand_collapsed(root, config, i);
                 remain -= lines_count as isize;
                 if selection.layout > i {
-                    selection.layout += count;
+                    selection.layout += count - 1;
                 }
             }
             if remain <= 0 { break; }
@@ -1289,6 +2113,11 @@ impl Layouts {
     // restore message layout with children
     // return a new count of layouts (instead of 1 before) and total lines in them
     fn expand_collapsed(&mut self, root: &MessageData, config: &LayoutConfig, pos: usize) -> (usize, usize) {
+        self.expand_message(root, config, pos, 1)
+    }
+
+    // replace the message layout and `count - 1` layouts of its content with all the content expanded
+    fn expand_message(&mut self, root: &MessageData, config: &LayoutConfig, pos: usize, count: usize) -> (usize, usize) {
         let mut new_layout_count = 0;
         let mut new_lines_count = 0;
         let mut path = None;
@@ -1296,16 +2125,28 @@ impl Layouts {
             path = Some(current.path.clone());
         }
         if let Some(path) = path {
-            let mut negotiator = self.start_indent_update();
+            let mut negotiator = self.start_indent_update(config);
             let amount = if root.get_field(&path.0).is_some() { 1 } else { 0 };
-            let mut layouts = Self::create_message_layouts(root, config, &path, amount, true);
+            let mut layouts = Self::create_message_layouts(root, config, &self.pages, &path, amount, true);
             new_layout_count = layouts.len();
-            self.items.remove(pos);
-            while !layouts.is_empty() {
-                let mut new_item = layouts.pop().unwrap();
-                new_item.calc_sizes(root, config, self.width, &mut negotiator);
+            // the layouts below the screen keep the height of one line until they are sized
+            let mut pending = None;
+            for (index, new_item) in layouts.iter_mut().enumerate() {
+                if new_lines_count >= EAGER_SIZED_LINES {
+                    pending = Some(pos + index..pos + new_layout_count);
+                    new_lines_count += new_layout_count - index;
+                    break;
+                }
+                new_item.ensure_sizes(root, config, self.width, self.revision, &mut negotiator);
                 new_lines_count += new_item.height;
-                self.items.insert(pos, new_item);
+            }
+            // one move of the following layouts
+            self.replace_items(pos..pos + count, layouts);
+            if let Some(range) = pending {
+                self.pending_sizes = Some(match self.pending_sizes.take() {
+                    Some(pending) => pending.start.min(range.start)..pending.end.max(range.end),
+                    None => range,
+                });
             }
             self.indents = negotiator.into();
         }
@@ -1315,6 +2156,221 @@ impl Layouts {
     }
 
 
+    // the layouts in the range are replaced, the pending range is moved with the layouts after it;
+    // the replaced layouts are sized by the caller, so they are not pending anymore
+    fn replace_items(&mut self, range: Range<usize>, layouts: Vec<LayoutParams>) {
+        let (removed, added) = (range.len(), layouts.len());
+        self.items.splice(range.clone(), layouts);
+        // the copies sized on the thread are for the layouts at their old places
+        self.sizing = None;
+        let Some(pending) = self.pending_sizes.take() else { return; };
+        let shift = |index: usize| if index >= range.end { index - removed + added } else { index.min(range.start) };
+        let (start, end) = (shift(pending.start), shift(pending.end));
+        // the pending layouts replaced in the middle are sized again with those after them
+        self.pending_sizes = Some(start..end).filter(|pending| !pending.is_empty());
+    }
+
+    // takes the layouts sized on the sizing thread, waiting for all of them if `wait`; true when the last ones
+    // are taken; the thread is started for the pending layouts, and again if it sizes them for other data or config
+    pub fn receive_sizes(&mut self, root: &Arc<MessageData>, config: &LayoutConfig, wait: bool) -> bool {
+        loop {
+            let Some(pending) = self.pending_sizes.clone() else { return false; };
+            let current = self.sizing.as_ref().is_some_and(|sizing|
+                Arc::ptr_eq(&sizing.root, root) && sizing.width == self.width && sizing.revision == self.revision);
+            if !current { self.start_sizing(root, config, pending); }
+            let Some(sizing) = self.sizing.as_mut() else { return false; };
+            let batch = if wait { sizing.results.recv().map_err(|_| TryRecvError::Disconnected) } else { sizing.results.try_recv() };
+            match batch {
+                Ok(batch) => self.take_sized(batch, config),
+                Err(TryRecvError::Empty) => return false,
+                // the thread stopped before the end, the layouts are sized when they are shown
+                Err(TryRecvError::Disconnected) => {
+                    self.pending_sizes = None;
+                    self.sizing = None;
+                }
+            }
+            if self.pending_sizes.is_none() { return true; }
+        }
+    }
+
+    fn start_sizing(&mut self, root: &Arc<MessageData>, config: &LayoutConfig, pending: Range<usize>) {
+        let copies: Vec<LayoutParams> = self.items[pending.clone()].iter().map(LayoutParams::copy).collect();
+        let (sender, results) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (width, revision) = (self.width, self.revision);
+        let (snapshot, config, indents, stop) = (root.clone(), config.clone(), self.indents.clone(), cancel.clone());
+        thread::spawn(move || Self::size_copies(&snapshot, &config, width, revision, indents, copies, &stop, sender));
+        // the previous thread is stopped by dropping its sizing
+        self.sizing = Some(Sizing { root: root.clone(), width, revision, pending, results, cancel });
+    }
+
+    // on the sizing thread, until the layouts are sized, the sizing is dropped or the editor is closed
+    fn size_copies(root: &MessageData, config: &LayoutConfig, width: u16, revision: u64, indents: Vec<u16>, copies: Vec<LayoutParams>, cancel: &AtomicBool, sender: Sender<SizedLayouts>) {
+        let mut negotiator = IndentsCalc::new_for_update(indents, config);
+        let mut items = vec![];
+        let mut started = Instant::now();
+        for mut item in copies {
+            if cancel.load(atomic::Ordering::Relaxed) { return; }
+            item.ensure_sizes(root, config, width, revision, &mut negotiator);
+            items.push(item);
+            if started.elapsed() >= SIZING_BATCH {
+                if sender.send(SizedLayouts { items: mem::take(&mut items), indents: negotiator.level_indents.clone() }).is_err() { return; }
+                started = Instant::now();
+            }
+        }
+        let _ = sender.send(SizedLayouts { items, indents: negotiator.level_indents });
+    }
+
+    // the sized copies replace the pending layouts, except those sized meanwhile to be shown
+    fn take_sized(&mut self, batch: SizedLayouts, config: &LayoutConfig) {
+        let Some(sizing) = self.sizing.as_mut() else { return; };
+        let start = sizing.pending.start;
+        sizing.pending.start += batch.items.len();
+        let pending = sizing.pending.clone();
+        for (item, sized) in self.items[start..pending.start].iter_mut().zip(batch.items) {
+            if item.sized.is_none() { *item = sized; }
+        }
+        // the levels are widened for the names of the sized layouts as if they were sized here
+        let mut negotiator = self.start_indent_update(config);
+        for (level, indent) in batch.indents.into_iter().enumerate() {
+            negotiator.add(indent.saturating_sub(config.margin_left) as usize, level + 1);
+        }
+        self.indents = negotiator.into();
+        self.pending_sizes = Some(pending).filter(|pending| !pending.is_empty());
+        if self.pending_sizes.is_none() { self.sizing = None; }
+    }
+
+    // the page of the group with the value at the index replaces the page shown, `pos` is a layout of the group
+    fn show_page(&mut self, root: &MessageData, config: &LayoutConfig, pos: usize, index: usize) {
+        let Some(page) = self.items[pos].layout.as_ref().and_then(|layout| layout.page()) else { return; };
+        let (group, total) = (page.group.clone(), page.total);
+        let start = (index - group.0.last().unwrap().index) / config.page_size * config.page_size;
+        self.pages.retain(|(other, _)| other.0 != group.0);
+        self.pages.push((group.clone(), start));
+
+        let range = self.group_range(pos, &group, total);
+        let mut negotiator = self.start_indent_update(config);
+        // top-level messages are loaded when shown, as at the start
+        let mut layouts = Self::create_field_layouts(root, config, &self.pages, &group, total, group.0.len() > 1);
+        for new_item in &mut layouts {
+            new_item.ensure_sizes(root, config, self.width, self.revision, &mut negotiator);
+        }
+        let first = range.start;
+        self.replace_items(range, layouts);
+        self.indents = negotiator.into();
+        match self.get_parent_pos(first) {
+            Some(parent_pos) => self.items[parent_pos].children_count = self.calc_children_count(parent_pos),
+            None => self.top_layouts_count = Self::calc_top_layouts_count(&self.items),
+        }
+    }
+
+    // the layouts of the values of a paged group around `pos` with their content, and its page rows
+    fn group_range(&self, pos: usize, group: &FieldPath, total: usize) -> Range<usize> {
+        let level = group.0.len();
+        let last = group.0.last().unwrap();
+        let in_group = |item: &LayoutParams| item.level() == level && item.path.0[..level - 1] == group.0[..level - 1] &&
+            item.path.0[level - 1].id == last.id && (last.index..last.index + total).contains(&item.path.0[level - 1].index);
+        let mut start = pos;
+        for i in (0..pos).rev() {
+            let item = &self.items[i];
+            if in_group(item) { start = i; } else if item.level() <= level { break; }
+        }
+        let mut end = pos + 1;
+        while end < self.items.len() && (self.items[end].level() > level || in_group(&self.items[end])) { end += 1; }
+        start..end
+    }
+
+    // replace the message layout and layouts of its content with one line, return false if it's not a message
+    fn collapse_message(&mut self, root: &MessageData, pos: usize) -> bool {
+        let Some(current) = self.items.get(pos) else { return false; };
+        if current.layout.as_ref().map_or(false, |layout| layout.layout_type() != LayoutType::Message) { return false; }
+        // there is no reason to collapse a message that does not exist, it's already displayed in one line
+        let Some(msg) = root.get_submessage(&current.path.0) else { return false; };
+        let collapsed = LayoutParams::new(current.path.clone(), current.amount, Box::new(CollapsedLayout { display_size: msg.len() }));
+        let count = self.calc_children_count(pos);
+        self.replace_items(pos + 1..pos + count, vec![]);
+        self.items[pos] = collapsed;
+        true
+    }
+
+    fn is_message(&self, pos: usize) -> bool {
+        match &self.items[pos].layout {
+            Some(layout) => matches!(layout.layout_type(), LayoutType::Message | LayoutType::Collapsed),
+            None => true, // not loaded yet
+        }
+    }
+
+    // the selected layout is kept, or its collapsed parent
+    fn select_path(&self, path: &FieldPath, selection: &mut Selection) {
+        let mut best = 0;
+        for (pos, item) in self.items.iter().enumerate() {
+            if item.path.0.len() <= path.0.len() && item.path.0[..] == path.0[..item.path.0.len()] {
+                best = pos;
+                if item.path.0.len() == path.0.len() { break; }
+            }
+        }
+        *selection = Selection { layout: best, x: 0, y: 0 };
+    }
+
+    // path of the selected field, with the index of the selected value for repeated scalars
+    pub fn selected_path(&self, selection: &Selection) -> Option<FieldPath> {
+        let item = self.items.get(selection.layout).filter(|item| !item.is_page())?;
+        let index = item.layout.as_ref().map_or(0, |layout| layout.selected_index(selection.x, selection.y));
+        Some(item.path.with_last_index(item.path.0.last()?.index + index))
+    }
+
+    // the nearest repeated one of the field and its parents: its path, index and count of its values
+    pub fn enclosing_group(root: &MessageData, path: &FieldPath) -> Option<(FieldPath, usize, usize)> {
+        for len in (1..=path.0.len()).rev() {
+            let group = FieldPath(path.0[..len].to_vec());
+            let Some(def) = root.get_field_definition(&group) else { continue; };
+            if !def.repeated() { continue; }
+            let (id, index) = (group.0[len - 1].id, group.0[len - 1].index);
+            let count = root.get_submessage(&group.0[..len - 1]).map_or(0, |msg| msg.fields.iter().filter(|f| f.id() == id).count());
+            if index < count { return Some((group, index, count)); }
+        }
+        None
+    }
+
+    // the top-level repeated message field of the path, or the one with the most values if the path is not in such a field:
+    // its id and count of values (records of a file that is a list of them)
+    pub fn records(root: &MessageData, path: Option<&FieldPath>) -> Option<(i32, usize)> {
+        let count = |id: i32| root.fields.iter().filter(|field| field.id() == id).count();
+        if let Some(pos) = path.and_then(|path| path.0.first()) {
+            if root.def.get_field(pos.id).is_some_and(|def| def.repeated() && def.is_message()) {
+                return Some((pos.id, count(pos.id)));
+            }
+        }
+        root.def.fields.iter().filter(|def| def.repeated() && def.is_message()).map(|def| (def.id(), count(def.id()))).
+            filter(|(_, count)| *count > 0).max_by_key(|(_, count)| *count)
+    }
+
+    // a top-level value is usually the first item of its field plus its index (the values between are collapsed),
+    // then it is found without going through all the items
+    fn find_top_level(&self, path: &FieldPath) -> Option<usize> {
+        let target = path.0.first().filter(|_| path.0.len() == 1)?;
+        let mut first = self.items.iter().position(|item| item.path.0.len() == 1 && item.path.0[0].id == target.id)?;
+        if self.items[first].is_page() { first += 1; }
+        let pos = first + target.index.checked_sub(self.items[first].path.0[0].index)?;
+        self.items.get(pos).filter(|item| item.path.0 == path.0).map(|_| pos)
+    }
+
+    // the layout showing the path or its nearest parent, true if the path itself is found
+    // a layout of repeated scalars matches each index of its range
+    fn find_path(&self, path: &FieldPath) -> (usize, bool) {
+        if let Some(pos) = self.find_top_level(path) { return (pos, true); }
+        let mut best = (0, false);
+        for (pos, item) in self.items.iter().enumerate() {
+            let len = item.path.0.len();
+            if len > path.0.len() || item.path.0[..len - 1] != path.0[..len - 1] { continue; }
+            let (last, target) = (&item.path.0[len - 1], &path.0[len - 1]);
+            if last.id != target.id || target.index < last.index || target.index >= last.index + item.amount.max(1) { continue; }
+            best = (pos, len == path.0.len());
+            if best.1 { break; }
+        }
+        best
+    }
+
     pub fn calc_relative_pos(&self, mut pos: usize) -> f32 {
         let mut index = 0;
         let mut level = usize::MAX;
@@ -1361,6 +2417,20 @@ impl Layouts {
         position
     }
 
+    // layout and cursor position at a line (counted from the first line of the first layout) and a column
+    pub fn selection_at(&self, root: &MessageData, config: &LayoutConfig, mut line: usize, column: u16) -> Option<Selection> {
+        for (index, item) in self.items.iter().enumerate() {
+            if item.layout.is_none() { return None; }
+            if line < item.height {
+                let indent = self.indents[item.level() - 1];
+                let x = item.cursor_x_at(root, self.width, indent, config, column, line);
+                return Some(Selection { layout: index, y: line, x });
+            }
+            line -= item.height;
+        }
+        None
+    }
+
     pub fn get_parent_pos(&self, mut pos: usize) -> Option<usize> {
         if let Some(current) = self.items.get(pos) {
             let parent_len = current.path.0.len() - 1;
@@ -1373,8 +2443,17 @@ impl Layouts {
         None
     }
 
+    // a scalar value is changed in place, the other layouts keep their sizes
+    pub fn update_after_value_changed(&mut self, root: &MessageData, config: &LayoutConfig, changed_layout: usize) {
+        let Some(item) = self.items.get_mut(changed_layout) else { return; };
+        item.sized = None;
+        let mut negotiator = self.start_indent_update(config);
+        self.items[changed_layout].ensure_sizes(root, config, self.width, self.revision, &mut negotiator);
+        self.indents = negotiator.into();
+    }
+
     pub fn update_after_data_changed(&mut self, root: &MessageData, config: &LayoutConfig, changed_layout: usize) {
-        let mut negotiator = self.start_indent_update();
+        let mut negotiator = self.start_indent_update(config);
 
         // when a field changed, recreate layout of the parent message.
         // the field may be repeated, so delete/create it may influence siblings
@@ -1386,39 +2465,42 @@ impl Layouts {
 
             if let Some(parent) = self.items.get(parent_pos) {
                 if let Some(parent_msg) = root.get_submessage(&parent.path.0) {
-                    let mut layouts = Self::create_message_layouts(root, config, &parent.path, 1, true);
-                    self.items.drain(parent_pos..parent_pos + children_count);
-                    while !layouts.is_empty() {
-                        let mut new_item = layouts.pop().unwrap();
-                        new_item.calc_sizes(root, config, self.width, &mut negotiator);
-                        self.items.insert(parent_pos, new_item);
+                    let mut layouts = Self::create_message_layouts(root, config, &self.pages, &parent.path, 1, true);
+                    for new_item in &mut layouts {
+                        new_item.ensure_sizes(root, config, self.width, self.revision, &mut negotiator);
                     }
+                    self.replace_items(parent_pos..parent_pos + children_count, layouts);
                 }
             }
         } else { // if changed a field of the root message, rebuild all layouts
             let sorted_fields = root.get_sorted_fields(&config.field_order);
             let mut items: Vec<LayoutParams> =
                 sorted_fields.into_iter().
-                    map(|pos_ex| Self::create_field_layouts(root, &config, &FieldPath([pos_ex.0].into()), pos_ex.1, true)).
+                    map(|pos_ex| Self::create_field_layouts(root, &config, &self.pages, &FieldPath([pos_ex.0].into()), pos_ex.1, true)).
                     flatten().collect();
 
             for item in &mut items {
-                item.calc_sizes(root, config, self.width, &mut negotiator);
+                item.ensure_sizes(root, config, self.width, self.revision, &mut negotiator);
             }
             self.top_layouts_count = Self::calc_top_layouts_count(&items);
             self.items = items;
+            self.pending_sizes = None;
+            self.sizing = None;
         }
         self.indents = negotiator.into();
     }
     fn run_command_current_layout(&mut self, command: UserCommand, root: &MessageData, config: &LayoutConfig, selection: &mut Selection) -> CommandResult {
         if let Some(current) = self.items.get_mut(selection.layout) {
             let indent = self.indents[current.level() - 1 as usize];
+            // the command may change the look of the layout, like wrapping a string
+            current.sized = None;
             current.on_command(root, command, config, self.width, indent, &mut selection.x, &mut selection.y)
         } else {
             CommandResult::None
         }
     }
-    pub fn run_command(&mut self, command: UserCommand, root: &MessageData, config: &LayoutConfig, selection: &mut Selection) -> CommandResult {
+    // the collapsed messages are recorded in the config
+    pub fn run_command(&mut self, command: UserCommand, root: &MessageData, config: &mut LayoutConfig, selection: &mut Selection) -> CommandResult {
         match &command {
             UserCommand::ScrollVertically(mut delta) => {
                 if delta == 0 { return CommandResult::None; }
@@ -1481,7 +2563,7 @@ impl Layouts {
 
             UserCommand::DeleteData => {
                 if selection.x == 0 && selection.y == 0 {
-                    if let Some(current) = self.items.get(selection.layout) {
+                    if let Some(current) = self.items.get(selection.layout).filter(|item| !item.is_page()) {
                         CommandResult::ChangeData(Change { path: current.path.clone(), action: ChangeType::Delete })
                     } else { CommandResult::None }
                 } else {
@@ -1491,9 +2573,9 @@ impl Layouts {
 
             UserCommand::InsertData => {
                 if selection.x == 0 && selection.y == 0 {
-                    if let Some(current) = self.items.get(selection.layout) {
+                    if let Some(current) = self.items.get(selection.layout).filter(|item| !item.is_page()) {
                         let def = root.get_field_definition(&current.path).unwrap();
-                        CommandResult::ChangeData(Change { path: current.path.clone(), action: ChangeType::Insert(def.default()) })
+                        CommandResult::ChangeData(Change { path: current.path.clone(), action: ChangeType::Insert(config.inserted_value(&def)) })
                     } else { CommandResult::None }
                 } else {
                     self.run_command_current_layout(command, root, config, selection)
@@ -1505,32 +2587,139 @@ impl Layouts {
                     if let Some(layout) = &current.layout {
                         match layout.layout_type() {
                             LayoutType::Message => {
-                                let current_path = current.path.clone();
-                                let current_amount = current.amount;
-                                // there is no reason to collapse a message that does not exist, it's already displayed in one line
-                                if let Some(msg) = root.get_submessage(&current_path.0) {
-                                    // remove selected layout and all nested layouts
-                                    let path_len = current.path.0.len();
-                                    let mut end_pos = selection.layout + 1;
-                                    while end_pos < self.items.len() {
-                                        let len = self.items[end_pos].path.0.len();
-                                        if len <= path_len { break; }
-                                        end_pos += 1;
-                                    }
-                                    self.items.drain(selection.layout + 1..end_pos);
-                                    // create a collapsed layout in place of the deleted
-                                    self.items[selection.layout] = LayoutParams::new(current_path, current_amount, Box::new(CollapsedLayout { display_size: msg.len() }));
-                                }
+                                let path = current.path.clone();
+                                if self.collapse_message(root, selection.layout) { config.collapsed.insert(path); }
                             }
                             LayoutType::Collapsed => {
+                                config.collapsed.remove(&current.path);
                                 self.expand_collapsed(root, config, selection.layout);
                             }
+                            LayoutType::Page => {
+                                // the first value of the next page or the last one of the previous page
+                                let forward = layout.page().is_some_and(|page| page.forward);
+                                let index = current.path.0.last().unwrap().index + if forward { 0 } else { current.amount - 1 };
+                                let path = current.path.with_last_index(index);
+                                self.show_page(root, config, selection.layout, index);
+                                return self.run_command(UserCommand::GoToPath(path), root, config, selection);
+                            }
                             _ => {}
                         }
                     }
                 }
                 CommandResult::Redraw
             }
+
+            UserCommand::ExpandAll => {
+                if self.items.get(selection.layout).is_some() && self.is_message(selection.layout) {
+                    let path = &self.items[selection.layout].path;
+                    config.collapsed.retain(|other| !other.0.starts_with(&path.0));
+                    let count = self.calc_children_count(selection.layout);
+                    self.expand_message(root, config, selection.layout, count);
+                    (selection.x, selection.y) = (0, 0);
+                }
+                CommandResult::Redraw
+            }
+
+            UserCommand::CollapseAll => {
+                if self.items.get(selection.layout).is_some() && self.is_message(selection.layout) {
+                    // the selected message is expanded with all nested messages collapsed
+                    if self.items[selection.layout].layout.as_ref().map_or(true, |layout| layout.layout_type() == LayoutType::Collapsed) {
+                        config.collapsed.remove(&self.items[selection.layout].path);
+                        self.expand_collapsed(root, config, selection.layout);
+                    }
+                    let end = selection.layout + self.calc_children_count(selection.layout);
+                    let mut pos = selection.layout + 1;
+                    let mut removed = 0;
+                    while pos < end - removed {
+                        if self.is_message(pos) {
+                            let count = self.calc_children_count(pos);
+                            if self.collapse_message(root, pos) {
+                                removed += count - 1;
+                                config.collapsed.insert(self.items[pos].path.clone());
+                            }
+                        }
+                        pos += 1;
+                    }
+                    (selection.x, selection.y) = (0, 0);
+                }
+                CommandResult::Redraw
+            }
+
+            UserCommand::CollapseToDepth(depth) => {
+                let selected_path = self.items.get(selection.layout).map_or(FieldPath::new(), |item| item.path.clone());
+                let mut pos = 0;
+                while pos < self.items.len() {
+                    if self.is_message(pos) {
+                        let level = self.items[pos].level();
+                        if level < *depth && self.items[pos].layout.as_ref().map_or(true, |layout| layout.layout_type() == LayoutType::Collapsed) {
+                            config.collapsed.remove(&self.items[pos].path);
+                            self.expand_collapsed(root, config, pos);
+                        } else if level == *depth && self.collapse_message(root, pos) {
+                            config.collapsed.insert(self.items[pos].path.clone());
+                        }
+                    }
+                    pos += 1;
+                }
+                self.select_path(&selected_path, selection);
+                CommandResult::Redraw
+            }
+
+            UserCommand::GoToPath(path) => {
+                loop {
+                    let (pos, matched) = self.find_path(path);
+                    if self.items.get(pos).is_some_and(LayoutParams::is_page) {
+                        let level = self.items[pos].level();
+                        self.show_page(root, config, pos, path.0[level - 1].index);
+                        continue;
+                    }
+                    if matched {
+                        *selection = Selection { layout: pos, x: 0, y: 0 };
+                        let item = &self.items[pos];
+                        if item.amount > 1 {
+                            // the value among repeated scalars, its place is known after sizing
+                            let mut negotiator = self.start_indent_update(config);
+                            self.items[pos].ensure_sizes(root, config, self.width, self.revision, &mut negotiator);
+                            self.indents = negotiator.into();
+                            let item = &self.items[pos];
+                            let index = path.0.last().unwrap().index - item.path.0.last().unwrap().index;
+                            (selection.x, selection.y) = item.layout.as_ref().map_or((0, 0), |layout| layout.cursor_at_index(index));
+                        }
+                        return CommandResult::Redraw;
+                    }
+                    let collapsed = self.items.get(pos).map_or(false, |item| item.layout.as_ref().map_or(true, |layout| layout.layout_type() == LayoutType::Collapsed));
+                    if !collapsed || root.get_submessage(&self.items[pos].path.0).is_none() {
+                        // the field has no data or is not shown with the current field order
+                        *selection = Selection { layout: pos, x: 0, y: 0 };
+                        return CommandResult::ShowMessage("the field is not found in the data".into());
+                    }
+                    config.collapsed.remove(&self.items[pos].path);
+                    self.expand_collapsed(root, config, pos);
+                }
+            }
+            UserCommand::NextSet | UserCommand::PrevSet => {
+                let forward = matches!(command, UserCommand::NextSet);
+                // a message present in the data is a stop too, a collapsed one is passed with its content
+                let is_set = |item: &LayoutParams| item.amount > 0 || root.get_field(&item.path.0).is_some();
+                let found = if forward {
+                    (selection.layout + 1..self.items.len()).find(|&pos| is_set(&self.items[pos]))
+                } else {
+                    (0..selection.layout.min(self.items.len())).rev().find(|&pos| is_set(&self.items[pos]))
+                };
+                let Some(pos) = found else {
+                    return CommandResult::ShowMessage(format!("no set fields {}", if forward { "below" } else { "above" }));
+                };
+                if self.items[pos].layout.is_none() { self.expand_collapsed(root, config, pos); }
+                *selection = Selection { layout: pos, x: 0, y: 0 };
+                CommandResult::Redraw
+            }
+            UserCommand::GroupFirst | UserCommand::GroupLast => {
+                let Some(path) = self.selected_path(selection) else { return CommandResult::None; };
+                let Some((group, _, count)) = Self::enclosing_group(root, &path) else {
+                    return CommandResult::ShowMessage("the field is not in a repeated one".into());
+                };
+                let index = if let UserCommand::GroupFirst = command { 0 } else { count - 1 };
+                self.run_command(UserCommand::GoToPath(group.with_last_index(index)), root, config, selection)
+            }
             _ => self.run_command_current_layout(command, root, config, selection)
         }
 
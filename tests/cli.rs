@@ -0,0 +1,30 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// stdout of the tests is a pipe, not a terminal: the data is printed as text instead of opening the editor
+
+#[test]
+fn piped_output_prints_text() {
+    let output = Command::new(env!("CARGO_BIN_EXE_protoedit")).arg("data/ints.pb").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "f1: 1\nf2: 2\nf3: 3\n");
+}
+
+#[test]
+fn piped_output_needs_a_file() {
+    let output = Command::new(env!("CARGO_BIN_EXE_protoedit")).output().unwrap();
+    assert_eq!(output.status.code(), Some(101));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("a data file expected, stdout is not a terminal"));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn data_from_stdin() {
+    // cat data.pb | protoedit "-;format.proto"
+    let mut child = Command::new(env!("CARGO_BIN_EXE_protoedit")).arg("-;data/ints.proto")
+        .stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().unwrap();
+    child.stdin.take().unwrap().write_all(&std::fs::read("data/ints.pb").unwrap()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "f1: 1\nf2: 2\nf3: 3\n");
+}